@@ -0,0 +1,8 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! The C interface in `banshee::ffi`, built into the shared library
+//! `libbanshee_capi.so`, which `include/banshee.h` declares.
+
+pub use banshee::ffi::*;
@@ -0,0 +1,61 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+#ifndef BANSHEE_H
+#define BANSHEE_H
+
+// Generated by cbindgen from `src/ffi.rs`. Do not edit.
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define BANSHEE_OK 0
+
+#define BANSHEE_DONE 1
+
+#define BANSHEE_ERR_NULL -1
+
+#define BANSHEE_ERR_INVALID -2
+
+#define BANSHEE_ERR_TRANSLATE -3
+
+#define BANSHEE_ERR_EXECUTE -4
+
+#define BANSHEE_ERR_PANIC -5
+
+#define BANSHEE_ERR_RUNNING -6
+
+typedef struct BansheeEngine BansheeEngine;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+BansheeEngine *banshee_create(const char *config_json);
+
+BansheeEngine *banshee_engine_new(uint32_t num_clusters, uint32_t num_cores);
+
+void banshee_destroy(BansheeEngine *engine);
+
+int banshee_set_deterministic(BansheeEngine *engine, uint64_t quantum);
+
+int banshee_load_elf(BansheeEngine *engine, const char *path);
+
+int banshee_execute(BansheeEngine *engine, uint32_t *exit_code);
+
+int banshee_run(BansheeEngine *engine, uint64_t max_instret);
+
+int banshee_exit_code(BansheeEngine *engine, uint32_t *exit_code);
+
+int banshee_read_mem(BansheeEngine *engine, uint32_t addr, uint8_t *buf, size_t len);
+
+int banshee_write_mem(BansheeEngine *engine, uint32_t addr, const uint8_t *buf, size_t len);
+
+const char *banshee_last_error(const BansheeEngine *engine);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* BANSHEE_H */
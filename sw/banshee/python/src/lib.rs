@@ -0,0 +1,8 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! The Python bindings in `banshee::python`, built into the shared library
+//! `libbanshee_python.so`, which Python imports as `banshee.so`.
+
+extern crate banshee;
@@ -0,0 +1,124 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Command line arguments and environment of the simulated binary
+//!
+//! The arguments are placed in memory the way the newlib crt0 expects them
+//! on the initial stack: `argc`, the `argv` pointers terminated by a null
+//! pointer, the `envp` pointers terminated by a null pointer, and finally the
+//! null-terminated strings themselves.
+
+/// The command line arguments and environment variables of a binary.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppArgs {
+    /// The arguments, starting with the program name.
+    pub args: Vec<String>,
+    /// The environment variables, as `KEY=VALUE`.
+    pub env: Vec<String>,
+}
+
+impl AppArgs {
+    /// The size in bytes of the pointer arrays, before the strings.
+    fn header_size(&self) -> u32 {
+        4 * (1 + self.args.len() as u32 + 1 + self.env.len() as u32 + 1)
+    }
+
+    /// The size in bytes of the marshalled block, padded to 16 bytes to keep
+    /// the stack pointer aligned.
+    pub fn size(&self) -> u32 {
+        let strings: usize = self.strings().map(|s| s.len() + 1).sum();
+        (self.header_size() + strings as u32 + 15) & !15
+    }
+
+    /// The address of the `argv` array in a block at `addr`.
+    pub fn argv(&self, addr: u32) -> u32 {
+        addr + 4
+    }
+
+    /// The address of the `envp` array in a block at `addr`.
+    pub fn envp(&self, addr: u32) -> u32 {
+        addr + 4 * (1 + self.args.len() as u32 + 1)
+    }
+
+    /// Marshal the arguments into a block of `size()` bytes at `addr`.
+    pub fn layout(&self, addr: u32) -> Vec<u8> {
+        let mut ptrs = vec![self.args.len() as u32];
+        let mut strings = Vec::new();
+        let mut next = addr + self.header_size();
+        let mut push = |ptrs: &mut Vec<u32>, s: &str| {
+            ptrs.push(next);
+            strings.extend_from_slice(s.as_bytes());
+            strings.push(0);
+            next += s.len() as u32 + 1;
+        };
+        for arg in &self.args {
+            push(&mut ptrs, arg);
+        }
+        ptrs.push(0);
+        for var in &self.env {
+            push(&mut ptrs, var);
+        }
+        ptrs.push(0);
+
+        let mut block: Vec<u8> = ptrs.iter().flat_map(|p| p.to_le_bytes()).collect();
+        block.extend(strings);
+        block.resize(self.size() as usize, 0);
+        block
+    }
+
+    fn strings(&self) -> impl Iterator<Item = &String> {
+        self.args.iter().chain(self.env.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(block: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes([
+            block[offset],
+            block[offset + 1],
+            block[offset + 2],
+            block[offset + 3],
+        ])
+    }
+
+    fn string(block: &[u8], addr: u32, ptr: u32) -> &str {
+        let start = (ptr - addr) as usize;
+        let len = block[start..].iter().position(|&b| b == 0).unwrap();
+        std::str::from_utf8(&block[start..start + len]).unwrap()
+    }
+
+    #[test]
+    fn layout() {
+        let args = AppArgs {
+            args: vec!["prog".into(), "-n".into(), "42".into()],
+            env: vec!["HOME=/".into()],
+        };
+        let addr = 0x1000;
+        let block = args.layout(addr);
+        assert_eq!(block.len() as u32, args.size());
+        assert_eq!(args.size() % 16, 0);
+        assert_eq!(word(&block, 0), 3);
+        assert_eq!(args.argv(addr), addr + 4);
+        assert_eq!(string(&block, addr, word(&block, 4)), "prog");
+        assert_eq!(string(&block, addr, word(&block, 8)), "-n");
+        assert_eq!(string(&block, addr, word(&block, 12)), "42");
+        assert_eq!(word(&block, 16), 0);
+        assert_eq!(args.envp(addr), addr + 20);
+        assert_eq!(string(&block, addr, word(&block, 20)), "HOME=/");
+        assert_eq!(word(&block, 24), 0);
+    }
+
+    #[test]
+    fn empty() {
+        let args = AppArgs::default();
+        let block = args.layout(0x1000);
+        assert_eq!(args.size(), 16);
+        assert_eq!(word(&block, 0), 0);
+        assert_eq!(word(&block, 4), 0);
+        assert_eq!(word(&block, 8), 0);
+    }
+}
@@ -4,6 +4,7 @@
 
 /// Bootrom implementations for various architecture.
 use crate::configuration::Callback;
+use crate::util::log_target;
 use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
 /// Reference held by execution engine, referencing each peripheral instance in each cluster
@@ -41,11 +42,13 @@ impl Bootroms {
         for i in &self.bootrom[0] {
             if addr < i.0 {
                 trace!(
+                    target: log_target::MEM,
                     "Bootrom load from {}: offs 0x{:x}",
                     self.bootrom_types[i.1].get_name(),
                     addr
                 );
                 debug!(
+                    target: log_target::MEM,
                     "Bootrom load from {}: offs 0x{:x}",
                     self.bootrom_types[i.1].get_name(),
                     addr
@@ -54,7 +57,7 @@ impl Bootroms {
             }
             // addr = addr - i.0;
         }
-        trace!("Unmapped periph load: addr {}", addr);
+        trace!(target: log_target::MEM, "Unmapped periph load: addr {}", addr);
         0
     }
 }
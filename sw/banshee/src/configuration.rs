@@ -11,6 +11,7 @@ use std::io::prelude::*;
 
 /// A struct to store the whole system configuration
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Configuration {
     #[serde(default)]
     pub architecture: Architecture,
@@ -22,10 +23,19 @@ pub struct Configuration {
     pub address: Address,
     #[serde(default)]
     pub inst_latency: std::collections::HashMap<String, u64>,
+    /// The cycles of each instruction category, overridden for individual
+    /// instructions by `inst_latency`.
+    #[serde(default)]
+    pub cycle_model: CycleModel,
     #[serde(default)]
     pub ssr: Ssr,
     #[serde(default)]
+    pub dma: Dma,
+    #[serde(default)]
     pub interrupt_latency: u32,
+    /// Abort on writes to read-only CSRs instead of ignoring them.
+    #[serde(default)]
+    pub trap_read_only_csrs: bool,
 }
 
 impl Default for Configuration {
@@ -36,8 +46,11 @@ impl Default for Configuration {
             memory: Default::default(),
             address: Default::default(),
             inst_latency: Default::default(),
+            cycle_model: Default::default(),
             ssr: Default::default(),
+            dma: Default::default(),
             interrupt_latency: 10,
+            trap_read_only_csrs: false,
         }
     }
 }
@@ -56,11 +69,32 @@ impl Configuration {
             memory: vec![Default::default(); num_clusters],
             address: Default::default(),
             inst_latency: Default::default(),
+            cycle_model: Default::default(),
             ssr: Default::default(),
+            dma: Default::default(),
             interrupt_latency: 10,
+            trap_read_only_csrs: false,
         }
     }
+
+    /// The latency of an instruction in `inst_latency`, if configured.
+    ///
+    /// Instructions which were renamed can still be configured under their
+    /// former name.
+    pub fn inst_latency(&self, op: &str) -> Option<u64> {
+        const ALIASES: &[(&str, &str)] = &[("cpop", "pcnt")];
+        self.inst_latency.get(op).copied().or_else(|| {
+            ALIASES
+                .iter()
+                .find(|&&(name, _)| name == op)
+                .and_then(|&(_, alias)| self.inst_latency.get(alias).copied())
+        })
+    }
+
     /// Parse a json/yaml file into a `Configuration` struct
+    ///
+    /// Keys which are not part of the configuration are errors, such that
+    /// misspelled or misplaced settings are not silently ignored.
     pub fn parse(
         name: &str,
         num_clusters: usize,
@@ -69,17 +103,12 @@ impl Configuration {
         has_num_cores: bool,
         base_hartid: usize,
         has_base_hartid: bool,
-    ) -> Configuration {
-        let config: String = std::fs::read_to_string(name)
-            .unwrap_or_else(|_| panic!("Could not open file {}", name))
-            .parse()
-            .unwrap_or_else(|_| panic!("Could not parse file {}", name));
-        // Parse the configuration file based on it's name
-        let mut config: Configuration = if name.to_lowercase().contains("json") {
-            serde_json::from_str(&config).expect("Error while reading json")
-        } else {
-            serde_yaml::from_str(&config).expect("Error while reading yaml")
-        };
+    ) -> anyhow::Result<Configuration> {
+        use anyhow::Context;
+        let config = std::fs::read_to_string(name)
+            .with_context(|| format!("Could not open file {}", name))?;
+        let mut config = Self::parse_str(&config, name.to_lowercase().contains("json"))
+            .with_context(|| format!("Invalid configuration {}", name))?;
         if has_num_cores {
             config.architecture.num_cores = num_cores;
         }
@@ -90,7 +119,15 @@ impl Configuration {
             config.memory.resize_with(num_clusters, Default::default);
             config.architecture.num_clusters = num_clusters;
         }
-        config
+        Ok(config)
+    }
+
+    /// Parse a configuration from json or yaml.
+    pub fn parse_str(config: &str, json: bool) -> anyhow::Result<Configuration> {
+        Ok(match json {
+            true => serde_json::from_str(config)?,
+            false => serde_yaml::from_str(config)?,
+        })
     }
 
     /// Write the default `Configuration` struct into a json/yaml file
@@ -113,6 +150,7 @@ impl Configuration {
 
 /// Holds all the memories in the hierarchy
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Memories {
     pub tcdm: Memory,
     pub dram: Memory,
@@ -146,6 +184,7 @@ impl Default for Memories {
 
 /// Description of a single memory hierarchy
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Memory {
     pub start: u32,
     pub end: u32,
@@ -164,6 +203,7 @@ impl Default for Memory {
 
 /// Description of a single memory hierarchy with callback functions
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct MemoryCallback {
     pub start: u32,
     pub end: u32,
@@ -183,12 +223,14 @@ impl Default for MemoryCallback {
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Callback {
     pub name: String,
     pub size: u32,
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ExtTcdm {
     pub cluster: u32,
     pub start: u32,
@@ -196,6 +238,7 @@ pub struct ExtTcdm {
 
 /// Struct to configure specific addresses
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Address {
     pub tcdm_start: u32,
     pub tcdm_end: u32,
@@ -230,8 +273,162 @@ impl Default for Address {
     }
 }
 
+/// The cycles taken by each category of instructions
+///
+/// Memory accesses take the latency of the memory they access instead.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CycleModel {
+    /// Integer arithmetic and logic, and everything not covered otherwise.
+    pub alu: u64,
+    /// Integer multiplications.
+    pub mul: u64,
+    /// Integer divisions and remainders.
+    pub div: u64,
+    /// Conditional branches.
+    pub branch: u64,
+    /// Jumps.
+    pub jump: u64,
+    /// CSR accesses.
+    pub csr: u64,
+    /// Floating-point operations other than the ones below.
+    pub fp: u64,
+    /// Fused floating-point multiply-adds.
+    pub fma: u64,
+    /// Floating-point divisions and square roots.
+    pub fdiv: u64,
+    /// Fences, environment calls, and trap returns.
+    pub system: u64,
+}
+
+impl CycleModel {
+    /// The names of the categories.
+    pub const CATEGORIES: [&'static str; 10] = [
+        "alu", "mul", "div", "branch", "jump", "csr", "fp", "fma", "fdiv", "system",
+    ];
+
+    /// Read a cycle model from a json/yaml file.
+    ///
+    /// Categories missing from the file keep their default cost.
+    pub fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        use anyhow::Context;
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cycle model {}", path.display()))?;
+        let name = path.to_string_lossy().to_lowercase();
+        let (model, present): (anyhow::Result<Self>, Vec<String>) = if name.contains("json") {
+            let value: serde_json::Value = serde_json::from_str(&data)
+                .with_context(|| format!("Failed to parse cycle model {}", path.display()))?;
+            let present = value
+                .as_object()
+                .map(|m| m.keys().cloned().collect())
+                .unwrap_or_default();
+            (
+                serde_json::from_value(value).map_err(anyhow::Error::from),
+                present,
+            )
+        } else {
+            let value: serde_yaml::Value = serde_yaml::from_str(&data)
+                .with_context(|| format!("Failed to parse cycle model {}", path.display()))?;
+            let present = value
+                .as_mapping()
+                .map(|m| {
+                    m.iter()
+                        .filter_map(|(k, _)| k.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default();
+            (
+                serde_yaml::from_value(value).map_err(anyhow::Error::from),
+                present,
+            )
+        };
+        let model = model.with_context(|| format!("Invalid cycle model {}", path.display()))?;
+        for category in Self::CATEGORIES
+            .iter()
+            .filter(|c| !present.iter().any(|p| p == *c))
+        {
+            warn!(
+                "Cycle model {} lacks category `{}`, using the default of {} cycles",
+                path.display(),
+                category,
+                Self::default().cost(category)
+            );
+        }
+        Ok(model)
+    }
+
+    /// The cost of a category, where unknown categories cost as much as `alu`.
+    pub fn cost(&self, category: &str) -> u64 {
+        match category {
+            "mul" => self.mul,
+            "div" => self.div,
+            "branch" => self.branch,
+            "jump" => self.jump,
+            "csr" => self.csr,
+            "fp" => self.fp,
+            "fma" => self.fma,
+            "fdiv" => self.fdiv,
+            "system" => self.system,
+            _ => self.alu,
+        }
+    }
+
+    /// The category of an instruction mnemonic.
+    pub fn category(mnemonic: &str) -> &'static str {
+        let base = mnemonic.trim_start_matches("vf").trim_start_matches('f');
+        match mnemonic {
+            "mul" | "mulh" | "mulhsu" | "mulhu" => "mul",
+            "div" | "divu" | "rem" | "remu" => "div",
+            "beq" | "bne" | "blt" | "bge" | "bltu" | "bgeu" => "branch",
+            "jal" | "jalr" => "jump",
+            "fence" | "fence.i" | "ecall" | "ebreak" | "uret" | "sret" | "mret" | "dret"
+            | "wfi" => "system",
+            _ if mnemonic.starts_with("csr") => "csr",
+            _ if mnemonic.starts_with('f') || mnemonic.starts_with("vf") => {
+                if base.starts_with("div") || base.starts_with("sqrt") {
+                    "fdiv"
+                } else if base.starts_with("madd")
+                    || base.starts_with("msub")
+                    || base.starts_with("nmadd")
+                    || base.starts_with("nmsub")
+                    || base.starts_with("mac")
+                    || base.starts_with("mre")
+                {
+                    "fma"
+                } else {
+                    "fp"
+                }
+            }
+            _ => "alu",
+        }
+    }
+
+    /// The cycles taken by an instruction.
+    pub fn cycles(&self, mnemonic: &str) -> u64 {
+        self.cost(Self::category(mnemonic))
+    }
+}
+
+impl Default for CycleModel {
+    fn default() -> Self {
+        Self {
+            alu: 1,
+            mul: 1,
+            div: 1,
+            branch: 1,
+            jump: 1,
+            csr: 1,
+            fp: 1,
+            fma: 1,
+            fdiv: 1,
+            system: 1,
+        }
+    }
+}
+
 /// Struct to configure SSRs
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Ssr {
     pub num_dm: usize,
 }
@@ -242,12 +439,33 @@ impl Default for Ssr {
     }
 }
 
+/// Struct to configure the DMA timing
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dma {
+    /// The number of cycles each transfer takes in addition to the data.
+    #[serde(default)]
+    pub latency: u32,
+    /// The number of bytes transferred per cycle, or 0 to transfer the data
+    /// instantaneously.
+    #[serde(default)]
+    pub bandwidth: u32,
+}
+
 /// Description of the hierarchy
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Architecture {
+    #[serde(default)]
     pub num_cores: usize,
+    #[serde(default)]
     pub num_clusters: usize,
+    #[serde(default)]
     pub base_hartid: usize,
+    /// The cores of each cluster, if they are not all identical. Overrides
+    /// `num_cores` and `num_clusters`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topology: Option<Topology>,
 }
 
 impl Architecture {
@@ -256,6 +474,7 @@ impl Architecture {
             num_cores: num_cores,
             num_clusters: num_clusters,
             base_hartid: base_hartid,
+            topology: None,
         }
     }
 }
@@ -266,6 +485,161 @@ impl Default for Architecture {
             num_cores: 0,
             num_clusters: 0,
             base_hartid: 0,
+            topology: None,
         }
     }
 }
+
+/// The cores of each cluster and their features
+///
+/// Harts are numbered consecutively from the base hartid, cluster by cluster.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Topology {
+    pub clusters: Vec<Vec<CoreFeatures>>,
+}
+
+impl Topology {
+    /// A topology with the same number of identical cores in each cluster.
+    pub fn uniform(num_clusters: usize, num_cores: usize) -> Self {
+        Self {
+            clusters: vec![vec![Default::default(); num_cores]; num_clusters],
+        }
+    }
+
+    /// The total number of cores.
+    pub fn num_harts(&self) -> usize {
+        self.clusters.iter().map(|c| c.len()).sum()
+    }
+
+    /// The number of cores in the largest cluster.
+    pub fn max_cores(&self) -> usize {
+        self.clusters.iter().map(|c| c.len()).max().unwrap_or(0)
+    }
+
+    /// The index of the first core of a cluster, relative to the base hartid.
+    pub fn cluster_offset(&self, cluster_id: usize) -> usize {
+        self.clusters[..cluster_id].iter().map(|c| c.len()).sum()
+    }
+
+    /// The cluster and core index of a hart, relative to the base hartid.
+    pub fn locate(&self, index: usize) -> Option<(usize, usize)> {
+        let mut offset = 0;
+        for (cluster_id, cores) in self.clusters.iter().enumerate() {
+            if index < offset + cores.len() {
+                return Some((cluster_id, index - offset));
+            }
+            offset += cores.len();
+        }
+        None
+    }
+
+    /// The features which all cores of a cluster have in common.
+    pub fn common_features(&self, cluster_id: usize) -> u32 {
+        self.clusters[cluster_id]
+            .iter()
+            .fold(!0, |bits, core| bits & core.bits())
+    }
+
+    /// The features which at least one core of a cluster has.
+    pub fn any_features(&self, cluster_id: usize) -> u32 {
+        self.clusters[cluster_id]
+            .iter()
+            .fold(0, |bits, core| bits | core.bits())
+    }
+}
+
+/// The optional features of a core
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CoreFeatures {
+    /// The core has stream semantic registers.
+    pub ssr: bool,
+    /// The core can issue DMA transfers.
+    pub dma: bool,
+}
+
+impl CoreFeatures {
+    pub const SSR: u32 = 1 << 0;
+    pub const DMA: u32 = 1 << 1;
+
+    /// Encode the features as a bit mask.
+    pub fn bits(&self) -> u32 {
+        (if self.ssr { Self::SSR } else { 0 }) | (if self.dma { Self::DMA } else { 0 })
+    }
+
+    /// The name of a feature bit.
+    pub fn name(bit: u32) -> &'static str {
+        match bit {
+            Self::SSR => "SSR",
+            Self::DMA => "DMA",
+            _ => "unknown",
+        }
+    }
+}
+
+impl Default for CoreFeatures {
+    fn default() -> Self {
+        Self {
+            ssr: true,
+            dma: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_configurations() {
+        for name in &[
+            "mempool",
+            "multi_cluster_periph",
+            "multi_cluster_simple",
+            "snitch_cluster",
+        ] {
+            let path = format!("config/{}.yaml", name);
+            Configuration::parse(&path, 1, false, 1, false, 0, false).unwrap();
+        }
+        let config =
+            Configuration::parse("config/snitch_cluster.yaml", 1, false, 1, false, 0, false)
+                .unwrap();
+        assert_eq!(config.architecture.num_cores, 9);
+        assert_eq!(config.memory[0].tcdm.end, 0x120000);
+    }
+
+    #[test]
+    fn unknown_keys() {
+        assert!(Configuration::parse_str("interrupt_latency: 5", false).is_ok());
+        for config in &[
+            "interupt_latency: 5",
+            "address:\n  barier_reg: 0x100",
+            "memory:\n- tcdm: {start: 0, end: 0x100, latency: 1, size: 0x100}",
+            "architecture: {num_cores: 1, num_clusters: 1, base_hartid: 0, dma: true}",
+        ] {
+            let err = Configuration::parse_str(config, false).unwrap_err();
+            assert!(err.to_string().contains("unknown field"), "{}", err);
+        }
+        assert!(Configuration::parse_str(r#"{"ssr": {"num_dm": 2, "num": 1}}"#, true).is_err());
+    }
+
+    #[test]
+    fn partial_architecture() {
+        let config =
+            Configuration::parse_str(r#"{"architecture": {"num_cores": 4}}"#, true).unwrap();
+        assert_eq!(config.architecture.num_cores, 4);
+        assert_eq!(config.architecture.num_clusters, 0);
+        assert_eq!(config.architecture.base_hartid, 0);
+    }
+
+    #[test]
+    fn inst_latency_alias() {
+        let config = Configuration::parse_str("inst_latency: {pcnt: 3, clz: 2}", false).unwrap();
+        assert_eq!(config.inst_latency("cpop"), Some(3));
+        assert_eq!(config.inst_latency("clz"), Some(2));
+        assert_eq!(config.inst_latency("ctz"), None);
+        let config = Configuration::parse_str("inst_latency: {pcnt: 3, cpop: 4}", false).unwrap();
+        assert_eq!(config.inst_latency("cpop"), Some(4));
+    }
+}
@@ -2,6 +2,7 @@
 // Licensed under the Apache License, Version 2.0, see LICENSE for details.
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::util::log_target;
 use bytebuffer::ByteBuffer;
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
 use std::{
@@ -50,7 +51,7 @@ pub fn generic_bin_read<const N: usize>(
         // we convert the buffer into a u32
         let mut bit_buffer: &[u8] = &exact_buf;
         let u32_value = bit_buffer.read_u32::<LittleEndian>().unwrap();
-        trace!("u32_value: {:#034b}", u32_value);
+        trace!(target: log_target::MEM, "u32_value: {:#034b}", u32_value);
 
         // we add the address to the HashMap
         result.insert(offset, u32_value);
@@ -0,0 +1,160 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Source line information from the DWARF debug info of a binary
+//!
+//! Only the line number programs of the compilation units are decoded, which
+//! suffices to map instruction addresses to source files and lines. Only
+//! little-endian binaries are supported.
+
+use anyhow::{anyhow, bail, Result};
+use gimli::{EndianSlice, LittleEndian};
+use std::collections::HashMap;
+
+/// The mapping from instruction addresses to source lines of a binary.
+#[derive(Debug, Clone, Default)]
+pub struct LineTable {
+    /// The source files, with their directory.
+    files: Vec<String>,
+    /// The rows of the line programs, sorted by address.
+    rows: Vec<Row>,
+}
+
+/// A row of a line program.
+#[derive(Debug, Clone, Copy)]
+struct Row {
+    addr: u64,
+    /// The index into `files`, or `None` for the end of a sequence.
+    file: Option<usize>,
+    line: u32,
+}
+
+impl LineTable {
+    /// Decode the line programs of a binary, or return `None` if it has no
+    /// `.debug_line` section.
+    pub fn parse(elf: &elf::File) -> Result<Option<Self>> {
+        let section = |name: &str| {
+            elf.sections
+                .iter()
+                .find(|s| s.shdr.name == name)
+                .map(|s| s.data.as_slice())
+        };
+        if section(".debug_line").is_none() {
+            return Ok(None);
+        }
+        if elf.ehdr.data != elf::types::ELFDATA2LSB {
+            bail!("DWARF info of big-endian binaries is not supported");
+        }
+        let dwarf = gimli::Dwarf::load(|id| -> gimli::Result<_> {
+            Ok(EndianSlice::new(
+                section(id.name()).unwrap_or(&[]),
+                LittleEndian,
+            ))
+        })?;
+
+        let mut table = LineTable::default();
+        let mut indices = HashMap::new();
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+            let program = match unit.line_program.clone() {
+                Some(program) => program,
+                None => continue,
+            };
+            let mut rows = program.rows();
+            while let Some((header, row)) = rows.next_row()? {
+                if row.end_sequence() {
+                    table.rows.push(Row {
+                        addr: row.address(),
+                        file: None,
+                        line: 0,
+                    });
+                    continue;
+                }
+                let file = row.file(header).ok_or_else(|| {
+                    anyhow!(
+                        "Invalid file index {} in DWARF line program",
+                        row.file_index()
+                    )
+                })?;
+                let mut path = dwarf
+                    .attr_string(&unit, file.path_name())?
+                    .to_string_lossy()
+                    .into_owned();
+                // Prefix the file name with its directory, unless it is
+                // absolute.
+                if let Some(dir) = file.directory(header) {
+                    let dir = dwarf.attr_string(&unit, dir)?;
+                    if !dir.is_empty() && !path.starts_with('/') {
+                        path = format!("{}/{}", dir.to_string_lossy(), path);
+                    }
+                }
+                let files = &mut table.files;
+                let file = *indices.entry(path).or_insert_with_key(|path| {
+                    files.push(path.clone());
+                    files.len() - 1
+                });
+                table.rows.push(Row {
+                    addr: row.address(),
+                    file: Some(file),
+                    line: row.line().map_or(0, |line| line.get() as u32),
+                });
+            }
+        }
+        // End markers go first, such that a sequence which starts where
+        // another ends is found by `lookup`.
+        table.rows.sort_by_key(|row| (row.addr, row.file.is_some()));
+        Ok(Some(table))
+    }
+
+    /// Get the source file and line of the instruction at an address.
+    pub fn lookup(&self, addr: u64) -> Option<(&str, u32)> {
+        let index = self.rows.partition_point(|row| row.addr <= addr);
+        let row = self.rows.get(index.checked_sub(1)?)?;
+        row.file.map(|file| (self.files[file].as_str(), row.line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static LINES: &[u8] = include_bytes!("../tests/dwarf/lines");
+
+    #[test]
+    fn lookup() {
+        let elf = crate::loader::parse_elf(LINES).unwrap();
+        let table = LineTable::parse(&elf).unwrap().unwrap();
+        assert_eq!(table.lookup(0x7fff_fffe), None);
+        assert_eq!(table.lookup(0x8000_0000), Some(("dwarf/lines.s", 10)));
+        assert_eq!(table.lookup(0x8000_0002), Some(("dwarf/lines.s", 11)));
+        assert_eq!(table.lookup(0x8000_0004), Some(("dwarf/lines.s", 13)));
+        assert_eq!(table.lookup(0x8000_0006), Some(("dwarf/lines.s", 14)));
+        assert_eq!(table.lookup(0x8000_0008), Some(("dwarf/lines.s", 14)));
+        assert_eq!(table.lookup(0x8000_000a), None);
+
+        // Binaries without debug info have no line table.
+        let dummy = crate::loader::parse_elf(include_bytes!("../tests/bin/dummy")).unwrap();
+        assert!(LineTable::parse(&dummy).unwrap().is_none());
+    }
+
+    #[test]
+    fn truncated() {
+        let mut elf = crate::loader::parse_elf(LINES).unwrap();
+        let index = elf
+            .sections
+            .iter()
+            .position(|s| s.shdr.name == ".debug_line")
+            .unwrap();
+        let data = elf.sections[index].data.clone();
+        for len in 1..data.len() {
+            elf.sections[index].data = data[..len].to_vec();
+            assert!(
+                LineTable::parse(&elf).is_err(),
+                "Line table truncated to {} bytes accepted",
+                len
+            );
+        }
+    }
+}
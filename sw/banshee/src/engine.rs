@@ -5,38 +5,72 @@
 //! Engine for dynamic binary translation and execution
 
 use crate::{
-    bootroms::Bootroms, peripherals::Peripherals, riscv, tran::ElfTranslator, util::SiUnit,
-    Configuration,
+    app_args::AppArgs,
+    bootroms::Bootroms,
+    dwarf,
+    peripherals::Peripherals,
+    riscv,
+    softfloat::{self, Sf32, Sf64},
+    stats::{self, Coverage, DmaStats, ExecutionStats, HartStats, STATS_VERSION},
+    tran::{self, ElfTranslator},
+    util::{self, log_target, SiUnit},
+    Configuration, CoreFeatures, CycleModel, Memory, Topology,
 };
 extern crate flexfloat;
 extern crate termion;
 use anyhow::{anyhow, bail, Result};
 use itertools::Itertools;
 use llvm_sys::{
-    analysis::*, core::*, execution_engine::*, ir_reader::*, linker::*, prelude::*, support::*,
-    target_machine::*, transforms::pass_manager_builder::*,
+    analysis::*, core::*, execution_engine::*, initialization::*, ir_reader::*, linker::*,
+    prelude::*, support::*, target::*, target_machine::*, transforms::pass_manager_builder::*,
 };
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    hash::{Hash, Hasher},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Component, Path, PathBuf},
     sync::{
-        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
-        Mutex,
+        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
     },
 };
 use termion::{color, style};
 
-pub use crate::runtime::{Cpu, CpuState, DmaState, SsrState, WakeupState};
+pub use crate::runtime::{Cpu, CpuState, DmaState, MemRegion, SpinState, SsrState, WakeupState};
 
 /// An execution engine.
+///
+/// Use an [`EngineBuilder`] to create one.
 pub struct Engine {
-    /// The global LLVM context.
+    /// The LLVM context.
     pub context: LLVMContextRef,
+    /// Whether the context is disposed when the engine is dropped.
+    owns_context: bool,
+    /// The JIT compilers created during execution, which own the modules
+    /// they compiled.
+    jits: Mutex<Vec<(LLVMExecutionEngineRef, LLVMModuleRef)>>,
     /// The LLVM modules which contains the translated code for each cluster.
     pub modules: Vec<LLVMModuleRef>,
-    /// The exit code set by the binary.
-    pub exit_code: AtomicU32,
+    /// The protocol used to decode the exit code from the scratch register.
+    pub exit_protocol: ExitProtocol,
+    /// What a hart does after it executes an illegal instruction.
+    pub abort_policy: AbortPolicy,
+    /// What to do if a section of a binary overlaps a device or part of the
+    /// TCDM.
+    pub overlap_policy: OverlapPolicy,
+    /// Whether all harts should stop executing, because one of them faulted
+    /// or halted execution.
+    pub stop: AtomicBool,
+    /// Whether execution was stopped because a hart halted it, as opposed to
+    /// an external stop, a timeout, or a fault.
+    pub halted: AtomicBool,
     /// Whether an error occurred during execution.
     pub had_error: AtomicBool,
+    /// The number of loads from addresses outside the memory map.
+    pub unmapped_reads: AtomicU64,
+    /// The number of stores to addresses outside the memory map.
+    pub unmapped_writes: AtomicU64,
     /// Optimize the LLVM IR.
     pub opt_llvm: bool,
     /// Optimize during JIT compilation.
@@ -45,8 +79,82 @@ pub struct Engine {
     pub interrupt: bool,
     /// Enable instruction tracing.
     pub trace: bool,
+    /// Only trace every this many instructions of a hart, namely those after
+    /// which its `instret` is a multiple of it.
+    pub trace_sample: Option<u64>,
     /// Enable instruction latency.
     pub latency: bool,
+    /// Execute floating-point operations with exact rounding and exception
+    /// flags.
+    pub exact_fp: bool,
+    /// Flush subnormal inputs and results of floating-point operations to
+    /// zero of the same sign, like FPUs configured without subnormal support.
+    pub flush_to_zero: bool,
+    /// Enter the trap handler at `mtvec` on `ecall`, `ebreak`, and illegal
+    /// instructions, instead of handling `ecall` in the engine and aborting.
+    pub traps: bool,
+    /// Enter the trap handler at `mtvec` on illegal instructions, and only
+    /// abort if no handler is installed.
+    pub trap_illegal_instructions: bool,
+    /// Estimate the branch targets of the sections of a binary in parallel
+    /// during translation. Only this analysis is parallel: the LLVM IR is
+    /// emitted serially, since the sections are translated into a single
+    /// function, and LLVM contexts are not thread-safe.
+    pub parallel_analysis: bool,
+    /// Emit the code of identical basic blocks once into a function, which is
+    /// called with the address of each occurrence. This shrinks the module
+    /// and speeds up its compilation.
+    pub dedup_blocks: bool,
+    /// Execute the binary one instruction at a time, by calling a function
+    /// emitted for each instruction, rather than the translated binary as a
+    /// whole. This is much slower, but runs the same code for every
+    /// instruction and provides a reference for the optimized translation.
+    pub interpret: bool,
+    /// Execute all harts on one host thread in turns of this many
+    /// instructions, rather than each hart on a thread of its own, such that
+    /// every run interleaves the harts the same way. Takes precedence over
+    /// `interpret`.
+    pub deterministic: Option<u64>,
+    /// Pause a deterministic execution whenever the harts have used up the
+    /// instructions granted by the gate.
+    pub run_gate: Option<Arc<RunGate>>,
+    /// Return a clock shared by all harts from the `cycle` and `time` CSRs,
+    /// rather than the cycles of the reading hart.
+    pub shared_clock: bool,
+    /// The shared clock, which advances by the cycles each hart spent since it
+    /// last read the clock.
+    clock: AtomicU64,
+    /// Gather execution statistics.
+    pub stats: bool,
+    /// Record which instructions are translated and executed.
+    pub coverage: bool,
+    /// Report the execution progress at this interval.
+    pub progress: Option<std::time::Duration>,
+    /// Stop execution with a `Deadlock` error if no hart retires an
+    /// instruction for this long.
+    pub deadlock_timeout: Option<std::time::Duration>,
+    /// The deadlock detected during execution.
+    deadlock: Mutex<Option<Deadlock>>,
+    /// Stop execution with a fault once a hart takes a loop without side
+    /// effects this many times in a row without changing its registers.
+    pub livelock_threshold: Option<u64>,
+    /// Stop execution with a `LimitExceeded` error once a hart retires this
+    /// many instructions. Checked at the end of basic blocks.
+    pub max_instret: Option<u64>,
+    /// Stop execution with a `LimitExceeded` error after running this long.
+    pub max_duration: Option<std::time::Duration>,
+    /// The execution limit exceeded during execution.
+    limit_exceeded: Mutex<Option<LimitExceeded>>,
+    /// The guard regions below the stacks of the harts, as hart ID, start,
+    /// and (exclusive) end address.
+    stack_guards: Vec<(usize, u32, u32)>,
+    /// The first stack overflow detected during execution.
+    stack_overflow: Mutex<Option<StackOverflow>>,
+    /// The first fatal error of a hart, which stopped execution.
+    fault: Mutex<Option<HartFault>>,
+    /// Pin the hart threads to these host CPUs, or round-robin to all host
+    /// CPUs if empty.
+    pub pin_cpus: Option<Vec<usize>>,
     /// The base hartid.
     pub base_hartid: usize,
     /// The number of cores.
@@ -56,1127 +164,5960 @@ pub struct Engine {
     /// The system configuration.
     pub config: Configuration,
     // pub config: Configuration,
-    /// The global memory.
+    /// The global memory. The binaries are loaded into it, and it backs the
+    /// TCDMs, and everything else unless a memory backend is set.
     pub memory: Mutex<HashMap<u64, u32>>,
+    /// The memory backend set with `set_mem_backend`, if any.
+    mem_backend: Option<Mutex<Box<dyn MemBackend>>>,
+    /// The words the translated binaries loaded into memory, which
+    /// `reset_memory` restores.
+    preloaded_memory: Mutex<HashMap<u64, u32>>,
     /// The per-core putchar buffers (per hartid).
     pub putchar_buffer: Mutex<HashMap<usize, Vec<u8>>>,
+    /// The base address of the built-in UART, if enabled.
+    pub uart: Option<u32>,
+    /// The lines each hart is sending through the UART, until they are
+    /// complete.
+    uart_buffer: Mutex<HashMap<usize, Vec<u8>>>,
+    /// The address of the HTIF `tohost` word, if the binary defines one, to
+    /// which riscv-tests style binaries report whether they passed.
+    pub tohost: Option<u32>,
+    /// The instruction mnemonics which have a slot in the instruction mix.
+    pub inst_mnemonics: Mutex<Vec<String>>,
     /// The peripherals for each cluster
     peripherals: Peripherals,
     /// The bootrom
     bootrom: Bootroms,
+    /// The ELF images translated into the modules.
+    pub images: Vec<ElfImage>,
+    /// The basic blocks of the translated images.
+    basic_blocks: Vec<(u32, u32)>,
+    /// Whether the runtime library has been linked into the modules.
+    runtime_linked: bool,
+    /// The instructions encountered during translation and execution.
+    coverage_report: Mutex<Coverage>,
+    /// The instructions which could not be translated.
+    unsupported: Mutex<Vec<UnsupportedInst>>,
+    /// The registers set in each hart before execution.
+    initial_regs: Vec<(u8, u32)>,
+    /// Computes the initial register file of each hart from its hart ID.
+    initial_regs_fn: Option<Box<dyn Fn(usize) -> [u32; 32] + Send + Sync>>,
+    /// The command line arguments of the binary and the address they are
+    /// placed at, which is also the initial stack pointer of the first hart.
+    app_args: Option<(u32, AppArgs)>,
+    /// The register files of the harts, as the last execution left them and
+    /// with the registers set for the next one.
+    hart_regs: Mutex<HashMap<usize, HartRegs>>,
+    /// The watched word addresses, sorted.
+    watchpoints: Vec<(u32, WatchKind)>,
+    /// Called when a watchpoint triggers. Hits are logged if this is not set.
+    pub watch_callback: Option<Box<dyn Fn(&WatchHit) + Send + Sync>>,
+    /// Observes every load, store, and instruction, if set.
+    access_observer: Option<Box<dyn AccessObserver>>,
+    /// The number of most recent loads and stores to keep in the access log,
+    /// which is dumped if an error occurs. Zero disables the log.
+    pub access_log_size: usize,
+    /// The most recent loads and stores of all harts, oldest first.
+    access_log: Mutex<VecDeque<LoggedAccess>>,
+    /// The unimplemented CSRs which have been accessed, to warn only once.
+    unknown_csrs: Mutex<HashSet<u32>>,
+    /// Renders the lines of the execution trace. The built-in format is used
+    /// if this is not set.
+    pub trace_renderer: Option<Box<dyn TraceRenderer>>,
+    /// Log a backtrace of the hart when it aborts, by walking the stack
+    /// through the frame pointer.
+    pub backtrace_on_abort: bool,
+    /// Abort when an SSR is accessed after its stream has run past the end of
+    /// its access pattern, or when its strides move it to an unaligned
+    /// address or out of the TCDM or DRAM it started in.
+    pub check_ssr_bounds: bool,
+    /// Decode the DWARF line info of translated binaries, to annotate the
+    /// execution trace with source lines.
+    pub source_lines: bool,
+    /// Called with the hart ID and cycle when a hart executes `wfi` without a
+    /// pending interrupt. Returning `true` wakes the hart right away, e.g. to
+    /// model an interrupt source outside of the engine; otherwise the hart
+    /// sleeps until it is woken up by another hart or all harts sleep.
+    pub wfi_callback: Option<Box<dyn Fn(usize, u64) -> bool + Send + Sync>>,
+    /// The handlers of `ecall`s, by syscall number.
+    syscalls: HashMap<u32, Syscall>,
+    /// The host directory which the files opened by the binary are confined
+    /// to. The binary cannot open files if this is not set.
+    pub fs_root: Option<PathBuf>,
+    /// The region handed out by the `brk` syscall, as start and (exclusive)
+    /// end address.
+    heap: Option<(u32, u32)>,
+    /// The current program break.
+    brk: AtomicU32,
+    /// The host files opened by the binary, by file descriptor.
+    files: Mutex<HashMap<u32, fs::File>>,
+    /// The handlers of custom instructions, with the mask and value of the
+    /// encodings they handle.
+    custom_insts: Vec<(u32, u32, CustomInst)>,
+    /// The callbacks of breakpoints, by address.
+    breakpoints: HashMap<u32, Vec<Breakpoint>>,
 }
 
-// SAFETY: This is safe because only `context` and `module`
-unsafe impl std::marker::Send for Engine {}
-unsafe impl std::marker::Sync for Engine {}
+/// A handler of an `ecall`. Called with the hart and the arguments in `a0`
+/// to `a6`; the returned value is placed in `a0`.
+pub type Syscall = Box<dyn Fn(&mut Cpu, [u32; 7]) -> u32 + Send + Sync>;
 
-impl Engine {
-    /// Create a new execution engine.
-    pub fn new(context: LLVMContextRef) -> Self {
-        // Wrap everything up in an engine struct.
-        Self {
-            context,
-            modules: Default::default(),
-            exit_code: Default::default(),
-            had_error: Default::default(),
-            opt_llvm: true,
-            opt_jit: true,
-            interrupt: true,
-            trace: false,
-            latency: false,
-            base_hartid: 0,
-            num_cores: 1,
-            num_clusters: 1,
-            config: Default::default(),
-            memory: Default::default(),
-            putchar_buffer: Default::default(),
-            peripherals: Peripherals::new(),
-            bootrom: Bootroms::new(),
-        }
+/// A handler of a custom instruction. Called with the hart and the raw
+/// instruction each time the instruction is executed.
+pub type CustomInst = Box<dyn Fn(&mut Cpu, u32) + Send + Sync>;
+
+/// A callback of a breakpoint. Called each time a hart reaches the
+/// breakpoint's address, before the instruction there executes.
+pub type Breakpoint = Box<dyn Fn(&BreakpointCtx) -> BreakpointAction + Send + Sync>;
+
+/// What a hart does after a breakpoint callback returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BreakpointAction {
+    /// Execute the instruction at the breakpoint and carry on.
+    Continue,
+    /// Stop execution: the hart returns before the instruction at the
+    /// breakpoint, and all other harts at the end of their basic block.
+    Stop,
+}
+
+/// The register file of a hart between executions.
+#[derive(Debug, Clone, Copy, Default)]
+struct HartRegs {
+    regs: [u32; 32],
+    fregs: [u64; 32],
+    /// The integer registers set since the last execution, as a bit mask.
+    set_regs: u32,
+    /// The floating-point registers set since the last execution, as a bit
+    /// mask.
+    set_fregs: u32,
+}
+
+/// The state of a hart which reached a breakpoint.
+pub struct BreakpointCtx<'a> {
+    cpu: &'a Cpu<'a, 'a>,
+}
+
+impl BreakpointCtx<'_> {
+    /// The ID of the hart.
+    pub fn hartid(&self) -> usize {
+        self.cpu.hartid
     }
 
-    /// Create a Module for each cluster
-    pub fn create_modules(&mut self) {
-        for i in 0..self.num_clusters {
-            let module = unsafe {
-                // Wrap the runtime IR up in an LLVM memory buffer.
-                let mut initial_ir = crate::runtime::JIT_INITIAL
-                    .replace("Cpu", format!("{}{}", "Cpu", i.to_string()).as_str())
-                    .as_bytes()
-                    .to_vec();
-                initial_ir.push(0); // somehow this is needed despite RequireNullTerminated=0 below
-                let initial_buf = LLVMCreateMemoryBufferWithMemoryRange(
-                    initial_ir.as_ptr() as *const _,
-                    initial_ir.len() - 1,
-                    b"jit.ll\0".as_ptr() as *const _,
-                    0,
-                );
+    /// The address of the breakpoint.
+    pub fn pc(&self) -> u32 {
+        self.cpu.state.pc
+    }
 
-                // Parse the module.
-                let mut module = std::mem::MaybeUninit::uninit().assume_init();
-                let mut errmsg = std::mem::MaybeUninit::zeroed().assume_init();
-                if LLVMParseIRInContext(self.context, initial_buf, &mut module, &mut errmsg) != 0
-                    || !errmsg.is_null()
-                {
-                    error!(
-                        "Cannot parse `jit.ll` IR: {:?}",
-                        std::ffi::CStr::from_ptr(errmsg)
-                    );
-                }
+    /// The number of instructions the hart retired before the breakpoint.
+    pub fn instret(&self) -> u64 {
+        self.cpu.state.instret
+    }
 
-                // let module =
-                //     LLVMModuleCreateWithNameInContext(b"banshee\0".as_ptr() as *const _, context);
-                // LLVMSetDataLayout(module, b"i8:8-i16:16-i32:32-i64:64\0".as_ptr() as *const _);
-                module
-            };
+    /// The value of integer register `x<index>`.
+    pub fn reg(&self, index: usize) -> u32 {
+        self.cpu.state.regs[index]
+    }
 
-            self.modules.push(module);
-        }
+    /// The raw value of floating-point register `f<index>`.
+    pub fn freg(&self, index: usize) -> u64 {
+        self.cpu.state.fregs[index]
     }
 
-    /// Translate an ELF binary.
-    pub fn translate_elf(&self, elf: &elf::File) -> Result<()> {
-        for i in 0..self.num_clusters {
-            let mut tran = ElfTranslator::new(elf, self, i);
+    /// Read a region of memory as seen by the hart, including its TCDM.
+    pub fn read_mem(&self, addr: u32, len: usize) -> Vec<u8> {
+        self.cpu.read_mem(addr, len)
+    }
+}
 
-            // Dump the contents of the binary.
-            debug!("Loading ELF binary");
-            for section in tran.sections() {
-                debug!(
-                    "Loading ELF section `{}` from 0x{:x} to 0x{:x}",
-                    section.shdr.name,
-                    section.shdr.addr,
-                    section.shdr.addr + section.shdr.size
-                );
-                for (addr, inst) in tran.instructions(section) {
-                    trace!("  - 0x{:x}: {}", addr, inst);
-                }
-            }
+/// The number of the built-in `write` syscall.
+pub const SYS_WRITE: u32 = 64;
+/// The number of the built-in `exit` syscall.
+pub const SYS_EXIT: u32 = 93;
+/// The number of the built-in `exit_group` syscall, which behaves like `exit`.
+pub const SYS_EXIT_GROUP: u32 = 94;
+/// The number of the built-in `openat` syscall. Only paths relative to the
+/// current directory (`AT_FDCWD`) are supported.
+pub const SYS_OPENAT: u32 = 56;
+/// The number of the built-in `close` syscall.
+pub const SYS_CLOSE: u32 = 57;
+/// The number of the built-in `lseek` syscall.
+pub const SYS_LSEEK: u32 = 62;
+/// The number of the built-in `read` syscall.
+pub const SYS_READ: u32 = 63;
+/// The number of the built-in `fstat` syscall.
+pub const SYS_FSTAT: u32 = 80;
+/// The number of the built-in `brk` syscall.
+pub const SYS_BRK: u32 = 214;
+/// The number of the built-in `open` syscall, as used by older versions of
+/// newlib.
+pub const SYS_OPEN: u32 = 1024;
 
-            // Estimate the branch target addresses.
-            tran.update_target_addrs();
+/// The offset of the transmit register of the built-in UART.
+const UART_TX: u32 = 0x00;
+/// The offset of the line status register of the built-in UART.
+const UART_LSR: u32 = 0x14;
+/// The line status of the built-in UART: the transmitter is always empty.
+const UART_LSR_READY: u32 = 0x60;
+/// The size of the register window of the built-in UART.
+const UART_SIZE: u32 = 0x20;
 
-            // Translate the binary.
-            tran.translate()?;
+/// The `dirfd` of `openat` which refers to the current directory.
+const AT_FDCWD: i32 = -100;
+/// The size of the `struct stat` of newlib on RV32.
+const STAT_SIZE: usize = 128;
 
-            // Load and link the LLVM IR for the `jit.rs` runtime library.
-            unsafe {
-                let mut runtime_ir = crate::runtime::JIT_GENERATED.to_vec();
-                runtime_ir.push(0); // somehow this is needed despite RequireNullTerminated=0 below
-                let runtime_buf = LLVMCreateMemoryBufferWithMemoryRange(
-                    runtime_ir.as_ptr() as *const _,
-                    runtime_ir.len() - 1,
-                    b"jit.rs\0".as_ptr() as *const _,
-                    0,
-                );
+// The error numbers of newlib.
+const EIO: i32 = 5;
+const EBADF: i32 = 9;
+const EACCES: i32 = 13;
+const EINVAL: i32 = 22;
+const ESPIPE: i32 = 29;
 
-                // Parse the module.
-                let mut runtime = std::mem::MaybeUninit::uninit().assume_init();
-                let mut errmsg = std::mem::MaybeUninit::zeroed().assume_init();
-                if LLVMParseIRInContext(self.context, runtime_buf, &mut runtime, &mut errmsg) != 0
-                    || !errmsg.is_null()
-                {
-                    error!(
-                        "Cannot parse `jit.rs` IR: {:?}",
-                        std::ffi::CStr::from_ptr(errmsg)
-                    );
-                }
+// The `open` flags of newlib.
+const O_ACCMODE: u32 = 3;
+const O_RDONLY: u32 = 0;
+const O_WRONLY: u32 = 1;
+const O_APPEND: u32 = 0x008;
+const O_CREAT: u32 = 0x200;
+const O_TRUNC: u32 = 0x400;
+const O_EXCL: u32 = 0x800;
 
-                // Link the runtime module into the translated binary module.
-                LLVMLinkModules2(self.modules[i], runtime);
-            };
+/// The return value of a failed syscall.
+fn errno(e: i32) -> u32 {
+    -e as u32
+}
 
-            // Verify that nothing is broken at this point.
-            let failed = unsafe {
-                LLVMVerifyModule(
-                    self.modules[i],
-                    LLVMVerifierFailureAction::LLVMPrintMessageAction,
-                    std::ptr::null_mut(),
-                )
-            };
-            if failed != 0 {
-                let path = "/tmp/banshee_failed.ll";
-                unsafe {
-                    LLVMPrintModuleToFile(
-                        self.modules[i],
-                        format!("{}\0", path).as_ptr() as *const _,
-                        std::ptr::null_mut(),
-                    );
-                }
-                bail!(
-                    "LLVM module did not pass verification (failing IR written to {})",
-                    path
-                );
-            }
-        }
+/// The return value of a syscall which failed on the host.
+fn io_errno(e: std::io::Error) -> u32 {
+    errno(e.raw_os_error().unwrap_or(EIO))
+}
 
-        // Optimize the translation.
-        if self.opt_llvm {
-            unsafe { self.optimize() };
+/// Resolve a path of the binary below the file system root, or `None` if it
+/// would escape the root. Symbolic links are followed as long as they stay
+/// below the root.
+fn sandboxed_path(root: &Path, path: &str) -> Option<PathBuf> {
+    let mut resolved = root.to_path_buf();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(name) => resolved.push(name),
+            Component::RootDir | Component::CurDir => (),
+            Component::ParentDir | Component::Prefix(_) => return None,
         }
+    }
+    // Resolve the links of the part of the path which exists. The rest, e.g.
+    // the name of a file to be created, cannot contain any links.
+    let mut existing = resolved.as_path();
+    let mut missing = vec![];
+    while existing.symlink_metadata().is_err() {
+        missing.push(existing.file_name()?);
+        existing = existing.parent()?;
+    }
+    let mut canonical = existing.canonicalize().ok()?;
+    canonical.extend(missing.iter().rev());
+    if canonical.starts_with(root.canonicalize().ok()?) {
+        Some(canonical)
+    } else {
+        None
+    }
+}
 
-        // Copy the executable sections into memory.
-        {
-            let mut mem = self.memory.lock().unwrap();
-            for section in &elf.sections {
-                if (section.shdr.flags.0 & elf::types::SHF_ALLOC.0) == 0 {
-                    continue;
-                }
-                use byteorder::{LittleEndian, ReadBytesExt};
-                trace!("Preloading ELF section `{}`", section.shdr.name);
-                mem.extend(
-                    section
-                        .data
-                        .chunks(4)
-                        .enumerate()
-                        .map(|(offset, mut value)| {
-                            let addr = section.shdr.addr + offset as u64 * 4;
-                            let value = value.read_u32::<LittleEndian>().unwrap_or(0);
-                            trace!("  - 0x{:x} = 0x{:x}", addr, value);
-                            (addr, value)
-                        }),
-                );
-            }
-        }
+/// The `mstatus` fields which are modeled: MIE and MPIE.
+const MSTATUS_WRITABLE: u32 = 1 << 3 | 1 << 7;
+/// The read-only `mstatus` fields. MPP is always machine mode, and FS always
+/// reads as dirty since the floating-point state is not tracked, which sets SD.
+const MSTATUS_FIXED: u32 = 3 << 11 | 3 << 13 | 1 << 31;
 
-        Ok(())
+/// The exit code reported if the binary did not write one.
+const MISSING_EXIT_CODE: u32 = 117;
+
+/// The maximum number of frames walked by `Cpu::backtrace`.
+const MAX_BACKTRACE_DEPTH: usize = 64;
+
+/// The kind of memory access which triggers a watchpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Both,
+}
+
+impl WatchKind {
+    /// Whether an access triggers this kind of watchpoint.
+    pub fn matches(self, write: bool) -> bool {
+        match self {
+            WatchKind::Read => !write,
+            WatchKind::Write => write,
+            WatchKind::Both => true,
+        }
     }
+}
 
-    unsafe fn optimize(&self) {
-        debug!("Optimizing IR");
+/// A memory access which triggered a watchpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    /// The hart which performed the access.
+    pub hartid: usize,
+    /// The program counter of the access.
+    pub pc: u32,
+    /// The accessed address.
+    pub addr: u32,
+    /// Whether the access was a write.
+    pub write: bool,
+    /// The watched word before the access.
+    pub old: u32,
+    /// The watched word after the access.
+    pub new: u32,
+}
 
-        // Create the pass managers.
-        for i in 0..self.num_clusters {
-            let func_passes = LLVMCreateFunctionPassManagerForModule(self.modules[i]);
-            let module_passes = LLVMCreatePassManager();
+/// Observes the memory accesses and instructions of the harts, for example to
+/// drive a cache model.
+///
+/// The callbacks are called from the harts' threads as they execute, so they
+/// should be cheap. Sizes are in bytes. Accesses by SSRs and the DMA are not
+/// observed.
+pub trait AccessObserver: Send + Sync {
+    /// Called for every load, before it is performed.
+    fn on_load(&self, _hartid: usize, _pc: u32, _addr: u32, _size: u32) {}
 
-            // Determine the target machine we are running on.
-            let tm_triple = LLVMGetDefaultTargetTriple();
-            let mut tm_target = std::ptr::null_mut();
-            let mut tm_target_msg = std::ptr::null_mut();
-            assert_eq!(
-                LLVMGetTargetFromTriple(tm_triple, &mut tm_target, &mut tm_target_msg),
-                0
-            );
-            let tm_cpu = LLVMGetHostCPUName();
-            let tm_features = LLVMGetHostCPUFeatures();
-            let tm = LLVMCreateTargetMachine(
-                tm_target,
-                tm_triple,
-                tm_cpu,
-                tm_features,
-                LLVMCodeGenOptLevel::LLVMCodeGenLevelAggressive,
-                LLVMRelocMode::LLVMRelocDefault,
-                LLVMCodeModel::LLVMCodeModelJITDefault,
-            );
-            LLVMDisposeMessage(tm_triple);
-            LLVMDisposeMessage(tm_cpu);
-            LLVMDisposeMessage(tm_features);
+    /// Called for every store, before it is performed.
+    fn on_store(&self, _hartid: usize, _pc: u32, _addr: u32, _size: u32) {}
 
-            // Create a pass manager builder.
-            let builder = LLVMPassManagerBuilderCreate();
-            LLVMPassManagerBuilderSetOptLevel(builder, 3);
-            LLVMPassManagerBuilderSetSizeLevel(builder, 0);
-            LLVMPassManagerBuilderUseInlinerWithThreshold(builder, 275);
+    /// Called for every instruction, before it is executed.
+    fn on_instruction(&self, _hartid: usize, _pc: u32) {}
+}
 
-            LLVMPassManagerBuilderPopulateFunctionPassManager(builder, func_passes);
-            LLVMAddAnalysisPasses(tm, module_passes);
-            LLVMPassManagerBuilderPopulateLTOPassManager(builder, module_passes, 0, 1);
-            LLVMPassManagerBuilderPopulateModulePassManager(builder, module_passes);
+/// Stores the global memory outside the TCDMs, for example in a file mapped
+/// into memory, or in an array shared with another simulator.
+///
+/// Addresses are word-aligned. Words which have never been stored should load
+/// as zero. Smaller stores and atomics read, modify, and write the word while
+/// the engine holds the backend locked.
+pub trait MemBackend: Send {
+    /// Load the word at an address.
+    fn load_word(&mut self, addr: u32) -> u32;
 
-            // Create and run the function pass manager.
-            LLVMInitializeFunctionPassManager(func_passes);
-            let mut func = LLVMGetFirstFunction(self.modules[i]);
-            while !func.is_null() {
-                let mut name_len = 0;
-                let name = LLVMGetValueName2(func, &mut name_len);
-                let name = std::slice::from_raw_parts(name as *const u8, name_len as usize);
-                let name = std::str::from_utf8_unchecked(name);
-                trace!("  - Optimizing function {}", name);
-                LLVMRunFunctionPassManager(func_passes, func);
-                func = LLVMGetNextFunction(func);
-            }
-            LLVMFinalizeFunctionPassManager(func_passes);
+    /// Store a word at an address.
+    fn store_word(&mut self, addr: u32, value: u32);
+}
 
-            // Create and run the module pass manager.
-            trace!("  - Optimizing module");
-            LLVMRunPassManager(module_passes, self.modules[i]);
+/// The default memory backend, which only stores the words written so far.
+impl MemBackend for HashMap<u64, u32> {
+    fn load_word(&mut self, addr: u32) -> u32 {
+        self.get(&(addr as u64)).copied().unwrap_or(0)
+    }
 
-            // Clean up.
-            LLVMPassManagerBuilderDispose(builder);
-            LLVMDisposePassManager(func_passes);
-            LLVMDisposePassManager(module_passes);
-            LLVMDisposeTargetMachine(tm);
-        }
+    fn store_word(&mut self, addr: u32, value: u32) {
+        self.insert(addr as u64, value);
     }
+}
 
-    pub fn init_periphs(&mut self) {
-        debug!("Adding peripherals");
-        (0..self.num_clusters).for_each(|i| {
-            self.peripherals
-                .add_cluster(&self.config.memory[i].periphs.callbacks)
-        })
+/// No hart retired an instruction within the deadlock timeout, for example
+/// because harts wait in the cluster barrier for a hart which never arrives.
+///
+/// Harts which spin in a loop keep retiring instructions, and are not
+/// detected as deadlocked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deadlock {
+    /// The time without retired instructions after which execution stopped.
+    pub timeout: std::time::Duration,
+    /// The hart IDs and program counters of the harts which were still
+    /// running.
+    pub harts: Vec<(usize, u32)>,
+}
+
+impl std::fmt::Display for Deadlock {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Deadlock: no hart retired an instruction for {:?}, pcs: {}",
+            self.timeout,
+            self.harts
+                .iter()
+                .map(|(hartid, pc)| format!("{}:{:08x}", hartid, pc))
+                .join(" ")
+        )
     }
+}
 
-    pub fn init_bootrom(&mut self) {
-        debug!("Adding bootrom");
-        if self.config.bootrom.callbacks.is_empty() {
-            self.config.bootrom.end = 0;
-        } else {
-            self.bootrom.add_bootrom(&self.config.bootrom.callbacks)
+impl std::error::Error for Deadlock {}
+
+/// An execution limit which stops runaway binaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// The maximum number of instructions retired by each hart.
+    Instret(u64),
+    /// The maximum wall-clock duration of the execution.
+    Duration(std::time::Duration),
+}
+
+/// The execution exceeded an execution limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LimitExceeded {
+    /// The limit which was exceeded.
+    pub limit: Limit,
+    /// The hart IDs and program counters of the harts which exceeded it, or
+    /// of all running harts for the wall-clock limit.
+    pub harts: Vec<(usize, u32)>,
+}
+
+impl std::fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Execution limit exceeded at pc={}: ",
+            self.harts
+                .iter()
+                .map(|(hartid, pc)| format!("0x{:08x} (hart {})", pc, hartid))
+                .join(", ")
+        )?;
+        match self.limit {
+            Limit::Instret(n) => write!(f, "retired {} instructions", n),
+            Limit::Duration(d) => write!(f, "ran for {:?}", d),
         }
     }
+}
 
-    // Execute the loaded memory.
-    pub fn execute(&self) -> Result<u32> {
-        unsafe { self.execute_inner() }
+impl std::error::Error for LimitExceeded {}
+
+/// A hart accessed the stack guard region of a hart, which usually means that
+/// the stack of that hart overflowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackOverflow {
+    /// The hart which performed the access.
+    pub hartid: usize,
+    /// The program counter of the access.
+    pub pc: u32,
+    /// The accessed address.
+    pub addr: u32,
+    /// Whether the access was a write.
+    pub write: bool,
+    /// The hart whose stack guard was accessed.
+    pub owner: usize,
+}
+
+impl std::fmt::Display for StackOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Stack overflow: hart {} (pc=0x{:08x}) {} 0x{:08x} in the stack guard of hart {}",
+            self.hartid,
+            self.pc,
+            if self.write { "writes" } else { "reads" },
+            self.addr,
+            self.owner
+        )
     }
+}
 
-    unsafe fn execute_inner<'b>(&'b self) -> Result<u32> {
-        // Create a JIT compiler for the module (and consumes it).
-        debug!("Creating JIT compiler for translated code");
-        let execs: Vec<_> = (0..self.num_clusters)
-            .map(|i| {
-                let mut ee = std::mem::MaybeUninit::uninit().assume_init();
-                let mut errmsg = std::mem::MaybeUninit::zeroed().assume_init();
-                let optlevel = if self.opt_jit { 3 } else { 0 };
-                LLVMCreateJITCompilerForModule(&mut ee, self.modules[i], optlevel, &mut errmsg);
-                if !errmsg.is_null() {
-                    panic!(
-                        "Cannot create JIT compiler: {:?}",
-                        std::ffi::CStr::from_ptr(errmsg)
-                    )
-                }
+impl std::error::Error for StackOverflow {}
 
-                // Lookup the function which executes the binary.
-                let exec: for<'c> extern "C" fn(&'c Cpu<'b, 'c>) = std::mem::transmute(
-                    LLVMGetFunctionAddress(ee, b"execute_binary\0".as_ptr() as *const _),
-                );
-                debug!("Translated binary is at {:?}", exec as *const i8);
-                exec
-            })
-            .collect();
+/// An instruction which banshee cannot execute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedInst {
+    /// The address of the instruction.
+    pub addr: u32,
+    /// The encoding of the instruction.
+    pub raw: u32,
+    /// Why the instruction is not supported.
+    pub reason: String,
+}
 
-        // Allocate some TCDM memories.
-        let tcdms: Vec<_> = (0..self.num_clusters)
-            .map(|i| {
-                let mut tcdm = vec![
-                    0u32;
-                    ((self.config.memory[i].tcdm.end - self.config.memory[i].tcdm.start) / 4)
-                        as usize
-                ];
+/// A binary failed validation, because it contains instructions which banshee
+/// cannot execute.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// The unsupported instructions, by address.
+    pub unsupported: Vec<UnsupportedInst>,
+}
 
-                for (&addr, &value) in self.memory.lock().unwrap().iter() {
-                    if (addr as u32) >= self.config.memory[i].tcdm.start
-                        && (addr as u32) < self.config.memory[i].tcdm.end
-                    {
-                        tcdm[((addr - (self.config.memory[i].tcdm.start as u64)) / 4) as usize] =
-                            value;
-                    }
-                }
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} unsupported instruction(s): {}",
+            self.unsupported.len(),
+            self.unsupported
+                .iter()
+                .map(|inst| format!("0x{:08x} ({:08x}): {}", inst.addr, inst.raw, inst.reason))
+                .join(", ")
+        )
+    }
+}
 
-                tcdm
-            })
-            .collect();
+impl std::error::Error for ValidationError {}
 
-        // External TCDM
-        let ext_tcdms: Vec<_> = (0..self.num_clusters).map(|i| &tcdms[i][0]).collect();
+/// A hart hit a fatal error, which stopped all harts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HartFault {
+    /// The hart which faulted.
+    pub hartid: usize,
+    /// The program counter of the faulting instruction.
+    pub pc: u32,
+    /// What went wrong.
+    pub reason: String,
+}
 
-        // Allocate some barriers.
-        let barriers: Vec<_> = (0..self.num_clusters)
-            .map(|_| AtomicUsize::new(0))
-            .collect();
+impl std::fmt::Display for HartFault {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Hart {} (pc=0x{:08x}) {}",
+            self.hartid, self.pc, self.reason
+        )
+    }
+}
 
-        // Allocate state struct to keep track of sleeping cores.
-        let wakeup_state = Mutex::new(WakeupState {
-            num: 0,
-            req: vec![0; self.num_clusters * self.num_cores],
-            wfi: vec![false; self.num_clusters * self.num_cores],
-        });
+impl std::error::Error for HartFault {}
 
-        // Allocate CLINT registers
-        let n_virt_cores = self.num_clusters * self.num_cores + self.base_hartid;
-        let clint: Vec<_> = (0..(n_virt_cores + 32 - 1) / 32)
-            .map(|_| AtomicU32::new(0))
-            .collect();
+/// An error which stopped an execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecError {
+    /// All harts waited for each other, e.g. in `wfi` or the barrier.
+    Deadlock(Deadlock),
+    /// A hart accessed the stack guard region of a hart.
+    StackOverflow(StackOverflow),
+    /// The execution exceeded its instruction or time budget.
+    LimitExceeded(LimitExceeded),
+    /// A hart aborted, e.g. on an illegal instruction.
+    Fault(HartFault),
+    /// An error was flagged without a more specific cause.
+    Other,
+}
 
-        // Allocate cluster-local CLINT registers
-        let cl_clints: Vec<_> = (0..self.num_clusters)
-            .map(|_| AtomicUsize::new(0))
-            .collect();
+impl std::fmt::Display for ExecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ExecError::Deadlock(e) => e.fmt(f),
+            ExecError::StackOverflow(e) => e.fmt(f),
+            ExecError::LimitExceeded(e) => e.fmt(f),
+            ExecError::Fault(e) => e.fmt(f),
+            ExecError::Other => write!(f, "Encountered an error during execution"),
+        }
+    }
+}
 
-        // Create the CPUs.
-        let cpus: Vec<_> = (0..self.num_clusters)
-            .flat_map(|j| (0..self.num_cores).map(move |i| (j, i)))
-            .map(|(j, i)| {
-                let base_hartid = self.base_hartid + j * self.num_cores;
-                Cpu::new(
-                    self,
-                    &tcdms[j][0],
-                    &ext_tcdms,
-                    base_hartid + i,
-                    self.num_cores,
-                    base_hartid,
-                    j,
-                    &barriers[j],
-                    &wakeup_state,
-                    &clint,
-                    &cl_clints[j],
-                )
-            })
-            .collect();
-        trace!(
-            "Initial state hart {}: {:#?}",
-            cpus[0].hartid,
-            cpus[0].state
-        );
+impl std::error::Error for ExecError {}
 
-        // Execute the binary.
-        info!("Launching binary on {} harts", cpus.len());
-        let t0 = std::time::Instant::now();
-        crossbeam_utils::thread::scope(|s| {
-            for cpu in &cpus {
-                let exec = execs[cpu.cluster_id];
-                s.spawn(move |_| {
-                    exec(cpu);
-                    debug!("Hart {} finished", cpu.hartid);
-                });
+impl ExecError {
+    /// Convert into an error which can be downcast to the specific error.
+    pub fn into_error(self) -> anyhow::Error {
+        match self {
+            ExecError::Deadlock(e) => e.into(),
+            ExecError::StackOverflow(e) => e.into(),
+            ExecError::LimitExceeded(e) => e.into(),
+            ExecError::Fault(e) => e.into(),
+            e @ ExecError::Other => e.into(),
+        }
+    }
+}
+
+/// The outcome of an execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// The binary halted with an exit code, decoded with the exit protocol.
+    Halted(u32),
+    /// The harts ended without reporting an exit code.
+    Running,
+    /// Execution stopped with an error.
+    Error(ExecError),
+}
+
+impl ExitStatus {
+    /// The exit code, like `Engine::execute` returns it: 117 if the binary
+    /// reported none, and an error if execution stopped with one.
+    pub fn exit_code(self) -> Result<u32> {
+        match self {
+            ExitStatus::Halted(code) => Ok(code),
+            ExitStatus::Running => Ok(MISSING_EXIT_CODE),
+            ExitStatus::Error(e) => Err(e.into_error()),
+        }
+    }
+}
+
+/// A memory access recorded in the access log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoggedAccess {
+    /// The hart which performed the access.
+    pub hartid: usize,
+    /// The program counter of the access.
+    pub pc: u32,
+    /// The accessed address.
+    pub addr: u32,
+    /// The loaded or stored value.
+    pub value: u32,
+    /// The size of the access, as the log2 of the number of bytes.
+    pub size: u8,
+    /// Whether the access was a write.
+    pub write: bool,
+}
+
+/// An ELF image translated into the engine.
+#[derive(Debug, Clone)]
+pub struct ElfImage {
+    /// The name of the function which executes the image.
+    pub entry: String,
+    /// The harts executing the image, or `None` for all harts which are not
+    /// assigned to another image.
+    pub harts: Option<Vec<usize>>,
+    /// The executable address ranges of the image.
+    pub exec_ranges: Vec<(u64, u64)>,
+    /// The address ranges of all sections loaded into memory, including the
+    /// executable ones and the thread-local storage of the harts.
+    pub alloc_ranges: Vec<(u64, u64)>,
+    /// The thread-local storage of the harts, if the image has any.
+    pub tls: Option<TlsImage>,
+    /// The code symbols of the image and their addresses, sorted by address.
+    pub symbols: Vec<(u64, String)>,
+    /// The source lines of the image's code, if `source_lines` is enabled and
+    /// the binary has DWARF line info.
+    pub lines: Option<dwarf::LineTable>,
+    /// The address at which the image starts executing.
+    pub entry_point: u64,
+    /// The functions which execute the instructions of the image in
+    /// interpreter mode.
+    pub steps: Vec<tran::StepUnit>,
+}
+
+/// The thread-local storage of an image, of which each hart gets a copy.
+///
+/// The copies follow the sections of the image in the DRAM of the first
+/// cluster, one per hart in the order of the hart IDs. Each hart starts with
+/// `tp` pointing at its copy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsImage {
+    /// The address of the copy of the first hart.
+    pub base: u32,
+    /// The distance between the copies of consecutive harts.
+    pub stride: u32,
+    /// The initial contents of each copy: `.tdata`, followed by the zeroed
+    /// `.tbss`.
+    pub template: Vec<u8>,
+}
+
+impl TlsImage {
+    /// The address of the copy of the hart at an index from the base hartid.
+    pub fn addr(&self, index: usize) -> u32 {
+        self.base + self.stride * index as u32
+    }
+}
+
+/// Whether a section is `.tbss`, whose address only describes the layout of
+/// the thread-local storage and overlaps the sections after it.
+fn is_tbss(section: &elf::Section) -> bool {
+    section.shdr.shtype == elf::types::SHT_NOBITS
+        && (section.shdr.flags.0 & elf::types::SHF_TLS.0) != 0
+}
+
+/// Store `len` bytes at `addr` into the word-addressed memory, where `byte`
+/// returns the byte at an offset. Words which the bytes only partially cover
+/// keep their other bytes, such that unaligned sections do not clobber their
+/// neighbours.
+fn preload_bytes(mem: &mut HashMap<u64, u32>, addr: u64, len: u64, byte: impl Fn(u64) -> u8) {
+    let end = addr + len;
+    for word in ((addr & !3)..end).step_by(4) {
+        let value = mem.entry(word).or_default();
+        let mut bytes = value.to_le_bytes();
+        for (i, b) in bytes.iter_mut().enumerate() {
+            let a = word + i as u64;
+            if a >= addr && a < end {
+                *b = byte(a - addr);
             }
-        })
-        .unwrap();
-        let t1 = std::time::Instant::now();
-        let duration = (t1.duration_since(t0)).as_secs_f64();
-        debug!("All {} harts finished", cpus.len());
+        }
+        *value = u32::from_le_bytes(bytes);
+        trace!(target: log_target::MEM, "  - 0x{:x} = 0x{:x}", word, *value);
+    }
+}
 
-        // Count the number of instructions that we have retired.
-        let instret: u64 = cpus.iter().map(|cpu| cpu.state.instret).sum();
+/// The function which executes an interpreter step, with the signature
+/// described in `tran::ElfTranslator::emit_step_units`.
+type StepFunc<'b> = for<'c> extern "C" fn(&'c Cpu<'b, 'c>, u32, u32, &mut u32, &mut u32) -> u32;
 
-        // Print some final statistics.
-        trace!("Final state hart {}: {:#?}", cpus[0].hartid, cpus[0].state);
-        for cpu in &cpus {
-            trace!("Final state hart {}: {:#?}", cpu.hartid, cpu.state);
+/// The interpreter steps of an image, by the address of their first
+/// instruction.
+type StepFuncs<'b> = HashMap<u64, (StepFunc<'b>, tran::StepUnit)>;
+
+/// Grants a deterministic execution a budget of instructions at a time, and
+/// pauses it once they have been used up.
+///
+/// This runs a binary in bounded steps, e.g. interleaved with the simulation
+/// of an RTL testbench. Between the steps, the memory can be inspected and
+/// modified; the TCDM contents are only written back once execution ends.
+#[derive(Debug, Default)]
+pub struct RunGate {
+    state: Mutex<RunGateState>,
+    cond: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct RunGateState {
+    /// The instructions the harts may retire before pausing, or `None` if
+    /// they may run until the end.
+    budget: Option<u64>,
+    /// Whether the execution waits for more instructions.
+    paused: bool,
+    /// Whether the execution has ended.
+    finished: bool,
+}
+
+impl RunGate {
+    /// Prepare for an execution which pauses before its first instruction.
+    pub fn start(&self) {
+        *self.state.lock().unwrap() = RunGateState {
+            budget: Some(0),
+            ..Default::default()
+        };
+    }
+
+    /// Grant `n` more instructions to all harts together, and wait until the
+    /// execution pauses again or ends. Returns whether it has ended.
+    pub fn run(&self, n: u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.budget = state.budget.map(|budget| budget + n);
+        state.paused = false;
+        self.cond.notify_all();
+        while !state.paused && !state.finished {
+            state = self.cond.wait(state).unwrap();
         }
-        for cpu in &cpus {
-            let ssr_vec = &cpu.state.ssrs;
-            let mut ssr_id = 0;
-            for ssr in ssr_vec {
-                for idx in 0..ssr.index.len() {
-                    if ssr.index[idx] != ssr.bound[idx] && ssr.index[idx] != 0 {
-                        warn!(
-                            "Final state hart {}: SSR {} NOT fully consumed.",
-                            cpu.hartid, ssr_id
-                        );
-                        warn!(
-                            "index != bound : {:?} != {:?}",
-                            ssr.index[idx], ssr.bound[idx]
-                        );
-                    }
+        state.finished
+    }
+
+    /// Let the execution run until the end without pausing.
+    pub fn open(&self) {
+        self.state.lock().unwrap().budget = None;
+        self.cond.notify_all();
+    }
+
+    /// Record that the execution has ended.
+    pub fn finish(&self) {
+        self.state.lock().unwrap().finished = true;
+        self.cond.notify_all();
+    }
+
+    /// Take up to `max` instructions from the budget, and pause while it is
+    /// used up.
+    fn take(&self, max: u64) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            match state.budget {
+                None => return max,
+                Some(0) => {
+                    state.paused = true;
+                    self.cond.notify_all();
+                    state = self.cond.wait(state).unwrap();
                 }
-                if ((ssr.dims != 0) && !(ssr.done)) {
-                    trace!(
-                        "Final state hart {}: SSR {} NOT fully consumed.",
-                        cpu.hartid,
-                        ssr_id
-                    );
-                    warn!(
-                        "Final state hart {}: SSR {} NOT fully consumed.",
-                        cpu.hartid, ssr_id
-                    );
-                } else if ((ssr.dims != 0) && ssr.done) {
-                    trace!(
-                        "Final state hart {}: SSR {} fully consumed.",
-                        cpu.hartid,
-                        ssr_id
-                    );
-                } else {
-                    trace!("Final state hart {}: SSR {} not used.", cpu.hartid, ssr_id);
+                Some(budget) => {
+                    let n = budget.min(max);
+                    state.budget = Some(budget - n);
+                    return n;
                 }
-                ssr_id += 1;
             }
         }
-        // Fetch the return value {ret[31:1] = exit_code, ret[0] = exit_code_valid}
-        let ret = self.exit_code.load(Ordering::SeqCst);
-        if (ret & 0x1) == 0x1 {
-            info!("Exit code is 0x{:x}", ret >> 1);
-        } else {
-            warn!("Exit code register was empty.")
+    }
+
+    /// Whether the budget is used up, such that the execution pauses once it
+    /// asks for more.
+    fn exhausted(&self) -> bool {
+        self.state.lock().unwrap().budget == Some(0)
+    }
+
+    /// Return instructions which were taken but not executed.
+    fn give_back(&self, n: u64) {
+        if let Some(budget) = &mut self.state.lock().unwrap().budget {
+            *budget += n;
         }
-        info!(
-            "Retired {} ({}) in {}, {}",
-            instret,
-            (instret as isize).si_unit("inst"),
-            duration.si_unit("s"),
-            (instret as f64 / duration).si_unit("inst/s"),
-        );
-        if self.had_error.load(Ordering::SeqCst) {
-            Err(anyhow!("Encountered an error during execution"))
-        } else if (ret & 0x1) != 0x1 {
-            // Call the police if no return value was specified
-            Ok(117)
-        } else {
-            Ok(ret >> 1)
-        }
-    }
-}
-
-pub unsafe fn add_llvm_symbols() {
-    LLVMAddSymbol(
-        b"banshee_load\0".as_ptr() as *const _,
-        Cpu::binary_load as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_store\0".as_ptr() as *const _,
-        Cpu::binary_store as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_rmw\0".as_ptr() as *const _,
-        Cpu::binary_rmw as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_csr_read\0".as_ptr() as *const _,
-        Cpu::binary_csr_read as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_csr_write\0".as_ptr() as *const _,
-        Cpu::binary_csr_write as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_abort_escape\0".as_ptr() as *const _,
-        Cpu::binary_abort_escape as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_abort_illegal_inst\0".as_ptr() as *const _,
-        Cpu::binary_abort_illegal_inst as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_abort_illegal_branch\0".as_ptr() as *const _,
-        Cpu::binary_abort_illegal_branch as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_trace\0".as_ptr() as *const _,
-        Cpu::binary_trace as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_wfi\0".as_ptr() as *const _,
-        Cpu::binary_wfi as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_check_clint\0".as_ptr() as *const _,
-        Cpu::binary_check_clint as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_check_cl_clint\0".as_ptr() as *const _,
-        Cpu::binary_check_cl_clint as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_fp16_op_cvt_from_f\0".as_ptr() as *const _,
-        Cpu::binary_fp16_op_cvt_from_f as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_fp64_op_cvt_to_f\0".as_ptr() as *const _,
-        Cpu::binary_fp64_op_cvt_to_f as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_fp32_op_cvt_to_f\0".as_ptr() as *const _,
-        Cpu::binary_fp32_op_cvt_to_f as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_fp16_op_cvt_to_f\0".as_ptr() as *const _,
-        Cpu::binary_fp16_op_cvt_to_f as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_fp8_op_cvt_from_f\0".as_ptr() as *const _,
-        Cpu::binary_fp8_op_cvt_from_f as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_fp8_op_cvt_to_f\0".as_ptr() as *const _,
-        Cpu::binary_fp8_op_cvt_to_f as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_fp16_op_cmp\0".as_ptr() as *const _,
-        Cpu::binary_fp16_op_cmp as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_fp8_op_cmp\0".as_ptr() as *const _,
-        Cpu::binary_fp8_op_cmp as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_fp16_op\0".as_ptr() as *const _,
-        Cpu::binary_fp16_op as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_fp8_op\0".as_ptr() as *const _,
-        Cpu::binary_fp8_op as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_fp16_to_fp32_op\0".as_ptr() as *const _,
-        Cpu::binary_fp16_to_fp32_op as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_fp8_to_fp16_op\0".as_ptr() as *const _,
-        Cpu::binary_fp8_to_fp16_op as *mut _,
-    );
-    LLVMAddSymbol(
-        b"banshee_fp8_to_fp32_op\0".as_ptr() as *const _,
-        Cpu::binary_fp8_to_fp32_op as *mut _,
-    );
+    }
 }
 
-// /// A representation of the system state.
-// #[repr(C)]
-// pub struct System<'a> {}
+/// An execution in deterministic mode, which advances a bounded number of
+/// instructions at a time, e.g. to single-step a binary.
+///
+/// The harts keep their state while the execution pauses between the steps,
+/// and `Engine::get_reg` and `Engine::set_reg` read and set their registers.
+pub struct Stepper {
+    engine: Arc<Engine>,
+    gate: Arc<RunGate>,
+    /// The thread which executes the binary, until it has been joined.
+    execution: Option<std::thread::JoinHandle<Result<u32>>>,
+}
 
-impl CpuState {
-    /// Create a new CpuState
-    pub fn new(num_dm: usize, hartid: usize, bootrom_addr: u32) -> Self {
-        let mut reg_init: [u32; 32] = [0; 32];
-        reg_init[10] = hartid as u32;
-        reg_init[11] = bootrom_addr;
-        Self {
-            regs: reg_init,
-            regs_cycle: [0; 32],
-            fregs: [0; 32],
-            fregs_cycle: [0; 32],
-            cas_value: 0,
-            pc: 0,
-            cycle: 0,
-            instret: 0,
-            ssrs: (0..num_dm).map(|_| Default::default()).collect(),
-            ssr_enable: 0,
-            fpmode: 0,
-            wfi: false,
-            dma: Default::default(),
-            irq: Default::default(),
+impl Stepper {
+    /// Start executing the binary of an engine built with `stepping`, and
+    /// pause before the first instruction.
+    pub fn new(engine: Arc<Engine>) -> Result<Self> {
+        let gate = match &engine.run_gate {
+            Some(gate) => gate.clone(),
+            None => bail!("Stepping needs an engine built with the deterministic mode"),
+        };
+        gate.start();
+        let execution = {
+            let (engine, gate) = (engine.clone(), gate.clone());
+            std::thread::spawn(move || {
+                let result = engine.execute();
+                gate.finish();
+                result
+            })
+        };
+        gate.run(0);
+        Ok(Self {
+            engine,
+            gate,
+            execution: Some(execution),
+        })
+    }
+
+    /// The engine which executes the binary.
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Execute `n` more instructions of all harts together, where an FREP
+    /// loop counts as one. Returns the exit code once the execution has
+    /// ended, or `None` while it pauses.
+    pub fn step(&mut self, n: u64) -> Result<Option<u32>> {
+        if !self.gate.run(n) {
+            return Ok(None);
+        }
+        self.join().map(Some)
+    }
+
+    /// Execute the rest of the binary, and return its exit code.
+    pub fn finish(&mut self) -> Result<u32> {
+        self.gate.open();
+        self.gate.run(0);
+        self.join()
+    }
+
+    fn join(&mut self) -> Result<u32> {
+        let execution = match self.execution.take() {
+            Some(execution) => execution,
+            None => bail!("The execution has already ended"),
+        };
+        match execution.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
         }
     }
 }
 
-impl<'a, 'b> Cpu<'a, 'b> {
-    /// Create a new CPU in a default state.
-    pub fn new(
-        engine: &'a Engine,
-        tcdm_ptr: &'b u32,
-        tcdm_ext_ptr: &'b Vec<&'b u32>,
-        hartid: usize,
-        num_cores: usize,
-        cluster_base_hartid: usize,
-        cluster_id: usize,
-        barrier: &'b AtomicUsize,
-        wakeup_state: &'b Mutex<WakeupState>,
-        clint: &'b Vec<AtomicU32>,
-        cl_clint: &'b AtomicUsize,
-    ) -> Self {
+impl Drop for Stepper {
+    fn drop(&mut self) {
+        // Wind down a paused execution.
+        if let Some(execution) = self.execution.take() {
+            self.engine.stop.store(true, Ordering::SeqCst);
+            self.gate.open();
+            let _ = execution.join();
+        }
+    }
+}
+
+/// The position of a hart in interpreter mode, between two steps.
+struct Interpreter {
+    pc: u64,
+    /// Whether `pc` is the target of an indirect jump from `addr`.
+    indirect: bool,
+    addr: u32,
+}
+
+impl Interpreter {
+    fn new(entry_point: u64) -> Self {
         Self {
-            engine,
-            state: CpuState::new(
-                engine.config.ssr.num_dm,
-                hartid,
-                engine.config.bootrom.start,
-            ),
-            tcdm_ptr,
-            tcdm_ext_ptr,
-            hartid,
-            num_cores,
-            cluster_base_hartid,
-            cluster_id,
-            barrier,
-            wakeup_state,
-            clint,
-            cl_clint,
+            pc: entry_point,
+            indirect: false,
+            addr: 0,
         }
     }
 
-    fn binary_load(&self, addr: u32, size: u8) -> u32 {
-        match addr {
-            x if x == self.engine.config.address.tcdm_start => {
-                self.engine.config.memory[self.cluster_id].tcdm.start
-            } // tcdm_start
-            x if x == self.engine.config.address.tcdm_end => {
-                self.engine.config.memory[self.cluster_id].tcdm.end
-            } // tcdm_end
-            x if x == self.engine.config.address.nr_cores => self.num_cores as u32, // nr_cores
-            x if x == self.engine.config.address.scratch_reg => {
-                self.engine.exit_code.load(Ordering::SeqCst)
-            } // scratch_reg
-            x if x == self.engine.config.address.barrier_reg => {
-                self.cluster_barrier();
-                0
-            } // barrier_reg
-            x if x == self.engine.config.address.cluster_base_hartid => {
-                self.cluster_base_hartid as u32
-            } // cluster_base_hartid
-            x if x == self.engine.config.address.cluster_num => self.engine.num_clusters as u32, // cluster_num
-            x if x == self.engine.config.address.cluster_id => self.cluster_id as u32, // cluster_id
-            // TCDM
-            x if x >= self.engine.config.memory[self.cluster_id].tcdm.start
-                && x < self.engine.config.memory[self.cluster_id].tcdm.end =>
-            {
-                let tcdm_addr = addr - self.engine.config.memory[self.cluster_id].tcdm.start;
-                let word_addr = tcdm_addr / 4;
-                let word_offs = tcdm_addr - 4 * word_addr;
-                let ptr: *const u32 = self.tcdm_ptr;
-                let word = unsafe { *ptr.offset(word_addr as isize) };
-                (word >> (8 * word_offs)) & ((((1 as u64) << (8 << size)) - 1) as u32)
+    /// Execute the step at the current PC. Returns false once the hart has
+    /// returned from the binary.
+    fn step<'b, 'c>(&mut self, cpu: &'c Cpu<'b, 'c>, steps: &StepFuncs<'b>) -> bool {
+        let pc = self.pc;
+        let &(step, unit) = match steps.get(&pc) {
+            Some(step) => step,
+            None if self.indirect => {
+                cpu.binary_abort_illegal_branch(self.addr, pc as u32);
+                return false;
             }
-            // TCDM External
-            x if self
-                .engine
-                .config
-                .memory
-                .iter()
-                .any(|m| x >= m.tcdm.start && x < m.tcdm.end) =>
-            {
-                let id = self
-                    .engine
-                    .config
-                    .memory
-                    .iter()
-                    .position(|m| addr >= m.tcdm.start && addr < m.tcdm.end)
-                    .unwrap();
-                let tcdm_addr = addr - self.engine.config.memory[id].tcdm.start;
-                let word_addr = tcdm_addr / 4;
-                let word_offs = tcdm_addr - 4 * word_addr;
-                let ptr: *const u32 = self.tcdm_ext_ptr[id];
-                let word = unsafe { *ptr.offset(word_addr as isize) };
-                (word >> (8 * word_offs)) & ((((1 as u64) << (8 << size)) - 1) as u32)
+            None => {
+                cpu.binary_abort_escape(pc as u32);
+                return false;
             }
-            // Peripherals
-            x if x >= self.engine.config.memory[self.cluster_id].periphs.start
-                && x < self.engine.config.memory[self.cluster_id].periphs.end =>
-            {
-                self.engine.peripherals.load(
-                    self.cluster_id,
-                    addr - self.engine.config.memory[self.cluster_id].periphs.start,
-                    size,
-                )
-            }
-            // Bootrom
-            x if x >= self.engine.config.bootrom.start && x < self.engine.config.bootrom.end => {
-                self.engine
-                    .bootrom
-                    .load(addr - self.engine.config.bootrom.start)
-            }
-            // access to the CLINT
-            x if x >= self.engine.config.address.clint
-                && x < self.engine.config.address.clint + 0x1000 =>
-            {
-                trace!(
-                    "CLINT Load off 0x{:x}",
-                    addr as u64 - self.engine.config.address.clint as u64
-                );
-                let word_addr = (addr - self.engine.config.address.clint) / 4;
-                self.clint[word_addr as usize].load(Ordering::SeqCst)
-            }
-            // The cl_clint is WO
-            x if x >= self.engine.config.address.cl_clint
-                && x < self.engine.config.address.cl_clint + 0x8 =>
-            {
-                0
-            }
-            // DRAM
-            _ => {
-                // Map all remaining addresses to the hash map but throw a warning if we read outside the memory map
-                if addr < self.engine.config.memory[self.cluster_id].dram.start
-                    || addr >= self.engine.config.memory[self.cluster_id].dram.end
-                {
-                    warn!(
-                        "Hart {} (pc=0x{:08x}) is reading outside the memory map at 0x{:08x}",
-                        self.hartid, self.state.pc, addr
-                    );
+        };
+        let mut target = 0;
+        let exit = step(cpu, unit.start as u32, 0, &mut target, &mut self.addr);
+        self.indirect = false;
+        self.pc = match exit as u64 {
+            tran::BLOCK_EXIT_FALLTHROUGH => unit.end,
+            tran::BLOCK_EXIT_BRANCH => match unit.target {
+                Some(target) => target,
+                None => {
+                    cpu.fault(pc as u32, "branches out of an interpreter step".to_string());
+                    return false;
                 }
-                // trace!("Load 0x{:x} ({}B)", addr, 8 << size);
-                self.engine
-                    .memory
-                    .lock()
-                    .unwrap()
-                    .get(&(addr as u64))
-                    .copied()
-                    .unwrap_or(0)
+            },
+            tran::BLOCK_EXIT_INDIRECT => {
+                self.indirect = true;
+                target as u64
             }
-        }
+            _ => return false,
+        };
+        true
     }
+}
 
-    fn binary_store(&self, addr: u32, value: u32, mask: u32, size: u8) {
-        match addr {
-            x if x == self.engine.config.address.tcdm_start => (), // tcdm_start
-            x if x == self.engine.config.address.tcdm_end => (),   // tcdm_end
-            x if x == self.engine.config.address.nr_cores => (),   // nr_cores
-            x if x == self.engine.config.address.scratch_reg => {
-                self.engine.exit_code.store(value, Ordering::SeqCst)
-            } // scratch_reg
-            x if x == self.engine.config.address.wakeup_reg => {
-                // wakeup_req
-                self.wake(value);
-            } // wakeup_reg
-            x if x == self.engine.config.address.barrier_reg => (), // barrier_reg
-            x if x == self.engine.config.address.cluster_base_hartid => (), // cluster_base_hartid
-            x if x == self.engine.config.address.cluster_num => (), // cluster_num
-            x if x == self.engine.config.address.cluster_id => (), // cluster_id
-            x if x == self.engine.config.address.uart => {
-                let mut buffer = self.engine.putchar_buffer.lock().unwrap();
-                let buffer = buffer.entry(self.hartid).or_default();
-                if value == '\n' as u32 {
-                    eprintln!(
-                        "{}{} hart-{:03} {} {}",
-                        style::Invert,
-                        color::Fg(color::White),
-                        self.hartid,
-                        style::Reset,
-                        String::from_utf8_lossy(buffer)
-                    );
-                    buffer.clear();
-                } else {
-                    buffer.push(value as u8);
-                }
-            }
-            // TCDM
-            // TODO: this is *not* thread-safe and *will* lead to undefined behavior on simultaneous access
-            // by 2 harts. However, changing `tcdm_ptr` to a locked structure would require pervasive redesign.
-            x if x >= self.engine.config.memory[self.cluster_id].tcdm.start
-                && x < self.engine.config.memory[self.cluster_id].tcdm.end =>
-            {
-                let tcdm_addr = addr - self.engine.config.memory[self.cluster_id].tcdm.start;
-                let word_addr = tcdm_addr / 4;
-                let word_offs = tcdm_addr - 4 * word_addr;
-                let ptr = self.tcdm_ptr as *const u32;
-                let ptr_mut = ptr as *mut u32;
-                let wmask = ((((1 as u64) << (8 << size)) - 1) as u32) << (8 * word_offs);
-                unsafe {
-                    let word_ptr = ptr_mut.offset(word_addr as isize);
-                    let word = *word_ptr;
-                    *word_ptr = (word & !wmask) | ((value << (8 * word_offs)) & wmask);
-                }
-            }
-            // TCDM External
-            x if self
-                .engine
-                .config
-                .memory
-                .iter()
-                .any(|m| x >= m.tcdm.start && x < m.tcdm.end) =>
-            {
-                let id = self
-                    .engine
-                    .config
-                    .memory
-                    .iter()
-                    .position(|m| addr >= m.tcdm.start && addr < m.tcdm.end)
-                    .unwrap();
-                let tcdm_addr = addr - self.engine.config.memory[id].tcdm.start;
-                let word_addr = tcdm_addr / 4;
-                let word_offs = tcdm_addr - 4 * word_addr;
-                let ptr = self.tcdm_ext_ptr[id] as *const u32;
-                let ptr_mut = ptr as *mut u32;
-                let wmask = ((((1 as u64) << (8 << size)) - 1) as u32) << (8 * word_offs);
-                unsafe {
-                    let word_ptr = ptr_mut.offset(word_addr as isize);
-                    let word = *word_ptr;
-                    *word_ptr = (word & !wmask) | ((value << (8 * word_offs)) & wmask);
-                }
-            }
-            // Peripherals
-            x if x >= self.engine.config.memory[self.cluster_id].periphs.start
-                && x < self.engine.config.memory[self.cluster_id].periphs.end =>
-            {
-                self.engine.peripherals.store(
-                    self.cluster_id,
-                    addr - self.engine.config.memory[self.cluster_id].periphs.start,
-                    value,
-                    mask,
-                    size,
-                )
-            }
-            // Bootrom
-            x if x >= self.engine.config.bootrom.start && x < self.engine.config.bootrom.end => {}
-            // access to the CLINT
-            x if x >= self.engine.config.address.clint
-                && x < self.engine.config.address.clint + 0x1000 =>
-            {
-                let word_addr = (addr - self.engine.config.address.clint) / 4;
-                trace!("CLINT store word off {:x} = 0x{:x}", word_addr, value,);
-                let old_entry = self.clint[word_addr as usize].load(Ordering::SeqCst);
-                let entry = (old_entry & !mask) | (value & mask);
-                self.clint[word_addr as usize].store(entry, Ordering::SeqCst);
-                // wake cores affected by this write
+/// The protocol by which a binary reports its exit code through the scratch
+/// register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitProtocol {
+    /// The Snitch convention: the binary writes `{exit_code[30:0], 1}`, where
+    /// bit 0 marks the exit code as valid. Execution ends once all harts wait
+    /// for an interrupt.
+    Snitch,
+    /// Any nonzero value written is the exit code and halts execution.
+    Nonzero,
+}
 
-                let hart_base = 32 * word_addr as i32 - self.engine.base_hartid as i32;
-                for i in 0..32 {
-                    if ((!old_entry & entry) & (1 << i)) != 0 {
-                        trace!(
-                            "  wakeup_wus.req[{}] from CLINT",
-                            (hart_base + i as i32) as usize
-                        );
-                        self.wake((hart_base + i) as u32);
-                    }
-                }
-            }
-            x if x == self.engine.config.address.cl_clint => {
-                // clint set register
-                let old_entry = self
-                    .cl_clint
-                    .fetch_or((value & mask) as usize, Ordering::SeqCst);
-                // wake cores affected by this write
-                let hart_base = (self.cluster_id * self.num_cores) as u32;
-                for i in 0..32 {
-                    if ((!old_entry & (value & mask) as usize) & (1 << i)) != 0 {
-                        trace!(
-                            "  wakeup_wus.req[{}] from cluster-local CLINT",
-                            (hart_base + i) as usize
-                        );
-                        self.wake((hart_base + i) as u32);
-                    }
-                }
-            }
-            x if x == self.engine.config.address.cl_clint + 0x8 => {
-                // clint clear register
-                self.cl_clint
-                    .fetch_and(!(value & mask) as usize, Ordering::SeqCst);
-            }
-            // DRAM
-            _ => {
-                // Map all remaining addresses to the hash map but throw a warning if we write outside the memory map
-                if addr < self.engine.config.memory[self.cluster_id].dram.start
-                    || addr >= self.engine.config.memory[self.cluster_id].dram.end
-                {
-                    warn!(
-                        "Hart {} (pc=0x{:08x}) is writing outside the memory map at 0x{:08x}",
-                        self.hartid, self.state.pc, addr
-                    );
-                }
-                trace!(
-                    "Store 0x{:x} = 0x{:x} if 0x{:x} ({}B)",
-                    addr,
-                    value,
-                    mask,
-                    8 << size
-                );
-                let mut data = self.engine.memory.lock().unwrap();
-                let data = data.entry(addr as u64).or_default();
-                *data &= !mask;
-                *data |= value & mask;
-            }
+impl Default for ExitProtocol {
+    fn default() -> Self {
+        ExitProtocol::Snitch
+    }
+}
+
+impl std::str::FromStr for ExitProtocol {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "snitch" => Ok(ExitProtocol::Snitch),
+            "nonzero" => Ok(ExitProtocol::Nonzero),
+            _ => bail!("Unknown exit protocol `{}`", s),
         }
     }
+}
 
-    fn binary_rmw(&self, addr: u32, value: u32, op: AtomicOp) -> u32 {
-        trace!("RMW 0x{:x} (op={})= 0x{:x} (32B)", addr, op as u8, value);
-        let mut data = self.engine.memory.lock().unwrap();
-        let mut prev = data.get(&(addr as u64)).copied().unwrap_or(0);
-        // Atomics
-        let result = match op {
-            AtomicOp::Amoadd => prev.wrapping_add(value),
-            AtomicOp::Amoxor => prev ^ value,
-            AtomicOp::Amoor => prev | value,
-            AtomicOp::Amoand => prev & value,
-            AtomicOp::Amomin => std::cmp::min(prev as i32, value as i32) as u32,
-            AtomicOp::Amomax => std::cmp::max(prev as i32, value as i32) as u32,
-            AtomicOp::Amominu => std::cmp::min(prev as u32, value as u32),
-            AtomicOp::Amomaxu => std::cmp::max(prev as u32, value as u32),
-            AtomicOp::Amoswap => value,
-            AtomicOp::ScW => {
-                if prev == self.state.cas_value {
-                    prev = 0; // Store-conditional success
-                    value
-                } else {
-                    return 1 as u32; // Store-conditional failed
-                }
-            }
-        };
-        data.insert(addr as u64, result);
-        prev as u32
+impl ExitProtocol {
+    /// Decode the exit code from the value of the scratch register, or `None`
+    /// if the binary has not reported one.
+    pub fn exit_code(self, value: u32) -> Option<u32> {
+        match self {
+            ExitProtocol::Snitch if value & 1 == 1 => Some(value >> 1),
+            ExitProtocol::Nonzero if value != 0 => Some(value),
+            _ => None,
+        }
     }
 
-    fn binary_csr_read(&self, csr: riscv::Csr, notrace: u32) -> u32 {
-        if notrace == 0 {
-            trace!("Read CSR {:?}", csr);
+    /// Decode the exit code of the binary from the scratch registers of all
+    /// clusters, or `None` if no cluster has reported one.
+    ///
+    /// A failure takes precedence: this is the first nonzero exit code
+    /// reported by a cluster, or zero if all of them report success.
+    pub fn aggregate_exit_code(self, values: impl IntoIterator<Item = u32>) -> Option<u32> {
+        values
+            .into_iter()
+            .filter_map(|value| self.exit_code(value))
+            .fold(None, |acc, code| match acc {
+                Some(acc) if acc != 0 => Some(acc),
+                _ => Some(code),
+            })
+    }
+
+    /// Whether writing `value` to the scratch register halts execution.
+    pub fn halts(self, value: u32) -> bool {
+        match self {
+            ExitProtocol::Snitch => false,
+            ExitProtocol::Nonzero => value != 0,
         }
-        match csr {
-            riscv::Csr::Ssr => self.state.ssr_enable,
-            riscv::Csr::Fpmode => self.state.fpmode as u32,
-            riscv::Csr::Mcycle => self.state.cycle as u32, // csr_mcycle
-            riscv::Csr::Mcycleh => (self.state.cycle >> 32) as u32, // csr_mcycleh
-            riscv::Csr::Minstret => self.state.instret as u32, // csr_minstret
-            riscv::Csr::Minstreth => (self.state.instret >> 32) as u32, // csr_minstreth
-            riscv::Csr::Mhartid => self.hartid as u32,     // mhartid
-            riscv::Csr::Mstatus => self.state.irq.mstatus, // CSR_MSTATUS
-            riscv::Csr::Mie => self.state.irq.mie,         // CSR_MIE
-            riscv::Csr::Mip => self.state.irq.mip,         // CSR_MIP
-            riscv::Csr::Mtvec => self.state.irq.mtvec,     // CSR_MTVEC
-            riscv::Csr::Mepc => self.state.irq.mepc,       // CSR_MEPC
-            riscv::Csr::Mcause => self.state.irq.mcause,   // CSR_MCAUSE
-            riscv::Csr::Misa => {
-                // RV32IMAFDX A - Atomic Instructions extension
-                (1 << 0) | (1 << 3) | (1 << 5) | (1 << 8) | (1 << 12) | (1 << 23) | (1 << 30)
-            }
-            _ => 0,
+    }
+}
+
+/// What a hart does after it executes an illegal instruction.
+///
+/// This applies if the instruction is not handled otherwise, i.e. by a custom
+/// instruction handler, or by the trap handler at `mtvec` if traps are
+/// enabled and one is installed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbortPolicy {
+    /// Stop execution with a `HartFault`.
+    Stop,
+    /// Skip the instruction and continue with the next one.
+    Continue,
+    /// Continue at the handler at this address, with `mepc`, `mcause`, and
+    /// `mtval` set up as for an illegal instruction exception.
+    Trap(u32),
+}
+
+impl Default for AbortPolicy {
+    fn default() -> Self {
+        AbortPolicy::Stop
+    }
+}
+
+impl std::str::FromStr for AbortPolicy {
+    type Err = anyhow::Error;
+
+    /// Parse `stop`, `continue`, or `trap=ADDR` with a hex address.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "stop" => Ok(AbortPolicy::Stop),
+            "continue" => Ok(AbortPolicy::Continue),
+            _ => match s.strip_prefix("trap=") {
+                Some(addr) => u32::from_str_radix(addr.trim_start_matches("0x"), 16)
+                    .map(AbortPolicy::Trap)
+                    .map_err(|_| anyhow!("Invalid trap handler address `{}`", addr)),
+                None => bail!("Unknown abort policy `{}`", s),
+            },
         }
     }
+}
 
-    fn binary_csr_write(&mut self, csr: riscv::Csr, value: u32, notrace: u32) {
-        if notrace == 0 {
-            trace!("Write CSR {:?} = 0x{:?}", csr, value);
+/// What to do if a section of a binary overlaps a device, such as the
+/// peripherals or the cluster registers, or straddles the boundary of the
+/// TCDM. Accesses to the overlapping part then go to the device or TCDM,
+/// not to the preloaded section, which usually means that the linker script
+/// does not match the configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    /// Fail the translation.
+    Error,
+    /// Log a warning, and load the binary anyway.
+    Warn,
+}
+
+impl Default for OverlapPolicy {
+    fn default() -> Self {
+        OverlapPolicy::Error
+    }
+}
+
+impl std::str::FromStr for OverlapPolicy {
+    type Err = anyhow::Error;
+
+    /// Parse `error` or `warn`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "error" => Ok(OverlapPolicy::Error),
+            "warn" => Ok(OverlapPolicy::Warn),
+            _ => bail!("Unknown overlap policy `{}`", s),
         }
-        match csr {
-            riscv::Csr::Ssr => self.state.ssr_enable = value,
-            riscv::Csr::Fpmode => self.state.fpmode = value,
-            riscv::Csr::Mstatus => self.state.irq.mstatus = value, // CSR_MSTATUS
-            riscv::Csr::Mie => self.state.irq.mie = value,         // CSR_MIE
-            riscv::Csr::Mip => self.state.irq.mip = value,         // CSR_MIP
-            riscv::Csr::Mtvec => self.state.irq.mtvec = value,     // CSR_MTVEC
-            riscv::Csr::Mepc => self.state.irq.mepc = value,       // CSR_MEPC
-            riscv::Csr::Mcause => self.state.irq.mcause = value,   // CSR_MCAUSE
-            _ => (),
+    }
+}
+
+/// Configures and creates an execution engine.
+///
+/// This is the entry point for using banshee as a library. The builder
+/// initializes LLVM and registers the runtime symbols once per process, and
+/// creates an LLVM context owned by the engine, which is disposed together
+/// with its modules when the engine is dropped.
+///
+/// ```ignore
+/// let mut engine = EngineBuilder::new()
+///     .num_clusters(2)
+///     .num_cores(9)
+///     .trace(true)
+///     .build()?;
+/// engine.translate_elf(&elf)?;
+/// let exit_code = engine.execute()?;
+/// ```
+pub struct EngineBuilder {
+    opt_llvm: bool,
+    opt_jit: bool,
+    interrupt: bool,
+    trace: bool,
+    trace_sample: Option<u64>,
+    latency: bool,
+    exact_fp: bool,
+    flush_to_zero: bool,
+    traps: bool,
+    trap_illegal_instructions: bool,
+    parallel_analysis: bool,
+    dedup_blocks: bool,
+    interpret: bool,
+    deterministic: Option<u64>,
+    stepping: bool,
+    shared_clock: bool,
+    stats: bool,
+    coverage: bool,
+    backtrace_on_abort: bool,
+    check_ssr_bounds: bool,
+    source_lines: bool,
+    access_log_size: usize,
+    progress: Option<std::time::Duration>,
+    deadlock_timeout: Option<std::time::Duration>,
+    livelock_threshold: Option<u64>,
+    max_instret: Option<u64>,
+    max_duration: Option<std::time::Duration>,
+    pin_cpus: Option<Vec<usize>>,
+    exit_protocol: ExitProtocol,
+    abort_policy: AbortPolicy,
+    overlap_policy: OverlapPolicy,
+    fs_root: Option<PathBuf>,
+    uart: Option<u32>,
+    num_cores: Option<usize>,
+    num_clusters: Option<usize>,
+    base_hartid: Option<usize>,
+    config: Option<Configuration>,
+    tcdm: Option<(u32, u32, u64)>,
+    cycle_model: Option<CycleModel>,
+    llvm_args: Vec<String>,
+}
+
+impl Default for EngineBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EngineBuilder {
+    /// Create a builder for an engine with a single cluster of a single core
+    /// and the default configuration.
+    pub fn new() -> Self {
+        Self {
+            opt_llvm: true,
+            opt_jit: true,
+            interrupt: true,
+            trace: false,
+            trace_sample: None,
+            latency: false,
+            exact_fp: false,
+            flush_to_zero: false,
+            traps: false,
+            trap_illegal_instructions: false,
+            parallel_analysis: false,
+            dedup_blocks: false,
+            interpret: false,
+            deterministic: None,
+            stepping: false,
+            shared_clock: false,
+            stats: false,
+            coverage: false,
+            backtrace_on_abort: false,
+            check_ssr_bounds: false,
+            source_lines: false,
+            access_log_size: 0,
+            progress: None,
+            deadlock_timeout: None,
+            livelock_threshold: None,
+            max_instret: None,
+            max_duration: None,
+            pin_cpus: None,
+            exit_protocol: Default::default(),
+            abort_policy: Default::default(),
+            overlap_policy: Default::default(),
+            fs_root: None,
+            uart: None,
+            num_cores: None,
+            num_clusters: None,
+            base_hartid: None,
+            config: None,
+            tcdm: None,
+            cycle_model: None,
+            llvm_args: vec![],
         }
     }
 
-    fn binary_abort_escape(&self, addr: u32) {
-        error!("CPU escaped binary at 0x{:x}", addr);
-        self.engine.had_error.store(true, Ordering::SeqCst);
+    /// Optimize the LLVM IR.
+    pub fn opt_llvm(mut self, enable: bool) -> Self {
+        self.opt_llvm = enable;
+        self
     }
 
-    fn binary_abort_illegal_inst(&self, addr: u32, inst_raw: u32) {
-        error!(
-            "Illegal instruction {} at 0x{:x}",
-            riscv::parse_u32(inst_raw),
-            addr
-        );
-        self.engine.had_error.store(true, Ordering::SeqCst);
+    /// Optimize during JIT compilation.
+    pub fn opt_jit(mut self, enable: bool) -> Self {
+        self.opt_jit = enable;
+        self
     }
 
-    fn binary_abort_illegal_branch(&self, addr: u32, target: u32) {
-        error!(
-            "Branch to unpredicted address 0x{:x} at 0x{:x}",
-            target, addr
-        );
-        self.engine.had_error.store(true, Ordering::SeqCst);
+    /// Enable interrupt support.
+    pub fn interrupt(mut self, enable: bool) -> Self {
+        self.interrupt = enable;
+        self
     }
 
-    unsafe fn binary_trace(&self, addr: u32, inst: u32, accesses: &[TraceAccess], data: &[u64]) {
-        // Assemble the arguments.
-        let args = accesses.iter().copied().zip(data.iter().copied());
-        let mut args = args.map(|(access, data)| match access {
-            TraceAccess::ReadMem => format!("RA:{:08x}", data as u32),
-            TraceAccess::WriteMem => format!("WA:{:08x}", data as u32),
-            TraceAccess::RMWMem => format!("AMO:{:08x}", data as u32),
-            TraceAccess::ReadReg(x) => format!("x{}:{:08x}", x, data as u32),
-            TraceAccess::WriteReg(x) => format!("x{}={:08x}", x, data as u32),
-            TraceAccess::ReadFReg(x) => format!("f{:02}:{:>16.6}", x, f64::from_bits(data)),
-            TraceAccess::WriteFReg(x) => format!("f{:02}={:>16.6}", x, f64::from_bits(data)),
-            TraceAccess::ReadF32Reg(x) => {
-                format!("f{:02}:{:>12.4}", x, f32::from_bits(data as u32))
-            }
-            TraceAccess::WriteF32Reg(x) => {
-                format!("f{:02}={:>12.4}", x, f32::from_bits(data as u32))
-            }
-            TraceAccess::Readf8Reg(x) => format!(
-                "f{:02}:[{:>5.3}]",
-                x,
-                f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
-                    (data & 0x0000_0000_0000_00ff) >> 0,
-                    flexfloat::FfOpCvt::Fcvt8f2f,
-                    false,
-                    false
-                ) as u32)
-            ),
-            TraceAccess::Writef8Reg(x) => format!(
-                "f{:02}=[{:>5.3}]",
-                x,
-                f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
-                    (data & 0x0000_0000_0000_00ff) >> 0,
-                    flexfloat::FfOpCvt::Fcvt8f2f,
-                    false,
-                    false
-                ) as u32),
+    /// Enable instruction tracing.
+    pub fn trace(mut self, enable: bool) -> Self {
+        self.trace = enable;
+        self
+    }
+
+    /// Only trace every `n`th instruction of each hart.
+    pub fn trace_sample(mut self, n: u64) -> Self {
+        self.trace_sample = Some(n);
+        self
+    }
+
+    /// Enable instruction latency.
+    pub fn latency(mut self, enable: bool) -> Self {
+        self.latency = enable;
+        self
+    }
+
+    /// Execute floating-point operations with exact rounding and exception
+    /// flags.
+    pub fn exact_fp(mut self, enable: bool) -> Self {
+        self.exact_fp = enable;
+        self
+    }
+
+    /// Flush subnormal floating-point values to zero.
+    pub fn flush_to_zero(mut self, enable: bool) -> Self {
+        self.flush_to_zero = enable;
+        self
+    }
+
+    /// Enter the trap handler on `ecall`, `ebreak`, and illegal instructions.
+    pub fn traps(mut self, enable: bool) -> Self {
+        self.traps = enable;
+        self
+    }
+
+    /// Enter the trap handler on illegal instructions.
+    pub fn trap_illegal_instructions(mut self, enable: bool) -> Self {
+        self.trap_illegal_instructions = enable;
+        self
+    }
+
+    /// Estimate the branch targets of the sections of a binary in parallel.
+    pub fn parallel_analysis(mut self, enable: bool) -> Self {
+        self.parallel_analysis = enable;
+        self
+    }
+
+    /// Emit the code of identical basic blocks only once.
+    pub fn dedup_blocks(mut self, enable: bool) -> Self {
+        self.dedup_blocks = enable;
+        self
+    }
+
+    /// Execute the binary one instruction at a time.
+    pub fn interpret(mut self, enable: bool) -> Self {
+        self.interpret = enable;
+        self
+    }
+
+    /// Execute all harts on one host thread, interleaved round-robin in turns
+    /// of `quantum` instructions.
+    pub fn deterministic(mut self, quantum: u64) -> Self {
+        self.deterministic = Some(quantum);
+        self
+    }
+
+    /// Let a `Stepper` execute the binary a bounded number of instructions at
+    /// a time. Needs the deterministic mode.
+    pub fn stepping(mut self, enable: bool) -> Self {
+        self.stepping = enable;
+        self
+    }
+
+    /// Return a clock shared by all harts from the `cycle` and `time` CSRs.
+    pub fn shared_clock(mut self, enable: bool) -> Self {
+        self.shared_clock = enable;
+        self
+    }
+
+    /// Gather execution statistics.
+    pub fn stats(mut self, enable: bool) -> Self {
+        self.stats = enable;
+        self
+    }
+
+    /// Record which instructions are translated and executed.
+    pub fn coverage(mut self, enable: bool) -> Self {
+        self.coverage = enable;
+        self
+    }
+
+    /// Log a backtrace of the hart when it aborts.
+    pub fn backtrace_on_abort(mut self, enable: bool) -> Self {
+        self.backtrace_on_abort = enable;
+        self
+    }
+
+    /// Abort on SSR accesses past the end of the stream, at unaligned
+    /// addresses, or outside the memory the stream started in.
+    pub fn check_ssr_bounds(mut self, enable: bool) -> Self {
+        self.check_ssr_bounds = enable;
+        self
+    }
+
+    /// Annotate the trace with source lines from the DWARF line info.
+    pub fn source_lines(mut self, enable: bool) -> Self {
+        self.source_lines = enable;
+        self
+    }
+
+    /// Keep the given number of most recent memory accesses, to be dumped if
+    /// an error occurs.
+    pub fn access_log_size(mut self, size: usize) -> Self {
+        self.access_log_size = size;
+        self
+    }
+
+    /// Report the execution progress at this interval, with the
+    /// `banshee::progress` log target.
+    pub fn progress(mut self, interval: std::time::Duration) -> Self {
+        self.progress = Some(interval);
+        self
+    }
+
+    /// Stop execution if no hart retires an instruction for this long.
+    pub fn deadlock_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.deadlock_timeout = Some(timeout);
+        self
+    }
+
+    /// Stop execution if a hart spins in a loop without side effects for this
+    /// many iterations.
+    pub fn livelock_threshold(mut self, iterations: u64) -> Self {
+        self.livelock_threshold = Some(iterations);
+        self
+    }
+
+    /// Stop execution once a hart retires this many instructions.
+    pub fn max_instret(mut self, max: u64) -> Self {
+        self.max_instret = Some(max);
+        self
+    }
+
+    /// Stop execution after running this long.
+    pub fn max_duration(mut self, max: std::time::Duration) -> Self {
+        self.max_duration = Some(max);
+        self
+    }
+
+    /// Pin the hart threads to these host CPUs, or round-robin to all host
+    /// CPUs if empty.
+    pub fn pin_cpus(mut self, cpus: Vec<usize>) -> Self {
+        self.pin_cpus = Some(cpus);
+        self
+    }
+
+    /// The protocol used to decode the exit code from the scratch register.
+    pub fn exit_protocol(mut self, protocol: ExitProtocol) -> Self {
+        self.exit_protocol = protocol;
+        self
+    }
+
+    /// What a hart does after it executes an illegal instruction.
+    pub fn abort_policy(mut self, policy: AbortPolicy) -> Self {
+        self.abort_policy = policy;
+        self
+    }
+
+    /// What to do if a section of a binary overlaps a device or part of the
+    /// TCDM.
+    pub fn overlap_policy(mut self, policy: OverlapPolicy) -> Self {
+        self.overlap_policy = policy;
+        self
+    }
+
+    /// Confine the files the binary opens to a host directory.
+    pub fn fs_root(mut self, path: impl Into<PathBuf>) -> Self {
+        self.fs_root = Some(path.into());
+        self
+    }
+
+    /// Enable the built-in UART at a base address.
+    pub fn uart(mut self, addr: u32) -> Self {
+        self.uart = Some(addr);
+        self
+    }
+
+    /// The number of cores per cluster. Overrides the configuration.
+    pub fn num_cores(mut self, num_cores: usize) -> Self {
+        self.num_cores = Some(num_cores);
+        self
+    }
+
+    /// The number of clusters. Overrides the configuration.
+    pub fn num_clusters(mut self, num_clusters: usize) -> Self {
+        self.num_clusters = Some(num_clusters);
+        self
+    }
+
+    /// The hart ID of the first core. Overrides the configuration.
+    pub fn base_hartid(mut self, base_hartid: usize) -> Self {
+        self.base_hartid = Some(base_hartid);
+        self
+    }
+
+    /// The system configuration, e.g. as parsed from a file. The default
+    /// configuration is used if this is not set.
+    pub fn configuration(mut self, config: Configuration) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Place the TCDM of every cluster at `start..end` with the given access
+    /// latency. Overrides the configuration.
+    pub fn tcdm(mut self, start: u32, end: u32, latency: u64) -> Self {
+        self.tcdm = Some((start, end, latency));
+        self
+    }
+
+    /// The cycles per instruction category. Overrides the configuration.
+    pub fn cycle_model(mut self, model: CycleModel) -> Self {
+        self.cycle_model = Some(model);
+        self
+    }
+
+    /// Command line options passed to LLVM, e.g. `-debug-pass=Structure`.
+    /// LLVM only accepts these once per process.
+    pub fn llvm_args(mut self, args: Vec<String>) -> Self {
+        self.llvm_args = args;
+        self
+    }
+
+    /// Create the engine, along with its LLVM context and a module for each
+    /// cluster.
+    pub fn build(self) -> Result<Engine> {
+        if self.progress == Some(std::time::Duration::ZERO) {
+            bail!("The progress interval must be positive");
+        }
+        if self.stepping && self.deterministic.is_none() {
+            bail!("Stepping needs the deterministic mode");
+        }
+        init_llvm();
+        if !self.llvm_args.is_empty() {
+            let args = std::iter::once("banshee")
+                .chain(self.llvm_args.iter().map(String::as_str))
+                .map(std::ffi::CString::new)
+                .collect::<Result<Vec<_>, _>>()?;
+            let argv: Vec<_> = args.iter().map(|a| a.as_ptr()).collect();
+            unsafe {
+                LLVMParseCommandLineOptions(
+                    argv.len() as i32,
+                    argv.as_ptr(),
+                    b"Banshee is magic!\0".as_ptr() as *const _,
+                );
+            }
+        }
+
+        let mut engine = Engine::new(unsafe { LLVMContextCreate() });
+        engine.owns_context = true;
+        engine.opt_llvm = self.opt_llvm;
+        engine.opt_jit = self.opt_jit;
+        engine.interrupt = self.interrupt;
+        engine.trace = self.trace;
+        engine.trace_sample = self.trace_sample;
+        engine.latency = self.latency;
+        engine.exact_fp = self.exact_fp;
+        engine.flush_to_zero = self.flush_to_zero;
+        engine.traps = self.traps;
+        engine.trap_illegal_instructions = self.trap_illegal_instructions;
+        engine.parallel_analysis = self.parallel_analysis;
+        engine.dedup_blocks = self.dedup_blocks;
+        engine.interpret = self.interpret;
+        engine.deterministic = self.deterministic;
+        if self.stepping {
+            engine.run_gate = Some(Default::default());
+        }
+        engine.shared_clock = self.shared_clock;
+        engine.stats = self.stats;
+        engine.coverage = self.coverage;
+        engine.backtrace_on_abort = self.backtrace_on_abort;
+        engine.check_ssr_bounds = self.check_ssr_bounds;
+        engine.source_lines = self.source_lines;
+        engine.access_log_size = self.access_log_size;
+        engine.progress = self.progress;
+        engine.deadlock_timeout = self.deadlock_timeout;
+        engine.livelock_threshold = self.livelock_threshold;
+        engine.max_instret = self.max_instret;
+        engine.max_duration = self.max_duration;
+        engine.pin_cpus = self.pin_cpus;
+        engine.exit_protocol = self.exit_protocol;
+        engine.abort_policy = self.abort_policy;
+        engine.overlap_policy = self.overlap_policy;
+        engine.fs_root = self.fs_root;
+        engine.uart = self.uart;
+
+        // Apply the explicit architecture on top of the configuration.
+        let (num_clusters, num_cores, base_hartid) =
+            (self.num_clusters, self.num_cores, self.base_hartid);
+        let mut config = self.config.unwrap_or_else(|| {
+            Configuration::new(
+                num_clusters.unwrap_or(1),
+                num_cores.unwrap_or(1),
+                base_hartid.unwrap_or(0),
+            )
+        });
+        // A configuration which leaves out the architecture has one cluster
+        // with one core, like the engine without a configuration.
+        if config.architecture.num_clusters == 0 {
+            config.architecture.num_clusters = 1;
+        }
+        if config.architecture.num_cores == 0 {
+            config.architecture.num_cores = 1;
+        }
+        if let Some(num_cores) = num_cores {
+            config.architecture.num_cores = num_cores;
+        }
+        if let Some(base_hartid) = base_hartid {
+            config.architecture.base_hartid = base_hartid;
+        }
+        if let Some(num_clusters) = num_clusters {
+            config.architecture.num_clusters = num_clusters;
+        }
+        if config.architecture.num_clusters == 0 || config.architecture.num_cores == 0 {
+            bail!("The engine needs at least one cluster with one core");
+        }
+        config
+            .memory
+            .resize_with(config.architecture.num_clusters, Default::default);
+        if let Some((start, end, latency)) = self.tcdm {
+            if start >= end {
+                bail!("Empty TCDM at 0x{:x}..0x{:x}", start, end);
+            }
+            for memory in &mut config.memory {
+                memory.tcdm = Memory {
+                    start,
+                    end,
+                    latency,
+                };
+            }
+        }
+        if let Some(model) = self.cycle_model {
+            config.cycle_model = model;
+        }
+        engine.num_cores = config.architecture.num_cores;
+        engine.num_clusters = config.architecture.num_clusters;
+        engine.base_hartid = config.architecture.base_hartid;
+        let topology = config.architecture.topology.take();
+        engine.config = config;
+        if let Some(topology) = topology {
+            engine.set_topology(topology)?;
+        }
+
+        engine.init_periphs();
+        engine.init_bootrom();
+        engine.create_modules();
+        Ok(engine)
+    }
+}
+
+/// Initialize the LLVM targets and passes, and register the runtime symbols.
+/// Only the first call has an effect.
+fn init_llvm() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| unsafe {
+        LLVMLinkInMCJIT();
+        LLVM_InitializeNativeTarget();
+        LLVM_InitializeNativeAsmPrinter();
+
+        // Initialize passes (inspired by llvm/tools/opt/opt.cpp:527).
+        let pass_reg = LLVMGetGlobalPassRegistry();
+        LLVMInitializeAggressiveInstCombiner(pass_reg);
+        LLVMInitializeAnalysis(pass_reg);
+        LLVMInitializeCodeGen(pass_reg);
+        LLVMInitializeCore(pass_reg);
+        LLVMInitializeIPA(pass_reg);
+        LLVMInitializeIPO(pass_reg);
+        LLVMInitializeInstCombine(pass_reg);
+        LLVMInitializeInstrumentation(pass_reg);
+        LLVMInitializeObjCARCOpts(pass_reg);
+        LLVMInitializeScalarOpts(pass_reg);
+        LLVMInitializeTarget(pass_reg);
+        LLVMInitializeTransformUtils(pass_reg);
+        LLVMInitializeVectorization(pass_reg);
+
+        add_llvm_symbols();
+    });
+}
+
+// SAFETY: This is safe because only `context` and `module`
+unsafe impl std::marker::Send for Engine {}
+unsafe impl std::marker::Sync for Engine {}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        unsafe {
+            // Disposing a JIT compiler also disposes the module it owns.
+            let jits = std::mem::take(&mut *self.jits.lock().unwrap());
+            for &(ee, _) in &jits {
+                LLVMDisposeExecutionEngine(ee);
+            }
+            for &module in &self.modules {
+                if !jits.iter().any(|&(_, m)| m == module) {
+                    LLVMDisposeModule(module);
+                }
+            }
+            if self.owns_context {
+                LLVMContextDispose(self.context);
+            }
+        }
+    }
+}
+
+impl Engine {
+    /// Create a new execution engine in an existing LLVM context, which
+    /// must outlive the engine.
+    ///
+    /// The caller is responsible for initializing LLVM and calling
+    /// `add_llvm_symbols`; prefer an [`EngineBuilder`], which does both.
+    pub fn new(context: LLVMContextRef) -> Self {
+        // Wrap everything up in an engine struct.
+        Self {
+            context,
+            owns_context: false,
+            jits: Default::default(),
+            modules: Default::default(),
+            exit_protocol: Default::default(),
+            abort_policy: Default::default(),
+            overlap_policy: Default::default(),
+            stop: Default::default(),
+            halted: Default::default(),
+            had_error: Default::default(),
+            unmapped_reads: Default::default(),
+            unmapped_writes: Default::default(),
+            opt_llvm: true,
+            opt_jit: true,
+            interrupt: true,
+            trace: false,
+            trace_sample: None,
+            latency: false,
+            exact_fp: false,
+            flush_to_zero: false,
+            traps: false,
+            trap_illegal_instructions: false,
+            parallel_analysis: false,
+            dedup_blocks: false,
+            interpret: false,
+            deterministic: None,
+            run_gate: None,
+            shared_clock: false,
+            clock: Default::default(),
+            stats: false,
+            coverage: false,
+            progress: None,
+            deadlock_timeout: None,
+            deadlock: Default::default(),
+            livelock_threshold: None,
+            max_instret: None,
+            max_duration: None,
+            limit_exceeded: Default::default(),
+            stack_guards: Default::default(),
+            stack_overflow: Default::default(),
+            fault: Default::default(),
+            pin_cpus: None,
+            base_hartid: 0,
+            num_cores: 1,
+            num_clusters: 1,
+            config: Default::default(),
+            memory: Default::default(),
+            mem_backend: None,
+            preloaded_memory: Default::default(),
+            putchar_buffer: Default::default(),
+            uart: None,
+            uart_buffer: Default::default(),
+            tohost: None,
+            inst_mnemonics: Default::default(),
+            peripherals: Peripherals::new(),
+            bootrom: Bootroms::new(),
+            images: Default::default(),
+            basic_blocks: Default::default(),
+            runtime_linked: false,
+            coverage_report: Default::default(),
+            unsupported: Default::default(),
+            initial_regs: Default::default(),
+            initial_regs_fn: None,
+            app_args: None,
+            hart_regs: Default::default(),
+            watchpoints: Default::default(),
+            watch_callback: None,
+            access_observer: None,
+            access_log_size: 0,
+            access_log: Default::default(),
+            unknown_csrs: Default::default(),
+            trace_renderer: None,
+            backtrace_on_abort: false,
+            check_ssr_bounds: false,
+            source_lines: false,
+            wfi_callback: None,
+            syscalls: Default::default(),
+            fs_root: None,
+            heap: None,
+            brk: Default::default(),
+            files: Default::default(),
+            custom_insts: Default::default(),
+            breakpoints: Default::default(),
+        }
+    }
+
+    /// Create a Module for each cluster
+    pub fn create_modules(&mut self) {
+        for i in 0..self.num_clusters {
+            let module = unsafe {
+                // Wrap the runtime IR up in an LLVM memory buffer.
+                let mut initial_ir = crate::runtime::JIT_INITIAL
+                    .replace("Cpu", format!("{}{}", "Cpu", i.to_string()).as_str())
+                    .as_bytes()
+                    .to_vec();
+                initial_ir.push(0); // somehow this is needed despite RequireNullTerminated=0 below
+                let initial_buf = LLVMCreateMemoryBufferWithMemoryRange(
+                    initial_ir.as_ptr() as *const _,
+                    initial_ir.len() - 1,
+                    b"jit.ll\0".as_ptr() as *const _,
+                    0,
+                );
+
+                // Parse the module.
+                let mut module = std::mem::MaybeUninit::uninit().assume_init();
+                let mut errmsg = std::mem::MaybeUninit::zeroed().assume_init();
+                if LLVMParseIRInContext(self.context, initial_buf, &mut module, &mut errmsg) != 0
+                    || !errmsg.is_null()
+                {
+                    error!(
+                        target: log_target::TRANSLATE,
+                        "Cannot parse `jit.ll` IR: {:?}",
+                        std::ffi::CStr::from_ptr(errmsg)
+                    );
+                }
+
+                // let module =
+                //     LLVMModuleCreateWithNameInContext(b"banshee\0".as_ptr() as *const _, context);
+                // LLVMSetDataLayout(module, b"i8:8-i16:16-i32:32-i64:64\0".as_ptr() as *const _);
+                module
+            };
+
+            self.modules.push(module);
+        }
+    }
+
+    /// Translate an ELF binary which is executed by all harts.
+    pub fn translate_elf(&mut self, elf: &elf::File) -> Result<()> {
+        self.translate_elf_for_harts(elf, None)
+    }
+
+    /// Translate an ELF binary which is executed by all harts of a range of
+    /// clusters, like `translate_elf_for_harts`.
+    pub fn translate_elf_for_clusters(
+        &mut self,
+        elf: &elf::File,
+        clusters: std::ops::Range<usize>,
+    ) -> Result<()> {
+        if clusters.is_empty() || clusters.end > self.num_clusters {
+            bail!(
+                "Cluster range {}..{} is empty or exceeds the {} clusters",
+                clusters.start,
+                clusters.end,
+                self.num_clusters
+            );
+        }
+        let topology = self.topology();
+        let harts = clusters
+            .flat_map(|j| {
+                let base_hartid = self.base_hartid + topology.cluster_offset(j);
+                base_hartid..base_hartid + topology.clusters[j].len()
+            })
+            .collect();
+        self.translate_elf_for_harts(elf, Some(harts))
+    }
+
+    /// Translate an ELF binary which is executed by a set of harts.
+    ///
+    /// This may be called multiple times to load several images into the
+    /// engine. Each image is translated into its own entry function. If `harts`
+    /// is `None`, the image is executed by all harts which are not assigned to
+    /// another image. An image whose sections overlap those of an image loaded
+    /// before is an error.
+    pub fn translate_elf_for_harts(
+        &mut self,
+        elf: &elf::File,
+        harts: Option<Vec<usize>>,
+    ) -> Result<()> {
+        crate::loader::check_target(elf)?;
+
+        // The JIT compiler has taken over the modules of an execution.
+        let jits = self.jits.lock().unwrap();
+        if self
+            .modules
+            .iter()
+            .any(|&module| jits.iter().any(|&(_, jitted)| jitted == module))
+        {
+            bail!("Cannot translate a binary into an engine which has executed");
+        }
+        std::mem::drop(jits);
+
+        // Make sure all sections fit into the 32 bit address space.
+        for section in &elf.sections {
+            let flags = elf::types::SHF_ALLOC.0 | elf::types::SHF_EXECINSTR.0;
+            if (section.shdr.flags.0 & flags) != 0
+                && section
+                    .shdr
+                    .addr
+                    .checked_add(section.shdr.size)
+                    .map_or(true, |end| end > 1 << 32)
+            {
+                bail!(
+                    "Section `{}` at 0x{:x} with size 0x{:x} exceeds the 32 bit address space",
+                    section.shdr.name,
+                    section.shdr.addr,
+                    section.shdr.size
+                );
+            }
+        }
+
+        // Make sure the image does not collide with the ones already loaded,
+        // whose sections it would overwrite in memory.
+        let ranges = |flags: u64| -> Vec<_> {
+            elf.sections
+                .iter()
+                .filter(|section| (section.shdr.flags.0 & flags) != 0)
+                .filter(|section| section.shdr.size > 0 && !is_tbss(section))
+                .map(|section| (section.shdr.addr, section.shdr.addr + section.shdr.size))
+                .collect()
+        };
+        let exec_ranges = ranges(elf::types::SHF_EXECINSTR.0);
+        let mut alloc_ranges = ranges(elf::types::SHF_ALLOC.0);
+        let tls = self.tls_image(elf)?;
+        if let Some(tls) = &tls {
+            let end = tls.addr(self.topology().num_harts());
+            alloc_ranges.push((tls.base as u64, end as u64));
+        }
+        for (id, image) in self.images.iter().enumerate() {
+            for &(start, end) in &alloc_ranges {
+                if let Some(&(other_start, other_end)) = image
+                    .alloc_ranges
+                    .iter()
+                    .find(|&&(other_start, other_end)| start < other_end && other_start < end)
+                {
+                    bail!(
+                        "Section range 0x{:x}..0x{:x} overlaps with 0x{:x}..0x{:x} of image {}",
+                        start,
+                        end,
+                        other_start,
+                        other_end,
+                        id
+                    );
+                }
+            }
+            match (&harts, &image.harts) {
+                (None, None) => bail!("Image {} is already executed by all harts", id),
+                (Some(harts), Some(other_harts)) => {
+                    if let Some(hartid) = harts.iter().find(|h| other_harts.contains(h)) {
+                        bail!("Hart {} is already assigned to image {}", hartid, id);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        // Make sure the sections do not alias the devices or the TCDM.
+        let overlaps = self.memory_map_overlaps(elf);
+        if !overlaps.is_empty() {
+            match self.overlap_policy {
+                OverlapPolicy::Error => bail!("{}", overlaps.join("; ")),
+                OverlapPolicy::Warn => {
+                    for overlap in &overlaps {
+                        warn!(target: log_target::TRANSLATE, "{}", overlap);
+                    }
+                }
+            }
+        }
+
+        // Watch the HTIF `tohost` word, through which riscv-tests report
+        // their result.
+        if let Some(sym) = elf
+            .sections
+            .iter()
+            .filter(|section| (section.shdr.link as usize) < elf.sections.len())
+            .flat_map(|section| elf.get_symbols(section).unwrap_or_default())
+            .find(|sym| sym.name == "tohost")
+        {
+            debug!(
+                target: log_target::TRANSLATE,
+                "Watching HTIF tohost at 0x{:x}",
+                sym.value
+            );
+            self.tohost = Some(sym.value as u32);
+        }
+
+        // Collect the code symbols, to resolve addresses in backtraces.
+        let mut symbols: Vec<_> = elf
+            .sections
+            .iter()
+            .filter(|section| (section.shdr.link as usize) < elf.sections.len())
+            .flat_map(|section| elf.get_symbols(section).unwrap_or_default())
+            .filter(|sym| {
+                (sym.symtype == elf::types::STT_FUNC || sym.symtype == elf::types::STT_NOTYPE)
+                    && !sym.name.is_empty()
+                    && !sym.name.starts_with(".L")
+                    && !sym.name.starts_with('$')
+                    && exec_ranges
+                        .iter()
+                        .any(|&(start, end)| (start..end).contains(&sym.value))
+            })
+            .collect();
+        // Prefer function symbols over labels at the same address.
+        symbols.sort_by_key(|sym| (sym.value, sym.symtype != elf::types::STT_FUNC));
+        symbols.dedup_by_key(|sym| sym.value);
+        let symbols = symbols
+            .into_iter()
+            .map(|sym| (sym.value, sym.name))
+            .collect();
+
+        // Decode the source lines, which are only informative.
+        let lines = if self.source_lines {
+            match dwarf::LineTable::parse(elf) {
+                Ok(Some(lines)) => Some(lines),
+                Ok(None) => {
+                    warn!(
+                        target: log_target::TRANSLATE,
+                        "Binary has no DWARF line info; trace lacks source lines"
+                    );
+                    None
+                }
+                Err(e) => {
+                    warn!(
+                        target: log_target::TRANSLATE,
+                        "Cannot decode DWARF line info: {:#}",
+                        e
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let entry = match self.images.len() {
+            0 => "execute_binary".to_string(),
+            n => format!("execute_binary_{}", n),
+        };
+        debug!(target: log_target::TRANSLATE, "Translating image into `{}`", entry);
+
+        let mut basic_blocks = vec![];
+        let mut steps = vec![];
+        for i in 0..self.num_clusters {
+            let mut tran = ElfTranslator::new(elf, self, i);
+            tran.entry = entry.clone();
+
+            // Dump the contents of the binary.
+            debug!(target: log_target::TRANSLATE, "Loading ELF binary");
+            for section in tran.sections() {
+                debug!(
+                    target: log_target::TRANSLATE,
+                    "Loading ELF section `{}` from 0x{:x} to 0x{:x}",
+                    section.shdr.name,
+                    section.shdr.addr,
+                    section.shdr.addr + section.shdr.size
+                );
+                for (addr, _, inst) in tran.instructions(section) {
+                    trace!(target: log_target::TRANSLATE, "  - 0x{:x}: {}", addr, inst);
+                }
+            }
+
+            // Estimate the branch target addresses.
+            tran.update_target_addrs();
+            if i == 0 {
+                basic_blocks = tran.basic_blocks();
+            }
+
+            // Translate the binary.
+            tran.translate()?;
+            if i == 0 {
+                steps = std::mem::take(&mut tran.step_units);
+            }
+
+            // Load and link the LLVM IR for the `jit.rs` runtime library, once.
+            if !self.runtime_linked {
+                unsafe {
+                    let mut runtime_ir = crate::runtime::JIT_GENERATED.to_vec();
+                    runtime_ir.push(0); // somehow this is needed despite RequireNullTerminated=0 below
+                    let runtime_buf = LLVMCreateMemoryBufferWithMemoryRange(
+                        runtime_ir.as_ptr() as *const _,
+                        runtime_ir.len() - 1,
+                        b"jit.rs\0".as_ptr() as *const _,
+                        0,
+                    );
+
+                    // Parse the module.
+                    let mut runtime = std::mem::MaybeUninit::uninit().assume_init();
+                    let mut errmsg = std::mem::MaybeUninit::zeroed().assume_init();
+                    if LLVMParseIRInContext(self.context, runtime_buf, &mut runtime, &mut errmsg)
+                        != 0
+                        || !errmsg.is_null()
+                    {
+                        error!(
+                            target: log_target::TRANSLATE,
+                            "Cannot parse `jit.rs` IR: {:?}",
+                            std::ffi::CStr::from_ptr(errmsg)
+                        );
+                    }
+
+                    // Link the runtime module into the translated binary module.
+                    LLVMLinkModules2(self.modules[i], runtime);
+                }
+            }
+
+            // Verify that nothing is broken at this point.
+            let failed = unsafe {
+                LLVMVerifyModule(
+                    self.modules[i],
+                    LLVMVerifierFailureAction::LLVMPrintMessageAction,
+                    std::ptr::null_mut(),
+                )
+            };
+            if failed != 0 {
+                let path = "/tmp/banshee_failed.ll";
+                unsafe {
+                    LLVMPrintModuleToFile(
+                        self.modules[i],
+                        format!("{}\0", path).as_ptr() as *const _,
+                        std::ptr::null_mut(),
+                    );
+                }
+                bail!(
+                    "LLVM module did not pass verification (failing IR written to {})",
+                    path
+                );
+            }
+        }
+        self.runtime_linked = true;
+        self.basic_blocks.extend(basic_blocks);
+        self.basic_blocks.sort_unstable();
+
+        // Optimize the translation.
+        if self.opt_llvm {
+            unsafe { self.optimize() };
+        }
+
+        // Copy the executable sections into memory.
+        self.preload_sections(elf);
+        self.place_app_args(elf)?;
+        if let Some(tls) = &tls {
+            for index in 0..self.topology().num_harts() {
+                self.write_image(tls.addr(index), &tls.template);
+            }
+        }
+        if self.heap.is_none() {
+            self.heap = self.default_heap(elf, tls.as_ref());
+        }
+        self.load_mem_backend();
+
+        self.images.push(ElfImage {
+            entry,
+            harts,
+            exec_ranges,
+            alloc_ranges,
+            tls,
+            symbols,
+            lines,
+            entry_point: elf.ehdr.entry,
+            steps,
+        });
+        Ok(())
+    }
+
+    /// Copy the allocatable sections of a binary into memory.
+    ///
+    /// Sections without file data, such as `.bss`, are explicitly zeroed, so
+    /// that they are mapped and overwrite any previous memory contents.
+    fn preload_sections(&self, elf: &elf::File) {
+        let sections: Vec<_> = elf
+            .sections
+            .iter()
+            .filter(|section| (section.shdr.flags.0 & elf::types::SHF_ALLOC.0) != 0)
+            .filter(|section| !is_tbss(section))
+            .collect();
+        let nobits = |section: &elf::Section| section.shdr.shtype == elf::types::SHT_NOBITS;
+        let mut mem = self.memory.lock().unwrap();
+        // Make room for all words up front, rather than rehashing repeatedly
+        // while inserting large sections.
+        mem.reserve(
+            sections
+                .iter()
+                .map(|section| match nobits(section) {
+                    true => (section.shdr.size as usize + 3) / 4,
+                    false => (section.data.len() + 3) / 4,
+                })
+                .sum(),
+        );
+        for section in sections {
+            if nobits(section) {
+                trace!(
+                    target: log_target::MEM,
+                    "Zeroing ELF section `{}` at 0x{:x} ({} bytes)",
+                    section.shdr.name,
+                    section.shdr.addr,
+                    section.shdr.size
+                );
+                preload_bytes(&mut mem, section.shdr.addr, section.shdr.size, |_| 0);
+                self.keep_preloaded(&mem, section.shdr.addr, section.shdr.size);
+                continue;
+            }
+            trace!(target: log_target::MEM, "Preloading ELF section `{}`", section.shdr.name);
+            preload_bytes(
+                &mut mem,
+                section.shdr.addr,
+                section.data.len() as u64,
+                |offset| section.data[offset as usize],
+            );
+            self.keep_preloaded(&mem, section.shdr.addr, section.data.len() as u64);
+        }
+    }
+
+    /// Keep the words of a region of a loaded binary, for `reset_memory` to
+    /// restore.
+    fn keep_preloaded(&self, mem: &HashMap<u64, u32>, addr: u64, len: u64) {
+        let mut preloaded = self.preloaded_memory.lock().unwrap();
+        for word in ((addr & !3)..addr + len).step_by(4) {
+            preloaded.insert(word, mem[&word]);
+        }
+    }
+
+    /// Load the cycles of each instruction category from a json/yaml file.
+    ///
+    /// Only takes effect with `latency` enabled, and must be called before
+    /// the binary is translated.
+    pub fn load_cycle_model(&mut self, path: &Path) -> Result<()> {
+        self.config.cycle_model = CycleModel::load(path)?;
+        Ok(())
+    }
+
+    /// Set the cores of each cluster and their features.
+    ///
+    /// This overrides `num_clusters` and `num_cores`, the latter becoming the
+    /// number of cores in the largest cluster. Must be called before the
+    /// modules are created.
+    pub fn set_topology(&mut self, topology: Topology) -> Result<()> {
+        if topology.clusters.is_empty() {
+            bail!("Topology has no clusters");
+        }
+        if let Some(id) = topology.clusters.iter().position(|c| c.is_empty()) {
+            bail!("Cluster {} has no cores", id);
+        }
+        self.num_clusters = topology.clusters.len();
+        self.num_cores = topology.max_cores();
+        self.config.architecture.num_clusters = self.num_clusters;
+        self.config.architecture.num_cores = self.num_cores;
+        self.config
+            .memory
+            .resize_with(self.num_clusters, Default::default);
+        self.config.architecture.topology = Some(topology);
+        self.init_periphs();
+        Ok(())
+    }
+
+    /// Get the cores of each cluster and their features.
+    pub fn topology(&self) -> Topology {
+        match &self.config.architecture.topology {
+            Some(topology) => topology.clone(),
+            None => Topology::uniform(self.num_clusters, self.num_cores),
+        }
+    }
+
+    /// Determine the cluster and the core index within it of a hart.
+    pub fn hart_location(&self, hartid: usize) -> Option<(usize, usize)> {
+        let index = hartid.checked_sub(self.base_hartid)?;
+        self.topology().locate(index)
+    }
+
+    /// Decode the instructions of a binary without translating or executing
+    /// it, e.g. for static analysis.
+    ///
+    /// Yields the address and the decoded instruction of each instruction in
+    /// the executable sections. Compressed instructions are expanded to their
+    /// 32 bit equivalent, and undecodable ones yield `Format::Illegal`.
+    pub fn disassemble(elf: &elf::File) -> impl Iterator<Item = (u64, riscv::Format)> + '_ {
+        tran::elf_instructions(elf).map(|(addr, _, inst)| (addr, inst))
+    }
+
+    /// Get the start and (exclusive) end address of each basic block in the
+    /// translated images, in ascending order.
+    pub fn basic_blocks(&self) -> Vec<(u32, u32)> {
+        self.basic_blocks.clone()
+    }
+
+    /// Determine the image executed by a hart.
+    pub fn image_for_hart(&self, hartid: usize) -> Option<usize> {
+        self.images
+            .iter()
+            .position(|image| match &image.harts {
+                Some(harts) => harts.contains(&hartid),
+                None => false,
+            })
+            .or_else(|| self.images.iter().position(|image| image.harts.is_none()))
+    }
+
+    /// Find the code symbol an address lies in, and the offset into it.
+    pub fn symbolize(&self, addr: u32) -> Option<(&str, u32)> {
+        let addr = addr as u64;
+        let image = self.images.iter().find(|image| {
+            image
+                .exec_ranges
+                .iter()
+                .any(|&(start, end)| (start..end).contains(&addr))
+        })?;
+        let index = image.symbols.partition_point(|&(value, _)| value <= addr);
+        let (value, name) = image.symbols.get(index.checked_sub(1)?)?;
+        Some((name, (addr - value) as u32))
+    }
+
+    /// Find the source file and line of the instruction at an address, if
+    /// `source_lines` is enabled and the binary has line info.
+    pub fn source_line(&self, addr: u32) -> Option<(&str, u32)> {
+        let addr = addr as u64;
+        self.images
+            .iter()
+            .find(|image| {
+                image
+                    .exec_ranges
+                    .iter()
+                    .any(|&(start, end)| (start..end).contains(&addr))
+            })?
+            .lines
+            .as_ref()?
+            .lookup(addr)
+    }
+
+    /// Describe an address by the symbol it lies in, if any.
+    fn describe_addr(&self, addr: u32) -> String {
+        match self.symbolize(addr) {
+            Some((name, 0)) => format!(" <{}>", name),
+            Some((name, offset)) => format!(" <{}+0x{:x}>", name, offset),
+            None => String::new(),
+        }
+    }
+
+    /// Read a word from memory.
+    ///
+    /// Words which have never been written read as zero.
+    pub fn read_word(&self, addr: u32) -> u32 {
+        self.with_memory(addr, |memory| memory.load_word(addr & !3))
+    }
+
+    /// Read a region of memory, for example the results of a kernel after
+    /// execution has finished.
+    pub fn read_mem(&self, addr: u32, len: usize) -> Vec<u8> {
+        (0..len as u32)
+            .map(|i| {
+                let addr = addr.wrapping_add(i);
+                (self.read_word(addr) >> (8 * (addr & 3))) as u8
+            })
+            .collect()
+    }
+
+    /// Write a region of memory, for example the inputs of a kernel before
+    /// execution.
+    pub fn write_mem(&self, addr: u32, data: &[u8]) {
+        for (i, &byte) in data.iter().enumerate() {
+            let addr = addr.wrapping_add(i as u32);
+            let shift = 8 * (addr & 3);
+            self.with_memory(addr, |memory| {
+                let word = memory.load_word(addr & !3);
+                memory.store_word(addr & !3, word & !(0xff << shift) | (byte as u32) << shift);
+            });
+        }
+    }
+
+    /// Write a region of the loaded binary into the global memory, from which
+    /// `reset_memory` restores it.
+    fn write_image(&self, addr: u32, data: &[u8]) {
+        let mut memory = self.memory.lock().unwrap();
+        for (i, &byte) in data.iter().enumerate() {
+            let addr = addr.wrapping_add(i as u32);
+            let shift = 8 * (addr & 3);
+            let word = memory.entry((addr & !3) as u64).or_default();
+            *word = *word & !(0xff << shift) | (byte as u32) << shift;
+        }
+        self.keep_preloaded(&memory, addr as u64, data.len() as u64);
+    }
+
+    /// Back the global memory outside the TCDMs with a custom backend rather
+    /// than the engine's own, sparse memory.
+    ///
+    /// The backend must be set before the binary is translated. The words of
+    /// the binary outside the TCDMs are then stored into the backend, and the
+    /// loads, stores, and atomics of the harts, as well as `read_mem` and
+    /// `write_mem`, go through it. The TCDMs stay in the engine's memory,
+    /// since the harts access them directly.
+    pub fn set_mem_backend(&mut self, backend: Box<dyn MemBackend>) {
+        self.mem_backend = Some(Mutex::new(backend));
+    }
+
+    /// Access the memory holding an address: the memory backend, unless the
+    /// address lies in a TCDM or no backend is set.
+    fn with_memory<R>(&self, addr: u32, f: impl FnOnce(&mut dyn MemBackend) -> R) -> R {
+        match &self.mem_backend {
+            Some(backend) if !self.is_tcdm(addr) => f(&mut **backend.lock().unwrap()),
+            _ => f(&mut *self.memory.lock().unwrap()),
+        }
+    }
+
+    /// Whether an address lies in the TCDM of any cluster.
+    fn is_tcdm(&self, addr: u32) -> bool {
+        self.config
+            .memory
+            .iter()
+            .any(|m| addr >= m.tcdm.start && addr < m.tcdm.end)
+    }
+
+    /// Store the loaded binaries outside the TCDMs into the memory backend,
+    /// if one is set.
+    fn load_mem_backend(&self) {
+        if let Some(backend) = &self.mem_backend {
+            let mut backend = backend.lock().unwrap();
+            for (&addr, &value) in self.preloaded_memory.lock().unwrap().iter() {
+                if !self.is_tcdm(addr as u32) {
+                    backend.store_word(addr as u32, value);
+                }
+            }
+        }
+    }
+
+    /// Restore the memory to the words the translated binaries loaded,
+    /// discarding the writes of previous executions and of `write_mem`.
+    ///
+    /// With a memory backend set by `set_mem_backend`, the words of the binary
+    /// outside the TCDMs are stored into the backend again. Other words the
+    /// harts or `write_mem` stored into the backend keep their contents, and
+    /// are up to the owner of the backend to clear.
+    ///
+    /// Together with `write_mem` and `read_mem`, this allows executing a
+    /// translated binary several times with different inputs.
+    pub fn reset_memory(&self) {
+        *self.memory.lock().unwrap() = self.preloaded_memory.lock().unwrap().clone();
+        self.load_mem_backend();
+    }
+
+    /// Return the engine to its state right after the last binary was
+    /// translated and the peripherals were added, to execute it again
+    /// without translating it anew.
+    ///
+    /// Restores the memory like `reset_memory`, the peripherals to their
+    /// initial state, and the harts to their initial registers, and discards
+    /// the error of the last execution. The compiled code is reused.
+    pub fn reset(&mut self) {
+        self.reset_memory();
+        self.peripherals.reset();
+        self.hart_regs.get_mut().unwrap().clear();
+        self.take_exec_error();
+        self.stop.store(false, Ordering::SeqCst);
+        self.halted.store(false, Ordering::SeqCst);
+        self.had_error.store(false, Ordering::SeqCst);
+        self.access_log.get_mut().unwrap().clear();
+    }
+
+    /// Set registers of each hart to initial values before execution, for
+    /// example `sp` to the top of the TCDM. Overrides the defaults of `a0`
+    /// (the hart ID) and `a1` (the bootrom address).
+    pub fn set_initial_regs(&mut self, regs: &[(u8, u32)]) -> Result<()> {
+        for &(reg, _) in regs {
+            if reg == 0 || reg >= 32 {
+                bail!("Cannot set initial value of register x{}", reg);
+            }
+        }
+        self.initial_regs = regs.to_vec();
+        Ok(())
+    }
+
+    /// Set the registers of each hart to values computed from its hart ID
+    /// before execution, for example to give each hart its own stack.
+    ///
+    /// The returned register file replaces the defaults entirely; registers
+    /// set through `set_initial_regs` are applied on top. `x0` stays zero.
+    pub fn set_initial_regs_fn(&mut self, f: Box<dyn Fn(usize) -> [u32; 32] + Send + Sync>) {
+        self.initial_regs_fn = Some(f);
+    }
+
+    /// The register file of a hart, after checking that the hart and the
+    /// register exist.
+    fn hart_regs(&self, hartid: usize, index: usize) -> Result<HartRegs> {
+        if self.hart_location(hartid).is_none() {
+            bail!("Hart {} does not exist", hartid);
+        }
+        if index >= 32 {
+            bail!("Register {} does not exist", index);
+        }
+        Ok(self
+            .hart_regs
+            .lock()
+            .unwrap()
+            .get(&hartid)
+            .copied()
+            .unwrap_or_default())
+    }
+
+    /// The value of integer register `x<index>` of a hart, as the last
+    /// execution or the step of a paused `Stepper` left it, or as set since
+    /// with `set_reg`. Zero before the first execution.
+    pub fn get_reg(&self, hartid: usize, index: usize) -> Result<u32> {
+        Ok(self.hart_regs(hartid, index)?.regs[index])
+    }
+
+    /// The raw value of floating-point register `f<index>` of a hart, like
+    /// `get_reg`.
+    pub fn get_freg(&self, hartid: usize, index: usize) -> Result<u64> {
+        Ok(self.hart_regs(hartid, index)?.fregs[index])
+    }
+
+    /// Set integer register `x<index>` of a hart for the next execution, on
+    /// top of the initial registers, or for the next step of a paused
+    /// `Stepper`. For example, to inject a fault or to replay the inputs of
+    /// another simulator.
+    pub fn set_reg(&self, hartid: usize, index: usize, value: u32) -> Result<()> {
+        self.hart_regs(hartid, index)?;
+        if index == 0 {
+            bail!("Cannot set register x0");
+        }
+        let mut hart_regs = self.hart_regs.lock().unwrap();
+        let regs = hart_regs.entry(hartid).or_default();
+        regs.regs[index] = value;
+        regs.set_regs |= 1 << index;
+        Ok(())
+    }
+
+    /// Set the raw value of floating-point register `f<index>` of a hart for
+    /// the next execution or step, like `set_reg`.
+    pub fn set_freg(&self, hartid: usize, index: usize, value: u64) -> Result<()> {
+        self.hart_regs(hartid, index)?;
+        let mut hart_regs = self.hart_regs.lock().unwrap();
+        let regs = hart_regs.entry(hartid).or_default();
+        regs.fregs[index] = value;
+        regs.set_fregs |= 1 << index;
+        Ok(())
+    }
+
+    /// Keep the registers of the harts of a paused execution, except the ones
+    /// set since, which the harts take over once they continue.
+    fn keep_hart_regs(&self, cpus: &[Cpu]) {
+        let mut hart_regs = self.hart_regs.lock().unwrap();
+        for cpu in cpus {
+            // The harts do not run while the execution is paused.
+            let (cpu_regs, cpu_fregs) = unsafe {
+                (
+                    std::ptr::read_volatile(&cpu.state.regs),
+                    std::ptr::read_volatile(&cpu.state.fregs),
+                )
+            };
+            let regs = hart_regs.entry(cpu.hartid).or_default();
+            for index in 0..32 {
+                if regs.set_regs & 1 << index == 0 {
+                    regs.regs[index] = cpu_regs[index];
+                }
+                if regs.set_fregs & 1 << index == 0 {
+                    regs.fregs[index] = cpu_fregs[index];
+                }
+            }
+        }
+    }
+
+    /// Pass command line arguments and environment variables to the binary.
+    ///
+    /// The arguments are placed at `addr` following the newlib crt0
+    /// convention, or at the end of the DRAM of the first cluster if `None`.
+    /// The first hart starts with `sp` pointing at them, `a0` set to `argc`,
+    /// `a1` to `argv`, and `a2` to `envp`; the other harts are unchanged. Like
+    /// the stack guards, the arguments must be set before the binary is
+    /// translated, which writes them into memory.
+    pub fn set_app_args(&mut self, args: AppArgs, addr: Option<u32>) -> Result<()> {
+        let size = args.size();
+        let mems = &self.config.memory[0];
+        let addr = match addr {
+            Some(addr) => addr,
+            None => {
+                mems.dram.end.checked_sub(size).ok_or_else(|| {
+                    anyhow!("Arguments of 0x{:x} bytes do not fit into the DRAM", size)
+                })? & !15
+            }
+        };
+        if addr % 16 != 0 {
+            bail!("Arguments at 0x{:x} are not aligned to 16 bytes", addr);
+        }
+        let end = addr as u64 + size as u64;
+        let region = [&mems.tcdm, &mems.dram]
+            .iter()
+            .find(|mem| mem.start <= addr && end <= mem.end as u64)
+            .copied()
+            .ok_or_else(|| {
+                anyhow!(
+                    "Arguments at 0x{:x}..0x{:x} are not within the TCDM or DRAM of the first cluster",
+                    addr,
+                    end
+                )
+            })?;
+        // The stack grows down from the arguments, within the same memory.
+        if addr == region.start {
+            bail!(
+                "Arguments at 0x{:x} leave no room for the stack below",
+                addr
+            );
+        }
+        self.app_args = Some((addr, args));
+        Ok(())
+    }
+
+    /// Write the command line arguments into memory, after checking that they
+    /// do not overlap the sections of the binary or a stack guard.
+    fn place_app_args(&self, elf: &elf::File) -> Result<()> {
+        let (addr, args) = match &self.app_args {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        let end = *addr as u64 + args.size() as u64;
+        for section in &elf.sections {
+            if (section.shdr.flags.0 & elf::types::SHF_ALLOC.0) == 0 || is_tbss(section) {
+                continue;
+            }
+            let start = section.shdr.addr;
+            if start < end && (*addr as u64) < start + section.shdr.size {
+                bail!(
+                    "Arguments at 0x{:x}..0x{:x} overlap section {}",
+                    addr,
+                    end,
+                    section.shdr.name
+                );
+            }
+        }
+        if let Some(hartid) = self.stack_guard_owner(*addr, args.size()) {
+            bail!(
+                "Arguments at 0x{:x}..0x{:x} overlap the stack guard of hart {}",
+                addr,
+                end,
+                hartid
+            );
+        }
+        self.write_image(*addr, &args.layout(*addr));
+        Ok(())
+    }
+
+    /// The address ranges of the devices, which sections must not overlap,
+    /// with their names.
+    fn device_ranges(&self) -> Vec<(String, u64, u64)> {
+        let address = &self.config.address;
+        let mut devices: Vec<_> = [
+            ("tcdm_start", address.tcdm_start),
+            ("tcdm_end", address.tcdm_end),
+            ("nr_cores", address.nr_cores),
+            ("scratch_reg", address.scratch_reg),
+            ("wakeup_reg", address.wakeup_reg),
+            ("barrier_reg", address.barrier_reg),
+            ("cluster_base_hartid", address.cluster_base_hartid),
+            ("cluster_num", address.cluster_num),
+            ("cluster_id", address.cluster_id),
+            ("uart", address.uart),
+        ]
+        .iter()
+        .map(|&(name, addr)| {
+            let name = format!("the `{}` register", name);
+            (name, addr as u64, addr as u64 + 4)
+        })
+        .collect();
+        devices.push((
+            "the CLINT".to_string(),
+            address.clint as u64,
+            address.clint as u64 + 0x1000,
+        ));
+        devices.push((
+            "the cluster CLINT".to_string(),
+            address.cl_clint as u64,
+            address.cl_clint as u64 + 0xc,
+        ));
+        if let Some(base) = self.uart {
+            devices.push((
+                "the UART".to_string(),
+                base as u64,
+                base as u64 + UART_SIZE as u64,
+            ));
+        }
+        let bootrom = &self.config.bootrom;
+        if !bootrom.callbacks.is_empty() {
+            devices.push((
+                "the bootrom".to_string(),
+                bootrom.start as u64,
+                bootrom.end as u64,
+            ));
+        }
+        for (i, memory) in self.config.memory.iter().enumerate() {
+            devices.push((
+                format!("the peripherals of cluster {}", i),
+                memory.periphs.start as u64,
+                memory.periphs.end as u64,
+            ));
+            for ext in &memory.ext_tcdm {
+                let tcdm = &self.config.memory[ext.cluster as usize].tcdm;
+                devices.push((
+                    format!(
+                        "the TCDM of cluster {} as seen from cluster {}",
+                        ext.cluster, i
+                    ),
+                    ext.start as u64,
+                    ext.start as u64 + (tcdm.end - tcdm.start) as u64,
+                ));
+            }
+        }
+        devices
+    }
+
+    /// Describe how the allocatable sections of a binary overlap a device,
+    /// or straddle the boundary of a TCDM. Sections entirely within a TCDM
+    /// are preloaded into it.
+    fn memory_map_overlaps(&self, elf: &elf::File) -> Vec<String> {
+        let devices = self.device_ranges();
+        let mut overlaps = vec![];
+        for section in &elf.sections {
+            if (section.shdr.flags.0 & elf::types::SHF_ALLOC.0) == 0
+                || section.shdr.size == 0
+                || is_tbss(section)
+            {
+                continue;
+            }
+            let (start, end) = (section.shdr.addr, section.shdr.addr + section.shdr.size);
+            let describe = |what: &str, other_start: u64, other_end: u64| {
+                format!(
+                    "Section `{}` at 0x{:x}..0x{:x} {} at 0x{:x}..0x{:x}",
+                    section.shdr.name, start, end, what, other_start, other_end
+                )
+            };
+            for (name, other_start, other_end) in &devices {
+                if start < *other_end && *other_start < end {
+                    let what = format!("overlaps {}", name);
+                    overlaps.push(describe(&what, *other_start, *other_end));
+                }
+            }
+            for (i, memory) in self.config.memory.iter().enumerate() {
+                let (tcdm_start, tcdm_end) = (memory.tcdm.start as u64, memory.tcdm.end as u64);
+                let overlapping = start < tcdm_end && tcdm_start < end;
+                let inside = tcdm_start <= start && end <= tcdm_end;
+                if overlapping && !inside {
+                    let what = format!("straddles the boundary of the TCDM of cluster {}", i);
+                    overlaps.push(describe(&what, tcdm_start, tcdm_end));
+                }
+            }
+        }
+        overlaps
+    }
+
+    /// The end addresses of the allocatable sections of a binary, except for
+    /// `.tbss`, which takes no space of its own.
+    fn sections_end<'a>(&self, elf: &'a elf::File) -> impl Iterator<Item = u64> + 'a {
+        elf.sections
+            .iter()
+            .filter(|section| (section.shdr.flags.0 & elf::types::SHF_ALLOC.0) != 0)
+            .filter(|section| !is_tbss(section))
+            .map(|section| section.shdr.addr + section.shdr.size)
+    }
+
+    /// Collect the thread-local storage template of a binary, and place the
+    /// copies of the harts after its sections in the DRAM of the first
+    /// cluster.
+    fn tls_image(&self, elf: &elf::File) -> Result<Option<TlsImage>> {
+        let sections: Vec<_> = elf
+            .sections
+            .iter()
+            .filter(|section| (section.shdr.flags.0 & elf::types::SHF_TLS.0) != 0)
+            .collect();
+        let start = match sections.iter().map(|section| section.shdr.addr).min() {
+            Some(start) => start,
+            None => return Ok(None),
+        };
+        let end = sections
+            .iter()
+            .map(|section| section.shdr.addr + section.shdr.size)
+            .max()
+            .unwrap();
+        let align = sections
+            .iter()
+            .map(|section| section.shdr.addralign)
+            .chain(std::iter::once(16))
+            .max()
+            .unwrap();
+        let mut template = vec![0; (end - start) as usize];
+        for section in sections.iter().filter(|section| !is_tbss(section)) {
+            let offset = (section.shdr.addr - start) as usize;
+            template[offset..offset + section.data.len()].copy_from_slice(&section.data);
+        }
+
+        let dram = &self.config.memory[0].dram;
+        let align_up = |x: u64| (x + align - 1) / align * align;
+        let in_dram = |addr: u64| (dram.start as u64..=dram.end as u64).contains(&addr);
+        let base = self
+            .sections_end(elf)
+            .filter(|&end| in_dram(end))
+            .max()
+            .map_or(dram.start as u64, align_up);
+        let stride = align_up(end - start);
+        let size = stride * self.topology().num_harts() as u64;
+        if base + size > dram.end as u64 {
+            bail!(
+                "Thread-local storage of 0x{:x} bytes for each hart does not fit into the DRAM after the sections at 0x{:x}",
+                stride,
+                base
+            );
+        }
+        debug!(
+            target: log_target::TRANSLATE,
+            "Placing thread-local storage at 0x{:x}..0x{:x}",
+            base,
+            base + size
+        );
+        Ok(Some(TlsImage {
+            base: base as u32,
+            stride: stride as u32,
+            template,
+        }))
+    }
+
+    /// Set the region which the `brk` syscall hands out to the binary's
+    /// `malloc`, replacing the default of the DRAM of the first cluster after
+    /// the sections of the first binary.
+    pub fn set_heap(&mut self, start: u32, end: u32) -> Result<()> {
+        let in_memory = self.config.memory.iter().any(|mems| {
+            [&mems.tcdm, &mems.dram]
+                .iter()
+                .any(|mem| mem.start <= start && end <= mem.end)
+        });
+        if start > end || !in_memory {
+            bail!(
+                "Heap at 0x{:x}..0x{:x} is not within the TCDM or DRAM of a cluster",
+                start,
+                end
+            );
+        }
+        self.heap = Some((start, end));
+        Ok(())
+    }
+
+    /// The default heap, from the end of the sections of a binary and of its
+    /// thread-local storage in the DRAM of the first cluster to its end, or
+    /// to the arguments placed there.
+    fn default_heap(&self, elf: &elf::File, tls: Option<&TlsImage>) -> Option<(u32, u32)> {
+        let dram = &self.config.memory[0].dram;
+        let in_dram = |addr: u64| (dram.start as u64..=dram.end as u64).contains(&addr);
+        let start = match tls {
+            Some(tls) => tls.addr(self.topology().num_harts()),
+            None => self
+                .sections_end(elf)
+                .filter(|&end| in_dram(end))
+                .max()
+                .map_or(dram.start, |end| ((end + 15) & !15) as u32),
+        };
+        let end = match self.app_args {
+            Some((addr, _)) if in_dram(addr as u64) && addr >= start => addr,
+            _ => dram.end,
+        };
+        Some((start, end)).filter(|(start, end)| start < end)
+    }
+
+    /// Handle the `ecall`s with a syscall number in `a7`, overriding the
+    /// built-in syscalls.
+    pub fn add_syscall(&mut self, num: u32, handler: Syscall) {
+        self.syscalls.insert(num, handler);
+    }
+
+    /// Execute the instructions whose encoding masked with `mask` equals
+    /// `value` with a handler, if banshee cannot translate them itself.
+    ///
+    /// This allows extending banshee with custom instructions, for example in
+    /// the `custom-2` space with `mask = 0x7f` and `value = 0x5b`. Handlers
+    /// must be added before the binary is translated; the first matching
+    /// handler is used.
+    pub fn add_custom_inst(&mut self, mask: u32, value: u32, handler: CustomInst) {
+        self.custom_insts.push((mask, value & mask, handler));
+    }
+
+    /// Call a callback whenever a hart reaches an address, before it executes
+    /// the instruction there.
+    ///
+    /// Breakpoints must be added before the binary is translated, since the
+    /// callbacks are only called from the instructions which had a breakpoint
+    /// then. Several callbacks may share an address; execution stops if any
+    /// of them asks to.
+    pub fn add_breakpoint(&mut self, addr: u32, callback: Breakpoint) {
+        self.breakpoints.entry(addr).or_default().push(callback);
+    }
+
+    /// Whether there is a breakpoint at an address.
+    pub(crate) fn has_breakpoint(&self, addr: u32) -> bool {
+        self.breakpoints.contains_key(&addr)
+    }
+
+    /// Record an instruction which could not be translated, once for all
+    /// clusters.
+    pub(crate) fn record_unsupported(&self, addr: u32, raw: u32, reason: String) {
+        let mut unsupported = self.unsupported.lock().unwrap();
+        if !unsupported.iter().any(|inst| inst.addr == addr) {
+            unsupported.push(UnsupportedInst { addr, raw, reason });
+        }
+    }
+
+    /// Translate a binary and check that banshee can execute all of its
+    /// instructions, without executing it.
+    ///
+    /// Translation succeeds even if the binary contains instructions which
+    /// banshee does not support, or which need a feature that no core of a
+    /// cluster has; a hart aborts once it executes one. This reports them
+    /// up front instead, as a `ValidationError` which lists them, e.g. to
+    /// check many binaries quickly in CI. Instructions handled by a custom
+    /// instruction handler are supported. The translation is optimized as
+    /// for an execution.
+    pub fn validate(&mut self, elf: &elf::File) -> Result<()> {
+        self.translate_elf(elf)?;
+        if !self.opt_llvm {
+            unsafe { self.optimize() };
+        }
+        self.check_supported()
+    }
+
+    /// Fail with a `ValidationError` if the binaries translated so far
+    /// contain unsupported instructions.
+    pub fn check_supported(&self) -> Result<()> {
+        let mut unsupported = self.unsupported.lock().unwrap().clone();
+        if unsupported.is_empty() {
+            return Ok(());
+        }
+        unsupported.sort_by_key(|inst| inst.addr);
+        Err(ValidationError { unsupported }.into())
+    }
+
+    /// The index of the handler of a custom instruction, if any.
+    pub(crate) fn custom_inst(&self, raw: u32) -> Option<usize> {
+        self.custom_insts
+            .iter()
+            .position(|&(mask, value, _)| raw & mask == value)
+    }
+
+    /// Watch the word at an address for accesses.
+    ///
+    /// Watchpoints must be added before the binary is translated, since they
+    /// disable the fast TCDM accesses in the translated code. Accesses by SSRs
+    /// and the DMA are not watched.
+    pub fn add_watchpoint(&mut self, addr: u32, kind: WatchKind) {
+        let addr = addr & !3;
+        match self.watchpoints.binary_search_by_key(&addr, |&(a, _)| a) {
+            Ok(i) if self.watchpoints[i].1 != kind => self.watchpoints[i].1 = WatchKind::Both,
+            Ok(_) => (),
+            Err(i) => self.watchpoints.insert(i, (addr, kind)),
+        }
+    }
+
+    /// Call an observer for every load, store, and instruction of the harts.
+    ///
+    /// The observer must be set before the binary is translated, since it
+    /// disables the fast TCDM accesses in the translated code. Without an
+    /// observer, the translated code does not call into the runtime for it.
+    pub fn set_access_observer(&mut self, observer: Box<dyn AccessObserver>) {
+        self.access_observer = Some(observer);
+    }
+
+    /// Whether an access observer is set.
+    pub fn has_access_observer(&self) -> bool {
+        self.access_observer.is_some()
+    }
+
+    /// Set the guard region of `len` bytes at `addr` below the stack of a
+    /// hart, replacing any previous one. A `len` of zero removes the guard.
+    ///
+    /// Any load or store into a guard region fails the execution with a
+    /// `StackOverflow` error. Combine this with `set_initial_regs_fn` to set
+    /// up the stack pointer of each hart right above its guard. Like
+    /// watchpoints, guards must be set before the binary is translated.
+    pub fn set_stack_guard(&mut self, hartid: usize, addr: u32, len: u32) -> Result<()> {
+        if self.hart_location(hartid).is_none() {
+            bail!("Hart {} does not exist", hartid);
+        }
+        let end = addr.checked_add(len).ok_or_else(|| {
+            anyhow!(
+                "Stack guard at 0x{:x} with size 0x{:x} exceeds the 32 bit address space",
+                addr,
+                len
+            )
+        })?;
+        self.stack_guards.retain(|&(h, _, _)| h != hartid);
+        if len != 0 {
+            self.stack_guards.push((hartid, addr, end));
+        }
+        Ok(())
+    }
+
+    /// Whether any stack guards are set.
+    pub fn has_stack_guards(&self) -> bool {
+        !self.stack_guards.is_empty()
+    }
+
+    /// The hart whose stack guard contains any of the `len` bytes at `addr`.
+    fn stack_guard_owner(&self, addr: u32, len: u32) -> Option<usize> {
+        let end = addr as u64 + len as u64;
+        self.stack_guards
+            .iter()
+            .find(|&&(_, start, guard_end)| (addr as u64) < guard_end as u64 && end > start as u64)
+            .map(|&(hartid, _, _)| hartid)
+    }
+
+    /// Whether any watchpoints are set.
+    pub fn has_watchpoints(&self) -> bool {
+        !self.watchpoints.is_empty()
+    }
+
+    /// Get the most recent loads and stores recorded in the access log,
+    /// oldest first.
+    pub fn recent_accesses(&self) -> Vec<LoggedAccess> {
+        self.access_log.lock().unwrap().iter().copied().collect()
+    }
+
+    /// Record an access in the access log, evicting the oldest one if full.
+    fn log_access(&self, access: LoggedAccess) {
+        let mut log = self.access_log.lock().unwrap();
+        if log.len() == self.access_log_size {
+            log.pop_front();
+        }
+        log.push_back(access);
+    }
+
+    /// Check whether an access triggers a watchpoint.
+    fn is_watched(&self, addr: u32, write: bool) -> bool {
+        if self.watchpoints.is_empty() {
+            return false;
+        }
+        match self
+            .watchpoints
+            .binary_search_by_key(&(addr & !3), |&(a, _)| a)
+        {
+            Ok(i) => self.watchpoints[i].1.matches(write),
+            Err(_) => false,
+        }
+    }
+
+    /// Record an instruction encountered during translation.
+    pub fn record_translated(&self, mnemonic: String, translated: bool) {
+        let mut coverage = self.coverage_report.lock().unwrap();
+        if translated {
+            coverage.translated.insert(mnemonic);
+        } else {
+            coverage.untranslated.insert(mnemonic);
+        }
+    }
+
+    /// The instructions encountered so far during translation and execution,
+    /// if `coverage` is enabled.
+    pub fn coverage_report(&self) -> Coverage {
+        self.coverage_report.lock().unwrap().clone()
+    }
+
+    /// Determine the instruction mix slot of a mnemonic.
+    pub fn inst_mix_slot(&self, mnemonic: &str) -> usize {
+        let mut mnemonics = self.inst_mnemonics.lock().unwrap();
+        match mnemonics.iter().position(|m| m == mnemonic) {
+            Some(slot) => slot,
+            None => {
+                mnemonics.push(mnemonic.to_string());
+                mnemonics.len() - 1
+            }
+        }
+    }
+
+    unsafe fn optimize(&self) {
+        debug!(target: log_target::TRANSLATE, "Optimizing IR");
+
+        // Create the pass managers.
+        for i in 0..self.num_clusters {
+            let func_passes = LLVMCreateFunctionPassManagerForModule(self.modules[i]);
+            let module_passes = LLVMCreatePassManager();
+
+            // Determine the target machine we are running on.
+            let tm_triple = LLVMGetDefaultTargetTriple();
+            let mut tm_target = std::ptr::null_mut();
+            let mut tm_target_msg = std::ptr::null_mut();
+            assert_eq!(
+                LLVMGetTargetFromTriple(tm_triple, &mut tm_target, &mut tm_target_msg),
+                0
+            );
+            let tm_cpu = LLVMGetHostCPUName();
+            let tm_features = LLVMGetHostCPUFeatures();
+            let tm = LLVMCreateTargetMachine(
+                tm_target,
+                tm_triple,
+                tm_cpu,
+                tm_features,
+                LLVMCodeGenOptLevel::LLVMCodeGenLevelAggressive,
+                LLVMRelocMode::LLVMRelocDefault,
+                LLVMCodeModel::LLVMCodeModelJITDefault,
+            );
+            LLVMDisposeMessage(tm_triple);
+            LLVMDisposeMessage(tm_cpu);
+            LLVMDisposeMessage(tm_features);
+
+            // Create a pass manager builder.
+            let builder = LLVMPassManagerBuilderCreate();
+            LLVMPassManagerBuilderSetOptLevel(builder, 3);
+            LLVMPassManagerBuilderSetSizeLevel(builder, 0);
+            LLVMPassManagerBuilderUseInlinerWithThreshold(builder, 275);
+
+            LLVMPassManagerBuilderPopulateFunctionPassManager(builder, func_passes);
+            LLVMAddAnalysisPasses(tm, module_passes);
+            LLVMPassManagerBuilderPopulateLTOPassManager(builder, module_passes, 0, 1);
+            LLVMPassManagerBuilderPopulateModulePassManager(builder, module_passes);
+
+            // Create and run the function pass manager.
+            LLVMInitializeFunctionPassManager(func_passes);
+            let mut func = LLVMGetFirstFunction(self.modules[i]);
+            while !func.is_null() {
+                let mut name_len = 0;
+                let name = LLVMGetValueName2(func, &mut name_len);
+                let name = std::slice::from_raw_parts(name as *const u8, name_len as usize);
+                let name = std::str::from_utf8_unchecked(name);
+                trace!(target: log_target::TRANSLATE, "  - Optimizing function {}", name);
+                LLVMRunFunctionPassManager(func_passes, func);
+                func = LLVMGetNextFunction(func);
+            }
+            LLVMFinalizeFunctionPassManager(func_passes);
+
+            // Create and run the module pass manager.
+            trace!(target: log_target::TRANSLATE, "  - Optimizing module");
+            LLVMRunPassManager(module_passes, self.modules[i]);
+
+            // Clean up.
+            LLVMPassManagerBuilderDispose(builder);
+            LLVMDisposePassManager(func_passes);
+            LLVMDisposePassManager(module_passes);
+            LLVMDisposeTargetMachine(tm);
+        }
+    }
+
+    /// Add the peripherals of each cluster, in their initial state.
+    /// `EngineBuilder::build` and `set_topology` already do so.
+    pub fn init_periphs(&mut self) {
+        debug!(target: log_target::EXEC, "Adding peripherals");
+        self.peripherals = Peripherals::new();
+        (0..self.num_clusters).for_each(|i| {
+            self.peripherals
+                .add_cluster(&self.config.memory[i].periphs.callbacks)
+        })
+    }
+
+    /// Add the bootrom, or remove its address range if it has no callbacks.
+    /// `EngineBuilder::build` already does so.
+    pub fn init_bootrom(&mut self) {
+        debug!(target: log_target::EXEC, "Adding bootrom");
+        self.bootrom = Bootroms::new();
+        if self.config.bootrom.callbacks.is_empty() {
+            self.config.bootrom.end = 0;
+        } else {
+            self.bootrom.add_bootrom(&self.config.bootrom.callbacks)
+        }
+    }
+
+    /// Execute the loaded memory and return the exit code of the binary,
+    /// aggregated over the clusters. `execute_with_stats` additionally
+    /// reports the exit status of each hart.
+    ///
+    /// A shorthand for `execute_status`, which fails on execution errors and
+    /// returns 117 if the binary reported no exit code.
+    pub fn execute(&self) -> Result<u32> {
+        self.execute_status()?.exit_code()
+    }
+
+    /// Execute the translated binaries and return how the execution ended.
+    ///
+    /// Errors are only returned if execution could not be started.
+    pub fn execute_status(&self) -> Result<ExitStatus> {
+        let stats = self.execute_with_stats()?;
+        Ok(self.exit_status(&stats))
+    }
+
+    /// How the execution which gathered `stats` ended. Takes the error which
+    /// stopped it, like `take_execution_error`.
+    pub fn exit_status(&self, stats: &ExecutionStats) -> ExitStatus {
+        if let Some(e) = self.take_exec_error() {
+            ExitStatus::Error(e)
+        } else if stats.error {
+            ExitStatus::Error(ExecError::Other)
+        } else if stats.exit_code_valid {
+            ExitStatus::Halted(stats.exit_code)
+        } else {
+            ExitStatus::Running
+        }
+    }
+
+    /// Take the specific error which stopped the last execution, such as a
+    /// `Deadlock`, `StackOverflow`, `LimitExceeded`, or `HartFault`, if any.
+    pub fn take_execution_error(&self) -> Option<anyhow::Error> {
+        self.take_exec_error().map(ExecError::into_error)
+    }
+
+    /// Take the specific error which stopped the last execution, if any.
+    fn take_exec_error(&self) -> Option<ExecError> {
+        if let Some(deadlock) = self.deadlock.lock().unwrap().take() {
+            Some(ExecError::Deadlock(deadlock))
+        } else if let Some(overflow) = self.stack_overflow.lock().unwrap().take() {
+            Some(ExecError::StackOverflow(overflow))
+        } else if let Some(exceeded) = self.limit_exceeded.lock().unwrap().take() {
+            Some(ExecError::LimitExceeded(exceeded))
+        } else {
+            self.fault.lock().unwrap().take().map(ExecError::Fault)
+        }
+    }
+
+    /// Translate and execute a binary at every combination of `opt_llvm` and
+    /// `opt_jit`, and return the exit codes.
+    ///
+    /// The exit code at index `2 * opt_llvm + opt_jit` is the one obtained
+    /// with those optimizations enabled. Differing exit codes indicate a
+    /// translation which is only correct at some optimization levels.
+    ///
+    /// Since the JIT compiler consumes the translated modules, the binary is
+    /// translated anew for each combination, which makes this about four
+    /// times as costly as translating and executing it once. Any image
+    /// translated before is discarded, and the memory is restored to its
+    /// current contents before each execution. The peripherals keep their
+    /// state across the executions.
+    pub fn run_all_opt_levels(&mut self, elf: &elf::File) -> Result<[u32; 4]> {
+        let (opt_llvm, opt_jit) = (self.opt_llvm, self.opt_jit);
+        let memory = self.memory.lock().unwrap().clone();
+        let mut exit_codes = [0; 4];
+        let mut result = Ok(());
+        for (i, exit_code) in exit_codes.iter_mut().enumerate() {
+            self.opt_llvm = i & 2 != 0;
+            self.opt_jit = i & 1 != 0;
+            debug!(
+                target: log_target::EXEC,
+                "Running with opt_llvm={} opt_jit={}",
+                self.opt_llvm,
+                self.opt_jit
+            );
+
+            // Start over from a clean translation and memory.
+            self.modules.clear();
+            self.images.clear();
+            self.basic_blocks.clear();
+            self.runtime_linked = false;
+            *self.memory.lock().unwrap() = memory.clone();
+            self.preloaded_memory.lock().unwrap().clear();
+
+            self.create_modules();
+            match self.translate_elf(elf).and_then(|_| self.execute()) {
+                Ok(code) => *exit_code = code,
+                Err(e) => {
+                    result = Err(e.context(format!(
+                        "Failed with opt_llvm={} opt_jit={}",
+                        self.opt_llvm, self.opt_jit
+                    )));
+                    break;
+                }
+            }
+        }
+        self.opt_llvm = opt_llvm;
+        self.opt_jit = opt_jit;
+        result.map(|_| exit_codes)
+    }
+
+    /// Print the most recent accesses of the access log.
+    fn dump_access_log(&self) {
+        let log = self.access_log.lock().unwrap();
+        if log.is_empty() {
+            return;
+        }
+        error!(
+            target: log_target::MEM,
+            "Last {} memory accesses before the error:",
+            log.len()
+        );
+        for access in log.iter() {
+            error!(
+                target: log_target::MEM,
+                "Hart {} (pc=0x{:08x}) {} 0x{:08x} ({}B): 0x{:08x}",
+                access.hartid,
+                access.pc,
+                if access.write { "writes" } else { "reads" },
+                access.addr,
+                1 << access.size,
+                access.value
+            );
+        }
+    }
+
+    /// Execute the loaded memory and gather statistics.
+    ///
+    /// Errors during execution are reported through the `error` flag of the
+    /// returned statistics.
+    pub fn execute_with_stats(&self) -> Result<ExecutionStats> {
+        unsafe { self.execute_inner() }
+    }
+
+    unsafe fn execute_inner<'b>(&'b self) -> Result<ExecutionStats> {
+        if self.images.is_empty() {
+            bail!("No ELF image has been translated");
+        }
+
+        // Start from a clean slate if the binary was executed before.
+        self.stop.store(false, Ordering::SeqCst);
+        self.halted.store(false, Ordering::SeqCst);
+        self.had_error.store(false, Ordering::SeqCst);
+        self.unmapped_reads.store(0, Ordering::SeqCst);
+        self.unmapped_writes.store(0, Ordering::SeqCst);
+        self.clock.store(0, Ordering::SeqCst);
+        self.brk
+            .store(self.heap.map_or(0, |(start, _)| start), Ordering::SeqCst);
+        self.files.lock().unwrap().clear();
+
+        // Create a JIT compiler for the module (and consumes it), unless a
+        // previous execution already did.
+        debug!(target: log_target::EXEC, "Creating JIT compiler for translated code");
+        let execs: Vec<Vec<_>> = (0..self.num_clusters)
+            .map(|i| {
+                let mut jits = self.jits.lock().unwrap();
+                let ee = match jits.iter().find(|&&(_, module)| module == self.modules[i]) {
+                    Some(&(ee, _)) => ee,
+                    None => {
+                        let mut ee = std::mem::MaybeUninit::uninit().assume_init();
+                        let mut errmsg = std::mem::MaybeUninit::zeroed().assume_init();
+                        let optlevel = if self.opt_jit { 3 } else { 0 };
+                        LLVMCreateJITCompilerForModule(
+                            &mut ee,
+                            self.modules[i],
+                            optlevel,
+                            &mut errmsg,
+                        );
+                        if !errmsg.is_null() {
+                            panic!(
+                                "Cannot create JIT compiler: {:?}",
+                                std::ffi::CStr::from_ptr(errmsg)
+                            )
+                        }
+                        jits.push((ee, self.modules[i]));
+                        ee
+                    }
+                };
+
+                // Lookup the functions which execute the images, and the ones
+                // of their instructions in interpreter mode.
+                self.images
+                    .iter()
+                    .map(|image| {
+                        let exec: for<'c> extern "C" fn(&'c Cpu<'b, 'c>) =
+                            std::mem::transmute(LLVMGetFunctionAddress(
+                                ee,
+                                format!("{}\0", image.entry).as_ptr() as *const _,
+                            ));
+                        debug!(
+                            target: log_target::EXEC,
+                            "Translated `{}` is at {:?}",
+                            image.entry,
+                            exec as *const i8
+                        );
+                        let steps: StepFuncs<'b> = image
+                            .steps
+                            .iter()
+                            .map(|&unit| {
+                                let name = format!("{}_step_0x{:x}\0", image.entry, unit.start);
+                                let step: StepFunc<'b> = std::mem::transmute(
+                                    LLVMGetFunctionAddress(ee, name.as_ptr() as *const _),
+                                );
+                                (unit.start, (step, unit))
+                            })
+                            .collect();
+                        (exec, steps)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // Allocate some TCDM memories.
+        let tcdms: Vec<_> = (0..self.num_clusters)
+            .map(|i| {
+                let mut tcdm = vec![
+                    0u32;
+                    ((self.config.memory[i].tcdm.end - self.config.memory[i].tcdm.start) / 4)
+                        as usize
+                ];
+
+                for (&addr, &value) in self.memory.lock().unwrap().iter() {
+                    if (addr as u32) >= self.config.memory[i].tcdm.start
+                        && (addr as u32) < self.config.memory[i].tcdm.end
+                    {
+                        tcdm[((addr - (self.config.memory[i].tcdm.start as u64)) / 4) as usize] =
+                            value;
+                    }
+                }
+
+                tcdm
+            })
+            .collect();
+
+        // External TCDM
+        let ext_tcdms: Vec<_> = (0..self.num_clusters).map(|i| &tcdms[i][0]).collect();
+
+        // Allocate some barriers.
+        let barriers: Vec<_> = (0..self.num_clusters)
+            .map(|_| AtomicUsize::new(0))
+            .collect();
+
+        // Allocate state struct to keep track of sleeping cores.
+        let topology = self.topology();
+        let num_harts = topology.num_harts();
+        let wakeup_state = Mutex::new(WakeupState {
+            num: 0,
+            req: vec![0; num_harts],
+            wfi: vec![false; num_harts],
+            threads: vec![None; num_harts],
+        });
+
+        // Allocate CLINT registers
+        let n_virt_cores = num_harts + self.base_hartid;
+        let clint: Vec<_> = (0..(n_virt_cores + 32 - 1) / 32)
+            .map(|_| AtomicU32::new(0))
+            .collect();
+
+        // Allocate cluster-local CLINT registers
+        let cl_clints: Vec<_> = (0..self.num_clusters)
+            .map(|_| AtomicUsize::new(0))
+            .collect();
+
+        // Allocate the exit code registers of the clusters.
+        let exit_regs: Vec<_> = (0..self.num_clusters).map(|_| AtomicU32::new(0)).collect();
+
+        // Create the CPUs.
+        let cpus: Vec<_> = (0..num_harts)
+            .map(|index| {
+                let (j, i) = topology.locate(index).unwrap();
+                let base_hartid = self.base_hartid + topology.cluster_offset(j);
+                Cpu::new(
+                    self,
+                    base_hartid + i,
+                    topology.clusters[j].len(),
+                    base_hartid,
+                    j,
+                    topology.clusters[j][i].bits(),
+                    SharedState {
+                        tcdm_ptr: &tcdms[j][0],
+                        tcdm_ext_ptr: &ext_tcdms,
+                        barrier: &barriers[j],
+                        wakeup_state: &wakeup_state,
+                        clint: &clint,
+                        cl_clint: &cl_clints[j],
+                        exit_reg: &exit_regs[j],
+                    },
+                )
+            })
+            .collect();
+        trace!(
+            target: log_target::EXEC,
+            "Initial state hart {}: {:#?}",
+            cpus[0].hartid,
+            cpus[0].state
+        );
+
+        // Execute the binary.
+        info!(target: log_target::EXEC, "Launching binary on {} harts", cpus.len());
+        let t0 = std::time::Instant::now();
+        let running = AtomicUsize::new(0);
+        // When each hart started and ended, relative to `t0`.
+        let spans: Vec<Mutex<Option<(f64, f64)>>> =
+            cpus.iter().map(|_| Default::default()).collect();
+        let num_host_cpus = util::num_host_cpus();
+        crossbeam_utils::thread::scope(|s| {
+            let mut scheduled = vec![];
+            for (index, cpu) in cpus.iter().enumerate() {
+                let (image, (exec, steps)) = match self.image_for_hart(cpu.hartid) {
+                    Some(image) => (&self.images[image], &execs[cpu.cluster_id][image]),
+                    None => {
+                        debug!(
+                            target: log_target::EXEC,
+                            "Hart {} has no image to execute",
+                            cpu.hartid
+                        );
+                        continue;
+                    }
+                };
+                running.fetch_add(1, Ordering::SeqCst);
+                let running = &running;
+                let span = &spans[index];
+                if self.deterministic.is_some() {
+                    scheduled.push((index, *exec));
+                    continue;
+                }
+                let pin_cpu = self
+                    .pin_cpus
+                    .as_ref()
+                    .map(|cpus| util::pinned_cpu(cpus, index, num_host_cpus));
+                s.spawn(move |_| {
+                    let start = t0.elapsed().as_secs_f64();
+                    util::keep_subnormals();
+                    if let Some(pin_cpu) = pin_cpu {
+                        if !util::pin_thread(pin_cpu) {
+                            debug!(
+                                target: log_target::EXEC,
+                                "Cannot pin hart {} to CPU {}",
+                                cpu.hartid,
+                                pin_cpu
+                            );
+                        }
+                    }
+                    if self.interpret {
+                        self.interpret_hart(cpu, steps, image.entry_point);
+                    } else {
+                        exec(cpu);
+                    }
+                    debug!(target: log_target::EXEC, "Hart {} finished", cpu.hartid);
+                    *span.lock().unwrap() = Some((start, t0.elapsed().as_secs_f64()));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+            if let Some(quantum) = self.deterministic {
+                let (cpus, spans, running) = (&cpus, &spans, &running);
+                s.spawn(move |_| {
+                    util::keep_subnormals();
+                    self.run_deterministic(cpus, &scheduled, spans, quantum, running, t0);
+                });
+            }
+            if let Some(interval) = self.progress {
+                let (cpus, running) = (&cpus, &running);
+                s.spawn(move |_| self.report_progress(cpus, running, interval, t0));
+            }
+            if let Some(timeout) = self.deadlock_timeout {
+                let (cpus, running) = (&cpus, &running);
+                s.spawn(move |_| self.watch_deadlock(cpus, running, timeout));
+            }
+            if let Some(max) = self.max_duration {
+                let (cpus, running) = (&cpus, &running);
+                s.spawn(move |_| self.watch_duration(cpus, running, max, t0));
+            }
+        })
+        .unwrap();
+        let t1 = std::time::Instant::now();
+        let duration = (t1.duration_since(t0)).as_secs_f64();
+        debug!(target: log_target::EXEC, "All {} harts finished", cpus.len());
+
+        // Write the final TCDM contents back such that they can be inspected.
+        {
+            let mut memory = self.memory.lock().unwrap();
+            for (i, tcdm) in tcdms.iter().enumerate() {
+                let start = self.config.memory[i].tcdm.start as u64;
+                for (offset, &value) in tcdm.iter().enumerate() {
+                    let addr = start + offset as u64 * 4;
+                    if value != 0 || memory.contains_key(&addr) {
+                        memory.insert(addr, value);
+                    }
+                }
+            }
+        }
+
+        // Print the lines the harts did not finish.
+        for (hartid, line) in self.uart_buffer.lock().unwrap().drain() {
+            if !line.is_empty() {
+                println!("[hart {}] {}", hartid, String::from_utf8_lossy(&line));
+            }
+        }
+
+        // Keep the final registers, which also consumes the registers set
+        // for this execution.
+        {
+            let mut hart_regs = self.hart_regs.lock().unwrap();
+            for cpu in &cpus {
+                hart_regs.insert(
+                    cpu.hartid,
+                    HartRegs {
+                        regs: cpu.state.regs,
+                        fregs: cpu.state.fregs,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+
+        // Count the number of instructions that we have retired.
+        let instret: u64 = cpus.iter().map(|cpu| cpu.state.instret).sum();
+
+        // Print some final statistics.
+        trace!(
+            target: log_target::EXEC,
+            "Final state hart {}: {:#?}",
+            cpus[0].hartid,
+            cpus[0].state
+        );
+        for cpu in &cpus {
+            trace!(target: log_target::EXEC, "Final state hart {}: {:#?}", cpu.hartid, cpu.state);
+        }
+        for cpu in &cpus {
+            let ssr_vec = &cpu.state.ssrs;
+            let mut ssr_id = 0;
+            for ssr in ssr_vec {
+                for idx in 0..ssr.index.len() {
+                    if ssr.index[idx] != ssr.bound[idx] && ssr.index[idx] != 0 {
+                        warn!(
+                            target: log_target::EXEC,
+                            "Final state hart {}: SSR {} NOT fully consumed.",
+                            cpu.hartid, ssr_id
+                        );
+                        warn!(
+                            target: log_target::EXEC,
+                            "index != bound : {:?} != {:?}",
+                            ssr.index[idx], ssr.bound[idx]
+                        );
+                    }
+                }
+                if ((ssr.dims != 0) && !(ssr.done)) {
+                    trace!(
+                        target: log_target::EXEC,
+                        "Final state hart {}: SSR {} NOT fully consumed.",
+                        cpu.hartid,
+                        ssr_id
+                    );
+                    warn!(
+                        target: log_target::EXEC,
+                        "Final state hart {}: SSR {} NOT fully consumed.",
+                        cpu.hartid, ssr_id
+                    );
+                } else if ((ssr.dims != 0) && ssr.done) {
+                    trace!(
+                        target: log_target::EXEC,
+                        "Final state hart {}: SSR {} fully consumed.",
+                        cpu.hartid,
+                        ssr_id
+                    );
+                } else {
+                    trace!(
+                        target: log_target::EXEC,
+                        "Final state hart {}: SSR {} not used.",
+                        cpu.hartid,
+                        ssr_id
+                    );
+                }
+                ssr_id += 1;
+            }
+        }
+        // Fetch the return values and decode them according to the exit
+        // protocol.
+        let ret = self
+            .exit_protocol
+            .aggregate_exit_code(exit_regs.iter().map(|reg| reg.load(Ordering::SeqCst)));
+        if let Some(ret) = ret {
+            info!(target: log_target::EXEC, "Exit code is 0x{:x}", ret);
+        } else {
+            warn!(target: log_target::EXEC, "Exit code register was empty.")
+        }
+        info!(
+            target: log_target::EXEC,
+            "Retired {} ({}) in {}, {}",
+            instret,
+            (instret as isize).si_unit("inst"),
+            duration.si_unit("s"),
+            (instret as f64 / duration).si_unit("inst/s")
+        );
+        if self.had_error.load(Ordering::SeqCst) {
+            self.dump_access_log();
+        }
+        let unmapped_reads = self.unmapped_reads.load(Ordering::SeqCst);
+        let unmapped_writes = self.unmapped_writes.load(Ordering::SeqCst);
+        if unmapped_reads != 0 || unmapped_writes != 0 {
+            warn!(
+                target: log_target::MEM,
+                "{} reads and {} writes outside the memory map",
+                unmapped_reads,
+                unmapped_writes
+            );
+        }
+
+        // Assemble the statistics.
+        let mut stats = ExecutionStats {
+            version: STATS_VERSION,
+            // Call the police if no return value was specified
+            exit_code: ret.unwrap_or(MISSING_EXIT_CODE),
+            exit_code_valid: ret.is_some(),
+            error: self.had_error.load(Ordering::SeqCst),
+            duration,
+            instret,
+            inst_per_second: instret as f64 / duration,
+            harts: Default::default(),
+            slowest_hart: None,
+            inst_mix: Default::default(),
+            mem_accesses: Default::default(),
+            dma: DmaStats::default(),
+            unmapped_reads,
+            unmapped_writes,
+        };
+        let mnemonics = self.inst_mnemonics.lock().unwrap();
+        for (cpu, span) in cpus.iter().zip(&spans) {
+            let span = *span.lock().unwrap();
+            let exit_value = *cpu.exit_value.lock().unwrap();
+            stats.harts.push(HartStats {
+                hartid: cpu.hartid,
+                instret: cpu.state.instret,
+                cycles: if self.latency {
+                    Some(cpu.state.cycle)
+                } else {
+                    None
+                },
+                start: span.map(|(start, _)| start),
+                end: span.map(|(_, end)| end),
+                finished: cpu.state.finished,
+                parked: !cpu.state.finished && cpu.state.wfi,
+                pc: cpu.state.pc,
+                error: cpu.had_error.load(Ordering::SeqCst),
+                exit_value,
+                exit_code: exit_value.and_then(|value| self.exit_protocol.exit_code(value)),
+            });
+            if !cpu.state.finished {
+                debug!(
+                    target: log_target::EXEC,
+                    "Hart {} was still running when execution stopped",
+                    cpu.hartid
+                );
+            }
+            for (slot, &count) in cpu.state.stats.inst_mix.iter().enumerate() {
+                if count != 0 {
+                    *stats.inst_mix.entry(mnemonics[slot].clone()).or_default() += count;
+                }
+            }
+            for &region in &MemRegion::ALL {
+                let count = cpu.state.stats.mem_accesses[region as usize].load(Ordering::SeqCst);
+                if count != 0 {
+                    *stats
+                        .mem_accesses
+                        .entry(region.name().to_string())
+                        .or_default() += count;
+                }
+            }
+            stats.dma.transfers += cpu.state.stats.dma_transfers;
+            stats.dma.bytes += cpu.state.stats.dma_bytes;
+        }
+        stats.slowest_hart = stats::slowest_hart(&stats.harts);
+        if let Some(hart) = stats
+            .slowest_hart
+            .and_then(|id| stats.harts.iter().find(|h| h.hartid == id))
+        {
+            debug!(
+                target: log_target::EXEC,
+                "Hart {} is the slowest with {} instructions",
+                hart.hartid,
+                hart.instret
+            );
+        }
+        if self.coverage {
+            self.coverage_report
+                .lock()
+                .unwrap()
+                .record_executed(&stats.inst_mix);
+        }
+        Ok(stats)
+    }
+
+    /// Execute a hart in interpreter mode, by calling the function of one
+    /// instruction after another until one of them returns from the binary.
+    fn interpret_hart<'b, 'c>(
+        &self,
+        cpu: &'c Cpu<'b, 'c>,
+        steps: &StepFuncs<'b>,
+        entry_point: u64,
+    ) {
+        let mut interp = Interpreter::new(entry_point);
+        while !self.stop.load(Ordering::Relaxed) {
+            if !interp.step(cpu, steps) {
+                break;
+            }
+        }
+    }
+
+    /// Execute harts on the current thread, interleaved round-robin in turns
+    /// of `quantum` instructions in the order of their hart IDs, such that
+    /// every run interleaves them the same way.
+    ///
+    /// The translated code of a hart yields at the start of the first basic
+    /// block after it has used up its turn, or before the next instruction if
+    /// a run gate grants exact numbers of instructions, and continues there in
+    /// its next turn. A hart which sleeps in `wfi` or waits in the cluster
+    /// barrier ends its turn, and passes its next turns until it may continue.
+    fn run_deterministic<'b, 'c>(
+        &self,
+        cpus: &'c [Cpu<'b, 'c>],
+        harts: &[(usize, for<'d> extern "C" fn(&'d Cpu<'b, 'd>))],
+        spans: &[Mutex<Option<(f64, f64)>>],
+        quantum: u64,
+        running: &AtomicUsize,
+        t0: std::time::Instant,
+    ) {
+        let start = t0.elapsed().as_secs_f64();
+        let mut pending: Vec<_> = harts.iter().map(Some).collect();
+        while !self.stop.load(Ordering::SeqCst) && pending.iter().any(Option::is_some) {
+            let mut progress = false;
+            for slot in &mut pending {
+                let &(index, exec) = match slot {
+                    Some(hart) => *hart,
+                    None => continue,
+                };
+                // The hart only runs during its turns, and the scheduler talks
+                // to it through atomics. Its state is read like the progress
+                // report does.
+                let cpu = &cpus[index];
+                let instret = || unsafe { std::ptr::read_volatile(&cpu.state.instret) };
+                // A waiting hart only checks whether it may continue.
+                let waiting = cpu.is_waiting();
+                let gate = self.run_gate.as_ref().filter(|_| !waiting);
+                let budget = match gate {
+                    Some(gate) => {
+                        // Let the registers be inspected and set while the
+                        // execution pauses.
+                        if gate.exhausted() {
+                            self.keep_hart_regs(cpus);
+                        }
+                        gate.take(quantum.max(1))
+                    }
+                    None if waiting => 0,
+                    None => quantum.max(1),
+                };
+                let before = instret();
+                cpu.yield_at.store(before + budget, Ordering::SeqCst);
+                exec(cpu);
+                let used = instret() - before;
+                if let Some(gate) = gate {
+                    gate.give_back(budget.saturating_sub(used));
+                }
+                let done = !cpu.yielded.swap(false, Ordering::SeqCst);
+                progress |= done || used > 0 || (waiting && !cpu.is_waiting());
+                if done {
+                    debug!(target: log_target::EXEC, "Hart {} finished", cpu.hartid);
+                    *spans[index].lock().unwrap() = Some((start, t0.elapsed().as_secs_f64()));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    *slot = None;
+                }
+            }
+            // Only an external wake-up can end a round in which all harts
+            // wait.
+            if !progress {
+                std::thread::yield_now();
+            }
+        }
+        // Execution has been stopped while some harts were still running.
+        for _ in pending.iter().flatten() {
+            running.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Periodically report the progress of the harts until all of them have
+    /// finished executing.
+    fn report_progress(
+        &self,
+        cpus: &[Cpu],
+        running: &AtomicUsize,
+        interval: std::time::Duration,
+        t0: std::time::Instant,
+    ) {
+        let poll = std::cmp::min(interval, std::time::Duration::from_millis(50));
+        let mut next = t0 + interval;
+        let (mut last_time, mut last_instret) = (t0, 0);
+        while running.load(Ordering::SeqCst) > 0 {
+            let now = std::time::Instant::now();
+            if now < next {
+                std::thread::sleep(std::cmp::min(next - now, poll));
+                continue;
+            }
+            next += interval;
+            // The harts keep running while we read their state. The values
+            // are only displayed, so torn reads are acceptable.
+            let read = |cpu: &Cpu| unsafe {
+                (
+                    std::ptr::read_volatile(&cpu.state.instret),
+                    std::ptr::read_volatile(&cpu.state.pc),
+                )
+            };
+            let states: Vec<_> = cpus.iter().map(|cpu| (cpu.hartid, read(cpu))).collect();
+            let instret: u64 = states.iter().map(|(_, (instret, _))| instret).sum();
+            let rate = instret.saturating_sub(last_instret) as f64
+                / now.duration_since(last_time).as_secs_f64();
+            info!(
+                target: log_target::PROGRESS,
+                "[{:.1}s] Retired {} ({}), pcs: {}",
+                now.duration_since(t0).as_secs_f64(),
+                instret,
+                rate.si_unit("inst/s"),
+                states
+                    .iter()
+                    .map(|(hartid, (_, pc))| format!("{}:{:08x}", hartid, pc))
+                    .join(" ")
+            );
+            last_time = now;
+            last_instret = instret;
+        }
+    }
+
+    /// Stop execution if no hart retires an instruction for `timeout`, until
+    /// all harts have finished executing.
+    fn watch_deadlock(&self, cpus: &[Cpu], running: &AtomicUsize, timeout: std::time::Duration) {
+        let poll = std::cmp::min(timeout, std::time::Duration::from_millis(50));
+        // As for the progress report, torn reads of the state are acceptable:
+        // a changing count is all that matters.
+        let instret = || -> u64 {
+            cpus.iter()
+                .map(|cpu| unsafe { std::ptr::read_volatile(&cpu.state.instret) })
+                .sum()
+        };
+        let (mut last_instret, mut last_change) = (instret(), std::time::Instant::now());
+        while running.load(Ordering::SeqCst) > 0 && !self.stop.load(Ordering::SeqCst) {
+            std::thread::sleep(poll);
+            let now = std::time::Instant::now();
+            let current = instret();
+            if current != last_instret {
+                last_instret = current;
+                last_change = now;
+            } else if now.duration_since(last_change) >= timeout {
+                let harts = cpus
+                    .iter()
+                    .filter(|cpu| unsafe { !std::ptr::read_volatile(&cpu.state.finished) })
+                    .map(|cpu| {
+                        (cpu.hartid, unsafe {
+                            std::ptr::read_volatile(&cpu.state.pc)
+                        })
+                    })
+                    .collect();
+                let deadlock = Deadlock { timeout, harts };
+                error!(target: log_target::EXEC, "{}", deadlock);
+                *self.deadlock.lock().unwrap() = Some(deadlock);
+                self.had_error.store(true, Ordering::SeqCst);
+                self.stop.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Stop execution with a `LimitExceeded` error if it runs longer than
+    /// `max`.
+    fn watch_duration(
+        &self,
+        cpus: &[Cpu],
+        running: &AtomicUsize,
+        max: std::time::Duration,
+        t0: std::time::Instant,
+    ) {
+        let poll = std::cmp::min(max, std::time::Duration::from_millis(50));
+        while running.load(Ordering::SeqCst) > 0 && !self.stop.load(Ordering::SeqCst) {
+            let elapsed = t0.elapsed();
+            if elapsed < max {
+                std::thread::sleep(std::cmp::min(poll, max - elapsed));
+                continue;
+            }
+            let harts = cpus
+                .iter()
+                .filter(|cpu| unsafe { !std::ptr::read_volatile(&cpu.state.finished) })
+                .map(|cpu| {
+                    (cpu.hartid, unsafe {
+                        std::ptr::read_volatile(&cpu.state.pc)
+                    })
+                })
+                .collect();
+            self.exceed_limit(LimitExceeded {
+                limit: Limit::Duration(max),
+                harts,
+            });
+        }
+    }
+
+    /// Record an exceeded execution limit and stop execution.
+    fn exceed_limit(&self, exceeded: LimitExceeded) {
+        error!(target: log_target::EXEC, "{}", exceeded);
+        self.limit_exceeded.lock().unwrap().get_or_insert(exceeded);
+        self.had_error.store(true, Ordering::SeqCst);
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Register the runtime functions with LLVM, such that the translated code
+/// can call them by name.
+pub fn add_llvm_symbols() {
+    unsafe {
+        LLVMAddSymbol(
+            b"banshee_load\0".as_ptr() as *const _,
+            Cpu::binary_load as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_store\0".as_ptr() as *const _,
+            Cpu::binary_store as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_rmw\0".as_ptr() as *const _,
+            Cpu::binary_rmw as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_csr_read\0".as_ptr() as *const _,
+            Cpu::binary_csr_read as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_csr_write\0".as_ptr() as *const _,
+            Cpu::binary_csr_write as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_fp_op\0".as_ptr() as *const _,
+            Cpu::binary_fp_op as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_abort_escape\0".as_ptr() as *const _,
+            Cpu::binary_abort_escape as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_abort_limit\0".as_ptr() as *const _,
+            Cpu::binary_abort_limit as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_abort_illegal_inst\0".as_ptr() as *const _,
+            Cpu::binary_abort_illegal_inst as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_trap\0".as_ptr() as *const _,
+            Cpu::binary_trap as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_abort_missing_feature\0".as_ptr() as *const _,
+            Cpu::binary_abort_missing_feature as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_abort_illegal_branch\0".as_ptr() as *const _,
+            Cpu::binary_abort_illegal_branch as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_ssr_check_addr\0".as_ptr() as *const _,
+            Cpu::binary_ssr_check_addr as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_trace\0".as_ptr() as *const _,
+            Cpu::binary_trace as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_wfi\0".as_ptr() as *const _,
+            Cpu::binary_wfi as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_resume\0".as_ptr() as *const _,
+            Cpu::binary_resume as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_ecall\0".as_ptr() as *const _,
+            Cpu::binary_ecall as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_custom_inst\0".as_ptr() as *const _,
+            Cpu::binary_custom_inst as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_breakpoint\0".as_ptr() as *const _,
+            Cpu::binary_breakpoint as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_observe_inst\0".as_ptr() as *const _,
+            Cpu::binary_observe_inst as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_spin\0".as_ptr() as *const _,
+            Cpu::binary_spin as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_check_clint\0".as_ptr() as *const _,
+            Cpu::binary_check_clint as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_check_cl_clint\0".as_ptr() as *const _,
+            Cpu::binary_check_cl_clint as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_fp64_op_cvt_to_f\0".as_ptr() as *const _,
+            Cpu::binary_fp64_op_cvt_to_f as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_fp32_op_cvt_to_f\0".as_ptr() as *const _,
+            Cpu::binary_fp32_op_cvt_to_f as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_fp16_op_cvt_to_f\0".as_ptr() as *const _,
+            Cpu::binary_fp16_op_cvt_to_f as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_fp8_op_cvt_to_f\0".as_ptr() as *const _,
+            Cpu::binary_fp8_op_cvt_to_f as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_fp16_op_cmp\0".as_ptr() as *const _,
+            Cpu::binary_fp16_op_cmp as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_fp8_op_cmp\0".as_ptr() as *const _,
+            Cpu::binary_fp8_op_cmp as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_fp16_op\0".as_ptr() as *const _,
+            Cpu::binary_fp16_op as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_fp8_op\0".as_ptr() as *const _,
+            Cpu::binary_fp8_op as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_fp16_to_fp32_op\0".as_ptr() as *const _,
+            Cpu::binary_fp16_to_fp32_op as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_fp8_to_fp16_op\0".as_ptr() as *const _,
+            Cpu::binary_fp8_to_fp16_op as *mut _,
+        );
+        LLVMAddSymbol(
+            b"banshee_fp8_to_fp32_op\0".as_ptr() as *const _,
+            Cpu::binary_fp8_to_fp32_op as *mut _,
+        );
+    }
+}
+
+// /// A representation of the system state.
+// #[repr(C)]
+// pub struct System<'a> {}
+
+impl CpuState {
+    /// Create a new CpuState
+    pub fn new(num_dm: usize, hartid: usize, bootrom_addr: u32) -> Self {
+        let mut reg_init: [u32; 32] = [0; 32];
+        reg_init[10] = hartid as u32;
+        reg_init[11] = bootrom_addr;
+        Self {
+            regs: reg_init,
+            regs_cycle: [0; 32],
+            fregs: [0; 32],
+            fregs_cycle: [0; 32],
+            cas_value: 0,
+            pc: 0,
+            cycle: 0,
+            instret: 0,
+            ssrs: (0..num_dm).map(|_| Default::default()).collect(),
+            ssr_enable: 0,
+            fpmode: 0,
+            fflags: 0,
+            frm: 0,
+            wfi: false,
+            dma: Default::default(),
+            irq: Default::default(),
+            stats: Default::default(),
+            finished: false,
+        }
+    }
+}
+
+/// The memories and registers which a hart shares with the other harts of its
+/// cluster or of the system during an execution.
+pub struct SharedState<'b> {
+    pub tcdm_ptr: &'b u32,
+    pub tcdm_ext_ptr: &'b Vec<&'b u32>,
+    pub barrier: &'b AtomicUsize,
+    pub wakeup_state: &'b Mutex<WakeupState>,
+    pub clint: &'b Vec<AtomicU32>,
+    pub cl_clint: &'b AtomicUsize,
+    pub exit_reg: &'b AtomicU32,
+}
+
+impl<'a, 'b> Cpu<'a, 'b> {
+    /// Create a new CPU in a default state.
+    pub fn new(
+        engine: &'a Engine,
+        hartid: usize,
+        num_cores: usize,
+        cluster_base_hartid: usize,
+        cluster_id: usize,
+        features: u32,
+        shared: SharedState<'b>,
+    ) -> Self {
+        let mut state = CpuState::new(
+            engine.config.ssr.num_dm,
+            hartid,
+            engine.config.bootrom.start,
+        );
+        state.stats.inst_mix = vec![0; engine.inst_mnemonics.lock().unwrap().len()];
+        state.dma = DmaState::new(engine.config.dma.latency, engine.config.dma.bandwidth);
+        // The translated code continues at the PC in deterministic mode.
+        if let Some(image) = engine.image_for_hart(hartid) {
+            state.pc = engine.images[image].entry_point as u32;
+        }
+        if let Some(f) = &engine.initial_regs_fn {
+            state.regs = f(hartid);
+            state.regs[0] = 0;
+        }
+        let tls = engine
+            .image_for_hart(hartid)
+            .and_then(|image| engine.images[image].tls.as_ref());
+        if let Some(tls) = tls {
+            state.regs[4] = tls.addr(hartid - engine.base_hartid);
+        }
+        for &(reg, value) in &engine.initial_regs {
+            state.regs[reg as usize] = value;
+        }
+        match &engine.app_args {
+            Some((addr, args)) if hartid == engine.base_hartid => {
+                state.regs[2] = *addr;
+                state.regs[10] = args.args.len() as u32;
+                state.regs[11] = args.argv(*addr);
+                state.regs[12] = args.envp(*addr);
+            }
+            _ => (),
+        }
+        if let Some(regs) = engine.hart_regs.lock().unwrap().get(&hartid) {
+            for index in 0..32 {
+                if regs.set_regs & 1 << index != 0 {
+                    state.regs[index] = regs.regs[index];
+                }
+                if regs.set_fregs & 1 << index != 0 {
+                    state.fregs[index] = regs.fregs[index];
+                }
+            }
+        }
+        Self {
+            engine,
+            state,
+            tcdm_ptr: shared.tcdm_ptr,
+            tcdm_ext_ptr: shared.tcdm_ext_ptr,
+            hartid,
+            num_cores,
+            cluster_base_hartid,
+            cluster_id,
+            features,
+            barrier: shared.barrier,
+            wakeup_state: shared.wakeup_state,
+            clint: shared.clint,
+            cl_clint: shared.cl_clint,
+            stop: &engine.stop,
+            exit_reg: shared.exit_reg,
+            exit_value: Default::default(),
+            had_error: Default::default(),
+            spin: Default::default(),
+            clock_synced: Default::default(),
+            barrier_wait: Default::default(),
+            yield_at: Default::default(),
+            yielded: Default::default(),
+        }
+    }
+
+    /// Flag an error on this hart, which fails the execution.
+    fn flag_error(&self) {
+        self.had_error.store(true, Ordering::SeqCst);
+        self.engine.had_error.store(true, Ordering::SeqCst);
+    }
+
+    /// Abort execution because of a fatal error at `pc`.
+    ///
+    /// Flags the error and stops all harts, which return from the translated
+    /// code at the end of their current basic block. The first fault is kept
+    /// as the error of the execution.
+    fn fault(&self, pc: u32, reason: String) {
+        let fault = HartFault {
+            hartid: self.hartid,
+            pc,
+            reason,
+        };
+        error!(target: log_target::EXEC, "{}", fault);
+        self.engine.fault.lock().unwrap().get_or_insert(fault);
+        self.flag_error();
+        self.engine.stop.store(true, Ordering::SeqCst);
+        // Wake the sleeping harts of the cluster right away.
+        for thread in self.wakeup_state.lock().unwrap().threads.iter().flatten() {
+            thread.unpark();
+        }
+    }
+
+    /// Write the exit code register of the cluster.
+    fn write_exit_reg(&self, value: u32) {
+        self.exit_reg.store(value, Ordering::SeqCst);
+        *self.exit_value.lock().unwrap() = Some(value);
+    }
+
+    /// Report an exit code through the exit code register, encoded for the
+    /// exit protocol, and stop all harts.
+    fn exit(&self, code: u32) {
+        let value = match self.engine.exit_protocol {
+            ExitProtocol::Snitch => code << 1 | 1,
+            ExitProtocol::Nonzero => code,
+        };
+        self.write_exit_reg(value);
+        self.engine.halted.store(true, Ordering::SeqCst);
+        self.engine.stop.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether an address lies outside the DRAM of the hart's cluster and
+    /// all TCDMs, such that an access to it is unmapped.
+    fn is_unmapped(&self, addr: u32) -> bool {
+        let dram = &self.engine.config.memory[self.cluster_id].dram;
+        (addr < dram.start || addr >= dram.end) && !self.engine.is_tcdm(addr)
+    }
+
+    /// Count a memory access for the execution statistics.
+    fn count_access(&self, addr: u32) {
+        let config = &self.engine.config;
+        let memory = &config.memory[self.cluster_id];
+        let control = [
+            config.address.tcdm_start,
+            config.address.tcdm_end,
+            config.address.nr_cores,
+            config.address.scratch_reg,
+            config.address.wakeup_reg,
+            config.address.barrier_reg,
+            config.address.cluster_base_hartid,
+            config.address.cluster_num,
+            config.address.cluster_id,
+            config.address.uart,
+        ];
+        let region = if control.contains(&addr) {
+            MemRegion::Control
+        } else if addr >= memory.tcdm.start && addr < memory.tcdm.end {
+            MemRegion::Tcdm
+        } else if config
+            .memory
+            .iter()
+            .any(|m| addr >= m.tcdm.start && addr < m.tcdm.end)
+        {
+            MemRegion::TcdmExternal
+        } else if addr >= memory.periphs.start && addr < memory.periphs.end {
+            MemRegion::Peripherals
+        } else if addr >= config.bootrom.start && addr < config.bootrom.end {
+            MemRegion::Bootrom
+        } else if (addr >= config.address.clint && addr < config.address.clint + 0x1000)
+            || (addr >= config.address.cl_clint && addr <= config.address.cl_clint + 0x8)
+        {
+            MemRegion::Clint
+        } else {
+            MemRegion::Dram
+        };
+        self.state.stats.mem_accesses[region as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn binary_load(&self, addr: u32, size: u8) -> u32 {
+        if let Some(observer) = &self.engine.access_observer {
+            observer.on_load(self.hartid, self.state.pc, addr, 1 << size);
+        }
+        if self.check_stack_guard(addr, size, false) {
+            return 0;
+        }
+        let value = self.load(addr, size);
+        if self.engine.access_log_size != 0 {
+            self.log_access(addr, value, size, false);
+        }
+        if self.engine.is_watched(addr, false) {
+            let word = self.peek_word(addr);
+            self.watch_hit(addr, false, word, word);
+        }
+        value
+    }
+
+    /// Returns 1 if the store halts execution, 0 otherwise.
+    fn binary_store(&mut self, addr: u32, value: u32, mask: u32, size: u8) -> u32 {
+        if let Some(observer) = &self.engine.access_observer {
+            observer.on_store(self.hartid, self.state.pc, addr, 1 << size);
+        }
+        if self.check_stack_guard(addr, size, true) {
+            return 1;
+        }
+        if self.engine.access_log_size != 0 {
+            self.log_access(addr, value, size, true);
+        }
+        let halt = if !self.engine.is_watched(addr, true) {
+            self.store(addr, value, mask, size)
+        } else {
+            let old = self.peek_word(addr);
+            let halt = self.store(addr, value, mask, size);
+            let new = self.peek_word(addr);
+            self.watch_hit(addr, true, old, new);
+            halt
+        };
+        // A hart which reports its exit code, or halts through the exit code
+        // register, has reached its normal exit.
+        let reports_exit = addr == self.engine.config.address.scratch_reg
+            && self.engine.exit_protocol.exit_code(value).is_some();
+        self.state.finished |= halt != 0 || reports_exit;
+        halt
+    }
+
+    /// Check whether an access of `1 << size` bytes hits a stack guard, in
+    /// which case the access is dropped and execution stops with an error.
+    fn check_stack_guard(&self, addr: u32, size: u8, write: bool) -> bool {
+        if !self.engine.has_stack_guards() {
+            return false;
+        }
+        let owner = match self.engine.stack_guard_owner(addr, 1 << size) {
+            Some(owner) => owner,
+            None => return false,
+        };
+        let overflow = StackOverflow {
+            hartid: self.hartid,
+            pc: self.state.pc,
+            addr,
+            write,
+            owner,
+        };
+        error!(target: log_target::EXEC, "{}", overflow);
+        self.log_backtrace(self.state.pc);
+        self.engine
+            .stack_overflow
+            .lock()
+            .unwrap()
+            .get_or_insert(overflow);
+        self.flag_error();
+        self.engine.stop.store(true, Ordering::SeqCst);
+        true
+    }
+
+    /// Read the word at an address in the TCDMs or DRAM, without side
+    /// effects.
+    fn peek_word(&self, addr: u32) -> u32 {
+        let addr = addr & !3;
+        match self
+            .engine
+            .config
+            .memory
+            .iter()
+            .position(|m| addr >= m.tcdm.start && addr < m.tcdm.end)
+        {
+            Some(id) => {
+                let word_addr = (addr - self.engine.config.memory[id].tcdm.start) / 4;
+                let ptr: *const u32 = self.tcdm_ext_ptr[id];
+                unsafe { *ptr.offset(word_addr as isize) }
+            }
+            None => self
+                .engine
+                .with_memory(addr, |memory| memory.load_word(addr)),
+        }
+    }
+
+    /// Record an access of this hart in the engine's access log.
+    fn log_access(&self, addr: u32, value: u32, size: u8, write: bool) {
+        self.engine.log_access(LoggedAccess {
+            hartid: self.hartid,
+            pc: self.state.pc,
+            addr,
+            value,
+            size,
+            write,
+        });
+    }
+
+    /// Report an access which triggered a watchpoint.
+    fn watch_hit(&self, addr: u32, write: bool, old: u32, new: u32) {
+        let hit = WatchHit {
+            hartid: self.hartid,
+            pc: self.state.pc,
+            addr,
+            write,
+            old,
+            new,
+        };
+        match &self.engine.watch_callback {
+            Some(callback) => callback(&hit),
+            None => info!(
+                target: log_target::MEM,
+                "Hart {} (pc=0x{:08x}) {} 0x{:08x}: 0x{:08x} -> 0x{:08x}",
+                hit.hartid,
+                hit.pc,
+                if write { "writes" } else { "reads" },
+                addr,
+                old,
+                new
             ),
-            TraceAccess::Readf16Reg(x) => format!(
-                "f{:02}=[{:>8.4}]",
-                x,
-                f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
-                    (data & 0x0000_0000_0000_ffff) >> 0,
-                    flexfloat::FfOpCvt::Fcvt16f2f,
-                    false,
-                    false
-                ) as u32),
+        }
+    }
+
+    fn load(&self, addr: u32, size: u8) -> u32 {
+        if self.engine.stats {
+            self.count_access(addr);
+        }
+        match addr {
+            x if x == self.engine.config.address.tcdm_start => {
+                self.engine.config.memory[self.cluster_id].tcdm.start
+            } // tcdm_start
+            x if x == self.engine.config.address.tcdm_end => {
+                self.engine.config.memory[self.cluster_id].tcdm.end
+            } // tcdm_end
+            x if x == self.engine.config.address.nr_cores => self.num_cores as u32, // nr_cores
+            x if x == self.engine.config.address.scratch_reg => {
+                self.exit_reg.load(Ordering::SeqCst)
+            } // scratch_reg
+            x if x == self.engine.config.address.barrier_reg => {
+                if self.engine.deterministic.is_some() {
+                    self.arrive_at_barrier();
+                } else {
+                    self.cluster_barrier();
+                }
+                0
+            } // barrier_reg
+            x if x == self.engine.config.address.cluster_base_hartid => {
+                self.cluster_base_hartid as u32
+            } // cluster_base_hartid
+            x if x == self.engine.config.address.cluster_num => self.engine.num_clusters as u32, // cluster_num
+            x if x == self.engine.config.address.cluster_id => self.cluster_id as u32, // cluster_id
+            x if self.in_uart(x) => self.uart_load(x),
+            // TCDM
+            x if x >= self.engine.config.memory[self.cluster_id].tcdm.start
+                && x < self.engine.config.memory[self.cluster_id].tcdm.end =>
+            {
+                let tcdm_addr = addr - self.engine.config.memory[self.cluster_id].tcdm.start;
+                let word_addr = tcdm_addr / 4;
+                let ptr: *const u32 = self.tcdm_ptr;
+                unsafe { *ptr.offset(word_addr as isize) }
+            }
+            // TCDM External
+            x if self
+                .engine
+                .config
+                .memory
+                .iter()
+                .any(|m| x >= m.tcdm.start && x < m.tcdm.end) =>
+            {
+                let id = self
+                    .engine
+                    .config
+                    .memory
+                    .iter()
+                    .position(|m| addr >= m.tcdm.start && addr < m.tcdm.end)
+                    .unwrap();
+                let tcdm_addr = addr - self.engine.config.memory[id].tcdm.start;
+                let word_addr = tcdm_addr / 4;
+                let ptr: *const u32 = self.tcdm_ext_ptr[id];
+                unsafe { *ptr.offset(word_addr as isize) }
+            }
+            // Peripherals
+            x if x >= self.engine.config.memory[self.cluster_id].periphs.start
+                && x < self.engine.config.memory[self.cluster_id].periphs.end =>
+            {
+                self.engine.peripherals.load(
+                    self.cluster_id,
+                    addr - self.engine.config.memory[self.cluster_id].periphs.start,
+                    size,
+                )
+            }
+            // Bootrom
+            x if x >= self.engine.config.bootrom.start && x < self.engine.config.bootrom.end => {
+                self.engine
+                    .bootrom
+                    .load(addr - self.engine.config.bootrom.start)
+            }
+            // access to the CLINT
+            x if x >= self.engine.config.address.clint
+                && x < self.engine.config.address.clint + 0x1000 =>
+            {
+                trace!(
+                    target: log_target::MEM,
+                    "CLINT Load off 0x{:x}",
+                    addr as u64 - self.engine.config.address.clint as u64
+                );
+                let word_addr = (addr - self.engine.config.address.clint) / 4;
+                self.clint[word_addr as usize].load(Ordering::SeqCst)
+            }
+            // The cl_clint is WO
+            x if x >= self.engine.config.address.cl_clint
+                && x < self.engine.config.address.cl_clint + 0x8 =>
+            {
+                0
+            }
+            // SSR configuration of a data mover which is not implemented
+            x if self.unimplemented_ssr(x) => {
+                self.abort_unimplemented_ssr(addr);
+                0
+            }
+            // DRAM
+            _ => {
+                // Map all remaining addresses to the hash map but throw a warning if we read outside the memory map
+                if self.is_unmapped(addr) {
+                    self.engine.unmapped_reads.fetch_add(1, Ordering::Relaxed);
+                    warn!(
+                        target: log_target::MEM,
+                        "Hart {} (pc=0x{:08x}) is reading outside the memory map at 0x{:08x}",
+                        self.hartid, self.state.pc, addr
+                    );
+                }
+                // trace!("Load 0x{:x} ({}B)", addr, 8 << size);
+                self.engine
+                    .with_memory(addr, |memory| memory.load_word(addr))
+            }
+        }
+    }
+
+    fn store(&self, addr: u32, value: u32, mask: u32, size: u8) -> u32 {
+        if self.engine.stats {
+            self.count_access(addr);
+        }
+        match addr {
+            x if x == self.engine.config.address.tcdm_start => (), // tcdm_start
+            x if x == self.engine.config.address.tcdm_end => (),   // tcdm_end
+            x if x == self.engine.config.address.nr_cores => (),   // nr_cores
+            x if x == self.engine.config.address.scratch_reg => {
+                self.write_exit_reg(value);
+                if self.engine.exit_protocol.halts(value) {
+                    debug!(target: log_target::EXEC, "Hart {} halts execution", self.hartid);
+                    self.engine.halted.store(true, Ordering::SeqCst);
+                    self.engine.stop.store(true, Ordering::SeqCst);
+                    return 1;
+                }
+            } // scratch_reg
+            x if x == self.engine.config.address.wakeup_reg => {
+                // wakeup_req
+                self.wake(value);
+            } // wakeup_reg
+            x if x == self.engine.config.address.barrier_reg => (), // barrier_reg
+            x if x == self.engine.config.address.cluster_base_hartid => (), // cluster_base_hartid
+            x if x == self.engine.config.address.cluster_num => (), // cluster_num
+            x if x == self.engine.config.address.cluster_id => (), // cluster_id
+            x if x == self.engine.config.address.uart => self.putchar(value as u8),
+            x if self.in_uart(x) => self.uart_store(x, value, mask),
+            x if Some(x) == self.engine.tohost => return self.htif_store(value),
+            // TCDM
+            // TODO: this is *not* thread-safe and *will* lead to undefined behavior on simultaneous access
+            // by 2 harts. However, changing `tcdm_ptr` to a locked structure would require pervasive redesign.
+            x if x >= self.engine.config.memory[self.cluster_id].tcdm.start
+                && x < self.engine.config.memory[self.cluster_id].tcdm.end =>
+            {
+                let tcdm_addr = addr - self.engine.config.memory[self.cluster_id].tcdm.start;
+                let word_addr = tcdm_addr / 4;
+                let ptr = self.tcdm_ptr as *const u32;
+                let ptr_mut = ptr as *mut u32;
+                unsafe {
+                    let word_ptr = ptr_mut.offset(word_addr as isize);
+                    let word = *word_ptr;
+                    *word_ptr = (word & !mask) | (value & mask);
+                }
+            }
+            // TCDM External
+            x if self
+                .engine
+                .config
+                .memory
+                .iter()
+                .any(|m| x >= m.tcdm.start && x < m.tcdm.end) =>
+            {
+                let id = self
+                    .engine
+                    .config
+                    .memory
+                    .iter()
+                    .position(|m| addr >= m.tcdm.start && addr < m.tcdm.end)
+                    .unwrap();
+                let tcdm_addr = addr - self.engine.config.memory[id].tcdm.start;
+                let word_addr = tcdm_addr / 4;
+                let ptr = self.tcdm_ext_ptr[id] as *const u32;
+                let ptr_mut = ptr as *mut u32;
+                unsafe {
+                    let word_ptr = ptr_mut.offset(word_addr as isize);
+                    let word = *word_ptr;
+                    *word_ptr = (word & !mask) | (value & mask);
+                }
+            }
+            // Peripherals
+            x if x >= self.engine.config.memory[self.cluster_id].periphs.start
+                && x < self.engine.config.memory[self.cluster_id].periphs.end =>
+            {
+                self.engine.peripherals.store(
+                    self.cluster_id,
+                    addr - self.engine.config.memory[self.cluster_id].periphs.start,
+                    value,
+                    mask,
+                    size,
+                )
+            }
+            // Bootrom
+            x if x >= self.engine.config.bootrom.start && x < self.engine.config.bootrom.end => {}
+            // access to the CLINT
+            x if x >= self.engine.config.address.clint
+                && x < self.engine.config.address.clint + 0x1000 =>
+            {
+                let word_addr = (addr - self.engine.config.address.clint) / 4;
+                trace!(
+                    target: log_target::MEM,
+                    "CLINT store word off {:x} = 0x{:x}",
+                    word_addr,
+                    value
+                );
+                let old_entry = self.clint[word_addr as usize].load(Ordering::SeqCst);
+                let entry = (old_entry & !mask) | (value & mask);
+                self.clint[word_addr as usize].store(entry, Ordering::SeqCst);
+                // wake cores affected by this write
+
+                let hart_base = 32 * word_addr as i32 - self.engine.base_hartid as i32;
+                for i in 0..32 {
+                    if ((!old_entry & entry) & (1 << i)) != 0 {
+                        trace!(
+                            target: log_target::MEM,
+                            "  wakeup_wus.req[{}] from CLINT",
+                            (hart_base + i as i32) as usize
+                        );
+                        self.wake((hart_base + i) as u32);
+                    }
+                }
+            }
+            x if x == self.engine.config.address.cl_clint => {
+                // clint set register
+                let old_entry = self
+                    .cl_clint
+                    .fetch_or((value & mask) as usize, Ordering::SeqCst);
+                // wake cores affected by this write
+                let hart_base = (self.cluster_base_hartid - self.engine.base_hartid) as u32;
+                for i in 0..32 {
+                    if ((!old_entry & (value & mask) as usize) & (1 << i)) != 0 {
+                        trace!(
+                            target: log_target::MEM,
+                            "  wakeup_wus.req[{}] from cluster-local CLINT",
+                            (hart_base + i) as usize
+                        );
+                        self.wake((hart_base + i) as u32);
+                    }
+                }
+            }
+            x if x == self.engine.config.address.cl_clint + 0x8 => {
+                // clint clear register
+                self.cl_clint
+                    .fetch_and(!(value & mask) as usize, Ordering::SeqCst);
+            }
+            // SSR configuration of a data mover which is not implemented
+            x if self.unimplemented_ssr(x) => self.abort_unimplemented_ssr(addr),
+            // DRAM
+            _ => {
+                // Map all remaining addresses to the hash map but throw a warning if we write outside the memory map
+                if self.is_unmapped(addr) {
+                    self.engine.unmapped_writes.fetch_add(1, Ordering::Relaxed);
+                    warn!(
+                        target: log_target::MEM,
+                        "Hart {} (pc=0x{:08x}) is writing outside the memory map at 0x{:08x}",
+                        self.hartid, self.state.pc, addr
+                    );
+                }
+                trace!(
+                    target: log_target::MEM,
+                    "Store 0x{:x} = 0x{:x} if 0x{:x} ({}B)",
+                    addr,
+                    value,
+                    mask,
+                    8 << size
+                );
+                // Only partial stores need the old word.
+                self.engine.with_memory(addr, |memory| {
+                    let data = match mask {
+                        u32::MAX => value,
+                        _ => memory.load_word(addr) & !mask | value & mask,
+                    };
+                    memory.store_word(addr, data);
+                });
+            }
+        }
+        0
+    }
+
+    fn binary_rmw(&self, addr: u32, value: u32, op: AtomicOp) -> u32 {
+        if self.check_stack_guard(addr, 2, true) {
+            return 0;
+        }
+        trace!(target: log_target::MEM, "RMW 0x{:x} (op={})= 0x{:x} (32B)", addr, op as u8, value);
+        // An atomic both reads and writes the word.
+        if self.is_unmapped(addr) {
+            self.engine.unmapped_reads.fetch_add(1, Ordering::Relaxed);
+            self.engine.unmapped_writes.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                target: log_target::MEM,
+                "Hart {} (pc=0x{:08x}) is accessing outside the memory map at 0x{:08x}",
+                self.hartid, self.state.pc, addr
+            );
+        }
+        self.engine.with_memory(addr, |data| {
+            let mut prev = data.load_word(addr);
+            // Atomics
+            let result = match op {
+                AtomicOp::Amoadd => prev.wrapping_add(value),
+                AtomicOp::Amoxor => prev ^ value,
+                AtomicOp::Amoor => prev | value,
+                AtomicOp::Amoand => prev & value,
+                AtomicOp::Amomin => std::cmp::min(prev as i32, value as i32) as u32,
+                AtomicOp::Amomax => std::cmp::max(prev as i32, value as i32) as u32,
+                AtomicOp::Amominu => std::cmp::min(prev as u32, value as u32),
+                AtomicOp::Amomaxu => std::cmp::max(prev as u32, value as u32),
+                AtomicOp::Amoswap => value,
+                AtomicOp::ScW => {
+                    if prev == self.state.cas_value {
+                        prev = 0; // Store-conditional success
+                        value
+                    } else {
+                        return 1; // Store-conditional failed
+                    }
+                }
+            };
+            data.store_word(addr, result);
+            prev as u32
+        })
+    }
+
+    /// The cycle count reported by the cycle and time counters: the estimated
+    /// cycles with the latency model, otherwise the retired instructions.
+    fn cycles(&self) -> u64 {
+        if self.engine.latency {
+            self.state.cycle
+        } else {
+            self.state.instret
+        }
+    }
+
+    /// Read the engine's shared clock, after advancing it by the cycles the
+    /// hart spent since it last did. The clock thus approximates the
+    /// aggregate progress of the harts, and increases with every read, since
+    /// the reading instruction itself retires.
+    fn shared_clock(&self) -> u64 {
+        let cycles = self.cycles();
+        let synced = self.clock_synced.swap(cycles, Ordering::Relaxed);
+        let delta = cycles.saturating_sub(synced);
+        self.engine.clock.fetch_add(delta, Ordering::SeqCst) + delta
+    }
+
+    /// The value of the `cycle` and `time` CSRs.
+    fn clock(&self) -> u64 {
+        if self.engine.shared_clock {
+            self.shared_clock()
+        } else {
+            self.cycles()
+        }
+    }
+
+    fn binary_csr_read(&self, csr: riscv::Csr, notrace: u32) -> u32 {
+        if notrace == 0 {
+            trace!(target: log_target::EXEC, "Read CSR {:?}", csr);
+        }
+        match csr {
+            riscv::Csr::Ssr => self.state.ssr_enable,
+            riscv::Csr::Fflags => self.state.fflags,
+            riscv::Csr::Frm => self.state.frm,
+            riscv::Csr::Fcsr => self.state.frm << 5 | self.state.fflags,
+            riscv::Csr::Fpmode => self.state.fpmode as u32,
+            riscv::Csr::Mcycle => self.cycles() as u32,
+            riscv::Csr::Mcycleh => (self.cycles() >> 32) as u32,
+            riscv::Csr::Cycle | riscv::Csr::Time => self.clock() as u32,
+            riscv::Csr::Cycleh | riscv::Csr::Timeh => (self.clock() >> 32) as u32,
+            riscv::Csr::Minstret | riscv::Csr::Instret => self.state.instret as u32,
+            riscv::Csr::Minstreth | riscv::Csr::Instreth => (self.state.instret >> 32) as u32,
+            riscv::Csr::Mhartid => self.hartid as u32, // mhartid
+            riscv::Csr::Mvendorid | riscv::Csr::Marchid | riscv::Csr::Mimpid => 0,
+            riscv::Csr::Mstatus => self.state.irq.mstatus | MSTATUS_FIXED, // CSR_MSTATUS
+            riscv::Csr::Mie => self.state.irq.mie,                         // CSR_MIE
+            riscv::Csr::Mip => {
+                // The software interrupts are pending as long as they are set
+                // in the CLINT, even if interrupts are not sampled.
+                self.state.irq.mip & !(1 << 3 | 1 << 19)
+                    | self.binary_check_clint() << 3
+                    | self.binary_check_cl_clint() << 19
+            }
+            riscv::Csr::Mtvec => self.state.irq.mtvec, // CSR_MTVEC
+            riscv::Csr::Mepc => self.state.irq.mepc,   // CSR_MEPC
+            riscv::Csr::Mcause => self.state.irq.mcause, // CSR_MCAUSE
+            riscv::Csr::Mtval => self.state.irq.mtval, // CSR_MTVAL
+            riscv::Csr::Mscratch => self.state.irq.mscratch,
+            riscv::Csr::Misa => {
+                // RV32IMAFDCX A - Atomic Instructions extension
+                (1 << 0)
+                    | (1 << 2)
+                    | (1 << 3)
+                    | (1 << 5)
+                    | (1 << 8)
+                    | (1 << 12)
+                    | (1 << 23)
+                    | (1 << 30)
+            }
+            _ => {
+                self.unknown_csr(csr);
+                0
+            }
+        }
+    }
+
+    fn binary_csr_write(&mut self, csr: riscv::Csr, value: u32, notrace: u32) {
+        if notrace == 0 {
+            trace!(target: log_target::EXEC, "Write CSR {:?} = 0x{:?}", csr, value);
+        }
+        // CSRs with the two upper address bits set are read-only.
+        if csr as u32 >> 10 == 0b11 {
+            if self.engine.config.trap_read_only_csrs {
+                self.fault(self.state.pc, format!("writes read-only CSR {:?}", csr));
+            }
+            return;
+        }
+        match csr {
+            riscv::Csr::Ssr => self.state.ssr_enable = value,
+            riscv::Csr::Fflags => self.state.fflags = value & 0x1f,
+            riscv::Csr::Frm => self.state.frm = value & 0x7,
+            riscv::Csr::Fcsr => {
+                self.state.fflags = value & 0x1f;
+                self.state.frm = (value >> 5) & 0x7;
+            }
+            riscv::Csr::Fpmode => self.state.fpmode = value,
+            riscv::Csr::Mstatus => self.state.irq.mstatus = value & MSTATUS_WRITABLE, // CSR_MSTATUS
+            riscv::Csr::Mie => self.state.irq.mie = value,                            // CSR_MIE
+            riscv::Csr::Mip => self.state.irq.mip = value,                            // CSR_MIP
+            riscv::Csr::Mtvec => self.state.irq.mtvec = value,                        // CSR_MTVEC
+            riscv::Csr::Mepc => self.state.irq.mepc = value,                          // CSR_MEPC
+            riscv::Csr::Mcause => self.state.irq.mcause = value,                      // CSR_MCAUSE
+            riscv::Csr::Mtval => self.state.irq.mtval = value,                        // CSR_MTVAL
+            riscv::Csr::Mscratch => self.state.irq.mscratch = value,
+            // The ISA string is fixed.
+            riscv::Csr::Misa => (),
+            _ => self.unknown_csr(csr),
+        }
+    }
+
+    /// Warn about an access to a CSR which is not implemented, once per CSR.
+    fn unknown_csr(&self, csr: riscv::Csr) {
+        if self.engine.unknown_csrs.lock().unwrap().insert(csr as u32) {
+            warn!(
+                target: log_target::EXEC,
+                "Hart {} (pc=0x{:08x}) accesses unimplemented CSR {:?} (0x{:03x}), which reads as zero and ignores writes",
+                self.hartid,
+                self.state.pc,
+                csr,
+                csr as u32
+            );
+        }
+    }
+
+    fn binary_fp_op(
+        &mut self,
+        op: FpOp,
+        rm: u32,
+        double: u32,
+        rs1: u64,
+        rs2: u64,
+        rs3: u64,
+    ) -> u64 {
+        let rm = if rm == 7 { self.state.frm } else { rm };
+        if rm > 4 {
+            self.fault(self.state.pc, format!("uses invalid rounding mode {}", rm));
+        }
+        unsafe {
+            // The SoftFloat rounding modes and flags are numbered the same
+            // way as in the `frm` and `fflags` CSRs.
+            softfloat::set_rounding_mode(rm as u8);
+            softfloat::set_flags(0);
+            let result = if double != 0 {
+                fp_op_f64(op, rm as u8, rs1, rs2, rs3)
+            } else {
+                fp_op_f32(op, rm as u8, rs1, rs2, rs3)
+            };
+            self.state.fflags |= softfloat::get_flags() as u32;
+            result
+        }
+    }
+
+    /// Check whether an address lies in the configuration space of an SSR
+    /// data mover beyond the configured number of them.
+    fn unimplemented_ssr(&self, addr: u32) -> bool {
+        let offset = (addr as u64).wrapping_sub(tran::SSR_BASE);
+        offset < tran::SSR_MAX_NUM_DM * 32 * 8
+            && offset / (32 * 8) >= self.engine.config.ssr.num_dm as u64
+    }
+
+    fn abort_unimplemented_ssr(&self, addr: u32) {
+        self.fault(
+            self.state.pc,
+            format!(
+                "is configuring SSR {} at 0x{:08x}, but only {} are implemented",
+                (addr as u64 - tran::SSR_BASE) / (32 * 8),
+                addr,
+                self.engine.config.ssr.num_dm
             ),
-            TraceAccess::Writef16Reg(x) => format!(
-                "f{:02}=[{:>5.3}]",
-                x,
-                f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
-                    (data & 0x0000_0000_0000_00ff) >> 0,
-                    flexfloat::FfOpCvt::Fcvt16f2f,
-                    false,
-                    false
-                ) as u32),
+        );
+    }
+
+    /// Walk the call stack through the frame pointer `s0` and return the
+    /// return addresses, innermost first.
+    ///
+    /// This is best-effort: it assumes that the binary keeps frame pointers,
+    /// with the return address and the caller's frame pointer saved right
+    /// below the frame pointer, and stops at the first frame which does not
+    /// look like one.
+    pub fn backtrace(&self) -> Vec<u32> {
+        let executable = |addr: u32| {
+            self.engine.images.iter().any(|image| {
+                image
+                    .exec_ranges
+                    .iter()
+                    .any(|&(start, end)| (start..end).contains(&(addr as u64)))
+            })
+        };
+        let mut frames = vec![];
+        let mut fp = self.state.regs[8];
+        while frames.len() < MAX_BACKTRACE_DEPTH && fp >= 8 && fp % 4 == 0 {
+            let mut ra = self.peek_word(fp - 4);
+            let mut next = self.peek_word(fp - 8);
+            // A leaf function need not save `ra`, in which case the caller's
+            // frame pointer is saved in its slot.
+            if frames.is_empty() && !executable(ra) {
+                next = ra;
+                ra = self.state.regs[1];
+            }
+            if !executable(ra) {
+                break;
+            }
+            frames.push(ra);
+            // The stack grows downwards, so the caller's frame lies above.
+            if next <= fp {
+                break;
+            }
+            fp = next;
+        }
+        frames
+    }
+
+    /// Log the backtrace of the hart, if enabled.
+    fn log_backtrace(&self, addr: u32) {
+        if !self.engine.backtrace_on_abort {
+            return;
+        }
+        error!(target: log_target::EXEC, "Backtrace of hart {}:", self.hartid);
+        for (i, addr) in std::iter::once(addr).chain(self.backtrace()).enumerate() {
+            error!(
+                target: log_target::EXEC,
+                "  #{} 0x{:08x}{}",
+                i,
+                addr,
+                self.engine.describe_addr(addr)
+            );
+        }
+    }
+
+    fn binary_abort_escape(&self, addr: u32) {
+        self.fault(addr, "escaped the binary".to_string());
+        self.log_backtrace(addr);
+    }
+
+    fn binary_abort_limit(&self, addr: u32) {
+        self.log_backtrace(addr);
+        self.flag_error();
+        self.engine.exceed_limit(LimitExceeded {
+            limit: Limit::Instret(self.engine.max_instret.unwrap_or(self.state.instret)),
+            harts: vec![(self.hartid, addr)],
+        });
+    }
+
+    /// Handle an illegal instruction according to the abort policy. The
+    /// translated code then returns, or continues at the next instruction or
+    /// the handler.
+    fn binary_abort_illegal_inst(&mut self, addr: u32, inst_raw: u32) {
+        let inst = riscv::parse_u32(inst_raw);
+        match self.engine.abort_policy {
+            AbortPolicy::Stop => {
+                self.fault(addr, format!("executes illegal instruction {}", inst));
+                self.log_backtrace(addr);
+            }
+            AbortPolicy::Continue => warn!(
+                target: log_target::EXEC,
+                "Hart {} skips illegal instruction {} at 0x{:x}",
+                self.hartid,
+                inst,
+                addr
             ),
-            TraceAccess::Readvf64sReg(x) => format!(
-                "f{:02}:[{:>12.4}, {:>12.4}]",
-                x,
-                f32::from_bits((data >> 32) as u32),
-                f32::from_bits((data) as u32)
+            AbortPolicy::Trap(handler) => {
+                warn!(
+                    target: log_target::EXEC,
+                    "Hart {} enters handler at 0x{:x} for illegal instruction {} at 0x{:x}",
+                    self.hartid,
+                    handler,
+                    inst,
+                    addr
+                );
+                self.enter_trap(addr, 2, inst_raw);
+            }
+        }
+    }
+
+    /// Enter the trap handler for an exception and return its address, or 0
+    /// if no handler is installed.
+    fn binary_trap(&mut self, addr: u32, cause: u32, tval: u32) -> u32 {
+        if self.state.irq.mtvec & !3 == 0 {
+            return 0;
+        }
+        trace!(
+            target: log_target::EXEC,
+            "Hart {} traps at 0x{:x} with mcause {} and mtval 0x{:x}",
+            self.hartid,
+            addr,
+            cause,
+            tval
+        );
+        self.enter_trap(addr, cause, tval);
+        // Exceptions enter the base address in vectored mode, too.
+        self.state.irq.mtvec & !3
+    }
+
+    /// Update the machine-mode CSRs for taking an exception at `addr`.
+    fn enter_trap(&mut self, addr: u32, cause: u32, tval: u32) {
+        let irq = &mut self.state.irq;
+        // Save mstatus.MIE in mstatus.MPIE and disable interrupts.
+        irq.mstatus = irq.mstatus & !((1 << 3) | (1 << 7)) | (irq.mstatus & (1 << 3)) << 4;
+        irq.mepc = addr;
+        irq.mcause = cause;
+        irq.mtval = tval;
+    }
+
+    fn binary_abort_missing_feature(&self, addr: u32, feature: u32) {
+        self.fault(
+            addr,
+            format!(
+                "uses {}, which the hart does not support",
+                CoreFeatures::name(feature)
             ),
-            TraceAccess::Writevf64sReg(x) => format!(
-                "f{:02}=[{:>12.4}, {:>12.4}]",
-                x,
-                f32::from_bits((data >> 32) as u32),
-                f32::from_bits((data) as u32)
+        );
+        self.log_backtrace(addr);
+    }
+
+    /// Check the next access of an SSR whose stream started at `base`, which
+    /// is `overrun` once the stream ran past the end of its access pattern.
+    fn binary_ssr_check_addr(&self, dm: u32, base: u32, addr: u32, overrun: u32) {
+        let problem = if overrun != 0 {
+            format!("past the end of its stream (next address 0x{:x})", addr)
+        } else if addr % 8 != 0 {
+            format!("at 0x{:x}, which its strides do not align to 8 bytes", addr)
+        } else {
+            match self.stream_memory(base) {
+                Some((start, end)) if addr >= start && addr < end => return,
+                Some((start, end)) => format!(
+                    "at 0x{:x}, outside the memory 0x{:x}-0x{:x} its stream started in",
+                    addr, start, end
+                ),
+                None => format!("at 0x{:x}, outside the TCDM and DRAM", addr),
+            }
+        };
+        self.fault(self.state.pc, format!("accesses SSR {} {}", dm, problem));
+        self.log_backtrace(self.state.pc);
+    }
+
+    /// The TCDM or DRAM which contains an address, as its start and end.
+    fn stream_memory(&self, addr: u32) -> Option<(u32, u32)> {
+        let dram = &self.engine.config.memory[self.cluster_id].dram;
+        self.engine
+            .config
+            .memory
+            .iter()
+            .map(|m| (m.tcdm.start, m.tcdm.end))
+            .chain(std::iter::once((dram.start, dram.end)))
+            .find(|&(start, end)| addr >= start && addr < end)
+    }
+
+    fn binary_abort_illegal_branch(&self, addr: u32, target: u32) {
+        // Every instruction of the executed image is a valid branch target, so
+        // point out why the target is not one.
+        let image = self.engine.images.iter().position(|image| {
+            image
+                .exec_ranges
+                .iter()
+                .any(|&(start, end)| (start..end).contains(&(target as u64)))
+        });
+        match image {
+            Some(id) if Some(id) == self.engine.image_for_hart(self.hartid) => warn!(
+                target: log_target::EXEC,
+                "Hart {} branches from 0x{:x} into the middle of an instruction at 0x{:x}",
+                self.hartid, addr, target
             ),
-            TraceAccess::Readvf64hReg(x) => format!(
-                "f{:02}:[{:>8.4}, {:>8.4}, {:>8.4}, {:>8.4}]",
-                x,
-                f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
-                    (data & 0xffff_0000_0000_0000) >> 48,
-                    flexfloat::FfOpCvt::Fcvt16f2f,
-                    false,
-                    false
-                ) as u32),
-                f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
-                    (data & 0x0000_ffff_0000_0000) >> 32,
-                    flexfloat::FfOpCvt::Fcvt16f2f,
-                    false,
-                    false
-                ) as u32),
-                f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
-                    (data & 0x0000_0000_ffff_0000) >> 16,
-                    flexfloat::FfOpCvt::Fcvt16f2f,
-                    false,
-                    false
-                ) as u32),
-                f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
-                    (data & 0x0000_0000_0000_ffff) >> 0,
-                    flexfloat::FfOpCvt::Fcvt16f2f,
-                    false,
-                    false
-                ) as u32),
+            Some(id) => warn!(
+                target: log_target::EXEC,
+                "Hart {} branches from 0x{:x} to 0x{:x} in image {}, which it does not execute",
+                self.hartid, addr, target, id
             ),
-            TraceAccess::Writevf64hReg(x) => format!(
-                "f{:02}=[{:>8.4}, {:>8.4}, {:>8.4}, {:>8.4}]",
-                x,
-                f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
-                    (data & 0xffff_0000_0000_0000) >> 48,
-                    flexfloat::FfOpCvt::Fcvt16f2f,
-                    false,
-                    false
-                ) as u32),
-                f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
-                    (data & 0x0000_ffff_0000_0000) >> 32,
-                    flexfloat::FfOpCvt::Fcvt16f2f,
-                    false,
-                    false
-                ) as u32),
-                f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
-                    (data & 0x0000_0000_ffff_0000) >> 16,
-                    flexfloat::FfOpCvt::Fcvt16f2f,
-                    false,
-                    false
-                ) as u32),
-                f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
-                    (data & 0x0000_0000_0000_ffff) >> 0,
-                    flexfloat::FfOpCvt::Fcvt16f2f,
-                    false,
-                    false
-                ) as u32),
+            None => warn!(
+                target: log_target::EXEC,
+                "Hart {} branches from 0x{:x} to 0x{:x} outside of the executable sections",
+                self.hartid, addr, target
             ),
-            TraceAccess::Readvf64bReg(x) => format!(
+        }
+        self.fault(
+            addr,
+            format!("branches to unpredicted address 0x{:x}", target),
+        );
+    }
+
+    unsafe fn binary_trace(&self, addr: u32, inst: u32, accesses: &[TraceAccess], data: &[u64]) {
+        if let Some(n) = self.engine.trace_sample {
+            if self.state.instret % n != 0 {
+                return;
+            }
+        }
+        println!("{}", self.trace_line(addr, inst, accesses, data));
+    }
+
+    /// Render the trace line of an instruction, with the engine's renderer.
+    fn trace_line(&self, addr: u32, inst: u32, accesses: &[TraceAccess], data: &[u64]) -> String {
+        let renderer = match &self.engine.trace_renderer {
+            Some(renderer) => renderer.as_ref(),
+            None => &DefaultTraceRenderer,
+        };
+        let line = renderer.render(&TracedInst {
+            cycle: self.state.cycle,
+            instret: self.state.instret,
+            hartid: self.hartid,
+            addr,
+            inst,
+            accesses,
+            data,
+        });
+        match self.engine.source_line(addr) {
+            Some((file, lineno)) => format!("{} @ {}:{}", line, file, lineno),
+            None => line,
+        }
+    }
+
+    fn binary_wfi(&mut self) -> u32 {
+        // Terminate if execution has been stopped.
+        if self.engine.stop.load(Ordering::SeqCst) {
+            self.park();
+            return 1;
+        }
+        let mut wus = self.wakeup_state.lock().unwrap();
+        // Don't wfi if any interrupt is pending. Mip is updated before each instruction in tran.rs
+        let mie = self.binary_csr_read(riscv::Csr::Mie, 1);
+        let mip = self.binary_csr_read(riscv::Csr::Mip, 1);
+        let hartid = self.hartid - self.engine.base_hartid;
+        if mip & mie != 0 {
+            trace!(target: log_target::EXEC, " hart: {} wfi is nop. mip: {:x}", self.hartid, mip);
+            // clear a possible outstanding wakeup request
+            wus.req[hartid] = 0;
+            // Trigger IRQ check on next instruction
+            self.state.irq.sample_ctr = u32::MAX - 1;
+            return 0;
+        }
+        if let Some(callback) = &self.engine.wfi_callback {
+            std::mem::drop(wus);
+            if callback(self.hartid, self.state.cycle) {
+                trace!(target: log_target::EXEC, " hart: {} woken by callback", self.hartid);
+                self.state.irq.sample_ctr = u32::MAX - 1;
+                return 0;
+            }
+            wus = self.wakeup_state.lock().unwrap();
+        }
+        // Set own wfi.
+        self.state.wfi = true;
+        wus.wfi[hartid] = true;
+        wus.num += 1;
+        wus.threads[hartid] = Some(std::thread::current());
+        if wus.num == wus.req.len() && wus.req.iter().all(|&n| n == 0) {
+            // Let the other sleeping harts notice that everyone is sleeping.
+            for thread in wus.threads.iter().flatten() {
+                thread.unpark();
+            }
+        }
+        std::mem::drop(wus);
+        // Sleep while this hart is not requested to wake. In deterministic
+        // mode, the scheduler polls the hart instead between its turns.
+        loop {
+            match self.poll_wfi() {
+                Some(exit) => return exit as u32,
+                None if self.engine.deterministic.is_some() => {
+                    self.yield_at.store(0, Ordering::SeqCst);
+                    return 0;
+                }
+                // Wake up now and then to notice when execution has been stopped.
+                None => std::thread::park_timeout(std::time::Duration::from_millis(10)),
+            }
+        }
+    }
+
+    /// Check whether a hart sleeping in `wfi` may continue. Returns `None`
+    /// while it keeps sleeping, `Some(false)` once it has been woken up, and
+    /// `Some(true)` if execution ends because all harts are sleeping without
+    /// any outstanding wake-up request, or because it has been stopped.
+    fn poll_wfi(&mut self) -> Option<bool> {
+        let mut wus = self.wakeup_state.lock().unwrap();
+        let hartid = self.hartid - self.engine.base_hartid;
+        if wus.req[hartid] == 0 {
+            let all_asleep = wus.num == wus.req.len() && wus.req.iter().all(|&n| n == 0);
+            if all_asleep {
+                self.state.finished = true;
+                return Some(true);
+            }
+            if self.engine.stop.load(Ordering::SeqCst) {
+                std::mem::drop(wus);
+                self.park();
+                return Some(true);
+            }
+            return None;
+        }
+        // Someone woke us up --> Clear the flag
+        let cycle = wus.req[hartid];
+        self.state.cycle = std::cmp::max(self.state.cycle, cycle as u64);
+        self.state.wfi = false;
+        wus.req[hartid] = 0;
+        wus.wfi[hartid] = false;
+        wus.num -= 1;
+        // Trigger IRQ check on next instruction
+        self.state.irq.sample_ctr = u32::MAX - 1;
+        Some(false)
+    }
+
+    /// End the execution of a hart sleeping in `wfi` because execution has
+    /// been stopped. The hart has finished if the program halted, but is
+    /// merely parked if execution was stopped externally, e.g. by a timeout.
+    fn park(&mut self) {
+        self.state.wfi = true;
+        if self.engine.halted.load(Ordering::SeqCst) {
+            self.state.finished = true;
+        }
+    }
+
+    /// Check whether a hart in deterministic mode may continue, like
+    /// `poll_wfi`. A hart which waits in the cluster barrier continues once
+    /// all cores of the cluster have arrived.
+    fn poll_wait(&mut self) -> Option<bool> {
+        if self.state.wfi {
+            return self.poll_wfi();
+        }
+        let mut wait = self.barrier_wait.lock().unwrap();
+        match *wait {
+            Some(release) if self.barrier.load(Ordering::SeqCst) < release => None,
+            _ => {
+                *wait = None;
+                Some(false)
+            }
+        }
+    }
+
+    /// Whether a hart in deterministic mode sleeps in `wfi` or waits in the
+    /// cluster barrier.
+    fn is_waiting(&self) -> bool {
+        self.state.wfi || self.barrier_wait.lock().unwrap().is_some()
+    }
+
+    /// Take over the registers set with `Engine::set_reg` and `set_freg` while
+    /// the execution was paused.
+    fn take_set_regs(&mut self) {
+        let mut hart_regs = self.engine.hart_regs.lock().unwrap();
+        let regs = match hart_regs.get_mut(&self.hartid) {
+            Some(regs) => regs,
+            None => return,
+        };
+        for index in 0..32 {
+            if regs.set_regs & 1 << index != 0 {
+                self.state.regs[index] = regs.regs[index];
+            }
+            if regs.set_fregs & 1 << index != 0 {
+                self.state.fregs[index] = regs.fregs[index];
+            }
+        }
+        regs.set_regs = 0;
+        regs.set_fregs = 0;
+    }
+
+    /// Check at the start of a turn in deterministic mode whether a waiting
+    /// hart may continue. Returns nonzero to end the turn right away, which
+    /// the hart does while it keeps waiting, once it has been woken up, and
+    /// once execution ends.
+    fn binary_resume(&mut self) -> u32 {
+        if self.engine.run_gate.is_some() {
+            self.take_set_regs();
+        }
+        if !self.is_waiting() {
+            return 0;
+        }
+        if self.poll_wait() != Some(true) {
+            self.yielded.store(true, Ordering::SeqCst);
+        }
+        1
+    }
+
+    /// Print a character, buffered per hart until the end of the line.
+    fn putchar(&self, c: u8) {
+        let mut buffer = self.engine.putchar_buffer.lock().unwrap();
+        let buffer = buffer.entry(self.hartid).or_default();
+        if c == b'\n' {
+            eprintln!(
+                "{}{} hart-{:03} {} {}",
+                style::Invert,
+                color::Fg(color::White),
+                self.hartid,
+                style::Reset,
+                String::from_utf8_lossy(buffer)
+            );
+            buffer.clear();
+        } else {
+            buffer.push(c);
+        }
+    }
+
+    /// Handle a write to the HTIF `tohost` word: 1 reports that the test
+    /// passed, other odd values the number of the failed test in the upper
+    /// bits, and even values point to a syscall for the host to proxy.
+    fn htif_store(&self, value: u32) -> u32 {
+        if value == 0 {
+            return 0;
+        }
+        if value & 1 == 0 {
+            self.fault(
+                self.state.pc,
+                format!(
+                    "requests an HTIF syscall (tohost = 0x{:x}), which is not supported",
+                    value
+                ),
+            );
+            return 1;
+        }
+        let test = value >> 1;
+        if test == 0 {
+            debug!(target: log_target::EXEC, "Hart {} passes through tohost", self.hartid);
+        } else {
+            error!(
+                target: log_target::EXEC,
+                "Hart {} fails test {} through tohost", self.hartid, test
+            );
+        }
+        self.exit(test);
+        1
+    }
+
+    /// Whether an address is in the register window of the built-in UART.
+    fn in_uart(&self, addr: u32) -> bool {
+        match self.engine.uart {
+            Some(base) => addr.wrapping_sub(base) < UART_SIZE,
+            None => false,
+        }
+    }
+
+    /// Read a register of the built-in UART. It is always ready to transmit,
+    /// and never receives anything.
+    fn uart_load(&self, addr: u32) -> u32 {
+        match addr - self.engine.uart.unwrap() {
+            UART_LSR => UART_LSR_READY,
+            _ => 0,
+        }
+    }
+
+    /// Write a register of the built-in UART. Transmitted characters are
+    /// printed to stdout a line at a time, prefixed with the hart ID, so that
+    /// the lines of concurrently printing harts do not interleave.
+    fn uart_store(&self, addr: u32, value: u32, mask: u32) {
+        // A store which writes no byte of the register sends no character.
+        if addr - self.engine.uart.unwrap() != UART_TX || mask == 0 {
+            return;
+        }
+        let c = ((value & mask) >> mask.trailing_zeros()) as u8;
+        let mut buffer = self.engine.uart_buffer.lock().unwrap();
+        let line = buffer.entry(self.hartid).or_default();
+        if c == b'\n' {
+            println!("[hart {}] {}", self.hartid, String::from_utf8_lossy(line));
+            line.clear();
+        } else {
+            line.push(c);
+        }
+    }
+
+    /// Read a region of memory as seen by this hart.
+    pub fn read_mem(&self, addr: u32, len: usize) -> Vec<u8> {
+        (0..len as u32)
+            .map(|i| {
+                let addr = addr.wrapping_add(i);
+                (self.load(addr & !3, 2) >> (8 * (addr & 3))) as u8
+            })
+            .collect()
+    }
+
+    /// Call the handler of a custom instruction.
+    ///
+    /// Returns 1 if execution should terminate, 0 otherwise.
+    fn binary_custom_inst(&mut self, index: u32, raw: u32) -> u32 {
+        trace!(target: log_target::EXEC, "Hart {} custom instruction 0x{:08x}", self.hartid, raw);
+        let engine = self.engine;
+        (engine.custom_insts[index as usize].2)(self, raw);
+        engine.stop.load(Ordering::SeqCst) as u32
+    }
+
+    /// Call the callbacks of the breakpoint at an address.
+    ///
+    /// Returns 1 if execution should stop, 0 otherwise.
+    fn binary_breakpoint(&self, addr: u32) -> u32 {
+        trace!(target: log_target::EXEC, "Hart {} hits breakpoint at 0x{:x}", self.hartid, addr);
+        let ctx = BreakpointCtx { cpu: self };
+        let action = self.engine.breakpoints[&addr]
+            .iter()
+            .map(|callback| callback(&ctx))
+            .max()
+            .unwrap_or(BreakpointAction::Continue);
+        if action == BreakpointAction::Stop {
+            debug!(
+                target: log_target::EXEC,
+                "Hart {} stops execution at breakpoint 0x{:x}",
+                self.hartid,
+                addr
+            );
+            self.engine.stop.store(true, Ordering::SeqCst);
+            return 1;
+        }
+        0
+    }
+
+    /// Report an instruction to the access observer.
+    fn binary_observe_inst(&self, addr: u32) {
+        if let Some(observer) = &self.engine.access_observer {
+            observer.on_instruction(self.hartid, addr);
+        }
+    }
+
+    /// Take a loop without side effects, and fault once the hart spun in it
+    /// with the same registers for the livelock threshold.
+    ///
+    /// Reading a stream register while SSRs are enabled makes progress, even
+    /// though the registers stay the same.
+    fn binary_spin(&mut self, addr: u32) {
+        let threshold = match self.engine.livelock_threshold {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.state.regs.hash(&mut hasher);
+        self.state.fregs.hash(&mut hasher);
+        let regs_hash = hasher.finish();
+        if self.spin.addr == addr && self.spin.regs_hash == regs_hash && self.state.ssr_enable == 0
+        {
+            self.spin.count += 1;
+        } else {
+            self.spin = SpinState {
+                addr,
+                regs_hash,
+                count: 1,
+            };
+        }
+        if self.spin.count == threshold {
+            self.fault(
+                addr,
+                format!(
+                    "spins in a loop without side effects for {} iterations (livelock suspected)",
+                    threshold
+                ),
+            );
+        }
+    }
+
+    /// Dispatch an `ecall` on the syscall number in `a7`.
+    ///
+    /// Returns 1 if execution should terminate, 0 otherwise.
+    fn binary_ecall(&mut self) -> u32 {
+        let num = self.state.regs[17];
+        let mut args = [0; 7];
+        args.copy_from_slice(&self.state.regs[10..17]);
+        trace!(target: log_target::EXEC, "Hart {} syscall {} {:x?}", self.hartid, num, args);
+        let ret = match (self.engine.syscalls.get(&num), num) {
+            (Some(handler), _) => handler(self, args),
+            (None, SYS_WRITE) => self.sys_write(args[0], args[1], args[2]),
+            (None, SYS_READ) => self.sys_read(args[0], args[1], args[2]),
+            (None, SYS_OPEN) => self.sys_open(args[0], args[1], args[2]),
+            (None, SYS_OPENAT) if args[0] as i32 == AT_FDCWD => {
+                self.sys_open(args[1], args[2], args[3])
+            }
+            (None, SYS_OPENAT) => errno(EBADF),
+            (None, SYS_CLOSE) => self.sys_close(args[0]),
+            (None, SYS_LSEEK) => self.sys_lseek(args[0], args[1] as i32, args[2]),
+            (None, SYS_FSTAT) => self.sys_fstat(args[0], args[1]),
+            (None, SYS_BRK) => self.sys_brk(args[0]),
+            (None, SYS_EXIT) | (None, SYS_EXIT_GROUP) => {
+                self.exit(args[0]);
+                self.state.finished = true;
+                return 1;
+            }
+            (None, _) => {
+                self.fault(self.state.pc, format!("issued unknown syscall {}", num));
+                return 1;
+            }
+        };
+        self.state.regs[10] = ret;
+        self.engine.stop.load(Ordering::SeqCst) as u32
+    }
+
+    /// Write to stdout or stderr, which are printed with the hart ID in front
+    /// of each line, or to a file.
+    fn sys_write(&self, fd: u32, buf: u32, len: u32) -> u32 {
+        let data = self.read_mem(buf, len as usize);
+        if fd == 1 || fd == 2 {
+            for c in data {
+                self.putchar(c);
+            }
+            return len;
+        }
+        match self.engine.files.lock().unwrap().get_mut(&fd) {
+            Some(file) => file.write_all(&data).map_or_else(io_errno, |_| len),
+            None => errno(EBADF),
+        }
+    }
+
+    /// Read from the host's stdin or from a file.
+    fn sys_read(&self, fd: u32, buf: u32, len: u32) -> u32 {
+        let mut data = vec![0; len as usize];
+        let result = match fd {
+            0 => std::io::stdin().read(&mut data),
+            _ => match self.engine.files.lock().unwrap().get_mut(&fd) {
+                Some(file) => file.read(&mut data),
+                None => return errno(EBADF),
+            },
+        };
+        match result {
+            Ok(n) => {
+                self.write_mem(buf, &data[..n]);
+                n as u32
+            }
+            Err(e) => io_errno(e),
+        }
+    }
+
+    /// Open a file below the file system root, with the newlib `O_*` flags.
+    fn sys_open(&self, path: u32, flags: u32, mode: u32) -> u32 {
+        let root = match &self.engine.fs_root {
+            Some(root) => root,
+            None => return errno(EACCES),
+        };
+        let path = String::from_utf8_lossy(&self.read_c_str(path)).into_owned();
+        let path = match sandboxed_path(root, &path) {
+            Some(path) => path,
+            None => return errno(EACCES),
+        };
+        let mut options = fs::OpenOptions::new();
+        options
+            .read(flags & O_ACCMODE != O_WRONLY)
+            .write(flags & O_ACCMODE != O_RDONLY)
+            .append(flags & O_APPEND != 0)
+            .truncate(flags & O_TRUNC != 0);
+        match (flags & O_CREAT != 0, flags & O_EXCL != 0) {
+            (true, true) => options.create_new(true),
+            (true, false) => options.create(true),
+            _ => &mut options,
+        };
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, mode & 0o777);
+        debug!(
+            target: log_target::EXEC,
+            "Hart {} opens {} with flags 0x{:x}",
+            self.hartid,
+            path.display(),
+            flags
+        );
+        let file = match options.open(&path) {
+            Ok(file) => file,
+            Err(e) => return io_errno(e),
+        };
+        let mut files = self.engine.files.lock().unwrap();
+        let fd = (3..).find(|fd| !files.contains_key(fd)).unwrap();
+        files.insert(fd, file);
+        fd
+    }
+
+    fn sys_close(&self, fd: u32) -> u32 {
+        if fd < 3 || self.engine.files.lock().unwrap().remove(&fd).is_some() {
+            0
+        } else {
+            errno(EBADF)
+        }
+    }
+
+    fn sys_lseek(&self, fd: u32, offset: i32, whence: u32) -> u32 {
+        let pos = match whence {
+            0 if offset >= 0 => SeekFrom::Start(offset as u64),
+            1 => SeekFrom::Current(offset as i64),
+            2 => SeekFrom::End(offset as i64),
+            _ => return errno(EINVAL),
+        };
+        match self.engine.files.lock().unwrap().get_mut(&fd) {
+            Some(file) => file.seek(pos).map_or_else(io_errno, |pos| pos as u32),
+            None if fd < 3 => errno(ESPIPE),
+            None => errno(EBADF),
+        }
+    }
+
+    /// Fill in the newlib `struct stat` of stdio, which newlib uses to decide
+    /// whether to line-buffer it, or of a file.
+    fn sys_fstat(&self, fd: u32, buf: u32) -> u32 {
+        let mut stat = [0u8; STAT_SIZE];
+        let mut put = |offset: usize, value: u64, len: usize| {
+            stat[offset..offset + len].copy_from_slice(&value.to_le_bytes()[..len]);
+        };
+        if fd < 3 {
+            // A character device, readable and writable by the owner.
+            put(16, 0o020620, 4);
+        } else {
+            let meta = match self.engine.files.lock().unwrap().get(&fd) {
+                Some(file) => file.metadata(),
+                None => return errno(EBADF),
+            };
+            let meta = match meta {
+                Ok(meta) => meta,
+                Err(e) => return io_errno(e),
+            };
+            let kind = if meta.is_dir() { 0o040000 } else { 0o100000 };
+            let perm = std::os::unix::fs::PermissionsExt::mode(&meta.permissions()) & 0o777;
+            put(16, kind | perm as u64, 4);
+            put(20, 1, 4);
+            put(48, meta.len(), 8);
+            put(64, (meta.len() + 511) / 512, 8);
+        }
+        put(56, 4096, 4);
+        self.write_mem(buf, &stat);
+        0
+    }
+
+    /// Set the program break within the heap, and return the new break, or
+    /// the current one if `addr` is zero or outside of the heap.
+    fn sys_brk(&self, addr: u32) -> u32 {
+        match self.engine.heap {
+            Some((start, end)) if (start..=end).contains(&addr) => {
+                self.engine.brk.store(addr, Ordering::SeqCst);
+                addr
+            }
+            _ => self.engine.brk.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Read a null-terminated string from memory, without the terminator.
+    fn read_c_str(&self, addr: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        for i in 0..4096 {
+            match self.read_mem(addr.wrapping_add(i), 1)[0] {
+                0 => break,
+                c => data.push(c),
+            }
+        }
+        data
+    }
+
+    /// Write a region of memory as seen by this hart.
+    fn write_mem(&self, addr: u32, data: &[u8]) {
+        for (i, &byte) in data.iter().enumerate() {
+            let addr = addr.wrapping_add(i as u32);
+            let shift = 8 * (addr & 3);
+            self.store(addr & !3, (byte as u32) << shift, 0xff << shift, 0);
+        }
+    }
+
+    fn binary_check_clint(&self) -> u32 {
+        // read the clint software interrupt and return 1 if interrupt pending
+        let hartid = self.hartid;
+        return (self.clint[(hartid / 32) as usize].load(Ordering::SeqCst) & (1 << (hartid % 32)))
+            >> (hartid % 32);
+    }
+
+    fn binary_check_cl_clint(&self) -> u32 {
+        // read the cluster-local clint software interrupt and return 1 if interrupt pending
+        let hartid = self.hartid - self.cluster_base_hartid;
+        return (self.cl_clint.load(Ordering::SeqCst) as u32 & (1 << (hartid % 32)))
+            >> (hartid % 32);
+    }
+
+    /// A simple barrier across all cores in the cluster.
+    ///
+    /// Uses an atomic barrier flag shared across all CPU threads in a cluster.
+    /// Core 0 coordinates. In a first phase, it waits until all cores but
+    /// itself have bumped the flag, then bumps itself and waits for all cores
+    /// to make it through.
+    fn cluster_barrier(&self) {
+        let core_id = self.hartid - self.cluster_base_hartid;
+        let core_num = self.num_cores;
+        // Spin until the barrier reaches a state, or give up if execution
+        // has been stopped, for example because a hart never arrives.
+        let wait = |done: &dyn Fn(usize) -> bool| {
+            while !done(self.barrier.load(Ordering::Relaxed)) {
+                if self.engine.stop.load(Ordering::Relaxed) {
+                    return false;
+                }
+                std::thread::yield_now();
+            }
+            true
+        };
+        if core_id == 0 {
+            if !wait(&|n| n >= core_num - 1) {
+                return;
+            }
+            self.barrier.fetch_add(1, Ordering::Relaxed);
+            if !wait(&|n| n >= 2 * core_num - 1) {
+                return;
+            }
+            self.barrier.store(0, Ordering::Relaxed);
+        } else {
+            if !wait(&|n| n < core_num) {
+                return;
+            }
+            self.barrier.fetch_add(1, Ordering::Relaxed);
+            if !wait(&|n| n >= core_num) {
+                return;
+            }
+            self.barrier.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Arrive at the cluster barrier without waiting for the other cores,
+    /// for deterministic mode. The barrier flag counts the arrivals, and the
+    /// hart waits until it reaches the end of the current round.
+    fn arrive_at_barrier(&self) {
+        let arrived = self.barrier.fetch_add(1, Ordering::SeqCst);
+        let release = (arrived / self.num_cores + 1) * self.num_cores;
+        *self.barrier_wait.lock().unwrap() = Some(release);
+        self.yield_at.store(0, Ordering::SeqCst);
+    }
+
+    fn wake(&self, hart: u32) {
+        // Lock is released once out of scope
+        let mut wus = self.wakeup_state.lock().unwrap();
+        let harts = if hart as i32 == -1 {
+            0..wus.req.len()
+        } else {
+            hart as usize..(hart as usize + 1).min(wus.req.len())
+        };
+        for i in harts {
+            wus.req[i] = self.state.cycle + 1;
+            if let Some(thread) = &wus.threads[i] {
+                thread.unpark();
+            }
+        }
+        trace!(
+            target: log_target::EXEC,
+            "[{}] wake num: {:?} req: {:?} wfi: {:?}",
+            self.hartid,
+            wus.num,
+            wus.req,
+            wus.wfi
+        );
+    }
+
+    /*
+     * Flexfloat Conversions
+     */
+    pub fn binary_fp64_op_cvt_to_f(
+        rs1: u64,
+        op: flexfloat::FfOpCvt,
+        fpmode_src: bool,
+        fpmode_dst: bool,
+    ) -> u64 {
+        unsafe { flexfloat::ff_instruction_cvt_to_d(rs1, op, fpmode_src, fpmode_dst) }
+    }
+
+    pub fn binary_fp32_op_cvt_to_f(
+        rs1: u64,
+        op: flexfloat::FfOpCvt,
+        fpmode_src: bool,
+        fpmode_dst: bool,
+    ) -> i32 {
+        unsafe { flexfloat::ff_instruction_cvt_to_s(rs1, op, fpmode_src, fpmode_dst) }
+    }
+
+    pub fn binary_fp16_op_cvt_to_f(
+        rs1: u64,
+        op: flexfloat::FfOpCvt,
+        fpmode_src: bool,
+        fpmode_dst: bool,
+    ) -> u16 {
+        unsafe { flexfloat::ff_instruction_cvt_to_h(rs1, op, fpmode_src, fpmode_dst) }
+    }
+
+    pub fn binary_fp8_op_cvt_to_f(
+        rs1: u64,
+        op: flexfloat::FfOpCvt,
+        fpmode_src: bool,
+        fpmode_dst: bool,
+    ) -> u8 {
+        unsafe { flexfloat::ff_instruction_cvt_to_b(rs1, op, fpmode_src, fpmode_dst) }
+    }
+
+    /*
+     * Flexfloat Comparisons
+     */
+    pub fn binary_fp16_op_cmp(
+        rs1: u16,
+        rs2: u16,
+        op: flexfloat::FlexfloatOpCmp,
+        fpmode_dst: bool,
+    ) -> bool {
+        unsafe { flexfloat::ff_instruction_cmp_h(rs1, rs2, op, fpmode_dst) }
+    }
+
+    pub fn binary_fp8_op_cmp(
+        rs1: u8,
+        rs2: u8,
+        op: flexfloat::FlexfloatOpCmp,
+        fpmode_dst: bool,
+    ) -> bool {
+        unsafe { flexfloat::ff_instruction_cmp_b(rs1, rs2, op, fpmode_dst) }
+    }
+
+    /*
+     * Flexfloat Operations
+     */
+    pub fn binary_fp16_op(
+        rs1: u16,
+        rs2: u16,
+        rs3: u16,
+        op: flexfloat::FlexfloatOp,
+        fpmode_dst: bool,
+    ) -> u16 {
+        unsafe { flexfloat::ff_instruction_h(rs1, rs2, rs3, op, fpmode_dst) }
+    }
+    pub fn binary_fp8_op(
+        rs1: u8,
+        rs2: u8,
+        rs3: u8,
+        op: flexfloat::FlexfloatOp,
+        fpmode_dst: bool,
+    ) -> u8 {
+        unsafe { flexfloat::ff_instruction_b(rs1, rs2, rs3, op, fpmode_dst) }
+    }
+    pub fn binary_fp16_to_fp32_op(
+        rs1: u16,
+        rs2: u16,
+        rs3: f32,
+        op: flexfloat::FlexfloatOpExp,
+        fpmode_src: bool,
+    ) -> f32 {
+        unsafe { flexfloat::ff_fp16_to_fp32_op(rs1, rs2, rs3, op, fpmode_src) }
+    }
+    pub fn binary_fp8_to_fp16_op(
+        rs1: u8,
+        rs2: u8,
+        rs3: u16,
+        op: flexfloat::FlexfloatOpExp,
+        fpmode_src: bool,
+        fpmode_dst: bool,
+    ) -> u16 {
+        unsafe { flexfloat::ff_fp8_to_fp16_op(rs1, rs2, rs3, op, fpmode_src, fpmode_dst) }
+    }
+    pub fn binary_fp8_to_fp32_op(
+        rs1: u8,
+        rs2: u8,
+        rs3: f32,
+        op: flexfloat::FlexfloatOpExp,
+        fpmode_src: bool,
+    ) -> f32 {
+        unsafe { flexfloat::ff_fp8_to_fp32_op(rs1, rs2, rs3, op, fpmode_src) }
+    }
+}
+
+/// A single register or memory access as recorded in a trace.
+#[derive(Debug, Clone, Copy)]
+#[repr(C, u8)]
+pub enum TraceAccess {
+    ReadMem,
+    ReadReg(u8),
+    ReadFReg(u8),
+    Readf8Reg(u8),
+    Readf16Reg(u8),
+    ReadF32Reg(u8),
+    Readvf64sReg(u8),
+    Readvf64hReg(u8),
+    Readvf64bReg(u8),
+    WriteMem,
+    WriteReg(u8),
+    WriteFReg(u8),
+    Writef8Reg(u8),
+    Writef16Reg(u8),
+    WriteF32Reg(u8),
+    Writevf64sReg(u8),
+    Writevf64hReg(u8),
+    Writevf64bReg(u8),
+    RMWMem,
+}
+
+impl TraceAccess {
+    /// Format the access and the accessed data as an argument of a trace
+    /// line.
+    pub fn format(self, data: u64) -> String {
+        // The narrow floating-point formats are converted by FlexFloat.
+        unsafe {
+            match self {
+                TraceAccess::ReadMem => format!("RA:{:08x}", data as u32),
+                TraceAccess::WriteMem => format!("WA:{:08x}", data as u32),
+                TraceAccess::RMWMem => format!("AMO:{:08x}", data as u32),
+                TraceAccess::ReadReg(x) => format!("x{}:{:08x}", x, data as u32),
+                TraceAccess::WriteReg(x) => format!("x{}={:08x}", x, data as u32),
+                TraceAccess::ReadFReg(x) => format!("f{:02}:{:>16.6}", x, f64::from_bits(data)),
+                TraceAccess::WriteFReg(x) => format!("f{:02}={:>16.6}", x, f64::from_bits(data)),
+                TraceAccess::ReadF32Reg(x) => {
+                    format!("f{:02}:{:>12.4}", x, f32::from_bits(data as u32))
+                }
+                TraceAccess::WriteF32Reg(x) => {
+                    format!("f{:02}={:>12.4}", x, f32::from_bits(data as u32))
+                }
+                TraceAccess::Readf8Reg(x) => format!(
+                    "f{:02}:[{:>5.3}]",
+                    x,
+                    f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
+                        (data & 0x0000_0000_0000_00ff) >> 0,
+                        flexfloat::FfOpCvt::Fcvt8f2f,
+                        false,
+                        false
+                    ) as u32)
+                ),
+                TraceAccess::Writef8Reg(x) => format!(
+                    "f{:02}=[{:>5.3}]",
+                    x,
+                    f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
+                        (data & 0x0000_0000_0000_00ff) >> 0,
+                        flexfloat::FfOpCvt::Fcvt8f2f,
+                        false,
+                        false
+                    ) as u32),
+                ),
+                TraceAccess::Readf16Reg(x) => format!(
+                    "f{:02}=[{:>8.4}]",
+                    x,
+                    f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
+                        (data & 0x0000_0000_0000_ffff) >> 0,
+                        flexfloat::FfOpCvt::Fcvt16f2f,
+                        false,
+                        false
+                    ) as u32),
+                ),
+                TraceAccess::Writef16Reg(x) => format!(
+                    "f{:02}=[{:>5.3}]",
+                    x,
+                    f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
+                        (data & 0x0000_0000_0000_00ff) >> 0,
+                        flexfloat::FfOpCvt::Fcvt16f2f,
+                        false,
+                        false
+                    ) as u32),
+                ),
+                TraceAccess::Readvf64sReg(x) => format!(
+                    "f{:02}:[{:>12.4}, {:>12.4}]",
+                    x,
+                    f32::from_bits((data >> 32) as u32),
+                    f32::from_bits((data) as u32)
+                ),
+                TraceAccess::Writevf64sReg(x) => format!(
+                    "f{:02}=[{:>12.4}, {:>12.4}]",
+                    x,
+                    f32::from_bits((data >> 32) as u32),
+                    f32::from_bits((data) as u32)
+                ),
+                TraceAccess::Readvf64hReg(x) => format!(
+                    "f{:02}:[{:>8.4}, {:>8.4}, {:>8.4}, {:>8.4}]",
+                    x,
+                    f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
+                        (data & 0xffff_0000_0000_0000) >> 48,
+                        flexfloat::FfOpCvt::Fcvt16f2f,
+                        false,
+                        false
+                    ) as u32),
+                    f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
+                        (data & 0x0000_ffff_0000_0000) >> 32,
+                        flexfloat::FfOpCvt::Fcvt16f2f,
+                        false,
+                        false
+                    ) as u32),
+                    f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
+                        (data & 0x0000_0000_ffff_0000) >> 16,
+                        flexfloat::FfOpCvt::Fcvt16f2f,
+                        false,
+                        false
+                    ) as u32),
+                    f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
+                        (data & 0x0000_0000_0000_ffff) >> 0,
+                        flexfloat::FfOpCvt::Fcvt16f2f,
+                        false,
+                        false
+                    ) as u32),
+                ),
+                TraceAccess::Writevf64hReg(x) => format!(
+                    "f{:02}=[{:>8.4}, {:>8.4}, {:>8.4}, {:>8.4}]",
+                    x,
+                    f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
+                        (data & 0xffff_0000_0000_0000) >> 48,
+                        flexfloat::FfOpCvt::Fcvt16f2f,
+                        false,
+                        false
+                    ) as u32),
+                    f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
+                        (data & 0x0000_ffff_0000_0000) >> 32,
+                        flexfloat::FfOpCvt::Fcvt16f2f,
+                        false,
+                        false
+                    ) as u32),
+                    f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
+                        (data & 0x0000_0000_ffff_0000) >> 16,
+                        flexfloat::FfOpCvt::Fcvt16f2f,
+                        false,
+                        false
+                    ) as u32),
+                    f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
+                        (data & 0x0000_0000_0000_ffff) >> 0,
+                        flexfloat::FfOpCvt::Fcvt16f2f,
+                        false,
+                        false
+                    ) as u32),
+                ),
+                TraceAccess::Readvf64bReg(x) => format!(
                 "f{:02}:[{:>5.3}, {:>5.3}, {:>5.3}, {:>5.3}, {:>5.3}, {:>5.3}, {:>5.3}, {:>5.3}]",
                 x,
                 f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
@@ -1228,7 +6169,7 @@ impl<'a, 'b> Cpu<'a, 'b> {
                     false
                 ) as u32),
             ),
-            TraceAccess::Writevf64bReg(x) => format!(
+                TraceAccess::Writevf64bReg(x) => format!(
                 "f{:02}=[{:>5.3}, {:>5.3}, {:>5.3}, {:>5.3}, {:>5.3}, {:>5.3}, {:>5.3}, {:>5.3}]",
                 x,
                 f32::from_bits(flexfloat::ff_instruction_cvt_to_s(
@@ -1280,294 +6221,2935 @@ impl<'a, 'b> Cpu<'a, 'b> {
                     false
                 ) as u32),
             ),
+            }
+        }
+    }
+}
+
+/// An instruction executed by a hart, as passed to a `TraceRenderer`.
+#[derive(Debug, Clone, Copy)]
+pub struct TracedInst<'a> {
+    /// The cycle of the hart.
+    pub cycle: u64,
+    /// The number of instructions the hart retired.
+    pub instret: u64,
+    /// The hart which executed the instruction.
+    pub hartid: usize,
+    /// The address of the instruction.
+    pub addr: u32,
+    /// The raw instruction.
+    pub inst: u32,
+    /// The register and memory accesses the instruction performs.
+    pub accesses: &'a [TraceAccess],
+    /// The accessed data, one value per access.
+    pub data: &'a [u64],
+}
+
+/// Renders the lines of the execution trace.
+pub trait TraceRenderer: Send + Sync {
+    /// Render the line of an instruction executed by a hart.
+    fn render(&self, inst: &TracedInst) -> String;
+}
+
+/// The built-in trace format, with the cycle, the retired instructions, the
+/// hart ID, the address, the accesses, and the raw instruction.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultTraceRenderer;
+
+impl TraceRenderer for DefaultTraceRenderer {
+    fn render(&self, inst: &TracedInst) -> String {
+        let args: Vec<_> = inst
+            .accesses
+            .iter()
+            .zip(inst.data.iter())
+            .map(|(&access, &data)| access.format(data))
+            .collect();
+        format!(
+            "{:08} {:08} {:04} {:08x}  {:38}  # DASM({:08x})",
+            inst.cycle,
+            inst.instret,
+            inst.hartid,
+            inst.addr,
+            args.join(" "),
+            inst.inst
+        )
+    }
+}
+
+/// Which type of AMO to execute.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub enum AtomicOp {
+    Amoadd,
+    Amoxor,
+    Amoor,
+    Amoand,
+    Amomin,
+    Amomax,
+    Amominu,
+    Amomaxu,
+    Amoswap,
+    ScW,
+}
+
+/// Which floating-point operation to execute with exact rounding.
+#[derive(Debug, Clone, Copy)]
+#[repr(u8)]
+pub enum FpOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Sqrt,
+    Madd,
+    Msub,
+    Nmsub,
+    Nmadd,
+    Min,
+    Max,
+    Eq,
+    Lt,
+    Le,
+    /// Convert to a signed integer.
+    CvtToW,
+    /// Convert to an unsigned integer.
+    CvtToWu,
+    /// Convert from a signed integer.
+    CvtFromW,
+    /// Convert from an unsigned integer.
+    CvtFromWu,
+    /// Convert between single and double precision.
+    CvtToOther,
+}
+
+/// Unbox a single-precision value, treating improperly boxed values as NaN.
+fn unbox_f32(value: u64) -> Sf32 {
+    if value >> 32 == 0xffff_ffff {
+        Sf32(value as u32)
+    } else {
+        Sf32::NAN
+    }
+}
+
+/// Execute a single-precision operation in software.
+unsafe fn fp_op_f32(op: FpOp, rm: u8, rs1: u64, rs2: u64, rs3: u64) -> u64 {
+    let (a, b, c) = (unbox_f32(rs1), unbox_f32(rs2), unbox_f32(rs3));
+    let is_nan = |x: Sf32| x.0 & 0x7fff_ffff > 0x7f80_0000;
+    let result = match op {
+        FpOp::Add => softfloat::f32_add(a, b),
+        FpOp::Sub => softfloat::f32_sub(a, b),
+        FpOp::Mul => softfloat::f32_mul(a, b),
+        FpOp::Div => softfloat::f32_div(a, b),
+        FpOp::Sqrt => softfloat::f32_sqrt(a),
+        FpOp::Madd => softfloat::f32_mulAdd(a, b, c),
+        FpOp::Msub => softfloat::f32_mulAdd(a, b, c.negate()),
+        FpOp::Nmsub => softfloat::f32_mulAdd(a.negate(), b, c),
+        FpOp::Nmadd => softfloat::f32_mulAdd(a.negate(), b, c.negate()),
+        FpOp::Min | FpOp::Max => {
+            if softfloat::f32_is_signaling_nan(a) || softfloat::f32_is_signaling_nan(b) {
+                softfloat::raise_flags(softfloat::FLAG_INVALID);
+            }
+            if is_nan(a) && is_nan(b) {
+                Sf32::NAN
+            } else if is_nan(a) {
+                b
+            } else if is_nan(b) {
+                a
+            } else {
+                // Order -0 below +0.
+                let a_lt_b = softfloat::f32_lt(a, b) || (a.0 >> 31 == 1 && b.0 >> 31 == 0);
+                if a_lt_b != matches!(op, FpOp::Max) {
+                    a
+                } else {
+                    b
+                }
+            }
+        }
+        FpOp::Eq => return softfloat::f32_eq(a, b) as u64,
+        FpOp::Lt => return softfloat::f32_lt(a, b) as u64,
+        FpOp::Le => return softfloat::f32_le(a, b) as u64,
+        FpOp::CvtToW => return softfloat::f32_to_i32(a, rm, true) as u32 as u64,
+        FpOp::CvtToWu => return softfloat::f32_to_u32(a, rm, true) as u64,
+        FpOp::CvtFromW => softfloat::i32_to_f32(rs1 as i32),
+        FpOp::CvtFromWu => softfloat::u32_to_f32(rs1 as u32),
+        FpOp::CvtToOther => return softfloat::f32_to_f64(a).0,
+    };
+    Sf64::from(result).0
+}
+
+/// Execute a double-precision operation in software.
+unsafe fn fp_op_f64(op: FpOp, rm: u8, rs1: u64, rs2: u64, rs3: u64) -> u64 {
+    let (a, b, c) = (Sf64(rs1), Sf64(rs2), Sf64(rs3));
+    let is_nan = |x: Sf64| x.0 & 0x7fff_ffff_ffff_ffff > 0x7ff0_0000_0000_0000;
+    let result = match op {
+        FpOp::Add => softfloat::f64_add(a, b),
+        FpOp::Sub => softfloat::f64_sub(a, b),
+        FpOp::Mul => softfloat::f64_mul(a, b),
+        FpOp::Div => softfloat::f64_div(a, b),
+        FpOp::Sqrt => softfloat::f64_sqrt(a),
+        FpOp::Madd => softfloat::f64_mulAdd(a, b, c),
+        FpOp::Msub => softfloat::f64_mulAdd(a, b, c.negate()),
+        FpOp::Nmsub => softfloat::f64_mulAdd(a.negate(), b, c),
+        FpOp::Nmadd => softfloat::f64_mulAdd(a.negate(), b, c.negate()),
+        FpOp::Min | FpOp::Max => {
+            if softfloat::f64_is_signaling_nan(a) || softfloat::f64_is_signaling_nan(b) {
+                softfloat::raise_flags(softfloat::FLAG_INVALID);
+            }
+            if is_nan(a) && is_nan(b) {
+                Sf64::NAN
+            } else if is_nan(a) {
+                b
+            } else if is_nan(b) {
+                a
+            } else {
+                // Order -0 below +0.
+                let a_lt_b = softfloat::f64_lt(a, b) || (a.0 >> 63 == 1 && b.0 >> 63 == 0);
+                if a_lt_b != matches!(op, FpOp::Max) {
+                    a
+                } else {
+                    b
+                }
+            }
+        }
+        FpOp::Eq => return softfloat::f64_eq(a, b) as u64,
+        FpOp::Lt => return softfloat::f64_lt(a, b) as u64,
+        FpOp::Le => return softfloat::f64_le(a, b) as u64,
+        FpOp::CvtToW => return softfloat::f64_to_i32(a, rm, true) as u32 as u64,
+        FpOp::CvtToWu => return softfloat::f64_to_u32(a, rm, true) as u64,
+        FpOp::CvtFromW => softfloat::i32_to_f64(rs1 as i32),
+        FpOp::CvtFromWu => softfloat::u32_to_f64(rs1 as u32),
+        FpOp::CvtToOther => return Sf64::from(softfloat::f64_to_f32(a)).0,
+    };
+    result.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_protocol_snitch() {
+        let p = ExitProtocol::Snitch;
+        assert_eq!(p.exit_code(0), None);
+        assert_eq!(p.exit_code(6), None);
+        assert_eq!(p.exit_code(1), Some(0));
+        assert_eq!(p.exit_code(7), Some(3));
+        assert!(!p.halts(0));
+        assert!(!p.halts(7));
+
+        // The first failing cluster determines the exit code.
+        assert_eq!(p.aggregate_exit_code(vec![]), None);
+        assert_eq!(p.aggregate_exit_code(vec![0, 6]), None);
+        assert_eq!(p.aggregate_exit_code(vec![0, 1, 1]), Some(0));
+        assert_eq!(p.aggregate_exit_code(vec![1, 7, 5, 1]), Some(3));
+    }
+
+    #[test]
+    fn exit_protocol_nonzero() {
+        let p = ExitProtocol::Nonzero;
+        assert_eq!(p.exit_code(0), None);
+        assert_eq!(p.exit_code(6), Some(6));
+        assert_eq!(p.exit_code(7), Some(7));
+        assert!(!p.halts(0));
+        assert!(p.halts(6));
+    }
+
+    #[test]
+    fn exit_protocol_parse() {
+        assert_eq!(
+            "snitch".parse::<ExitProtocol>().unwrap(),
+            ExitProtocol::Snitch
+        );
+        assert_eq!(
+            "nonzero".parse::<ExitProtocol>().unwrap(),
+            ExitProtocol::Nonzero
+        );
+        assert!("other".parse::<ExitProtocol>().is_err());
+    }
+
+    #[test]
+    fn exact_fp_rounding() {
+        let third = |rm| unsafe {
+            softfloat::set_rounding_mode(rm);
+            softfloat::set_flags(0);
+            let one = Sf64::from(1.0f32).0;
+            let three = Sf64::from(3.0f32).0;
+            fp_op_f32(FpOp::Div, rm, one, three, 0) as u32
+        };
+        assert_eq!(third(softfloat::ROUND_NEAR_EVEN), 0x3eaaaaab);
+        assert_eq!(third(softfloat::ROUND_MIN_MAG), 0x3eaaaaaa);
+        assert_eq!(third(softfloat::ROUND_MAX), 0x3eaaaaab);
+        assert_eq!(unsafe { softfloat::get_flags() }, softfloat::FLAG_INEXACT);
+    }
+
+    #[test]
+    fn exact_fp_flags() {
+        unsafe {
+            softfloat::set_rounding_mode(softfloat::ROUND_NEAR_EVEN);
+            softfloat::set_flags(0);
+            let one = 1.0f64.to_bits();
+            let result = fp_op_f64(FpOp::Div, 0, one, 0, 0);
+            assert_eq!(result, f64::INFINITY.to_bits());
+            assert_eq!(softfloat::get_flags(), softfloat::FLAG_INFINITE);
+
+            softfloat::set_flags(0);
+            let max = f64::MAX.to_bits();
+            fp_op_f64(FpOp::Mul, 0, max, max, 0);
+            assert_eq!(
+                softfloat::get_flags(),
+                softfloat::FLAG_OVERFLOW | softfloat::FLAG_INEXACT
+            );
+
+            // Comparisons with signaling NaNs, and unboxed single-precision
+            // values, which read as canonical NaN.
+            softfloat::set_flags(0);
+            let snan = 0x7ff0_0000_0000_0001;
+            assert_eq!(fp_op_f64(FpOp::Eq, 0, snan, one, 0), 0);
+            assert_eq!(softfloat::get_flags(), softfloat::FLAG_INVALID);
+            softfloat::set_flags(0);
+            assert_eq!(fp_op_f32(FpOp::Eq, 0, 1, 1, 0), 0);
+            assert_eq!(softfloat::get_flags(), 0);
+            let min = fp_op_f32(FpOp::Min, 0, 1, Sf64::from(2.0f32).0, 0);
+            assert_eq!(min, Sf64::from(2.0f32).0);
+
+            // Saturating conversions to integers.
+            softfloat::set_flags(0);
+            let big = 1e20f64.to_bits();
+            assert_eq!(fp_op_f64(FpOp::CvtToW, 0, big, 0, 0), 0x7fff_ffff);
+            assert_eq!(softfloat::get_flags(), softfloat::FLAG_INVALID);
+        }
+    }
+
+    #[test]
+    fn invalid_rounding_mode() {
+        let run = |insts: &[u32]| {
+            let mut engine = EngineBuilder::new().build().unwrap();
+            engine.translate_elf(&kernel(insts)).unwrap();
+            engine.execute()
+        };
+        let exit = [0x400003b7, 0x00100e13, 0x03c3a023, 0x10500073];
+
+        // fsrmi 1; fadd.s ft0, ft0, ft0, dyn; <exit>
+        let valid = [&[0x0020d073, 0x00007053][..], &exit].concat();
+        assert_eq!(run(&valid).unwrap(), 0);
+
+        // fsrmi 5; fadd.s ft0, ft0, ft0, dyn; <exit>
+        let dynamic = [&[0x0022d073, 0x00007053][..], &exit].concat();
+        let e = run(&dynamic).unwrap_err();
+        let fault = e.downcast_ref::<HartFault>().unwrap();
+        assert_eq!(fault.pc, 0x80010004);
+        assert!(fault.reason.contains("illegal instruction"));
+
+        // fadd.s ft0, ft0, ft0, <rm 5>; <exit>
+        let reserved = [&[0x00005053][..], &exit].concat();
+        let e = run(&reserved).unwrap_err();
+        let fault = e.downcast_ref::<HartFault>().unwrap();
+        assert_eq!(fault.pc, 0x80010000);
+        assert!(fault.reason.contains("illegal instruction"));
+    }
+
+    #[test]
+    fn saturating_conversions() {
+        // The results of out-of-range conversions to integers from the table
+        // in the F extension chapter of the ISA manual, which all raise the
+        // invalid flag.
+        let cases = [
+            (f64::INFINITY, 0x7fff_ffff, 0xffff_ffff),
+            (f64::NEG_INFINITY, 0x8000_0000, 0),
+            (f64::NAN, 0x7fff_ffff, 0xffff_ffff),
+            (-f64::NAN, 0x7fff_ffff, 0xffff_ffff),
+            (3e9, 0x7fff_ffff, 3_000_000_000),
+            (-3e9, 0x8000_0000, 0),
+            (-1.0, 0xffff_ffff, 0),
+        ];
+        unsafe {
+            for &(x, w, wu) in &cases {
+                for &double in &[false, true] {
+                    let convert = |op| {
+                        softfloat::set_flags(0);
+                        let result = if double {
+                            fp_op_f64(op, softfloat::ROUND_MIN_MAG, Sf64::from(x).0, 0, 0)
+                        } else {
+                            fp_op_f32(op, softfloat::ROUND_MIN_MAG, Sf64::from(x as f32).0, 0, 0)
+                        };
+                        (
+                            result as u32,
+                            softfloat::get_flags() & softfloat::FLAG_INVALID != 0,
+                        )
+                    };
+                    // Only the values out of range of a format are invalid.
+                    let (in_w, in_wu) = (w as i32 as f64 == x, wu as f64 == x);
+                    assert_eq!(convert(FpOp::CvtToW), (w, !in_w), "{} to w", x);
+                    assert_eq!(convert(FpOp::CvtToWu), (wu, !in_wu), "{} to wu", x);
+                }
+            }
+        }
+
+        // Half-precision values are converted the same way.
+        // lui t0, 0x100;
+        // li a0, 0x7c00; fmv.h.x ft0, a0; fcvt.w.h a1, ft0, rtz; sw a1, 0(t0);
+        // li a0, -0x400; fmv.h.x ft0, a0; fcvt.w.h a1, ft0, rtz; sw a1, 4(t0);
+        // li a0, 0x7e00; fmv.h.x ft0, a0; fcvt.w.h a1, ft0, rtz; sw a1, 8(t0);
+        // li a0, -0x4400; fmv.h.x ft0, a0; fcvt.wu.h a1, ft0, rtz; sw a1, 12(t0);
+        // csrr a1, fflags; sw a1, 16(t0);
+        // lui t1, 0x40000; li t2, 1; sw t2, 32(t1); wfi
+        let elf = kernel(&[
+            0x001002b7, 0x00008537, 0xc0050513, 0xf4050053, 0xc40015d3, 0x00b2a023, 0xc0000513,
+            0xf4050053, 0xc40015d3, 0x00b2a223, 0x00008537, 0xe0050513, 0xf4050053, 0xc40015d3,
+            0x00b2a423, 0xffffc537, 0xc0050513, 0xf4050053, 0xc41015d3, 0x00b2a623, 0x001025f3,
+            0x00b2a823, 0x40000337, 0x00100393, 0x02732023, 0x10500073,
+        ]);
+        let mut engine = EngineBuilder::new().build().unwrap();
+        engine.translate_elf(&elf).unwrap();
+        assert_eq!(engine.execute().unwrap(), 0);
+        let results: Vec<_> = (0..5).map(|i| engine.read_word(0x100000 + 4 * i)).collect();
+        assert_eq!(results, [0x7fff_ffff, 0x8000_0000, 0x7fff_ffff, 0, 0x10]);
+    }
+
+    #[test]
+    fn fused_multiply_add() {
+        // The translation computes the fused operations with `llvm.fma` on
+        // negated operands and canonicalizes NaN results, which the host's
+        // fused multiply-add models here.
+        let ops = [
+            (FpOp::Madd, false, false),
+            (FpOp::Msub, false, true),
+            (FpOp::Nmsub, true, false),
+            (FpOp::Nmadd, true, true),
+        ];
+        let neg32 = |x: f32, neg| if neg { -x } else { x };
+        let neg64 = |x: f64, neg| if neg { -x } else { x };
+        let grid32 = [
+            0.0,
+            -0.0,
+            1.0,
+            -1.5,
+            f32::MIN_POSITIVE,
+            f32::from_bits(0x0000_0001),
+            f32::from_bits(0x807f_ffff),
+            f32::MAX,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            f32::NAN,
+            f32::from_bits(0x7f80_0001),
+            f32::from_bits(0xffc1_2345),
+        ];
+        let grid64 = [
+            0.0,
+            -0.0,
+            1.0,
+            -1.5,
+            f64::MIN_POSITIVE,
+            f64::from_bits(0x0000_0000_0000_0001),
+            f64::from_bits(0x800f_ffff_ffff_ffff),
+            f64::MAX,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NAN,
+            f64::from_bits(0x7ff0_0000_0000_0001),
+            f64::from_bits(0xfff8_1234_5678_9abc),
+        ];
+        unsafe { softfloat::set_rounding_mode(softfloat::ROUND_NEAR_EVEN) };
+        for &(op, neg_product, neg_addend) in &ops {
+            for &a in &grid32 {
+                for &b in &grid32 {
+                    for &c in &grid32 {
+                        let model = neg32(a, neg_product).mul_add(b, neg32(c, neg_addend));
+                        let model = if model.is_nan() { f32::NAN } else { model };
+                        let soft = unsafe {
+                            fp_op_f32(op, 0, Sf64::from(a).0, Sf64::from(b).0, Sf64::from(c).0)
+                        };
+                        assert_eq!(
+                            soft,
+                            Sf64::from(model).0,
+                            "{:?}({:e}, {:e}, {:e})",
+                            op,
+                            a,
+                            b,
+                            c
+                        );
+                    }
+                }
+            }
+            for &a in &grid64 {
+                for &b in &grid64 {
+                    for &c in &grid64 {
+                        let model = neg64(a, neg_product).mul_add(b, neg64(c, neg_addend));
+                        let model = if model.is_nan() { f64::NAN } else { model };
+                        let soft =
+                            unsafe { fp_op_f64(op, 0, a.to_bits(), b.to_bits(), c.to_bits()) };
+                        assert_eq!(soft, model.to_bits(), "{:?}({:e}, {:e}, {:e})", op, a, b, c);
+                    }
+                }
+            }
+        }
+
+        // The negated forms negate the product, not the result.
+        let nmadd = |a: f32, b: f32, c: f32| unsafe {
+            fp_op_f32(
+                FpOp::Nmadd,
+                0,
+                Sf64::from(a).0,
+                Sf64::from(b).0,
+                Sf64::from(c).0,
+            ) as u32
+        };
+        assert_eq!(nmadd(0.0, 1.0, -0.0), 0.0f32.to_bits());
+        assert_eq!(nmadd(-0.0, 1.0, 0.0), 0.0f32.to_bits());
+        assert_eq!(nmadd(0.0, 1.0, 0.0), (-0.0f32).to_bits());
+
+        // The translated code stores the same signed zeros.
+        // fmv.w.x fa0/fa1/fa2 = +0.0/1.0/-0.0;
+        // fnmadd.s fa3, fa0, fa1, fa2; fnmadd.s fa4, fa2, fa1, fa0;
+        // fnmadd.s fa5, fa0, fa1, fa0; each stored to 0x80020000 onwards
+        let elf = kernel(&[
+            0x800202b7, 0x00000513, 0x3f8005b7, 0x80000637, 0xf0050553, 0xf00585d3, 0xf0060653,
+            0x60b576cf, 0xe0068353, 0x0062a023, 0x50b6774f, 0xe0070353, 0x0062a223, 0x50b577cf,
+            0xe0078353, 0x0062a423, 0x400003b7, 0x00100e13, 0x03c3a023, 0x10500073,
+        ]);
+        let mut engine = EngineBuilder::new().build().unwrap();
+        engine.translate_elf(&elf).unwrap();
+        let ir = unsafe {
+            let ir = LLVMPrintModuleToString(engine.modules[0]);
+            let ir_string = std::ffi::CStr::from_ptr(ir).to_string_lossy().into_owned();
+            LLVMDisposeMessage(ir);
+            ir_string
+        };
+        assert!(ir.contains("call float @llvm.fma.f32("));
+        assert_eq!(engine.execute().unwrap(), 0);
+        assert_eq!(engine.read_word(0x80020000), 0);
+        assert_eq!(engine.read_word(0x80020004), 0);
+        assert_eq!(engine.read_word(0x80020008), 0x80000000);
+    }
+
+    /// Create an engine with a single-core configuration.
+    fn test_engine() -> Engine {
+        init_llvm();
+        let mut engine = Engine::new(unsafe { LLVMContextCreate() });
+        engine.config = Configuration::new(1, 1, 0);
+        engine.config.bootrom.start = 0x1000;
+        engine.config.bootrom.end = 0x2000;
+        engine
+    }
+
+    /// Run a function on a hart of an engine, outside of any translated code.
+    fn with_cpu(engine: &Engine, hartid: usize, f: impl FnOnce(&mut Cpu)) {
+        with_cpus(engine, &[hartid], |cpus| f(&mut cpus[0]));
+    }
+
+    /// Run a function on harts of one cluster, outside of any translated code.
+    fn with_cpus(engine: &Engine, hartids: &[usize], f: impl FnOnce(&mut [Cpu])) {
+        let tcdm = vec![0u32; 0x8000];
+        let tcdm_ext = vec![&tcdm[0]];
+        let (barrier, cl_clint) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let wakeup_state = Mutex::new(WakeupState {
+            num: 0,
+            req: vec![0; hartids.len()],
+            wfi: vec![false; hartids.len()],
+            threads: vec![None; hartids.len()],
+        });
+        let clint = vec![AtomicU32::new(0)];
+        let exit_reg = AtomicU32::new(0);
+        let mut cpus: Vec<_> = hartids
+            .iter()
+            .map(|&hartid| {
+                Cpu::new(
+                    engine,
+                    hartid,
+                    hartids.len(),
+                    0,
+                    0,
+                    !0,
+                    SharedState {
+                        tcdm_ptr: &tcdm[0],
+                        tcdm_ext_ptr: &tcdm_ext,
+                        barrier: &barrier,
+                        wakeup_state: &wakeup_state,
+                        clint: &clint,
+                        cl_clint: &cl_clint,
+                        exit_reg: &exit_reg,
+                    },
+                )
+            })
+            .collect();
+        f(&mut cpus);
+    }
+
+    #[test]
+    fn initial_regs() {
+        let mut engine = test_engine();
+        engine.set_initial_regs(&[(2, 0x120000), (10, 7)]).unwrap();
+        assert!(engine.set_initial_regs(&[(0, 1)]).is_err());
+        assert!(engine.set_initial_regs(&[(32, 1)]).is_err());
+        with_cpu(&engine, 0, |cpu| {
+            assert_eq!(cpu.state.regs[2], 0x120000);
+            assert_eq!(cpu.state.regs[10], 7);
+            assert_eq!(cpu.state.regs[11], 0x1000);
+        });
+    }
+
+    #[test]
+    fn initial_regs_fn() {
+        let mut engine = test_engine();
+        engine.set_initial_regs_fn(Box::new(|hartid| {
+            let mut regs = [u32::max_value(); 32];
+            regs[2] = 0x120000 - 0x400 * hartid as u32;
+            regs[10] = hartid as u32;
+            regs
+        }));
+        engine.set_initial_regs(&[(11, 42)]).unwrap();
+        for hartid in 0..4 {
+            with_cpu(&engine, hartid, |cpu| {
+                assert_eq!(cpu.state.regs[0], 0);
+                assert_eq!(cpu.state.regs[2], 0x120000 - 0x400 * hartid as u32);
+                assert_eq!(cpu.state.regs[10], hartid as u32);
+                assert_eq!(cpu.state.regs[11], 42);
+            });
+        }
+    }
+
+    #[test]
+    fn binary_per_cluster() {
+        // Each hart stores `k` at 0x80020000 + 4 * hartid, and exits.
+        let store = |k: u32| {
+            kernel(&[
+                0xf1402573,
+                0x00251293,
+                0x80020337,
+                0x006282b3,
+                0x00000393 | k << 20,
+                0x0072a023,
+                0x40000337,
+                0x00100393,
+                0x02732023,
+                0x10500073,
+            ])
+        };
+        let moved = |mut elf: elf::File, addr: u64| {
+            elf.sections[0].shdr.addr = addr;
+            elf.ehdr.entry = addr;
+            elf
+        };
+        let mut engine = EngineBuilder::new()
+            .num_clusters(4)
+            .num_cores(2)
+            .build()
+            .unwrap();
+        engine.translate_elf_for_clusters(&store(10), 0..1).unwrap();
+        engine
+            .translate_elf_for_clusters(&moved(store(11), 0x80011000), 1..3)
+            .unwrap();
+        assert!(engine
+            .translate_elf_for_clusters(&moved(store(12), 0x80012000), 2..5)
+            .is_err());
+        let err = engine
+            .translate_elf_for_clusters(&moved(store(12), 0x80011020), 3..4)
+            .unwrap_err();
+        assert!(err.to_string().contains("overlaps"));
+        engine
+            .translate_elf_for_clusters(&moved(store(12), 0x80012000), 3..4)
+            .unwrap();
+        let entries: Vec<_> = engine
+            .images
+            .iter()
+            .map(|image| image.entry.as_str())
+            .collect();
+        assert_eq!(
+            entries,
+            ["execute_binary", "execute_binary_1", "execute_binary_2"]
+        );
+        assert_eq!(engine.image_for_hart(1), Some(0));
+        assert_eq!(engine.image_for_hart(5), Some(1));
+        assert_eq!(engine.image_for_hart(6), Some(2));
+        assert_eq!(engine.execute().unwrap(), 0);
+        let stored: Vec<_> = (0..8)
+            .map(|i| engine.read_word(0x80020000 + 4 * i))
+            .collect();
+        assert_eq!(stored, [10, 10, 11, 11, 11, 11, 12, 12]);
+    }
+
+    #[test]
+    fn binary_per_hart() {
+        // Each hart stores `k` at 0x80020000 + 4 * hartid, and exits.
+        let store = |k: u32, addr: u64| {
+            let mut elf = kernel(&[
+                0xf1402573,
+                0x00251293,
+                0x80020337,
+                0x006282b3,
+                0x00000393 | k << 20,
+                0x0072a023,
+                0x40000337,
+                0x00100393,
+                0x02732023,
+                0x10500073,
+            ]);
+            elf.sections[0].shdr.addr = addr;
+            elf.ehdr.entry = addr;
+            elf
+        };
+        let mut engine = EngineBuilder::new().num_cores(2).build().unwrap();
+        engine
+            .translate_elf_for_harts(&store(10, 0x80010000), Some(vec![0]))
+            .unwrap();
+
+        // An image may not overwrite the sections of another one.
+        let err = engine
+            .translate_elf_for_harts(&store(11, 0x80010010), Some(vec![1]))
+            .unwrap_err();
+        assert!(err.to_string().contains("overlaps"), "{}", err);
+        let err = engine
+            .translate_elf_for_harts(&store(11, 0x80011000), Some(vec![0]))
+            .unwrap_err();
+        assert!(err.to_string().contains("already assigned"), "{}", err);
+        engine
+            .translate_elf_for_harts(&store(11, 0x80011000), Some(vec![1]))
+            .unwrap();
+        assert_eq!(engine.image_for_hart(0), Some(0));
+        assert_eq!(engine.image_for_hart(1), Some(1));
+        assert_eq!(engine.execute().unwrap(), 0);
+        assert_eq!(engine.read_word(0x80020000), 10);
+        assert_eq!(engine.read_word(0x80020004), 11);
+    }
+
+    #[test]
+    fn get_set_reg() {
+        // add a1, a0, a0; lui t0, 0x100; sw a1, 0(t0);
+        // lui t1, 0x40000; li t2, 1; sw t2, 32(t1); wfi
+        let elf = kernel(&[
+            0x00a505b3, 0x001002b7, 0x00b2a023, 0x40000337, 0x00100393, 0x02732023, 0x10500073,
+        ]);
+        let mut engine = EngineBuilder::new().num_cores(2).build().unwrap();
+        engine.translate_elf(&elf).unwrap();
+        assert!(engine.set_reg(0, 0, 1).is_err());
+        assert!(engine.set_reg(0, 32, 1).is_err());
+        assert!(engine.set_reg(2, 10, 1).is_err());
+        engine.set_reg(0, 10, 21).unwrap();
+        engine.set_freg(1, 3, 0xffffffff3f800000).unwrap();
+        assert_eq!(engine.get_reg(0, 10).unwrap(), 21);
+        assert_eq!(engine.execute().unwrap(), 0);
+        assert_eq!(engine.get_reg(0, 11).unwrap(), 42);
+        assert_eq!(engine.get_reg(1, 11).unwrap(), 2);
+        assert_eq!(engine.get_freg(1, 3).unwrap(), 0xffffffff3f800000);
+
+        // The registers only apply to the next execution, after which `a0`
+        // is the hart ID again.
+        engine.set_reg(1, 10, 100).unwrap();
+        assert_eq!(engine.execute().unwrap(), 0);
+        assert_eq!(engine.get_reg(1, 11).unwrap(), 200);
+        assert_eq!(engine.execute().unwrap(), 0);
+        assert_eq!(engine.get_reg(1, 11).unwrap(), 2);
+        assert_eq!(engine.get_reg(0, 11).unwrap(), 0);
+    }
+
+    #[test]
+    fn step_set_reg() {
+        // add a1, a0, a0; lui t0, 0x100; sw a1, 0(t0);
+        // lui t1, 0x40000; li t2, 1; sw t2, 32(t1); wfi
+        let elf = kernel(&[
+            0x00a505b3, 0x001002b7, 0x00b2a023, 0x40000337, 0x00100393, 0x02732023, 0x10500073,
+        ]);
+        let mut engine = EngineBuilder::new()
+            .deterministic(1000)
+            .stepping(true)
+            .build()
+            .unwrap();
+        engine.translate_elf(&elf).unwrap();
+        let mut stepper = Stepper::new(std::sync::Arc::new(engine)).unwrap();
+        let engine = stepper.engine();
+        assert_eq!(engine.get_reg(0, 10).unwrap(), 0);
+
+        // The registers set between the steps apply to the next instruction.
+        engine.set_reg(0, 10, 21).unwrap();
+        assert_eq!(stepper.step(1).unwrap(), None);
+        let engine = stepper.engine();
+        assert_eq!(engine.get_reg(0, 11).unwrap(), 42);
+        assert_eq!(engine.get_reg(0, 10).unwrap(), 21);
+        engine.set_reg(0, 11, 7).unwrap();
+        assert_eq!(stepper.step(2).unwrap(), None);
+        assert_eq!(stepper.engine().get_reg(0, 5).unwrap(), 0x100000);
+        assert_eq!(stepper.finish().unwrap(), 0);
+        assert_eq!(stepper.engine().read_word(0x100000), 7);
+        assert!(stepper.step(1).is_err());
+
+        // Stepping needs the deterministic mode.
+        assert!(EngineBuilder::new().stepping(true).build().is_err());
+    }
+
+    #[test]
+    fn app_args() {
+        let args = || AppArgs {
+            args: vec!["prog".into(), "7".into()],
+            env: vec!["KEY=VAL".into()],
+        };
+        let mut engine = test_engine();
+        assert!(engine.set_app_args(args(), Some(0x80000008)).is_err());
+        assert!(engine.set_app_args(args(), Some(0x40000000)).is_err());
+        assert!(engine.set_app_args(args(), Some(0x100000)).is_err());
+        engine.set_app_args(args(), None).unwrap();
+        let addr = (0x90000000 - args().size()) & !15;
+        for hartid in 0..2 {
+            with_cpu(&engine, hartid, |cpu| {
+                let regs = &cpu.state.regs;
+                assert_eq!(regs[2] == addr, hartid == 0);
+                assert_eq!(regs[10] == 2, hartid == 0);
+            });
+        }
+
+        // The binary exits with the first character of `argv[1]` minus the
+        // `argc` found on the stack.
+        // lw t0, 4(a1); lbu a0, 0(t0); lw t1, 0(sp); sub a0, a0, t1;
+        // slli a0, a0, 1; ori a0, a0, 1; lui t1, 0x40000; sw a0, 32(t1); wfi
+        let elf = kernel(&[
+            0x0045a283, 0x0002c503, 0x00012303, 0x40650533, 0x00151513, 0x00156513, 0x40000337,
+            0x02a32023, 0x10500073,
+        ]);
+        let mut engine = EngineBuilder::new().build().unwrap();
+        engine.set_app_args(args(), None).unwrap();
+        engine.translate_elf(&elf).unwrap();
+        assert_eq!(engine.execute().unwrap(), b'7' as u32 - 2);
+        let envp = args().envp(addr);
+        let ptr = engine.read_mem(envp, 4);
+        let var = u32::from_le_bytes([ptr[0], ptr[1], ptr[2], ptr[3]]);
+        assert_eq!(engine.read_mem(var, 8), b"KEY=VAL\0");
+
+        // The arguments must not overlap the binary.
+        let mut engine = EngineBuilder::new().build().unwrap();
+        engine.set_app_args(args(), Some(0x80010000)).unwrap();
+        assert!(engine.translate_elf(&elf).is_err());
+    }
+
+    #[test]
+    fn unimplemented_ssr() {
+        let mut engine = test_engine();
+        engine.config.ssr.num_dm = 3;
+        with_cpu(&engine, 0, |cpu| {
+            assert!(!cpu.unimplemented_ssr(tran::SSR_BASE as u32 + 2 * 32 * 8));
+            assert!(cpu.unimplemented_ssr(tran::SSR_BASE as u32 + 3 * 32 * 8));
+            assert!(!cpu.unimplemented_ssr(tran::SSR_BASE as u32 - 8));
+            assert!(!cpu.engine.had_error.load(Ordering::SeqCst));
+            cpu.binary_store(tran::SSR_BASE as u32 + 3 * 32 * 8, 1, u32::max_value(), 2);
+            assert!(cpu.engine.had_error.load(Ordering::SeqCst));
+        });
+    }
+
+    #[test]
+    fn syscalls() {
+        let mut engine = test_engine();
+        engine.add_syscall(
+            1000,
+            Box::new(|cpu, args| {
+                cpu.state.regs[5] = 1;
+                args[0] + args[6]
+            }),
+        );
+        engine
+            .memory
+            .lock()
+            .unwrap()
+            .insert(0x80000000, u32::from_le_bytes(*b"hi!\n"));
+        let ecall = |cpu: &mut Cpu, num: u32, args: &[u32]| {
+            cpu.state.regs[17] = num;
+            cpu.state.regs[10..10 + args.len()].copy_from_slice(args);
+            cpu.binary_ecall()
+        };
+        with_cpu(&engine, 0, |cpu| {
+            assert_eq!(ecall(cpu, 1000, &[3, 0, 0, 0, 0, 0, 4]), 0);
+            assert_eq!(cpu.state.regs[10], 7);
+            assert_eq!(cpu.state.regs[5], 1);
+
+            assert_eq!(ecall(cpu, SYS_WRITE, &[1, 0x80000000, 2]), 0);
+            assert_eq!(cpu.state.regs[10], 2);
+            assert_eq!(engine.putchar_buffer.lock().unwrap()[&0], b"hi");
+            assert_eq!(ecall(cpu, SYS_WRITE, &[3, 0x80000000, 2]), 0);
+            assert_eq!(cpu.state.regs[10], -9i32 as u32);
+
+            assert_eq!(ecall(cpu, SYS_EXIT, &[5]), 1);
+            let value = cpu.exit_reg.load(Ordering::SeqCst);
+            assert_eq!(*cpu.exit_value.lock().unwrap(), Some(value));
+            assert_eq!(engine.exit_protocol.exit_code(value), Some(5));
+            assert!(!cpu.had_error.load(Ordering::SeqCst));
+        });
+        assert!(engine.stop.load(Ordering::SeqCst));
+        assert!(!engine.had_error.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn newlib_syscalls() {
+        let root = std::env::temp_dir().join(format!("banshee-{}-fs", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("in.txt"), b"hello").unwrap();
+        let mut engine = test_engine();
+        engine.fs_root = Some(root.clone());
+        engine.set_heap(0x80100000, 0x80200000).unwrap();
+        engine.brk.store(0x80100000, Ordering::SeqCst);
+        engine.write_mem(0x80000000, b"/in.txt\0out.txt\0../x\0");
+        let ecall = |cpu: &mut Cpu, num: u32, args: &[u32]| {
+            cpu.state.regs[17] = num;
+            cpu.state.regs[10..10 + args.len()].copy_from_slice(args);
+            cpu.binary_ecall();
+            cpu.state.regs[10]
+        };
+        with_cpu(&engine, 0, |cpu| {
+            // Read a file, seek back, and read it again.
+            let fd = ecall(cpu, SYS_OPEN, &[0x80000000, O_RDONLY, 0]);
+            assert_eq!(fd, 3);
+            assert_eq!(ecall(cpu, SYS_READ, &[fd, 0x80001000, 16]), 5);
+            assert_eq!(cpu.read_mem(0x80001000, 5), b"hello");
+            assert_eq!(ecall(cpu, SYS_LSEEK, &[fd, 1, 0]), 1);
+            assert_eq!(ecall(cpu, SYS_READ, &[fd, 0x80001000, 2]), 2);
+            assert_eq!(cpu.read_mem(0x80001000, 2), b"el");
+            assert_eq!(ecall(cpu, SYS_FSTAT, &[fd, 0x80002000]), 0);
+            assert_eq!(cpu.read_mem(0x80002000 + 48, 1), [5]);
+            assert_eq!(ecall(cpu, SYS_CLOSE, &[fd]), 0);
+            assert_eq!(ecall(cpu, SYS_CLOSE, &[fd]), errno(EBADF));
+
+            // Create a file relative to the root.
+            let flags = O_WRONLY | O_CREAT | O_TRUNC;
+            let fd = ecall(
+                cpu,
+                SYS_OPENAT,
+                &[AT_FDCWD as u32, 0x80000008, flags, 0o644],
+            );
+            assert_eq!(fd, 3);
+            assert_eq!(ecall(cpu, SYS_WRITE, &[fd, 0x80001000, 2]), 2);
+            assert_eq!(ecall(cpu, SYS_CLOSE, &[fd]), 0);
+
+            // Paths cannot escape the root.
+            assert_eq!(
+                ecall(cpu, SYS_OPEN, &[0x80000010, O_RDONLY, 0]),
+                errno(EACCES)
+            );
+
+            // Stdio is a character device.
+            assert_eq!(ecall(cpu, SYS_FSTAT, &[1, 0x80002000]), 0);
+            assert_eq!(cpu.read_mem(0x80002000 + 16, 4), 0o020620u32.to_le_bytes());
+
+            // The break moves within the heap only.
+            assert_eq!(ecall(cpu, SYS_BRK, &[0]), 0x80100000);
+            assert_eq!(ecall(cpu, SYS_BRK, &[0x80100100]), 0x80100100);
+            assert_eq!(ecall(cpu, SYS_BRK, &[0x80300000]), 0x80100100);
+        });
+        assert_eq!(fs::read(root.join("out.txt")).unwrap(), b"el");
+        fs::remove_dir_all(&root).unwrap();
+
+        // Without a root, no files can be opened.
+        let engine = test_engine();
+        engine.write_mem(0x80000000, b"in.txt\0");
+        with_cpu(&engine, 0, |cpu| {
+            assert_eq!(
+                ecall(cpu, SYS_OPEN, &[0x80000000, O_RDONLY, 0]),
+                errno(EACCES)
+            );
+        });
+    }
+
+    #[test]
+    fn newlib_file_io() {
+        // The binary writes a file below the root, but cannot create one
+        // through a link which leads out of the root.
+        let dir = std::env::temp_dir().join(format!("banshee-{}-io", std::process::id()));
+        let root = dir.join("root");
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(dir.join("outside")).unwrap();
+        std::os::unix::fs::symlink(dir.join("outside"), root.join("link")).unwrap();
+        // lui s0, 0x80020; mv a0, s0; li a1, 0x601; li a2, 0644; li a7, 1024; ecall
+        // mv s1, a0; addi a1, s0, 16; li a2, 2; li a7, 64; ecall
+        // mv a0, s1; li a7, 57; ecall
+        // addi a0, s0, 8; li a1, 0x201; li a2, 0644; li a7, 1024; ecall
+        // sw a0, 32(s0); li a0, 0; li a7, 93; ecall
+        let elf = kernel(&[
+            0x80020437, 0x00040513, 0x60100593, 0x1a400613, 0x40000893, 0x00000073, 0x00050493,
+            0x01040593, 0x00200613, 0x04000893, 0x00000073, 0x00048513, 0x03900893, 0x00000073,
+            0x00840513, 0x20100593, 0x1a400613, 0x40000893, 0x00000073, 0x02a42023, 0x00000513,
+            0x05d00893, 0x00000073,
+        ]);
+        let mut engine = EngineBuilder::new().fs_root(&root).build().unwrap();
+        engine.translate_elf(&elf).unwrap();
+        engine.write_mem(0x80020000, b"out.txt\0link/x\0\0hi");
+        assert_eq!(engine.execute_status().unwrap(), ExitStatus::Halted(0));
+        assert_eq!(fs::read(root.join("out.txt")).unwrap(), b"hi");
+        assert_eq!(engine.read_word(0x80020020), errno(EACCES));
+        assert!(!dir.join("outside/x").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn default_heap() {
+        // The heap follows the sections of the binary in the DRAM.
+        let elf = kernel(&[0x10500073]);
+        let mut engine = EngineBuilder::new().build().unwrap();
+        engine.translate_elf(&elf).unwrap();
+        assert_eq!(engine.heap, Some((0x80010010, 0x90000000)));
+    }
+
+    #[test]
+    fn uart() {
+        let mut engine = test_engine();
+        engine.uart = Some(0x10000000);
+        with_cpus(&engine, &[0, 1], |cpus| {
+            assert_eq!(cpus[0].binary_load(0x10000014, 2), UART_LSR_READY);
+            for &c in b"hi" {
+                cpus[0].binary_store(0x10000000, c as u32, 0xff, 0);
+            }
+            cpus[1].binary_store(0x10000000, (b'x' as u32) << 8, 0xff00, 0);
+            cpus[1].binary_store(0x10000000, b'y' as u32, 0, 0);
+            {
+                let buffer = engine.uart_buffer.lock().unwrap();
+                assert_eq!(buffer[&0], b"hi");
+                assert_eq!(buffer[&1], b"x");
+            }
+            cpus[0].binary_store(0x10000000, b'\n' as u32, 0xff, 0);
+            assert!(engine.uart_buffer.lock().unwrap()[&0].is_empty());
+        });
+        assert_eq!(engine.read_mem(0x10000000, 4), [0; 4]);
+
+        // Without the UART, the stores go to memory.
+        let engine = test_engine();
+        with_cpu(&engine, 0, |cpu| {
+            cpu.binary_store(0x10000000, b'h' as u32, 0xff, 0);
+            assert_eq!(cpu.binary_load(0x10000000, 2), b'h' as u32);
+        });
+    }
+
+    #[test]
+    fn htif_tohost() {
+        let mut engine = test_engine();
+        engine.tohost = Some(0x80001000);
+        with_cpu(&engine, 0, |cpu| {
+            // Test 3 fails; clearing tohost is no command.
+            assert_eq!(cpu.binary_store(0x80001000, 0, u32::max_value(), 2), 0);
+            assert_eq!(
+                cpu.binary_store(0x80001000, 3 << 1 | 1, u32::max_value(), 2),
+                1
+            );
+            assert!(cpu.state.finished);
+            let value = cpu.exit_reg.load(Ordering::SeqCst);
+            assert_eq!(engine.exit_protocol.exit_code(value), Some(3));
+        });
+        assert!(engine.stop.load(Ordering::SeqCst));
+        assert!(!engine.had_error.load(Ordering::SeqCst));
+
+        // Syscall proxying is not supported.
+        engine.stop.store(false, Ordering::SeqCst);
+        with_cpu(&engine, 0, |cpu| {
+            assert_eq!(
+                cpu.binary_store(0x80001000, 0x80002000, u32::max_value(), 2),
+                1
+            );
+        });
+        assert!(engine.had_error.load(Ordering::SeqCst));
+
+        // The stock riscv-tests environment reports through tohost from its
+        // trap handler.
+        let elf = crate::loader::parse_elf(include_bytes!("../tests/bin/rv32ui-p-add")).unwrap();
+        let mut engine = EngineBuilder::new()
+            .num_cores(1)
+            .traps(true)
+            .build()
+            .unwrap();
+        engine.translate_elf(&elf).unwrap();
+        assert_eq!(engine.tohost, Some(0x80001000));
+        assert_eq!(engine.execute().unwrap(), 0);
+    }
+
+    #[test]
+    fn custom_insts() {
+        // Count in a register with a custom instruction in the `custom-2`
+        // space, whose rd field selects the register.
+        let mut engine = test_engine();
+        engine.add_custom_inst(
+            0x7f,
+            0x5b,
+            Box::new(|cpu, raw| cpu.state.regs[(raw >> 7 & 0x1f) as usize] += 1),
+        );
+        let raw = 5 << 7 | 0x5b;
+        assert_eq!(engine.custom_inst(raw), Some(0));
+        assert_eq!(engine.custom_inst(0x7b), None);
+        with_cpu(&engine, 0, |cpu| {
+            assert_eq!(cpu.binary_custom_inst(0, raw), 0);
+            assert_eq!(cpu.binary_custom_inst(0, raw), 0);
+            assert_eq!(cpu.state.regs[5], 2);
+        });
+    }
+
+    #[test]
+    fn watchpoints() {
+        let mut engine = test_engine();
+        engine.add_watchpoint(0x80000006, WatchKind::Write);
+        engine.add_watchpoint(0x100004, WatchKind::Read);
+        let hits = std::sync::Arc::new(Mutex::new(vec![]));
+        let sink = hits.clone();
+        engine.watch_callback = Some(Box::new(move |hit| sink.lock().unwrap().push(*hit)));
+        engine.memory.lock().unwrap().insert(0x80000004, 0x11223344);
+
+        // Only the watched kind of access to the watched word triggers.
+        with_cpu(&engine, 0, |cpu| {
+            cpu.binary_store(0x80000004, 0xaa00, 0xff00, 0);
+            cpu.binary_load(0x80000004, 2);
+            cpu.binary_store(0x80000008, 0, u32::max_value(), 2);
+            cpu.binary_store(0x100004, 0x55, u32::max_value(), 2);
+            cpu.binary_load(0x100004, 2);
+        });
+        let hits = hits.lock().unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].addr, 0x80000004);
+        assert!(hits[0].write);
+        assert_eq!(hits[0].old, 0x11223344);
+        assert_eq!(hits[0].new, 0x1122aa44);
+        assert_eq!(hits[1].addr, 0x100004);
+        assert!(!hits[1].write);
+        assert_eq!(hits[1].new, 0x55);
+    }
+
+    #[test]
+    fn progress_interval() {
+        let zero = std::time::Duration::from_secs(0);
+        assert!(EngineBuilder::new().progress(zero).build().is_err());
+        let elf = kernel(&[0x40000337, 0x00100393, 0x02732023, 0x10500073]);
+        let mut engine = EngineBuilder::new()
+            .progress(std::time::Duration::from_millis(1))
+            .build()
+            .unwrap();
+        engine.translate_elf(&elf).unwrap();
+        assert_eq!(engine.execute().unwrap(), 0);
+    }
+
+    #[test]
+    fn deadlock() {
+        // Harts 0 and 1 wait in the cluster barrier for hart 2, which has
+        // finished without arriving.
+        let engine = test_engine();
+        let timeout = std::time::Duration::from_millis(100);
+        with_cpus(&engine, &[0, 1, 2], |cpus| {
+            cpus[0].state.pc = 0x80000010;
+            cpus[1].state.pc = 0x80000020;
+            cpus[2].state.finished = true;
+            let cpus = &*cpus;
+            let running = AtomicUsize::new(2);
+            crossbeam_utils::thread::scope(|s| {
+                for cpu in &cpus[..2] {
+                    let running = &running;
+                    s.spawn(move |_| {
+                        cpu.cluster_barrier();
+                        running.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+                engine.watch_deadlock(cpus, &running, timeout);
+            })
+            .unwrap();
+        });
+        assert!(engine.had_error.load(Ordering::SeqCst));
+        let deadlock = engine.deadlock.lock().unwrap().take().unwrap();
+        assert_eq!(deadlock.harts, vec![(0, 0x80000010), (1, 0x80000020)]);
+        assert!(deadlock.to_string().ends_with("pcs: 0:80000010 1:80000020"));
+    }
+
+    #[test]
+    fn preload_large_section() {
+        // Add a 4 MiB data section to a small binary.
+        let mut elf = crate::loader::parse_elf(include_bytes!("../tests/bin/dummy")).unwrap();
+        elf.sections.push(elf::Section {
+            shdr: elf::types::SectionHeader {
+                name: ".data".to_string(),
+                shtype: elf::types::SHT_PROGBITS,
+                flags: elf::types::SHF_ALLOC,
+                addr: 0x80100000,
+                offset: 0,
+                size: 4 << 20,
+                link: 0,
+                info: 0,
+                addralign: 4,
+                entsize: 0,
+            },
+            data: (0..1u32 << 20).flat_map(|i| i.to_le_bytes()).collect(),
+        });
+
+        let engine = test_engine();
+        engine.preload_sections(&elf);
+        let memory = engine.memory.lock().unwrap();
+        assert!(memory.len() > 1 << 20);
+        // The memory was sized once for all words of the binary.
+        let words = elf
+            .sections
+            .iter()
+            .filter(|section| (section.shdr.flags.0 & elf::types::SHF_ALLOC.0) != 0)
+            .map(|section| (section.data.len() + 3) / 4)
+            .sum();
+        assert_eq!(
+            memory.capacity(),
+            HashMap::<u64, u32>::with_capacity(words).capacity()
+        );
+        assert_eq!(memory[&0x80100000], 0);
+        assert_eq!(memory[&0x80100004], 1);
+        assert_eq!(memory[&(0x80100000 + (4 << 20) - 4)], (1 << 20) - 1);
+    }
+
+    #[test]
+    fn all_opt_levels() {
+        // Each combination translates the binary into fresh modules of the
+        // same context.
+        let elf = crate::loader::parse_elf(include_bytes!("../tests/bin/dummy")).unwrap();
+        let mut engine = EngineBuilder::new().num_cores(1).build().unwrap();
+        assert_eq!(engine.run_all_opt_levels(&elf).unwrap(), [0; 4]);
+        assert_eq!(engine.read_mem(0x100, 4), 0x100u32.to_le_bytes());
+    }
+
+    #[test]
+    fn preload_bss() {
+        // A `.bss` section without file data, over memory with stale contents.
+        let mut elf = crate::loader::parse_elf(include_bytes!("../tests/bin/dummy")).unwrap();
+        elf.sections.push(elf::Section {
+            shdr: elf::types::SectionHeader {
+                name: ".bss".to_string(),
+                shtype: elf::types::SHT_NOBITS,
+                flags: elf::types::SectionFlag(elf::types::SHF_ALLOC.0 | elf::types::SHF_WRITE.0),
+                addr: 0x80100000,
+                offset: 0,
+                size: 0x16,
+                link: 0,
+                info: 0,
+                addralign: 4,
+                entsize: 0,
+            },
+            data: vec![],
+        });
+
+        // Neither the section nor the `.data` bytes after it are word-aligned.
+        elf.sections.last_mut().unwrap().shdr.addr = 0x80100002;
+        elf.sections.last_mut().unwrap().shdr.size = 0x14;
+        elf.sections.push(elf::Section {
+            shdr: elf::types::SectionHeader {
+                name: ".data".to_string(),
+                shtype: elf::types::SHT_PROGBITS,
+                flags: elf::types::SectionFlag(elf::types::SHF_ALLOC.0 | elf::types::SHF_WRITE.0),
+                addr: 0x80100019,
+                offset: 0,
+                size: 2,
+                link: 0,
+                info: 0,
+                addralign: 1,
+                entsize: 0,
+            },
+            data: vec![0x11, 0x22],
+        });
+
+        let engine = test_engine();
+        engine
+            .memory
+            .lock()
+            .unwrap()
+            .extend((0x80100000..0x80100020).step_by(4).map(|a| (a, 0xdeadbeef)));
+        engine.preload_sections(&elf);
+        let memory = engine.memory.lock().unwrap();
+        // The bytes around the sections keep their contents.
+        assert_eq!(memory[&0x80100000], 0x0000beef);
+        for addr in (0x80100004..0x80100014).step_by(4) {
+            assert_eq!(memory.get(&addr), Some(&0), "0x{:x} not zeroed", addr);
+        }
+        assert_eq!(memory[&0x80100014], 0xdead0000);
+        assert_eq!(memory[&0x80100018], 0xde2211ef);
+        assert_eq!(memory[&0x8010001c], 0xdeadbeef);
+    }
+
+    #[test]
+    fn disassemble() {
+        // addi a0, zero, 5; c.li a1, 3; add a0, a0, a1; wfi
+        let mut elf = crate::loader::parse_elf(include_bytes!("../tests/bin/dummy")).unwrap();
+        let code = [
+            &0x00500513u32.to_le_bytes()[..],
+            &0x458du16.to_le_bytes(),
+            &0x00b50533u32.to_le_bytes(),
+            &0x10500073u32.to_le_bytes(),
+        ]
+        .concat();
+        elf.sections = vec![elf::Section {
+            shdr: elf::types::SectionHeader {
+                name: ".text".to_string(),
+                shtype: elf::types::SHT_PROGBITS,
+                flags: elf::types::SectionFlag(
+                    elf::types::SHF_ALLOC.0 | elf::types::SHF_EXECINSTR.0,
+                ),
+                addr: 0x80000000,
+                offset: 0,
+                size: 14,
+                link: 0,
+                info: 0,
+                addralign: 2,
+                entsize: 0,
+            },
+            data: code,
+        }];
+
+        let insts: Vec<_> = Engine::disassemble(&elf)
+            .map(|(addr, inst)| (addr, inst.to_string()))
+            .collect();
+        assert_eq!(
+            insts,
+            vec![
+                (0x80000000, "addi imm12=5 rd=a rs1=0".to_string()),
+                (0x80000004, "addi imm12=3 rd=b rs1=0".to_string()),
+                (0x80000006, "add rd=a rs1=a rs2=b".to_string()),
+                (0x8000000a, "wfi".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn stack_guard() {
+        let mut engine = test_engine();
+        let top = 0x120000;
+        engine.set_stack_guard(0, top - 0x1400, 0x400).unwrap();
+        assert!(engine.set_stack_guard(1, top, 0x400).is_err());
+        assert!(engine.set_stack_guard(0, u32::MAX - 4, 8).is_err());
+        with_cpu(&engine, 0, |cpu| {
+            // A recursive function which saves `ra` and `s0` in a 48 byte
+            // frame, with a 4 KiB stack.
+            let mut sp = top;
+            let mut depth = 0;
+            loop {
+                sp -= 48;
+                cpu.state.pc = 0x80000104;
+                if cpu.binary_store(sp + 44, 0x80000120, !0, 2) != 0 {
+                    break;
+                }
+                assert_eq!(cpu.binary_store(sp + 40, sp + 48, !0, 2), 0);
+                depth += 1;
+            }
+            assert_eq!(depth, 86);
+            assert!(cpu.had_error.load(Ordering::SeqCst));
+
+            // Only the first overflow is reported.
+            cpu.binary_load(top - 0x1400, 2);
+        });
+        assert!(engine.stop.load(Ordering::SeqCst));
+        assert!(engine.had_error.load(Ordering::SeqCst));
+        let overflow = engine.stack_overflow.lock().unwrap().unwrap();
+        assert_eq!(
+            overflow,
+            StackOverflow {
+                hartid: 0,
+                pc: 0x80000104,
+                addr: 0x11efdc,
+                write: true,
+                owner: 0,
+            }
+        );
+        assert_eq!(
+            overflow.to_string(),
+            "Stack overflow: hart 0 (pc=0x80000104) writes 0x0011efdc in the stack guard of hart 0"
+        );
+    }
+
+    #[test]
+    fn stack_overflow() {
+        // A function which recurses 1000 times with a 48 byte frame overflows
+        // its 4 KiB stack when it saves `ra` in the 87th frame.
+        // lui sp, 0x80040; li a0, 1000; jal f
+        // lui t2, 0x40000; li t3, 1; sw t3, 32(t2); wfi
+        // f: addi sp, sp, -48; sw ra, 44(sp); sw s0, 40(sp); addi s0, sp, 48
+        // beqz a0, 1f; addi a0, a0, -1; jal f
+        // 1: lw ra, 44(sp); lw s0, 40(sp); addi sp, sp, 48; ret
+        let elf = kernel(&[
+            0x80040137, 0x3e800513, 0x014000ef, 0x400003b7, 0x00100e13, 0x03c3a023, 0x10500073,
+            0xfd010113, 0x02112623, 0x02812423, 0x03010413, 0x00050663, 0xfff50513, 0xfe9ff0ef,
+            0x02c12083, 0x02812403, 0x03010113, 0x00008067,
+        ]);
+        let top = 0x80040000;
+        let mut engine = EngineBuilder::new().build().unwrap();
+        engine.set_stack_guard(0, top - 0x1400, 0x400).unwrap();
+        engine.translate_elf(&elf).unwrap();
+        let overflow = StackOverflow {
+            hartid: 0,
+            pc: elf.ehdr.entry as u32 + 0x20,
+            addr: top - 87 * 48 + 44,
+            write: true,
+            owner: 0,
+        };
+        assert_eq!(
+            engine.execute_status().unwrap(),
+            ExitStatus::Error(ExecError::StackOverflow(overflow))
+        );
+    }
+
+    #[test]
+    fn execution_limits() {
+        let mut engine = test_engine();
+        engine.max_instret = Some(100);
+        with_cpu(&engine, 0, |cpu| {
+            cpu.state.instret = 100;
+            cpu.binary_abort_limit(0x80000010);
+            assert!(cpu.had_error.load(Ordering::SeqCst));
+        });
+        assert!(engine.stop.load(Ordering::SeqCst));
+        assert_eq!(
+            engine.take_execution_error().unwrap().to_string(),
+            "Execution limit exceeded at pc=0x80000010 (hart 0): retired 100 instructions"
+        );
+        assert!(engine.take_execution_error().is_none());
+
+        // The watchdog stops harts which run too long.
+        let engine = test_engine();
+        with_cpus(&engine, &[0, 1], |cpus| {
+            cpus[0].state.finished = true;
+            cpus[1].state.pc = 0x80000020;
+            let running = AtomicUsize::new(1);
+            let t0 = std::time::Instant::now();
+            let max = std::time::Duration::from_millis(20);
+            engine.watch_duration(cpus, &running, max, t0);
+            assert!(t0.elapsed() >= max);
+        });
+        assert!(engine.stop.load(Ordering::SeqCst));
+        assert!(engine.had_error.load(Ordering::SeqCst));
+        assert_eq!(
+            engine.take_execution_error().unwrap().to_string(),
+            "Execution limit exceeded at pc=0x80000020 (hart 1): ran for 20ms"
+        );
+    }
+
+    #[test]
+    fn ssr_overrun() {
+        // A stream of four elements, which is accessed a fifth time.
+        let engine = test_engine();
+        with_cpu(&engine, 0, |cpu| {
+            let ssr = &mut cpu.state.ssrs[0];
+            ssr.bound[0] = 3;
+            for _ in 0..4 {
+                assert!(!ssr.overrun());
+                ssr.step();
+            }
+            assert!(ssr.overrun());
+
+            // Lowering the bound mid-stream leaves the index beyond it.
+            ssr.restart();
+            ssr.done = false;
+            ssr.step();
+            ssr.step();
+            assert!(!ssr.overrun());
+            ssr.bound[0] = 1;
+            assert!(ssr.overrun());
+
+            assert!(!engine.had_error.load(Ordering::SeqCst));
+            cpu.binary_ssr_check_addr(0, 0x100000, 0x100020, 0);
+            assert!(!engine.had_error.load(Ordering::SeqCst));
+            cpu.binary_ssr_check_addr(0, 0x100000, 0x100020, 1);
+        });
+        assert!(engine.had_error.load(Ordering::SeqCst));
+
+        // Streams which run out of their memory, or whose strides leave the
+        // elements unaligned, abort as well.
+        // lui t2, 0x80020; lui t0, 0x205; addi t0, t0, -2048
+        // lw t1, 0(t2); sw t1, 16(t0); lw t1, 4(t2); sw t1, 48(t0)
+        // lw t1, 8(t2); sw t1, 192(t0); csrsi ssr, 1
+        // fmv.d ft2, ft0 (4 times); csrci ssr, 1
+        // lui t2, 0x40000; li t3, 1; sw t3, 32(t2); wfi
+        let elf = kernel(&[
+            0x800203b7, 0x002052b7, 0x80028293, 0x0003a303, 0x0062a823, 0x0043a303, 0x0262a823,
+            0x0083a303, 0x0c62a023, 0x7c00e073, 0x22000153, 0x22000153, 0x22000153, 0x22000153,
+            0x7c00f073, 0x400003b7, 0x00100e13, 0x03c3a023, 0x10500073,
+        ]);
+        let run = |bound: u32, stride: u32, base: u32| {
+            let mut engine = EngineBuilder::new().check_ssr_bounds(true).build().unwrap();
+            engine.translate_elf(&elf).unwrap();
+            let cfg: Vec<u8> = [bound, stride, base]
+                .iter()
+                .flat_map(|word| word.to_le_bytes())
+                .collect();
+            engine.write_mem(0x80020000, &cfg);
+            match engine.execute_status().unwrap() {
+                ExitStatus::Error(ExecError::Fault(fault)) => Some(fault.reason),
+                status => {
+                    assert_eq!(status, ExitStatus::Halted(0));
+                    None
+                }
+            }
+        };
+        assert_eq!(run(3, 8, 0x100000), None);
+        assert_eq!(
+            run(2, 8, 0x100000).unwrap(),
+            "accesses SSR 0 past the end of its stream (next address 0x100018)"
+        );
+        assert_eq!(
+            run(3, 4, 0x100000).unwrap(),
+            "accesses SSR 0 at 0x100004, which its strides do not align to 8 bytes"
+        );
+        assert_eq!(
+            run(3, 8, 0x11fff0).unwrap(),
+            "accesses SSR 0 at 0x120000, outside the memory 0x100000-0x120000 its stream started in"
+        );
+    }
+
+    #[test]
+    fn hart_fault() {
+        // The first fatal error stops all harts and identifies the hart and
+        // instruction which caused it.
+        let engine = test_engine();
+        with_cpus(&engine, &[0, 1], |cpus| {
+            cpus[1].binary_abort_illegal_inst(0x80000010, 0);
+            assert!(engine.stop.load(Ordering::SeqCst));
+            assert_eq!(cpus[0].binary_wfi(), 1);
+            cpus[0].binary_abort_escape(0x80000020);
+            assert!(cpus[0].had_error.load(Ordering::SeqCst));
+            assert!(cpus[1].had_error.load(Ordering::SeqCst));
+        });
+        let e = engine.take_execution_error().unwrap();
+        let fault = e.downcast_ref::<HartFault>().unwrap();
+        assert_eq!((fault.hartid, fault.pc), (1, 0x80000010));
+        assert!(e
+            .to_string()
+            .starts_with("Hart 1 (pc=0x80000010) executes illegal"));
+        assert!(engine.take_execution_error().is_none());
+    }
+
+    #[test]
+    fn backtrace() {
+        // A call chain `_start` -> `main` -> `foo` -> `leaf`, with the frames
+        // laid out by GCC with frame pointers. `leaf` does not save `ra`.
+        let mut engine = test_engine();
+        engine.images.push(ElfImage {
+            entry: "execute_binary".to_string(),
+            harts: None,
+            exec_ranges: vec![(0x80000000, 0x80001000)],
+            alloc_ranges: vec![(0x80000000, 0x80001000)],
+            tls: None,
+            symbols: vec![
+                (0x80000000, "_start".to_string()),
+                (0x80000100, "main".to_string()),
+                (0x80000200, "foo".to_string()),
+                (0x80000300, "leaf".to_string()),
+            ],
+            lines: None,
+            entry_point: 0x80000000,
+            steps: vec![],
+        });
+        engine.memory.lock().unwrap().extend(vec![
+            // The frame of `main`.
+            (0x8000fffc, 0x80000010),
+            (0x8000fff8, 0),
+            // The frame of `foo`.
+            (0x8000ffec, 0x80000124),
+            (0x8000ffe8, 0x80010000),
+            // The frame of `leaf`.
+            (0x8000ffdc, 0x8000fff0),
+        ]);
+        with_cpu(&engine, 0, |cpu| {
+            cpu.state.regs[1] = 0x80000238;
+            cpu.state.regs[8] = 0x8000ffe0;
+            assert_eq!(cpu.backtrace(), vec![0x80000238, 0x80000124, 0x80000010]);
+            // Frames outside of the stack end the walk.
+            cpu.state.regs[8] = 0x8000fff0;
+            cpu.state.regs[1] = 0;
+            engine.memory.lock().unwrap().insert(0x8000ffe8, 0x8000ff00);
+            assert_eq!(cpu.backtrace(), vec![0x80000124]);
+        });
+        assert_eq!(engine.symbolize(0x80000238), Some(("foo", 0x38)));
+        assert_eq!(engine.symbolize(0x80000300), Some(("leaf", 0)));
+        assert_eq!(engine.symbolize(0x80000010), Some(("_start", 0x10)));
+        assert_eq!(engine.symbolize(0x80001000), None);
+        assert_eq!(engine.describe_addr(0x80000124), " <main+0x24>");
+    }
+
+    #[test]
+    fn wfi_callback() {
+        // A lone sleeping hart terminates, unless the callback wakes it.
+        let mut engine = test_engine();
+        with_cpu(&engine, 0, |cpu| assert_eq!(cpu.binary_wfi(), 1));
+        let calls = std::sync::Arc::new(Mutex::new(vec![]));
+        let sink = calls.clone();
+        engine.wfi_callback = Some(Box::new(move |hartid, _| {
+            let mut calls = sink.lock().unwrap();
+            calls.push(hartid);
+            calls.len() < 2
+        }));
+        with_cpu(&engine, 0, |cpu| {
+            assert_eq!(cpu.binary_wfi(), 0);
+            assert_eq!(cpu.binary_wfi(), 1);
+        });
+        assert_eq!(*calls.lock().unwrap(), vec![0, 0]);
+    }
+
+    #[test]
+    fn finished() {
+        // Hart 0 writes the exit code and hart 2 halts in `wfi`, while hart 1
+        // is deadlocked and still running when execution is stopped.
+        let mut engine = test_engine();
+        engine.exit_protocol = ExitProtocol::Nonzero;
+        let scratch = engine.config.address.scratch_reg;
+        with_cpus(&engine, &[0, 1, 2], |cpus| {
+            assert_eq!(cpus[0].binary_store(scratch, 1, u32::max_value(), 2), 1);
+            assert_eq!(cpus[2].binary_wfi(), 1);
+            assert!(cpus[0].state.finished);
+            assert!(!cpus[1].state.finished);
+            assert!(cpus[2].state.finished);
+        });
+
+        // With the Snitch protocol, reporting the exit code does not halt,
+        // but the hart has finished all the same.
+        let engine = test_engine();
+        with_cpus(&engine, &[0, 1], |cpus| {
+            assert_eq!(cpus[0].binary_store(scratch, 0, u32::max_value(), 2), 0);
+            assert!(!cpus[0].state.finished);
+            assert_eq!(cpus[0].binary_store(scratch, 1, u32::max_value(), 2), 0);
+            assert!(cpus[0].state.finished);
+            assert!(!cpus[1].state.finished);
+        });
+
+        // When execution is stopped externally, e.g. by a timeout, no hart
+        // has finished, but the sleeping harts are parked in `wfi`.
+        let engine = test_engine();
+        engine.stop.store(true, Ordering::SeqCst);
+        with_cpus(&engine, &[0, 1], |cpus| {
+            assert_eq!(cpus[1].binary_wfi(), 1);
+            assert!(!cpus[0].state.finished);
+            assert!(!cpus[1].state.finished);
+            assert!(cpus[1].state.wfi);
+        });
+    }
+
+    #[test]
+    fn access_log() {
+        // The log keeps the most recent accesses of all harts, oldest first,
+        // up to an escape which stops execution with an error.
+        let mut engine = test_engine();
+        engine.access_log_size = 3;
+        let dram = engine.config.memory[0].dram.start;
+        with_cpus(&engine, &[0, 1], |cpus| {
+            cpus[0].binary_store(dram, 1, u32::max_value(), 2);
+            cpus[1].binary_store(dram + 4, 2, u32::max_value(), 2);
+            assert_eq!(cpus[0].binary_load(dram + 4, 2), 2);
+            cpus[1].state.pc = 0x80000010;
+            cpus[1].binary_store(dram, 3, 0xff, 0);
+            cpus[1].binary_abort_escape(0x80000014);
+        });
+        assert!(engine.had_error.load(Ordering::SeqCst));
+        let access = |hartid, pc, addr, value, size, write| LoggedAccess {
+            hartid,
+            pc,
+            addr,
+            value,
+            size,
+            write,
+        };
+        assert_eq!(
+            engine.recent_accesses(),
+            vec![
+                access(1, 0, dram + 4, 2, 2, true),
+                access(0, 0, dram + 4, 2, 2, false),
+                access(1, 0x80000010, dram, 3, 0, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn parallel_analysis() {
+        // Analyzing the sections in parallel yields the same translation.
+        let elf = crate::loader::parse_elf(include_bytes!("../tests/bin/matmul_ssr_frep")).unwrap();
+        let translate = |parallel| {
+            let mut engine = test_engine();
+            engine.parallel_analysis = parallel;
+            engine.create_modules();
+            engine.translate_elf(&elf).unwrap();
+            unsafe {
+                let ir = LLVMPrintModuleToString(engine.modules[0]);
+                let ir_string = std::ffi::CStr::from_ptr(ir).to_string_lossy().into_owned();
+                LLVMDisposeMessage(ir);
+                (ir_string, std::mem::take(&mut engine.basic_blocks))
+            }
+        };
+        assert_eq!(translate(false), translate(true));
+
+        // Images with several sections execute the same way. Each hart jumps
+        // from `.text` to `.text.store`, which stores `k` at
+        // 0x80020000 + 4 * hartid, and exits.
+        let image = |k: u32, addr: u64| {
+            let mut store = kernel(&[
+                0xf1402573,
+                0x00251293,
+                0x80020337,
+                0x006282b3,
+                0x00000393 | k << 20,
+                0x0072a023,
+                0x40000337,
+                0x00100393,
+                0x02732023,
+                0x10500073,
+            ])
+            .sections
+            .pop()
+            .unwrap();
+            store.shdr.name = ".text.store".to_string();
+            store.shdr.addr = addr + 0x100;
+            // j 0x100
+            let mut elf = kernel(&[0x1000006f]);
+            elf.sections[0].shdr.addr = addr;
+            elf.sections.push(store);
+            elf.ehdr.entry = addr;
+            elf
+        };
+        let execute = |parallel| {
+            let mut engine = EngineBuilder::new()
+                .num_cores(2)
+                .parallel_analysis(parallel)
+                .build()
+                .unwrap();
+            engine
+                .translate_elf_for_harts(&image(10, 0x80010000), Some(vec![0]))
+                .unwrap();
+            engine
+                .translate_elf_for_harts(&image(11, 0x80011000), Some(vec![1]))
+                .unwrap();
+            let stats = engine.execute_with_stats().unwrap();
+            let stored = (engine.read_word(0x80020000), engine.read_word(0x80020004));
+            let instret: Vec<_> = stats.harts.iter().map(|hart| hart.instret).collect();
+            (stats.exit_code, stored, instret)
+        };
+        let parallel = execute(true);
+        assert_eq!(parallel, (0, (10, 11), vec![11, 11]));
+        assert_eq!(parallel, execute(false));
+    }
+
+    #[test]
+    fn dedup_blocks() {
+        // Identical blocks are emitted once into a function, which shrinks the
+        // translated binary but leaves its basic blocks and the coverage of
+        // its instructions unchanged.
+        let elf = crate::loader::parse_elf(include_bytes!("../tests/bin/spmm_issr_frep")).unwrap();
+        let translate = |dedup| {
+            let mut engine = test_engine();
+            engine.dedup_blocks = dedup;
+            engine.coverage = true;
+            engine.create_modules();
+            engine.translate_elf(&elf).unwrap();
+            let (mut funcs, mut insts) = (0, 0);
+            unsafe {
+                let mut func = LLVMGetFirstFunction(engine.modules[0]);
+                while !func.is_null() {
+                    let mut len = 0;
+                    let name = LLVMGetValueName2(func, &mut len);
+                    let name = std::slice::from_raw_parts(name as *const u8, len);
+                    if name.starts_with(b"execute_binary") {
+                        funcs += 1;
+                        let mut bb = LLVMGetFirstBasicBlock(func);
+                        while !bb.is_null() {
+                            let mut inst = LLVMGetFirstInstruction(bb);
+                            while !inst.is_null() {
+                                insts += 1;
+                                inst = LLVMGetNextInstruction(inst);
+                            }
+                            bb = LLVMGetNextBasicBlock(bb);
+                        }
+                    }
+                    func = LLVMGetNextFunction(func);
+                }
+            }
+            let blocks = std::mem::take(&mut engine.basic_blocks);
+            (funcs, insts, blocks, engine.coverage_report())
+        };
+        let (funcs, insts, blocks, coverage) = translate(false);
+        let (dedup_funcs, dedup_insts, dedup_blocks, dedup_coverage) = translate(true);
+        assert_eq!(funcs, 1);
+        assert!(dedup_funcs > 1);
+        assert!(
+            dedup_insts < insts,
+            "{} instructions with deduplication, {} without",
+            dedup_insts,
+            insts
+        );
+        assert_eq!(dedup_blocks, blocks);
+        assert_eq!(dedup_coverage, coverage);
+    }
+
+    #[test]
+    fn read_only_csrs() {
+        // Writes to read-only CSRs are ignored, or abort if so configured.
+        let mut engine = test_engine();
+        with_cpu(&engine, 3, |cpu| {
+            cpu.binary_csr_write(riscv::Csr::Mhartid, 5, 1);
+            cpu.binary_csr_write(riscv::Csr::Mepc, 5, 1);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Mhartid, 1), 3);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Mepc, 1), 5);
+        });
+        assert!(!engine.had_error.load(Ordering::SeqCst));
+        engine.config.trap_read_only_csrs = true;
+        with_cpu(&engine, 3, |cpu| {
+            cpu.binary_csr_write(riscv::Csr::Cycle, 0, 1)
+        });
+        assert!(engine.had_error.load(Ordering::SeqCst));
+        assert!(engine.stop.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn trace_renderer() {
+        struct Csv;
+        impl TraceRenderer for Csv {
+            fn render(&self, inst: &TracedInst) -> String {
+                let mut line = format!(
+                    "{},{},{:x},{:x}",
+                    inst.instret, inst.hartid, inst.addr, inst.inst
+                );
+                for (access, data) in inst.accesses.iter().zip(inst.data) {
+                    line += &format!(",{:?}={:x}", access, data);
+                }
+                line
+            }
+        }
+
+        // `addi a0, a1, 1`
+        let accesses = [TraceAccess::ReadReg(11), TraceAccess::WriteReg(10)];
+        let data = [41, 42];
+        let mut engine = test_engine();
+        with_cpu(&engine, 2, |cpu| {
+            cpu.state.cycle = 9;
+            cpu.state.instret = 7;
+            assert_eq!(
+                cpu.trace_line(0x80000010, 0x00158513, &accesses, &data),
+                format!(
+                    "00000009 00000007 0002 80000010  {:38}  # DASM(00158513)",
+                    "x11:00000029 x10=0000002a"
+                )
+            );
+        });
+        engine.trace_renderer = Some(Box::new(Csv));
+        with_cpu(&engine, 2, |cpu| {
+            cpu.state.instret = 7;
+            assert_eq!(
+                cpu.trace_line(0x80000010, 0x00158513, &accesses, &data),
+                "7,2,80000010,158513,ReadReg(11)=29,WriteReg(10)=2a"
+            );
+        });
+    }
+
+    #[test]
+    fn trace_sample() {
+        // Record the `instret` of the traced instructions of each hart.
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<(usize, u64)>>);
+        impl TraceRenderer for std::sync::Arc<Recorder> {
+            fn render(&self, inst: &TracedInst) -> String {
+                self.0.lock().unwrap().push((inst.hartid, inst.instret));
+                String::new()
+            }
+        }
+
+        // Hart 1 counts down from 100, hart 0 from 10, as in `slowest_hart`.
+        let elf = kernel(&[
+            0xf1402573, 0x06400293, 0x00051463, 0x00a00293, 0xfff28293, 0xfe029ee3, 0x40000337,
+            0x00100393, 0x02732023, 0x10500073,
+        ]);
+        let recorder = std::sync::Arc::new(Recorder::default());
+        let mut engine = EngineBuilder::new()
+            .num_cores(2)
+            .trace(true)
+            .trace_sample(10)
+            .build()
+            .unwrap();
+        engine.trace_renderer = Some(Box::new(recorder.clone()));
+        engine.translate_elf(&elf).unwrap();
+        let stats = engine.execute_with_stats().unwrap();
+        let traced = recorder.0.lock().unwrap();
+        for hart in &stats.harts {
+            let instrets: Vec<_> = traced
+                .iter()
+                .filter(|&&(hartid, _)| hartid == hart.hartid)
+                .map(|&(_, instret)| instret)
+                .collect();
+            let expected: Vec<_> = (1..=hart.instret / 10).map(|i| 10 * i).collect();
+            assert_eq!(instrets, expected);
+        }
+        assert!(traced.len() > 20);
+    }
+
+    #[test]
+    fn frep_compressed() {
+        // Record the address and encoding of the traced instructions.
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<(u32, u32)>>);
+        impl TraceRenderer for std::sync::Arc<Recorder> {
+            fn render(&self, inst: &TracedInst) -> String {
+                self.0.lock().unwrap().push((inst.addr, inst.inst));
+                String::new()
+            }
+        }
+
+        // lui a1, 0x80020; li t0, 2; frep.i t0, 1, 0, 0; c.fsd fa0, 0(a1);
+        // c.nop; <exit>
+        let elf = kernel(&[
+            0x800205b7, 0x00200293, 0x0002800b, 0x0001a188, 0x400003b7, 0x00100e13, 0x03c3a023,
+            0x10500073,
+        ]);
+        let recorder = std::sync::Arc::new(Recorder::default());
+        let mut engine = EngineBuilder::new().trace(true).build().unwrap();
+        engine.trace_renderer = Some(Box::new(recorder.clone()));
+        engine.translate_elf(&elf).unwrap();
+        assert_eq!(engine.execute().unwrap(), 0);
+
+        // Every repetition is traced with the compressed encoding, and
+        // execution continues right after it.
+        let traced = recorder.0.lock().unwrap();
+        assert_eq!(
+            traced[3..6],
+            [
+                (0x8001000c, 0xa188),
+                (0x8001000c, 0xa188),
+                (0x8001000c, 0xa188)
+            ]
+        );
+        assert_eq!(traced[6], (0x8001000e, 0x0001));
+    }
+
+    #[test]
+    fn counter_csrs() {
+        // Read a 64 bit counter with the `hi, lo, hi` idiom, while it counts
+        // up with each read.
+        fn read64(cpu: &mut Cpu, lo: riscv::Csr, hi: riscv::Csr) -> u64 {
+            loop {
+                cpu.state.instret += 1;
+                let upper = cpu.binary_csr_read(hi, 1);
+                cpu.state.instret += 1;
+                let lower = cpu.binary_csr_read(lo, 1);
+                cpu.state.instret += 1;
+                if cpu.binary_csr_read(hi, 1) == upper {
+                    return (upper as u64) << 32 | lower as u64;
+                }
+            }
+        }
+
+        let mut engine = test_engine();
+        with_cpu(&engine, 0, |cpu| {
+            cpu.state.instret = 0x1_fffffffd;
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Instret, 1), 0xfffffffd);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Instreth, 1), 1);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Minstreth, 1), 1);
+            // Without the latency model, cycles are retired instructions.
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Cycle, 1), 0xfffffffd);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Time, 1), 0xfffffffd);
+            // The low word wraps between the reads of the high word.
+            let pairs = [
+                (riscv::Csr::Instret, riscv::Csr::Instreth),
+                (riscv::Csr::Minstret, riscv::Csr::Minstreth),
+                (riscv::Csr::Cycle, riscv::Csr::Cycleh),
+                (riscv::Csr::Mcycle, riscv::Csr::Mcycleh),
+                (riscv::Csr::Time, riscv::Csr::Timeh),
+            ];
+            let mut last = 0;
+            for &(lo, hi) in pairs.iter().cycle().take(10) {
+                let value = read64(cpu, lo, hi);
+                assert!(value > last, "{:?} is 0x{:x} after 0x{:x}", lo, value, last);
+                assert!(value < cpu.state.instret);
+                last = value;
+            }
+            assert!(last > 0x2_00000000);
+        });
+        engine.latency = true;
+        with_cpu(&engine, 0, |cpu| {
+            cpu.state.instret = 5;
+            cpu.state.cycle = 0x3_00000007;
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Cycle, 1), 7);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Cycleh, 1), 3);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Mcycle, 1), 7);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Timeh, 1), 3);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Instret, 1), 5);
+        });
+    }
+
+    #[test]
+    fn shared_clock() {
+        // The clock advances by the instructions each hart retired since it
+        // last read the clock, while `mcycle` stays local to the hart.
+        let mut engine = test_engine();
+        engine.shared_clock = true;
+        with_cpus(&engine, &[0, 1], |cpus| {
+            cpus[0].state.instret = 10;
+            assert_eq!(cpus[0].binary_csr_read(riscv::Csr::Cycle, 1), 10);
+            cpus[1].state.instret = 3;
+            assert_eq!(cpus[1].binary_csr_read(riscv::Csr::Time, 1), 13);
+            assert_eq!(cpus[1].binary_csr_read(riscv::Csr::Mcycle, 1), 3);
+            cpus[0].state.instret = 12;
+            assert_eq!(cpus[0].binary_csr_read(riscv::Csr::Cycle, 1), 15);
+            assert_eq!(cpus[0].binary_csr_read(riscv::Csr::Cycleh, 1), 0);
+            assert_eq!(cpus[0].binary_csr_read(riscv::Csr::Cycle, 1), 15);
+        });
+
+        // Two successive `rdcycle` report whether the second is larger.
+        // rdcycle a0; rdcycle a1; sltu a0, a0, a1; slli a0, a0, 1;
+        // ori a0, a0, 1; lui t1, 0x40000; sw a0, 32(t1); wfi
+        let elf = kernel(&[
+            0xc0002573, 0xc00025f3, 0x00b53533, 0x00151513, 0x00156513, 0x40000337, 0x02a32023,
+            0x10500073,
+        ]);
+        for &shared in &[false, true] {
+            let mut engine = EngineBuilder::new().shared_clock(shared).build().unwrap();
+            engine.translate_elf(&elf).unwrap();
+            assert_eq!(engine.execute().unwrap(), 1);
+        }
+    }
+
+    #[test]
+    fn msip() {
+        // Hart 0 raises and clears the software interrupt of hart 1.
+        let engine = test_engine();
+        let msip = engine.config.address.clint;
+        with_cpus(&engine, &[0, 1], |cpus| {
+            assert_eq!(cpus[1].binary_csr_read(riscv::Csr::Mip, 1), 0);
+            cpus[0].binary_store(msip, 1 << 1, u32::max_value(), 2);
+            assert_eq!(cpus[0].binary_csr_read(riscv::Csr::Mip, 1), 0);
+            assert_eq!(cpus[1].binary_csr_read(riscv::Csr::Mip, 1), 1 << 3);
+            cpus[0].binary_store(msip, 0, u32::max_value(), 2);
+            assert_eq!(cpus[1].binary_csr_read(riscv::Csr::Mip, 1), 0);
+        });
+    }
+
+    #[test]
+    fn machine_csrs() {
+        let engine = test_engine();
+        with_cpu(&engine, 0, |cpu| {
+            // Only MIE and MPIE of `mstatus` are writable; MPP and FS are
+            // fixed.
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Mstatus, 1), 0x80007800);
+            cpu.binary_csr_write(riscv::Csr::Mstatus, !0, 1);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Mstatus, 1), 0x80007888);
+            cpu.binary_csr_write(riscv::Csr::Mstatus, 0, 1);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Mstatus, 1), 0x80007800);
+
+            cpu.binary_csr_write(riscv::Csr::Mscratch, 0xdeadbeef, 1);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Mscratch, 1), 0xdeadbeef);
+
+            // RV32IMAFDC with non-standard extensions, which cannot be
+            // changed.
+            let misa = cpu.binary_csr_read(riscv::Csr::Misa, 1);
+            assert_eq!(misa, 0x4080112d);
+            cpu.binary_csr_write(riscv::Csr::Misa, 0, 1);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Misa, 1), misa);
+
+            // Unimplemented CSRs read as zero and ignore writes.
+            cpu.binary_csr_write(riscv::Csr::Mcounteren, 7, 1);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Mcounteren, 1), 0);
+        });
+        assert!(!engine.had_error.load(Ordering::SeqCst));
+        assert_eq!(
+            *engine.unknown_csrs.lock().unwrap(),
+            [riscv::Csr::Mcounteren as u32].iter().copied().collect()
+        );
+    }
+
+    #[test]
+    fn traps() {
+        let engine = test_engine();
+        with_cpu(&engine, 0, |cpu| {
+            cpu.binary_csr_write(riscv::Csr::Mstatus, 1 << 3, 1);
+            cpu.binary_csr_write(riscv::Csr::Mtvec, 0x80001001, 1);
+            assert_eq!(cpu.binary_trap(0x80000010, 2, 0xffffffff), 0x80001000);
+            assert_eq!(
+                cpu.binary_csr_read(riscv::Csr::Mstatus, 1),
+                1 << 7 | MSTATUS_FIXED
+            );
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Mepc, 1), 0x80000010);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Mcause, 1), 2);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Mtval, 1), 0xffffffff);
+
+            // Without a handler, the trap is not taken.
+            cpu.binary_csr_write(riscv::Csr::Mtvec, 0, 1);
+            assert_eq!(cpu.binary_trap(0x80000020, 2, 0), 0);
+            assert_eq!(cpu.binary_csr_read(riscv::Csr::Mepc, 1), 0x80000010);
+        });
+    }
+
+    #[test]
+    fn abort_policy() {
+        // addi a0, x0, 1; <illegal>; addi a0, a0, 2; j exit;
+        // handler: csrr a0, mcause; addi a0, a0, 8; nop;
+        // exit: slli a0, a0, 1; ori a0, a0, 1; lui t1, 0x40000; sw a0, 32(t1); wfi
+        let elf = kernel(&[
+            0x00100513, 0x0000005b, 0x00250513, 0x0100006f, 0x34202573, 0x00850513, 0x00000013,
+            0x00151513, 0x00156513, 0x40000337, 0x02a32023, 0x10500073,
+        ]);
+        let run = |policy| {
+            let mut engine = EngineBuilder::new().abort_policy(policy).build().unwrap();
+            engine.translate_elf(&elf).unwrap();
+            engine.execute()
+        };
+
+        let e = run(AbortPolicy::Stop).unwrap_err();
+        let fault = e.downcast_ref::<HartFault>().unwrap();
+        assert_eq!(fault.pc, 0x80010004);
+
+        // The illegal instruction is skipped.
+        assert_eq!(run(AbortPolicy::Continue).unwrap(), 3);
+        // The handler sees the cause of an illegal instruction exception.
+        assert_eq!(run(AbortPolicy::Trap(0x80010010)).unwrap(), 10);
+    }
+
+    #[test]
+    fn abort_policy_parse() {
+        assert_eq!("stop".parse::<AbortPolicy>().unwrap(), AbortPolicy::Stop);
+        assert_eq!(
+            "continue".parse::<AbortPolicy>().unwrap(),
+            AbortPolicy::Continue
+        );
+        assert_eq!(
+            "trap=0x80010010".parse::<AbortPolicy>().unwrap(),
+            AbortPolicy::Trap(0x80010010)
+        );
+        assert!("trap=zz".parse::<AbortPolicy>().is_err());
+        assert!("other".parse::<AbortPolicy>().is_err());
+    }
+
+    #[test]
+    fn log_targets() {
+        // Count the messages about a store, per target.
+        static MEM: AtomicUsize = AtomicUsize::new(0);
+        static OTHER: AtomicUsize = AtomicUsize::new(0);
+        struct Logger;
+        impl log::Log for Logger {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+            fn log(&self, record: &log::Record) {
+                if record.args().to_string().starts_with("Store 0x80000100") {
+                    let count = match record.target() {
+                        log_target::MEM => &MEM,
+                        _ => &OTHER,
+                    };
+                    count.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+            fn flush(&self) {}
+        }
+        static LOGGER: Logger = Logger;
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let engine = test_engine();
+        with_cpu(&engine, 0, |cpu| {
+            cpu.binary_store(0x80000100, 1, u32::max_value(), 2);
+        });
+        assert_eq!(MEM.load(Ordering::SeqCst), 1);
+        assert_eq!(OTHER.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn unmapped_accesses() {
+        let engine = test_engine();
+        with_cpu(&engine, 0, |cpu| {
+            // DRAM and the TCDM are mapped.
+            cpu.binary_load(0x80000100, 2);
+            cpu.binary_store(0x80000100, 1, u32::max_value(), 2);
+            cpu.binary_load(0x100000, 2);
+            assert_eq!(engine.unmapped_reads.load(Ordering::SeqCst), 0);
+            assert_eq!(engine.unmapped_writes.load(Ordering::SeqCst), 0);
+
+            // The control registers are mapped.
+            cpu.binary_load(0x40000000, 2);
+            cpu.binary_store(0x40000000, 1, u32::max_value(), 2);
+            cpu.binary_rmw(0x80000100, 1, AtomicOp::Amoadd);
+            assert_eq!(engine.unmapped_reads.load(Ordering::SeqCst), 0);
+            assert_eq!(engine.unmapped_writes.load(Ordering::SeqCst), 0);
+
+            // No section or device covers these addresses.
+            cpu.binary_load(0x40000004, 2);
+            cpu.binary_load(0x30000000, 2);
+            cpu.binary_store(0x40000004, 1, u32::max_value(), 2);
+            cpu.binary_rmw(0x30000000, 1, AtomicOp::Amoadd);
         });
-        let args = args.join(" ");
+        assert_eq!(engine.unmapped_reads.load(Ordering::SeqCst), 3);
+        assert_eq!(engine.unmapped_writes.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn read_mem() {
+        let engine = Engine::new(unsafe { LLVMContextCreate() });
+        {
+            // Memory contents as left behind by a kernel writing the
+            // half-word array `[0x0201, 0x0403, 0x0605]` to 0x1000.
+            let mut memory = engine.memory.lock().unwrap();
+            memory.insert(0x1000, 0x04030201);
+            memory.insert(0x1004, 0x00000605);
+        }
+        assert_eq!(engine.read_word(0x1000), 0x04030201);
+        assert_eq!(engine.read_word(0x1006), 0x00000605);
+        assert_eq!(engine.read_word(0x2000), 0);
+        assert_eq!(engine.read_mem(0x1000, 6), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(engine.read_mem(0x1003, 4), vec![4, 5, 6, 0]);
+        assert_eq!(engine.read_mem(0x0ffe, 3), vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn validate() {
+        let mut engine = EngineBuilder::new().build().unwrap();
+        engine.validate(&doubling_kernel()).unwrap();
+
+        // The unsupported instruction at 0x80010004 is reported once, even
+        // though both clusters translate it.
+        let elf = kernel(&[0x00100513, 0x0000005b, 0x10500073]);
+        let mut engine = EngineBuilder::new().num_clusters(2).build().unwrap();
+        let err = engine.validate(&elf).unwrap_err();
+        let unsupported = &err.downcast_ref::<ValidationError>().unwrap().unsupported;
+        assert_eq!(unsupported.len(), 1);
+        assert_eq!(
+            (unsupported[0].addr, unsupported[0].raw),
+            (0x80010004, 0x5b)
+        );
+        assert!(
+            err.to_string()
+                .starts_with("1 unsupported instruction(s): 0x80010004 (0000005b): Unsupported"),
+            "{}",
+            err
+        );
+
+        // A custom instruction handler supports it.
+        let mut engine = EngineBuilder::new().build().unwrap();
+        engine.add_custom_inst(0x7f, 0x5b, Box::new(|_, _| ()));
+        engine.validate(&elf).unwrap();
+
+        // No core has the DMA which `dmsrc a0, a1` needs.
+        let mut engine = EngineBuilder::new().build().unwrap();
+        let topology = Topology {
+            clusters: vec![vec![CoreFeatures {
+                ssr: true,
+                dma: false,
+            }]],
+        };
+        engine.set_topology(topology).unwrap();
+        let err = engine
+            .validate(&kernel(&[0x00b5002b, 0x10500073]))
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("needs the DMA feature, which no core of cluster 0 has"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn topology() {
+        let mut engine = test_engine();
+        engine.base_hartid = 4;
+        let compute = CoreFeatures {
+            ssr: true,
+            dma: false,
+        };
+        let manager = CoreFeatures {
+            ssr: false,
+            dma: true,
+        };
+        let topology = Topology {
+            clusters: vec![vec![compute, compute, manager], vec![compute, manager]],
+        };
+        engine.set_topology(topology.clone()).unwrap();
+        assert_eq!((engine.num_clusters, engine.num_cores), (2, 3));
+        assert_eq!(engine.config.memory.len(), 2);
+        assert_eq!(engine.topology(), topology);
+
+        // Harts are numbered consecutively across clusters of different size.
+        let locations: Vec<_> = (3..10).map(|h| engine.hart_location(h)).collect();
+        assert_eq!(
+            locations,
+            vec![
+                None,
+                Some((0, 0)),
+                Some((0, 1)),
+                Some((0, 2)),
+                Some((1, 0)),
+                Some((1, 1)),
+                None
+            ]
+        );
+        assert_eq!(topology.num_harts(), 5);
+        assert_eq!(topology.cluster_offset(1), 3);
+        assert_eq!(topology.common_features(0), 0);
+        assert_eq!(
+            topology.any_features(0),
+            CoreFeatures::SSR | CoreFeatures::DMA
+        );
+        let compute_only = Topology {
+            clusters: vec![vec![compute; 2]],
+        };
+        assert_eq!(compute_only.common_features(0), CoreFeatures::SSR);
+        assert_eq!(compute_only.any_features(0), CoreFeatures::SSR);
+
+        // Features default to present when parsed from a configuration.
+        let arch: crate::configuration::Architecture = serde_yaml::from_str(
+            "{num_cores: 0, num_clusters: 0, base_hartid: 0, \
+             topology: {clusters: [[{}, {ssr: false}]]}}",
+        )
+        .unwrap();
+        assert_eq!(
+            arch.topology.unwrap().clusters[0],
+            vec![CoreFeatures::default(), manager]
+        );
+
+        assert!(engine.set_topology(Topology { clusters: vec![] }).is_err());
+        assert!(engine
+            .set_topology(Topology {
+                clusters: vec![vec![compute], vec![]]
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn builder() {
+        let engine = EngineBuilder::new()
+            .num_clusters(2)
+            .num_cores(3)
+            .base_hartid(4)
+            .tcdm(0x10_0000, 0x10_8000, 3)
+            .trace(true)
+            .opt_jit(false)
+            .build()
+            .unwrap();
+        assert!(engine.owns_context);
+        assert!(engine.trace && !engine.opt_jit && engine.opt_llvm);
+        assert_eq!(
+            (engine.num_clusters, engine.num_cores, engine.base_hartid),
+            (2, 3, 4)
+        );
+        assert_eq!(engine.config.architecture.num_clusters, 2);
+        assert_eq!(engine.config.memory.len(), 2);
+        assert_eq!(engine.modules.len(), 2);
+        for memory in &engine.config.memory {
+            assert_eq!(
+                (memory.tcdm.start, memory.tcdm.end, memory.tcdm.latency),
+                (0x10_0000, 0x10_8000, 3)
+            );
+        }
+        assert_eq!(engine.hart_location(8), Some((1, 1)));
+        drop(engine);
+
+        // The explicit architecture overrides the configuration.
+        let engine = EngineBuilder::new()
+            .configuration(Configuration::new(1, 8, 0))
+            .num_clusters(4)
+            .build()
+            .unwrap();
+        assert_eq!((engine.num_clusters, engine.num_cores), (4, 8));
+        assert_eq!(engine.config.memory.len(), 4);
 
-        // Assemble the trace line.
-        let line = format!(
-            "{:08} {:08} {:04} {:08x}  {:38}  # DASM({:08x})",
-            self.state.cycle, self.state.instret, self.hartid, addr, args, inst
-        );
-        println!("{}", line);
+        assert!(EngineBuilder::new().num_cores(0).build().is_err());
+        assert!(EngineBuilder::new()
+            .tcdm(0x2000, 0x1000, 1)
+            .build()
+            .is_err());
     }
 
-    fn binary_wfi(&mut self) -> u32 {
-        let mut wus = self.wakeup_state.lock().unwrap();
-        // Don't wfi if any interrupt is pending. Mip is updated before each instruction in tran.rs
-        let mie = self.binary_csr_read(riscv::Csr::Mie, 1);
-        let mip = self.binary_csr_read(riscv::Csr::Mip, 1);
-        let hartid = self.hartid - self.engine.base_hartid;
-        if mip & mie != 0 {
-            trace!(" hart: {} wfi is nop. mip: {:x}", self.hartid, mip);
-            // clear a possible outstanding wakeup request
-            wus.req[hartid] = 0;
-            // Trigger IRQ check on next instruction
-            self.state.irq.sample_ctr = u32::MAX - 1;
-            return 0;
-        }
-        // Set own wfi.
-        self.state.wfi = true;
-        wus.wfi[hartid] = true;
-        wus.num += 1;
-        // Wait for the wake up call: poll while this hart is not requested to wake and
-        // exit iff all harts are in the WFI loop and no requests are outstanding
-        let mut do_poll = wus.req[hartid] == 0;
-        let mut do_exit =
-            wus.num == wus.req.len() && wus.req.iter().filter(|&n| *n != 0).count() == 0;
-        std::mem::drop(wus);
-        while do_poll {
-            // Check if everyone is sleeping
-            if do_exit {
-                return 1;
+    #[test]
+    fn cycle_model() {
+        let path = std::env::temp_dir().join(format!("banshee-{}-cycles.json", std::process::id()));
+        std::fs::write(&path, r#"{"mul": 5, "branch": 2}"#).unwrap();
+        let mut engine = test_engine();
+        engine.load_cycle_model(&path).unwrap();
+        assert_eq!(engine.config.cycle_model.mul, 5);
+        assert_eq!(engine.config.cycle_model.fma, 1);
+
+        // Square a number four times, where each `mul` waits for the previous
+        // one, and store the result.
+        // li a0, 3; li a1, 4; 1: mul a0, a0, a0; addi a1, a1, -1; bnez a1, 1b
+        // lui t1, 0x80020; sw a0, 0(t1)
+        // lui t2, 0x40000; li t3, 1; sw t3, 32(t2); wfi
+        let elf = kernel(&[
+            0x00300513, 0x00400593, 0x02a50533, 0xfff58593, 0xfe059ce3, 0x80020337, 0x00a32023,
+            0x400003b7, 0x00100e13, 0x03c3a023, 0x10500073,
+        ]);
+        let cycles = |model: Option<&Path>| {
+            let mut engine = EngineBuilder::new().latency(true).build().unwrap();
+            if let Some(path) = model {
+                engine.load_cycle_model(path).unwrap();
             }
-            std::thread::yield_now();
-            let wus = self.wakeup_state.lock().unwrap();
-            do_poll = wus.req[hartid] == 0;
-            do_exit = wus.num == wus.req.len() && wus.req.iter().filter(|&n| *n != 0).count() == 0;
-            std::mem::drop(wus);
-        }
-        let mut wus = self.wakeup_state.lock().unwrap();
-        // Someone woke us up --> Clear the flag
-        let cycle = wus.req[hartid];
-        self.state.cycle = std::cmp::max(self.state.cycle, cycle as u64);
-        self.state.wfi = false;
-        wus.req[hartid] = 0;
-        wus.wfi[hartid] = false;
-        wus.num -= 1;
-        // Trigger IRQ check on next instruction
-        self.state.irq.sample_ctr = u32::MAX - 1;
-        return 0;
+            engine.translate_elf(&elf).unwrap();
+            let stats = engine.execute_with_stats().unwrap();
+            assert_eq!(stats.exit_code, 0);
+            assert_eq!(engine.read_word(0x80020000), 3u32.pow(16));
+            stats.harts[0].cycles.unwrap()
+        };
+        // Each iteration takes three cycles, or as long as the `mul` once it
+        // costs more. The store waits for the last one.
+        assert_eq!(cycles(None), 20);
+        assert_eq!(cycles(Some(&path)), 27);
+
+        // Unknown categories are rejected.
+        std::fs::write(&path, r#"{"mul": 5, "mult": 3}"#).unwrap();
+        assert!(engine.load_cycle_model(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
     }
 
-    fn binary_check_clint(&mut self) -> u32 {
-        // read the clint software interrupt and return 1 if interrupt pending
-        let hartid = self.hartid;
-        return (self.clint[(hartid / 32) as usize].load(Ordering::SeqCst) & (1 << (hartid % 32)))
-            >> (hartid % 32);
+    #[test]
+    fn exit_status() {
+        // Patch `li t1, 1` before the store to the scratch register into
+        // `li t1, 7`, which reports exit code 3 under the Snitch protocol.
+        let mut elf = crate::loader::parse_elf(include_bytes!("../tests/bin/dummy")).unwrap();
+        let text = elf
+            .sections
+            .iter_mut()
+            .find(|section| section.shdr.name == ".text")
+            .unwrap();
+        assert_eq!(&text.data[0x3c..0x40], &0x00100313u32.to_le_bytes());
+        text.data[0x3c..0x40].copy_from_slice(&0x00700313u32.to_le_bytes());
+
+        let mut engine = EngineBuilder::new().build().unwrap();
+        engine.translate_elf(&elf).unwrap();
+        assert_eq!(engine.execute_status().unwrap(), ExitStatus::Halted(3));
     }
 
-    fn binary_check_cl_clint(&mut self) -> u32 {
-        // read the cluster-local clint software interrupt and return 1 if interrupt pending
-        let hartid = self.hartid - self.engine.base_hartid - self.cluster_id * self.num_cores;
-        return (self.cl_clint.load(Ordering::SeqCst) as u32 & (1 << (hartid % 32)))
-            >> (hartid % 32);
+    /// A binary which doubles the word at the start of the TCDM into the next
+    /// word, and reports exit code 0.
+    fn doubling_kernel() -> elf::File {
+        // lui t0, 0x100; lw a0, 0(t0); slli a0, a0, 1; sw a0, 4(t0);
+        // lui t1, 0x40000; li t2, 1; sw t2, 32(t1); wfi
+        kernel(&[
+            0x001002b7, 0x0002a503, 0x00151513, 0x00a2a223, 0x40000337, 0x00100393, 0x02732023,
+            0x10500073,
+        ])
     }
 
-    /// A simple barrier across all cores in the cluster.
-    ///
-    /// Uses an atomic barrier flag shared across all CPU threads in a cluster.
-    /// Core 0 coordinates. In a first phase, it waits until all cores but
-    /// itself have bumped the flag, then bumps itself and waits for all cores
-    /// to make it through.
-    fn cluster_barrier(&self) {
-        let core_id = self.hartid - self.cluster_base_hartid;
-        let core_num = self.num_cores;
-        if core_id == 0 {
-            while self.barrier.load(Ordering::Relaxed) < core_num - 1 {
-                std::thread::yield_now();
-            }
-            self.barrier.fetch_add(1, Ordering::Relaxed);
-            while self.barrier.load(Ordering::Relaxed) < 2 * core_num - 1 {
-                std::thread::yield_now();
-            }
-            self.barrier.store(0, Ordering::Relaxed);
-        } else {
-            while self.barrier.load(Ordering::Relaxed) >= core_num {
-                std::thread::yield_now();
-            }
-            self.barrier.fetch_add(1, Ordering::Relaxed);
-            while self.barrier.load(Ordering::Relaxed) < core_num {
-                std::thread::yield_now();
-            }
-            self.barrier.fetch_add(1, Ordering::Relaxed);
-        }
+    /// A binary whose only section is the code of a kernel.
+    fn kernel(insts: &[u32]) -> elf::File {
+        let mut elf = crate::loader::parse_elf(include_bytes!("../tests/bin/dummy")).unwrap();
+        let code: Vec<u8> = insts.iter().flat_map(|inst| inst.to_le_bytes()).collect();
+        let index = elf
+            .sections
+            .iter()
+            .position(|section| section.shdr.name == ".text")
+            .unwrap();
+        let mut text = elf.sections.swap_remove(index);
+        text.shdr.size = code.len() as u64;
+        text.data = code;
+        elf.sections = vec![text];
+        elf
     }
 
-    fn wake(&self, hart: u32) {
-        // Lock is released once out of scope
-        let mut wus = self.wakeup_state.lock().unwrap();
-        if hart as i32 == -1 {
-            for i in 0..wus.req.len() {
-                wus.req[i] = self.state.cycle + 1;
-            }
-        } else if (hart as usize) < wus.req.len() {
-            wus.req[hart as usize] = self.state.cycle + 1;
+    #[test]
+    fn rerun_with_inputs() {
+        // Translate once, then execute with two different inputs.
+        let elf = doubling_kernel();
+        let mut engine = EngineBuilder::new().build().unwrap();
+        engine.translate_elf(&elf).unwrap();
+        // Only the words of the binary are kept to restore them.
+        assert_eq!(engine.preloaded_memory.lock().unwrap().len(), 8);
+        for &input in &[5u32, 21] {
+            engine.reset_memory();
+            assert_eq!(engine.read_mem(0x100000, 8), vec![0; 8]);
+            engine.write_mem(0x100000, &input.to_le_bytes());
+            assert_eq!(engine.execute().unwrap(), 0);
+            assert_eq!(engine.read_word(0x100004), 2 * input);
         }
-        trace!(
-            "[{}] wake num: {:?} req: {:?} wfi: {:?}",
-            self.hartid,
-            wus.num,
-            wus.req,
-            wus.wfi,
-        );
-    }
 
-    /*
-     * Flexfloat Conversions
-     */
-    pub unsafe fn binary_fp64_op_cvt_to_f(
-        rs1: u64,
-        op: flexfloat::FfOpCvt,
-        fpmode_src: bool,
-        fpmode_dst: bool,
-    ) -> u64 {
-        flexfloat::ff_instruction_cvt_to_d(rs1, op, fpmode_src, fpmode_dst)
+        // Byte-wise writes keep the rest of the word.
+        engine.write_mem(0x100005, &[0xab, 0xcd]);
+        assert_eq!(engine.read_word(0x100004), 0x00cdab2a);
+        assert!(engine.translate_elf(&elf).is_err());
     }
 
-    pub unsafe fn binary_fp32_op_cvt_to_f(
-        rs1: u64,
-        op: flexfloat::FfOpCvt,
-        fpmode_src: bool,
-        fpmode_dst: bool,
-    ) -> i32 {
-        flexfloat::ff_instruction_cvt_to_s(rs1, op, fpmode_src, fpmode_dst)
-    }
+    #[test]
+    fn thread_local() {
+        // Each hart adds its ID to a thread-local variable initialized to
+        // 0x1000, and stores the result and its `tp` at 0x100000 + 8h.
+        let mut elf = kernel(&[
+            0xf1402573, 0x00022283, 0x00a282b3, 0x00522023, 0x00022303, 0x00351393, 0x00100eb7,
+            0x01d383b3, 0x0063a023, 0x0043a223, 0x00051863, 0x40000337, 0x00100393, 0x02732023,
+            0x10500073,
+        ]);
+        let tls = |name: &str, shtype, addr, size, data| elf::Section {
+            shdr: elf::types::SectionHeader {
+                name: name.to_string(),
+                shtype,
+                flags: elf::types::SectionFlag(
+                    elf::types::SHF_ALLOC.0 | elf::types::SHF_WRITE.0 | elf::types::SHF_TLS.0,
+                ),
+                addr,
+                offset: 0,
+                size,
+                link: 0,
+                info: 0,
+                addralign: 4,
+                entsize: 0,
+            },
+            data,
+        };
+        elf.sections.push(tls(
+            ".tdata",
+            elf::types::SHT_PROGBITS,
+            0x80011000,
+            4,
+            0x1000u32.to_le_bytes().to_vec(),
+        ));
+        elf.sections
+            .push(tls(".tbss", elf::types::SHT_NOBITS, 0x80011004, 8, vec![]));
 
-    pub unsafe fn binary_fp16_op_cvt_from_f(
-        rs1: u64,
-        op: flexfloat::FfOpCvt,
-        fpmode_src: bool,
-        fpmode_dst: bool,
-    ) -> i32 {
-        flexfloat::ff_instruction_cvt_from_h(rs1, op, fpmode_src, fpmode_dst)
+        // Interleave the harts, so that both store before either loads.
+        let mut engine = EngineBuilder::new()
+            .num_cores(2)
+            .deterministic(1)
+            .build()
+            .unwrap();
+        engine.translate_elf(&elf).unwrap();
+        let image = engine.images[0].tls.clone().unwrap();
+        assert_eq!(image.base, 0x80011010);
+        assert_eq!(image.stride, 16);
+        assert_eq!(image.template, [0x00, 0x10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(engine.heap.unwrap().0, 0x80011030);
+        assert_eq!(engine.execute().unwrap(), 0);
+        assert_eq!(engine.read_word(0x100000), 0x1000);
+        assert_eq!(engine.read_word(0x100004), 0x80011010);
+        assert_eq!(engine.read_word(0x100008), 0x1001);
+        assert_eq!(engine.read_word(0x10000c), 0x80011020);
+        assert_eq!(engine.read_word(0x80011000), 0x1000);
+
+        // The copies are restored to the template for the next execution.
+        engine.reset();
+        assert_eq!(engine.read_word(0x80011020), 0x1000);
     }
 
-    pub unsafe fn binary_fp16_op_cvt_to_f(
-        rs1: u64,
-        op: flexfloat::FfOpCvt,
-        fpmode_src: bool,
-        fpmode_dst: bool,
-    ) -> u16 {
-        flexfloat::ff_instruction_cvt_to_h(rs1, op, fpmode_src, fpmode_dst)
+    #[test]
+    fn memory_map_overlap() {
+        let data = |addr, size| elf::Section {
+            shdr: elf::types::SectionHeader {
+                name: ".data".to_string(),
+                shtype: elf::types::SHT_PROGBITS,
+                flags: elf::types::SectionFlag(elf::types::SHF_ALLOC.0 | elf::types::SHF_WRITE.0),
+                addr,
+                offset: 0,
+                size,
+                link: 0,
+                info: 0,
+                addralign: 4,
+                entsize: 0,
+            },
+            data: vec![0; size as usize],
+        };
+        let mut elf = kernel(&[0x10500073]);
+        elf.sections.push(data(0x10000010, 4));
+
+        // The `.data` section overlaps the UART.
+        let mut engine = EngineBuilder::new().uart(0x10000000).build().unwrap();
+        let err = engine.translate_elf(&elf).unwrap_err().to_string();
+        assert!(
+            err.contains("Section `.data` at 0x10000010..0x10000014"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("overlaps the UART at 0x10000000..0x10000020"),
+            "{}",
+            err
+        );
+
+        // The policy can downgrade it to a warning.
+        let mut engine = EngineBuilder::new()
+            .uart(0x10000000)
+            .overlap_policy(OverlapPolicy::Warn)
+            .build()
+            .unwrap();
+        engine.translate_elf(&elf).unwrap();
+
+        // Sections within the TCDM are fine, but not across its end.
+        elf.sections[1] = data(0x100000, 0x20);
+        EngineBuilder::new()
+            .build()
+            .unwrap()
+            .translate_elf(&elf)
+            .unwrap();
+        elf.sections[1] = data(0x11fff0, 0x20);
+        let mut engine = EngineBuilder::new().build().unwrap();
+        let err = engine.translate_elf(&elf).unwrap_err().to_string();
+        assert!(
+            err.contains("straddles the boundary of the TCDM of cluster 0"),
+            "{}",
+            err
+        );
     }
 
-    pub unsafe fn binary_fp8_op_cvt_from_f(
-        rs1: u64,
-        op: flexfloat::FfOpCvt,
-        fpmode_src: bool,
-        fpmode_dst: bool,
-    ) -> i32 {
-        flexfloat::ff_instruction_cvt_from_b(rs1, op, fpmode_src, fpmode_dst)
+    #[test]
+    fn reset() {
+        // Execute the translated kernel three times, with an error and a
+        // register set for a single execution in between.
+        let elf = doubling_kernel();
+        let mut engine = EngineBuilder::new().build().unwrap();
+        engine.translate_elf(&elf).unwrap();
+        for &input in &[3u32, 100, 0x7fff_ffff] {
+            engine.reset();
+            assert_eq!(engine.read_mem(0x100000, 8), vec![0; 8]);
+            assert_eq!(engine.get_reg(0, 10).unwrap(), 0);
+            engine.write_mem(0x100000, &input.to_le_bytes());
+            assert_eq!(engine.execute().unwrap(), 0);
+            assert_eq!(engine.read_word(0x100004), input.wrapping_mul(2));
+            engine.had_error.store(true, Ordering::SeqCst);
+            engine.set_reg(0, 10, 1).unwrap();
+        }
+        assert_eq!(engine.jits.lock().unwrap().len(), 1);
     }
 
-    pub unsafe fn binary_fp8_op_cvt_to_f(
-        rs1: u64,
-        op: flexfloat::FfOpCvt,
-        fpmode_src: bool,
-        fpmode_dst: bool,
-    ) -> u8 {
-        flexfloat::ff_instruction_cvt_to_b(rs1, op, fpmode_src, fpmode_dst)
+    #[test]
+    fn breakpoints() {
+        // Inspect the doubled value before it is stored, then stop before the
+        // exit code is written.
+        let elf = doubling_kernel();
+        let text = elf.ehdr.entry as u32;
+        let hits = std::sync::Arc::new(Mutex::new(vec![]));
+        let mut engine = EngineBuilder::new().build().unwrap();
+        let log = hits.clone();
+        engine.add_breakpoint(
+            text + 12,
+            Box::new(move |ctx| {
+                log.lock().unwrap().push((
+                    ctx.hartid(),
+                    ctx.pc(),
+                    ctx.instret(),
+                    ctx.reg(10),
+                    ctx.read_mem(0x100000, 4),
+                ));
+                BreakpointAction::Continue
+            }),
+        );
+        engine.add_breakpoint(text + 16, Box::new(|_| BreakpointAction::Stop));
+        engine.translate_elf(&elf).unwrap();
+        engine.write_mem(0x100000, &7u32.to_le_bytes());
+
+        assert_eq!(engine.execute_status().unwrap(), ExitStatus::Running);
+        assert_eq!(
+            *hits.lock().unwrap(),
+            vec![(0, text + 12, 3, 14, 7u32.to_le_bytes().to_vec())]
+        );
+        assert!(engine.stop.load(Ordering::SeqCst));
+        assert_eq!(engine.read_word(0x100004), 14);
     }
 
-    /*
-     * Flexfloat Comparisons
-     */
-    pub unsafe fn binary_fp16_op_cmp(
-        rs1: u16,
-        rs2: u16,
-        op: flexfloat::FlexfloatOpCmp,
-        fpmode_dst: bool,
-    ) -> bool {
-        flexfloat::ff_instruction_cmp_h(rs1, rs2, op, fpmode_dst)
+    #[test]
+    fn per_hart_exit_codes() {
+        // Both harts write to the same exit code register: hart 0 reports 2,
+        // hart 1 reports 3.
+        // csrr a0, mhartid; addi a0, a0, 2; slli a0, a0, 1; ori a0, a0, 1;
+        // lui t1, 0x40000; sw a0, 32(t1); wfi
+        let elf = kernel(&[
+            0xf1402573, 0x00250513, 0x00151513, 0x00156513, 0x40000337, 0x02a32023, 0x10500073,
+        ]);
+        let mut engine = EngineBuilder::new().num_cores(2).build().unwrap();
+        engine.translate_elf(&elf).unwrap();
+        let stats = engine.execute_with_stats().unwrap();
+        assert_eq!(
+            stats.exit_codes(),
+            vec![(0, 2), (1, 3)].into_iter().collect()
+        );
+        assert_eq!(stats.harts[1].exit_value, Some(7));
+        assert!([2, 3].contains(&stats.exit_code));
     }
 
-    pub unsafe fn binary_fp8_op_cmp(
-        rs1: u8,
-        rs2: u8,
-        op: flexfloat::FlexfloatOpCmp,
-        fpmode_dst: bool,
-    ) -> bool {
-        flexfloat::ff_instruction_cmp_b(rs1, rs2, op, fpmode_dst)
+    #[test]
+    fn slowest_hart() {
+        // Hart 1 counts down from 100, hart 0 from 10.
+        // csrr a0, mhartid; li t0, 100; bnez a0, 1f; li t0, 10;
+        // 1: addi t0, t0, -1; bnez t0, 1b;
+        // lui t1, 0x40000; li t2, 1; sw t2, 32(t1); wfi
+        let elf = kernel(&[
+            0xf1402573, 0x06400293, 0x00051463, 0x00a00293, 0xfff28293, 0xfe029ee3, 0x40000337,
+            0x00100393, 0x02732023, 0x10500073,
+        ]);
+        let mut engine = EngineBuilder::new().num_cores(2).build().unwrap();
+        engine.translate_elf(&elf).unwrap();
+        let stats = engine.execute_with_stats().unwrap();
+        assert_eq!(stats.slowest_hart, Some(1));
+        // Hart 0 additionally executes `li t0, 10`.
+        assert_eq!(
+            stats.harts[1].instret - stats.harts[0].instret,
+            2 * (100 - 10) - 1
+        );
+        for hart in &stats.harts {
+            let (start, end) = (hart.start.unwrap(), hart.end.unwrap());
+            assert!(start <= end && end <= stats.duration);
+        }
     }
 
-    /*
-     * Flexfloat Operations
-     */
-    pub unsafe fn binary_fp16_op(
-        rs1: u16,
-        rs2: u16,
-        rs3: u16,
-        op: flexfloat::FlexfloatOp,
-        fpmode_dst: bool,
-    ) -> u16 {
-        flexfloat::ff_instruction_h(rs1, rs2, rs3, op, fpmode_dst)
+    #[test]
+    fn deterministic() {
+        // Hart h appends its ID to a log at 0x100004 in 4h+3 iterations,
+        // through a counter at 0x100000. After the cluster barrier, it
+        // appends 16+h. Hart 0 then exits, and all harts sleep in `wfi`.
+        let elf = kernel(&[
+            0xf1402573, 0x00100337, 0x00251293, 0x00328293, 0x00100e13, 0x01c32eaf, 0x002e9e93,
+            0x006e8eb3, 0x00aea223, 0xfff28293, 0xfe0296e3, 0x40000f37, 0x038f2f83, 0x01c32eaf,
+            0x002e9e93, 0x006e8eb3, 0x01050393, 0x007ea223, 0x00051663, 0x00100393, 0x027f2023,
+            0x10500073,
+        ]);
+        // Record the instructions the harts execute, in order.
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<(usize, u32)>>);
+        impl AccessObserver for std::sync::Arc<Recorder> {
+            fn on_instruction(&self, hartid: usize, pc: u32) {
+                self.0.lock().unwrap().push((hartid, pc));
+            }
+        }
+        let run = |quantum| {
+            let mut engine = EngineBuilder::new()
+                .num_cores(3)
+                .deterministic(quantum)
+                .build()
+                .unwrap();
+            let recorder = std::sync::Arc::new(Recorder::default());
+            engine.set_access_observer(Box::new(recorder.clone()));
+            engine.translate_elf(&elf).unwrap();
+            let stats = engine.execute_with_stats().unwrap();
+            assert_eq!(stats.exit_code, 0);
+            let log: Vec<_> = (0..=24)
+                .map(|i| engine.read_word(0x100000 + 4 * i))
+                .collect();
+            let harts: Vec<_> = stats
+                .harts
+                .iter()
+                .map(|hart| (hart.instret, hart.cycles, hart.finished))
+                .collect();
+            let trace = std::mem::take(&mut *recorder.0.lock().unwrap());
+            (log, harts, trace)
+        };
+        // Every hart finishes its loop within its first turn.
+        let (log, _, _) = run(1000);
+        assert_eq!(log[0], 24);
+        let mut expected = vec![0; 3];
+        expected.extend(vec![1; 7]);
+        expected.extend(vec![2; 11]);
+        expected.extend(&[16, 17, 18]);
+        assert_eq!(&log[1..], &expected[..]);
+
+        // The harts interleave within their loops, the same way every time.
+        let (log, harts, trace) = run(2);
+        assert_ne!(&log[1..], &expected[..]);
+        let switches = trace.windows(2).filter(|w| w[0].0 != w[1].0).count();
+        assert!(switches > 10, "{}", switches);
+        let mut post_barrier = log[22..].to_vec();
+        post_barrier.sort_unstable();
+        assert_eq!(post_barrier, vec![16, 17, 18]);
+        assert!(harts.iter().all(|&(_, _, finished)| finished));
+        for _ in 0..3 {
+            assert_eq!(run(2), (log.clone(), harts.clone(), trace.clone()));
+        }
     }
-    pub unsafe fn binary_fp8_op(
-        rs1: u8,
-        rs2: u8,
-        rs3: u8,
-        op: flexfloat::FlexfloatOp,
-        fpmode_dst: bool,
-    ) -> u8 {
-        flexfloat::ff_instruction_b(rs1, rs2, rs3, op, fpmode_dst)
+
+    #[test]
+    fn access_observer() {
+        // Record the TCDM and peripheral accesses, and the instructions.
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<(&'static str, usize, u32, u32, u32)>>);
+        impl AccessObserver for std::sync::Arc<Recorder> {
+            fn on_load(&self, hartid: usize, pc: u32, addr: u32, size: u32) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(("load", hartid, pc, addr, size));
+            }
+            fn on_store(&self, hartid: usize, pc: u32, addr: u32, size: u32) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(("store", hartid, pc, addr, size));
+            }
+            fn on_instruction(&self, hartid: usize, pc: u32) {
+                self.0.lock().unwrap().push(("inst", hartid, pc, 0, 0));
+            }
+        }
+        let elf = doubling_kernel();
+        let text = elf.ehdr.entry as u32;
+        let recorder = std::sync::Arc::new(Recorder::default());
+        let mut engine = EngineBuilder::new().build().unwrap();
+        engine.set_access_observer(Box::new(recorder.clone()));
+        engine.translate_elf(&elf).unwrap();
+        assert_eq!(engine.execute().unwrap(), 0);
+
+        let events = recorder.0.lock().unwrap();
+        let accesses: Vec<_> = events.iter().filter(|e| e.0 != "inst").copied().collect();
+        assert_eq!(
+            accesses,
+            vec![
+                ("load", 0, text + 4, 0x100000, 4),
+                ("store", 0, text + 12, 0x100004, 4),
+                ("store", 0, text + 24, 0x40000020, 4),
+            ]
+        );
+        let pcs: Vec<_> = events
+            .iter()
+            .filter(|e| e.0 == "inst")
+            .map(|e| e.2)
+            .collect();
+        assert_eq!(pcs, (0..8).map(|i| text + 4 * i).collect::<Vec<_>>());
     }
-    pub unsafe fn binary_fp16_to_fp32_op(
-        rs1: u16,
-        rs2: u16,
-        rs3: f32,
-        op: flexfloat::FlexfloatOpExp,
-        fpmode_src: bool,
-    ) -> f32 {
-        flexfloat::ff_fp16_to_fp32_op(rs1, rs2, rs3, op, fpmode_src)
+
+    #[test]
+    fn mem_backend() {
+        // Record the accesses to a backend, which stores the words in a map.
+        type Log = std::sync::Arc<Mutex<Vec<(&'static str, u32, u32)>>>;
+        struct Recorder(HashMap<u32, u32>, Log);
+        impl MemBackend for Recorder {
+            fn load_word(&mut self, addr: u32) -> u32 {
+                let value = self.0.get(&addr).copied().unwrap_or(0);
+                self.1.lock().unwrap().push(("load", addr, value));
+                value
+            }
+            fn store_word(&mut self, addr: u32, value: u32) {
+                self.0.insert(addr, value);
+                self.1.lock().unwrap().push(("store", addr, value));
+            }
+        }
+
+        // lui t0, 0x80020; lw t1, 0(t0); addi t1, t1, 1; sw t1, 4(t0);
+        // lui t2, 0x40000; li t3, 1; sw t3, 32(t2); wfi
+        let mut elf = kernel(&[
+            0x800202b7, 0x0002a303, 0x00130313, 0x0062a223, 0x400003b7, 0x00100e13, 0x03c3a023,
+            0x10500073,
+        ]);
+        elf.sections.push(elf::Section {
+            shdr: elf::types::SectionHeader {
+                name: ".data".to_string(),
+                shtype: elf::types::SHT_PROGBITS,
+                flags: elf::types::SectionFlag(elf::types::SHF_ALLOC.0 | elf::types::SHF_WRITE.0),
+                addr: 0x80020000,
+                offset: 0,
+                size: 4,
+                link: 0,
+                info: 0,
+                addralign: 4,
+                entsize: 0,
+            },
+            data: 41u32.to_le_bytes().to_vec(),
+        });
+        let log = Log::default();
+        let mut engine = EngineBuilder::new().build().unwrap();
+        engine.set_mem_backend(Box::new(Recorder(HashMap::new(), log.clone())));
+        engine.translate_elf(&elf).unwrap();
+
+        // The binary is stored into the backend.
+        assert!(log.lock().unwrap().contains(&("store", 0x80020000, 41)));
+        log.lock().unwrap().clear();
+        assert_eq!(engine.execute().unwrap(), 0);
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec![("load", 0x80020000, 41), ("store", 0x80020004, 42)]
+        );
+        assert_eq!(engine.read_word(0x80020004), 42);
+        assert_eq!(engine.memory.lock().unwrap().get(&0x80020004), None);
+
+        // The TCDM stays in the engine's memory.
+        log.lock().unwrap().clear();
+        engine.write_mem(0x100000, &[1, 2, 3, 4]);
+        assert_eq!(engine.read_word(0x100000), 0x04030201);
+        assert!(log.lock().unwrap().is_empty());
     }
-    pub unsafe fn binary_fp8_to_fp16_op(
-        rs1: u8,
-        rs2: u8,
-        rs3: u16,
-        op: flexfloat::FlexfloatOpExp,
-        fpmode_src: bool,
-        fpmode_dst: bool,
-    ) -> u16 {
-        flexfloat::ff_fp8_to_fp16_op(rs1, rs2, rs3, op, fpmode_src, fpmode_dst)
+
+    #[test]
+    fn livelock() {
+        // `1: j 1b` spins forever.
+        let elf = kernel(&[0x0000006f]);
+        let mut engine = EngineBuilder::new()
+            .livelock_threshold(1000)
+            .build()
+            .unwrap();
+        engine.translate_elf(&elf).unwrap();
+        match engine.execute_status().unwrap() {
+            ExitStatus::Error(ExecError::Fault(fault)) => {
+                assert_eq!((fault.hartid, fault.pc), (0, elf.ehdr.entry as u32));
+                assert!(fault.reason.contains("livelock"), "{}", fault.reason);
+            }
+            status => panic!("unexpected {:?}", status),
+        }
+
+        // A loop which counts down changes its registers.
+        // li t0, 100; 1: addi t0, t0, -1; bnez t0, 1b;
+        // lui t1, 0x40000; li t2, 1; sw t2, 32(t1); wfi
+        let elf = kernel(&[
+            0x06400293, 0xfff28293, 0xfe029ee3, 0x40000337, 0x00100393, 0x02732023, 0x10500073,
+        ]);
+        let mut engine = EngineBuilder::new().livelock_threshold(10).build().unwrap();
+        engine.translate_elf(&elf).unwrap();
+        assert_eq!(engine.execute_status().unwrap(), ExitStatus::Halted(0));
+
+        // Hart 1 waits for a flag, which hart 0 sets after counting down.
+        // csrr a0, mhartid; lui t1, 0x80020; bnez a0, 2f
+        // lui t0, 0x10; 1: addi t0, t0, -1; bnez t0, 1b
+        // li t2, 1; sw t2, 0(t1); j 3f
+        // 2: lw t0, 0(t1); beqz t0, 2b
+        // 3: lui t2, 0x40000; li t3, 1; sw t3, 32(t2); wfi
+        let elf = kernel(&[
+            0xf1402573, 0x80020337, 0x00051e63, 0x000102b7, 0xfff28293, 0xfe029ee3, 0x00100393,
+            0x00732023, 0x00c0006f, 0x00032283, 0xfe028ee3, 0x400003b7, 0x00100e13, 0x03c3a023,
+            0x10500073,
+        ]);
+        let mut engine = EngineBuilder::new()
+            .num_cores(2)
+            .livelock_threshold(10)
+            .build()
+            .unwrap();
+        engine.translate_elf(&elf).unwrap();
+        assert_eq!(engine.execute_status().unwrap(), ExitStatus::Halted(0));
+        assert_eq!(engine.read_mem(0x80020000, 4), 1u32.to_le_bytes());
     }
-    pub unsafe fn binary_fp8_to_fp32_op(
-        rs1: u8,
-        rs2: u8,
-        rs3: f32,
-        op: flexfloat::FlexfloatOpExp,
-        fpmode_src: bool,
-    ) -> f32 {
-        flexfloat::ff_fp8_to_fp32_op(rs1, rs2, rs3, op, fpmode_src)
+
+    #[test]
+    fn parked_on_timeout() {
+        // Hart 0 spins while hart 1 sleeps, until the timeout stops both.
+        // csrr a0, mhartid; bnez a0, 1f; 0: j 0b; 1: wfi; j 1b
+        let elf = kernel(&[0xf1402573, 0x00051463, 0x0000006f, 0x10500073, 0xffdff06f]);
+        let mut engine = EngineBuilder::new()
+            .num_cores(2)
+            .max_duration(std::time::Duration::from_millis(200))
+            .build()
+            .unwrap();
+        engine.translate_elf(&elf).unwrap();
+        let stats = engine.execute_with_stats().unwrap();
+        assert!(stats.error);
+        let harts: Vec<_> = stats
+            .harts
+            .iter()
+            .map(|hart| (hart.finished, hart.parked))
+            .collect();
+        assert_eq!(harts, vec![(false, false), (false, true)]);
     }
-}
 
-/// A single register or memory access as recorded in a trace.
-#[derive(Debug, Clone, Copy)]
-#[repr(C, u8)]
-pub enum TraceAccess {
-    ReadMem,
-    ReadReg(u8),
-    ReadFReg(u8),
-    Readf8Reg(u8),
-    Readf16Reg(u8),
-    ReadF32Reg(u8),
-    Readvf64sReg(u8),
-    Readvf64hReg(u8),
-    Readvf64bReg(u8),
-    WriteMem,
-    WriteReg(u8),
-    WriteFReg(u8),
-    Writef8Reg(u8),
-    Writef16Reg(u8),
-    WriteF32Reg(u8),
-    Writevf64sReg(u8),
-    Writevf64hReg(u8),
-    Writevf64bReg(u8),
-    RMWMem,
-}
+    /// Execute a binary, and capture the registers of each hart once it waits
+    /// for an interrupt.
+    fn final_regs(
+        elf: &elf::File,
+        interpret: bool,
+    ) -> (u32, std::collections::BTreeMap<usize, Vec<u64>>) {
+        let regs = std::sync::Arc::new(Mutex::new(std::collections::BTreeMap::new()));
+        let mut engine = EngineBuilder::new().interpret(interpret).build().unwrap();
+        for (addr, raw, _) in tran::elf_instructions(elf) {
+            if raw != 0x10500073 {
+                continue;
+            }
+            let regs = regs.clone();
+            engine.add_breakpoint(
+                addr as u32,
+                Box::new(move |ctx| {
+                    let state = (0..32)
+                        .map(|i| ctx.reg(i) as u64)
+                        .chain((0..32).map(|i| ctx.freg(i)))
+                        .collect();
+                    regs.lock().unwrap().insert(ctx.hartid(), state);
+                    BreakpointAction::Continue
+                }),
+            );
+        }
+        engine.translate_elf(elf).unwrap();
+        let exit_code = engine.execute().unwrap();
+        let regs = regs.lock().unwrap().clone();
+        (exit_code, regs)
+    }
 
-/// Which type of AMO to execute.
-#[derive(Debug, Clone, Copy)]
-#[repr(C)]
-pub enum AtomicOp {
-    Amoadd,
-    Amoxor,
-    Amoor,
-    Amoand,
-    Amomin,
-    Amomax,
-    Amominu,
-    Amomaxu,
-    Amoswap,
-    ScW,
+    #[test]
+    fn interpreter_matches_jit() {
+        let binaries: &[(&str, &[u8])] = &[
+            ("dummy", include_bytes!("../tests/bin/dummy")),
+            ("far_jump", include_bytes!("../tests/bin/far_jump")),
+            ("unpred_jr_1", include_bytes!("../tests/bin/unpred_jr_1")),
+            ("frep_single", include_bytes!("../tests/bin/frep_single")),
+            (
+                "frep_multiple",
+                include_bytes!("../tests/bin/frep_multiple"),
+            ),
+        ];
+        for &(name, data) in binaries {
+            let elf = crate::loader::parse_elf(data).unwrap();
+            let jit = final_regs(&elf, false);
+            let interpreted = final_regs(&elf, true);
+            assert_eq!(jit.0, 0, "{}", name);
+            assert!(!jit.1.is_empty(), "{}", name);
+            assert_eq!(interpreted, jit, "{}", name);
+        }
+    }
 }
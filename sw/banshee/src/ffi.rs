@@ -0,0 +1,539 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! C interface for embedding banshee into other simulators
+//!
+//! The engine is handed out as an opaque pointer, created by
+//! `banshee_create` or `banshee_engine_new` and released by
+//! `banshee_destroy`. The other functions return `BANSHEE_OK` or one of the
+//! negative `BANSHEE_ERR_*` codes; `banshee_last_error` then describes the
+//! error. No panic crosses the boundary: panics are reported as
+//! `BANSHEE_ERR_PANIC`. The declarations are in `include/banshee.h`, which
+//! `make header` generates with cbindgen. The `banshee-capi` crate in `capi`
+//! builds the interface into a shared library.
+//!
+//! A testbench can advance the binary in bounded steps with `banshee_run`,
+//! interleaved with the RTL simulation, once `banshee_set_deterministic`
+//! has enabled the deterministic mode:
+//!
+//! ```c
+//! BansheeEngine *engine = banshee_create(NULL);
+//! banshee_set_deterministic(engine, 1000);
+//! int ret = banshee_load_elf(engine, "app.elf");
+//! while (ret == BANSHEE_OK) {
+//!     ret = banshee_run(engine, 1000);
+//!     // Advance the RTL, and exchange data through banshee_read_mem and
+//!     // banshee_write_mem.
+//! }
+//! uint32_t exit_code;
+//! if (ret != BANSHEE_DONE || banshee_exit_code(engine, &exit_code) != BANSHEE_OK)
+//!     fprintf(stderr, "%s\n", banshee_last_error(engine));
+//! banshee_destroy(engine);
+//! ```
+
+use crate::{
+    configuration::Configuration,
+    engine::{Engine, EngineBuilder, RunGate, Stepper},
+    loader,
+};
+use anyhow::{anyhow, bail, Result};
+use std::{
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int},
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::Arc,
+};
+
+/// The call succeeded.
+pub const BANSHEE_OK: c_int = 0;
+/// The execution has ended; `banshee_exit_code` returns its exit code.
+pub const BANSHEE_DONE: c_int = 1;
+/// A pointer argument was null.
+pub const BANSHEE_ERR_NULL: c_int = -1;
+/// An argument was invalid, e.g. a path which is not valid UTF-8.
+pub const BANSHEE_ERR_INVALID: c_int = -2;
+/// The binary could not be loaded or translated.
+pub const BANSHEE_ERR_TRANSLATE: c_int = -3;
+/// The execution failed, e.g. because a hart faulted.
+pub const BANSHEE_ERR_EXECUTE: c_int = -4;
+/// Banshee panicked.
+pub const BANSHEE_ERR_PANIC: c_int = -5;
+/// The execution has not ended yet, or is still in progress.
+pub const BANSHEE_ERR_RUNNING: c_int = -6;
+
+/// An engine and the description of the last error, behind an opaque pointer.
+pub struct BansheeEngine {
+    engine: Arc<Engine>,
+    /// The execution started by `banshee_run`, which pauses between calls.
+    execution: Option<Stepper>,
+    /// The exit code of the last execution which ended.
+    exit_code: Option<u32>,
+    last_error: CString,
+}
+
+impl BansheeEngine {
+    fn new(engine: Engine) -> Self {
+        Self {
+            engine: Arc::new(engine),
+            execution: None,
+            exit_code: None,
+            last_error: CString::default(),
+        }
+    }
+
+    /// Run `f`, and turn its error or panic into an error code.
+    fn call(&mut self, code: c_int, f: impl FnOnce(&mut Self) -> Result<c_int>) -> c_int {
+        let (code, message) = match catch_unwind(AssertUnwindSafe(|| f(self))) {
+            Ok(Ok(code)) => (code, String::new()),
+            Ok(Err(e)) => (code, format!("{:#}", e)),
+            Err(_) => (BANSHEE_ERR_PANIC, "banshee panicked".to_string()),
+        };
+        self.set_error(&message);
+        code
+    }
+
+    fn set_error(&mut self, message: &str) {
+        self.last_error = CString::new(message.replace('\0', " ")).unwrap();
+    }
+
+    /// The engine, unless an execution started by `banshee_run` still uses
+    /// it.
+    fn engine_mut(&mut self) -> Result<&mut Engine> {
+        Arc::get_mut(&mut self.engine).ok_or_else(|| anyhow!("An execution is in progress"))
+    }
+
+    /// Continue the execution for `max_instret` instructions, or until it
+    /// ends if zero, and start it first if none is in progress.
+    fn run(&mut self, max_instret: u64) -> Result<c_int> {
+        if self.engine.run_gate.is_none() {
+            bail!("Bounded execution needs the deterministic mode of banshee_set_deterministic");
+        }
+        if self.execution.is_none() {
+            self.exit_code = None;
+            self.execution = Some(Stepper::new(self.engine.clone())?);
+        }
+        let stepper = self.execution.as_mut().unwrap();
+        let result = match max_instret {
+            0 => stepper.finish().map(Some),
+            n => stepper.step(n),
+        };
+        if let Ok(None) = result {
+            return Ok(BANSHEE_OK);
+        }
+        self.execution = None;
+        self.exit_code = result?;
+        Ok(BANSHEE_DONE)
+    }
+}
+
+/// Hand out a newly built engine, or return null on failure.
+fn new_engine(build: impl FnOnce() -> Result<Engine>) -> *mut BansheeEngine {
+    match catch_unwind(AssertUnwindSafe(build)) {
+        Ok(Ok(engine)) => Box::into_raw(Box::new(BansheeEngine::new(engine))),
+        Ok(Err(e)) => {
+            error!("Failed to create engine: {:#}", e);
+            std::ptr::null_mut()
+        }
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Create an engine for the system described by a null-terminated JSON
+/// configuration, or the default one if `config_json` is null. Returns null
+/// on failure.
+///
+/// # Safety
+///
+/// `config_json` must be null or a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn banshee_create(config_json: *const c_char) -> *mut BansheeEngine {
+    new_engine(|| {
+        let mut builder = EngineBuilder::new();
+        if !config_json.is_null() {
+            let config = CStr::from_ptr(config_json).to_str()?;
+            builder = builder.configuration(Configuration::parse_str(config, true)?);
+        }
+        builder.build()
+    })
+}
+
+/// Create an engine with a number of clusters and cores per cluster, where
+/// zero keeps the default of the configuration. Returns null on failure.
+#[no_mangle]
+pub extern "C" fn banshee_engine_new(num_clusters: u32, num_cores: u32) -> *mut BansheeEngine {
+    new_engine(|| {
+        let mut builder = EngineBuilder::new();
+        if num_clusters != 0 {
+            builder = builder.num_clusters(num_clusters as usize);
+        }
+        if num_cores != 0 {
+            builder = builder.num_cores(num_cores as usize);
+        }
+        builder.build()
+    })
+}
+
+/// Release an engine, and stop its execution if one is in progress. Null is
+/// ignored.
+///
+/// # Safety
+///
+/// `engine` must be null or come from `banshee_create` or
+/// `banshee_engine_new`, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn banshee_destroy(engine: *mut BansheeEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+/// Execute the harts on one thread in turns of `quantum` instructions, such
+/// that repeated runs interleave them the same way and `banshee_run` can
+/// pause and continue the execution. A `quantum` of zero executes each hart
+/// on a thread of its own again, which is the default.
+///
+/// Must be called before the binary is loaded.
+///
+/// # Safety
+///
+/// `engine` must be null or a live engine.
+#[no_mangle]
+pub unsafe extern "C" fn banshee_set_deterministic(
+    engine: *mut BansheeEngine,
+    quantum: u64,
+) -> c_int {
+    match engine.as_mut() {
+        Some(engine) => engine.call(BANSHEE_ERR_INVALID, |engine| {
+            let engine = engine.engine_mut()?;
+            if !engine.images.is_empty() {
+                bail!("The deterministic mode must be set before loading a binary");
+            }
+            engine.deterministic = Some(quantum).filter(|&quantum| quantum > 0);
+            engine.run_gate = engine.deterministic.map(|_| Arc::new(RunGate::default()));
+            Ok(BANSHEE_OK)
+        }),
+        None => BANSHEE_ERR_NULL,
+    }
+}
+
+/// Load and translate the ELF binary at a null-terminated path, such that
+/// `banshee_execute` or `banshee_run` can execute it.
+///
+/// # Safety
+///
+/// `engine` must be null or a live engine, and `path` null or a
+/// null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn banshee_load_elf(
+    engine: *mut BansheeEngine,
+    path: *const c_char,
+) -> c_int {
+    let engine = match engine.as_mut() {
+        Some(engine) => engine,
+        None => return BANSHEE_ERR_NULL,
+    };
+    if path.is_null() {
+        engine.set_error("path is null");
+        return BANSHEE_ERR_NULL;
+    }
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => path,
+        Err(e) => {
+            engine.set_error(&format!("path is not valid UTF-8: {}", e));
+            return BANSHEE_ERR_INVALID;
+        }
+    };
+    engine.call(BANSHEE_ERR_TRANSLATE, |engine| {
+        let engine = engine.engine_mut()?;
+        let elf = loader::load_elf(std::path::Path::new(path))?;
+        engine.translate_elf(&elf)?;
+        Ok(BANSHEE_OK)
+    })
+}
+
+/// Execute the translated binary until it ends, and store its exit code in
+/// `exit_code`.
+///
+/// # Safety
+///
+/// `engine` must be null or a live engine, and `exit_code` null or valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn banshee_execute(engine: *mut BansheeEngine, exit_code: *mut u32) -> c_int {
+    let (engine, exit_code) = match (engine.as_mut(), exit_code.as_mut()) {
+        (Some(engine), Some(exit_code)) => (engine, exit_code),
+        _ => return BANSHEE_ERR_NULL,
+    };
+    engine.call(BANSHEE_ERR_EXECUTE, |engine| {
+        if engine.execution.is_some() {
+            bail!("An execution started by banshee_run is in progress");
+        }
+        *exit_code = engine.engine.execute()?;
+        engine.exit_code = Some(*exit_code);
+        Ok(BANSHEE_OK)
+    })
+}
+
+/// Execute the translated binary for at most `max_instret` more instructions
+/// of all harts together, or until it ends if `max_instret` is zero. The
+/// first call starts the execution, and the following ones continue it.
+///
+/// The instructions are counted in interpreter steps, where a basic block
+/// with an FREP loop counts as one. Returns `BANSHEE_OK` if the execution
+/// paused after the instructions, and `BANSHEE_DONE` once it has ended. The
+/// next call then starts a new execution. Needs the deterministic mode of
+/// `banshee_set_deterministic`.
+///
+/// # Safety
+///
+/// `engine` must be null or a live engine.
+#[no_mangle]
+pub unsafe extern "C" fn banshee_run(engine: *mut BansheeEngine, max_instret: u64) -> c_int {
+    match engine.as_mut() {
+        Some(engine) => engine.call(BANSHEE_ERR_EXECUTE, |engine| engine.run(max_instret)),
+        None => BANSHEE_ERR_NULL,
+    }
+}
+
+/// Store the exit code of the last execution which ended in `exit_code`.
+/// Returns `BANSHEE_ERR_RUNNING` if no execution has ended since the last
+/// one was started.
+///
+/// # Safety
+///
+/// `engine` must be null or a live engine, and `exit_code` null or valid
+/// for writes.
+#[no_mangle]
+pub unsafe extern "C" fn banshee_exit_code(
+    engine: *mut BansheeEngine,
+    exit_code: *mut u32,
+) -> c_int {
+    let (engine, exit_code) = match (engine.as_mut(), exit_code.as_mut()) {
+        (Some(engine), Some(exit_code)) => (engine, exit_code),
+        _ => return BANSHEE_ERR_NULL,
+    };
+    engine.call(BANSHEE_ERR_RUNNING, |engine| match engine.exit_code {
+        Some(code) => {
+            *exit_code = code;
+            Ok(BANSHEE_OK)
+        }
+        None => bail!("No execution has ended"),
+    })
+}
+
+/// Copy `len` bytes of memory at `addr` into `buf`.
+///
+/// # Safety
+///
+/// `engine` must be null or a live engine, and `buf` null or valid for
+/// writes of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn banshee_read_mem(
+    engine: *mut BansheeEngine,
+    addr: u32,
+    buf: *mut u8,
+    len: usize,
+) -> c_int {
+    let engine = match engine.as_mut() {
+        Some(engine) => engine,
+        None => return BANSHEE_ERR_NULL,
+    };
+    if buf.is_null() {
+        engine.set_error("buffer is null");
+        return BANSHEE_ERR_NULL;
+    }
+    if addr as u64 + len as u64 > 1 << 32 {
+        engine.set_error(&format!(
+            "reading 0x{:x} bytes at 0x{:x} exceeds the address space",
+            len, addr
+        ));
+        return BANSHEE_ERR_INVALID;
+    }
+    let buf = std::slice::from_raw_parts_mut(buf, len);
+    engine.call(BANSHEE_ERR_INVALID, |engine| {
+        buf.copy_from_slice(&engine.engine.read_mem(addr, len));
+        Ok(BANSHEE_OK)
+    })
+}
+
+/// Copy `len` bytes from `buf` into memory at `addr`.
+///
+/// # Safety
+///
+/// `engine` must be null or a live engine, and `buf` null or valid for
+/// reads of `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn banshee_write_mem(
+    engine: *mut BansheeEngine,
+    addr: u32,
+    buf: *const u8,
+    len: usize,
+) -> c_int {
+    let engine = match engine.as_mut() {
+        Some(engine) => engine,
+        None => return BANSHEE_ERR_NULL,
+    };
+    if buf.is_null() {
+        engine.set_error("buffer is null");
+        return BANSHEE_ERR_NULL;
+    }
+    if addr as u64 + len as u64 > 1 << 32 {
+        engine.set_error(&format!(
+            "writing 0x{:x} bytes at 0x{:x} exceeds the address space",
+            len, addr
+        ));
+        return BANSHEE_ERR_INVALID;
+    }
+    let buf = std::slice::from_raw_parts(buf, len);
+    engine.call(BANSHEE_ERR_INVALID, |engine| {
+        engine.engine.write_mem(addr, buf);
+        Ok(BANSHEE_OK)
+    })
+}
+
+/// The description of the last error of an engine, or an empty string if the
+/// last call succeeded. The string lives until the next call on the engine.
+///
+/// # Safety
+///
+/// `engine` must be null or a live engine.
+#[no_mangle]
+pub unsafe extern "C" fn banshee_last_error(engine: *const BansheeEngine) -> *const c_char {
+    match engine.as_ref() {
+        Some(engine) => engine.last_error.as_ptr(),
+        None => b"engine is null\0".as_ptr() as *const c_char,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr::null_mut;
+
+    fn last_error(engine: *const BansheeEngine) -> String {
+        unsafe { CStr::from_ptr(banshee_last_error(engine)) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn c_caller() {
+        unsafe {
+            let engine = banshee_engine_new(1, 1);
+            assert!(!engine.is_null());
+
+            let path = CString::new("does/not/exist").unwrap();
+            assert_eq!(
+                banshee_load_elf(engine, path.as_ptr()),
+                BANSHEE_ERR_TRANSLATE
+            );
+            assert!(!last_error(engine).is_empty());
+
+            let path = CString::new("tests/bin/dummy").unwrap();
+            assert_eq!(banshee_load_elf(engine, path.as_ptr()), BANSHEE_OK);
+            assert_eq!(last_error(engine), "");
+            let mut exit_code = u32::max_value();
+            assert_eq!(banshee_execute(engine, &mut exit_code), BANSHEE_OK);
+            assert_eq!(exit_code, 0);
+
+            // The first instruction is `lui t0, 0x12345`.
+            let mut buf = [0u8; 4];
+            assert_eq!(
+                banshee_read_mem(engine, 0x80010000, buf.as_mut_ptr(), 4),
+                BANSHEE_OK
+            );
+            assert_eq!(u32::from_le_bytes(buf), 0x123452b7);
+            assert_eq!(
+                banshee_read_mem(engine, 0xfffffffe, buf.as_mut_ptr(), 4),
+                BANSHEE_ERR_INVALID
+            );
+            assert_eq!(banshee_read_mem(engine, 0, null_mut(), 4), BANSHEE_ERR_NULL);
+            banshee_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn bounded_run() {
+        unsafe {
+            let config = CString::new(r#"{"architecture": {"num_cores": 1}}"#).unwrap();
+            let engine = banshee_create(config.as_ptr());
+            assert!(!engine.is_null());
+            let mut exit_code = 0;
+            assert_eq!(banshee_run(engine, 10), BANSHEE_ERR_EXECUTE);
+            assert!(last_error(engine).contains("deterministic"));
+            assert_eq!(banshee_set_deterministic(engine, 1000), BANSHEE_OK);
+            assert_eq!(
+                banshee_exit_code(engine, &mut exit_code),
+                BANSHEE_ERR_RUNNING
+            );
+            let path = CString::new("tests/bin/dummy").unwrap();
+            assert_eq!(banshee_load_elf(engine, path.as_ptr()), BANSHEE_OK);
+            assert_eq!(banshee_set_deterministic(engine, 0), BANSHEE_ERR_INVALID);
+
+            // The fourth instruction stores 0x100 to 0x100.
+            let mut buf = [0u8; 4];
+            assert_eq!(banshee_run(engine, 3), BANSHEE_OK);
+            assert_eq!(
+                banshee_read_mem(engine, 0x100, buf.as_mut_ptr(), 4),
+                BANSHEE_OK
+            );
+            assert_eq!(u32::from_le_bytes(buf), 0);
+            assert_eq!(banshee_run(engine, 1), BANSHEE_OK);
+            assert_eq!(
+                banshee_read_mem(engine, 0x100, buf.as_mut_ptr(), 4),
+                BANSHEE_OK
+            );
+            assert_eq!(u32::from_le_bytes(buf), 0x100);
+            assert_eq!(
+                banshee_exit_code(engine, &mut exit_code),
+                BANSHEE_ERR_RUNNING
+            );
+            assert_eq!(banshee_execute(engine, &mut exit_code), BANSHEE_ERR_EXECUTE);
+            assert_eq!(
+                banshee_load_elf(engine, path.as_ptr()),
+                BANSHEE_ERR_TRANSLATE
+            );
+
+            // Memory can be modified while paused.
+            let magic = 0xdeadbeefu32.to_le_bytes();
+            assert_eq!(
+                banshee_write_mem(engine, 0x200, magic.as_ptr(), 4),
+                BANSHEE_OK
+            );
+            assert_eq!(
+                banshee_read_mem(engine, 0x200, buf.as_mut_ptr(), 4),
+                BANSHEE_OK
+            );
+            assert_eq!(buf, magic);
+            assert_eq!(banshee_run(engine, 0), BANSHEE_DONE);
+            assert_eq!(banshee_exit_code(engine, &mut exit_code), BANSHEE_OK);
+            assert_eq!(exit_code, 0);
+
+            // The next run starts over, and may be abandoned while paused.
+            assert_eq!(banshee_run(engine, 2), BANSHEE_OK);
+            banshee_destroy(engine);
+
+            let config = CString::new("{\"no_such_key\": 1}").unwrap();
+            assert!(banshee_create(config.as_ptr()).is_null());
+        }
+    }
+
+    #[test]
+    fn null_engine() {
+        unsafe {
+            let mut exit_code = 0;
+            assert_eq!(
+                banshee_execute(null_mut(), &mut exit_code),
+                BANSHEE_ERR_NULL
+            );
+            assert_eq!(
+                banshee_load_elf(null_mut(), std::ptr::null()),
+                BANSHEE_ERR_NULL
+            );
+            assert_eq!(banshee_run(null_mut(), 1), BANSHEE_ERR_NULL);
+            assert_eq!(last_error(std::ptr::null()), "engine is null");
+            banshee_destroy(null_mut());
+        }
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright 2020 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Emulates a Snitch system using dynamic LLVM JIT binary translation.
+//!
+//! The `banshee` binary is a thin command line interface around this library.
+//! Rust programs embed the `engine::Engine`; other languages use the C
+//! interface in `ffi`, built with the `capi` feature, and Python the `banshee`
+//! module built with the `python` feature.
+
+#[macro_use]
+extern crate log;
+extern crate llvm_sys as llvm;
+
+pub mod app_args;
+pub mod bootroms;
+pub mod configuration;
+pub mod dram_preload;
+pub mod dwarf;
+pub mod engine;
+#[cfg(feature = "capi")]
+pub mod ffi;
+pub mod loader;
+pub mod peripherals;
+#[cfg(feature = "python")]
+mod python;
+pub mod riscv;
+mod runtime;
+pub mod rvc;
+mod softfloat;
+pub mod stats;
+pub mod tran;
+pub mod util;
+
+use crate::configuration::*;
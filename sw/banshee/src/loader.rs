@@ -0,0 +1,338 @@
+// Copyright 2020 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Loading of ELF binaries
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::Path;
+
+/// Read and parse an ELF binary from a file.
+pub fn load_elf(path: &Path) -> Result<elf::File> {
+    let data =
+        std::fs::read(path).with_context(|| format!("Failed to read binary {}", path.display()))?;
+    parse_elf(&data).with_context(|| format!("Failed to parse binary {}", path.display()))
+}
+
+/// Parse an ELF binary held in memory.
+///
+/// The `elf` crate panics or aborts on a number of malformed inputs, so the
+/// file and section headers are validated before the data is handed over.
+pub fn parse_elf(data: &[u8]) -> Result<elf::File> {
+    check_headers(data)?;
+    let elf = elf::File::open_stream(&mut std::io::Cursor::new(data))
+        .map_err(|e| anyhow!("Malformed ELF file: {:?}", e))?;
+    check_symbols(&elf)?;
+    Ok(elf)
+}
+
+/// The `e_machine` of RISC-V binaries, which the `elf` crate lacks.
+const EM_RISCV: elf::types::Machine = elf::types::Machine(243);
+
+/// Check that a binary targets RV32, the only architecture supported, and
+/// that its entry point is executable.
+pub fn check_target(elf: &elf::File) -> Result<()> {
+    let ehdr = &elf.ehdr;
+    if ehdr.class != elf::types::ELFCLASS32 {
+        bail!(
+            "Binary is a {} ELF file, but only 32-bit binaries (RV32) are supported",
+            ehdr.class
+        );
+    }
+    if ehdr.machine != EM_RISCV {
+        bail!(
+            "Binary is built for {} (machine 0x{:x}), but only RISC-V (machine 0x{:x}) is supported",
+            ehdr.machine,
+            ehdr.machine.0,
+            EM_RISCV.0
+        );
+    }
+    if ehdr.data != elf::types::ELFDATA2LSB {
+        bail!(
+            "Binary is {}, but only little-endian binaries are supported",
+            match ehdr.data {
+                elf::types::ELFDATA2MSB => "big-endian",
+                _ => "of unknown endianness",
+            }
+        );
+    }
+    let executable = elf.sections.iter().any(|section| {
+        (section.shdr.flags.0 & elf::types::SHF_EXECINSTR.0) != 0
+            && (section.shdr.addr..section.shdr.addr + section.shdr.size).contains(&ehdr.entry)
+    });
+    if !executable {
+        bail!(
+            "Entry point 0x{:x} does not lie in an executable section",
+            ehdr.entry
+        );
+    }
+    Ok(())
+}
+
+/// Raw access to the fields of an ELF file.
+struct RawElf<'a> {
+    data: &'a [u8],
+    wide: bool,
+    big_endian: bool,
+}
+
+impl RawElf<'_> {
+    /// Read an integer of `len` bytes at `offset`.
+    fn read(&self, offset: u64, len: u64, what: &str) -> Result<u64> {
+        let bytes = offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset as usize..end as usize))
+            .ok_or_else(|| anyhow!("Truncated {} at offset 0x{:x}", what, offset))?;
+        let fold = |acc: u64, &b: &u8| acc << 8 | b as u64;
+        Ok(if self.big_endian {
+            bytes.iter().fold(0, fold)
+        } else {
+            bytes.iter().rev().fold(0, fold)
+        })
+    }
+
+    /// Read an address-sized integer at `offset`.
+    fn read_word(&self, offset: u64, what: &str) -> Result<u64> {
+        self.read(offset, if self.wide { 8 } else { 4 }, what)
+    }
+}
+
+/// Check that the `elf` crate can extract a string at `index` from `table`.
+fn has_string(table: &[u8], index: u64) -> bool {
+    index == 0
+        || table
+            .get(index as usize..)
+            .map_or(false, |s| s.contains(&0))
+}
+
+/// Validate the file header and the section headers.
+fn check_headers(data: &[u8]) -> Result<()> {
+    if data.len() < 16 || data[0..4] != *b"\x7fELF" {
+        bail!("Not an ELF file");
+    }
+    let wide = match data[4] {
+        1 => false,
+        2 => true,
+        c => bail!("Invalid ELF class {}", c),
+    };
+    let big_endian = match data[5] {
+        1 => false,
+        2 => true,
+        d => bail!("Invalid ELF data encoding {}", d),
+    };
+    let raw = RawElf {
+        data,
+        wide,
+        big_endian,
+    };
+
+    // Locate the section header table.
+    let (shoff_at, shnum_at, shentsize) = if wide { (40, 60, 64) } else { (32, 48, 40) };
+    let shoff = raw.read_word(shoff_at, "file header")?;
+    let shnum = raw.read(shnum_at, 2, "file header")?;
+    let shstrndx = raw.read(shnum_at + 2, 2, "file header")?;
+    if shnum == 0 {
+        return Ok(());
+    }
+    if shstrndx >= shnum {
+        bail!(
+            "Section name table index {} out of range ({} sections)",
+            shstrndx,
+            shnum
+        );
+    }
+
+    // Check that the section contents lie within the file.
+    let mut sections = Vec::with_capacity(shnum as usize);
+    for i in 0..shnum {
+        let what = format!("header of section {}", i);
+        let base = shoff
+            .checked_add(i * shentsize)
+            .filter(|base| base.checked_add(shentsize).is_some())
+            .ok_or_else(|| anyhow!("Truncated {} at offset 0x{:x}", what, shoff))?;
+        let name = raw.read(base, 4, &what)?;
+        let shtype = raw.read(base + 4, 4, &what)?;
+        let (offset, size) = if wide {
+            (
+                raw.read_word(base + 24, &what)?,
+                raw.read_word(base + 32, &what)?,
+            )
+        } else {
+            (
+                raw.read_word(base + 16, &what)?,
+                raw.read_word(base + 20, &what)?,
+            )
+        };
+        let nobits = shtype == elf::types::SHT_NOBITS.0 as u64;
+        if nobits && size > 1 << 32 {
+            bail!(
+                "Section {} with size 0x{:x} exceeds the 32 bit address space",
+                i,
+                size
+            );
+        }
+        if !nobits
+            && offset
+                .checked_add(size)
+                .map_or(true, |end| end > data.len() as u64)
+        {
+            bail!(
+                "Section {} at offset 0x{:x} with size 0x{:x} extends past the end of the file (0x{:x} bytes)",
+                i,
+                offset,
+                size,
+                data.len()
+            );
+        }
+        sections.push((name, nobits, offset, size));
+    }
+
+    // Check that the section names can be looked up.
+    let (_, nobits, offset, size) = sections[shstrndx as usize];
+    for (i, &(name, _, _, _)) in sections.iter().enumerate() {
+        let valid = if nobits {
+            name == 0 || name < size
+        } else {
+            has_string(&data[offset as usize..(offset + size) as usize], name)
+        };
+        if !valid {
+            bail!(
+                "Section {} has invalid name offset 0x{:x} into section {}",
+                i,
+                name,
+                shstrndx
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Validate the string table links and symbol names of the symbol tables.
+fn check_symbols(elf: &elf::File) -> Result<()> {
+    let entsize = if elf.ehdr.class == elf::types::ELFCLASS64 {
+        24
+    } else {
+        16
+    };
+    for section in &elf.sections {
+        let shtype = section.shdr.shtype;
+        if shtype != elf::types::SHT_SYMTAB && shtype != elf::types::SHT_DYNSYM {
+            continue;
+        }
+        let strtab = elf
+            .sections
+            .get(section.shdr.link as usize)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Symbol table `{}` links to missing string table {}",
+                    section.shdr.name,
+                    section.shdr.link
+                )
+            })?;
+        for (i, sym) in section.data.chunks_exact(entsize).enumerate() {
+            let bytes = [sym[0], sym[1], sym[2], sym[3]];
+            let name = if elf.ehdr.data == elf::types::ELFDATA2MSB {
+                u32::from_be_bytes(bytes)
+            } else {
+                u32::from_le_bytes(bytes)
+            };
+            if !has_string(&strtab.data, name as u64) {
+                bail!(
+                    "Symbol {} in `{}` has invalid name offset 0x{:x} into `{}`",
+                    i,
+                    section.shdr.name,
+                    name,
+                    strtab.shdr.name
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static DUMMY: &[u8] = include_bytes!("../tests/bin/dummy");
+
+    #[test]
+    fn truncated() {
+        assert!(parse_elf(DUMMY).is_ok());
+        for &len in &[0, 4, 16, 40, 52, DUMMY.len() / 2, DUMMY.len() - 1] {
+            assert!(
+                parse_elf(&DUMMY[..len]).is_err(),
+                "ELF truncated to {} bytes accepted",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn target() {
+        assert!(check_target(&parse_elf(DUMMY).unwrap()).is_ok());
+
+        // An RV64 header without any sections.
+        let mut header = vec![0; 64];
+        header[..8].copy_from_slice(b"\x7fELF\x02\x01\x01\x00");
+        header[16..20].copy_from_slice(&[2, 0, 243, 0]);
+        header[20] = 1;
+        header[24..32].copy_from_slice(&0x80000000u64.to_le_bytes());
+        header[52] = 64;
+        let err = check_target(&parse_elf(&header).unwrap()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Binary is a 64-bit ELF file, but only 32-bit binaries (RV32) are supported"
+        );
+
+        // An x86-64 binary.
+        let mut data = DUMMY.to_vec();
+        data[18] = 62;
+        let err = check_target(&parse_elf(&data).unwrap()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Binary is built for AMD x86-64 architecture (machine 0x3e), \
+             but only RISC-V (machine 0xf3) is supported"
+        );
+
+        let mut elf = parse_elf(DUMMY).unwrap();
+        elf.ehdr.data = elf::types::ELFDATA2MSB;
+        let err = check_target(&elf).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Binary is big-endian, but only little-endian binaries are supported"
+        );
+
+        let mut elf = parse_elf(DUMMY).unwrap();
+        elf.ehdr.entry = 4;
+        let err = check_target(&elf).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Entry point 0x4 does not lie in an executable section"
+        );
+    }
+
+    #[test]
+    fn corrupted() {
+        // Overwrite random bytes in the file header and in the section
+        // headers and tables at the end of the file; this must never panic.
+        let mut state = 0x1234_5678_u64;
+        let mut rand = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as usize
+        };
+        for _ in 0..2000 {
+            let mut data = DUMMY.to_vec();
+            for _ in 0..(1 + rand() % 8) {
+                let i = match rand() % 2 {
+                    0 => rand() % 64,
+                    _ => data.len() - 1 - rand() % 1024,
+                };
+                data[i] = rand() as u8;
+            }
+            let _ = parse_elf(&data);
+        }
+    }
+}
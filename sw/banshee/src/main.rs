@@ -7,32 +7,24 @@
 extern crate clap;
 #[macro_use]
 extern crate log;
-extern crate llvm_sys as llvm;
 
 use anyhow::{bail, Context, Result};
-use clap::Arg;
-use llvm_sys::{
-    bit_writer::*, core::*, execution_engine::*, initialization::*, support::*, target::*,
-};
+use clap::{AppSettings, Arg, SubCommand};
+use llvm_sys::{bit_writer::*, core::*};
 
 use std::{
-    collections::HashMap, ffi::CString, fs, fs::File, io::prelude::*, num::ParseIntError,
-    os::raw::c_int, path::Path, ptr::null_mut, str::FromStr,
+    collections::HashMap, fs, fs::File, io::prelude::*, num::ParseIntError, path::Path,
+    ptr::null_mut, str::FromStr,
 };
 
-pub mod bootroms;
-pub mod configuration;
-pub mod dram_preload;
-pub mod engine;
-pub mod peripherals;
-pub mod riscv;
-mod runtime;
-mod softfloat;
-pub mod tran;
-pub mod util;
-
-use crate::configuration::*;
-use crate::engine::*;
+use banshee::{
+    app_args,
+    configuration::*,
+    dram_preload,
+    engine::*,
+    loader,
+    util::{self, log_target},
+};
 
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
 
@@ -42,7 +34,17 @@ fn main() -> Result<()> {
         .arg(
             Arg::with_name("binary")
                 .help("RISC-V ELF binary to execute")
-                .required(true),
+                .required_unless("cluster-binary"),
+        )
+        .arg(
+            Arg::with_name("cluster-binary")
+                .long("binary")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("PATH@CLUSTERS")
+                .conflicts_with("all-opt-levels")
+                .help("Execute a binary on some clusters, e.g. `app.elf@1-3` (repeatable)"),
         )
         .arg(
             Arg::with_name("dump-llvm")
@@ -70,6 +72,12 @@ fn main() -> Result<()> {
                 .short("n")
                 .help("Translate the binary, but do not execute"),
         )
+        .arg(
+            Arg::with_name("all-opt-levels")
+                .long("all-opt-levels")
+                .conflicts_with("dry-run")
+                .help("Execute with and without each optimization, and check that the exit codes match"),
+        )
         .arg(
             Arg::with_name("no-opt-llvm")
                 .long("no-opt-llvm")
@@ -96,6 +104,13 @@ fn main() -> Result<()> {
                 .short("t")
                 .help("Enable instruction tracing"),
         )
+        .arg(
+            Arg::with_name("trace-sample")
+                .long("trace-sample")
+                .takes_value(true)
+                .requires("trace")
+                .help("Only trace every Nth instruction of each hart"),
+        )
         .arg(
             Arg::with_name("no-interrupt")
                 .long("no-interrupt")
@@ -107,6 +122,212 @@ fn main() -> Result<()> {
                 .short("l")
                 .help("Enable instruction latency modeling"),
         )
+        .arg(
+            Arg::with_name("cycle-model")
+                .long("cycle-model")
+                .takes_value(true)
+                .help("A file with the cycles of each instruction category for latency modeling"),
+        )
+        .arg(
+            Arg::with_name("exact-fp")
+                .long("exact-fp")
+                .help("Honor FP rounding modes and exception flags (slower)"),
+        )
+        .arg(
+            Arg::with_name("flush-to-zero")
+                .long("flush-to-zero")
+                .help("Flush subnormal floating-point inputs and results to zero"),
+        )
+        .arg(
+            Arg::with_name("traps")
+                .long("traps")
+                .help("Enter the trap handler on ecall, ebreak, and illegal instructions"),
+        )
+        .arg(
+            Arg::with_name("trap-illegal-instructions")
+                .long("trap-illegal-instructions")
+                .help("Enter the trap handler on illegal instructions, if one is installed"),
+        )
+        .arg(
+            Arg::with_name("parallel-analysis")
+                .long("parallel-analysis")
+                .help("Estimate the branch targets of the sections of the binary in parallel"),
+        )
+        .arg(
+            Arg::with_name("dedup-blocks")
+                .long("dedup-blocks")
+                .help("Emit the code of identical basic blocks only once"),
+        )
+        .arg(
+            Arg::with_name("interpret")
+                .long("interpret")
+                .help("Execute the binary one instruction at a time"),
+        )
+        .arg(
+            Arg::with_name("deterministic")
+                .long("deterministic")
+                .help("Interleave the harts round-robin on one thread, the same way in every run"),
+        )
+        .arg(
+            Arg::with_name("quantum")
+                .long("quantum")
+                .takes_value(true)
+                .default_value("1000")
+                .help(
+                    "The number of instructions a hart executes per turn with --deterministic, \
+                     rounded up to the end of a basic block",
+                ),
+        )
+        .arg(
+            Arg::with_name("shared-clock")
+                .long("shared-clock")
+                .help("Return a clock shared by all harts from the `cycle` and `time` CSRs"),
+        )
+        .arg(
+            Arg::with_name("stats")
+                .long("stats")
+                .takes_value(true)
+                .help("Write execution statistics as JSON to a file"),
+        )
+        .arg(
+            Arg::with_name("coverage")
+                .long("coverage")
+                .takes_value(true)
+                .help("Write the translated and executed instructions as JSON to a file"),
+        )
+        .arg(
+            Arg::with_name("access-log")
+                .long("access-log")
+                .takes_value(true)
+                .help("Keep this many most recent memory accesses, and print them on errors"),
+        )
+        .arg(
+            Arg::with_name("backtrace-on-abort")
+                .long("backtrace-on-abort")
+                .help("Print a backtrace of a hart which aborts, if the binary keeps frame pointers"),
+        )
+        .arg(
+            Arg::with_name("check-ssr-bounds")
+                .long("check-ssr-bounds")
+                .help("Abort when an SSR is accessed past the end of its stream, at an unaligned address, or outside the memory its stream started in"),
+        )
+        .arg(
+            Arg::with_name("source-lines")
+                .long("source-lines")
+                .help("Annotate the trace with source lines from the binary's DWARF info"),
+        )
+        .arg(
+            Arg::with_name("progress")
+                .long("progress")
+                .takes_value(true)
+                .help("Report the execution progress at this interval (e.g. `5s`)"),
+        )
+        .arg(
+            Arg::with_name("deadlock-timeout")
+                .long("deadlock-timeout")
+                .takes_value(true)
+                .help("Stop with an error if no hart retires an instruction for this long (e.g. `10s`)"),
+        )
+        .arg(
+            Arg::with_name("detect-livelock")
+                .long("detect-livelock")
+                .help("Stop with an error if a hart spins in a loop without side effects"),
+        )
+        .arg(
+            Arg::with_name("livelock-threshold")
+                .long("livelock-threshold")
+                .takes_value(true)
+                .default_value("1000000")
+                .help("The number of iterations after which a loop without side effects is flagged"),
+        )
+        .arg(
+            Arg::with_name("max-instret")
+                .long("max-instret")
+                .takes_value(true)
+                .help("Stop with an error once a hart retires this many instructions"),
+        )
+        .arg(
+            Arg::with_name("max-seconds")
+                .long("max-seconds")
+                .takes_value(true)
+                .help("Stop with an error after running this long (e.g. `10`, `500ms`)"),
+        )
+        .arg(
+            Arg::with_name("pin-harts")
+                .long("pin-harts")
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .use_delimiter(true)
+                .value_name("CPUS")
+                .help("Pin each hart's thread to a host CPU (round-robin, or `=0,2,4`)"),
+        )
+        .arg(
+            Arg::with_name("app-arg")
+                .long("app-arg")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .allow_hyphen_values(true)
+                .help("Pass a command line argument to the binary (repeatable)"),
+        )
+        .arg(
+            Arg::with_name("app-env")
+                .long("app-env")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("KEY=VAL")
+                .help("Pass an environment variable to the binary (repeatable)"),
+        )
+        .arg(
+            Arg::with_name("app-args-addr")
+                .long("app-args-addr")
+                .takes_value(true)
+                .help("Place the arguments of the binary at this hex address (default: end of DRAM)"),
+        )
+        .arg(
+            Arg::with_name("abort-policy")
+                .long("abort-policy")
+                .takes_value(true)
+                .value_name("stop|continue|trap=ADDR")
+                .help("What a hart does after an illegal instruction (default: stop)"),
+        )
+        .arg(
+            Arg::with_name("overlap-policy")
+                .long("overlap-policy")
+                .takes_value(true)
+                .possible_values(&["error", "warn"])
+                .help("What to do if a section overlaps a device or the end of the TCDM (default: error)"),
+        )
+        .arg(
+            Arg::with_name("fs-root")
+                .long("fs-root")
+                .takes_value(true)
+                .value_name("DIR")
+                .help("Let the binary open files below this host directory"),
+        )
+        .arg(
+            Arg::with_name("heap")
+                .long("heap")
+                .takes_value(true)
+                .value_name("START:END")
+                .help("The hex address range handed out by the brk syscall"),
+        )
+        .arg(
+            Arg::with_name("uart")
+                .long("uart")
+                .takes_value(true)
+                .value_name("ADDR")
+                .help("Enable the built-in UART at this hex address, which prints to stdout"),
+        )
+        .arg(
+            Arg::with_name("exit-protocol")
+                .long("exit-protocol")
+                .takes_value(true)
+                .possible_values(&["snitch", "nonzero"])
+                .help("How the binary reports its exit code through the scratch register"),
+        )
         .arg(
             Arg::with_name("num-cores")
                 .long("num-cores")
@@ -122,6 +343,7 @@ fn main() -> Result<()> {
         .arg(
             Arg::with_name("configuration")
                 .long("configuration")
+                .alias("config")
                 .takes_value(true)
                 .help("A configuration file describing the architecture"),
         )
@@ -161,129 +383,232 @@ fn main() -> Result<()> {
                 .value_delimiter(",")
                 .help("The memory offsets of the data files for training."),
         )
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(
+            SubCommand::with_name("dump-config")
+                .about("Print the effective configuration after applying the defaults and options"),
+        )
         .get_matches();
 
-    // Configure the logger.
-    pretty_env_logger::init_custom_env("SNITCH_LOG");
-
-    // Initialize the LLVM core.
-    let context = unsafe {
-        LLVMLinkInMCJIT();
-        LLVM_InitializeNativeTarget();
-        LLVM_InitializeNativeAsmPrinter();
-
-        // Initialize passes (inspired by llvm/tools/opt/opt.cpp:527).
-        let pass_reg = LLVMGetGlobalPassRegistry();
-        LLVMInitializeAggressiveInstCombiner(pass_reg);
-        LLVMInitializeAnalysis(pass_reg);
-        LLVMInitializeCodeGen(pass_reg);
-        LLVMInitializeCore(pass_reg);
-        LLVMInitializeIPA(pass_reg);
-        LLVMInitializeIPO(pass_reg);
-        LLVMInitializeInstCombine(pass_reg);
-        LLVMInitializeInstrumentation(pass_reg);
-        LLVMInitializeObjCARCOpts(pass_reg);
-        LLVMInitializeScalarOpts(pass_reg);
-        LLVMInitializeTarget(pass_reg);
-        LLVMInitializeTransformUtils(pass_reg);
-        LLVMInitializeVectorization(pass_reg);
-
-        engine::add_llvm_symbols();
-        LLVMGetGlobalContext()
-    };
-
-    // Pass command line arguments to LLVM.
-    if let Some(args) = matches.values_of("llvm-args") {
-        let exec_name = CString::new("banshee").unwrap();
-        let args: Vec<_> = args.map(|a| CString::new(a).unwrap()).collect();
-        let mut argv = vec![];
-        argv.push(exec_name.as_ptr());
-        argv.extend(args.iter().map(|a| (*a).as_ptr()));
-        let overview = CString::new("Banshee is magic!").unwrap();
-        unsafe {
-            LLVMParseCommandLineOptions(
-                argv.len() as c_int,
-                argv.as_ptr(),
-                overview.as_ptr() as *const _,
-            );
-        }
+    // Configure the logger. The progress report is shown unless the
+    // environment disables it.
+    let mut logger = pretty_env_logger::formatted_builder();
+    if matches.is_present("progress") {
+        logger.filter(Some(log_target::PROGRESS), log::LevelFilter::Info);
+    }
+    if let Ok(filters) = std::env::var("SNITCH_LOG") {
+        logger.parse_filters(&filters);
     }
+    logger.init();
 
     // Setup the execution engine.
-    let mut engine = Engine::new(context);
     if matches.is_present("opt-llvm") && matches.is_present("no-opt-llvm") {
         bail!("Both --opt-llvm and --no-opt-llvm provided");
     }
     if matches.is_present("opt-jit") && matches.is_present("no-opt-jit") {
         bail!("Both --opt-jit and --no-opt-jit provided");
     }
-    engine.opt_llvm = matches.is_present("opt-llvm");
-    engine.opt_jit = matches.is_present("opt-jit");
-    engine.interrupt = !matches.is_present("no-interrupt");
-    if engine.interrupt {
-        debug!("Interrupts enabled");
+    let mut builder = EngineBuilder::new()
+        .opt_llvm(matches.is_present("opt-llvm"))
+        .opt_jit(matches.is_present("opt-jit"))
+        .interrupt(!matches.is_present("no-interrupt"))
+        .trace(matches.is_present("trace"))
+        .latency(matches.is_present("latency"))
+        .exact_fp(matches.is_present("exact-fp"))
+        .flush_to_zero(matches.is_present("flush-to-zero"))
+        .traps(matches.is_present("traps"))
+        .trap_illegal_instructions(matches.is_present("trap-illegal-instructions"))
+        .parallel_analysis(matches.is_present("parallel-analysis"))
+        .dedup_blocks(matches.is_present("dedup-blocks"))
+        .interpret(matches.is_present("interpret"))
+        .shared_clock(matches.is_present("shared-clock"))
+        .stats(matches.is_present("stats"))
+        .coverage(matches.is_present("coverage"))
+        .backtrace_on_abort(matches.is_present("backtrace-on-abort"))
+        .check_ssr_bounds(matches.is_present("check-ssr-bounds"))
+        .source_lines(matches.is_present("source-lines"));
+    if let Some(args) = matches.values_of("llvm-args") {
+        builder = builder.llvm_args(args.map(String::from).collect());
+    }
+    if let Some(size) = matches.value_of("access-log") {
+        builder = builder.access_log_size(size.parse().context("Invalid access log size")?);
+    }
+    if let Some(interval) = matches.value_of("progress") {
+        builder = builder.progress(util::parse_duration(interval)?);
+    }
+    if let Some(timeout) = matches.value_of("deadlock-timeout") {
+        builder = builder.deadlock_timeout(util::parse_duration(timeout)?);
+    }
+    if let Some(n) = matches.value_of("trace-sample") {
+        let n: u64 = n.parse().context("Invalid trace sampling rate")?;
+        if n == 0 {
+            bail!("The trace sampling rate must be at least one instruction");
+        }
+        builder = builder.trace_sample(n);
+    }
+    if matches.is_present("deterministic") {
+        let quantum: u64 = matches
+            .value_of("quantum")
+            .unwrap()
+            .parse()
+            .context("Invalid quantum")?;
+        if quantum == 0 {
+            bail!("The quantum must be at least one instruction");
+        }
+        builder = builder.deterministic(quantum);
+    }
+    if matches.is_present("detect-livelock") {
+        let threshold = matches.value_of("livelock-threshold").unwrap();
+        builder =
+            builder.livelock_threshold(threshold.parse().context("Invalid livelock threshold")?);
+    }
+    if let Some(max) = matches.value_of("max-instret") {
+        builder = builder.max_instret(max.parse().context("Invalid instruction limit")?);
+    }
+    if let Some(max) = matches.value_of("max-seconds") {
+        builder = builder.max_duration(util::parse_duration(max)?);
+    }
+    if matches.is_present("pin-harts") {
+        builder = builder.pin_cpus(
+            matches
+                .values_of("pin-harts")
+                .into_iter()
+                .flatten()
+                .map(|x| x.parse())
+                .collect::<Result<_, _>>()
+                .context("Invalid CPU in `--pin-harts`")?,
+        );
+    }
+    if let Some(policy) = matches.value_of("abort-policy") {
+        builder = builder.abort_policy(policy.parse()?);
+    }
+    if let Some(policy) = matches.value_of("overlap-policy") {
+        builder = builder.overlap_policy(policy.parse()?);
+    }
+    if let Some(dir) = matches.value_of("fs-root") {
+        builder = builder.fs_root(dir);
+    }
+    if let Some(addr) = matches.value_of("uart") {
+        builder = builder.uart(
+            u32::from_str_radix(addr.trim_start_matches("0x"), 16)
+                .context("Invalid UART address")?,
+        );
+    }
+    if let Some(protocol) = matches.value_of("exit-protocol") {
+        builder = builder.exit_protocol(protocol.parse()?);
     }
-    engine.trace = matches.is_present("trace");
-    engine.latency = matches.is_present("latency");
 
-    let has_num_cores = matches.is_present("num-cores");
-    let has_num_clusters = matches.is_present("num-clusters");
-    let has_base_hartid = matches.is_present("base-hartid");
     let has_bin_files = matches.is_present("file-paths");
-
-    matches
-        .value_of("num-cores")
-        .map(|x| engine.num_cores = x.parse().unwrap());
-    matches
-        .value_of("num-clusters")
-        .map(|x| engine.num_clusters = x.parse().unwrap());
-    matches
-        .value_of("base-hartid")
-        .map(|x| engine.base_hartid = x.parse().unwrap());
+    if let Some(x) = matches.value_of("num-cores") {
+        builder = builder.num_cores(x.parse().context("Invalid number of cores")?);
+    }
+    if let Some(x) = matches.value_of("num-clusters") {
+        builder = builder.num_clusters(x.parse().context("Invalid number of clusters")?);
+    }
+    if let Some(x) = matches.value_of("base-hartid") {
+        builder = builder.base_hartid(x.parse().context("Invalid base hart ID")?);
+    }
 
     if let Some(file) = matches.value_of("create-configuration") {
         Configuration::print_default(file)?;
     }
-    debug!("Configuration used:\n{}", engine.config);
-    // debug!("Configuration used: {} {} {}\n", engine.num_cores, engine.num_clusters, engine.base_hartid);
-
-    engine.config = if let Some(config_file) = matches.value_of("configuration") {
-        // if configuration file is given and `architecture` information is set
-        // use that configuration, else banshee parameter
-        let config_used: Configuration = Configuration::parse(
+    if let Some(config_file) = matches.value_of("configuration") {
+        // The architecture given on the command line takes precedence over
+        // the one in the configuration file.
+        builder = builder.configuration(Configuration::parse(
             config_file,
-            engine.num_clusters,
-            has_num_clusters,
-            engine.num_cores,
-            has_num_cores,
-            engine.base_hartid,
-            has_base_hartid,
-        );
-        // get configuration
-        engine.num_cores = config_used.architecture.num_cores;
-        engine.num_clusters = config_used.architecture.num_clusters;
-        engine.base_hartid = config_used.architecture.base_hartid;
-        config_used
-    } else {
-        Configuration::new(engine.num_clusters, engine.num_cores, engine.base_hartid)
-    };
+            1,
+            false,
+            1,
+            false,
+            0,
+            false,
+        )?);
+    }
+    if let Some(file) = matches.value_of("cycle-model") {
+        builder = builder.cycle_model(CycleModel::load(Path::new(file))?);
+    }
+    let mut engine = builder.build().context("Invalid engine configuration")?;
+    if engine.interrupt {
+        debug!("Interrupts enabled");
+    }
     debug!("Configuration used:\n{}", engine.config);
+    if matches.subcommand_matches("dump-config").is_some() {
+        print!("{}", engine.config);
+        return Ok(());
+    }
 
-    // Read the binary.
-    let path = Path::new(matches.value_of("binary").unwrap());
-    info!("Loading binary {}", path.display());
-    let elf = match elf::File::open_path(&path) {
-        Ok(f) => f,
-        Err(e) => bail!("Failed to open binary {}: {:?}", path.display(), e),
+    // Read the binaries.
+    let mut cluster_elfs = vec![];
+    for binary in matches.values_of("cluster-binary").into_iter().flatten() {
+        let (path, clusters) = util::parse_cluster_binary(binary)?;
+        info!("Loading binary {} for clusters {:?}", path, clusters);
+        cluster_elfs.push((path, loader::load_elf(Path::new(path))?, clusters));
+    }
+    let elf = match matches.value_of("binary") {
+        Some(path) => {
+            info!("Loading binary {}", path);
+            Some(loader::load_elf(Path::new(path))?)
+        }
+        None => None,
     };
+    let path = matches
+        .value_of("binary")
+        .unwrap_or_else(|| cluster_elfs[0].0);
+
+    if let Some(heap) = matches.value_of("heap") {
+        let parse = |x: &str| u32::from_str_radix(x.trim_start_matches("0x"), 16);
+        let (start, end) = match heap.split_once(':') {
+            Some((start, end)) => (parse(start), parse(end)),
+            None => bail!("Invalid heap `{}`, expected START:END", heap),
+        };
+        engine
+            .set_heap(
+                start.context("Invalid heap start")?,
+                end.context("Invalid heap end")?,
+            )
+            .context("Invalid heap")?;
+    }
 
-    // Create a module for each cluster
-    engine.create_modules();
+    // Pass the command line arguments and environment to the binary.
+    if matches.is_present("app-arg") || matches.is_present("app-env") {
+        let mut args = app_args::AppArgs::default();
+        args.args.push(path.to_string());
+        args.args.extend(
+            matches
+                .values_of("app-arg")
+                .into_iter()
+                .flatten()
+                .map(String::from),
+        );
+        for var in matches.values_of("app-env").into_iter().flatten() {
+            if !var.contains('=') {
+                bail!("Environment variable `{}` is not of the form KEY=VAL", var);
+            }
+            args.env.push(var.to_string());
+        }
+        let addr = match matches.value_of("app-args-addr") {
+            Some(x) => Some(
+                u32::from_str_radix(x.trim_start_matches("0x"), 16)
+                    .context("Invalid address of the arguments")?,
+            ),
+            None => None,
+        };
+        engine.set_app_args(args, addr)?;
+    }
 
-    // Translate the binary.
-    engine
-        .translate_elf(&elf)
-        .context("Failed to translate ELF binary")?;
+    // Translate the binaries. The main binary runs on the harts of the
+    // clusters which have no binary of their own.
+    for (_, cluster_elf, clusters) in cluster_elfs {
+        engine
+            .translate_elf_for_clusters(&cluster_elf, clusters)
+            .context("Failed to translate ELF binary")?;
+    }
+    if let Some(elf) = &elf {
+        engine
+            .translate_elf(elf)
+            .context("Failed to translate ELF binary")?;
+    }
 
     if has_bin_files {
         // loop through the file paths
@@ -307,11 +632,13 @@ fn main() -> Result<()> {
         // loop through the files and offsets
         for (file_path, mem_offset) in file_paths.zip(mem_offsets) {
             trace!(
+                target: log_target::MEM,
                 "Loading binary data from file: {} and storing at memory offset: {}",
                 file_path,
                 mem_offset
             );
             debug!(
+                target: log_target::MEM,
                 "Loading binary data from file: {} and storing at memory offset: {}",
                 file_path, mem_offset
             );
@@ -329,7 +656,12 @@ fn main() -> Result<()> {
             mem.extend(data);
             for addr in mem_offset..mem_offset + data_length {
                 let val: u32 = mem.get(&(addr)).copied().unwrap_or(0);
-                trace!("address = 0x{:x}, binary value = {:#034b}", addr, val);
+                trace!(
+                    target: log_target::MEM,
+                    "address = 0x{:x}, binary value = {:#034b}",
+                    addr,
+                    val
+                );
             }
         }
     }
@@ -359,16 +691,44 @@ fn main() -> Result<()> {
         }
     }
 
-    // Init the peripherals
-    engine.init_periphs();
-
-    // Init the Bootrom
-    engine.init_bootrom();
+    // Execute the binary at all optimization levels if requested.
+    if matches.is_present("all-opt-levels") {
+        let exit_codes = engine
+            .run_all_opt_levels(elf.as_ref().unwrap())
+            .context("Failed to execute ELF binary")?;
+        info!("Exit codes by optimization level: {:?}", exit_codes);
+        if exit_codes.iter().any(|&code| code != exit_codes[0]) {
+            bail!(
+                "Exit codes differ between optimization levels: {:?}",
+                exit_codes
+            );
+        }
+        std::process::exit(exit_codes[0] as i32);
+    }
 
     // Execute the binary.
     if !matches.is_present("dry-run") {
-        let return_code = engine.execute().context("Failed to execute ELF binary")?;
-        std::process::exit(return_code as i32);
+        let stats = engine
+            .execute_with_stats()
+            .context("Failed to execute ELF binary")?;
+        if let Some(path) = matches.value_of("stats") {
+            stats
+                .write_json(path)
+                .with_context(|| format!("Failed to write statistics to {}", path))?;
+        }
+        if let Some(path) = matches.value_of("coverage") {
+            engine
+                .coverage_report()
+                .write_json(path)
+                .with_context(|| format!("Failed to write coverage to {}", path))?;
+        }
+        if let Some(e) = engine.take_execution_error() {
+            return Err(e.context("Failed to execute ELF binary"));
+        }
+        if stats.error {
+            bail!("Failed to execute ELF binary: Encountered an error during execution");
+        }
+        std::process::exit(stats.exit_code as i32);
     }
     Ok(())
 }
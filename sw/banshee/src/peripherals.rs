@@ -4,6 +4,7 @@
 
 /// Generic, memory-mapped peripherals implemented using runtime callbacks.
 use crate::configuration::Callback;
+use crate::util::log_target;
 use std::sync::atomic::{AtomicU32, Ordering};
 use PeriphReq::{Load, Store};
 
@@ -40,6 +41,12 @@ impl Peripherals {
         );
     }
 
+    /// Return the peripherals to their initial state, keeping the ones
+    /// added to each cluster.
+    pub fn reset(&mut self) {
+        self.peripherals = get_peripheral_types();
+    }
+
     pub fn load(&self, cluster_id: usize, addr: u32, size: u8) -> u32 {
         self.load_store(cluster_id, addr, size, Load)
     }
@@ -54,6 +61,7 @@ impl Peripherals {
                 return match req {
                     Load => {
                         trace!(
+                            target: log_target::MEM,
                             "Periph load from {}: cluster_id {}, offs 0x{:x}, size {}",
                             self.peripherals[i.1].get_name(),
                             cluster_id,
@@ -64,6 +72,7 @@ impl Peripherals {
                     }
                     Store(val, mask) => {
                         trace!(
+                            target: log_target::MEM,
                             "Periph store to {}: cluster_id {}, offs 0x{:x}, size {}, mask 0x{:x}, val {}",
                             self.peripherals[i.1].get_name(),
                             cluster_id,
@@ -83,12 +92,14 @@ impl Peripherals {
         // TODO: should we trigger an error-response-like exception here?
         match req {
             Load => trace!(
+                target: log_target::MEM,
                 "Unmapped periph load: cluster_id {}, addr {}, size {}",
                 cluster_id,
                 addr,
                 size
             ),
             Store(val, mask) => trace!(
+                target: log_target::MEM,
                 "Unmapped periph store: cluster_id {}, addr {}, size {}, mask {}, val {}",
                 cluster_id,
                 addr,
@@ -0,0 +1,129 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Python bindings for scripting simulations
+//!
+//! Built with the `python` feature into the shared library of the
+//! `banshee-python` crate, this is a Python extension module named `banshee`,
+//! which `make test-python` builds and tests:
+//!
+//! ```python
+//! import banshee
+//!
+//! engine = banshee.Engine('{"architecture": {"num_cores": 1}}')
+//! engine.load_elf("app.elf")
+//! engine.write_memory(0x100000, bytes([1, 2, 3, 4]))
+//! exit_code = engine.run()
+//! result = engine.read_memory(0x100000, 4)
+//! instret = engine.stats()["instret"]
+//! ```
+//!
+//! `run` releases the GIL while the binary executes, such that other Python
+//! threads keep running, and can end the execution with `stop`.
+
+use crate::{
+    configuration::Configuration,
+    engine::{Engine, EngineBuilder},
+    loader,
+    stats::ExecutionStats,
+};
+use pyo3::{exceptions::PyRuntimeError, prelude::*, types::PyBytes};
+use std::sync::{atomic::Ordering, Mutex};
+
+/// Convert an error into a Python `RuntimeError`, with its causes.
+fn to_py_err(e: anyhow::Error) -> PyErr {
+    PyRuntimeError::new_err(format!("{:#}", e))
+}
+
+/// A banshee engine, which translates and executes binaries.
+#[pyclass(name = "Engine")]
+struct PyEngine {
+    engine: Engine,
+    /// The statistics of the last execution.
+    stats: Mutex<Option<ExecutionStats>>,
+}
+
+#[pymethods]
+impl PyEngine {
+    /// Create an engine, optionally with a configuration in JSON.
+    #[new]
+    #[pyo3(signature = (config = None))]
+    fn new(config: Option<&str>) -> PyResult<Self> {
+        let mut builder = EngineBuilder::new();
+        if let Some(config) = config {
+            builder =
+                builder.configuration(Configuration::parse_str(config, true).map_err(to_py_err)?);
+        }
+        Ok(Self {
+            engine: builder.build().map_err(to_py_err)?,
+            stats: Default::default(),
+        })
+    }
+
+    /// Load and translate a binary.
+    fn load_elf(&mut self, path: &str) -> PyResult<()> {
+        let elf = loader::load_elf(std::path::Path::new(path)).map_err(to_py_err)?;
+        self.engine.translate_elf(&elf).map_err(to_py_err)?;
+        Ok(())
+    }
+
+    /// Write bytes to memory.
+    fn write_memory(&self, addr: u32, data: &[u8]) {
+        self.engine.write_mem(addr, data);
+    }
+
+    /// Read `len` bytes from memory.
+    fn read_memory<'py>(&self, py: Python<'py>, addr: u32, len: usize) -> &'py PyBytes {
+        PyBytes::new(py, &self.engine.read_mem(addr, len))
+    }
+
+    /// Execute the binary and return its exit code. Raises a `RuntimeError`
+    /// if execution fails.
+    fn run(&self, py: Python) -> PyResult<u32> {
+        let engine = &self.engine;
+        let stats = py
+            .allow_threads(|| engine.execute_with_stats())
+            .map_err(to_py_err)?;
+        let status = engine.exit_status(&stats);
+        *self.stats.lock().unwrap() = Some(stats);
+        status.exit_code().map_err(to_py_err)
+    }
+
+    /// Stop a running execution, e.g. from a watchdog thread.
+    fn stop(&self) {
+        self.engine.stop.store(true, Ordering::SeqCst);
+    }
+
+    /// The statistics of the last execution as a dict, in the format of the
+    /// `--stats` report, or `None` before the first execution.
+    fn stats(&self, py: Python) -> PyResult<PyObject> {
+        match &*self.stats.lock().unwrap() {
+            Some(stats) => {
+                let json = serde_json::to_string(stats)
+                    .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+                Ok(py.import("json")?.call_method1("loads", (json,))?.into())
+            }
+            None => Ok(py.None()),
+        }
+    }
+
+    /// The value of integer register `x<index>` of a hart after the last
+    /// execution.
+    fn get_reg(&self, hartid: usize, index: usize) -> PyResult<u32> {
+        self.engine.get_reg(hartid, index).map_err(to_py_err)
+    }
+
+    /// The raw value of floating-point register `f<index>` of a hart after
+    /// the last execution.
+    fn get_freg(&self, hartid: usize, index: usize) -> PyResult<u64> {
+        self.engine.get_freg(hartid, index).map_err(to_py_err)
+    }
+}
+
+/// The `banshee` Python module.
+#[pymodule]
+fn banshee(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyEngine>()?;
+    Ok(())
+}
@@ -879,7 +879,9 @@ pub enum OpcodeRdRmRs1 {
     FcvtLuH,
     FcvtHL,
     FcvtHLu,
+    FcvtSH,
     FcvtHS,
+    FcvtDH,
     FcvtHD,
     FcvtHH,
     FcvtBH,
@@ -940,7 +942,9 @@ impl std::fmt::Display for OpcodeRdRmRs1 {
             Self::FcvtLuH => write!(f, "fcvt.lu.h"),
             Self::FcvtHL => write!(f, "fcvt.h.l"),
             Self::FcvtHLu => write!(f, "fcvt.h.lu"),
+            Self::FcvtSH => write!(f, "fcvt.s.h"),
             Self::FcvtHS => write!(f, "fcvt.h.s"),
+            Self::FcvtDH => write!(f, "fcvt.d.h"),
             Self::FcvtHD => write!(f, "fcvt.h.d"),
             Self::FcvtHH => write!(f, "fcvt.h.h"),
             Self::FcvtBH => write!(f, "fcvt.b.h"),
@@ -1126,7 +1130,7 @@ pub enum OpcodeRdRs1 {
     FclassQ,
     Clz,
     Ctz,
-    Pcnt,
+    Cpop,
     SextB,
     SextH,
     Crc32B,
@@ -1138,8 +1142,6 @@ pub enum OpcodeRdRs1 {
     FmvXH,
     FclassH,
     FmvHX,
-    FcvtSH,
-    FcvtDH,
     FcvtHB,
     FcvtBB,
     VfsqrtS,
@@ -1224,7 +1226,7 @@ impl std::fmt::Display for OpcodeRdRs1 {
             Self::FclassQ => write!(f, "fclass.q"),
             Self::Clz => write!(f, "clz"),
             Self::Ctz => write!(f, "ctz"),
-            Self::Pcnt => write!(f, "pcnt"),
+            Self::Cpop => write!(f, "cpop"),
             Self::SextB => write!(f, "sext.b"),
             Self::SextH => write!(f, "sext.h"),
             Self::Crc32B => write!(f, "crc32.b"),
@@ -1236,8 +1238,6 @@ impl std::fmt::Display for OpcodeRdRs1 {
             Self::FmvXH => write!(f, "fmv.x.h"),
             Self::FclassH => write!(f, "fclass.h"),
             Self::FmvHX => write!(f, "fmv.h.x"),
-            Self::FcvtSH => write!(f, "fcvt.s.h"),
-            Self::FcvtDH => write!(f, "fcvt.d.h"),
             Self::FcvtHB => write!(f, "fcvt.h.b"),
             Self::FcvtBB => write!(f, "fcvt.b.b"),
             Self::VfsqrtS => write!(f, "vfsqrt.s"),
@@ -2336,8 +2336,8 @@ pub fn parse_u32(raw: u32) -> Format {
         0xa002033 => return parse_rd_rs1_rs2(OpcodeRdRs1Rs2::Clmulr, raw),
         0xa003033 => return parse_rd_rs1_rs2(OpcodeRdRs1Rs2::Clmulh, raw),
         0xa004033 => return parse_rd_rs1_rs2(OpcodeRdRs1Rs2::Min, raw),
-        0xa005033 => return parse_rd_rs1_rs2(OpcodeRdRs1Rs2::Max, raw),
-        0xa006033 => return parse_rd_rs1_rs2(OpcodeRdRs1Rs2::Minu, raw),
+        0xa005033 => return parse_rd_rs1_rs2(OpcodeRdRs1Rs2::Minu, raw),
+        0xa006033 => return parse_rd_rs1_rs2(OpcodeRdRs1Rs2::Max, raw),
         0xa007033 => return parse_rd_rs1_rs2(OpcodeRdRs1Rs2::Maxu, raw),
         0x8001033 => return parse_rd_rs1_rs2(OpcodeRdRs1Rs2::Shfl, raw),
         0x8005033 => return parse_rd_rs1_rs2(OpcodeRdRs1Rs2::Unshfl, raw),
@@ -2558,7 +2558,9 @@ pub fn parse_u32(raw: u32) -> Format {
         0xc4300053 => return parse_rd_rm_rs1(OpcodeRdRmRs1::FcvtLuH, raw),
         0xd4200053 => return parse_rd_rm_rs1(OpcodeRdRmRs1::FcvtHL, raw),
         0xd4300053 => return parse_rd_rm_rs1(OpcodeRdRmRs1::FcvtHLu, raw),
+        0x40200053 => return parse_rd_rm_rs1(OpcodeRdRmRs1::FcvtSH, raw),
         0x44000053 => return parse_rd_rm_rs1(OpcodeRdRmRs1::FcvtHS, raw),
+        0x42200053 => return parse_rd_rm_rs1(OpcodeRdRmRs1::FcvtDH, raw),
         0x44100053 => return parse_rd_rm_rs1(OpcodeRdRmRs1::FcvtHD, raw),
         0x44200053 => return parse_rd_rm_rs1(OpcodeRdRmRs1::FcvtHH, raw),
         0x46200053 => return parse_rd_rm_rs1(OpcodeRdRmRs1::FcvtBH, raw),
@@ -2583,7 +2585,7 @@ pub fn parse_u32(raw: u32) -> Format {
         0xe6001053 => return parse_rd_rs1(OpcodeRdRs1::FclassQ, raw),
         0x60001013 => return parse_rd_rs1(OpcodeRdRs1::Clz, raw),
         0x60101013 => return parse_rd_rs1(OpcodeRdRs1::Ctz, raw),
-        0x60201013 => return parse_rd_rs1(OpcodeRdRs1::Pcnt, raw),
+        0x60201013 => return parse_rd_rs1(OpcodeRdRs1::Cpop, raw),
         0x60401013 => return parse_rd_rs1(OpcodeRdRs1::SextB, raw),
         0x60501013 => return parse_rd_rs1(OpcodeRdRs1::SextH, raw),
         0x61001013 => return parse_rd_rs1(OpcodeRdRs1::Crc32B, raw),
@@ -2595,8 +2597,6 @@ pub fn parse_u32(raw: u32) -> Format {
         0xe4000053 => return parse_rd_rs1(OpcodeRdRs1::FmvXH, raw),
         0xe4001053 => return parse_rd_rs1(OpcodeRdRs1::FclassH, raw),
         0xf4000053 => return parse_rd_rs1(OpcodeRdRs1::FmvHX, raw),
-        0x40200053 => return parse_rd_rs1(OpcodeRdRs1::FcvtSH, raw),
-        0x42200053 => return parse_rd_rs1(OpcodeRdRs1::FcvtDH, raw),
         0x44300053 => return parse_rd_rs1(OpcodeRdRs1::FcvtHB, raw),
         0x46300053 => return parse_rd_rs1(OpcodeRdRs1::FcvtBB, raw),
         0x8e000033 => return parse_rd_rs1(OpcodeRdRs1::VfsqrtS, raw),
@@ -3148,7 +3148,9 @@ pub struct Latency {
     fcvt_lu_h: u8,
     fcvt_h_l: u8,
     fcvt_h_lu: u8,
+    fcvt_s_h: u8,
     fcvt_h_s: u8,
+    fcvt_d_h: u8,
     fcvt_h_d: u8,
     fcvt_h_h: u8,
     fcvt_b_h: u8,
@@ -3209,7 +3211,7 @@ pub struct Latency {
     fclass_q: u8,
     clz: u8,
     ctz: u8,
-    pcnt: u8,
+    cpop: u8,
     sext_b: u8,
     sext_h: u8,
     crc32_b: u8,
@@ -3221,8 +3223,6 @@ pub struct Latency {
     fmv_x_h: u8,
     fclass_h: u8,
     fmv_h_x: u8,
-    fcvt_s_h: u8,
-    fcvt_d_h: u8,
     fcvt_h_b: u8,
     fcvt_b_b: u8,
     vfsqrt_s: u8,
@@ -3675,7 +3675,9 @@ impl Default for Latency {
             fcvt_lu_h: 1,
             fcvt_h_l: 1,
             fcvt_h_lu: 1,
+            fcvt_s_h: 1,
             fcvt_h_s: 1,
+            fcvt_d_h: 1,
             fcvt_h_d: 1,
             fcvt_h_h: 1,
             fcvt_b_h: 1,
@@ -3733,7 +3735,7 @@ impl Default for Latency {
             fclass_q: 1,
             clz: 1,
             ctz: 1,
-            pcnt: 1,
+            cpop: 1,
             sext_b: 1,
             sext_h: 1,
             crc32_b: 1,
@@ -3745,8 +3747,6 @@ impl Default for Latency {
             fmv_x_h: 1,
             fclass_h: 1,
             fmv_h_x: 1,
-            fcvt_s_h: 1,
-            fcvt_d_h: 1,
             fcvt_h_b: 1,
             fcvt_b_b: 1,
             vfsqrt_s: 1,
@@ -13,7 +13,7 @@
 
 use std::{
     sync::{
-        atomic::{AtomicU32, AtomicUsize},
+        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize},
         Mutex,
     },
 };
@@ -23,6 +23,9 @@ pub struct WakeupState {
     pub num: usize,
     pub req: Vec<u64>,
     pub wfi: Vec<bool>,
+    /// The threads of the harts which have slept in `wfi`, to be unparked
+    /// when they are woken up.
+    pub threads: Vec<Option<std::thread::Thread>>,
 }
 
 /// A CPU pointer to be passed to the binary code.
@@ -37,12 +40,49 @@ pub struct Cpu<'a, 'b> {
     pub cluster_base_hartid: usize,
     /// The cluster's identifier.
     pub cluster_id: usize,
+    /// The optional features of the core, as a `CoreFeatures` bit mask.
+    pub features: u32,
     /// The cluster's shared barrier state.
     pub barrier: &'b AtomicUsize,
     pub wakeup_state: &'b Mutex<WakeupState>,
     pub clint: &'b Vec<AtomicU32>,
     /// cluster's shared CLINT state
     pub cl_clint: &'b AtomicUsize,
+    /// The engine's flag to stop execution.
+    pub stop: &'a AtomicBool,
+    /// The cluster's exit code (scratch) register.
+    pub exit_reg: &'b AtomicU32,
+    /// The last value the hart wrote to the exit code register.
+    pub exit_value: Mutex<Option<u32>>,
+    /// Whether an error occurred on this hart.
+    pub had_error: AtomicBool,
+    /// The loop without side effects the hart last took, for livelock
+    /// detection.
+    pub spin: SpinState,
+    /// The cycles of the hart which have been added to the engine's shared
+    /// clock.
+    pub clock_synced: AtomicU64,
+    /// The barrier flag value which releases the hart from the cluster
+    /// barrier, while it waits there in deterministic mode.
+    pub barrier_wait: Mutex<Option<usize>>,
+    /// The instret at which the hart yields to the scheduler of the
+    /// deterministic mode, to end its turn.
+    pub yield_at: AtomicU64,
+    /// Whether the hart has yielded to the scheduler to continue in its next
+    /// turn, rather than returned from the binary.
+    pub yielded: AtomicBool,
+}
+
+/// The iterations of a loop without side effects in which the registers of
+/// a hart did not change.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SpinState {
+    /// The address of the branch or jump which closes the loop.
+    pub addr: u32,
+    /// A hash of the registers when the loop was last taken.
+    pub regs_hash: u64,
+    /// The number of times the loop was taken with the same registers.
+    pub count: u64,
 }
 
 /// A representation of a single CPU core's state.
@@ -59,9 +99,15 @@ pub struct CpuState {
     pub ssrs: Vec<SsrState>,
     pub ssr_enable: u32,
     pub fpmode: u32,
+    pub fflags: u32,
+    pub frm: u32,
     pub dma: DmaState,
     pub wfi: bool,
     pub irq: IrqState,
+    pub stats: StatsState,
+    /// Whether the hart reached the end of execution on its own, rather than
+    /// being stopped while still running.
+    pub finished: bool,
 }
 
 /// A representation of a single SSR address generator's state.
@@ -77,6 +123,8 @@ pub struct SsrState {
     idx_ptr: u32,
     ptr: u32,
     ptr_next: u32,
+    /// The first address of the stream, whose memory it must not leave.
+    base: u32,
     repeat_count: u16,
     repeat_bound: u16,
     write: bool,
@@ -86,6 +134,69 @@ pub struct SsrState {
     accessed: bool,
 }
 
+impl SsrState {
+    /// Advance the loop indices to the next element of the affine access
+    /// pattern, and return the stride to be added to the pointer.
+    ///
+    /// The stride of the outermost loop which advances is used, such that the
+    /// strides configured for the outer loops are relative to the end of the
+    /// inner loops. Sets `done` once all loops have wrapped around.
+    #[inline(always)]
+    pub fn advance(&mut self) -> u32 {
+        let mut stride = 0;
+        self.done = true;
+        for i in 0..=(self.dims as usize & 3) {
+            unsafe {
+                stride = *self.stride.get_unchecked(i);
+                if *self.index.get_unchecked(i) == *self.bound.get_unchecked(i) {
+                    *self.index.get_unchecked_mut(i) = 0;
+                } else {
+                    *self.index.get_unchecked_mut(i) += 1;
+                    self.done = false;
+                    break;
+                }
+            }
+        }
+        stride
+    }
+
+    /// Move on to the next access of the stream, which repeats each element
+    /// `repeat_bound + 1` times before advancing to the next one.
+    ///
+    /// Returns the stride to be added to the pointer, or `None` if the
+    /// current element is repeated.
+    #[inline(always)]
+    pub fn step(&mut self) -> Option<u32> {
+        if self.repeat_count == self.repeat_bound {
+            self.repeat_count = 0;
+            Some(self.advance())
+        } else {
+            self.repeat_count += 1;
+            None
+        }
+    }
+
+    /// Check whether the stream has run past the end of its access pattern,
+    /// or a loop index lies beyond its bound.
+    #[inline(always)]
+    pub fn overrun(&self) -> bool {
+        self.done
+            || (0..=(self.dims as usize & 3)).any(|i| unsafe {
+                *self.index.get_unchecked(i) > *self.bound.get_unchecked(i)
+            })
+    }
+
+    /// Start a new stream at the first element of the access pattern.
+    #[inline(always)]
+    pub fn restart(&mut self) {
+        self.index = [0; 4];
+        self.repeat_count = 0;
+    }
+}
+
+/// The number of transfers the DMA can have outstanding.
+pub const DMA_QUEUE_LEN: usize = 16;
+
 /// A representation of a DMA backend's state.
 #[derive(Default)]
 #[repr(C)]
@@ -96,7 +207,17 @@ pub struct DmaState {
     dst_stride: u32,
     reps: u32,
     size: u32,
+    /// The ID of the next transfer to be issued.
+    next_id: u32,
+    /// The ID of the oldest transfer which has not completed yet.
     done_id: u32,
+    /// The time at which each outstanding transfer completes, by ID.
+    pending: [u64; DMA_QUEUE_LEN],
+    /// The number of cycles each transfer takes in addition to the data.
+    latency: u32,
+    /// The number of bytes transferred per cycle, or 0 for instantaneous
+    /// transfers.
+    bandwidth: u32,
 }
 
 /// Store IRQ relevant CSRs
@@ -117,4 +238,91 @@ pub struct IrqState {
     pub mepc: u32,
     // machine cause
     pub mcause: u32,
+    // machine trap value
+    pub mtval: u32,
+    // machine scratch register
+    pub mscratch: u32,
+}
+
+/// The memory regions distinguished by the access statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum MemRegion {
+    Tcdm,
+    TcdmExternal,
+    Peripherals,
+    Bootrom,
+    Clint,
+    Control,
+    Dram,
+}
+
+/// The number of variants in `MemRegion`.
+pub const NUM_MEM_REGIONS: usize = 7;
+
+/// Counters gathered for the end-of-run statistics.
+#[derive(Default)]
+#[repr(C)]
+pub struct StatsState {
+    /// Number of executions per instruction mnemonic slot.
+    pub inst_mix: Vec<u64>,
+    /// Number of memory accesses per `MemRegion`.
+    pub mem_accesses: [AtomicU64; NUM_MEM_REGIONS],
+    /// Number of DMA transfers issued.
+    pub dma_transfers: u64,
+    /// Number of bytes copied by the DMA.
+    pub dma_bytes: u64,
+}
+
+/// Classify a floating-point value for the `fclass` instructions.
+///
+/// The value has the given exponent and mantissa widths, and is held in the
+/// lower bits of a float register. Values narrower than the register which
+/// are not properly NaN-boxed are classified as quiet NaN.
+#[allow(dead_code)] // only used by the translated binary
+#[inline(always)]
+pub fn fclass(value: u64, exp_bits: u32, man_bits: u32) -> u32 {
+    let width = 1 + exp_bits + man_bits;
+    if width < 64 && (value >> width) != u64::max_value() >> width {
+        return 1 << 9;
+    }
+    let sign = (value >> (exp_bits + man_bits)) & 1 != 0;
+    let exp = (value >> man_bits) & ((1 << exp_bits) - 1);
+    let man = value & ((1 << man_bits) - 1);
+    let class = if exp == (1 << exp_bits) - 1 {
+        if man == 0 {
+            // infinity
+            if sign {
+                0
+            } else {
+                7
+            }
+        } else if (man >> (man_bits - 1)) & 1 != 0 {
+            // quiet NaN
+            9
+        } else {
+            // signaling NaN
+            8
+        }
+    } else if exp == 0 {
+        if man == 0 {
+            // zero
+            if sign {
+                3
+            } else {
+                4
+            }
+        } else if sign {
+            // subnormal
+            2
+        } else {
+            5
+        }
+    } else if sign {
+        // normal
+        1
+    } else {
+        6
+    };
+    1 << class
 }
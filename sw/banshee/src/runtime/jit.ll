@@ -18,15 +18,25 @@
 
 ; Forward declarations.
 declare i32 @banshee_load(%Cpu* %cpu, i32 %addr, i8 %size)
-declare void @banshee_store(%Cpu* %cpu, i32 %addr, i32 %value, i32 %mask, i8 %size)
+declare i32 @banshee_store(%Cpu* %cpu, i32 %addr, i32 %value, i32 %mask, i8 %size)
 declare i32 @banshee_rmw(%Cpu* %cpu, i32 %addr, i32 %value, i8 %op)
 declare i32 @banshee_csr_read(%Cpu* %cpu, i16 %csr, i32 %notrace)
 declare void @banshee_csr_write(%Cpu* %cpu, i16 %csr, i32 %value, i32 %notrace)
+declare i64 @banshee_fp_op(%Cpu* %cpu, i8 %op, i32 %rm, i32 %double, i64 %rs1, i64 %rs2, i64 %rs3)
 declare void @banshee_abort_escape(%Cpu* %cpu, i32 %addr)
+declare void @banshee_abort_limit(%Cpu* %cpu, i32 %addr)
 declare void @banshee_abort_illegal_inst(%Cpu* %cpu, i32 %addr, i32 %raw)
 declare void @banshee_abort_illegal_branch(%Cpu* %cpu, i32 %addr, i32 %target)
+declare void @banshee_abort_missing_feature(%Cpu* %cpu, i32 %addr, i32 %feature)
+declare i32 @banshee_trap(%Cpu* %cpu, i32 %addr, i32 %cause, i32 %tval)
 declare void @banshee_trace(%Cpu* %cpu, i32 %addr, i32 %raw, [2 x i64] %access_slice, [2 x i64] %data_slice)
 declare i32 @banshee_wfi(%Cpu* %cpu)
+declare i32 @banshee_resume(%Cpu* %cpu)
+declare i32 @banshee_ecall(%Cpu* %cpu)
+declare i32 @banshee_custom_inst(%Cpu* %cpu, i32 %index, i32 %raw)
+declare i32 @banshee_breakpoint(%Cpu* %cpu, i32 %addr)
+declare void @banshee_spin(%Cpu* %cpu, i32 %addr)
+declare void @banshee_observe_inst(%Cpu* %cpu, i32 %addr)
 declare i32 @banshee_check_clint(%Cpu* %cpu)
 declare i32 @banshee_check_cl_clint(%Cpu* %cpu)
 declare i64 @banshee_faddh(i64 %rs1, i64 %rs2, i8 %op)
@@ -39,6 +49,7 @@ declare i1 @banshee_fcmph(i16 %rs1, i16 %rs2, i8 %op)
 declare i1 @banshee_fcmpah(i16 %rs1, i16 %rs2, i8 %op)
 declare i1 @banshee_fcmpb(i8 %rs1, i8 %rs2, i8 %op)
 declare i1 @banshee_fcmpab(i8 %rs1, i8 %rs2, i8 %op)
+declare i32 @banshee_fclass(i64 %value, i32 %exp_bits, i32 %man_bits)
 declare i16 @banshee_fcvth(i64 %rs1, i8 %op)
 declare i16 @banshee_fcvtah(i64 %rs1, i8 %op)
 declare i8 @banshee_fcvtb(i64 %rs1, i8 %op)
@@ -47,11 +58,9 @@ declare i8 @banshee_fcvtab(i64 %rs1, i8 %op)
 declare i64 @banshee_fp64_op_cvt_to_f(i64 %rs1, i8 %op, i1 %fpmode_src, i1 %fpmode_dst)
 declare i32 @banshee_fp32_op_cvt_to_f(i64 %rs1, i8 %op, i1 %fpmode_src, i1 %fpmode_dst)
 declare i16 @banshee_fp16_op(i16 %rs1, i16 %rs2, i16 %rs3, i8 %op, i1 %fpmode_dst)
-declare i32 @banshee_fp16_op_cvt_from_f(i64 %rs1, i8 %op, i1 %fpmode_src, i1 %fpmode_dst)
 declare i16 @banshee_fp16_op_cvt_to_f(i64 %rs1, i8 %op, i1 %fpmode_src, i1 %fpmode_dst)
 declare i1 @banshee_fp16_op_cmp(i16 %rs1, i16 %rs2, i8 %op, i1 %fpmode_dst)
 declare i8 @banshee_fp8_op(i8 %rs1, i8 %rs2, i8 %rs3, i8 %op, i1 %fpmode_dst)
-declare i32 @banshee_fp8_op_cvt_from_f(i64 %rs1, i8 %op, i1 %fpmode_src, i1 %fpmode_dst)
 declare i8 @banshee_fp8_op_cvt_to_f(i64 %rs1, i8 %op, i1 %fpmode_src, i1 %fpmode_dst)
 declare i1 @banshee_fp8_op_cmp(i8 %rs1, i8 %rs2, i8 %op, i1 %fpmode_dst)
 declare float @banshee_fp16_to_fp32_op(i16 %rs1, i16 %rs2, float %rs3, i8 %op, i1 %fpmode_src)
@@ -60,7 +69,9 @@ declare float @banshee_fp8_to_fp32_op(i8 %rs1, i8 %rs2, float %rs3, i8 %op, i1 %
 
 declare void @banshee_ssr_write_cfg(%SsrState* %ssr,  %Cpu* %cpu, i32 %addr, i32 %value, i32 %mask)
 declare i32 @banshee_ssr_read_cfg(%SsrState* readonly %ssr, i32 %addr)
+declare i32 @banshee_ssr_active(%SsrState* readonly %ssr, i32 %write)
 declare i32 @banshee_ssr_next(%SsrState* %ssr, %Cpu* %cpu)
+declare void @banshee_ssr_check(%SsrState* readonly %ssr, %Cpu* %cpu, i32 %dm, i32 %write)
 declare void @banshee_ssr_eoi(%SsrState* %ssr)
 
 declare void @banshee_dma_src(%DmaState* writeonly %dma, i32 %lo, i32 %hi)
@@ -68,7 +79,7 @@ declare void @banshee_dma_dst(%DmaState* writeonly %dma, i32 %lo, i32 %hi)
 declare i32 @banshee_dma_strt(%DmaState* %dma, %Cpu* %cpu, i32 %size, i32 %flags)
 declare void @banshee_dma_str(%DmaState* writeonly %dma, i32 %src, i32 %dst)
 declare void @banshee_dma_rep(%DmaState* writeonly %dma, i32 %reps)
-declare i32 @banshee_dma_stat(%DmaState* readonly %dma, i32 %addr)
+declare i32 @banshee_dma_stat(%Cpu* %cpu, i32 %addr)
 
 declare i32* @banshee_reg_ptr(%Cpu* %cpu, i32 %reg)
 declare i64* @banshee_reg_cycle_ptr(%Cpu* %cpu, i32 %reg)
@@ -78,6 +89,13 @@ declare i32* @banshee_cas_value_ptr(%Cpu* %cpu)
 declare i32* @banshee_pc_ptr(%Cpu* %cpu)
 declare i64* @banshee_cycle_ptr(%Cpu* %cpu)
 declare i64* @banshee_instret_ptr(%Cpu* %cpu)
+declare i32* @banshee_fflags_ptr(%Cpu* %cpu)
+declare i32 @banshee_frm(%Cpu* readonly %cpu)
+declare i32 @banshee_stop_requested(%Cpu* readonly %cpu)
+declare i32 @banshee_yield(%Cpu* %cpu, i32 %pc)
+declare i32 @banshee_features(%Cpu* readonly %cpu)
+declare i64* @banshee_inst_mix_ptr(%Cpu* %cpu, i32 %slot)
+declare i64* @banshee_mem_access_ptr(%Cpu* %cpu, i32 %region)
 declare i32* @banshee_tcdm_ptr(%Cpu* %cpu)
 declare i32* @banshee_tcdm_ext_ptr(%Cpu* %cpu, i32 %cluster_id)
 declare %SsrState* @banshee_ssr_ptr(%Cpu* %cpu, i32 %ssr)
@@ -73,6 +73,66 @@ pub unsafe fn banshee_instret_ptr<'a>(cpu: &'a mut Cpu) -> &'a mut u64 {
     &mut cpu.state.instret
 }
 
+/// Get a pointer to the accrued floating-point exception flags.
+#[no_mangle]
+#[inline(always)]
+pub unsafe fn banshee_fflags_ptr<'a>(cpu: &'a mut Cpu) -> &'a mut u32 {
+    &mut cpu.state.fflags
+}
+
+/// Get the dynamic rounding mode.
+#[no_mangle]
+#[inline(always)]
+pub unsafe fn banshee_frm(cpu: &Cpu) -> u32 {
+    cpu.state.frm
+}
+
+/// Check whether a hart in deterministic mode has used up its turn, and if
+/// so, record `pc` to continue at in its next turn.
+#[no_mangle]
+#[inline(always)]
+pub unsafe fn banshee_yield(cpu: &mut Cpu, pc: u32) -> u32 {
+    if cpu.state.instret < cpu.yield_at.load(std::sync::atomic::Ordering::Relaxed) {
+        return 0;
+    }
+    cpu.state.pc = pc;
+    cpu.yielded
+        .store(true, std::sync::atomic::Ordering::Relaxed);
+    1
+}
+
+/// Check whether execution should stop.
+#[no_mangle]
+#[inline(always)]
+pub unsafe fn banshee_stop_requested(cpu: &Cpu) -> u32 {
+    cpu.stop.load(std::sync::atomic::Ordering::Relaxed) as u32
+}
+
+/// Get the optional features of the core.
+#[no_mangle]
+#[inline(always)]
+pub unsafe fn banshee_features(cpu: &Cpu) -> u32 {
+    cpu.features
+}
+
+/// Get a pointer to an instruction mix counter.
+#[no_mangle]
+#[inline(always)]
+pub unsafe fn banshee_inst_mix_ptr<'a>(cpu: &'a mut Cpu, slot: u32) -> &'a mut u64 {
+    cpu.state.stats.inst_mix.get_unchecked_mut(slot as usize)
+}
+
+/// Get a pointer to a memory region access counter.
+#[no_mangle]
+#[inline(always)]
+pub unsafe fn banshee_mem_access_ptr<'a>(cpu: &'a mut Cpu, region: u32) -> &'a mut u64 {
+    cpu.state
+        .stats
+        .mem_accesses
+        .get_unchecked_mut(region as usize)
+        .get_mut()
+}
+
 /// Get a pointer to the TCDM buffer.
 #[no_mangle]
 #[inline(always)]
@@ -108,6 +168,16 @@ pub unsafe fn banshee_dma_ptr<'a>(cpu: &'a mut Cpu) -> &'a mut DmaState {
     &mut cpu.state.dma
 }
 
+/// Load an index of an indirect SSR stream.
+#[inline(always)]
+unsafe fn ssr_load_idx(cpu: &mut Cpu, addr: u32, size: u32) -> u32 {
+    extern "C" {
+        fn banshee_load(cpu: &mut Cpu, addr: u32, size: u8) -> u32;
+    }
+    let word = banshee_load(cpu, addr & !3, 2);
+    (word >> (8 * (addr & 3))) & ((((1 as u64) << (8 << size)) - 1) as u32)
+}
+
 /// Write to an SSR control register.
 #[no_mangle]
 pub unsafe fn banshee_ssr_write_cfg(
@@ -117,15 +187,12 @@ pub unsafe fn banshee_ssr_write_cfg(
     value: u32,
     mask: u32,
 ) {
-    extern "C" {
-        fn banshee_load(cpu: &mut Cpu, addr: u32, size: u8) -> u32;
-    }
     // TODO: Handle the mask!
     let addr = addr as usize / 8;
-    let mut set_ptr = 0;
+    let mut set_ptr = None;
     match addr {
         0 => {
-            set_ptr = value & ((1 << 28) - 1);
+            set_ptr = Some(value & ((1 << 28) - 1));
             ssr.done = ((value >> 31) & 1) != 0;
             ssr.write = ((value >> 30) & 1) != 0;
             ssr.dims = ((value >> 28) & 3) as u8;
@@ -139,31 +206,38 @@ pub unsafe fn banshee_ssr_write_cfg(
         12 => ssr.idx_shift = value,
         // Indirection supports 1 loop only, but dimension fields are kept for future use.
         16..=19 | 24..=27 => {
-            set_ptr = value;
+            set_ptr = Some(value);
             ssr.done = false;
             ssr.write = false;
-            ssr.dims = (addr - 24) as u8;
+            ssr.dims = (addr & 3) as u8;
             ssr.indir = addr < 20;
         }
         20..=23 | 28..=31 => {
-            set_ptr = value;
+            set_ptr = Some(value);
             ssr.done = false;
             ssr.write = true;
-            ssr.dims = (addr - 28) as u8;
+            ssr.dims = (addr & 3) as u8;
             ssr.indir = addr < 24;
         }
         // TODO: Issue an error
         _ => (),
     }
+    // Only writing a pointer starts a new stream.
+    let set_ptr = match set_ptr {
+        Some(set_ptr) => set_ptr,
+        None => return,
+    };
+    ssr.restart();
     if ssr.indir {
         ssr.idx_ptr = set_ptr;
-        let idx = banshee_load(cpu, ssr.idx_ptr, ssr.idx_size as u8);
+        let idx = ssr_load_idx(cpu, ssr.idx_ptr, ssr.idx_size);
         ssr.ptr_next = ssr
             .idx_base
             .wrapping_add((idx << ssr.idx_shift) * ssr.stride.get_unchecked(0))
     } else {
         ssr.ptr_next = set_ptr;
     }
+    ssr.base = ssr.ptr_next;
 }
 
 /// Read from an SSR control register.
@@ -195,12 +269,34 @@ pub unsafe fn banshee_ssr_read_cfg(ssr: &mut SsrState, addr: u32) -> u32 {
     }
 }
 
-/// Generate the next address from an SSR.
+/// Check whether an SSR streams in the given direction.
+///
+/// Write streams which have been exhausted by a previous instruction are no
+/// longer active, such that surplus writes do not land in memory past the end
+/// of the configured access pattern.
 #[no_mangle]
-pub unsafe fn banshee_ssr_next(ssr: &mut SsrState, cpu: &mut Cpu) -> u32 {
+pub unsafe fn banshee_ssr_active(ssr: &SsrState, write: u32) -> u32 {
+    if ssr.write != (write != 0) {
+        return 0;
+    }
+    (!ssr.write || !ssr.done || ssr.accessed) as u32
+}
+
+/// Check the next access to an enabled SSR against the end of its access
+/// pattern and the memory its stream started in.
+#[no_mangle]
+pub unsafe fn banshee_ssr_check(ssr: &SsrState, cpu: &mut Cpu, dm: u32, write: u32) {
     extern "C" {
-        fn banshee_load(cpu: &mut Cpu, addr: u32, size: u8) -> u32;
+        fn banshee_ssr_check_addr(cpu: &mut Cpu, dm: u32, base: u32, addr: u32, overrun: u32);
+    }
+    if cpu.state.ssr_enable != 0 && ssr.write == (write != 0) && !ssr.accessed {
+        banshee_ssr_check_addr(cpu, dm, ssr.base, ssr.ptr, ssr.overrun() as u32);
     }
+}
+
+/// Generate the next address from an SSR.
+#[no_mangle]
+pub unsafe fn banshee_ssr_next(ssr: &mut SsrState, cpu: &mut Cpu) -> u32 {
     // TODO: Assert that the SSR is not done.
     let ptr = ssr.ptr;
     // execute increment only, if SSR register has not been previously
@@ -208,31 +304,16 @@ pub unsafe fn banshee_ssr_next(ssr: &mut SsrState, cpu: &mut Cpu) -> u32 {
     // is retired. This prohibits that an instruction using ftX multiple
     // times (e.g. fmul.d ft3, ft0, ft0) from being served different values
     if !ssr.accessed {
-        if ssr.repeat_count == ssr.repeat_bound {
-            ssr.repeat_count = 0;
-            let mut stride = 0;
-            ssr.done = true;
-            for i in 0..=(ssr.dims as usize) {
-                stride = *ssr.stride.get_unchecked(i);
-                if *ssr.index.get_unchecked(i) == *ssr.bound.get_unchecked(i) {
-                    *ssr.index.get_unchecked_mut(i) = 0;
-                } else {
-                    *ssr.index.get_unchecked_mut(i) += 1;
-                    ssr.done = false;
-                    break;
-                }
-            }
+        if let Some(stride) = ssr.step() {
             if ssr.indir {
                 ssr.idx_ptr = ssr.idx_ptr.wrapping_add(1 << ssr.idx_size);
-                let idx = banshee_load(cpu, ssr.idx_ptr, ssr.idx_size as u8);
+                let idx = ssr_load_idx(cpu, ssr.idx_ptr, ssr.idx_size);
                 ssr.ptr_next = ssr
                     .idx_base
                     .wrapping_add((idx << ssr.idx_shift) * ssr.stride.get_unchecked(0))
             } else {
                 ssr.ptr_next = ssr.ptr.wrapping_add(stride);
             }
-        } else {
-            ssr.repeat_count += 1;
         }
     }
     ssr.accessed = true;
@@ -271,48 +352,124 @@ pub unsafe fn banshee_dma_rep(dma: &mut DmaState, reps: u32) {
     dma.reps = reps;
 }
 
+/// Copy a contiguous block of memory on behalf of the DMA.
+///
+/// Whole words are copied wherever source and destination are both aligned,
+/// the remaining bytes one by one.
+#[inline(always)]
+unsafe fn dma_copy(cpu: &mut Cpu, src: u64, dst: u64, size: u64) {
+    extern "C" {
+        fn banshee_load(cpu: &mut Cpu, addr: u32, size: u8) -> u32;
+        fn banshee_store(cpu: &mut Cpu, addr: u32, value: u32, mask: u32, size: u8) -> u32;
+    }
+
+    let mut i = 0;
+    while i < size {
+        let src = (src + i) as u32;
+        let dst = (dst + i) as u32;
+        if (src | dst) & 3 == 0 && size - i >= 4 {
+            let tmp = banshee_load(cpu, src, 2);
+            banshee_store(cpu, dst, tmp, u32::max_value(), 2);
+            i += 4;
+        } else {
+            let tmp = (banshee_load(cpu, src & !3, 2) >> (8 * (src & 3))) & 0xff;
+            let shift = 8 * (dst & 3);
+            banshee_store(cpu, dst & !3, tmp << shift, 0xff << shift, 0);
+            i += 1;
+        }
+    }
+}
+
+/// The current time of a core, as seen by the DMA.
+///
+/// The cycle counter only advances if instruction latencies are modeled, so
+/// fall back to the number of retired instructions otherwise.
+#[inline(always)]
+fn dma_now(cpu: &Cpu) -> u64 {
+    std::cmp::max(cpu.state.cycle, cpu.state.instret)
+}
+
+/// Retire the outstanding DMA transfers which have completed at `now`.
+#[inline(always)]
+unsafe fn dma_retire(dma: &mut DmaState, now: u64) {
+    while dma.done_id != dma.next_id
+        && *dma.pending.get_unchecked(dma.done_id as usize % DMA_QUEUE_LEN) <= now
+    {
+        dma.done_id = dma.done_id.wrapping_add(1);
+    }
+}
+
 /// Implementation of the `dm.strt` and `dm.strti` instructions.
+///
+/// Two-dimensional transfers copy `reps` rows of `size` bytes, advancing
+/// source and destination by their respective stride after each row. The
+/// rows are copied in order, such that overlapping rows observe the data
+/// written by earlier ones.
+///
+/// The data is copied right away, but the transfer only completes after the
+/// DMA's configured latency, once all earlier transfers have completed. If
+/// the queue of outstanding transfers is full, the core stalls until the
+/// oldest one completes.
 #[no_mangle]
 pub unsafe fn banshee_dma_strt(dma: &mut DmaState, cpu: &mut Cpu, size: u32, flags: u32) -> u32 {
-    extern "C" {
-        fn banshee_load(cpu: &mut Cpu, addr: u32, size: u8) -> u32;
-        fn banshee_store(cpu: &mut Cpu, addr: u32, value: u32, mask: u32, size: u8);
+    let now = dma_now(cpu);
+    dma_retire(dma, now);
+    if dma.next_id.wrapping_sub(dma.done_id) as usize >= DMA_QUEUE_LEN {
+        let oldest = *dma.pending.get_unchecked(dma.done_id as usize % DMA_QUEUE_LEN);
+        cpu.state.cycle = std::cmp::max(cpu.state.cycle, oldest);
+        dma_retire(dma, oldest);
     }
+    let now = dma_now(cpu);
 
-    let id = dma.done_id;
-    dma.done_id += 1;
+    let id = dma.next_id;
+    dma.next_id = dma.next_id.wrapping_add(1);
     dma.size = size;
 
-    // assert_eq!(
-    //     size % 4,
-    //     0,
-    //     "DMA transfer size must be a multiple of 4B for now"
-    // );
-    let num_beats = size / 4;
     let enable_2d = (flags & (1 << 1)) != 0;
     let steps = if enable_2d { dma.reps } else { 1 };
 
     for i in 0..steps as u64 {
         let src = dma.src + i * dma.src_stride as u64;
         let dst = dma.dst + i * dma.dst_stride as u64;
-        // assert_eq!(src % 4, 0, "DMA src transfer block must be 4-byte-aligned");
-        // assert_eq!(dst % 4, 0, "DMA dst transfer block must be 4-byte-aligned");
-        for j in 0..num_beats as u64 {
-            let tmp = banshee_load(cpu, (src + j * 4) as u32, 2);
-            banshee_store(cpu, (dst + j * 4) as u32, tmp, u32::max_value(), 2);
-        }
+        dma_copy(cpu, src, dst, size as u64);
     }
+    let bytes = steps as u64 * size as u64;
+    cpu.state.stats.dma_transfers += 1;
+    cpu.state.stats.dma_bytes += bytes;
+
+    // Schedule the completion behind the previous transfer.
+    let start = if id != dma.done_id {
+        std::cmp::max(
+            now,
+            *dma.pending.get_unchecked(id.wrapping_sub(1) as usize % DMA_QUEUE_LEN),
+        )
+    } else {
+        now
+    };
+    let duration = dma.latency as u64
+        + match dma.bandwidth {
+            0 => 0,
+            bw => (bytes + bw as u64 - 1) / bw as u64,
+        };
+    *dma.pending.get_unchecked_mut(id as usize % DMA_QUEUE_LEN) = start + duration;
+    dma_retire(dma, now);
 
     id
 }
 
 /// Implementation of the `dm.stat` and `dm.stati` instructions.
 #[no_mangle]
-pub unsafe fn banshee_dma_stat(dma: &DmaState, addr: u32) -> u32 {
+pub unsafe fn banshee_dma_stat(cpu: &mut Cpu, addr: u32) -> u32 {
+    let now = dma_now(cpu);
+    let dma = &mut cpu.state.dma;
+    dma_retire(dma, now);
+    let outstanding = dma.next_id.wrapping_sub(dma.done_id);
     match addr & 0x3 {
-        0 => dma.done_id,     // completed_id
-        1 => dma.done_id + 1, // next_id
-        2 | 3 => 0,           // busy
+        // All IDs lower than the completed ID have completed.
+        0 => dma.done_id, // completed_id
+        1 => dma.next_id, // next_id
+        2 => (outstanding != 0) as u32, // busy
+        3 => (outstanding as usize >= DMA_QUEUE_LEN) as u32, // would_block
         _ => 0,
     }
 }
@@ -323,3 +480,9 @@ pub unsafe fn banshee_dma_stat(dma: &DmaState, addr: u32) -> u32 {
 pub unsafe fn banshee_irq_sample_ptr<'a>(cpu: &'a mut Cpu) -> &'a mut u32 {
     &mut cpu.state.irq.sample_ctr
 }
+
+/// Implementation of the `fclass` instructions.
+#[no_mangle]
+pub unsafe fn banshee_fclass(value: u64, exp_bits: u32, man_bits: u32) -> u32 {
+    fclass(value, exp_bits, man_bits)
+}
@@ -117,11 +117,24 @@ impl std::fmt::Debug for DmaState {
             .field("dst stride", &format_args!("{:08x}", self.dst_stride))
             .field("reps", &self.reps)
             .field("size", &self.size)
+            .field("next_id", &self.next_id)
             .field("done_id", &self.done_id)
             .finish()
     }
 }
 
+impl DmaState {
+    /// Create a DMA whose transfers take `latency` cycles plus one cycle for
+    /// every `bandwidth` bytes copied.
+    pub fn new(latency: u32, bandwidth: u32) -> Self {
+        Self {
+            latency,
+            bandwidth,
+            ..Default::default()
+        }
+    }
+}
+
 impl std::fmt::Debug for IrqState {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         f.debug_struct("IrqState")
@@ -131,6 +144,172 @@ impl std::fmt::Debug for IrqState {
             .field("mtvec", &format_args!("{:08x}", self.mtvec))
             .field("mepc", &format_args!("{:08x}", self.mepc))
             .field("mcause", &format_args!("{:08x}", self.mcause))
+            .field("mtval", &format_args!("{:08x}", self.mtval))
+            .field("mscratch", &format_args!("{:08x}", self.mscratch))
             .finish()
     }
 }
+
+impl MemRegion {
+    /// All memory regions, in the order of their access counters.
+    pub const ALL: [MemRegion; NUM_MEM_REGIONS] = [
+        MemRegion::Tcdm,
+        MemRegion::TcdmExternal,
+        MemRegion::Peripherals,
+        MemRegion::Bootrom,
+        MemRegion::Clint,
+        MemRegion::Control,
+        MemRegion::Dram,
+    ];
+
+    /// The name of the region in the execution statistics.
+    pub fn name(self) -> &'static str {
+        match self {
+            MemRegion::Tcdm => "tcdm",
+            MemRegion::TcdmExternal => "tcdm_external",
+            MemRegion::Peripherals => "peripherals",
+            MemRegion::Bootrom => "bootrom",
+            MemRegion::Clint => "clint",
+            MemRegion::Control => "control",
+            MemRegion::Dram => "dram",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Generate the addresses of an SSR with the given inclusive loop bounds
+    /// and absolute per-dimension strides.
+    fn ssr_addrs(base: u32, bounds: &[u32], strides: &[u32]) -> Vec<u32> {
+        let mut ssr = SsrState::default();
+        ssr.dims = bounds.len() as u8 - 1;
+        // The configured strides are relative to the end of the inner loops.
+        let mut inner = 0u32;
+        for i in 0..bounds.len() {
+            ssr.bound[i] = bounds[i];
+            ssr.stride[i] = strides[i].wrapping_sub(inner);
+            inner = inner.wrapping_add(bounds[i].wrapping_mul(strides[i]));
+        }
+        let mut ptr = base;
+        let mut addrs = vec![];
+        loop {
+            addrs.push(ptr);
+            let stride = ssr.advance();
+            if ssr.done {
+                break addrs;
+            }
+            ptr = ptr.wrapping_add(stride);
+        }
+    }
+
+    /// The reference nested loops of the affine access pattern.
+    fn reference_addrs(base: u32, bounds: &[u32], strides: &[u32]) -> Vec<u32> {
+        let mut addrs = vec![base];
+        for (&bound, &stride) in bounds.iter().zip(strides) {
+            addrs = (0..=bound)
+                .flat_map(|i| {
+                    addrs
+                        .iter()
+                        .map(move |a| a.wrapping_add(i.wrapping_mul(stride)))
+                })
+                .collect();
+        }
+        addrs
+    }
+
+    #[test]
+    fn ssr_affine_patterns() {
+        let shapes: &[(&[u32], &[u32])] = &[
+            (&[7], &[8]),
+            (&[3, 2], &[8, 64]),
+            (&[2, 0, 3], &[8, 1024, 32]),
+            (&[1, 2, 1, 2], &[8, 16, 128, 4096]),
+            (&[3, 3, 2, 4], &[8, 256, 8, 32]),
+            (&[0, 0, 0, 0], &[8, 16, 32, 64]),
+            (&[0, 3, 0, 1], &[8, 16, 32, 64]),
+            (&[2, 1, 1], &[8, -24i32 as u32, 512]),
+        ];
+        for &(bounds, strides) in shapes {
+            assert_eq!(
+                ssr_addrs(0x1000, bounds, strides),
+                reference_addrs(0x1000, bounds, strides),
+                "bounds {:?}, strides {:?}",
+                bounds,
+                strides
+            );
+        }
+    }
+
+    #[test]
+    fn fclass_boundaries() {
+        let box32 = |bits: u32| 0xffff_ffff_0000_0000 | bits as u64;
+        let single: &[(u64, u32)] = &[
+            (box32(0xff80_0000), 0),    // -inf
+            (box32(0xff7f_ffff), 1),    // most negative normal
+            (box32(0x8080_0000), 1),    // negative smallest normal
+            (box32(0x807f_ffff), 2),    // negative largest subnormal
+            (box32(0x8000_0001), 2),    // negative smallest subnormal
+            (box32(0x8000_0000), 3),    // -0
+            (box32(0x0000_0000), 4),    // +0
+            (box32(0x0000_0001), 5),    // smallest subnormal
+            (box32(0x007f_ffff), 5),    // largest subnormal
+            (box32(0x0080_0000), 6),    // smallest normal
+            (box32(0x7f7f_ffff), 6),    // largest normal
+            (box32(0x7f80_0000), 7),    // +inf
+            (box32(0x7f80_0001), 8),    // signaling NaN
+            (box32(0xffbf_ffff), 8),    // negative signaling NaN
+            (box32(0x7fc0_0000), 9),    // quiet NaN
+            (box32(0xffff_ffff), 9),    // negative quiet NaN
+            (0x0000_0000_3f80_0000, 9), // improperly boxed
+            (0x7fff_ffff_0000_0000, 9), // improperly boxed
+        ];
+        for &(value, class) in single {
+            assert_eq!(fclass(value, 8, 23), 1 << class, "fclass.s {:016x}", value);
+        }
+        let double: &[(u64, u32)] = &[
+            (0xfff0_0000_0000_0000, 0),
+            (0x8010_0000_0000_0000, 1),
+            (0x800f_ffff_ffff_ffff, 2),
+            (0x8000_0000_0000_0000, 3),
+            (0x0000_0000_0000_0000, 4),
+            (0x0000_0000_0000_0001, 5),
+            (0x000f_ffff_ffff_ffff, 5),
+            (0x0010_0000_0000_0000, 6),
+            (0x7fef_ffff_ffff_ffff, 6),
+            (0x7ff0_0000_0000_0000, 7),
+            (0x7ff0_0000_0000_0001, 8),
+            (0x7ff7_ffff_ffff_ffff, 8),
+            (0x7ff8_0000_0000_0000, 9),
+            (0xfff8_0000_0000_0001, 9),
+        ];
+        for &(value, class) in double {
+            assert_eq!(fclass(value, 11, 52), 1 << class, "fclass.d {:016x}", value);
+        }
+    }
+
+    #[test]
+    fn ssr_repeat() {
+        let mut ssr = SsrState::default();
+        ssr.bound[0] = 3;
+        ssr.stride[0] = 8;
+        ssr.repeat_bound = 3;
+        let mut ptr = 0x1000;
+        let mut addrs = vec![];
+        while !ssr.done {
+            addrs.push(ptr);
+            ptr += ssr.step().unwrap_or(0);
+        }
+        let expected: Vec<u32> = (0..16).map(|i| 0x1000 + i / 4 * 8).collect();
+        assert_eq!(addrs, expected);
+
+        // Restarting the stream discards the progress of the previous one.
+        ssr.done = false;
+        ssr.step();
+        ssr.step();
+        ssr.restart();
+        assert_eq!(ssr.index, [0; 4]);
+        assert_eq!(ssr.step(), None);
+    }
+}
@@ -0,0 +1,311 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Expansion of compressed (RVC) instructions
+//!
+//! Every 16 bit instruction of the C extension has an equivalent 32 bit
+//! instruction. Instead of translating the compressed instructions directly,
+//! they are expanded and translated like their 32 bit counterparts.
+
+/// Check whether the lower half of an instruction parcel is a compressed
+/// instruction.
+pub fn is_compressed(raw: u32) -> bool {
+    raw & 0b11 != 0b11
+}
+
+/// The length of an instruction in bytes, based on its lower 16 bits.
+pub fn inst_len(raw: u32) -> u64 {
+    if is_compressed(raw) {
+        2
+    } else {
+        4
+    }
+}
+
+/// Expand a compressed instruction into its 32 bit equivalent.
+///
+/// Returns `None` for illegal and reserved encodings, as well as for
+/// encodings which are only valid on RV64.
+pub fn expand(raw: u16) -> Option<u32> {
+    let raw = raw as u32;
+    let funct3 = bits(raw, 15, 13);
+    // The full and the compressed (x8-x15) register fields.
+    let rd = bits(raw, 11, 7);
+    let rs2 = bits(raw, 6, 2);
+    let rd_c = bits(raw, 4, 2) + 8;
+    let rs1_c = bits(raw, 9, 7) + 8;
+    // The 6 bit immediate of the CI format.
+    let imm6 = sext(bits(raw, 12, 12) << 5 | bits(raw, 6, 2), 6);
+    match (raw & 0b11, funct3) {
+        // C.ADDI4SPN
+        (0b00, 0b000) => {
+            let imm = bits(raw, 12, 11) << 4
+                | bits(raw, 10, 7) << 6
+                | bits(raw, 6, 6) << 2
+                | bits(raw, 5, 5) << 3;
+            if imm == 0 {
+                return None;
+            }
+            Some(itype(imm, 2, 0b000, rd_c, OP_IMM))
+        }
+        // C.FLD
+        (0b00, 0b001) => Some(itype(uimm_d(raw), rs1_c, 0b011, rd_c, LOAD_FP)),
+        // C.LW
+        (0b00, 0b010) => Some(itype(uimm_w(raw), rs1_c, 0b010, rd_c, LOAD)),
+        // C.FLW
+        (0b00, 0b011) => Some(itype(uimm_w(raw), rs1_c, 0b010, rd_c, LOAD_FP)),
+        // C.FSD
+        (0b00, 0b101) => Some(stype(uimm_d(raw), rd_c, rs1_c, 0b011, STORE_FP)),
+        // C.SW
+        (0b00, 0b110) => Some(stype(uimm_w(raw), rd_c, rs1_c, 0b010, STORE)),
+        // C.FSW
+        (0b00, 0b111) => Some(stype(uimm_w(raw), rd_c, rs1_c, 0b010, STORE_FP)),
+        // C.ADDI, C.NOP
+        (0b01, 0b000) => Some(itype(imm6, rd, 0b000, rd, OP_IMM)),
+        // C.JAL
+        (0b01, 0b001) => Some(jtype(cj_offset(raw), 1)),
+        // C.LI
+        (0b01, 0b010) => Some(itype(imm6, 0, 0b000, rd, OP_IMM)),
+        // C.ADDI16SP
+        (0b01, 0b011) if rd == 2 => {
+            let imm = bits(raw, 12, 12) << 9
+                | bits(raw, 6, 6) << 4
+                | bits(raw, 5, 5) << 6
+                | bits(raw, 4, 3) << 7
+                | bits(raw, 2, 2) << 5;
+            if imm == 0 {
+                return None;
+            }
+            Some(itype(sext(imm, 10), 2, 0b000, 2, OP_IMM))
+        }
+        // C.LUI
+        (0b01, 0b011) => {
+            if imm6 == 0 || rd == 0 {
+                return None;
+            }
+            Some(imm6 << 12 | rd << 7 | LUI)
+        }
+        (0b01, 0b100) => match bits(raw, 11, 10) {
+            // C.SRLI
+            0b00 if bits(raw, 12, 12) == 0 => Some(itype(rs2, rs1_c, 0b101, rs1_c, OP_IMM)),
+            // C.SRAI
+            0b01 if bits(raw, 12, 12) == 0 => {
+                Some(itype(0b0100000 << 5 | rs2, rs1_c, 0b101, rs1_c, OP_IMM))
+            }
+            // C.ANDI
+            0b10 => Some(itype(imm6, rs1_c, 0b111, rs1_c, OP_IMM)),
+            0b11 if bits(raw, 12, 12) == 0 => {
+                let (funct7, funct3) = match bits(raw, 6, 5) {
+                    0b00 => (0b0100000, 0b000), // C.SUB
+                    0b01 => (0b0000000, 0b100), // C.XOR
+                    0b10 => (0b0000000, 0b110), // C.OR
+                    _ => (0b0000000, 0b111),    // C.AND
+                };
+                Some(rtype(funct7, rd_c, rs1_c, funct3, rs1_c, OP))
+            }
+            _ => None,
+        },
+        // C.J
+        (0b01, 0b101) => Some(jtype(cj_offset(raw), 0)),
+        // C.BEQZ
+        (0b01, 0b110) => Some(btype(cb_offset(raw), 0, rs1_c, 0b000)),
+        // C.BNEZ
+        (0b01, 0b111) => Some(btype(cb_offset(raw), 0, rs1_c, 0b001)),
+        // C.SLLI
+        (0b10, 0b000) if bits(raw, 12, 12) == 0 => Some(itype(rs2, rd, 0b001, rd, OP_IMM)),
+        // C.FLDSP
+        (0b10, 0b001) => {
+            let imm = bits(raw, 12, 12) << 5 | bits(raw, 6, 5) << 3 | bits(raw, 4, 2) << 6;
+            Some(itype(imm, 2, 0b011, rd, LOAD_FP))
+        }
+        // C.LWSP, C.FLWSP
+        (0b10, 0b010) | (0b10, 0b011) => {
+            let imm = bits(raw, 12, 12) << 5 | bits(raw, 6, 4) << 2 | bits(raw, 3, 2) << 6;
+            if funct3 == 0b010 {
+                if rd == 0 {
+                    return None;
+                }
+                Some(itype(imm, 2, 0b010, rd, LOAD))
+            } else {
+                Some(itype(imm, 2, 0b010, rd, LOAD_FP))
+            }
+        }
+        (0b10, 0b100) => match (bits(raw, 12, 12), rd, rs2) {
+            // C.JR
+            (0, 0, 0) => None,
+            (0, _, 0) => Some(itype(0, rd, 0b000, 0, JALR)),
+            // C.MV
+            (0, _, _) => Some(rtype(0, rs2, 0, 0b000, rd, OP)),
+            // C.EBREAK
+            (1, 0, 0) => Some(0x00100073),
+            // C.JALR
+            (1, _, 0) => Some(itype(0, rd, 0b000, 1, JALR)),
+            // C.ADD
+            (_, _, _) => Some(rtype(0, rs2, rd, 0b000, rd, OP)),
+        },
+        // C.FSDSP
+        (0b10, 0b101) => {
+            let imm = bits(raw, 12, 10) << 3 | bits(raw, 9, 7) << 6;
+            Some(stype(imm, rs2, 2, 0b011, STORE_FP))
+        }
+        // C.SWSP, C.FSWSP
+        (0b10, 0b110) | (0b10, 0b111) => {
+            let imm = bits(raw, 12, 9) << 2 | bits(raw, 8, 7) << 6;
+            let opcode = if funct3 == 0b110 { STORE } else { STORE_FP };
+            Some(stype(imm, rs2, 2, 0b010, opcode))
+        }
+        _ => None,
+    }
+}
+
+const LOAD: u32 = 0b0000011;
+const LOAD_FP: u32 = 0b0000111;
+const OP_IMM: u32 = 0b0010011;
+const STORE: u32 = 0b0100011;
+const STORE_FP: u32 = 0b0100111;
+const OP: u32 = 0b0110011;
+const LUI: u32 = 0b0110111;
+const BRANCH: u32 = 0b1100011;
+const JALR: u32 = 0b1100111;
+const JAL: u32 = 0b1101111;
+
+/// Extract the bits `hi..=lo` of a value.
+fn bits(raw: u32, hi: u32, lo: u32) -> u32 {
+    (raw >> lo) & ((1 << (hi - lo + 1)) - 1)
+}
+
+/// Sign-extend a `width` bit value.
+fn sext(value: u32, width: u32) -> u32 {
+    (((value << (32 - width)) as i32) >> (32 - width)) as u32
+}
+
+/// The word-scaled offset of C.LW, C.SW, C.FLW, and C.FSW.
+fn uimm_w(raw: u32) -> u32 {
+    bits(raw, 12, 10) << 3 | bits(raw, 6, 6) << 2 | bits(raw, 5, 5) << 6
+}
+
+/// The double-word-scaled offset of C.FLD and C.FSD.
+fn uimm_d(raw: u32) -> u32 {
+    bits(raw, 12, 10) << 3 | bits(raw, 6, 5) << 6
+}
+
+/// The jump offset of C.J and C.JAL.
+fn cj_offset(raw: u32) -> u32 {
+    let offset = bits(raw, 12, 12) << 11
+        | bits(raw, 11, 11) << 4
+        | bits(raw, 10, 9) << 8
+        | bits(raw, 8, 8) << 10
+        | bits(raw, 7, 7) << 6
+        | bits(raw, 6, 6) << 7
+        | bits(raw, 5, 3) << 1
+        | bits(raw, 2, 2) << 5;
+    sext(offset, 12)
+}
+
+/// The branch offset of C.BEQZ and C.BNEZ.
+fn cb_offset(raw: u32) -> u32 {
+    let offset = bits(raw, 12, 12) << 8
+        | bits(raw, 11, 10) << 3
+        | bits(raw, 6, 5) << 6
+        | bits(raw, 4, 3) << 1
+        | bits(raw, 2, 2) << 5;
+    sext(offset, 9)
+}
+
+fn rtype(funct7: u32, rs2: u32, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    funct7 << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode
+}
+
+fn itype(imm: u32, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    (imm & 0xfff) << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode
+}
+
+fn stype(imm: u32, rs2: u32, rs1: u32, funct3: u32, opcode: u32) -> u32 {
+    bits(imm, 11, 5) << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | bits(imm, 4, 0) << 7 | opcode
+}
+
+fn btype(imm: u32, rs2: u32, rs1: u32, funct3: u32) -> u32 {
+    bits(imm, 12, 12) << 31
+        | bits(imm, 10, 5) << 25
+        | rs2 << 20
+        | rs1 << 15
+        | funct3 << 12
+        | bits(imm, 4, 1) << 8
+        | bits(imm, 11, 11) << 7
+        | BRANCH
+}
+
+fn jtype(imm: u32, rd: u32) -> u32 {
+    bits(imm, 20, 20) << 31
+        | bits(imm, 10, 1) << 21
+        | bits(imm, 11, 11) << 20
+        | bits(imm, 19, 12) << 12
+        | rd << 7
+        | JAL
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quadrant0() {
+        assert_eq!(expand(0x0000), None);
+        assert_eq!(expand(0x0808), Some(0x01010513)); // addi a0, sp, 16
+        assert_eq!(expand(0x4188), Some(0x0005a503)); // lw a0, 0(a1)
+        assert_eq!(expand(0x41c8), Some(0x0045a503)); // lw a0, 4(a1)
+        assert_eq!(expand(0xc188), Some(0x00a5a023)); // sw a0, 0(a1)
+        assert_eq!(expand(0x2588), Some(0x0085b507)); // fld fa0, 8(a1)
+        assert_eq!(expand(0xa588), Some(0x00a5b427)); // fsd fa0, 8(a1)
+        assert_eq!(expand(0x6188), Some(0x0005a507)); // flw fa0, 0(a1)
+        assert_eq!(expand(0xe188), Some(0x00a5a027)); // fsw fa0, 0(a1)
+    }
+
+    #[test]
+    fn quadrant1() {
+        assert_eq!(expand(0x0001), Some(0x00000013)); // nop
+        assert_eq!(expand(0x0505), Some(0x00150513)); // addi a0, a0, 1
+        assert_eq!(expand(0x157d), Some(0xfff50513)); // addi a0, a0, -1
+        assert_eq!(expand(0x4515), Some(0x00500513)); // li a0, 5
+        assert_eq!(expand(0x6505), Some(0x00001537)); // lui a0, 0x1
+        assert_eq!(expand(0x7541), Some(0xffff0537)); // lui a0, 0xffff0
+        assert_eq!(expand(0x1141), Some(0xff010113)); // addi sp, sp, -16
+        assert_eq!(expand(0x0141), Some(0x01010113)); // addi sp, sp, 16
+        assert_eq!(expand(0x8105), Some(0x00155513)); // srli a0, a0, 1
+        assert_eq!(expand(0x8505), Some(0x40155513)); // srai a0, a0, 1
+        assert_eq!(expand(0x893d), Some(0x00f57513)); // andi a0, a0, 15
+        assert_eq!(expand(0x8d0d), Some(0x40b50533)); // sub a0, a0, a1
+        assert_eq!(expand(0x8d2d), Some(0x00b54533)); // xor a0, a0, a1
+        assert_eq!(expand(0x8d4d), Some(0x00b56533)); // or a0, a0, a1
+        assert_eq!(expand(0x8d6d), Some(0x00b57533)); // and a0, a0, a1
+        assert_eq!(expand(0xa001), Some(0x0000006f)); // j .
+        assert_eq!(expand(0xbffd), Some(0xfffff06f)); // j .-2
+        assert_eq!(expand(0x2021), Some(0x008000ef)); // jal .+8
+        assert_eq!(expand(0xc111), Some(0x00050263)); // beqz a0, .+4
+        assert_eq!(expand(0xfd75), Some(0xfe051ee3)); // bnez a0, .-4
+    }
+
+    #[test]
+    fn quadrant2() {
+        assert_eq!(expand(0x050a), Some(0x00251513)); // slli a0, a0, 2
+        assert_eq!(expand(0x4502), Some(0x00012503)); // lw a0, 0(sp)
+        assert_eq!(expand(0x4532), Some(0x00c12503)); // lw a0, 12(sp)
+        assert_eq!(expand(0x4002), None); // lw zero, 0(sp)
+        assert_eq!(expand(0xc62a), Some(0x00a12623)); // sw a0, 12(sp)
+        assert_eq!(expand(0x2522), Some(0x00813507)); // fld fa0, 8(sp)
+        assert_eq!(expand(0xa42a), Some(0x00a13427)); // fsd fa0, 8(sp)
+        assert_eq!(expand(0x8082), Some(0x00008067)); // ret
+        assert_eq!(expand(0x8002), None); // jr zero
+        assert_eq!(expand(0x9502), Some(0x000500e7)); // jalr a0
+        assert_eq!(expand(0x852e), Some(0x00b00533)); // mv a0, a1
+        assert_eq!(expand(0x952e), Some(0x00b50533)); // add a0, a0, a1
+        assert_eq!(expand(0x9002), Some(0x00100073)); // ebreak
+    }
+
+    #[test]
+    fn lengths() {
+        assert_eq!(inst_len(0x4501), 2);
+        assert_eq!(inst_len(0x00000513), 4);
+    }
+}
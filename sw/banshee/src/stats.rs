@@ -0,0 +1,298 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+//! Machine-readable statistics gathered during execution
+//!
+//! The structures in this module are serialized as the `--stats` report. Add
+//! fields rather than changing or removing existing ones, and bump
+//! `STATS_VERSION` whenever the meaning of a field changes.
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+};
+
+/// The version of the statistics report schema.
+pub const STATS_VERSION: u32 = 2;
+
+/// Statistics of an entire execution.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ExecutionStats {
+    /// The version of the report schema.
+    pub version: u32,
+    /// The exit code of the binary. Each cluster has its own exit code
+    /// register; this is the first nonzero exit code among them, or zero.
+    pub exit_code: u32,
+    /// Whether the binary set the exit code register of any cluster.
+    pub exit_code_valid: bool,
+    /// Whether an error occurred during execution.
+    pub error: bool,
+    /// The wall-clock duration of the execution in seconds.
+    pub duration: f64,
+    /// The total number of retired instructions.
+    pub instret: u64,
+    /// The aggregate number of retired instructions per second.
+    pub inst_per_second: f64,
+    /// The statistics of the individual harts.
+    pub harts: Vec<HartStats>,
+    /// The hart which took the longest (see `slowest_hart`), which points to
+    /// load imbalance.
+    pub slowest_hart: Option<usize>,
+    /// The number of executed instructions per mnemonic.
+    pub inst_mix: BTreeMap<String, u64>,
+    /// The number of memory accesses per memory region.
+    pub mem_accesses: BTreeMap<String, u64>,
+    /// The DMA transfer totals.
+    pub dma: DmaStats,
+    /// The number of loads from addresses outside the memory map, which
+    /// usually indicate a wild pointer.
+    pub unmapped_reads: u64,
+    /// The number of stores to addresses outside the memory map.
+    pub unmapped_writes: u64,
+}
+
+/// Statistics of a single hart.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HartStats {
+    /// The hart's identifier.
+    pub hartid: usize,
+    /// The number of retired instructions.
+    pub instret: u64,
+    /// The estimated number of cycles, if latency modeling is enabled.
+    pub cycles: Option<u64>,
+    /// The wall-clock time in seconds since the start of the execution at
+    /// which the hart started, if it executed an image.
+    pub start: Option<f64>,
+    /// The wall-clock time in seconds since the start of the execution at
+    /// which the hart ended, if it executed an image.
+    pub end: Option<f64>,
+    /// Whether the hart reached the end of execution, as opposed to being
+    /// stopped while still running, e.g. because it was deadlocked.
+    pub finished: bool,
+    /// Whether the hart was sleeping in `wfi` when execution was stopped
+    /// before it finished, e.g. by a timeout.
+    pub parked: bool,
+    /// The program counter when execution ended.
+    pub pc: u32,
+    /// Whether an error occurred on the hart.
+    pub error: bool,
+    /// The raw value the hart last wrote to its cluster's exit code
+    /// register, if any.
+    pub exit_value: Option<u32>,
+    /// The exit code the hart reported, decoded from `exit_value` with the
+    /// exit protocol. Unlike the aggregate `exit_code`, this is not
+    /// overwritten by other harts of the cluster.
+    pub exit_code: Option<u32>,
+}
+
+/// Statistics of the DMA transfers.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DmaStats {
+    /// The number of transfers issued.
+    pub transfers: u64,
+    /// The number of bytes copied.
+    pub bytes: u64,
+}
+
+impl ExecutionStats {
+    /// The exit codes reported by the individual harts, by hart ID.
+    pub fn exit_codes(&self) -> BTreeMap<usize, u32> {
+        self.harts
+            .iter()
+            .filter_map(|hart| Some((hart.hartid, hart.exit_code?)))
+            .collect()
+    }
+
+    /// Write the statistics as JSON to a file.
+    pub fn write_json(&self, path: &str) -> std::io::Result<()> {
+        let f = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(f, self)?;
+        Ok(())
+    }
+}
+
+/// The hart which took the longest: the one with the most estimated cycles
+/// if latency modeling is enabled, or else the most retired instructions. Of
+/// equally busy harts, the one which ended last is the slowest.
+pub fn slowest_hart(harts: &[HartStats]) -> Option<usize> {
+    harts
+        .iter()
+        .max_by(|a, b| {
+            (a.cycles.unwrap_or(a.instret), a.instret)
+                .cmp(&(b.cycles.unwrap_or(b.instret), b.instret))
+                .then(a.end.partial_cmp(&b.end).unwrap_or(Ordering::Equal))
+        })
+        .map(|hart| hart.hartid)
+}
+
+/// The instructions encountered during translation and execution, by
+/// mnemonic.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Coverage {
+    /// The instructions which were translated.
+    pub translated: BTreeSet<String>,
+    /// The instructions which were decoded but could not be translated.
+    pub untranslated: BTreeSet<String>,
+    /// The instructions which were executed.
+    pub executed: BTreeSet<String>,
+}
+
+impl Coverage {
+    /// Record the executed instructions from an instruction mix.
+    pub fn record_executed(&mut self, inst_mix: &BTreeMap<String, u64>) {
+        self.executed.extend(
+            inst_mix
+                .iter()
+                .filter(|&(_, &count)| count != 0)
+                .map(|(mnemonic, _)| mnemonic.clone()),
+        );
+    }
+
+    /// The instructions which were translated but never executed.
+    pub fn never_executed(&self) -> BTreeSet<String> {
+        self.translated
+            .difference(&self.executed)
+            .cloned()
+            .collect()
+    }
+
+    /// Write the coverage as JSON to a file.
+    pub fn write_json(&self, path: &str) -> std::io::Result<()> {
+        let f = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(f, self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_round_trip() {
+        let stats = ExecutionStats {
+            version: STATS_VERSION,
+            exit_code: 3,
+            exit_code_valid: true,
+            error: false,
+            duration: 1.25,
+            instret: 300,
+            inst_per_second: 240.0,
+            harts: vec![
+                HartStats {
+                    hartid: 0,
+                    instret: 100,
+                    cycles: Some(150),
+                    start: Some(0.0),
+                    end: Some(1.0),
+                    finished: true,
+                    parked: false,
+                    pc: 0x80000010,
+                    error: false,
+                    exit_value: Some(7),
+                    exit_code: Some(3),
+                },
+                HartStats {
+                    hartid: 1,
+                    instret: 200,
+                    cycles: None,
+                    start: Some(0.25),
+                    end: Some(1.25),
+                    finished: false,
+                    parked: true,
+                    pc: 0x80000024,
+                    error: true,
+                    exit_value: None,
+                    exit_code: None,
+                },
+            ],
+            slowest_hart: Some(1),
+            inst_mix: vec![("addi".to_string(), 200), ("lw".to_string(), 100)]
+                .into_iter()
+                .collect(),
+            mem_accesses: vec![("tcdm".to_string(), 100)].into_iter().collect(),
+            dma: DmaStats {
+                transfers: 2,
+                bytes: 64,
+            },
+            unmapped_reads: 1,
+            unmapped_writes: 0,
+        };
+        let json = serde_json::to_string(&stats).unwrap();
+        let parsed: ExecutionStats = serde_json::from_str(&json).unwrap();
+        assert_eq!(stats, parsed);
+        assert_eq!(stats.exit_codes(), vec![(0, 3)].into_iter().collect());
+    }
+
+    #[test]
+    fn serde_schema() {
+        let json = serde_json::to_value(&ExecutionStats::default()).unwrap();
+        let keys: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+        assert_eq!(
+            keys,
+            vec![
+                "dma",
+                "duration",
+                "error",
+                "exit_code",
+                "exit_code_valid",
+                "harts",
+                "inst_mix",
+                "inst_per_second",
+                "instret",
+                "mem_accesses",
+                "slowest_hart",
+                "unmapped_reads",
+                "unmapped_writes",
+                "version",
+            ]
+        );
+    }
+
+    #[test]
+    fn slowest() {
+        let hart = |hartid, instret, cycles, end| HartStats {
+            hartid,
+            instret,
+            cycles,
+            end: Some(end),
+            ..Default::default()
+        };
+        assert_eq!(slowest_hart(&[]), None);
+        assert_eq!(
+            slowest_hart(&[hart(0, 100, None, 2.0), hart(1, 300, None, 1.0)]),
+            Some(1)
+        );
+        // The cycles take precedence over the retired instructions.
+        assert_eq!(
+            slowest_hart(&[hart(0, 100, Some(900), 1.0), hart(1, 300, Some(400), 1.0)]),
+            Some(0)
+        );
+        // Ties go to the hart which ended last.
+        assert_eq!(
+            slowest_hart(&[hart(0, 100, None, 2.0), hart(1, 100, None, 1.0)]),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn coverage() {
+        let mut coverage = Coverage::default();
+        for mnemonic in &["add", "lw", "beq", "jal"] {
+            coverage.translated.insert(mnemonic.to_string());
+        }
+        coverage.untranslated.insert("fdiv.q".to_string());
+        let inst_mix = vec![
+            ("add".to_string(), 10),
+            ("lw".to_string(), 3),
+            ("beq".to_string(), 0),
+        ]
+        .into_iter()
+        .collect();
+        coverage.record_executed(&inst_mix);
+        let set = |m: &[&str]| m.iter().map(|m| m.to_string()).collect::<BTreeSet<_>>();
+        assert_eq!(coverage.executed, set(&["add", "lw"]));
+        assert_eq!(coverage.never_executed(), set(&["beq", "jal"]));
+    }
+}
@@ -5,8 +5,10 @@
 //! Binary translation
 
 use crate::{
-    engine::{AtomicOp, Engine, TraceAccess},
-    riscv,
+    configuration::CoreFeatures,
+    engine::{AbortPolicy, AtomicOp, Engine, FpOp, MemRegion, TraceAccess},
+    riscv, rvc,
+    util::log_target,
 };
 use anyhow::{anyhow, bail, Context, Result};
 use llvm_sys::{
@@ -15,7 +17,7 @@ use llvm_sys::{
 };
 use std::{
     cell::{Cell, RefCell},
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeSet, HashMap, HashSet},
     ffi::CString,
 };
 extern crate flexfloat;
@@ -23,14 +25,40 @@ extern crate flexfloat;
 static NONAME: &'static i8 = unsafe { std::mem::transmute("\0".as_ptr()) };
 
 /// Base address of the stream semantic regsiters
-static SSR_BASE: u64 = 0x204800;
+pub static SSR_BASE: u64 = 0x204800;
+
+/// Number of SSR data movers which can be addressed by the `scfg`
+/// instructions. Only the configured number of them is implemented.
+pub const SSR_MAX_NUM_DM: u64 = 32;
+
+/// The data mover addressed by the `scfg*` instructions to configure all data
+/// movers at once.
+pub const SSR_DM_ALL: u64 = 31;
 
 /// Number of arguments the trace maximally shows per instruction.
 const TRACE_BUFFER_LEN: u32 = 8;
 
+/// The invalid operation flag in `fflags`.
+const FFLAGS_NV: u32 = 1 << 4;
+
 /// The length of a sequencer's instruction ring buffer.
 const SEQ_BUFFER_LEN: u8 = 16;
 
+/// The minimum number of instructions of a basic block to deduplicate it.
+const MIN_SHARED_BLOCK_LEN: usize = 2;
+
+/// The maximum size in bytes of a loop which is checked for livelocks.
+const MAX_SPIN_LOOP_SIZE: u64 = 256;
+
+/// Where to continue after the function of a deduplicated basic block or of
+/// an interpreter step returns: return from the binary, fall through to the
+/// end of the block, take the branch or jump which ends the block, or make an
+/// indirect jump.
+pub(crate) const BLOCK_EXIT_RETURN: u64 = 0;
+pub(crate) const BLOCK_EXIT_FALLTHROUGH: u64 = 1;
+pub(crate) const BLOCK_EXIT_BRANCH: u64 = 2;
+pub(crate) const BLOCK_EXIT_INDIRECT: u64 = 3;
+
 /// The sequencer's JIT iterators for loop emulation.
 struct SequencerIterators {
     /// A u32* pointing to the repetition index.
@@ -39,6 +67,45 @@ struct SequencerIterators {
     max_rpt_ref: LLVMValueRef,
 }
 
+/// An occurrence of a basic block whose code is emitted once into a function
+/// shared by all identical blocks.
+#[derive(Clone, Copy)]
+struct SharedOccurrence {
+    /// The function with the code of the block.
+    func: LLVMValueRef,
+    /// The start address of the occurrence.
+    start: u64,
+    /// The end address of the occurrence.
+    end: u64,
+    /// The target of the branch or jump which ends the occurrence, if any.
+    target: Option<u64>,
+}
+
+/// The code of one or more instructions emitted into a function of its own,
+/// which the engine calls in interpreter mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepUnit {
+    /// The address of the first instruction.
+    pub start: u64,
+    /// The address after the last instruction.
+    pub end: u64,
+    /// The target of the branch or jump which ends the unit, if any.
+    pub target: Option<u64>,
+}
+
+/// The state to emit the code of a basic block into its own function.
+struct SharedBlock {
+    /// The parameter holding the start address of the occurrence the function
+    /// is called for.
+    base: LLVMValueRef,
+    /// The start address of the block the code is emitted for.
+    start: u64,
+    /// Basic blocks for the instructions of the block and for its end.
+    inst_bbs: HashMap<u64, LLVMBasicBlockRef>,
+    /// The basic block which returns to take a branch out of the block.
+    branch_bb: LLVMBasicBlockRef,
+}
+
 /// The sequencer's context during section-level translation.
 struct SequencerContext {
     /// Whether the sequencer is currently buffering instructions
@@ -51,8 +118,9 @@ struct SequencerContext {
     stagger_max: u8,
     /// A mask indicating which register numbers to stagger.
     stagger_mask: u8,
-    /// The addresses for the instruction basic blocks buffered.
-    inst_buffer: [(u64, riscv::Format); SEQ_BUFFER_LEN as usize],
+    /// The addresses, encodings, and decoded forms of the instructions
+    /// buffered. Compressed instructions keep their 16 bit encoding.
+    inst_buffer: [(u64, u32, riscv::Format); SEQ_BUFFER_LEN as usize],
     /// The current buffer insertion point.
     buffer_pos: u8,
 }
@@ -66,7 +134,7 @@ impl SequencerContext {
             is_outer: false,
             stagger_max: 0,
             stagger_mask: 0,
-            inst_buffer: [(0, riscv::Format::Illegal(0)); SEQ_BUFFER_LEN as usize],
+            inst_buffer: [(0, 0, riscv::Format::Illegal(0)); SEQ_BUFFER_LEN as usize],
             buffer_pos: 0,
         }
     }
@@ -101,10 +169,10 @@ impl SequencerContext {
     }
 
     /// Push an instruction into the sequence buffer.
-    fn push_rep_instruction(&mut self, addr: u64, inst: riscv::Format) -> Result<()> {
+    fn push_rep_instruction(&mut self, addr: u64, raw: u32, inst: riscv::Format) -> Result<()> {
         if self.active {
             if self.buffer_pos <= self.max_inst {
-                self.inst_buffer[self.buffer_pos as usize] = (addr, inst);
+                self.inst_buffer[self.buffer_pos as usize] = (addr, raw, inst);
                 self.buffer_pos += 1;
                 Ok(())
             } else {
@@ -146,6 +214,14 @@ pub struct ElfTranslator<'a> {
     pub trace: bool,
     /// Generate instruction tracing code.
     pub latency: bool,
+    /// Execute floating-point operations with exact rounding and flags.
+    pub exact_fp: bool,
+    /// Flush subnormal floating-point inputs and results to zero.
+    pub flush_to_zero: bool,
+    /// Route all memory accesses through the runtime to check watchpoints and
+    /// stack guards, record them in the access log, and report them to the
+    /// access observer.
+    pub watch: bool,
     /// Start address of the fast local scratchpad.
     pub tcdm_start: u32,
     /// End address of the fast local scratchpad.
@@ -154,6 +230,34 @@ pub struct ElfTranslator<'a> {
     pub tcdm_ext_range: Vec<(u32, u32, u32)>,
     /// Cluster ID
     pub cluster_id: usize,
+    /// The features which all cores of the cluster have. Instructions which
+    /// need any other feature check for it at runtime.
+    pub common_features: u32,
+    /// The features which any core of the cluster has. Instructions which
+    /// need any other feature are unsupported.
+    pub any_features: u32,
+    /// Name of the emitted function which runs the binary.
+    pub entry: String,
+    /// Emit the code of identical basic blocks once into a function, which
+    /// each of them calls.
+    pub dedup_blocks: bool,
+    /// The deduplicated basic block occurrence of each instruction address,
+    /// and whether the instruction could be translated.
+    shared_blocks: HashMap<u64, (SharedOccurrence, bool)>,
+    /// Emit each instruction into a function of its own, which the engine
+    /// calls one at a time, instead of the function which runs the binary.
+    pub interpret: bool,
+    /// Let the harts yield to the scheduler of the deterministic mode at the
+    /// start of basic blocks, and after the instructions which may wait.
+    pub deterministic: bool,
+    /// Let the harts yield before any instruction in deterministic mode, such
+    /// that a run gate can grant exact numbers of instructions.
+    pub yield_every_inst: bool,
+    /// The functions emitted for the interpreter mode.
+    pub step_units: Vec<StepUnit>,
+    /// The addresses of the backward branches and jumps which close a loop
+    /// without side effects, to check for livelocks.
+    spin_loops: HashSet<u64>,
 }
 
 impl<'a> ElfTranslator<'a> {
@@ -220,31 +324,40 @@ impl<'a> ElfTranslator<'a> {
             inst_bbs: Default::default(),
             trace: engine.trace,
             latency: engine.latency,
+            exact_fp: engine.exact_fp,
+            flush_to_zero: engine.flush_to_zero,
+            watch: engine.has_watchpoints()
+                || engine.has_stack_guards()
+                || engine.has_access_observer()
+                || engine.access_log_size != 0,
             tcdm_start: engine.config.memory[cluster_id].tcdm.start,
             tcdm_end: engine.config.memory[cluster_id].tcdm.end,
             tcdm_ext_range,
             cluster_id,
+            common_features: engine.topology().common_features(cluster_id),
+            any_features: engine.topology().any_features(cluster_id),
+            entry: "execute_binary".to_string(),
+            dedup_blocks: engine.dedup_blocks,
+            shared_blocks: Default::default(),
+            interpret: engine.interpret && engine.deterministic.is_none(),
+            deterministic: engine.deterministic.is_some(),
+            yield_every_inst: engine.run_gate.is_some(),
+            step_units: Default::default(),
+            spin_loops: Default::default(),
         }
     }
 
     /// Get an iterator over the sections in the binary.
     pub fn sections(&self) -> impl Iterator<Item = &'a elf::Section> + '_ {
-        self.elf
-            .sections
-            .iter()
-            .filter(|section| (section.shdr.flags.0 & elf::types::SHF_EXECINSTR.0) != 0)
+        executable_sections(self.elf)
     }
 
     /// Get an iterator over the instructions in a section.
     pub fn instructions(
         &self,
         section: &'a elf::Section,
-    ) -> impl Iterator<Item = (u64, riscv::Format)> + '_ {
-        section
-            .data
-            .chunks(4)
-            .enumerate()
-            .map(move |(i, raw)| (section.shdr.addr + i as u64 * 4, riscv::parse(raw)))
+    ) -> impl Iterator<Item = (u64, u32, riscv::Format)> + '_ {
+        section_instructions(section)
     }
 
     /// Get an iterator over the `.symtab` sections.
@@ -256,7 +369,7 @@ impl<'a> ElfTranslator<'a> {
     }
 
     /// Get an iterator over all instructions in the binary.
-    pub fn all_instructions(&self) -> impl Iterator<Item = (u64, riscv::Format)> + '_ {
+    pub fn all_instructions(&self) -> impl Iterator<Item = (u64, u32, riscv::Format)> + '_ {
         self.sections().flat_map(move |s| self.instructions(s))
     }
 
@@ -282,135 +395,123 @@ impl<'a> ElfTranslator<'a> {
                 b.extend(a);
                 b
             });
-        trace!("Loaded {} symbols", symbols.len());
+        trace!(target: log_target::TRANSLATE, "Loaded {} symbols", symbols.len());
         for sym in symbols {
             if sym.symtype == elf::types::STT_FUNC {
-                debug!("Found symbol 0x{:x}: {}", sym.value, sym.name);
+                debug!(
+                    target: log_target::TRANSLATE,
+                    "Found symbol 0x{:x}: {}",
+                    sym.value,
+                    sym.name
+                );
                 target_addrs.insert(sym.value);
                 symbol_hints.insert(sym.value, sym.name);
             }
         }
 
-        // Estimate target addresses.
-        for (addr, inst) in self.all_instructions() {
-            match inst {
-                riscv::Format::Imm12RdRs1(
-                    fmt @ riscv::FormatImm12RdRs1 {
-                        op: riscv::OpcodeImm12RdRs1::Jalr,
-                        ..
-                    },
-                ) => {
-                    debug!("Found register jump 0x{:x}: {}", addr, inst);
-
-                    // If we keep the PC around, we expect to jump back to the
-                    // next instruction at some point.
-                    if fmt.rd != 0 {
-                        target_addrs.insert(addr + 4);
-                    }
-                }
-                riscv::Format::Jimm20Rd(
-                    fmt @ riscv::FormatJimm20Rd {
-                        op: riscv::OpcodeJimm20Rd::Jal,
-                        ..
-                    },
-                ) => {
-                    // Ensure that we can branch to the target address.
-                    let target = (addr as i64).wrapping_add(fmt.jimm() as i64) as u64;
-                    debug!(
-                        "Found immediate jump 0x{:x}: {} to 0x{:x}",
-                        addr, inst, target,
-                    );
-                    target_addrs.insert(target);
-
-                    // If we keep the PC around, we expect to jump back to the
-                    // next instruction at some point.
-                    if fmt.rd != 0 {
-                        target_addrs.insert(addr + 4);
-                    }
+        // Estimate target addresses, for each section in parallel if enabled.
+        let sections: Vec<_> = self.sections().collect();
+        if self.engine.parallel_analysis {
+            crossbeam_utils::thread::scope(|s| {
+                let handles: Vec<_> = sections
+                    .iter()
+                    .map(|&section| s.spawn(move |_| section_target_addrs(section)))
+                    .collect();
+                for handle in handles {
+                    target_addrs.extend(handle.join().unwrap());
                 }
-                riscv::Format::Bimm12hiBimm12loRs1Rs2(fmt) => {
-                    let target = (addr as i64).wrapping_add(fmt.bimm() as i64) as u64;
-                    debug!("Found branch 0x{:x}: {} to 0x{:x}", addr, inst, target,);
-                    target_addrs.insert(target);
-                    target_addrs.insert(addr + 4);
-                }
-                _ => (),
+            })
+            .unwrap();
+        } else {
+            for section in sections {
+                target_addrs.extend(section_target_addrs(section));
             }
         }
 
         // Dump what we have found.
-        trace!("Predicted jump targets:");
+        trace!(target: log_target::TRANSLATE, "Predicted jump targets:");
         for &addr in &target_addrs {
-            trace!("  - 0x{:x}", addr);
+            trace!(target: log_target::TRANSLATE, "  - 0x{:x}", addr);
         }
 
         self.target_addrs = target_addrs;
         self.symbol_hints = symbol_hints;
     }
 
+    /// Determine the basic blocks of the binary, based on the branch target
+    /// addresses estimated by `update_target_addrs`.
+    pub fn basic_blocks(&self) -> Vec<(u32, u32)> {
+        split_basic_blocks(self.all_instructions(), &self.target_addrs)
+    }
+
+    /// Find the short loops whose instructions have no side effects beyond
+    /// the registers of the hart, which spin forever if they do not change
+    /// any register.
+    fn find_spin_loops(&self) -> HashSet<u64> {
+        let mut spin_loops = HashSet::new();
+        for section in self.sections() {
+            let insts: Vec<_> = section_instructions(section).collect();
+            for &(addr, _, inst) in &insts {
+                let target = match direct_target(addr, inst) {
+                    Some(target) if target <= addr && addr - target <= MAX_SPIN_LOOP_SIZE => target,
+                    _ => continue,
+                };
+                let start = insts.partition_point(|&(addr, _, _)| addr < target);
+                let pure = insts[start..]
+                    .iter()
+                    .take_while(|&&(body_addr, _, _)| body_addr <= addr)
+                    .all(|&(_, _, inst)| !has_side_effects(inst));
+                if pure {
+                    trace!(
+                        target: log_target::TRANSLATE,
+                        "Loop 0x{:x}..=0x{:x} has no side effects",
+                        target,
+                        addr
+                    );
+                    spin_loops.insert(addr);
+                }
+            }
+        }
+        spin_loops
+    }
+
     /// Translate the binary.
     pub fn translate(&mut self) -> Result<()> {
         unsafe { self.translate_inner() }
     }
 
     unsafe fn translate_inner(&mut self) -> Result<()> {
-        debug!("Translating binary");
+        debug!(target: log_target::TRANSLATE, "Translating binary");
         let builder = LLVMCreateBuilderInContext(self.engine.context);
+        if self.engine.livelock_threshold.is_some() {
+            self.spin_loops = self.find_spin_loops();
+        }
 
-        // Assemble the struct type which holds the CPU state.
-        let state_type = LLVMGetTypeByName2(
-            self.engine.context,
-            format!("{}{}{}", "Cpu", self.cluster_id.to_string(), "\0").as_ptr() as *const _,
+        // Take the struct type which holds the CPU state from the runtime
+        // declarations of the module. Looking it up by name in the context
+        // finds the type of an earlier module of the same cluster instead,
+        // since LLVM renames the types of modules parsed later.
+        let load_func = LLVMGetNamedFunction(
+            self.engine.modules[self.cluster_id],
+            b"banshee_load\0".as_ptr() as *const _,
         );
-
-        let state_ptr_type = LLVMPointerType(state_type, 0u32);
+        let state_ptr_type = LLVMTypeOf(LLVMGetParam(load_func, 0));
 
         // Emit the function which will run the binary.
-        let func_name = format!("execute_binary\0");
-        let func_type = LLVMFunctionType(LLVMVoidType(), [state_ptr_type].as_mut_ptr(), 1, 0);
+        let func_name = format!("{}\0", self.entry);
+        let func_type = LLVMFunctionType(
+            LLVMVoidTypeInContext(self.engine.context),
+            [state_ptr_type].as_mut_ptr(),
+            1,
+            0,
+        );
         let func = LLVMAddFunction(
             self.engine.modules[self.cluster_id],
             func_name.as_ptr() as *const _,
             func_type,
         );
         let state_ptr = LLVMGetParam(func, 0);
-
-        // Emit the subprogram debug information for this function.
-        // let di_builder = LLVMCreateDIBuilder(self.engine.module);
-        let di_builder = self.di_builder;
-        let di_name = "execute_binary";
-        let di_linkage_name = "execute_binary";
-        let di_scope = LLVMDIBuilderCreateFunction(
-            di_builder,                           // Builder
-            self.di_file,                         // Scope
-            di_name.as_ptr() as *const _,         // Name
-            di_name.len(),                        // NameLen
-            di_linkage_name.as_ptr() as *const _, // LinkageName
-            di_linkage_name.len(),                // LinkageNameLen
-            self.di_file,                         // File
-            0,                                    // LineNo
-            LLVMDIBuilderCreateSubroutineType(
-                di_builder,
-                self.di_file,
-                [].as_mut_ptr(),
-                0,
-                LLVMDIFlagZero,
-            ), // Ty
-            0,                                    // IsLocalToUnit
-            1,                                    // IsDefinition
-            0,                                    // ScopeLine
-            LLVMDIFlagPrototyped,                 // Flags
-            0,                                    // IsOptimized
-        );
-        LLVMSetSubprogram(func, di_scope);
-        let di_loc = LLVMDIBuilderCreateDebugLocation(
-            self.engine.context,  // Ctx
-            0,                    // Line
-            0,                    // Column
-            di_scope,             // Scope
-            std::ptr::null_mut(), // InlinedAt
-        );
-        LLVMSetCurrentDebugLocation2(builder, di_loc);
+        let di_scope = self.emit_subprogram(func, &self.entry, builder);
 
         // Create the entry block.
         let entry_bb = LLVMAppendBasicBlockInContext(
@@ -420,47 +521,27 @@ impl<'a> ElfTranslator<'a> {
         );
         LLVMPositionBuilderAtEnd(builder, entry_bb);
 
+        // In interpreter mode, the engine calls the function of one
+        // instruction after another instead.
+        if self.interpret {
+            LLVMBuildRetVoid(builder);
+            let mut inst_index = 0;
+            self.step_units = self.emit_step_units(state_ptr_type, &mut inst_index)?;
+            LLVMDIBuilderFinalize(self.di_builder);
+            LLVMDisposeBuilder(builder);
+            return Ok(());
+        }
+
         // Allocate space for tracing data, if needed.
-        let (trace_access_buffer, trace_data_buffer) = if self.trace {
-            (
-                LLVMBuildAlloca(
-                    builder,
-                    LLVMArrayType(LLVMInt16Type(), TRACE_BUFFER_LEN),
-                    NONAME,
-                ),
-                LLVMBuildAlloca(
-                    builder,
-                    LLVMArrayType(LLVMInt64Type(), TRACE_BUFFER_LEN),
-                    NONAME,
-                ),
-            )
-        } else {
-            (std::ptr::null_mut(), std::ptr::null_mut())
-        };
+        let (trace_access_buffer, trace_data_buffer) = self.emit_trace_buffers(builder);
 
-        // Allocate the sequencer iterators and init them as pointing to a zero constant.
-        let const_zero_32 = LLVMConstInt(LLVMInt32Type(), 0, 0);
-        let rpt_ptr_ref = LLVMBuildAlloca(
-            builder,
-            LLVMInt32Type(),
-            b"frep_rpt_ptr\0".as_ptr() as *const _,
-        );
-        LLVMBuildStore(builder, const_zero_32, rpt_ptr_ref);
-        let max_rpt_ref = LLVMBuildAlloca(
-            builder,
-            LLVMInt32Type(),
-            b"frep_max_rpt\0".as_ptr() as *const _,
-        );
-        LLVMBuildStore(builder, const_zero_32, max_rpt_ref);
-        let fseq_iter = SequencerIterators {
-            rpt_ptr_ref,
-            max_rpt_ref,
-        };
+        // Allocate the sequencer iterators.
+        let fseq_iter = self.emit_sequencer_iterators(builder);
 
         // Gather the set of executable addresses.
         let inst_addrs: BTreeSet<u64> = self
             .all_instructions()
-            .map(|(addr, _)| addr)
+            .map(|(addr, _, _)| addr)
             .chain(self.sections().map(|section| section.shdr.addr))
             .chain(
                 self.sections()
@@ -489,12 +570,12 @@ impl<'a> ElfTranslator<'a> {
         // Create a block for the fallback indirect jump table.
         let indirect_target_var = LLVMBuildAlloca(
             builder,
-            LLVMInt32Type(),
+            LLVMInt32TypeInContext(self.engine.context),
             b"indirect_target\0".as_ptr() as *const _,
         );
         let indirect_addr_var = LLVMBuildAlloca(
             builder,
-            LLVMInt32Type(),
+            LLVMInt32TypeInContext(self.engine.context),
             b"indirect_addr\0".as_ptr() as *const _,
         );
         let indirect_fail_bb = LLVMAppendBasicBlockInContext(
@@ -520,7 +601,7 @@ impl<'a> ElfTranslator<'a> {
         for &addr in &inst_addrs {
             LLVMAddCase(
                 sw,
-                LLVMConstInt(LLVMInt32Type(), addr as u64, 0),
+                LLVMConstInt(LLVMInt32TypeInContext(self.engine.context), addr as u64, 0),
                 self.inst_bbs[&addr],
             );
         }
@@ -541,22 +622,73 @@ impl<'a> ElfTranslator<'a> {
         LLVMBuildRetVoid(builder);
         LLVMPositionBuilderAtEnd(builder, entry_bb);
 
-        // Emit the branch to the entry symbol.
+        // Emit the branch to the entry symbol. In deterministic mode, a hart
+        // continues at its PC in every turn instead, unless it has to wait.
         match self.inst_bbs.get(&self.elf.ehdr.entry) {
+            Some(_) if self.deterministic => {
+                let resume = LLVMBuildCall(
+                    builder,
+                    self.lookup_func("banshee_resume"),
+                    [state_ptr].as_mut_ptr(),
+                    1,
+                    NONAME,
+                );
+                let wait = LLVMBuildICmp(
+                    builder,
+                    LLVMIntNE,
+                    resume,
+                    LLVMConstInt(LLVMTypeOf(resume), 0, 0),
+                    NONAME,
+                );
+                let wait_bb = LLVMAppendBasicBlockInContext(
+                    self.engine.context,
+                    func,
+                    b"wait\0".as_ptr() as *const _,
+                );
+                let resume_bb = LLVMAppendBasicBlockInContext(
+                    self.engine.context,
+                    func,
+                    b"resume\0".as_ptr() as *const _,
+                );
+                LLVMBuildCondBr(builder, wait, wait_bb, resume_bb);
+                LLVMPositionBuilderAtEnd(builder, wait_bb);
+                LLVMBuildRetVoid(builder);
+                LLVMPositionBuilderAtEnd(builder, resume_bb);
+                let pc_ptr = LLVMBuildCall(
+                    builder,
+                    self.lookup_func("banshee_pc_ptr"),
+                    [state_ptr].as_mut_ptr(),
+                    1,
+                    NONAME,
+                );
+                let pc = LLVMBuildLoad(builder, pc_ptr, NONAME);
+                LLVMBuildStore(builder, pc, indirect_target_var);
+                LLVMBuildStore(builder, pc, indirect_addr_var);
+                LLVMBuildBr(builder, indirect_bb);
+            }
             Some(&bb) => {
                 LLVMBuildBr(builder, bb);
             }
             None => {
-                error!("No instruction at entry point 0x{:x}", self.elf.ehdr.entry);
+                error!(
+                    target: log_target::TRANSLATE,
+                    "No instruction at entry point 0x{:x}",
+                    self.elf.ehdr.entry
+                );
                 LLVMBuildBr(builder, entry_bb);
             }
         }
 
+        // Emit the code of identical basic blocks into shared functions.
+        let mut inst_index = 0;
+        if self.dedup_blocks && !self.deterministic {
+            self.shared_blocks = self.emit_shared_blocks(state_ptr_type, &mut inst_index)?;
+        }
+
         // Emit the instructions for each section.
         let mut last_section_tran = None;
-        let mut inst_index = 0;
         for section in self.sections() {
-            debug!("Translating section `{}`", section.shdr.name);
+            debug!(target: log_target::TRANSLATE, "Translating section `{}`", section.shdr.name);
             let tran = SectionTranslator {
                 elf: self,
                 section,
@@ -572,6 +704,7 @@ impl<'a> ElfTranslator<'a> {
                 indirect_addr_var,
                 indirect_bb,
                 fseq_iter: &fseq_iter,
+                shared: None,
             };
             tran.emit(&mut inst_index)?;
             last_section_tran = Some(tran);
@@ -580,7 +713,11 @@ impl<'a> ElfTranslator<'a> {
         // Emit escape abort code into all unpopulated instruction locations.
         for (&addr, &bb) in &self.inst_bbs {
             if LLVMGetBasicBlockTerminator(bb).is_null() {
-                trace!("Plugging instruction slot hole at 0x{:x}", addr);
+                trace!(
+                    target: log_target::TRANSLATE,
+                    "Plugging instruction slot hole at 0x{:x}",
+                    addr
+                );
                 LLVMPositionBuilderAtEnd(builder, bb);
                 last_section_tran
                     .as_ref()
@@ -590,255 +727,1033 @@ impl<'a> ElfTranslator<'a> {
         }
 
         // Clean up.
-        LLVMDIBuilderFinalize(di_builder);
+        LLVMDIBuilderFinalize(self.di_builder);
         LLVMDisposeBuilder(builder);
         Ok(())
     }
 
-    unsafe fn lookup_func(&self, name: &str) -> LLVMValueRef {
-        let n = CString::new(name).unwrap();
-        let ptr =
-            LLVMGetNamedFunction(self.engine.modules[self.cluster_id], n.as_ptr() as *const _);
-        assert!(
-            !ptr.is_null(),
-            "function `{}` not found in LLVM module",
-            name
-        );
-        ptr
+    /// Find the basic blocks which occur more than once in the binary.
+    ///
+    /// Returns the section and the instructions of each occurrence, grouped by
+    /// the encoding of the blocks. Blocks with an FREP are left alone, since
+    /// the sequencer buffers the subsequent instructions, and so are blocks
+    /// with a breakpoint, which only applies to one occurrence.
+    fn duplicate_blocks(&self) -> Vec<Vec<(&'a elf::Section, Vec<(u64, u32, riscv::Format)>)>> {
+        let mut groups: HashMap<Vec<u32>, Vec<_>> = HashMap::new();
+        for section in self.sections() {
+            for block in self.section_blocks(section) {
+                let has_frep = block.iter().any(|&(_, _, inst)| is_frep(inst));
+                let has_breakpoint = block
+                    .iter()
+                    .any(|&(addr, _, _)| self.engine.has_breakpoint(addr as u32));
+                if block.len() < MIN_SHARED_BLOCK_LEN || has_frep || has_breakpoint {
+                    continue;
+                }
+                let key = block.iter().map(|&(_, raw, _)| raw).collect();
+                groups.entry(key).or_default().push((section, block));
+            }
+        }
+        let mut groups: Vec<_> = groups
+            .into_iter()
+            .map(|(_, group)| group)
+            .filter(|group| group.len() > 1)
+            .collect();
+        groups.sort_by_key(|group| group[0].1[0].0);
+        groups
     }
-}
-
-/// A translator for a section.
-pub struct SectionTranslator<'a> {
-    elf: &'a ElfTranslator<'a>,
-    section: &'a elf::Section,
-    engine: &'a Engine,
-    /// The debug info scope for additional debug info emitted in the section.
-    di_scope: LLVMMetadataRef,
-    /// An LLVM value that holds the pointer to the CPU state structure.
-    state_ptr: LLVMValueRef,
-    /// An LLVM value that holds the pointer to the trace access buffer.
-    trace_access_buffer: LLVMValueRef,
-    /// An LLVM value that holds the pointer to the trace data buffer.
-    trace_data_buffer: LLVMValueRef,
-    /// The builder to emit instructions with.
-    builder: LLVMBuilderRef,
-    /// The first address in the section.
-    #[allow(dead_code)]
-    addr_start: u64,
-    /// The point beyond the last address in the section.
-    #[allow(dead_code)]
-    addr_end: u64,
-    /// The alloca variable holding the indirect jump target address.
-    indirect_target_var: LLVMValueRef,
-    /// The alloca variable holding the indirect jump instruction address.
-    indirect_addr_var: LLVMValueRef,
-    /// The basic block to branch to to make a fallback indirect jump.
-    indirect_bb: LLVMBasicBlockRef,
-    /// The JIT-level sequencer iterators.
-    fseq_iter: &'a SequencerIterators,
-}
 
-impl<'a> SectionTranslator<'a> {
-    /// Emit the code to handle the case when the PC lands outside the sections
-    /// of the binary.
-    unsafe fn emit_escape_abort(&self, addr: u64) {
-        trace!("Emit escape abort at 0x{:x}", addr);
-        self.emit_call(
-            "banshee_abort_escape",
-            [
-                self.state_ptr,
-                LLVMConstInt(LLVMInt32Type(), addr as u64, 0),
-            ],
-        );
-        LLVMBuildRetVoid(self.builder);
+    /// Split the instructions of a section into basic blocks.
+    fn section_blocks(&self, section: &elf::Section) -> Vec<Vec<(u64, u32, riscv::Format)>> {
+        let mut insts = section_instructions(section).peekable();
+        split_basic_blocks(section_instructions(section), &self.target_addrs)
+            .into_iter()
+            .map(|(_, end)| {
+                std::iter::from_fn(|| insts.next_if(|&(addr, _, _)| addr < end as u64)).collect()
+            })
+            .collect()
     }
 
-    /// Emit the code to handle an illegal instruction.
-    unsafe fn emit_illegal_abort(&self, addr: u64, inst: riscv::Format) {
-        trace!(
-            "Emit illegal instruction abort at 0x{:x} for {}",
-            addr,
-            inst
-        );
-        self.emit_call(
-            "banshee_abort_illegal_inst",
-            [
-                self.state_ptr,
-                LLVMConstInt(LLVMInt32Type(), addr as u64, 0),
-                LLVMConstInt(LLVMInt32Type(), inst.raw() as u64, 0),
-            ],
+    /// Emit the code of the basic blocks which occur more than once into
+    /// functions, and return the occurrence of each of their instructions,
+    /// along with whether it could be translated.
+    ///
+    /// The functions take the start address of the occurrence, such that the
+    /// code which depends on the PC is correct for each of them, and the
+    /// offset of the instruction to start at. They return one of the
+    /// `BLOCK_EXIT_*` codes.
+    unsafe fn emit_shared_blocks(
+        &self,
+        state_ptr_type: LLVMTypeRef,
+        inst_index: &mut u32,
+    ) -> Result<HashMap<u64, (SharedOccurrence, bool)>> {
+        let mut shared_blocks = HashMap::new();
+        let func_type = block_func_type(state_ptr_type);
+        let noinline = LLVMCreateEnumAttribute(
+            self.engine.context,
+            LLVMGetEnumAttributeKindForName("noinline".as_ptr() as *const _, 8),
+            0,
         );
-        LLVMBuildRetVoid(self.builder);
+
+        for group in self.duplicate_blocks() {
+            let (section, block) = &group[0];
+            let start = block[0].0;
+            let (last_addr, last_raw, _) = *block.last().unwrap();
+            let end = last_addr + rvc::inst_len(last_raw);
+            let name = format!("{}_block_0x{:x}", self.entry, start);
+            debug!(
+                target: log_target::TRANSLATE,
+                "Emitting `{}` for {} identical blocks",
+                name,
+                group.len()
+            );
+
+            // Emit the function, and keep it out of line such that the code
+            // is actually shared.
+            let (func, translated) =
+                self.emit_block_func(&name, func_type, section, block, inst_index)?;
+            LLVMSetLinkage(func, llvm_sys::LLVMLinkage::LLVMInternalLinkage);
+            LLVMAddAttributeAtIndex(func, llvm_sys::LLVMAttributeFunctionIndex, noinline);
+
+            // Call the function from every occurrence of the block.
+            for (_, block) in &group {
+                let &(last_addr, _, last_inst) = block.last().unwrap();
+                let occurrence = SharedOccurrence {
+                    func,
+                    start: block[0].0,
+                    end: block[0].0 + (end - start),
+                    target: direct_target(last_addr, last_inst),
+                };
+                for (&(addr, _, _), &translated) in block.iter().zip(&translated) {
+                    shared_blocks.insert(addr, (occurrence, translated));
+                }
+            }
+        }
+        Ok(shared_blocks)
     }
 
-    /// Emit the code to handle a branch to an unpredicted instruction.
-    #[allow(dead_code)]
-    unsafe fn emit_branch_abort(&self, inst_addr: u64, target: LLVMValueRef) {
-        trace!("Emit illegal branch abort at 0x{:x}", inst_addr);
-        self.emit_call(
-            "banshee_abort_illegal_branch",
-            [
-                self.state_ptr,
-                LLVMConstInt(LLVMInt32Type(), inst_addr as u64, 0),
-                target,
-            ],
+    /// Emit the code of each instruction into a function of its own, for the
+    /// interpreter mode.
+    ///
+    /// The functions are named after the address of the instruction, and have
+    /// the signature of the ones of deduplicated basic blocks. A basic block
+    /// with an FREP is emitted into one function, since the sequencer buffers
+    /// the instructions of the loop body.
+    unsafe fn emit_step_units(
+        &self,
+        state_ptr_type: LLVMTypeRef,
+        inst_index: &mut u32,
+    ) -> Result<Vec<StepUnit>> {
+        let func_type = block_func_type(state_ptr_type);
+        let mut units = vec![];
+        for section in self.sections() {
+            for block in self.section_blocks(section) {
+                let chunk_len = match block.iter().any(|&(_, _, inst)| is_frep(inst)) {
+                    true => block.len(),
+                    false => 1,
+                };
+                for insts in block.chunks(chunk_len) {
+                    let start = insts[0].0;
+                    let &(last_addr, last_raw, last_inst) = insts.last().unwrap();
+                    let name = format!("{}_step_0x{:x}", self.entry, start);
+                    self.emit_block_func(&name, func_type, section, insts, inst_index)?;
+                    units.push(StepUnit {
+                        start,
+                        end: last_addr + rvc::inst_len(last_raw),
+                        target: direct_target(last_addr, last_inst),
+                    });
+                }
+            }
+        }
+        debug!(
+            target: log_target::TRANSLATE,
+            "Emitted {} interpreter steps",
+            units.len()
         );
-        LLVMBuildRetVoid(self.builder);
+        Ok(units)
     }
 
-    /// Emit the code for the remaining iterations of a buffered FREP loop.
-    unsafe fn emit_frep(
+    /// Emit a function with the code of a sequence of instructions, which
+    /// starts at the given offset from the first one and returns one of the
+    /// `BLOCK_EXIT_*` codes. Also returns whether each instruction could be
+    /// translated.
+    unsafe fn emit_block_func(
         &self,
+        name: &str,
+        func_type: LLVMTypeRef,
+        section: &elf::Section,
+        block: &[(u64, u32, riscv::Format)],
         inst_index: &mut u32,
-        fseq: &SequencerContext,
-        curr_addr: u64,
-    ) -> Result<()> {
-        // Create dummy sequencer context for inner use
-        let mut fseq_inner = SequencerContext::new();
-
-        if fseq.is_outer {
-            // Create basic block for first increment-and-branch ahead of time
-            let mut bb_incr_branch = LLVMCreateBasicBlockInContext(self.engine.context, NONAME);
-
-            // Jump to first increment-and-branch block from unterminated last frep instruction block
-            if LLVMGetBasicBlockTerminator(LLVMGetInsertBlock(self.builder)).is_null() {
-                LLVMBuildBr(self.builder, bb_incr_branch);
-            } else {
-                error!("Cannot add branch to FREP to already terminated instruction");
-            }
+    ) -> Result<(LLVMValueRef, Vec<bool>)> {
+        let start = block[0].0;
+        let (last_addr, last_raw, _) = *block.last().unwrap();
+        let end = last_addr + rvc::inst_len(last_raw);
+        let func = LLVMAddFunction(
+            self.engine.modules[self.cluster_id],
+            format!("{}\0", name).as_ptr() as *const _,
+            func_type,
+        );
+        let builder = LLVMCreateBuilderInContext(self.engine.context);
+        let di_scope = self.emit_subprogram(func, name, builder);
+        let entry_bb = LLVMAppendBasicBlockInContext(
+            self.engine.context,
+            func,
+            b"entry\0".as_ptr() as *const _,
+        );
+        LLVMPositionBuilderAtEnd(builder, entry_bb);
+        let (trace_access_buffer, trace_data_buffer) = self.emit_trace_buffers(builder);
+        let fseq_iter = self.emit_sequencer_iterators(builder);
 
-            // Create staggered loop bodies if any
+        // Create a basic block for every instruction, and the ones which
+        // return to the caller.
+        let mut inst_bbs: HashMap<u64, LLVMBasicBlockRef> = block
+            .iter()
+            .map(|&(addr, _, _)| {
+                let name = format!("inst_0x{:x}\0", addr);
+                let bb = LLVMAppendBasicBlockInContext(
+                    self.engine.context,
+                    func,
+                    name.as_ptr() as *const _,
+                );
+                (addr, bb)
+            })
+            .collect();
+        let emit_exit = |name: &[u8], exit: u64| {
+            let bb =
+                LLVMAppendBasicBlockInContext(self.engine.context, func, name.as_ptr() as *const _);
+            LLVMPositionBuilderAtEnd(builder, bb);
+            LLVMBuildRet(
+                builder,
+                LLVMConstInt(LLVMInt32TypeInContext(self.engine.context), exit, 0),
+            );
+            bb
+        };
+        inst_bbs.insert(end, emit_exit(b"fallthrough\0", BLOCK_EXIT_FALLTHROUGH));
+        let branch_bb = emit_exit(b"branch\0", BLOCK_EXIT_BRANCH);
+        let indirect_bb = emit_exit(b"indirect\0", BLOCK_EXIT_INDIRECT);
+
+        // Start at the requested instruction.
+        LLVMPositionBuilderAtEnd(builder, entry_bb);
+        let sw = LLVMBuildSwitch(
+            builder,
+            LLVMGetParam(func, 2),
+            inst_bbs[&start],
+            block.len() as u32 - 1,
+        );
+        for &(addr, _, _) in &block[1..] {
+            LLVMAddCase(
+                sw,
+                LLVMConstInt(LLVMInt32TypeInContext(self.engine.context), addr - start, 0),
+                inst_bbs[&addr],
+            );
+        }
+
+        // Emit the instructions of the block.
+        let tran = SectionTranslator {
+            elf: self,
+            section,
+            engine: self.engine,
+            di_scope,
+            state_ptr: LLVMGetParam(func, 0),
+            trace_access_buffer,
+            trace_data_buffer,
+            builder,
+            addr_start: start,
+            addr_end: end,
+            indirect_target_var: LLVMGetParam(func, 3),
+            indirect_addr_var: LLVMGetParam(func, 4),
+            indirect_bb,
+            fseq_iter: &fseq_iter,
+            shared: Some(SharedBlock {
+                base: LLVMGetParam(func, 1),
+                start,
+                inst_bbs,
+                branch_bb,
+            }),
+        };
+        let translated = tran.emit(inst_index)?;
+        LLVMDisposeBuilder(builder);
+        Ok((func, translated))
+    }
+
+    /// Allocate the sequencer iterators and init them as pointing to a zero
+    /// constant.
+    unsafe fn emit_sequencer_iterators(&self, builder: LLVMBuilderRef) -> SequencerIterators {
+        let const_zero_32 = LLVMConstInt(LLVMInt32TypeInContext(self.engine.context), 0, 0);
+        let rpt_ptr_ref = LLVMBuildAlloca(
+            builder,
+            LLVMInt32TypeInContext(self.engine.context),
+            b"frep_rpt_ptr\0".as_ptr() as *const _,
+        );
+        LLVMBuildStore(builder, const_zero_32, rpt_ptr_ref);
+        let max_rpt_ref = LLVMBuildAlloca(
+            builder,
+            LLVMInt32TypeInContext(self.engine.context),
+            b"frep_max_rpt\0".as_ptr() as *const _,
+        );
+        LLVMBuildStore(builder, const_zero_32, max_rpt_ref);
+        SequencerIterators {
+            rpt_ptr_ref,
+            max_rpt_ref,
+        }
+    }
+
+    /// Emit the subprogram debug information for a function, and set the
+    /// debug location of the builder to it.
+    unsafe fn emit_subprogram(
+        &self,
+        func: LLVMValueRef,
+        name: &str,
+        builder: LLVMBuilderRef,
+    ) -> LLVMMetadataRef {
+        let di_builder = self.di_builder;
+        let di_name = name;
+        let di_linkage_name = name;
+        let di_scope = LLVMDIBuilderCreateFunction(
+            di_builder,                           // Builder
+            self.di_file,                         // Scope
+            di_name.as_ptr() as *const _,         // Name
+            di_name.len(),                        // NameLen
+            di_linkage_name.as_ptr() as *const _, // LinkageName
+            di_linkage_name.len(),                // LinkageNameLen
+            self.di_file,                         // File
+            0,                                    // LineNo
+            LLVMDIBuilderCreateSubroutineType(
+                di_builder,
+                self.di_file,
+                [].as_mut_ptr(),
+                0,
+                LLVMDIFlagZero,
+            ), // Ty
+            0,                                    // IsLocalToUnit
+            1,                                    // IsDefinition
+            0,                                    // ScopeLine
+            LLVMDIFlagPrototyped,                 // Flags
+            0,                                    // IsOptimized
+        );
+        LLVMSetSubprogram(func, di_scope);
+        let di_loc = LLVMDIBuilderCreateDebugLocation(
+            self.engine.context,  // Ctx
+            0,                    // Line
+            0,                    // Column
+            di_scope,             // Scope
+            std::ptr::null_mut(), // InlinedAt
+        );
+        LLVMSetCurrentDebugLocation2(builder, di_loc);
+        di_scope
+    }
+
+    /// Allocate space for tracing data, if needed.
+    unsafe fn emit_trace_buffers(&self, builder: LLVMBuilderRef) -> (LLVMValueRef, LLVMValueRef) {
+        if self.trace {
+            (
+                LLVMBuildAlloca(
+                    builder,
+                    LLVMArrayType(
+                        LLVMInt16TypeInContext(self.engine.context),
+                        TRACE_BUFFER_LEN,
+                    ),
+                    NONAME,
+                ),
+                LLVMBuildAlloca(
+                    builder,
+                    LLVMArrayType(
+                        LLVMInt64TypeInContext(self.engine.context),
+                        TRACE_BUFFER_LEN,
+                    ),
+                    NONAME,
+                ),
+            )
+        } else {
+            (std::ptr::null_mut(), std::ptr::null_mut())
+        }
+    }
+
+    unsafe fn lookup_func(&self, name: &str) -> LLVMValueRef {
+        let n = CString::new(name).unwrap();
+        let ptr =
+            LLVMGetNamedFunction(self.engine.modules[self.cluster_id], n.as_ptr() as *const _);
+        assert!(
+            !ptr.is_null(),
+            "function `{}` not found in LLVM module",
+            name
+        );
+        ptr
+    }
+}
+
+/// Get an iterator over the executable sections of a binary.
+pub fn executable_sections(elf: &elf::File) -> impl Iterator<Item = &elf::Section> {
+    elf.sections
+        .iter()
+        .filter(|section| (section.shdr.flags.0 & elf::types::SHF_EXECINSTR.0) != 0)
+}
+
+/// Get an iterator over the instructions in all executable sections of a
+/// binary, in the order of the sections. See `section_instructions`.
+pub fn elf_instructions(elf: &elf::File) -> impl Iterator<Item = (u64, u32, riscv::Format)> + '_ {
+    executable_sections(elf).flat_map(section_instructions)
+}
+
+/// Get an iterator over the instructions in a section.
+///
+/// Yields the address, the raw encoding, and the decoded instruction.
+/// Compressed instructions are expanded to their 32 bit equivalent, but
+/// retain their 16 bit raw encoding.
+pub fn section_instructions(
+    section: &elf::Section,
+) -> impl Iterator<Item = (u64, u32, riscv::Format)> + '_ {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        let data = section.data.get(offset..)?;
+        let addr = section.shdr.addr + offset as u64;
+        let (raw, inst) = match *data {
+            [b0, b1, ..] if rvc::is_compressed(b0 as u32) => {
+                let raw = u16::from_le_bytes([b0, b1]);
+                let inst = rvc::expand(raw)
+                    .map(riscv::parse_u32)
+                    .unwrap_or(riscv::Format::Illegal(raw as u32));
+                (raw as u32, inst)
+            }
+            [b0, b1, b2, b3, ..] => {
+                let raw = u32::from_le_bytes([b0, b1, b2, b3]);
+                (raw, riscv::parse_u32(raw))
+            }
+            [] => return None,
+            _ => (0, riscv::Format::Illegal(0)),
+        };
+        offset += rvc::inst_len(raw) as usize;
+        Some((addr, raw, inst))
+    })
+}
+
+/// The type of the functions of deduplicated basic blocks and interpreter
+/// steps: `(state, start address, offset, indirect target, indirect address)`.
+unsafe fn block_func_type(state_ptr_type: LLVMTypeRef) -> LLVMTypeRef {
+    let context = LLVMGetTypeContext(state_ptr_type);
+    let i32_ptr_type = LLVMPointerType(LLVMInt32TypeInContext(context), 0);
+    let mut param_types = [
+        state_ptr_type,
+        LLVMInt32TypeInContext(context),
+        LLVMInt32TypeInContext(context),
+        i32_ptr_type,
+        i32_ptr_type,
+    ];
+    LLVMFunctionType(
+        LLVMInt32TypeInContext(context),
+        param_types.as_mut_ptr(),
+        param_types.len() as u32,
+        0,
+    )
+}
+
+/// Whether an instruction may have an effect beyond the registers of the
+/// hart, such as a store, an atomic, a CSR write, or an instruction of an
+/// extension. Reading a CSR is free of side effects.
+///
+/// Loads count as well, since other harts, the DMA, or peripherals may write
+/// the memory they read: a hart which spins on a flag waits for them rather
+/// than livelocks.
+fn has_side_effects(inst: riscv::Format) -> bool {
+    let raw = inst.raw();
+    let rs1 = (raw >> 15) & 0x1f;
+    match raw & 0x7f {
+        // Fences, integer and floating-point operations, branches and jumps.
+        0x0f | 0x13 | 0x17 | 0x33 | 0x37 | 0x43 | 0x47 | 0x4b | 0x4f | 0x53 | 0x63 | 0x67
+        | 0x6f => false,
+        // CSR reads.
+        0x73 => !matches!((raw >> 12) & 0x7, 2 | 3 | 6 | 7) || rs1 != 0,
+        _ => true,
+    }
+}
+
+/// Whether an instruction is an FREP, whose loop body the sequencer buffers.
+fn is_frep(inst: riscv::Format) -> bool {
+    matches!(inst, riscv::Format::Imm12Rs1StaggerMaskStaggerMax(_))
+}
+
+/// Check whether an instruction may let a hart wait, by sleeping in `wfi` or
+/// by loading from the cluster barrier.
+fn may_wait(inst: riscv::Format) -> bool {
+    matches!(
+        inst,
+        riscv::Format::Unit(riscv::FormatUnit {
+            op: riscv::OpcodeUnit::Wfi,
+            ..
+        }) | riscv::Format::Imm12RdRs1(riscv::FormatImm12RdRs1 {
+            op: riscv::OpcodeImm12RdRs1::Lb
+                | riscv::OpcodeImm12RdRs1::Lh
+                | riscv::OpcodeImm12RdRs1::Lw
+                | riscv::OpcodeImm12RdRs1::Lbu
+                | riscv::OpcodeImm12RdRs1::Lhu,
+            ..
+        })
+    )
+}
+
+/// Determine the target of a branch or immediate jump.
+fn direct_target(addr: u64, inst: riscv::Format) -> Option<u64> {
+    match inst {
+        riscv::Format::Jimm20Rd(fmt) => Some((addr as i64).wrapping_add(fmt.jimm() as i64) as u64),
+        riscv::Format::Bimm12hiBimm12loRs1Rs2(fmt) => {
+            Some((addr as i64).wrapping_add(fmt.bimm() as i64) as u64)
+        }
+        _ => None,
+    }
+}
+
+/// Estimate the branch target addresses of the instructions in a section.
+fn section_target_addrs(section: &elf::Section) -> BTreeSet<u64> {
+    let mut target_addrs = BTreeSet::new();
+    for (addr, raw, inst) in section_instructions(section) {
+        let next_addr = addr + rvc::inst_len(raw);
+        match inst {
+            riscv::Format::Imm12RdRs1(
+                fmt @ riscv::FormatImm12RdRs1 {
+                    op: riscv::OpcodeImm12RdRs1::Jalr,
+                    ..
+                },
+            ) => {
+                debug!(
+                    target: log_target::TRANSLATE,
+                    "Found register jump 0x{:x}: {}",
+                    addr,
+                    inst
+                );
+
+                // If we keep the PC around, we expect to jump back to the
+                // next instruction at some point.
+                if fmt.rd != 0 {
+                    target_addrs.insert(next_addr);
+                }
+            }
+            riscv::Format::Jimm20Rd(
+                fmt @ riscv::FormatJimm20Rd {
+                    op: riscv::OpcodeJimm20Rd::Jal,
+                    ..
+                },
+            ) => {
+                // Ensure that we can branch to the target address.
+                let target = (addr as i64).wrapping_add(fmt.jimm() as i64) as u64;
+                debug!(
+                    target: log_target::TRANSLATE,
+                    "Found immediate jump 0x{:x}: {} to 0x{:x}",
+                    addr, inst, target
+                );
+                target_addrs.insert(target);
+
+                // If we keep the PC around, we expect to jump back to the
+                // next instruction at some point.
+                if fmt.rd != 0 {
+                    target_addrs.insert(next_addr);
+                }
+            }
+            riscv::Format::Bimm12hiBimm12loRs1Rs2(fmt) => {
+                let target = (addr as i64).wrapping_add(fmt.bimm() as i64) as u64;
+                debug!(
+                    target: log_target::TRANSLATE,
+                    "Found branch 0x{:x}: {} to 0x{:x}",
+                    addr,
+                    inst,
+                    target
+                );
+                target_addrs.insert(target);
+                target_addrs.insert(next_addr);
+            }
+            _ => (),
+        }
+    }
+    target_addrs
+}
+
+/// Split a sequence of instructions into basic blocks.
+///
+/// A block ends after a branch, jump, or return from a trap handler, before a
+/// branch target, and at gaps between the instructions. Returns the start and
+/// (exclusive) end address of each block.
+pub fn split_basic_blocks(
+    insts: impl Iterator<Item = (u64, u32, riscv::Format)>,
+    target_addrs: &BTreeSet<u64>,
+) -> Vec<(u32, u32)> {
+    let mut blocks = vec![];
+    let mut current: Option<(u64, u64)> = None;
+    for (addr, raw, inst) in insts {
+        if let Some((start, end)) = current {
+            if end != addr || target_addrs.contains(&addr) {
+                blocks.push((start as u32, end as u32));
+                current = None;
+            }
+        }
+        let next_addr = addr + rvc::inst_len(raw);
+        let start = current.map_or(addr, |(start, _)| start);
+        let ends_block = match inst {
+            riscv::Format::Bimm12hiBimm12loRs1Rs2(_)
+            | riscv::Format::Jimm20Rd(riscv::FormatJimm20Rd {
+                op: riscv::OpcodeJimm20Rd::Jal,
+                ..
+            })
+            | riscv::Format::Imm12RdRs1(riscv::FormatImm12RdRs1 {
+                op: riscv::OpcodeImm12RdRs1::Jalr,
+                ..
+            })
+            | riscv::Format::Unit(riscv::FormatUnit {
+                op:
+                    riscv::OpcodeUnit::Uret
+                    | riscv::OpcodeUnit::Sret
+                    | riscv::OpcodeUnit::Mret
+                    | riscv::OpcodeUnit::Dret,
+                ..
+            }) => true,
+            _ => false,
+        };
+        if ends_block {
+            blocks.push((start as u32, next_addr as u32));
+            current = None;
+        } else {
+            current = Some((start, next_addr));
+        }
+    }
+    if let Some((start, end)) = current {
+        blocks.push((start as u32, end as u32));
+    }
+    blocks
+}
+
+/// A translator for a section.
+pub struct SectionTranslator<'a> {
+    elf: &'a ElfTranslator<'a>,
+    section: &'a elf::Section,
+    engine: &'a Engine,
+    /// The debug info scope for additional debug info emitted in the section.
+    di_scope: LLVMMetadataRef,
+    /// An LLVM value that holds the pointer to the CPU state structure.
+    state_ptr: LLVMValueRef,
+    /// An LLVM value that holds the pointer to the trace access buffer.
+    trace_access_buffer: LLVMValueRef,
+    /// An LLVM value that holds the pointer to the trace data buffer.
+    trace_data_buffer: LLVMValueRef,
+    /// The builder to emit instructions with.
+    builder: LLVMBuilderRef,
+    /// The first address in the section.
+    addr_start: u64,
+    /// The point beyond the last address in the section.
+    addr_end: u64,
+    /// The alloca variable holding the indirect jump target address.
+    indirect_target_var: LLVMValueRef,
+    /// The alloca variable holding the indirect jump instruction address.
+    indirect_addr_var: LLVMValueRef,
+    /// The basic block to branch to to make a fallback indirect jump.
+    indirect_bb: LLVMBasicBlockRef,
+    /// The JIT-level sequencer iterators.
+    fseq_iter: &'a SequencerIterators,
+    /// The basic block being emitted into its own function, if any. The
+    /// section translator then only covers the instructions of that block.
+    shared: Option<SharedBlock>,
+}
+
+impl<'a> SectionTranslator<'a> {
+    /// Emit the code to handle the case when the PC lands outside the sections
+    /// of the binary.
+    unsafe fn emit_escape_abort(&self, addr: u64) {
+        trace!(target: log_target::TRANSLATE, "Emit escape abort at 0x{:x}", addr);
+        self.emit_call(
+            "banshee_abort_escape",
+            [self.state_ptr, self.addr_value(addr)],
+        );
+        self.emit_return();
+    }
+
+    /// Emit the code for an instruction which could not be translated.
+    ///
+    /// Calls the handler of a custom instruction if there is one, and handles
+    /// the instruction as illegal otherwise.
+    unsafe fn emit_unsupported(
+        &self,
+        addr: u64,
+        next_addr: u64,
+        inst: riscv::Format,
+        error: anyhow::Error,
+    ) {
+        let index = match self.engine.custom_inst(inst.raw()) {
+            Some(index) => index,
+            None => {
+                error!(target: log_target::TRANSLATE, "{}", error);
+                self.engine
+                    .record_unsupported(addr as u32, inst.raw(), format!("{:#}", error));
+                self.emit_illegal_abort(addr, next_addr, inst);
+                return;
+            }
+        };
+        trace!(target: log_target::TRANSLATE, "Emit custom instruction at 0x{:x}", addr);
+        let terminate = self.emit_call(
+            "banshee_custom_inst",
+            [
+                self.state_ptr,
+                LLVMConstInt(LLVMInt32TypeInContext(self.engine.context), index as u64, 0),
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.engine.context),
+                    inst.raw() as u64,
+                    0,
+                ),
+            ],
+        );
+        let terminate = LLVMBuildIntCast(
+            self.builder,
+            terminate,
+            LLVMInt1TypeInContext(self.engine.context),
+            NONAME,
+        );
+        let bb_terminate = LLVMCreateBasicBlockInContext(self.engine.context, NONAME);
+        let bb_continue = LLVMCreateBasicBlockInContext(self.engine.context, NONAME);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_terminate);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_continue);
+        LLVMBuildCondBr(self.builder, terminate, bb_terminate, bb_continue);
+        LLVMPositionBuilderAtEnd(self.builder, bb_terminate);
+        self.emit_return();
+        LLVMPositionBuilderAtEnd(self.builder, bb_continue);
+    }
+
+    /// Emit the code to enter the trap handler for an exception.
+    ///
+    /// Branches to `bb_no_handler` instead, if given and no trap handler is
+    /// installed.
+    unsafe fn emit_trap(
+        &self,
+        addr: u64,
+        cause: u32,
+        tval: LLVMValueRef,
+        bb_no_handler: Option<LLVMBasicBlockRef>,
+    ) {
+        trace!(target: log_target::TRANSLATE, "Emit trap {} at 0x{:x}", cause, addr);
+        let addr = self.addr_value(addr);
+        let target = self.emit_call(
+            "banshee_trap",
+            [
+                self.state_ptr,
+                addr,
+                LLVMConstInt(LLVMInt32TypeInContext(self.engine.context), cause as u64, 0),
+                tval,
+            ],
+        );
+        if let Some(bb_no_handler) = bb_no_handler {
+            let has_handler = LLVMBuildICmp(
+                self.builder,
+                LLVMIntNE,
+                target,
+                LLVMConstInt(LLVMInt32TypeInContext(self.engine.context), 0, 0),
+                NONAME,
+            );
+            let bb_handler = LLVMCreateBasicBlockInContext(self.engine.context, NONAME);
+            LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_handler);
+            LLVMBuildCondBr(self.builder, has_handler, bb_handler, bb_no_handler);
+            LLVMPositionBuilderAtEnd(self.builder, bb_handler);
+        }
+
+        // Use the prepared indirect jump switch statement.
+        LLVMBuildStore(self.builder, target, self.indirect_target_var);
+        LLVMBuildStore(self.builder, addr, self.indirect_addr_var);
+        LLVMBuildBr(self.builder, self.indirect_bb);
+    }
+
+    /// Emit the code to handle an illegal instruction.
+    ///
+    /// Enters the trap handler instead of aborting if traps are enabled and a
+    /// handler is installed.
+    unsafe fn emit_illegal_abort(&self, addr: u64, next_addr: u64, inst: riscv::Format) {
+        if self.engine.traps || self.engine.trap_illegal_instructions {
+            let bb_abort = LLVMCreateBasicBlockInContext(self.engine.context, NONAME);
+            LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_abort);
+            let tval = LLVMConstInt(
+                LLVMInt32TypeInContext(self.engine.context),
+                inst.raw() as u64,
+                0,
+            );
+            self.emit_trap(addr, 2, tval, Some(bb_abort));
+            LLVMPositionBuilderAtEnd(self.builder, bb_abort);
+        }
+        trace!(
+            target: log_target::TRANSLATE,
+            "Emit illegal instruction abort at 0x{:x} for {}",
+            addr,
+            inst
+        );
+        self.emit_abort_policy(addr, next_addr, inst.raw());
+    }
+
+    /// Emit the call into the runtime for an illegal instruction, and then
+    /// stop the hart, continue at `next_addr`, or enter the handler according
+    /// to the abort policy.
+    unsafe fn emit_abort_policy(&self, addr: u64, next_addr: u64, raw: u32) {
+        self.emit_call(
+            "banshee_abort_illegal_inst",
+            [
+                self.state_ptr,
+                self.addr_value(addr),
+                LLVMConstInt(LLVMInt32TypeInContext(self.engine.context), raw as u64, 0),
+            ],
+        );
+        let target = match self.engine.abort_policy {
+            AbortPolicy::Stop => return self.emit_return(),
+            AbortPolicy::Continue => self.addr_value(next_addr),
+            AbortPolicy::Trap(handler) => LLVMConstInt(
+                LLVMInt32TypeInContext(self.engine.context),
+                handler as u64,
+                0,
+            ),
+        };
+        // Use the prepared indirect jump switch statement.
+        LLVMBuildStore(self.builder, target, self.indirect_target_var);
+        LLVMBuildStore(self.builder, self.addr_value(addr), self.indirect_addr_var);
+        LLVMBuildBr(self.builder, self.indirect_bb);
+    }
+
+    /// Emit the code to handle a branch to an unpredicted instruction.
+    #[allow(dead_code)]
+    unsafe fn emit_branch_abort(&self, inst_addr: u64, target: LLVMValueRef) {
+        trace!(target: log_target::TRANSLATE, "Emit illegal branch abort at 0x{:x}", inst_addr);
+        self.emit_call(
+            "banshee_abort_illegal_branch",
+            [
+                self.state_ptr,
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.engine.context),
+                    inst_addr as u64,
+                    0,
+                ),
+                target,
+            ],
+        );
+        self.emit_return();
+    }
+
+    /// Emit the code for the remaining iterations of a buffered FREP loop.
+    ///
+    /// For an outer FREP this is called once the entire loop body has been
+    /// buffered, and repeats the body. For an inner FREP this is called after
+    /// every buffered instruction, and repeats that instruction alone.
+    unsafe fn emit_frep(
+        &self,
+        inst_index: &mut u32,
+        fseq: &SequencerContext,
+        next_addr: u64,
+    ) -> Result<()> {
+        if fseq.is_outer {
+            // Create basic block for first increment-and-branch ahead of time
+            let mut bb_incr_branch = LLVMCreateBasicBlockInContext(self.engine.context, NONAME);
+
+            // Jump to first increment-and-branch block from unterminated last frep instruction block
+            if LLVMGetBasicBlockTerminator(LLVMGetInsertBlock(self.builder)).is_null() {
+                LLVMBuildBr(self.builder, bb_incr_branch);
+            } else {
+                error!(
+                    target: log_target::TRANSLATE,
+                    "Cannot add branch to FREP to already terminated instruction"
+                );
+            }
+
+            // Create staggered loop bodies if any
             for stg_offs in 1..=(fseq.stagger_max as u32) {
                 // Place and start inserting into block for increment-and-branch
                 LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_incr_branch);
                 LLVMPositionBuilderAtEnd(self.builder, bb_incr_branch);
 
-                // Load repetition counter from stack.
-                let rpt_cnt = LLVMBuildLoad(self.builder, self.fseq_iter.rpt_ptr_ref, NONAME);
-                // Load max repetition from stack.
-                let max_rpt = LLVMBuildLoad(self.builder, self.fseq_iter.max_rpt_ref, NONAME);
-                // Compare to repetition maximum: repeat if less than maximum iteration.
-                let rpt_cmp = LLVMBuildICmp(self.builder, LLVMIntULT, rpt_cnt, max_rpt, NONAME);
-                // Increment rep counter, store
-                let const_one = LLVMConstInt(LLVMTypeOf(rpt_cnt), 1, 0);
-                let rpt_cnt_inc = LLVMBuildAdd(self.builder, rpt_cnt, const_one, NONAME);
-                LLVMBuildStore(self.builder, rpt_cnt_inc, self.fseq_iter.rpt_ptr_ref);
-
                 // Create basic block for first loop instruction ahead of time
-                let mut bb_loop_inst = LLVMCreateBasicBlockInContext(self.engine.context, NONAME);
+                let bb_loop_inst = LLVMCreateBasicBlockInContext(self.engine.context, NONAME);
                 // Insert branch terminating the FREP iterations or going to next loop body (following terminator will be omitted).
-                LLVMBuildCondBr(
-                    self.builder,
-                    rpt_cmp,
-                    bb_loop_inst,
-                    self.elf.inst_bbs[&(curr_addr + 4)],
-                );
+                self.emit_frep_incr_branch(bb_loop_inst, self.inst_bb(next_addr));
 
                 // Emit loop body for current stagger offset
-                for &(addr, inst_nonstag) in fseq.inst_buffer[0..=(fseq.max_inst as usize)].iter() {
-                    // Read register fields
-                    let inst_raw = inst_nonstag.raw();
-                    let mut rd = (inst_raw >> 7) & 0x1f;
-                    let mut rs1 = (inst_raw >> 15) & 0x1f;
-                    let mut rs2 = (inst_raw >> 20) & 0x1f;
-                    let mut rs3 = (inst_raw >> 27) & 0x1f;
-                    // Stagger register fields
-                    if fseq.stagger_mask & 0b0001 != 0 {
-                        rd = (rd + stg_offs) & 0x1f;
-                    }
-                    if fseq.stagger_mask & 0b0010 != 0 {
-                        rs1 = (rs1 + stg_offs) & 0x1f;
-                    }
-                    if fseq.stagger_mask & 0b0100 != 0 {
-                        rs2 = (rs2 + stg_offs) & 0x1f;
-                    }
-                    if fseq.stagger_mask & 0b1000 != 0 {
-                        rs3 = (rs3 + stg_offs) & 0x1f;
-                    }
-                    // Assemble, return new instruction
-                    const MREST: u32 =
-                        0xffff_ffff ^ ((0x1f << 7) | (0x1f << 15) | (0x1f << 20) | (0x1f << 27));
-                    let inst = riscv::parse_u32(
-                        (inst_raw & MREST) | (rd << 7) | (rs1 << 15) | (rs2 << 20) | (rs3 << 27),
-                    );
-
-                    // Create translator for staggered instruction
-                    let tran = InstructionTranslator {
-                        section: self,
-                        builder: self.builder,
-                        addr,
-                        inst,
-                        was_terminator: Default::default(),
-                        trace_accesses: Default::default(),
-                        trace_emitted: Default::default(),
-                        trace_disabled: Default::default(),
-                        was_freppable: Default::default(),
-                    };
-                    // Place and start inserting into premade loop instruction block
-                    LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_loop_inst);
-                    LLVMPositionBuilderAtEnd(self.builder, bb_loop_inst);
-                    // Emit instruction into loop instruction block
-                    match tran.emit(inst_index, &mut fseq_inner) {
-                        Ok(()) => (),
-                        Err(e) => {
-                            error!("{}", e);
-                            self.emit_illegal_abort(addr, inst);
-                        }
-                    }
-                    // Create next loop instruction block ahead of time
-                    bb_loop_inst = LLVMCreateBasicBlockInContext(self.engine.context, NONAME);
-                    // Terminate with branch to next loop instruction block
-                    if LLVMGetBasicBlockTerminator(LLVMGetInsertBlock(self.builder)).is_null() {
-                        LLVMBuildBr(self.builder, bb_loop_inst);
-                    } else {
-                        error!("Cannot use terminating instruction inside an FREP");
-                    }
-                }
-
-                // Use left-over loop instruction block as next increment-and-branch block
-                bb_incr_branch = bb_loop_inst;
+                bb_incr_branch = self.emit_frep_body(
+                    inst_index,
+                    fseq,
+                    &fseq.inst_buffer[0..=(fseq.max_inst as usize)],
+                    stg_offs,
+                    bb_loop_inst,
+                );
             }
 
             // Place and start inserting into final branch-and-increment block pointing back to original loop body
             LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_incr_branch);
             LLVMPositionBuilderAtEnd(self.builder, bb_incr_branch);
 
-            // Load repetition counter from stack.
-            let rpt_cnt = LLVMBuildLoad(self.builder, self.fseq_iter.rpt_ptr_ref, NONAME);
-            // Load max repetition from stack.
-            let max_rpt = LLVMBuildLoad(self.builder, self.fseq_iter.max_rpt_ref, NONAME);
-            // Compare to repetition maximum: repeat if less than maximum iteration.
-            let rpt_cmp = LLVMBuildICmp(self.builder, LLVMIntULT, rpt_cnt, max_rpt, NONAME);
-            // Increment rep counter, store
-            let const_one = LLVMConstInt(LLVMTypeOf(rpt_cnt), 1, 0);
-            let rpt_cnt_inc = LLVMBuildAdd(self.builder, rpt_cnt, const_one, NONAME);
-            LLVMBuildStore(self.builder, rpt_cnt_inc, self.fseq_iter.rpt_ptr_ref);
-
             // Insert branch terminating the FREP iterations or going to original loop body (following terminator will be omitted).
-            LLVMBuildCondBr(
-                self.builder,
-                rpt_cmp,
-                self.elf.inst_bbs[&(fseq.inst_buffer[0].0)],
-                self.elf.inst_bbs[&(curr_addr + 4)],
+            self.emit_frep_incr_branch(
+                self.inst_bb(fseq.inst_buffer[0].0),
+                self.inst_bb(next_addr),
             );
 
             Ok(())
         } else {
-            Err(anyhow!("Inner FREP not yet supported"))
+            // The first iteration of the instruction has already been emitted.
+            // Every instruction repeats on its own, so restart the counter.
+            let inst = &fseq.inst_buffer[fseq.buffer_pos as usize - 1..][..1];
+            LLVMBuildStore(
+                self.builder,
+                LLVMConstInt(LLVMInt32TypeInContext(self.engine.context), 0, 0),
+                self.fseq_iter.rpt_ptr_ref,
+            );
+
+            // Emit the staggered repetitions, continuing with the unstaggered
+            // one once the stagger offset wraps around.
+            let stg_offsets: Vec<u32> = (1..=(fseq.stagger_max as u32)).chain(Some(0)).collect();
+            let bb_incr_branches: Vec<_> = stg_offsets
+                .iter()
+                .map(|_| LLVMCreateBasicBlockInContext(self.engine.context, NONAME))
+                .collect();
+            LLVMBuildBr(self.builder, bb_incr_branches[0]);
+            for (i, &stg_offs) in stg_offsets.iter().enumerate() {
+                LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_incr_branches[i]);
+                LLVMPositionBuilderAtEnd(self.builder, bb_incr_branches[i]);
+                let bb_loop_inst = LLVMCreateBasicBlockInContext(self.engine.context, NONAME);
+                self.emit_frep_incr_branch(bb_loop_inst, self.inst_bb(next_addr));
+                let bb_next = self.emit_frep_body(inst_index, fseq, inst, stg_offs, bb_loop_inst);
+
+                // Continue with the next stagger offset.
+                LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_next);
+                LLVMPositionBuilderAtEnd(self.builder, bb_next);
+                LLVMBuildBr(
+                    self.builder,
+                    bb_incr_branches[(i + 1) % bb_incr_branches.len()],
+                );
+            }
+            Ok(())
         }
     }
 
-    /// Emit the code for the entire section.
-    unsafe fn emit(&self, inst_index: &mut u32) -> Result<()> {
+    /// Emit the check whether an FREP repetition is left and the increment of
+    /// the repetition counter.
+    unsafe fn emit_frep_incr_branch(
+        &self,
+        bb_repeat: LLVMBasicBlockRef,
+        bb_done: LLVMBasicBlockRef,
+    ) {
+        // Load repetition counter from stack.
+        let rpt_cnt = LLVMBuildLoad(self.builder, self.fseq_iter.rpt_ptr_ref, NONAME);
+        // Load max repetition from stack.
+        let max_rpt = LLVMBuildLoad(self.builder, self.fseq_iter.max_rpt_ref, NONAME);
+        // Compare to repetition maximum: repeat if less than maximum iteration.
+        let rpt_cmp = LLVMBuildICmp(self.builder, LLVMIntULT, rpt_cnt, max_rpt, NONAME);
+        // Increment rep counter, store
+        let const_one = LLVMConstInt(LLVMTypeOf(rpt_cnt), 1, 0);
+        let rpt_cnt_inc = LLVMBuildAdd(self.builder, rpt_cnt, const_one, NONAME);
+        LLVMBuildStore(self.builder, rpt_cnt_inc, self.fseq_iter.rpt_ptr_ref);
+        LLVMBuildCondBr(self.builder, rpt_cmp, bb_repeat, bb_done);
+    }
+
+    /// Emit one repetition of FREP loop body instructions, with the register
+    /// fields staggered by an offset. Returns the unplaced basic block that
+    /// follows the body.
+    unsafe fn emit_frep_body(
+        &self,
+        inst_index: &mut u32,
+        fseq: &SequencerContext,
+        body: &[(u64, u32, riscv::Format)],
+        stg_offs: u32,
+        mut bb_loop_inst: LLVMBasicBlockRef,
+    ) -> LLVMBasicBlockRef {
+        // Create dummy sequencer context for inner use
+        let mut fseq_inner = SequencerContext::new();
+
+        for &(addr, raw, inst_nonstag) in body {
+            // Read register fields, of the expanded form of compressed
+            // instructions
+            let inst_raw = inst_nonstag.raw();
+            let mut rd = (inst_raw >> 7) & 0x1f;
+            let mut rs1 = (inst_raw >> 15) & 0x1f;
+            let mut rs2 = (inst_raw >> 20) & 0x1f;
+            let mut rs3 = (inst_raw >> 27) & 0x1f;
+            // Stagger register fields
+            if fseq.stagger_mask & 0b0001 != 0 {
+                rd = (rd + stg_offs) & 0x1f;
+            }
+            if fseq.stagger_mask & 0b0010 != 0 {
+                rs1 = (rs1 + stg_offs) & 0x1f;
+            }
+            if fseq.stagger_mask & 0b0100 != 0 {
+                rs2 = (rs2 + stg_offs) & 0x1f;
+            }
+            if fseq.stagger_mask & 0b1000 != 0 {
+                rs3 = (rs3 + stg_offs) & 0x1f;
+            }
+            // Assemble, return new instruction
+            const MREST: u32 =
+                0xffff_ffff ^ ((0x1f << 7) | (0x1f << 15) | (0x1f << 20) | (0x1f << 27));
+            let inst = riscv::parse_u32(
+                (inst_raw & MREST) | (rd << 7) | (rs1 << 15) | (rs2 << 20) | (rs3 << 27),
+            );
+            // Compressed instructions keep their encoding, which determines
+            // their length.
+            let raw = match rvc::inst_len(raw) {
+                2 => raw,
+                _ => inst.raw(),
+            };
+
+            // Create translator for staggered instruction
+            let tran = InstructionTranslator {
+                section: self,
+                builder: self.builder,
+                addr,
+                raw,
+                inst,
+                was_terminator: Default::default(),
+                trace_accesses: Default::default(),
+                trace_emitted: Default::default(),
+                trace_disabled: Default::default(),
+                was_freppable: Default::default(),
+            };
+            // Place and start inserting into premade loop instruction block
+            LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_loop_inst);
+            LLVMPositionBuilderAtEnd(self.builder, bb_loop_inst);
+            // Emit instruction into loop instruction block
+            match tran.emit(inst_index, &mut fseq_inner) {
+                Ok(()) => (),
+                Err(e) => self.emit_unsupported(addr, tran.next_addr(), inst, e),
+            }
+            // Create next loop instruction block ahead of time
+            bb_loop_inst = LLVMCreateBasicBlockInContext(self.engine.context, NONAME);
+            // Terminate with branch to next loop instruction block
+            if LLVMGetBasicBlockTerminator(LLVMGetInsertBlock(self.builder)).is_null() {
+                LLVMBuildBr(self.builder, bb_loop_inst);
+            } else {
+                error!(
+                    target: log_target::TRANSLATE,
+                    "Cannot use terminating instruction inside an FREP"
+                );
+            }
+        }
+        bb_loop_inst
+    }
+
+    /// Emit the code for the entire section, and return whether each
+    /// instruction could be translated.
+    unsafe fn emit(&self, inst_index: &mut u32) -> Result<Vec<bool>> {
         // Initialize floating point sequencer context.
         let mut fseq = SequencerContext::new();
+        let mut translated = vec![];
         // iterate over section instructions
-        for (addr, inst) in self.elf.instructions(self.section) {
+        for (addr, raw, inst) in self
+            .elf
+            .instructions(self.section)
+            .filter(|&(addr, _, _)| addr >= self.addr_start && addr < self.addr_end)
+        {
+            // Call the code of deduplicated basic blocks, unless the
+            // sequencer buffers the instructions.
+            if let Some((occurrence, ok)) = self.elf.shared_blocks.get(&addr) {
+                if !fseq.active {
+                    if self.engine.coverage {
+                        self.engine
+                            .record_translated(riscv::inst_to_string(inst), *ok);
+                    }
+                    translated.push(*ok);
+                    LLVMPositionBuilderAtEnd(self.builder, self.inst_bb(addr));
+                    self.emit_shared_call(addr, occurrence);
+                    continue;
+                }
+            }
             let tran = InstructionTranslator {
                 section: self,
                 builder: self.builder,
                 addr,
+                raw,
                 inst,
                 was_terminator: Default::default(),
                 trace_accesses: Default::default(),
@@ -846,30 +1761,159 @@ impl<'a> SectionTranslator<'a> {
                 trace_disabled: Default::default(),
                 was_freppable: Default::default(),
             };
-            LLVMPositionBuilderAtEnd(self.builder, self.elf.inst_bbs[&addr]);
-            match tran.emit(inst_index, &mut fseq) {
+            LLVMPositionBuilderAtEnd(self.builder, self.inst_bb(addr));
+            // In deterministic mode, a hart yields at the start of basic
+            // blocks, but not within the instructions the sequencer buffers.
+            let yields = self.elf.deterministic && !fseq.active;
+            if yields && (self.elf.yield_every_inst || self.elf.target_addrs.contains(&addr)) {
+                self.emit_yield(addr);
+            }
+            let result = tran.emit(inst_index, &mut fseq);
+            if self.engine.coverage {
+                self.engine
+                    .record_translated(riscv::inst_to_string(inst), result.is_ok());
+            }
+            translated.push(result.is_ok());
+            match result {
                 Ok(()) => (),
-                Err(e) => {
-                    error!("{}", e);
-                    self.emit_illegal_abort(addr, inst);
-                }
+                Err(e) => self.emit_unsupported(addr, tran.next_addr(), inst, e),
             }
             // Note that FREP itself is not freppable.
             if fseq.active && tran.was_freppable.get() {
-                fseq.push_rep_instruction(addr, inst)?;
+                fseq.push_rep_instruction(addr, raw, inst)?;
                 if !fseq.is_outer || fseq.is_body_complete() {
-                    self.emit_frep(inst_index, &fseq, addr)?;
+                    self.emit_frep(inst_index, &fseq, tran.next_addr())?;
+                }
+                if fseq.is_body_complete() {
                     fseq.active = false;
                 }
             }
+            // A hart which has to wait ends its turn right away.
+            let terminated =
+                !LLVMGetBasicBlockTerminator(LLVMGetInsertBlock(self.builder)).is_null();
+            if yields && may_wait(inst) && !terminated {
+                self.emit_yield(tran.next_addr());
+            }
             // Place branch to next instruction only after processing of sequence
             // (a BB that already has a terminator corresponds to a jump instruction and
             // doesn't need a branch to the next subsequent instruction)
             if LLVMGetBasicBlockTerminator(LLVMGetInsertBlock(self.builder)).is_null() {
-                LLVMBuildBr(self.builder, self.elf.inst_bbs[&(addr + 4)]);
+                LLVMBuildBr(self.builder, self.inst_bb(tran.next_addr()));
             }
         }
-        Ok(())
+        Ok(translated)
+    }
+
+    /// Get the basic block of an instruction address.
+    ///
+    /// Within the function of a deduplicated basic block, addresses outside
+    /// of it map to the block which returns to take the branch.
+    unsafe fn inst_bb(&self, addr: u64) -> LLVMBasicBlockRef {
+        match self.shared {
+            Some(ref shared) => shared
+                .inst_bbs
+                .get(&addr)
+                .copied()
+                .unwrap_or(shared.branch_bb),
+            None => self.elf.inst_bbs[&addr],
+        }
+    }
+
+    /// Get the value of an instruction address.
+    ///
+    /// Within the function of a deduplicated basic block, this is relative to
+    /// the start address of the occurrence the function is called for.
+    unsafe fn addr_value(&self, addr: u64) -> LLVMValueRef {
+        match self.shared {
+            Some(ref shared) => LLVMBuildAdd(
+                self.builder,
+                shared.base,
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.engine.context),
+                    (addr as u32).wrapping_sub(shared.start as u32) as u64,
+                    0,
+                ),
+                NONAME,
+            ),
+            None => LLVMConstInt(LLVMInt32TypeInContext(self.engine.context), addr, 0),
+        }
+    }
+
+    /// Emit the code to return from the binary.
+    unsafe fn emit_return(&self) {
+        match self.shared {
+            Some(_) => LLVMBuildRet(
+                self.builder,
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.engine.context),
+                    BLOCK_EXIT_RETURN,
+                    0,
+                ),
+            ),
+            None => LLVMBuildRetVoid(self.builder),
+        };
+    }
+
+    /// Emit the code to yield to the scheduler of the deterministic mode if
+    /// the hart has used up its turn, to continue at `addr` in the next one.
+    unsafe fn emit_yield(&self, addr: u64) {
+        let yielded = self.emit_call("banshee_yield", [self.state_ptr, self.addr_value(addr)]);
+        let yielded = LLVMBuildIntCast(
+            self.builder,
+            yielded,
+            LLVMInt1TypeInContext(self.engine.context),
+            NONAME,
+        );
+        let bb_yield = LLVMCreateBasicBlockInContext(self.engine.context, NONAME);
+        let bb_cont = LLVMCreateBasicBlockInContext(self.engine.context, NONAME);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_cont);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_yield);
+        LLVMBuildCondBr(self.builder, yielded, bb_yield, bb_cont);
+        LLVMPositionBuilderAtEnd(self.builder, bb_yield);
+        self.emit_return();
+        LLVMPositionBuilderAtEnd(self.builder, bb_cont);
+    }
+
+    /// Emit a call to the code of a deduplicated basic block, which continues
+    /// at `addr`, and branch to where the block exits to.
+    unsafe fn emit_shared_call(&self, addr: u64, occurrence: &SharedOccurrence) {
+        let mut args = [
+            self.state_ptr,
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.engine.context),
+                occurrence.start,
+                0,
+            ),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.engine.context),
+                addr - occurrence.start,
+                0,
+            ),
+            self.indirect_target_var,
+            self.indirect_addr_var,
+        ];
+        let exit = LLVMBuildCall(
+            self.builder,
+            occurrence.func,
+            args.as_mut_ptr(),
+            args.len() as u32,
+            NONAME,
+        );
+        let bb_return = LLVMCreateBasicBlockInContext(self.engine.context, NONAME);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_return);
+        let sw = LLVMBuildSwitch(self.builder, exit, bb_return, 3);
+        let case = |exit| LLVMConstInt(LLVMInt32TypeInContext(self.engine.context), exit, 0);
+        LLVMAddCase(
+            sw,
+            case(BLOCK_EXIT_FALLTHROUGH),
+            self.inst_bb(occurrence.end),
+        );
+        if let Some(target) = occurrence.target {
+            LLVMAddCase(sw, case(BLOCK_EXIT_BRANCH), self.inst_bb(target));
+        }
+        LLVMAddCase(sw, case(BLOCK_EXIT_INDIRECT), self.indirect_bb);
+        LLVMPositionBuilderAtEnd(self.builder, bb_return);
+        self.emit_return();
     }
 
     /// Emit a call to a named function.
@@ -910,6 +1954,8 @@ pub struct InstructionTranslator<'a> {
     section: &'a SectionTranslator<'a>,
     builder: LLVMBuilderRef,
     addr: u64,
+    /// The raw encoding, which is 16 bit wide for compressed instructions.
+    raw: u32,
     inst: riscv::Format,
     was_terminator: Cell<bool>,
     trace_accesses: RefCell<Vec<(TraceAccess, LLVMValueRef)>>,
@@ -919,6 +1965,11 @@ pub struct InstructionTranslator<'a> {
 }
 
 impl<'a> InstructionTranslator<'a> {
+    /// The address of the subsequent instruction.
+    fn next_addr(&self) -> u64 {
+        self.addr + rvc::inst_len(self.raw)
+    }
+
     unsafe fn emit(&self, inst_index: &mut u32, fseq: &mut SequencerContext) -> Result<()> {
         // Emit some debug information that indicates what instruction we are
         // currently processing.
@@ -966,7 +2017,7 @@ impl<'a> InstructionTranslator<'a> {
         // Update the PC register to reflect this instruction.
         LLVMBuildStore(
             self.builder,
-            LLVMConstInt(LLVMInt32Type(), self.addr, 0),
+            self.section.addr_value(self.addr),
             self.pc_ptr(),
         );
 
@@ -975,6 +2026,19 @@ impl<'a> InstructionTranslator<'a> {
             self.emit_irq_check();
         }
 
+        // Call the breakpoint callbacks.
+        if self.section.engine.has_breakpoint(self.addr as u32) {
+            self.emit_breakpoint();
+        }
+
+        // Report the instruction to the access observer.
+        if self.section.engine.has_access_observer() {
+            self.section.emit_call(
+                "banshee_observe_inst",
+                [self.section.state_ptr, self.section.addr_value(self.addr)],
+            );
+        }
+
         // Update the instret counter.
         let instret = LLVMBuildLoad(self.builder, self.instret_ptr(), NONAME);
         let instret = LLVMBuildAdd(
@@ -985,11 +2049,63 @@ impl<'a> InstructionTranslator<'a> {
         );
         LLVMBuildStore(self.builder, instret, self.instret_ptr());
 
+        // Update the instruction mix counter.
+        if self.section.engine.stats || self.section.engine.coverage {
+            let slot = self
+                .section
+                .engine
+                .inst_mix_slot(&riscv::inst_to_string(self.inst));
+            let ptr = self.section.emit_call(
+                "banshee_inst_mix_ptr",
+                [
+                    self.section.state_ptr,
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        slot as u64,
+                        0,
+                    ),
+                ],
+            );
+            self.emit_increment(ptr);
+        }
+
         // reset ssr streamer flags to serve new values for SSR registers
         for i in 0..self.section.engine.config.ssr.num_dm as u32 {
             self.section.emit_call("banshee_ssr_eoi", [self.ssr_ptr(i)]);
         }
 
+        // Make sure the core has the features the instruction needs.
+        let feature = self.required_feature();
+        if feature & !self.section.elf.common_features != 0 {
+            if feature & !self.section.elf.any_features != 0 {
+                self.section.engine.record_unsupported(
+                    self.addr as u32,
+                    self.raw,
+                    format!(
+                        "{} needs the {} feature, which no core of cluster {} has",
+                        self.inst,
+                        CoreFeatures::name(feature),
+                        self.section.elf.cluster_id
+                    ),
+                );
+            }
+            self.emit_feature_check(feature);
+        }
+
+        // The reserved rounding modes are illegal, as is the dynamic one if
+        // `frm` holds an invalid mode.
+        let rm = match self.inst {
+            riscv::Format::RdRmRs1(x) => Some(x.rm),
+            riscv::Format::RdRmRs1Rs2(x) => Some(x.rm),
+            riscv::Format::RdRmRs1Rs2Rs3(x) => Some(x.rm),
+            _ => None,
+        };
+        match rm {
+            Some(5) | Some(6) => bail!("{} uses reserved rounding mode {}", self.inst, rm.unwrap()),
+            Some(7) => self.emit_frm_check(),
+            _ => (),
+        }
+
         // Emit the code for the instruction itself.
         match self.inst {
             riscv::Format::AqrlRdRs1(x) => self.emit_aqrl_rd_rs1(x),
@@ -1020,7 +2136,7 @@ impl<'a> InstructionTranslator<'a> {
             _ => {
                 if self.inst.raw() == 0 {
                     // Only generate a warning if the instruction is zero, as this usually comes from padding
-                    warn!("Zero instruction at 0x{:x}", self.addr);
+                    warn!(target: log_target::TRANSLATE, "Zero instruction at 0x{:x}", self.addr);
                     return Ok(());
                 } else {
                     Err(anyhow!("Unsupported instruction format"))
@@ -1034,8 +2150,92 @@ impl<'a> InstructionTranslator<'a> {
         Ok(())
     }
 
+    /// The optional core features the instruction needs, as a bit mask.
+    fn required_feature(&self) -> u32 {
+        let mnemonic = riscv::inst_to_string(self.inst);
+        match self.inst {
+            riscv::Format::Imm12RdRs1(x)
+                if mnemonic.starts_with("csrr") && x.imm12 == riscv::Csr::Ssr as u32 =>
+            {
+                CoreFeatures::SSR
+            }
+            _ if mnemonic.starts_with("scfg") => CoreFeatures::SSR,
+            _ if mnemonic.starts_with("dm") => CoreFeatures::DMA,
+            _ => 0,
+        }
+    }
+
+    /// Emit the code to abort if the core lacks a feature.
+    unsafe fn emit_feature_check(&self, feature: u32) {
+        let features = self
+            .section
+            .emit_call("banshee_features", [self.section.state_ptr]);
+        let missing = LLVMBuildICmp(
+            self.builder,
+            LLVMIntEQ,
+            LLVMBuildAnd(
+                self.builder,
+                features,
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    feature as u64,
+                    0,
+                ),
+                NONAME,
+            ),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0, 0),
+            NONAME,
+        );
+        let bb_missing = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+        let bb_present = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_present);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_missing);
+        LLVMBuildCondBr(self.builder, missing, bb_missing, bb_present);
+
+        LLVMPositionBuilderAtEnd(self.builder, bb_missing);
+        self.section.emit_call(
+            "banshee_abort_missing_feature",
+            [
+                self.section.state_ptr,
+                self.section.addr_value(self.addr),
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    feature as u64,
+                    0,
+                ),
+            ],
+        );
+        self.section.emit_return();
+        LLVMPositionBuilderAtEnd(self.builder, bb_present);
+    }
+
+    /// Emit the code which handles the instruction as illegal if `frm` holds
+    /// an invalid rounding mode.
+    unsafe fn emit_frm_check(&self) {
+        let frm = self
+            .section
+            .emit_call("banshee_frm", [self.section.state_ptr]);
+        let invalid = LLVMBuildICmp(
+            self.builder,
+            LLVMIntUGT,
+            frm,
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 4, 0),
+            NONAME,
+        );
+        let bb_invalid = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+        let bb_valid = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_valid);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_invalid);
+        LLVMBuildCondBr(self.builder, invalid, bb_invalid, bb_valid);
+
+        LLVMPositionBuilderAtEnd(self.builder, bb_invalid);
+        self.section
+            .emit_illegal_abort(self.addr, self.next_addr(), self.inst);
+        LLVMPositionBuilderAtEnd(self.builder, bb_valid);
+    }
+
     unsafe fn emit_aqrl_rd_rs1(&self, data: riscv::FormatAqrlRdRs1) -> Result<()> {
-        trace!("{} x{} = x{}", data.op, data.rd, data.rs1);
+        trace!(target: log_target::TRANSLATE, "{} x{} = x{}", data.op, data.rd, data.rs1);
 
         // LR is not freppable
         self.was_freppable.set(false);
@@ -1068,14 +2268,14 @@ impl<'a> InstructionTranslator<'a> {
         let is_aligned = LLVMBuildAnd(
             self.builder,
             addr,
-            LLVMConstInt(LLVMInt32Type(), 3, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 3, 0),
             NONAME,
         );
         let is_aligned = LLVMBuildICmp(
             self.builder,
             LLVMIntEQ,
             is_aligned,
-            LLVMConstInt(LLVMInt32Type(), 0, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0, 0),
             NONAME,
         );
         let bb_valid = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
@@ -1086,15 +2286,8 @@ impl<'a> InstructionTranslator<'a> {
 
         // Abort due to unaligned LR
         LLVMPositionBuilderAtEnd(self.builder, bb_invalid);
-        self.section.emit_call(
-            "banshee_abort_illegal_inst",
-            [
-                self.section.state_ptr,
-                LLVMConstInt(LLVMInt32Type(), addr as u64, 0),
-                LLVMConstInt(LLVMInt32Type(), self.inst.raw() as u64, 0),
-            ],
-        );
-        LLVMBuildRetVoid(self.builder);
+        self.section
+            .emit_abort_policy(self.addr, self.next_addr(), self.inst.raw());
 
         let phi_size = self.section.elf.tcdm_ext_range.len() + 2;
         let mut values: Vec<LLVMValueRef> = Vec::with_capacity(phi_size);
@@ -1111,6 +2304,7 @@ impl<'a> InstructionTranslator<'a> {
 
         // Emit the TCDM fast case.
         LLVMPositionBuilderAtEnd(self.builder, bb_yes);
+        self.emit_count_access(MemRegion::Tcdm);
         values.push(LLVMBuildLoad(self.builder, tcdm_ptr, NONAME));
         LLVMBuildBr(self.builder, bb_end);
         bbs.push(LLVMGetInsertBlock(self.builder));
@@ -1126,6 +2320,7 @@ impl<'a> InstructionTranslator<'a> {
 
             // Emit the external TCDM fast case.
             LLVMPositionBuilderAtEnd(self.builder, bb_yes);
+            self.emit_count_access(MemRegion::TcdmExternal);
             values.push(LLVMBuildLoad(self.builder, tcdm_ptr, NONAME));
             LLVMBuildBr(self.builder, bb_end);
             bbs.push(LLVMGetInsertBlock(self.builder));
@@ -1142,7 +2337,11 @@ impl<'a> InstructionTranslator<'a> {
             [
                 self.section.state_ptr,
                 addr,
-                LLVMConstInt(LLVMInt8Type(), 4 as u64, 0),
+                LLVMConstInt(
+                    LLVMInt8TypeInContext(self.section.engine.context),
+                    4 as u64,
+                    0,
+                ),
             ]
             .as_mut_ptr(),
             3,
@@ -1154,7 +2353,11 @@ impl<'a> InstructionTranslator<'a> {
 
         // Build the PHI node to bring the two together.
         LLVMPositionBuilderAtEnd(self.builder, bb_end);
-        let phi = LLVMBuildPhi(self.builder, LLVMInt32Type(), NONAME);
+        let phi = LLVMBuildPhi(
+            self.builder,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
         LLVMAddIncoming(phi, values.as_mut_ptr(), bbs.as_mut_ptr(), phi_size as u32);
 
         // Write the final result to the register
@@ -1165,7 +2368,14 @@ impl<'a> InstructionTranslator<'a> {
     }
 
     unsafe fn emit_aqrl_rd_rs1_rs2(&self, data: riscv::FormatAqrlRdRs1Rs2) -> Result<()> {
-        trace!("{} x{} = x{}, x{}", data.op, data.rd, data.rs1, data.rs2);
+        trace!(
+            target: log_target::TRANSLATE,
+            "{} x{} = x{}, x{}",
+            data.op,
+            data.rd,
+            data.rs1,
+            data.rs2
+        );
 
         // AMOs are not freppable
         self.was_freppable.set(false);
@@ -1208,14 +2418,14 @@ impl<'a> InstructionTranslator<'a> {
         let is_aligned = LLVMBuildAnd(
             self.builder,
             addr,
-            LLVMConstInt(LLVMInt32Type(), 3, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 3, 0),
             NONAME,
         );
         let is_aligned = LLVMBuildICmp(
             self.builder,
             LLVMIntEQ,
             is_aligned,
-            LLVMConstInt(LLVMInt32Type(), 0, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0, 0),
             NONAME,
         );
         let bb_valid = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
@@ -1225,16 +2435,9 @@ impl<'a> InstructionTranslator<'a> {
         LLVMBuildCondBr(self.builder, is_aligned, bb_valid, bb_invalid);
 
         // Abort due to unaligned AMO
-        LLVMPositionBuilderAtEnd(self.builder, bb_invalid);
-        self.section.emit_call(
-            "banshee_abort_illegal_inst",
-            [
-                self.section.state_ptr,
-                LLVMConstInt(LLVMInt32Type(), addr as u64, 0),
-                LLVMConstInt(LLVMInt32Type(), self.inst.raw() as u64, 0),
-            ],
-        );
-        LLVMBuildRetVoid(self.builder);
+        LLVMPositionBuilderAtEnd(self.builder, bb_invalid);
+        self.section
+            .emit_abort_policy(self.addr, self.next_addr(), self.inst.raw());
 
         let phi_size = self.section.elf.tcdm_ext_range.len() + 2;
         let mut values: Vec<LLVMValueRef> = Vec::with_capacity(phi_size);
@@ -1336,7 +2539,12 @@ impl<'a> InstructionTranslator<'a> {
                 );
                 let success = LLVMBuildExtractValue(self.builder, val_success, 1, NONAME);
                 let success = LLVMBuildNot(self.builder, success, NONAME);
-                LLVMBuildZExtOrBitCast(self.builder, success, LLVMInt32Type(), NONAME)
+                LLVMBuildZExtOrBitCast(
+                    self.builder,
+                    success,
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    NONAME,
+                )
             }
         });
         LLVMBuildBr(self.builder, bb_end);
@@ -1438,7 +2646,12 @@ impl<'a> InstructionTranslator<'a> {
                     );
                     let success = LLVMBuildExtractValue(self.builder, val_success, 1, NONAME);
                     let success = LLVMBuildNot(self.builder, success, NONAME);
-                    LLVMBuildZExtOrBitCast(self.builder, success, LLVMInt32Type(), NONAME)
+                    LLVMBuildZExtOrBitCast(
+                        self.builder,
+                        success,
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        NONAME,
+                    )
                 }
             });
 
@@ -1448,7 +2661,11 @@ impl<'a> InstructionTranslator<'a> {
 
         // Encode the operation
         let op_value: u8 = std::mem::transmute(op as u8);
-        let op = LLVMConstInt(LLVMInt8Type(), op_value as u64, 0);
+        let op = LLVMConstInt(
+            LLVMInt8TypeInContext(self.section.engine.context),
+            op_value as u64,
+            0,
+        );
 
         // Emit the regular slow case.
         LLVMPositionBuilderAtEnd(self.builder, bb_no);
@@ -1468,7 +2685,11 @@ impl<'a> InstructionTranslator<'a> {
 
         // Build the PHI node to bring the two together.
         LLVMPositionBuilderAtEnd(self.builder, bb_end);
-        let phi = LLVMBuildPhi(self.builder, LLVMInt32Type(), NONAME);
+        let phi = LLVMBuildPhi(
+            self.builder,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
         LLVMAddIncoming(phi, values.as_mut_ptr(), bbs.as_mut_ptr(), phi_size as u32);
 
         // Write the final result to the register
@@ -1482,7 +2703,14 @@ impl<'a> InstructionTranslator<'a> {
         data: riscv::FormatBimm12hiBimm12loRs1Rs2,
     ) -> Result<()> {
         let target = (self.addr as i64).wrapping_add(data.bimm() as i64) as u64;
-        trace!("{} x{}, x{}, 0x{:x}", data.op, data.rs1, data.rs2, target);
+        trace!(
+            target: log_target::TRANSLATE,
+            "{} x{}, x{}, 0x{:x}",
+            data.op,
+            data.rs1,
+            data.rs2,
+            target
+        );
         let rs1 = self.read_reg(data.rs1);
         let rs2 = self.read_reg(data.rs2);
         let name = format!("{}_x{}_x{}\0", data.op, data.rs1, data.rs2);
@@ -1500,13 +2728,24 @@ impl<'a> InstructionTranslator<'a> {
             LLVMCreateBasicBlockInContext(self.section.engine.context, b"\0".as_ptr() as *const _);
         LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb);
         self.emit_trace();
-        LLVMBuildCondBr(self.builder, cmp, self.section.elf.inst_bbs[&target], bb);
+        self.emit_stop_check();
+        if self.section.elf.spin_loops.contains(&self.addr) {
+            let bb_taken = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+            LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_taken);
+            LLVMBuildCondBr(self.builder, cmp, bb_taken, bb);
+            LLVMPositionBuilderAtEnd(self.builder, bb_taken);
+            self.emit_spin_check();
+            LLVMBuildBr(self.builder, self.section.inst_bb(target));
+        } else {
+            LLVMBuildCondBr(self.builder, cmp, self.section.inst_bb(target), bb);
+        }
         LLVMPositionBuilderAtEnd(self.builder, bb);
         Ok(())
     }
 
     unsafe fn emit_fm_pred_rd_rs1_succ(&self, data: riscv::FormatFmPredRdRs1Succ) -> Result<()> {
         trace!(
+            target: log_target::TRANSLATE,
             "{} fm={:x}, pred={:x}, succ={:x}",
             data.op,
             data.fm,
@@ -1533,7 +2772,7 @@ impl<'a> InstructionTranslator<'a> {
 
     unsafe fn emit_imm12_rs1(&self, data: riscv::FormatImm12Rs1) -> Result<()> {
         let imm = data.imm();
-        trace!("{} x{}, {}", data.op, data.rs1, imm);
+        trace!(target: log_target::TRANSLATE, "{} x{}, {}", data.op, data.rs1, imm);
 
         // Compute the address.
         let rs1 = self.read_reg(data.rs1);
@@ -1544,18 +2783,15 @@ impl<'a> InstructionTranslator<'a> {
         #[allow(unreachable_patterns)]
         match data.op {
             riscv::OpcodeImm12Rs1::Scfgwi => {
-                // ssr write immediate holds address offset in imm12, content in rs1
-                // imm12[11:5]=reg_word imm12[4:0]=dm -> addr_off = {dm, reg_word[4:0], 000}
-                let dm = (imm as u64) & 0x1f;
-                let reg_word = ((imm as u64) >> 5) & 0x1f;
-                let addr_off = LLVMConstInt(LLVMInt32Type(), (dm << 8) | (reg_word << 3), 0);
-                let addr = LLVMBuildAdd(
-                    self.builder,
-                    LLVMConstInt(LLVMInt32Type(), SSR_BASE, 0),
-                    addr_off,
-                    NONAME,
+                // ssr write immediate holds the register in imm12, content in rs1
+                self.emit_ssr_cfg_write(
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        imm as u64,
+                        0,
+                    ),
+                    rs1,
                 );
-                self.write_mem(addr, rs1, 2);
             }
             _ => bail!("Unsupported opcode {}", data.op),
         };
@@ -1567,11 +2803,22 @@ impl<'a> InstructionTranslator<'a> {
         data: riscv::FormatImm12hiImm12loRs1Rs2,
     ) -> Result<()> {
         let imm = data.imm();
-        trace!("{} x{}, x{}, 0x{:x}", data.op, data.rs1, data.rs2, imm);
+        trace!(
+            target: log_target::TRANSLATE,
+            "{} x{}, x{}, 0x{:x}",
+            data.op,
+            data.rs1,
+            data.rs2,
+            imm
+        );
 
         // Compute the address.
         let rs1 = self.read_reg(data.rs1);
-        let imm = LLVMConstInt(LLVMInt32Type(), (imm as i64) as u64, 0);
+        let imm = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            (imm as i64) as u64,
+            0,
+        );
         let addr = LLVMBuildAdd(self.builder, rs1, imm, NONAME);
 
         // Perform the operation.
@@ -1588,38 +2835,57 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeImm12hiImm12loRs1Rs2::Fsb => {
                 self.was_freppable.set(true);
                 let rs2 = self.read_freg(data.rs2);
-                let rs2_lo = LLVMBuildTrunc(self.builder, rs2, LLVMInt32Type(), NONAME);
+                let rs2_lo = LLVMBuildTrunc(
+                    self.builder,
+                    rs2,
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 self.write_mem(addr, rs2_lo, 1);
             }
             riscv::OpcodeImm12hiImm12loRs1Rs2::Fsh => {
                 self.was_freppable.set(true);
                 let rs2 = self.read_freg(data.rs2);
-                let rs2_lo = LLVMBuildTrunc(self.builder, rs2, LLVMInt32Type(), NONAME);
+                let rs2_lo = LLVMBuildTrunc(
+                    self.builder,
+                    rs2,
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 self.write_mem(addr, rs2_lo, 1);
             }
             riscv::OpcodeImm12hiImm12loRs1Rs2::Fsw => {
                 self.was_freppable.set(true);
-                let rs2 = self.read_freg_f32(data.rs2, true);
-                let rs2 = LLVMBuildBitCast(self.builder, rs2, LLVMInt32Type(), NONAME);
+                let rs2 = self.read_freg_f32_bits(data.rs2);
                 self.write_mem(addr, rs2, 2);
             }
             riscv::OpcodeImm12hiImm12loRs1Rs2::Fsd => {
                 self.was_freppable.set(true);
                 let rs2 = self.read_freg(data.rs2);
-                let rs2_lo = LLVMBuildTrunc(self.builder, rs2, LLVMInt32Type(), NONAME);
+                let rs2_lo = LLVMBuildTrunc(
+                    self.builder,
+                    rs2,
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let rs2_hi = LLVMBuildLShr(
                     self.builder,
                     rs2,
-                    LLVMConstInt(LLVMInt64Type(), 32, 0),
+                    LLVMConstInt(LLVMInt64TypeInContext(self.section.engine.context), 32, 0),
+                    NONAME,
+                );
+                let rs2_hi = LLVMBuildTrunc(
+                    self.builder,
+                    rs2_hi,
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
-                let rs2_hi = LLVMBuildTrunc(self.builder, rs2_hi, LLVMInt32Type(), NONAME);
                 self.write_mem(addr, rs2_lo, 2);
                 self.write_mem(
                     LLVMBuildAdd(
                         self.builder,
                         addr,
-                        LLVMConstInt(LLVMInt32Type(), 4, 0),
+                        LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 4, 0),
                         NONAME,
                     ),
                     rs2_hi,
@@ -1633,15 +2899,19 @@ impl<'a> InstructionTranslator<'a> {
 
     unsafe fn emit_imm5_rd(&self, data: riscv::FormatImm5Rd) -> Result<()> {
         let imm = data.imm5;
-        trace!("{} x{} = 0x{:x}", data.op, data.rd, imm);
-        let imm = LLVMConstInt(LLVMInt32Type(), (imm as i64) as u64, 0);
+        trace!(target: log_target::TRANSLATE, "{} x{} = 0x{:x}", data.op, data.rd, imm);
+        let imm = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            (imm as i64) as u64,
+            0,
+        );
         let name = format!("{}\0", data.op);
         let _name = name.as_ptr() as *const _;
 
         let value = match data.op {
             riscv::OpcodeImm5Rd::Dmstati => self
                 .section
-                .emit_call("banshee_dma_stat", [self.dma_ptr(), imm]),
+                .emit_call("banshee_dma_stat", [self.section.state_ptr, imm]),
         };
         self.write_reg(data.rd, value);
         Ok(())
@@ -1649,23 +2919,27 @@ impl<'a> InstructionTranslator<'a> {
 
     unsafe fn emit_imm12_rd(&self, data: riscv::FormatImm12Rd) -> Result<()> {
         let imm = data.imm();
-        trace!("{} x{} = 0x{:x}", data.op, data.rd, imm);
+        trace!(target: log_target::TRANSLATE, "{} x{} = 0x{:x}", data.op, data.rd, imm);
         let name = format!("{}\0", data.op);
         let _name = name.as_ptr() as *const _;
 
-        let ssr_start = LLVMConstInt(LLVMInt32Type(), SSR_BASE, 0);
+        let ssr_start = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            SSR_BASE,
+            0,
+        );
 
         // suppress compiler warning that detects the bail! statement as unrechable
         // Scfgri is currently the OpcodeImm12Rd but this might change
         #[allow(unreachable_patterns)]
         match data.op {
             riscv::OpcodeImm12Rd::Scfgri => {
-                // srr load immediate from offset in imm12
-                // reorder imm12 to form address
-                // imm12[11:5]=reg_word imm12[4:0]=dm -> addr_off = {dm, reg_word[4:0], 000}
-                let dm = (imm as u64) & 0x1f;
-                let reg_word = ((imm as u64) >> 5) & 0x1f;
-                let addr_off = LLVMConstInt(LLVMInt32Type(), (dm << 8) | (reg_word << 3), 0);
+                // srr load immediate from the register in imm12
+                let addr_off = self.emit_ssr_cfg_offset(LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    imm as u64,
+                    0,
+                ));
                 let value = self.emit_load(ssr_start, addr_off, 2, true);
                 self.write_reg(data.rd, value);
             }
@@ -1677,7 +2951,11 @@ impl<'a> InstructionTranslator<'a> {
     unsafe fn emit_imm5_rd_rs1(&self, data: riscv::FormatImm5RdRs1) -> Result<()> {
         let imm = data.imm5;
         let rs1 = self.read_reg(data.rs1);
-        let imm = LLVMConstInt(LLVMInt32Type(), (imm as i64) as u64, 0);
+        let imm = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            (imm as i64) as u64,
+            0,
+        );
         let value = match data.op {
             riscv::OpcodeImm5RdRs1::Dmcpyi => self.section.emit_call(
                 "banshee_dma_strt",
@@ -1691,7 +2969,14 @@ impl<'a> InstructionTranslator<'a> {
 
     unsafe fn emit_imm12_rd_rs1(&self, data: riscv::FormatImm12RdRs1) -> Result<()> {
         let imm = data.imm();
-        trace!("{} x{} = x{}, 0x{:x}", data.op, data.rd, data.rs1, imm);
+        trace!(
+            target: log_target::TRANSLATE,
+            "{} x{} = x{}, 0x{:x}",
+            data.op,
+            data.rd,
+            data.rs1,
+            imm
+        );
 
         // Handle CSR instructions, since we don't want to gratuitously read
         // registers.
@@ -1706,7 +2991,11 @@ impl<'a> InstructionTranslator<'a> {
         }
 
         let rs1 = self.read_reg(data.rs1);
-        let imm = LLVMConstInt(LLVMInt32Type(), (imm as i64) as u64, 0);
+        let imm = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            (imm as i64) as u64,
+            0,
+        );
         let name = format!("{}\0", data.op);
         let name = name.as_ptr() as *const _;
         let value = match data.op {
@@ -1714,13 +3003,13 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeImm12RdRs1::Slti => LLVMBuildZExt(
                 self.builder,
                 LLVMBuildICmp(self.builder, LLVMIntSLT, rs1, imm, NONAME),
-                LLVMInt32Type(),
+                LLVMInt32TypeInContext(self.section.engine.context),
                 name,
             ),
             riscv::OpcodeImm12RdRs1::Sltiu => LLVMBuildZExt(
                 self.builder,
                 LLVMBuildICmp(self.builder, LLVMIntULT, rs1, imm, NONAME),
-                LLVMInt32Type(),
+                LLVMInt32TypeInContext(self.section.engine.context),
                 name,
             ),
             riscv::OpcodeImm12RdRs1::Andi => LLVMBuildAnd(self.builder, rs1, imm, name),
@@ -1743,17 +3032,15 @@ impl<'a> InstructionTranslator<'a> {
                 let target = LLVMBuildAdd(self.builder, rs1, imm, name);
 
                 // Write the link register.
-                self.write_reg(
-                    data.rd,
-                    LLVMConstInt(LLVMInt32Type(), (self.addr + 4) as u64, 0),
-                );
+                self.write_reg(data.rd, self.section.addr_value(self.next_addr()));
                 self.emit_trace();
+                self.emit_stop_check();
 
                 // Use the prepared indirect jump switch statement.
                 LLVMBuildStore(self.builder, target, self.section.indirect_target_var);
                 LLVMBuildStore(
                     self.builder,
-                    LLVMConstInt(LLVMInt32Type(), self.addr as u64, 0),
+                    self.section.addr_value(self.addr),
                     self.section.indirect_addr_var,
                 );
                 LLVMBuildBr(self.builder, self.section.indirect_bb);
@@ -1763,8 +3050,17 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeImm12RdRs1::Flb => {
                 self.was_freppable.set(true);
                 let raw = self.emit_load(rs1, imm, 1, false);
-                let raw = LLVMBuildZExt(self.builder, raw, LLVMInt64Type(), NONAME);
-                let pad = LLVMConstInt(LLVMInt64Type(), (-1i64 as u64) << 8, 0);
+                let raw = LLVMBuildZExt(
+                    self.builder,
+                    raw,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let pad = LLVMConstInt(
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    (-1i64 as u64) << 8,
+                    0,
+                );
                 let value = LLVMBuildOr(self.builder, raw, pad, NONAME);
                 self.write_freg(data.rd, value);
                 return Ok(());
@@ -1772,8 +3068,17 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeImm12RdRs1::Flh => {
                 self.was_freppable.set(true);
                 let raw = self.emit_load(rs1, imm, 1, false);
-                let raw = LLVMBuildZExt(self.builder, raw, LLVMInt64Type(), NONAME);
-                let pad = LLVMConstInt(LLVMInt64Type(), (-1i64 as u64) << 16, 0);
+                let raw = LLVMBuildZExt(
+                    self.builder,
+                    raw,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let pad = LLVMConstInt(
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    (-1i64 as u64) << 16,
+                    0,
+                );
                 let value = LLVMBuildOr(self.builder, raw, pad, NONAME);
                 self.write_freg(data.rd, value);
                 return Ok(());
@@ -1781,8 +3086,7 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeImm12RdRs1::Flw => {
                 self.was_freppable.set(true);
                 let raw = self.emit_load(rs1, imm, 2, false);
-                let value = LLVMBuildBitCast(self.builder, raw, LLVMFloatType(), NONAME);
-                self.write_freg_f32(data.rd, value, true);
+                self.write_freg_f32(data.rd, raw, false);
                 return Ok(());
             }
             riscv::OpcodeImm12RdRs1::Fld => {
@@ -1802,18 +3106,28 @@ impl<'a> InstructionTranslator<'a> {
             LLVMBuildAdd(
                 self.builder,
                 addr,
-                LLVMConstInt(LLVMInt32Type(), 4, 0),
+                LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 4, 0),
                 NONAME,
             ),
             2,
             false,
         );
-        let raw_lo = LLVMBuildZExt(self.builder, raw_lo, LLVMInt64Type(), NONAME);
-        let raw_hi = LLVMBuildZExt(self.builder, raw_hi, LLVMInt64Type(), NONAME);
+        let raw_lo = LLVMBuildZExt(
+            self.builder,
+            raw_lo,
+            LLVMInt64TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let raw_hi = LLVMBuildZExt(
+            self.builder,
+            raw_hi,
+            LLVMInt64TypeInContext(self.section.engine.context),
+            NONAME,
+        );
         let raw_hi = LLVMBuildShl(
             self.builder,
             raw_hi,
-            LLVMConstInt(LLVMInt64Type(), 32, 0),
+            LLVMConstInt(LLVMInt64TypeInContext(self.section.engine.context), 32, 0),
             NONAME,
         );
         let value = LLVMBuildOr(self.builder, raw_lo, raw_hi, NONAME);
@@ -1823,20 +3137,30 @@ impl<'a> InstructionTranslator<'a> {
     unsafe fn emit_fsd(&self, rs: u32, addr: LLVMValueRef) {
         let ptr = self.freg_ptr(rs);
         let rs = LLVMBuildLoad(self.builder, ptr, format!("f{}\0", rs).as_ptr() as *const _);
-        let rs_lo = LLVMBuildTrunc(self.builder, rs, LLVMInt32Type(), NONAME);
+        let rs_lo = LLVMBuildTrunc(
+            self.builder,
+            rs,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
         let rs_hi = LLVMBuildLShr(
             self.builder,
             rs,
-            LLVMConstInt(LLVMInt64Type(), 32, 0),
+            LLVMConstInt(LLVMInt64TypeInContext(self.section.engine.context), 32, 0),
+            NONAME,
+        );
+        let rs_hi = LLVMBuildTrunc(
+            self.builder,
+            rs_hi,
+            LLVMInt32TypeInContext(self.section.engine.context),
             NONAME,
         );
-        let rs_hi = LLVMBuildTrunc(self.builder, rs_hi, LLVMInt32Type(), NONAME);
         self.write_mem(addr, rs_lo, 2);
         self.write_mem(
             LLVMBuildAdd(
                 self.builder,
                 addr,
-                LLVMConstInt(LLVMInt32Type(), 4, 0),
+                LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 4, 0),
                 NONAME,
             ),
             rs_hi,
@@ -1846,14 +3170,16 @@ impl<'a> InstructionTranslator<'a> {
 
     unsafe fn emit_imm20_rd(&self, data: riscv::FormatImm20Rd) -> Result<()> {
         let imm = data.imm20 << 12;
-        trace!("{} x{} = 0x{:x}", data.op, data.rd, imm);
+        trace!(target: log_target::TRANSLATE, "{} x{} = 0x{:x}", data.op, data.rd, imm);
         let value = match data.op {
-            riscv::OpcodeImm20Rd::Auipc => LLVMConstInt(
-                LLVMInt32Type(),
-                (self.addr as u32).wrapping_add(imm) as u64,
+            riscv::OpcodeImm20Rd::Auipc => self
+                .section
+                .addr_value((self.addr as u32).wrapping_add(imm) as u64),
+            riscv::OpcodeImm20Rd::Lui => LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                imm as u64,
                 0,
             ),
-            riscv::OpcodeImm20Rd::Lui => LLVMConstInt(LLVMInt32Type(), imm as u64, 0),
         };
         self.write_reg(data.rd, value);
         Ok(())
@@ -1863,13 +3189,14 @@ impl<'a> InstructionTranslator<'a> {
         match data.op {
             riscv::OpcodeJimm20Rd::Jal => {
                 let target = (self.addr as i64).wrapping_add(data.jimm() as i64) as u64;
-                trace!("jal x{}, 0x{:x}", data.rd, target);
-                self.write_reg(
-                    data.rd,
-                    LLVMConstInt(LLVMInt32Type(), (self.addr + 4) as u64, 0),
-                );
+                trace!(target: log_target::TRANSLATE, "jal x{}, 0x{:x}", data.rd, target);
+                self.write_reg(data.rd, self.section.addr_value(self.next_addr()));
                 self.emit_trace(); // need to do this before we branch away
-                LLVMBuildBr(self.builder, self.section.elf.inst_bbs[&target]);
+                self.emit_stop_check();
+                if self.section.elf.spin_loops.contains(&self.addr) {
+                    self.emit_spin_check();
+                }
+                LLVMBuildBr(self.builder, self.section.inst_bb(target));
                 self.was_terminator.set(true);
                 Ok(())
             }
@@ -1877,34 +3204,79 @@ impl<'a> InstructionTranslator<'a> {
     }
 
     unsafe fn emit_rd_rm_rs1(&self, data: riscv::FormatRdRmRs1) -> Result<()> {
-        trace!("{} x{}, f{}", data.op, data.rd, data.rs1);
+        trace!(target: log_target::TRANSLATE, "{} x{}, f{}", data.op, data.rd, data.rs1);
         let name = format!("{}\0", data.op);
         let name = name.as_ptr() as *const _;
+        // Operations from float to float, float to integer, and integer to
+        // float registers. The conversions which round or saturate are always
+        // executed in software, to honor the rounding mode, saturate out of
+        // range values and NaNs, and accrue the exception flags.
+        let exact = match data.op {
+            riscv::OpcodeRdRmRs1::FcvtWS => Some((FpOp::CvtToW, false, true, false)),
+            riscv::OpcodeRdRmRs1::FcvtWuS => Some((FpOp::CvtToWu, false, true, false)),
+            riscv::OpcodeRdRmRs1::FcvtWD => Some((FpOp::CvtToW, true, true, false)),
+            riscv::OpcodeRdRmRs1::FcvtWuD => Some((FpOp::CvtToWu, true, true, false)),
+            riscv::OpcodeRdRmRs1::FcvtSW => Some((FpOp::CvtFromW, false, false, true)),
+            riscv::OpcodeRdRmRs1::FcvtSWu => Some((FpOp::CvtFromWu, false, false, true)),
+            _ if !self.section.elf.exact_fp => None,
+            riscv::OpcodeRdRmRs1::FsqrtS => Some((FpOp::Sqrt, false, true, true)),
+            riscv::OpcodeRdRmRs1::FsqrtD => Some((FpOp::Sqrt, true, true, true)),
+            riscv::OpcodeRdRmRs1::FcvtDS => Some((FpOp::CvtToOther, false, true, true)),
+            riscv::OpcodeRdRmRs1::FcvtSD => Some((FpOp::CvtToOther, true, true, true)),
+            riscv::OpcodeRdRmRs1::FcvtDW => Some((FpOp::CvtFromW, true, false, true)),
+            riscv::OpcodeRdRmRs1::FcvtDWu => Some((FpOp::CvtFromWu, true, false, true)),
+            _ => None,
+        };
+        if let Some((op, double, float_src, float_dst)) = exact {
+            let rs1 = if float_src {
+                self.read_freg(data.rs1)
+            } else {
+                self.read_reg(data.rs1)
+            };
+            let value = self.emit_exact_fp(op, data.rm, double, &[rs1]);
+            if float_dst {
+                self.write_freg(data.rd, value);
+            } else {
+                let value = LLVMBuildTrunc(
+                    self.builder,
+                    value,
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                self.write_reg(data.rd, value);
+            }
+            return Ok(());
+        }
         match data.op {
             riscv::OpcodeRdRmRs1::FcvtDW => {
                 let rs1 = self.read_reg(data.rs1);
-                let value = LLVMBuildSIToFP(self.builder, rs1, LLVMDoubleType(), name);
+                let value = LLVMBuildSIToFP(
+                    self.builder,
+                    rs1,
+                    LLVMDoubleTypeInContext(self.section.engine.context),
+                    name,
+                );
                 self.write_freg_f64(data.rd, value, true);
             }
             riscv::OpcodeRdRmRs1::FcvtDWu => {
                 let rs1 = self.read_reg(data.rs1);
-                let value = LLVMBuildUIToFP(self.builder, rs1, LLVMDoubleType(), name);
+                let value = LLVMBuildUIToFP(
+                    self.builder,
+                    rs1,
+                    LLVMDoubleTypeInContext(self.section.engine.context),
+                    name,
+                );
                 self.write_freg_f64(data.rd, value, true);
             }
-            riscv::OpcodeRdRmRs1::FcvtSW => {
-                let rs1 = self.read_reg(data.rs1);
-                let value = LLVMBuildSIToFP(self.builder, rs1, LLVMFloatType(), name);
-                self.write_freg_f32(data.rd, value, true);
-            }
-            riscv::OpcodeRdRmRs1::FcvtSWu => {
-                let rs1 = self.read_reg(data.rs1);
-                let value = LLVMBuildUIToFP(self.builder, rs1, LLVMFloatType(), name);
-                self.write_freg_f32(data.rd, value, true);
-            }
             riscv::OpcodeRdRmRs1::FcvtHW => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_reg(data.rs1);
-                let rs1 = LLVMBuildIntCast(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = LLVMBuildIntCast(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp16_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fcvtw2f,
@@ -1916,7 +3288,12 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeRdRmRs1::FcvtHWu => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_reg(data.rs1);
-                let rs1 = LLVMBuildIntCast(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = LLVMBuildIntCast(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp16_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fcvtwu2f,
@@ -1928,7 +3305,12 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeRdRmRs1::FcvtQW => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_reg(data.rs1);
-                let rs1 = LLVMBuildIntCast(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = LLVMBuildIntCast(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp8_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fcvtw2f,
@@ -1940,7 +3322,12 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeRdRmRs1::FcvtQWu => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_reg(data.rs1);
-                let rs1 = LLVMBuildIntCast(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = LLVMBuildIntCast(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp8_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fcvtwu2f,
@@ -1949,99 +3336,91 @@ impl<'a> InstructionTranslator<'a> {
                 );
                 self.write_freg_f8(data.rd, value);
             }
-            riscv::OpcodeRdRmRs1::FcvtWQ => {
+            riscv::OpcodeRdRmRs1::FcvtWQ
+            | riscv::OpcodeRdRmRs1::FcvtWuQ
+            | riscv::OpcodeRdRmRs1::FcvtWH
+            | riscv::OpcodeRdRmRs1::FcvtWuH => {
+                // Widen to double, which is exact, and convert that in
+                // software like the other conversions to integers.
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
-                let rs1 = self.read_freg(data.rs1);
-                let value = self.emit_fp8_op_cvt_from_f(
+                let (rs1, widen) = match data.op {
+                    riscv::OpcodeRdRmRs1::FcvtWQ | riscv::OpcodeRdRmRs1::FcvtWuQ => {
+                        (self.read_freg_f8(data.rs1), flexfloat::FfOpCvt::Fcvt8f2f)
+                    }
+                    _ => (self.read_freg_f16(data.rs1), flexfloat::FfOpCvt::Fcvt16f2f),
+                };
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
                     rs1,
-                    flexfloat::FfOpCvt::Fcvtf2w,
-                    fpmode_src,
-                    fpmode_dst,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
                 );
-                self.write_reg(data.rd, value);
-            }
-            riscv::OpcodeRdRmRs1::FcvtWH => {
-                let (fpmode_src, fpmode_dst) = self.read_fpmode();
-                let rs1 = self.read_freg(data.rs1);
-                let value = self.emit_fp16_op_cvt_from_f(
-                    rs1,
-                    flexfloat::FfOpCvt::Fcvtf2w,
-                    fpmode_src,
-                    fpmode_dst,
+                let rs1 = self.emit_fp64_op_cvt_to_f(rs1, widen, fpmode_src, fpmode_dst);
+                let op = match data.op {
+                    riscv::OpcodeRdRmRs1::FcvtWQ | riscv::OpcodeRdRmRs1::FcvtWH => FpOp::CvtToW,
+                    _ => FpOp::CvtToWu,
+                };
+                let value = self.emit_exact_fp(op, data.rm, true, &[rs1]);
+                let value = LLVMBuildTrunc(
+                    self.builder,
+                    value,
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    NONAME,
                 );
-                let value = LLVMBuildZExt(self.builder, value, LLVMInt32Type(), NONAME);
                 self.write_reg(data.rd, value);
             }
-            riscv::OpcodeRdRmRs1::FcvtWS => {
+            riscv::OpcodeRdRmRs1::FcvtDS => {
                 let rs1 = self.read_freg_f32(data.rs1, true);
-                let value = LLVMBuildFPToSI(self.builder, rs1, LLVMInt32Type(), name);
-                self.write_reg(data.rd, value);
-            }
-            riscv::OpcodeRdRmRs1::FcvtWD => {
-                let rs1 = self.read_freg_f64(data.rs1, true);
-                let value = LLVMBuildFPToSI(self.builder, rs1, LLVMInt32Type(), name);
-                self.write_reg(data.rd, value);
-            }
-            riscv::OpcodeRdRmRs1::FcvtWuQ => {
-                let (fpmode_src, fpmode_dst) = self.read_fpmode();
-                let rs1 = self.read_freg(data.rs1);
-                let value = self.emit_fp8_op_cvt_from_f(
-                    rs1,
-                    flexfloat::FfOpCvt::Fcvtf2wu,
-                    fpmode_src,
-                    fpmode_dst,
-                );
-                self.write_reg(data.rd, value);
-            }
-            riscv::OpcodeRdRmRs1::FcvtWuH => {
-                let (fpmode_src, fpmode_dst) = self.read_fpmode();
-                let rs1 = self.read_freg(data.rs1);
-                let value = self.emit_fp16_op_cvt_from_f(
+                let value = LLVMBuildFPCast(
+                    self.builder,
                     rs1,
-                    flexfloat::FfOpCvt::Fcvtf2wu,
-                    fpmode_src,
-                    fpmode_dst,
+                    LLVMDoubleTypeInContext(self.section.engine.context),
+                    NONAME,
                 );
-                let value = LLVMBuildZExt(self.builder, value, LLVMInt32Type(), NONAME);
-                self.write_reg(data.rd, value);
-            }
-            riscv::OpcodeRdRmRs1::FcvtWuS => {
-                let rs1 = self.read_freg_f32(data.rs1, true);
-                let value = LLVMBuildFPToUI(self.builder, rs1, LLVMInt32Type(), name);
-                self.write_reg(data.rd, value);
-            }
-            riscv::OpcodeRdRmRs1::FcvtWuD => {
-                let rs1 = self.read_freg_f64(data.rs1, true);
-                let value = LLVMBuildFPToUI(self.builder, rs1, LLVMInt32Type(), name);
-                self.write_reg(data.rd, value);
-            }
-            riscv::OpcodeRdRmRs1::FcvtDS => {
-                let rs1 = self.read_freg_f32(data.rs1, true);
-                let value = LLVMBuildFPCast(self.builder, rs1, LLVMDoubleType(), name);
-                self.write_freg_f64(data.rd, value, true);
+                self.write_freg_f64(data.rd, self.emit_canonical_nan(value, name), true);
             }
             riscv::OpcodeRdRmRs1::FcvtSD => {
                 let rs1 = self.read_freg_f64(data.rs1, true);
-                let value = LLVMBuildFPCast(self.builder, rs1, LLVMFloatType(), name);
-                self.write_freg_f32(data.rd, value, true);
+                let value = LLVMBuildFPCast(
+                    self.builder,
+                    rs1,
+                    LLVMFloatTypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                self.write_freg_f32(data.rd, self.emit_canonical_nan(value, name), true);
             }
             riscv::OpcodeRdRmRs1::FcvtSQ => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_freg_f8(data.rs1);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp32_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fcvt8f2f,
                     fpmode_src,
                     fpmode_dst,
                 );
-                let value = LLVMBuildBitCast(self.builder, value, LLVMFloatType(), name);
+                let value = LLVMBuildBitCast(
+                    self.builder,
+                    value,
+                    LLVMFloatTypeInContext(self.section.engine.context),
+                    name,
+                );
                 self.write_freg_f32(data.rd, value, true);
             }
             riscv::OpcodeRdRmRs1::FcvtDQ => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_freg_f8(data.rs1);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp64_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fcvt8f2f,
@@ -2053,7 +3432,12 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeRdRmRs1::FcvtBH => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_freg_f16(data.rs1);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp8_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fcvt16f2f,
@@ -2064,32 +3448,87 @@ impl<'a> InstructionTranslator<'a> {
             }
             riscv::OpcodeRdRmRs1::FcvtQS => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
-                let rs1 = self.read_freg(data.rs1);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = self.read_freg_f32(data.rs1, false);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp8_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fcvt32f2f,
                     fpmode_src,
                     fpmode_dst,
                 );
-                self.write_freg_f8(data.rd, value);
-            }
-            riscv::OpcodeRdRmRs1::FcvtQD => {
-                let (fpmode_src, fpmode_dst) = self.read_fpmode();
-                let rs1 = self.read_freg_f64(data.rs1, false);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
-                let value = self.emit_fp8_op_cvt_to_f(
+                self.write_freg_f8(data.rd, value);
+            }
+            riscv::OpcodeRdRmRs1::FcvtQD => {
+                let (fpmode_src, fpmode_dst) = self.read_fpmode();
+                let rs1 = self.read_freg_f64(data.rs1, false);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let value = self.emit_fp8_op_cvt_to_f(
+                    rs1,
+                    flexfloat::FfOpCvt::Fcvt64f2f,
+                    fpmode_src,
+                    fpmode_dst,
+                );
+                self.write_freg_f8(data.rd, value);
+            }
+            riscv::OpcodeRdRmRs1::FcvtSH => {
+                let (fpmode_src, fpmode_dst) = self.read_fpmode();
+                let rs1 = self.read_freg_f16(data.rs1);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let value = self.emit_fp32_op_cvt_to_f(
+                    rs1,
+                    flexfloat::FfOpCvt::Fcvt16f2f,
+                    fpmode_src,
+                    fpmode_dst,
+                );
+                let value = LLVMBuildBitCast(
+                    self.builder,
+                    value,
+                    LLVMFloatTypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                self.write_freg_f32(data.rd, value, true);
+            }
+            riscv::OpcodeRdRmRs1::FcvtDH => {
+                let (fpmode_src, fpmode_dst) = self.read_fpmode();
+                let rs1 = self.read_freg_f16(data.rs1);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let value = self.emit_fp64_op_cvt_to_f(
                     rs1,
-                    flexfloat::FfOpCvt::Fcvt64f2f,
+                    flexfloat::FfOpCvt::Fcvt16f2f,
                     fpmode_src,
                     fpmode_dst,
                 );
-                self.write_freg_f8(data.rd, value);
+                self.write_freg(data.rd, value);
             }
             riscv::OpcodeRdRmRs1::FcvtHS => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
-                let rs1 = self.read_freg(data.rs1);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = self.read_freg_f32(data.rs1, false);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp16_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fcvt32f2f,
@@ -2101,7 +3540,12 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeRdRmRs1::FcvtHD => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_freg(data.rs1);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp16_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fcvt64f2f,
@@ -2113,7 +3557,12 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeRdRmRs1::FsqrtD => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_freg_f64(data.rs1, false);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp64_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fsqrt,
@@ -2125,7 +3574,12 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeRdRmRs1::FsqrtS => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_freg_f32(data.rs1, false);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp32_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fsqrt,
@@ -2137,7 +3591,12 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeRdRmRs1::FsqrtH => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_freg_f16(data.rs1);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp16_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fsqrt,
@@ -2149,7 +3608,12 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeRdRmRs1::FsqrtQ => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_freg_f8(data.rs1);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp8_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fsqrt,
@@ -2164,41 +3628,19 @@ impl<'a> InstructionTranslator<'a> {
     }
 
     unsafe fn emit_rd_rs2(&self, data: riscv::FormatRdRs2) -> Result<()> {
-        trace!("{} x{}, f{}", data.op, data.rd, data.rs2);
+        trace!(target: log_target::TRANSLATE, "{} x{}, f{}", data.op, data.rd, data.rs2);
         let rs2 = self.read_reg(data.rs2);
 
         let value = match data.op {
             riscv::OpcodeRdRs2::Scfgr => {
-                // reorder rs2 to form address
-                // rs2[11:5]=reg_word rs2[4:0]=dm -> addr_off = {dm, reg_word[4:0], 000}
-                let reg = LLVMBuildLShr(
-                    self.builder,
-                    rs2,
-                    LLVMConstInt(LLVMInt32Type(), 2 as u64, 0),
-                    NONAME,
-                );
-                let reg_masked = LLVMBuildAnd(
-                    self.builder,
-                    reg,
-                    LLVMConstInt(LLVMInt32Type(), 0xf8 as u64, 0),
-                    NONAME,
-                );
-                let rs2_dm = LLVMBuildAnd(
-                    self.builder,
-                    rs2,
-                    LLVMConstInt(LLVMInt32Type(), 0x1f as u64, 0),
-                    NONAME,
-                );
-                let rs2_dm_shifted = LLVMBuildShl(
-                    self.builder,
-                    rs2_dm,
-                    LLVMConstInt(LLVMInt32Type(), 8 as u64, 0),
-                    NONAME,
-                );
-                let addr_off = LLVMBuildOr(self.builder, rs2_dm_shifted, reg_masked, NONAME);
+                let addr_off = self.emit_ssr_cfg_offset(rs2);
                 // perform load
                 self.emit_load(
-                    LLVMConstInt(LLVMInt32Type(), SSR_BASE, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        SSR_BASE,
+                        0,
+                    ),
                     addr_off,
                     2,
                     true,
@@ -2206,7 +3648,7 @@ impl<'a> InstructionTranslator<'a> {
             }
             riscv::OpcodeRdRs2::Dmstat => self
                 .section
-                .emit_call("banshee_dma_stat", [self.dma_ptr(), rs2]),
+                .emit_call("banshee_dma_stat", [self.section.state_ptr, rs2]),
             // _ => bail!("Unsupported opcode {}", data.op),
         };
 
@@ -2216,10 +3658,40 @@ impl<'a> InstructionTranslator<'a> {
 
     unsafe fn emit_rd_rm_rs1_rs2(&self, data: riscv::FormatRdRmRs1Rs2) -> Result<()> {
         self.was_freppable.set(true);
-        trace!("{} f{} = f{}, f{}", data.op, data.rd, data.rs1, data.rs2);
+        trace!(
+            target: log_target::TRANSLATE,
+            "{} f{} = f{}, f{}",
+            data.op,
+            data.rd,
+            data.rs1,
+            data.rs2
+        );
         let name = format!("{}\0", data.op);
         let name = name.as_ptr() as *const _;
         self.was_freppable.set(true);
+        if self.section.elf.exact_fp {
+            let exact = match data.op {
+                riscv::OpcodeRdRmRs1Rs2::FaddS => Some((FpOp::Add, false)),
+                riscv::OpcodeRdRmRs1Rs2::FsubS => Some((FpOp::Sub, false)),
+                riscv::OpcodeRdRmRs1Rs2::FmulS => Some((FpOp::Mul, false)),
+                riscv::OpcodeRdRmRs1Rs2::FdivS => Some((FpOp::Div, false)),
+                riscv::OpcodeRdRmRs1Rs2::FaddD => Some((FpOp::Add, true)),
+                riscv::OpcodeRdRmRs1Rs2::FsubD => Some((FpOp::Sub, true)),
+                riscv::OpcodeRdRmRs1Rs2::FmulD => Some((FpOp::Mul, true)),
+                riscv::OpcodeRdRmRs1Rs2::FdivD => Some((FpOp::Div, true)),
+                _ => None,
+            };
+            if let Some((op, double)) = exact {
+                let value = self.emit_exact_fp(
+                    op,
+                    data.rm,
+                    double,
+                    &[self.read_freg(data.rs1), self.read_freg(data.rs2)],
+                );
+                self.write_freg(data.rd, value);
+                return Ok(());
+            }
+        }
         match data.op {
             // FloatB
             riscv::OpcodeRdRmRs1Rs2::FaddQ => {
@@ -2327,86 +3799,78 @@ impl<'a> InstructionTranslator<'a> {
                     ),
                 )
             }
-            riscv::OpcodeRdRmRs1Rs2::FaddS => self.write_freg_f32(
-                data.rd,
-                LLVMBuildFAdd(
+            riscv::OpcodeRdRmRs1Rs2::FaddS => {
+                let value = LLVMBuildFAdd(
                     self.builder,
                     self.read_freg_f32(data.rs1, true),
                     self.read_freg_f32(data.rs2, true),
-                    name,
-                ),
-                true,
-            ),
-            riscv::OpcodeRdRmRs1Rs2::FsubS => self.write_freg_f32(
-                data.rd,
-                LLVMBuildFSub(
+                    NONAME,
+                );
+                self.write_freg_f32(data.rd, self.emit_canonical_nan(value, name), true);
+            }
+            riscv::OpcodeRdRmRs1Rs2::FsubS => {
+                let value = LLVMBuildFSub(
                     self.builder,
                     self.read_freg_f32(data.rs1, true),
                     self.read_freg_f32(data.rs2, true),
-                    name,
-                ),
-                true,
-            ),
-            riscv::OpcodeRdRmRs1Rs2::FmulS => self.write_freg_f32(
-                data.rd,
-                LLVMBuildFMul(
+                    NONAME,
+                );
+                self.write_freg_f32(data.rd, self.emit_canonical_nan(value, name), true);
+            }
+            riscv::OpcodeRdRmRs1Rs2::FmulS => {
+                let value = LLVMBuildFMul(
                     self.builder,
                     self.read_freg_f32(data.rs1, true),
                     self.read_freg_f32(data.rs2, true),
-                    name,
-                ),
-                true,
-            ),
-            riscv::OpcodeRdRmRs1Rs2::FdivS => self.write_freg_f32(
-                data.rd,
-                LLVMBuildFDiv(
+                    NONAME,
+                );
+                self.write_freg_f32(data.rd, self.emit_canonical_nan(value, name), true);
+            }
+            riscv::OpcodeRdRmRs1Rs2::FdivS => {
+                let value = LLVMBuildFDiv(
                     self.builder,
                     self.read_freg_f32(data.rs1, true),
                     self.read_freg_f32(data.rs2, true),
-                    name,
-                ),
-                true,
-            ),
-            riscv::OpcodeRdRmRs1Rs2::FaddD => self.write_freg_f64(
-                data.rd,
-                LLVMBuildFAdd(
+                    NONAME,
+                );
+                self.write_freg_f32(data.rd, self.emit_canonical_nan(value, name), true);
+            }
+            riscv::OpcodeRdRmRs1Rs2::FaddD => {
+                let value = LLVMBuildFAdd(
                     self.builder,
                     self.read_freg_f64(data.rs1, true),
                     self.read_freg_f64(data.rs2, true),
-                    name,
-                ),
-                true,
-            ),
-            riscv::OpcodeRdRmRs1Rs2::FsubD => self.write_freg_f64(
-                data.rd,
-                LLVMBuildFSub(
+                    NONAME,
+                );
+                self.write_freg_f64(data.rd, self.emit_canonical_nan(value, name), true);
+            }
+            riscv::OpcodeRdRmRs1Rs2::FsubD => {
+                let value = LLVMBuildFSub(
                     self.builder,
                     self.read_freg_f64(data.rs1, true),
                     self.read_freg_f64(data.rs2, true),
-                    name,
-                ),
-                true,
-            ),
-            riscv::OpcodeRdRmRs1Rs2::FmulD => self.write_freg_f64(
-                data.rd,
-                LLVMBuildFMul(
+                    NONAME,
+                );
+                self.write_freg_f64(data.rd, self.emit_canonical_nan(value, name), true);
+            }
+            riscv::OpcodeRdRmRs1Rs2::FmulD => {
+                let value = LLVMBuildFMul(
                     self.builder,
                     self.read_freg_f64(data.rs1, true),
                     self.read_freg_f64(data.rs2, true),
-                    name,
-                ),
-                true,
-            ),
-            riscv::OpcodeRdRmRs1Rs2::FdivD => self.write_freg_f64(
-                data.rd,
-                LLVMBuildFDiv(
+                    NONAME,
+                );
+                self.write_freg_f64(data.rd, self.emit_canonical_nan(value, name), true);
+            }
+            riscv::OpcodeRdRmRs1Rs2::FdivD => {
+                let value = LLVMBuildFDiv(
                     self.builder,
                     self.read_freg_f64(data.rs1, true),
                     self.read_freg_f64(data.rs2, true),
-                    name,
-                ),
-                true,
-            ),
+                    NONAME,
+                );
+                self.write_freg_f64(data.rd, self.emit_canonical_nan(value, name), true);
+            }
             riscv::OpcodeRdRmRs1Rs2::FmulexSH => {
                 let (fpmode_src, _fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_freg_f16(data.rs1);
@@ -2471,6 +3935,7 @@ impl<'a> InstructionTranslator<'a> {
     unsafe fn emit_rd_rm_rs1_rs2_rs3(&self, data: riscv::FormatRdRmRs1Rs2Rs3) -> Result<()> {
         self.was_freppable.set(true);
         trace!(
+            target: log_target::TRANSLATE,
             "{} f{} = f{}, f{}, f{}",
             data.op,
             data.rd,
@@ -2478,6 +3943,33 @@ impl<'a> InstructionTranslator<'a> {
             data.rs2,
             data.rs3
         );
+        if self.section.elf.exact_fp {
+            let exact = match data.op {
+                riscv::OpcodeRdRmRs1Rs2Rs3::FmaddS => Some((FpOp::Madd, false)),
+                riscv::OpcodeRdRmRs1Rs2Rs3::FmsubS => Some((FpOp::Msub, false)),
+                riscv::OpcodeRdRmRs1Rs2Rs3::FnmsubS => Some((FpOp::Nmsub, false)),
+                riscv::OpcodeRdRmRs1Rs2Rs3::FnmaddS => Some((FpOp::Nmadd, false)),
+                riscv::OpcodeRdRmRs1Rs2Rs3::FmaddD => Some((FpOp::Madd, true)),
+                riscv::OpcodeRdRmRs1Rs2Rs3::FmsubD => Some((FpOp::Msub, true)),
+                riscv::OpcodeRdRmRs1Rs2Rs3::FnmsubD => Some((FpOp::Nmsub, true)),
+                riscv::OpcodeRdRmRs1Rs2Rs3::FnmaddD => Some((FpOp::Nmadd, true)),
+                _ => None,
+            };
+            if let Some((op, double)) = exact {
+                let value = self.emit_exact_fp(
+                    op,
+                    data.rm,
+                    double,
+                    &[
+                        self.read_freg(data.rs1),
+                        self.read_freg(data.rs2),
+                        self.read_freg(data.rs3),
+                    ],
+                );
+                self.write_freg(data.rd, value);
+                return Ok(());
+            }
+        }
         match data.op {
             riscv::OpcodeRdRmRs1Rs2Rs3::FmaddH
             | riscv::OpcodeRdRmRs1Rs2Rs3::FmsubH
@@ -2531,6 +4023,7 @@ impl<'a> InstructionTranslator<'a> {
         fseq: &mut SequencerContext,
     ) -> Result<()> {
         trace!(
+            target: log_target::TRANSLATE,
             "{} x{}, {}, 0b{:b}, {}",
             data.op,
             data.rs1,          // register containing max repetition
@@ -2541,7 +4034,7 @@ impl<'a> InstructionTranslator<'a> {
         // Initialize repetition iterator to 0
         LLVMBuildStore(
             self.builder,
-            LLVMConstInt(LLVMInt32Type(), 0, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0, 0),
             self.section.fseq_iter.rpt_ptr_ref,
         );
         // Initialize repetition bound
@@ -2573,15 +4066,33 @@ impl<'a> InstructionTranslator<'a> {
     /// get fpmode_src and fpmode_dst bits for fpmode CSR
     unsafe fn read_fpmode(&self) -> (LLVMValueRef, LLVMValueRef) {
         let fpmode_csr = self.read_csr(0x7c1);
-        let const1 = LLVMConstInt(LLVMInt32Type(), 1 as u64, 0);
-        let const2 = LLVMConstInt(LLVMInt32Type(), 2 as u64, 0);
+        let const1 = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            1 as u64,
+            0,
+        );
+        let const2 = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            2 as u64,
+            0,
+        );
         // get fpmode_src
         let fpmode_src = LLVMBuildAnd(self.builder, fpmode_csr, const1, NONAME);
-        let fpmode_src = LLVMBuildIntCast(self.builder, fpmode_src, LLVMInt1Type(), NONAME);
+        let fpmode_src = LLVMBuildIntCast(
+            self.builder,
+            fpmode_src,
+            LLVMInt1TypeInContext(self.section.engine.context),
+            NONAME,
+        );
         // get fpmode_dst
         let fpmode_dst = LLVMBuildAnd(self.builder, fpmode_csr, const2, NONAME);
         let fpmode_dst = LLVMBuildLShr(self.builder, fpmode_dst, const1, NONAME);
-        let fpmode_dst = LLVMBuildIntCast(self.builder, fpmode_dst, LLVMInt1Type(), NONAME);
+        let fpmode_dst = LLVMBuildIntCast(
+            self.builder,
+            fpmode_dst,
+            LLVMInt1TypeInContext(self.section.engine.context),
+            NONAME,
+        );
         (fpmode_src, fpmode_dst)
     }
 
@@ -2596,7 +4107,11 @@ impl<'a> InstructionTranslator<'a> {
     ) -> LLVMValueRef {
         // Encode the operation
         let op_value: u8 = std::mem::transmute(op as u8);
-        let op = LLVMConstInt(LLVMInt8Type(), op_value as u64, 0);
+        let op = LLVMConstInt(
+            LLVMInt8TypeInContext(self.section.engine.context),
+            op_value as u64,
+            0,
+        );
         let rd = self.section.emit_call_with_name(
             "banshee_fp8_op",
             [rs1, rs2, rs3, op, fpmode_dst],
@@ -2616,7 +4131,11 @@ impl<'a> InstructionTranslator<'a> {
     ) -> LLVMValueRef {
         // Encode the operation
         let op_value: u8 = std::mem::transmute(op as u8);
-        let op = LLVMConstInt(LLVMInt8Type(), op_value as u64, 0);
+        let op = LLVMConstInt(
+            LLVMInt8TypeInContext(self.section.engine.context),
+            op_value as u64,
+            0,
+        );
         let rd = self.section.emit_call_with_name(
             "banshee_fp16_op",
             [rs1, rs2, rs3, op, fpmode_dst],
@@ -2635,7 +4154,11 @@ impl<'a> InstructionTranslator<'a> {
     ) -> LLVMValueRef {
         // Encode the operation
         let op_value: u8 = std::mem::transmute(op as u8);
-        let op = LLVMConstInt(LLVMInt8Type(), op_value as u64, 0);
+        let op = LLVMConstInt(
+            LLVMInt8TypeInContext(self.section.engine.context),
+            op_value as u64,
+            0,
+        );
         let rd = self.section.emit_call_with_name(
             "banshee_fp8_op_cmp",
             [rs1, rs2, op, fpmode_dst],
@@ -2654,7 +4177,11 @@ impl<'a> InstructionTranslator<'a> {
     ) -> LLVMValueRef {
         // Encode the operation
         let op_value: u8 = std::mem::transmute(op as u8);
-        let op = LLVMConstInt(LLVMInt8Type(), op_value as u64, 0);
+        let op = LLVMConstInt(
+            LLVMInt8TypeInContext(self.section.engine.context),
+            op_value as u64,
+            0,
+        );
         let rd = self.section.emit_call_with_name(
             "banshee_fp16_op_cmp",
             [rs1, rs2, op, fpmode_dst],
@@ -2663,25 +4190,6 @@ impl<'a> InstructionTranslator<'a> {
         rd
     }
 
-    /// emit fp8 or fp8fpmode_dst conversion instruction to flexfloat
-    unsafe fn emit_fp8_op_cvt_from_f(
-        &self,
-        rs1: LLVMValueRef,
-        op: flexfloat::FfOpCvt,
-        fpmode_src: LLVMValueRef,
-        fpmode_dst: LLVMValueRef,
-    ) -> LLVMValueRef {
-        // Encode the operation
-        let op_value: u8 = std::mem::transmute(op as u8);
-        let op = LLVMConstInt(LLVMInt8Type(), op_value as u64, 0);
-        let rd = self.section.emit_call_with_name(
-            "banshee_fp8_op_cvt_from_f",
-            [rs1, op, fpmode_src, fpmode_dst],
-            "fp8_op_cvt_from_f",
-        );
-        rd
-    }
-
     /// emit fp8 or fp8fpmode_dst conversion instruction to flexfloat
     unsafe fn emit_fp8_op_cvt_to_f(
         &self,
@@ -2692,7 +4200,11 @@ impl<'a> InstructionTranslator<'a> {
     ) -> LLVMValueRef {
         // Encode the operation
         let op_value: u8 = std::mem::transmute(op as u8);
-        let op = LLVMConstInt(LLVMInt8Type(), op_value as u64, 0);
+        let op = LLVMConstInt(
+            LLVMInt8TypeInContext(self.section.engine.context),
+            op_value as u64,
+            0,
+        );
         let rd = self.section.emit_call_with_name(
             "banshee_fp8_op_cvt_to_f",
             [rs1, op, fpmode_src, fpmode_dst],
@@ -2711,7 +4223,11 @@ impl<'a> InstructionTranslator<'a> {
     ) -> LLVMValueRef {
         // Encode the operation
         let op_value: u8 = std::mem::transmute(op as u8);
-        let op = LLVMConstInt(LLVMInt8Type(), op_value as u64, 0);
+        let op = LLVMConstInt(
+            LLVMInt8TypeInContext(self.section.engine.context),
+            op_value as u64,
+            0,
+        );
         let rd = self.section.emit_call_with_name(
             "banshee_fp16_op_cvt_to_f",
             [rs1, op, fpmode_src, fpmode_dst],
@@ -2720,25 +4236,6 @@ impl<'a> InstructionTranslator<'a> {
         rd
     }
 
-    /// emit fp16 or fp16alt conversion instruction to flexfloat
-    unsafe fn emit_fp16_op_cvt_from_f(
-        &self,
-        rs1: LLVMValueRef,
-        op: flexfloat::FfOpCvt,
-        fpmode_src: LLVMValueRef,
-        fpmode_dst: LLVMValueRef,
-    ) -> LLVMValueRef {
-        // Encode the operation
-        let op_value: u8 = std::mem::transmute(op as u8);
-        let op = LLVMConstInt(LLVMInt8Type(), op_value as u64, 0);
-        let rd = self.section.emit_call_with_name(
-            "banshee_fp16_op_cvt_from_f",
-            [rs1, op, fpmode_src, fpmode_dst],
-            "fp16_op_cvt_from_f",
-        );
-        rd
-    }
-
     /// emit fp32 conversion instruction to flexfloat
     unsafe fn emit_fp32_op_cvt_to_f(
         &self,
@@ -2749,7 +4246,11 @@ impl<'a> InstructionTranslator<'a> {
     ) -> LLVMValueRef {
         // Encode the operation
         let op_value: u8 = std::mem::transmute(op as u8);
-        let op = LLVMConstInt(LLVMInt8Type(), op_value as u64, 0);
+        let op = LLVMConstInt(
+            LLVMInt8TypeInContext(self.section.engine.context),
+            op_value as u64,
+            0,
+        );
         let rd = self.section.emit_call_with_name(
             "banshee_fp32_op_cvt_to_f",
             [rs1, op, fpmode_src, fpmode_dst],
@@ -2768,7 +4269,11 @@ impl<'a> InstructionTranslator<'a> {
     ) -> LLVMValueRef {
         // Encode the operation
         let op_value: u8 = std::mem::transmute(op as u8);
-        let op = LLVMConstInt(LLVMInt8Type(), op_value as u64, 0);
+        let op = LLVMConstInt(
+            LLVMInt8TypeInContext(self.section.engine.context),
+            op_value as u64,
+            0,
+        );
         let rd = self.section.emit_call_with_name(
             "banshee_fp64_op_cvt_to_f",
             [rs1, op, fpmode_src, fpmode_dst],
@@ -2865,63 +4370,135 @@ impl<'a> InstructionTranslator<'a> {
         res1: LLVMValueRef,
         res0: LLVMValueRef,
     ) -> LLVMValueRef {
-        let value = LLVMConstNull(LLVMInt32Type());
+        let value = LLVMConstNull(LLVMInt32TypeInContext(self.section.engine.context));
 
-        let res0_pad = LLVMBuildZExt(self.builder, res0, LLVMInt32Type(), NONAME);
-        let res1_pad = LLVMBuildZExt(self.builder, res1, LLVMInt32Type(), NONAME);
-        let res2_pad = LLVMBuildZExt(self.builder, res2, LLVMInt32Type(), NONAME);
-        let res3_pad = LLVMBuildZExt(self.builder, res3, LLVMInt32Type(), NONAME);
-        let res4_pad = LLVMBuildZExt(self.builder, res4, LLVMInt32Type(), NONAME);
-        let res5_pad = LLVMBuildZExt(self.builder, res5, LLVMInt32Type(), NONAME);
-        let res6_pad = LLVMBuildZExt(self.builder, res6, LLVMInt32Type(), NONAME);
-        let res7_pad = LLVMBuildZExt(self.builder, res7, LLVMInt32Type(), NONAME);
+        let res0_pad = LLVMBuildZExt(
+            self.builder,
+            res0,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let res1_pad = LLVMBuildZExt(
+            self.builder,
+            res1,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let res2_pad = LLVMBuildZExt(
+            self.builder,
+            res2,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let res3_pad = LLVMBuildZExt(
+            self.builder,
+            res3,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let res4_pad = LLVMBuildZExt(
+            self.builder,
+            res4,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let res5_pad = LLVMBuildZExt(
+            self.builder,
+            res5,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let res6_pad = LLVMBuildZExt(
+            self.builder,
+            res6,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let res7_pad = LLVMBuildZExt(
+            self.builder,
+            res7,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
 
         let res0_pad = LLVMBuildShl(
             self.builder,
             res0_pad,
-            LLVMConstInt(LLVMInt32Type(), 0 as u64, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                0 as u64,
+                0,
+            ),
             NONAME,
         );
         let res1_pad = LLVMBuildShl(
             self.builder,
             res1_pad,
-            LLVMConstInt(LLVMInt32Type(), 1 as u64, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                1 as u64,
+                0,
+            ),
             NONAME,
         );
         let res2_pad = LLVMBuildShl(
             self.builder,
             res2_pad,
-            LLVMConstInt(LLVMInt32Type(), 2 as u64, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                2 as u64,
+                0,
+            ),
             NONAME,
         );
         let res3_pad = LLVMBuildShl(
             self.builder,
             res3_pad,
-            LLVMConstInt(LLVMInt32Type(), 3 as u64, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                3 as u64,
+                0,
+            ),
             NONAME,
         );
         let res4_pad = LLVMBuildShl(
             self.builder,
             res4_pad,
-            LLVMConstInt(LLVMInt32Type(), 4 as u64, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                4 as u64,
+                0,
+            ),
             NONAME,
         );
         let res5_pad = LLVMBuildShl(
             self.builder,
             res5_pad,
-            LLVMConstInt(LLVMInt32Type(), 5 as u64, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                5 as u64,
+                0,
+            ),
             NONAME,
         );
         let res6_pad = LLVMBuildShl(
             self.builder,
             res6_pad,
-            LLVMConstInt(LLVMInt32Type(), 6 as u64, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                6 as u64,
+                0,
+            ),
             NONAME,
         );
         let res7_pad = LLVMBuildShl(
             self.builder,
             res7_pad,
-            LLVMConstInt(LLVMInt32Type(), 7 as u64, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                7 as u64,
+                0,
+            ),
             NONAME,
         );
 
@@ -2990,35 +4567,71 @@ impl<'a> InstructionTranslator<'a> {
         res1: LLVMValueRef,
         res0: LLVMValueRef,
     ) -> LLVMValueRef {
-        let value = LLVMConstNull(LLVMInt32Type());
+        let value = LLVMConstNull(LLVMInt32TypeInContext(self.section.engine.context));
 
-        let res0_pad = LLVMBuildZExt(self.builder, res0, LLVMInt32Type(), NONAME);
-        let res1_pad = LLVMBuildZExt(self.builder, res1, LLVMInt32Type(), NONAME);
-        let res2_pad = LLVMBuildZExt(self.builder, res2, LLVMInt32Type(), NONAME);
-        let res3_pad = LLVMBuildZExt(self.builder, res3, LLVMInt32Type(), NONAME);
+        let res0_pad = LLVMBuildZExt(
+            self.builder,
+            res0,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let res1_pad = LLVMBuildZExt(
+            self.builder,
+            res1,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let res2_pad = LLVMBuildZExt(
+            self.builder,
+            res2,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let res3_pad = LLVMBuildZExt(
+            self.builder,
+            res3,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
 
         let res0_pad = LLVMBuildShl(
             self.builder,
             res0_pad,
-            LLVMConstInt(LLVMInt32Type(), 0 as u64, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                0 as u64,
+                0,
+            ),
             NONAME,
         );
         let res1_pad = LLVMBuildShl(
             self.builder,
             res1_pad,
-            LLVMConstInt(LLVMInt32Type(), 1 as u64, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                1 as u64,
+                0,
+            ),
             NONAME,
         );
         let res2_pad = LLVMBuildShl(
             self.builder,
             res2_pad,
-            LLVMConstInt(LLVMInt32Type(), 2 as u64, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                2 as u64,
+                0,
+            ),
             NONAME,
         );
         let res3_pad = LLVMBuildShl(
             self.builder,
             res3_pad,
-            LLVMConstInt(LLVMInt32Type(), 3 as u64, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                3 as u64,
+                0,
+            ),
             NONAME,
         );
 
@@ -3074,7 +4687,11 @@ impl<'a> InstructionTranslator<'a> {
     ) -> LLVMValueRef {
         // Encode the operation
         let op_value: u8 = std::mem::transmute(op as u8);
-        let op = LLVMConstInt(LLVMInt8Type(), op_value as u64, 0);
+        let op = LLVMConstInt(
+            LLVMInt8TypeInContext(self.section.engine.context),
+            op_value as u64,
+            0,
+        );
         let rd = self.section.emit_call_with_name(
             "banshee_fp16_to_fp32_op",
             [rs1, rs2, rs3, op, fpmode_src],
@@ -3095,7 +4712,11 @@ impl<'a> InstructionTranslator<'a> {
     ) -> LLVMValueRef {
         // Encode the operation
         let op_value: u8 = std::mem::transmute(op as u8);
-        let op = LLVMConstInt(LLVMInt8Type(), op_value as u64, 0);
+        let op = LLVMConstInt(
+            LLVMInt8TypeInContext(self.section.engine.context),
+            op_value as u64,
+            0,
+        );
         let rd = self.section.emit_call_with_name(
             "banshee_fp8_to_fp16_op",
             [rs1, rs2, rs3, op, fpmode_src, fpmode_dst],
@@ -3115,7 +4736,11 @@ impl<'a> InstructionTranslator<'a> {
     ) -> LLVMValueRef {
         // Encode the operation
         let op_value: u8 = std::mem::transmute(op as u8);
-        let op = LLVMConstInt(LLVMInt8Type(), op_value as u64, 0);
+        let op = LLVMConstInt(
+            LLVMInt8TypeInContext(self.section.engine.context),
+            op_value as u64,
+            0,
+        );
         let rd = self.section.emit_call_with_name(
             "banshee_fp8_to_fp32_op",
             [rs1, rs2, rs3, op, fpmode_src],
@@ -3125,6 +4750,9 @@ impl<'a> InstructionTranslator<'a> {
     }
 
     /// emit fmadd instructions for non-flexfloat single and double precision float format
+    ///
+    /// The negated variants negate the product rather than the result, which
+    /// matters for the sign of zero results, and NaN results are canonical.
     unsafe fn emit_fmadd(
         &self,
         data: riscv::FormatRdRmRs1Rs2Rs3,
@@ -3134,54 +4762,183 @@ impl<'a> InstructionTranslator<'a> {
     ) -> Result<LLVMValueRef> {
         let name = format!("{}\0", data.op);
         let name = name.as_ptr() as *const _;
-        Ok(match data.op {
+        let neg = |x| LLVMBuildFNeg(self.builder, x, NONAME);
+        let (rs1, rs3) = match data.op {
             riscv::OpcodeRdRmRs1Rs2Rs3::FmaddS
             | riscv::OpcodeRdRmRs1Rs2Rs3::FmaddD
-            | riscv::OpcodeRdRmRs1Rs2Rs3::FmaddQ => LLVMBuildFAdd(
-                self.builder,
-                LLVMBuildFMul(self.builder, rs1, rs2, NONAME),
-                rs3,
-                name,
-            ),
+            | riscv::OpcodeRdRmRs1Rs2Rs3::FmaddQ => (rs1, rs3),
             riscv::OpcodeRdRmRs1Rs2Rs3::FmsubS
             | riscv::OpcodeRdRmRs1Rs2Rs3::FmsubD
-            | riscv::OpcodeRdRmRs1Rs2Rs3::FmsubQ => LLVMBuildFSub(
-                self.builder,
-                LLVMBuildFMul(self.builder, rs1, rs2, NONAME),
-                rs3,
-                name,
-            ),
-            riscv::OpcodeRdRmRs1Rs2Rs3::FnmaddS
-            | riscv::OpcodeRdRmRs1Rs2Rs3::FnmaddD
-            | riscv::OpcodeRdRmRs1Rs2Rs3::FnmaddQ => LLVMBuildFNeg(
-                self.builder,
-                LLVMBuildFAdd(
-                    self.builder,
-                    LLVMBuildFMul(self.builder, rs1, rs2, NONAME),
-                    rs3,
-                    NONAME,
-                ),
-                name,
-            ),
+            | riscv::OpcodeRdRmRs1Rs2Rs3::FmsubQ => (rs1, neg(rs3)),
             riscv::OpcodeRdRmRs1Rs2Rs3::FnmsubS
             | riscv::OpcodeRdRmRs1Rs2Rs3::FnmsubD
-            | riscv::OpcodeRdRmRs1Rs2Rs3::FnmsubQ => LLVMBuildFNeg(
-                self.builder,
-                LLVMBuildFSub(
-                    self.builder,
-                    LLVMBuildFMul(self.builder, rs1, rs2, NONAME),
-                    rs3,
-                    NONAME,
-                ),
-                name,
-            ),
+            | riscv::OpcodeRdRmRs1Rs2Rs3::FnmsubQ => (neg(rs1), rs3),
+            riscv::OpcodeRdRmRs1Rs2Rs3::FnmaddS
+            | riscv::OpcodeRdRmRs1Rs2Rs3::FnmaddD
+            | riscv::OpcodeRdRmRs1Rs2Rs3::FnmaddQ => (neg(rs1), neg(rs3)),
             riscv::OpcodeRdRmRs1Rs2Rs3::FmaddH
             | riscv::OpcodeRdRmRs1Rs2Rs3::FmsubH
             | riscv::OpcodeRdRmRs1Rs2Rs3::FnmaddH
             | riscv::OpcodeRdRmRs1Rs2Rs3::FnmsubH => {
                 bail!("Flexfloat opcode should not be decoded here: {}", data.op)
             }
-        })
+        };
+        let value = self.emit_intrinsic("llvm.fma", &[rs1, rs2, rs3]);
+        Ok(self.emit_canonical_nan(value, name))
+    }
+
+    /// Emit a floating-point comparison of two registers.
+    ///
+    /// Comparisons involving a NaN are false. `feq` raises the invalid
+    /// operation flag only for signaling NaNs, `flt` and `fle` for any NaN.
+    unsafe fn emit_fp_compare(
+        &self,
+        pred: llvm_sys::LLVMRealPredicate,
+        double: bool,
+        rs1: u32,
+        rs2: u32,
+        name: *const i8,
+    ) -> LLVMValueRef {
+        let (rs1, rs2, ty, bits) = if double {
+            (
+                self.read_freg_f64(rs1, false),
+                self.read_freg_f64(rs2, false),
+                LLVMDoubleTypeInContext(self.section.engine.context),
+                64,
+            )
+        } else {
+            (
+                self.read_freg_f32(rs1, false),
+                self.read_freg_f32(rs2, false),
+                LLVMFloatTypeInContext(self.section.engine.context),
+                32,
+            )
+        };
+        let int_ty = LLVMTypeOf(rs1);
+        let man_bits = if double { 52 } else { 23 };
+        let (rs1, rs2) = if self.section.elf.flush_to_zero {
+            let exp_mask = ((1 << (bits - 1 - man_bits)) - 1) << man_bits;
+            (
+                self.emit_flush_subnormal_bits(rs1, exp_mask, 1 << (bits - 1)),
+                self.emit_flush_subnormal_bits(rs2, exp_mask, 1 << (bits - 1)),
+            )
+        } else {
+            (rs1, rs2)
+        };
+        let exp_mask = LLVMConstInt(int_ty, ((1 << (bits - 1 - man_bits)) - 1) << man_bits, 0);
+        let abs_mask = LLVMConstInt(int_ty, (1 << (bits - 1)) - 1, 0);
+        let quiet = LLVMConstInt(int_ty, 1 << (man_bits - 1), 0);
+        let zero = LLVMConstInt(int_ty, 0, 0);
+        let is_nan = |value| {
+            let abs = LLVMBuildAnd(self.builder, value, abs_mask, NONAME);
+            LLVMBuildICmp(self.builder, LLVMIntUGT, abs, exp_mask, NONAME)
+        };
+        let is_snan = |value| {
+            let quiet_bit = LLVMBuildAnd(self.builder, value, quiet, NONAME);
+            let is_signaling = LLVMBuildICmp(self.builder, LLVMIntEQ, quiet_bit, zero, NONAME);
+            LLVMBuildAnd(self.builder, is_nan(value), is_signaling, NONAME)
+        };
+        let any_nan = LLVMBuildOr(self.builder, is_nan(rs1), is_nan(rs2), NONAME);
+        let invalid = if pred == LLVMRealOEQ {
+            LLVMBuildOr(self.builder, is_snan(rs1), is_snan(rs2), NONAME)
+        } else {
+            any_nan
+        };
+        self.emit_accrue_fflags(invalid, FFLAGS_NV);
+
+        // Do not rely on the comparison itself to order NaNs.
+        let cmp = LLVMBuildFCmp(
+            self.builder,
+            pred,
+            LLVMBuildBitCast(self.builder, rs1, ty, NONAME),
+            LLVMBuildBitCast(self.builder, rs2, ty, NONAME),
+            NONAME,
+        );
+        LLVMBuildSelect(
+            self.builder,
+            any_nan,
+            LLVMConstInt(LLVMInt1TypeInContext(self.section.engine.context), 0, 0),
+            cmp,
+            name,
+        )
+    }
+
+    /// Set the exception flags in `flags` if `cond` holds.
+    unsafe fn emit_accrue_fflags(&self, cond: LLVMValueRef, flags: u32) {
+        let ptr = self.fflags_ptr();
+        let old = LLVMBuildLoad(self.builder, ptr, NONAME);
+        let set = LLVMBuildOr(
+            self.builder,
+            old,
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                flags as u64,
+                0,
+            ),
+            NONAME,
+        );
+        let value = LLVMBuildSelect(self.builder, cond, set, old, NONAME);
+        LLVMBuildStore(self.builder, value, ptr);
+    }
+
+    /// Flush a subnormal `float` or `double` to zero of the same sign, if
+    /// `flush_to_zero` is enabled.
+    unsafe fn emit_flush_subnormal(&self, value: LLVMValueRef) -> LLVMValueRef {
+        if !self.section.elf.flush_to_zero {
+            return value;
+        }
+        let ty = LLVMTypeOf(value);
+        let (int_ty, exp_mask, sign_mask) = match LLVMGetTypeKind(ty) {
+            llvm_sys::LLVMTypeKind::LLVMDoubleTypeKind => (
+                LLVMInt64TypeInContext(self.section.engine.context),
+                0x7ff << 52,
+                1 << 63,
+            ),
+            _ => (
+                LLVMInt32TypeInContext(self.section.engine.context),
+                0xff << 23,
+                1 << 31,
+            ),
+        };
+        let raw = LLVMBuildBitCast(self.builder, value, int_ty, NONAME);
+        let raw = self.emit_flush_subnormal_bits(raw, exp_mask, sign_mask);
+        LLVMBuildBitCast(self.builder, raw, ty, NONAME)
+    }
+
+    /// Flush the raw bits of a subnormal to zero, given the mask of the
+    /// exponent and the mask of the bits to keep, i.e. the sign and the
+    /// NaN-boxing.
+    unsafe fn emit_flush_subnormal_bits(
+        &self,
+        raw: LLVMValueRef,
+        exp_mask: u64,
+        keep_mask: u64,
+    ) -> LLVMValueRef {
+        let ty = LLVMTypeOf(raw);
+        let exp = LLVMBuildAnd(self.builder, raw, LLVMConstInt(ty, exp_mask, 0), NONAME);
+        let is_subnormal =
+            LLVMBuildICmp(self.builder, LLVMIntEQ, exp, LLVMConstInt(ty, 0, 0), NONAME);
+        let zero = LLVMBuildAnd(self.builder, raw, LLVMConstInt(ty, keep_mask, 0), NONAME);
+        LLVMBuildSelect(self.builder, is_subnormal, zero, raw, NONAME)
+    }
+
+    /// Flush a raw single- or double-precision register value, which is
+    /// NaN-boxed in the former case, if `flush_to_zero` is enabled.
+    unsafe fn emit_flush_subnormal_reg(&self, raw: LLVMValueRef, double: bool) -> LLVMValueRef {
+        if !self.section.elf.flush_to_zero {
+            raw
+        } else if double {
+            self.emit_flush_subnormal_bits(raw, 0x7ff << 52, 1 << 63)
+        } else {
+            self.emit_flush_subnormal_bits(raw, 0xff << 23, !0x7fff_ffff)
+        }
+    }
+
+    /// Replace a NaN result by the canonical NaN.
+    unsafe fn emit_canonical_nan(&self, value: LLVMValueRef, name: *const i8) -> LLVMValueRef {
+        let is_nan = LLVMBuildFCmp(self.builder, LLVMRealUNO, value, value, NONAME);
+        let nan = LLVMConstReal(LLVMTypeOf(value), f64::NAN);
+        LLVMBuildSelect(self.builder, is_nan, nan, value, name)
     }
     unsafe fn emit_fmadd_flexfloat(
         &self,
@@ -3238,92 +4995,95 @@ impl<'a> InstructionTranslator<'a> {
     }
 
     unsafe fn emit_rd_rs1(&self, data: riscv::FormatRdRs1) -> Result<()> {
-        trace!("{} x{} = x{}", data.op, data.rd, data.rs1);
+        trace!(target: log_target::TRANSLATE, "{} x{} = x{}", data.op, data.rd, data.rs1);
         let name = format!("{}\0", data.op);
         let name = name.as_ptr() as *const _;
 
         // Handle floating-point operations
         match data.op {
             riscv::OpcodeRdRs1::FmvXW => {
-                // float (rs1) to integer (rd) register, bits are not modified
-                let rs1 = self.read_freg_f32(data.rs1, true);
-                // cast the integer reg pointer to a float pointer
-                let raw_ptr = self.reg_ptr(data.rd);
-                let ptr = LLVMBuildBitCast(
-                    self.builder,
-                    raw_ptr,
-                    LLVMPointerType(LLVMFloatType(), 0),
-                    NONAME,
-                );
-                // build the actual store and add trace
-                LLVMBuildStore(self.builder, rs1, ptr);
-                self.trace_access(
-                    TraceAccess::WriteReg(data.rd as u8),
-                    LLVMBuildLoad(self.builder, raw_ptr, NONAME),
-                );
+                // float (rs1) to integer (rd) register, bits are not modified;
+                // as a transfer, this does not check the NaN-boxing
+                let rs1 = self.read_freg_f32_bits(data.rs1);
+                self.write_reg(data.rd, rs1);
                 return Ok(());
             }
             riscv::OpcodeRdRs1::FmvWX => {
                 // integer (rs1) to float (rd) register, bits are not modified
                 let rs1 = self.read_reg(data.rs1);
-                // cast the float reg pointer to an integer pointer
-                let raw_ptr = self.freg_ptr(data.rd);
-                let ptr = LLVMBuildBitCast(
-                    self.builder,
-                    raw_ptr,
-                    LLVMPointerType(LLVMInt32Type(), 0),
-                    NONAME,
-                );
-                // pad left bits with 1
-                LLVMBuildStore(
-                    self.builder,
-                    LLVMConstInt(LLVMInt64Type(), -1i32 as u64, 0),
-                    raw_ptr,
-                );
-                // build the actual store and add trace
-                LLVMBuildStore(self.builder, rs1, ptr);
-                self.trace_access(
-                    TraceAccess::WriteF32Reg(data.rd as u8),
-                    LLVMBuildLoad(self.builder, raw_ptr, NONAME),
+                self.write_freg_f32(data.rd, rs1, false);
+                return Ok(());
+            }
+            riscv::OpcodeRdRs1::FclassS
+            | riscv::OpcodeRdRs1::FclassD
+            | riscv::OpcodeRdRs1::FclassH
+            | riscv::OpcodeRdRs1::FclassQ => {
+                let (exp_bits, man_bits) = match data.op {
+                    riscv::OpcodeRdRs1::FclassS => (8, 23),
+                    riscv::OpcodeRdRs1::FclassD => (11, 52),
+                    riscv::OpcodeRdRs1::FclassH => (5, 10),
+                    _ => (5, 2),
+                };
+                let rs1 = self.read_freg(data.rs1);
+                let value = self.section.emit_call_with_name(
+                    "banshee_fclass",
+                    [
+                        rs1,
+                        LLVMConstInt(
+                            LLVMInt32TypeInContext(self.section.engine.context),
+                            exp_bits,
+                            0,
+                        ),
+                        LLVMConstInt(
+                            LLVMInt32TypeInContext(self.section.engine.context),
+                            man_bits,
+                            0,
+                        ),
+                    ],
+                    "fclass",
                 );
+                self.write_reg(data.rd, value);
                 return Ok(());
             }
             riscv::OpcodeRdRs1::FmvXH => {
-                // float (rs1) to integer (rd) register, bits are not modified
+                // float (rs1) to integer (rd) register, sign-extending the
+                // half-precision bits
                 let rs1 = self.read_freg_f16(data.rs1);
-                // cast the integer reg pointer to a float pointer
-                let raw_ptr = self.reg_ptr(data.rd);
-                let ptr = LLVMBuildBitCast(
+                let value = LLVMBuildSExt(
                     self.builder,
-                    raw_ptr,
-                    LLVMPointerType(LLVMInt16Type(), 0),
+                    rs1,
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
-                // build the actual store and add trace
-                LLVMBuildStore(self.builder, rs1, ptr);
-                self.trace_access(
-                    TraceAccess::WriteReg(data.rd as u8),
-                    LLVMBuildLoad(self.builder, raw_ptr, NONAME),
-                );
+                self.write_reg(data.rd, value);
                 return Ok(());
             }
             riscv::OpcodeRdRs1::FmvHX => {
                 // integer (rs1) to float (rd) register, bits are not modified
                 let rs1 = self.read_reg(data.rs1);
 
-                let rs1 = LLVMBuildIntCast(self.builder, rs1, LLVMInt16Type(), NONAME);
+                let rs1 = LLVMBuildIntCast(
+                    self.builder,
+                    rs1,
+                    LLVMInt16TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 // cast the float reg pointer to an integer pointer
                 let raw_ptr = self.freg_ptr(data.rd);
                 let ptr = LLVMBuildBitCast(
                     self.builder,
                     raw_ptr,
-                    LLVMPointerType(LLVMInt16Type(), 0),
+                    LLVMPointerType(LLVMInt16TypeInContext(self.section.engine.context), 0),
                     NONAME,
                 );
                 // pad left bits with 1
                 LLVMBuildStore(
                     self.builder,
-                    LLVMConstInt(LLVMInt64Type(), -1i32 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt64TypeInContext(self.section.engine.context),
+                        -1i32 as u64,
+                        0,
+                    ),
                     raw_ptr,
                 );
                 // build the actual store and add trace
@@ -3337,7 +5097,12 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeRdRs1::FcvtBB => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_freg_f8(data.rs1);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp8_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fcvt8f2f,
@@ -3349,7 +5114,12 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeRdRs1::FcvtHB => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let rs1 = self.read_freg_f16(data.rs1);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
+                let rs1 = LLVMBuildZExt(
+                    self.builder,
+                    rs1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let value = self.emit_fp16_op_cvt_to_f(
                     rs1,
                     flexfloat::FfOpCvt::Fcvt8f2f,
@@ -3358,36 +5128,21 @@ impl<'a> InstructionTranslator<'a> {
                 );
                 self.write_freg_f16(data.rd, value);
             }
-            riscv::OpcodeRdRs1::FcvtSH => {
-                let (fpmode_src, fpmode_dst) = self.read_fpmode();
-                let rs1 = self.read_freg_f16(data.rs1);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
-                let value = self.emit_fp32_op_cvt_to_f(
-                    rs1,
-                    flexfloat::FfOpCvt::Fcvt16f2f,
-                    fpmode_src,
-                    fpmode_dst,
-                );
-                let value = LLVMBuildBitCast(self.builder, value, LLVMFloatType(), NONAME);
-                self.write_freg_f32(data.rd, value, true);
-            }
-            riscv::OpcodeRdRs1::FcvtDH => {
-                let (fpmode_src, fpmode_dst) = self.read_fpmode();
-                let rs1 = self.read_freg_f16(data.rs1);
-                let rs1 = LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME);
-                let value = self.emit_fp64_op_cvt_to_f(
-                    rs1,
-                    flexfloat::FfOpCvt::Fcvt16f2f,
-                    fpmode_src,
-                    fpmode_dst,
-                );
-                self.write_freg(data.rd, value);
-            }
             riscv::OpcodeRdRs1::VfsqrtS => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let (a1, a0) = self.read_freg_vf64s(data.rs1, false);
-                let a0 = LLVMBuildZExt(self.builder, a0, LLVMInt64Type(), NONAME);
-                let a1 = LLVMBuildZExt(self.builder, a1, LLVMInt64Type(), NONAME);
+                let a0 = LLVMBuildZExt(
+                    self.builder,
+                    a0,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let a1 = LLVMBuildZExt(
+                    self.builder,
+                    a1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let res0 = self.emit_fp32_op_cvt_to_f(
                     a0,
                     flexfloat::FfOpCvt::Fsqrt,
@@ -3405,10 +5160,30 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeRdRs1::VfsqrtH => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let (a3, a2, a1, a0) = self.read_freg_vf64h(data.rs1);
-                let a0 = LLVMBuildZExt(self.builder, a0, LLVMInt64Type(), NONAME);
-                let a1 = LLVMBuildZExt(self.builder, a1, LLVMInt64Type(), NONAME);
-                let a2 = LLVMBuildZExt(self.builder, a2, LLVMInt64Type(), NONAME);
-                let a3 = LLVMBuildZExt(self.builder, a3, LLVMInt64Type(), NONAME);
+                let a0 = LLVMBuildZExt(
+                    self.builder,
+                    a0,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let a1 = LLVMBuildZExt(
+                    self.builder,
+                    a1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let a2 = LLVMBuildZExt(
+                    self.builder,
+                    a2,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let a3 = LLVMBuildZExt(
+                    self.builder,
+                    a3,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let res0 = self.emit_fp16_op_cvt_to_f(
                     a0,
                     flexfloat::FfOpCvt::Fsqrt,
@@ -3438,14 +5213,54 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeRdRs1::VfsqrtB => {
                 let (fpmode_src, fpmode_dst) = self.read_fpmode();
                 let (a7, a6, a5, a4, a3, a2, a1, a0) = self.read_freg_vf64b(data.rs1);
-                let a0 = LLVMBuildZExt(self.builder, a0, LLVMInt64Type(), NONAME);
-                let a1 = LLVMBuildZExt(self.builder, a1, LLVMInt64Type(), NONAME);
-                let a2 = LLVMBuildZExt(self.builder, a2, LLVMInt64Type(), NONAME);
-                let a3 = LLVMBuildZExt(self.builder, a3, LLVMInt64Type(), NONAME);
-                let a4 = LLVMBuildZExt(self.builder, a4, LLVMInt64Type(), NONAME);
-                let a5 = LLVMBuildZExt(self.builder, a5, LLVMInt64Type(), NONAME);
-                let a6 = LLVMBuildZExt(self.builder, a6, LLVMInt64Type(), NONAME);
-                let a7 = LLVMBuildZExt(self.builder, a7, LLVMInt64Type(), NONAME);
+                let a0 = LLVMBuildZExt(
+                    self.builder,
+                    a0,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let a1 = LLVMBuildZExt(
+                    self.builder,
+                    a1,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let a2 = LLVMBuildZExt(
+                    self.builder,
+                    a2,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let a3 = LLVMBuildZExt(
+                    self.builder,
+                    a3,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let a4 = LLVMBuildZExt(
+                    self.builder,
+                    a4,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let a5 = LLVMBuildZExt(
+                    self.builder,
+                    a5,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let a6 = LLVMBuildZExt(
+                    self.builder,
+                    a6,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
+                let a7 = LLVMBuildZExt(
+                    self.builder,
+                    a7,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let res0 = self.emit_fp8_op_cvt_to_f(
                     a0,
                     flexfloat::FfOpCvt::Fsqrt,
@@ -3521,6 +5336,86 @@ impl<'a> InstructionTranslator<'a> {
                 self.write_freg_vf64h(data.rd, c3, c2, res1, res0);
                 return Ok(());
             }
+            riscv::OpcodeRdRs1::VfnsumS => {
+                let (a1, a0) = self.read_freg_vf64s(data.rs1, true);
+                let c0 = self.read_freg_f32(data.rd, true);
+                let res = LLVMBuildFSub(
+                    self.builder,
+                    c0,
+                    LLVMBuildFAdd(self.builder, a1, a0, name),
+                    name,
+                );
+                self.write_freg_f32(data.rd, res, true);
+                return Ok(());
+            }
+            riscv::OpcodeRdRs1::VfnsumH => {
+                let (a3, a2, a1, a0) = self.read_freg_vf64h(data.rs1);
+                let (c3, c2, c1, c0) = self.read_freg_vf64h(data.rd);
+                let (_fpmode_src, fpmode_dst) = self.read_fpmode();
+                let res0 = self.emit_fp16_op(a1, a0, c0, flexfloat::FlexfloatOp::Fadd, fpmode_dst);
+                let res1 = self.emit_fp16_op(a3, a2, c1, flexfloat::FlexfloatOp::Fadd, fpmode_dst);
+                let res0 =
+                    self.emit_fp16_op(c0, res0, c0, flexfloat::FlexfloatOp::Fsub, fpmode_dst);
+                let res1 =
+                    self.emit_fp16_op(c1, res1, c1, flexfloat::FlexfloatOp::Fsub, fpmode_dst);
+                self.write_freg_vf64h(data.rd, c3, c2, res1, res0);
+                return Ok(());
+            }
+            riscv::OpcodeRdRs1::VfcvtSH | riscv::OpcodeRdRs1::VfcvtuSH => {
+                // The `u` variant widens the upper two halves.
+                let (a3, a2, a1, a0) = self.read_freg_vf64h(data.rs1);
+                let (a1, a0) = match data.op {
+                    riscv::OpcodeRdRs1::VfcvtuSH => (a3, a2),
+                    _ => (a1, a0),
+                };
+                let (fpmode_src, fpmode_dst) = self.read_fpmode();
+                let mut res = [a0, a1];
+                for r in &mut res {
+                    let rs1 = LLVMBuildZExt(
+                        self.builder,
+                        *r,
+                        LLVMInt64TypeInContext(self.section.engine.context),
+                        NONAME,
+                    );
+                    *r = self.emit_fp32_op_cvt_to_f(
+                        rs1,
+                        flexfloat::FfOpCvt::Fcvt16f2f,
+                        fpmode_src,
+                        fpmode_dst,
+                    );
+                }
+                self.write_freg_vf64s(data.rd, res[1], res[0], false);
+                return Ok(());
+            }
+            riscv::OpcodeRdRs1::VfcvtHS | riscv::OpcodeRdRs1::VfcvtuHS => {
+                // The `u` variant narrows into the upper two halves and keeps
+                // the lower ones.
+                let (a1, a0) = self.read_freg_vf64s(data.rs1, false);
+                let (rd3, rd2, rd1, rd0) = self.read_freg_vf64h(data.rd);
+                let (fpmode_src, fpmode_dst) = self.read_fpmode();
+                let mut res = [a0, a1];
+                for r in &mut res {
+                    let rs1 = LLVMBuildZExt(
+                        self.builder,
+                        *r,
+                        LLVMInt64TypeInContext(self.section.engine.context),
+                        NONAME,
+                    );
+                    *r = self.emit_fp16_op_cvt_to_f(
+                        rs1,
+                        flexfloat::FfOpCvt::Fcvt32f2f,
+                        fpmode_src,
+                        fpmode_dst,
+                    );
+                }
+                match data.op {
+                    riscv::OpcodeRdRs1::VfcvtuHS => {
+                        self.write_freg_vf64h(data.rd, res[1], res[0], rd1, rd0)
+                    }
+                    _ => self.write_freg_vf64h(data.rd, rd3, rd2, res[1], res[0]),
+                }
+                return Ok(());
+            }
             riscv::OpcodeRdRs1::VfsumB => {
                 let (a7, a6, a5, a4, a3, a2, a1, a0) = self.read_freg_vf64b(data.rs1);
                 let (_c7, _c6, _c5, _c4, c3, c2, c1, c0) = self.read_freg_vf64b(data.rd);
@@ -3606,19 +5501,94 @@ impl<'a> InstructionTranslator<'a> {
                 self.write_freg_vf64h(data.rd, res3, res2, res1, res0);
                 return Ok(());
             }
-            _ => (),
+            riscv::OpcodeRdRs1::Clz | riscv::OpcodeRdRs1::Ctz => {
+                let name = match data.op {
+                    riscv::OpcodeRdRs1::Clz => "llvm.ctlz",
+                    _ => "llvm.cttz",
+                };
+                // The result for a zero operand is defined as 32.
+                let zero_is_poison =
+                    LLVMConstInt(LLVMInt1TypeInContext(self.section.engine.context), 0, 0);
+                let value = self.emit_intrinsic(name, &[self.read_reg(data.rs1), zero_is_poison]);
+                self.write_reg(data.rd, value);
+            }
+            riscv::OpcodeRdRs1::Cpop => {
+                let value = self.emit_intrinsic("llvm.ctpop", &[self.read_reg(data.rs1)]);
+                self.write_reg(data.rd, value);
+            }
+            riscv::OpcodeRdRs1::SextB | riscv::OpcodeRdRs1::SextH => {
+                let ty = match data.op {
+                    riscv::OpcodeRdRs1::SextB => LLVMInt8TypeInContext(self.section.engine.context),
+                    _ => LLVMInt16TypeInContext(self.section.engine.context),
+                };
+                let value = LLVMBuildTrunc(self.builder, self.read_reg(data.rs1), ty, NONAME);
+                let value = LLVMBuildSExt(
+                    self.builder,
+                    value,
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    name,
+                );
+                self.write_reg(data.rd, value);
+            }
+            _ => bail!("Unsupported opcode {}", data.op),
         }
         Ok(())
     }
 
     unsafe fn emit_rd_rs1_rs2(&self, data: riscv::FormatRdRs1Rs2) -> Result<()> {
-        trace!("{} x{} = x{}, x{}", data.op, data.rd, data.rs1, data.rs2);
+        trace!(
+            target: log_target::TRANSLATE,
+            "{} x{} = x{}, x{}",
+            data.op,
+            data.rd,
+            data.rs1,
+            data.rs2
+        );
         let name = format!("{}\0", data.op);
         let name = name.as_ptr() as *const _;
 
         // Assume generally freppable, later exclude comparisons.
         self.was_freppable.set(true);
 
+        // Handle floating-point operations with exact rounding and flags.
+        if self.section.elf.exact_fp {
+            let exact = match data.op {
+                riscv::OpcodeRdRs1Rs2::FminS => Some((FpOp::Min, false)),
+                riscv::OpcodeRdRs1Rs2::FmaxS => Some((FpOp::Max, false)),
+                riscv::OpcodeRdRs1Rs2::FeqS => Some((FpOp::Eq, false)),
+                riscv::OpcodeRdRs1Rs2::FltS => Some((FpOp::Lt, false)),
+                riscv::OpcodeRdRs1Rs2::FleS => Some((FpOp::Le, false)),
+                riscv::OpcodeRdRs1Rs2::FminD => Some((FpOp::Min, true)),
+                riscv::OpcodeRdRs1Rs2::FmaxD => Some((FpOp::Max, true)),
+                riscv::OpcodeRdRs1Rs2::FeqD => Some((FpOp::Eq, true)),
+                riscv::OpcodeRdRs1Rs2::FltD => Some((FpOp::Lt, true)),
+                riscv::OpcodeRdRs1Rs2::FleD => Some((FpOp::Le, true)),
+                _ => None,
+            };
+            if let Some((op, double)) = exact {
+                let value = self.emit_exact_fp(
+                    op,
+                    0,
+                    double,
+                    &[self.read_freg(data.rs1), self.read_freg(data.rs2)],
+                );
+                match op {
+                    FpOp::Min | FpOp::Max => self.write_freg(data.rd, value),
+                    _ => {
+                        self.was_freppable.set(false);
+                        let value = LLVMBuildTrunc(
+                            self.builder,
+                            value,
+                            LLVMInt32TypeInContext(self.section.engine.context),
+                            NONAME,
+                        );
+                        self.write_reg(data.rd, value);
+                    }
+                }
+                return Ok(());
+            }
+        }
+
         // Handle floating-point operations.
         match data.op {
             // VfloatB instructions
@@ -4628,18 +6598,8 @@ impl<'a> InstructionTranslator<'a> {
                 let (a1, a0) = self.read_freg_vf64s(data.rs1, true);
                 let (b1, b0) = self.read_freg_vf64s(data.rs2, true);
                 let (c1, c0) = self.read_freg_vf64s(data.rd, true);
-                let res0 = LLVMBuildFAdd(
-                    self.builder,
-                    LLVMBuildFMul(self.builder, a0, b0, name),
-                    c0,
-                    name,
-                );
-                let res1 = LLVMBuildFAdd(
-                    self.builder,
-                    LLVMBuildFMul(self.builder, a1, b1, name),
-                    c1,
-                    name,
-                );
+                let res0 = self.emit_intrinsic("llvm.fma", &[a0, b0, c0]);
+                let res1 = self.emit_intrinsic("llvm.fma", &[a1, b1, c1]);
                 self.write_freg_vf64s(data.rd, res1, res0, true);
                 return Ok(());
             }
@@ -4647,18 +6607,8 @@ impl<'a> InstructionTranslator<'a> {
                 let (a1, a0) = self.read_freg_vf64s(data.rs1, true);
                 let (_b1, b0) = self.read_freg_vf64s(data.rs2, true);
                 let (c1, c0) = self.read_freg_vf64s(data.rd, true);
-                let res0 = LLVMBuildFAdd(
-                    self.builder,
-                    LLVMBuildFMul(self.builder, a0, b0, name),
-                    c0,
-                    name,
-                );
-                let res1 = LLVMBuildFAdd(
-                    self.builder,
-                    LLVMBuildFMul(self.builder, a1, b0, name),
-                    c1,
-                    name,
-                );
+                let res0 = self.emit_intrinsic("llvm.fma", &[a0, b0, c0]);
+                let res1 = self.emit_intrinsic("llvm.fma", &[a1, b0, c1]);
                 self.write_freg_vf64s(data.rd, res1, res0, true);
                 return Ok(());
             }
@@ -4666,17 +6616,13 @@ impl<'a> InstructionTranslator<'a> {
                 let (a1, a0) = self.read_freg_vf64s(data.rs1, true);
                 let (b1, b0) = self.read_freg_vf64s(data.rs2, true);
                 let (c1, c0) = self.read_freg_vf64s(data.rd, true);
-                let res0 = LLVMBuildFSub(
-                    self.builder,
-                    c0,
-                    LLVMBuildFMul(self.builder, a0, b0, name),
-                    name,
+                let res0 = self.emit_intrinsic(
+                    "llvm.fma",
+                    &[LLVMBuildFNeg(self.builder, a0, NONAME), b0, c0],
                 );
-                let res1 = LLVMBuildFSub(
-                    self.builder,
-                    c1,
-                    LLVMBuildFMul(self.builder, a1, b1, name),
-                    name,
+                let res1 = self.emit_intrinsic(
+                    "llvm.fma",
+                    &[LLVMBuildFNeg(self.builder, a1, NONAME), b1, c1],
                 );
                 self.write_freg_vf64s(data.rd, res1, res0, true);
                 return Ok(());
@@ -4685,17 +6631,13 @@ impl<'a> InstructionTranslator<'a> {
                 let (a1, a0) = self.read_freg_vf64s(data.rs1, true);
                 let (_b1, b0) = self.read_freg_vf64s(data.rs2, true);
                 let (c1, c0) = self.read_freg_vf64s(data.rd, true);
-                let res0 = LLVMBuildFSub(
-                    self.builder,
-                    c0,
-                    LLVMBuildFMul(self.builder, a0, b0, name),
-                    name,
+                let res0 = self.emit_intrinsic(
+                    "llvm.fma",
+                    &[LLVMBuildFNeg(self.builder, a0, NONAME), b0, c0],
                 );
-                let res1 = LLVMBuildFSub(
-                    self.builder,
-                    c1,
-                    LLVMBuildFMul(self.builder, a1, b0, name),
-                    name,
+                let res1 = self.emit_intrinsic(
+                    "llvm.fma",
+                    &[LLVMBuildFNeg(self.builder, a1, NONAME), b0, c1],
                 );
                 self.write_freg_vf64s(data.rd, res1, res0, true);
                 return Ok(());
@@ -4817,51 +6759,51 @@ impl<'a> InstructionTranslator<'a> {
                 return Ok(());
             }
             riscv::OpcodeRdRs1Rs2::VfsgnjS => {
-                let (a1, a0) = self.read_freg_vf64s(data.rs1, true);
-                let (b1, b0) = self.read_freg_vf64s(data.rs2, true);
+                let (a1, a0) = self.read_freg_vf64s(data.rs1, false);
+                let (b1, b0) = self.read_freg_vf64s(data.rs2, false);
                 let res0 = self.emit_fsgnj(a0, b0);
                 let res1 = self.emit_fsgnj(a1, b1);
-                self.write_freg_vf64s(data.rd, res1, res0, true);
+                self.write_freg_vf64s(data.rd, res1, res0, false);
                 return Ok(());
             }
             riscv::OpcodeRdRs1Rs2::VfsgnjRS => {
-                let (a1, a0) = self.read_freg_vf64s(data.rs1, true);
-                let (_b1, b0) = self.read_freg_vf64s(data.rs2, true);
+                let (a1, a0) = self.read_freg_vf64s(data.rs1, false);
+                let (_b1, b0) = self.read_freg_vf64s(data.rs2, false);
                 let res0 = self.emit_fsgnj(a0, b0);
                 let res1 = self.emit_fsgnj(a1, b0);
-                self.write_freg_vf64s(data.rd, res1, res0, true);
+                self.write_freg_vf64s(data.rd, res1, res0, false);
                 return Ok(());
             }
             riscv::OpcodeRdRs1Rs2::VfsgnjnS => {
-                let (a1, a0) = self.read_freg_vf64s(data.rs1, true);
-                let (b1, b0) = self.read_freg_vf64s(data.rs2, true);
+                let (a1, a0) = self.read_freg_vf64s(data.rs1, false);
+                let (b1, b0) = self.read_freg_vf64s(data.rs2, false);
                 let res0 = self.emit_fsgnjn(a0, b0);
                 let res1 = self.emit_fsgnjn(a1, b1);
-                self.write_freg_vf64s(data.rd, res1, res0, true);
+                self.write_freg_vf64s(data.rd, res1, res0, false);
                 return Ok(());
             }
             riscv::OpcodeRdRs1Rs2::VfsgnjnRS => {
-                let (a1, a0) = self.read_freg_vf64s(data.rs1, true);
-                let (_b1, b0) = self.read_freg_vf64s(data.rs2, true);
+                let (a1, a0) = self.read_freg_vf64s(data.rs1, false);
+                let (_b1, b0) = self.read_freg_vf64s(data.rs2, false);
                 let res0 = self.emit_fsgnjn(a0, b0);
                 let res1 = self.emit_fsgnjn(a1, b0);
-                self.write_freg_vf64s(data.rd, res1, res0, true);
+                self.write_freg_vf64s(data.rd, res1, res0, false);
                 return Ok(());
             }
             riscv::OpcodeRdRs1Rs2::VfsgnjxS => {
-                let (a1, a0) = self.read_freg_vf64s(data.rs1, true);
-                let (b1, b0) = self.read_freg_vf64s(data.rs2, true);
+                let (a1, a0) = self.read_freg_vf64s(data.rs1, false);
+                let (b1, b0) = self.read_freg_vf64s(data.rs2, false);
                 let res0 = self.emit_fsgnjx(a0, b0);
                 let res1 = self.emit_fsgnjx(a1, b1);
-                self.write_freg_vf64s(data.rd, res1, res0, true);
+                self.write_freg_vf64s(data.rd, res1, res0, false);
                 return Ok(());
             }
             riscv::OpcodeRdRs1Rs2::VfsgnjxRS => {
-                let (a1, a0) = self.read_freg_vf64s(data.rs1, true);
-                let (_b1, b0) = self.read_freg_vf64s(data.rs2, true);
+                let (a1, a0) = self.read_freg_vf64s(data.rs1, false);
+                let (_b1, b0) = self.read_freg_vf64s(data.rs2, false);
                 let res0 = self.emit_fsgnjx(a0, b0);
                 let res1 = self.emit_fsgnjx(a1, b0);
-                self.write_freg_vf64s(data.rd, res1, res0, true);
+                self.write_freg_vf64s(data.rd, res1, res0, false);
                 return Ok(());
             }
             riscv::OpcodeRdRs1Rs2::VfeqS => {
@@ -4871,19 +6813,23 @@ impl<'a> InstructionTranslator<'a> {
                 let res0 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOEQ, a0, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOEQ, a1, b1, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildShl(
                     self.builder,
                     res1,
-                    LLVMConstInt(LLVMInt32Type(), 1 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        1 as u64,
+                        0,
+                    ),
                     name,
                 );
                 let res = LLVMBuildOr(self.builder, res0, res1, name);
@@ -4897,19 +6843,23 @@ impl<'a> InstructionTranslator<'a> {
                 let res0 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOEQ, a0, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOEQ, a1, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildShl(
                     self.builder,
                     res1,
-                    LLVMConstInt(LLVMInt32Type(), 1 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        1 as u64,
+                        0,
+                    ),
                     name,
                 );
                 let res = LLVMBuildOr(self.builder, res0, res1, name);
@@ -4923,19 +6873,23 @@ impl<'a> InstructionTranslator<'a> {
                 let res0 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOLT, a0, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOLT, a1, b1, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildShl(
                     self.builder,
                     res1,
-                    LLVMConstInt(LLVMInt32Type(), 1 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        1 as u64,
+                        0,
+                    ),
                     name,
                 );
                 let res = LLVMBuildOr(self.builder, res0, res1, name);
@@ -4949,19 +6903,23 @@ impl<'a> InstructionTranslator<'a> {
                 let res0 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOLT, a0, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOLT, a1, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildShl(
                     self.builder,
                     res1,
-                    LLVMConstInt(LLVMInt32Type(), 1 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        1 as u64,
+                        0,
+                    ),
                     name,
                 );
                 let res = LLVMBuildOr(self.builder, res0, res1, name);
@@ -4975,19 +6933,23 @@ impl<'a> InstructionTranslator<'a> {
                 let res0 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOLE, a0, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOLE, a1, b1, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildShl(
                     self.builder,
                     res1,
-                    LLVMConstInt(LLVMInt32Type(), 1 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        1 as u64,
+                        0,
+                    ),
                     name,
                 );
                 let res = LLVMBuildOr(self.builder, res0, res1, name);
@@ -5001,19 +6963,23 @@ impl<'a> InstructionTranslator<'a> {
                 let res0 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOLE, a0, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOLE, a1, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildShl(
                     self.builder,
                     res1,
-                    LLVMConstInt(LLVMInt32Type(), 1 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        1 as u64,
+                        0,
+                    ),
                     name,
                 );
                 let res = LLVMBuildOr(self.builder, res0, res1, name);
@@ -5027,19 +6993,23 @@ impl<'a> InstructionTranslator<'a> {
                 let res0 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOGE, a0, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOGE, a1, b1, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildShl(
                     self.builder,
                     res1,
-                    LLVMConstInt(LLVMInt32Type(), 1 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        1 as u64,
+                        0,
+                    ),
                     name,
                 );
                 let res = LLVMBuildOr(self.builder, res0, res1, name);
@@ -5053,19 +7023,23 @@ impl<'a> InstructionTranslator<'a> {
                 let res0 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOGE, a0, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOGE, a1, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildShl(
                     self.builder,
                     res1,
-                    LLVMConstInt(LLVMInt32Type(), 1 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        1 as u64,
+                        0,
+                    ),
                     name,
                 );
                 let res = LLVMBuildOr(self.builder, res0, res1, name);
@@ -5079,19 +7053,23 @@ impl<'a> InstructionTranslator<'a> {
                 let res0 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOGT, a0, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOGT, a1, b1, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildShl(
                     self.builder,
                     res1,
-                    LLVMConstInt(LLVMInt32Type(), 1 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        1 as u64,
+                        0,
+                    ),
                     name,
                 );
                 let res = LLVMBuildOr(self.builder, res0, res1, name);
@@ -5105,19 +7083,23 @@ impl<'a> InstructionTranslator<'a> {
                 let res0 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOGT, a0, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealOGT, a1, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildShl(
                     self.builder,
                     res1,
-                    LLVMConstInt(LLVMInt32Type(), 1 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        1 as u64,
+                        0,
+                    ),
                     name,
                 );
                 let res = LLVMBuildOr(self.builder, res0, res1, name);
@@ -5131,19 +7113,23 @@ impl<'a> InstructionTranslator<'a> {
                 let res0 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealONE, a0, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealONE, a1, b1, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildShl(
                     self.builder,
                     res1,
-                    LLVMConstInt(LLVMInt32Type(), 1 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        1 as u64,
+                        0,
+                    ),
                     name,
                 );
                 let res = LLVMBuildOr(self.builder, res0, res1, name);
@@ -5157,19 +7143,23 @@ impl<'a> InstructionTranslator<'a> {
                 let res0 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealONE, a0, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildZExt(
                     self.builder,
                     LLVMBuildFCmp(self.builder, LLVMRealONE, a1, b0, name),
-                    LLVMInt32Type(),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
                 );
                 let res1 = LLVMBuildShl(
                     self.builder,
                     res1,
-                    LLVMConstInt(LLVMInt32Type(), 1 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        1 as u64,
+                        0,
+                    ),
                     name,
                 );
                 let res = LLVMBuildOr(self.builder, res0, res1, name);
@@ -5182,10 +7172,10 @@ impl<'a> InstructionTranslator<'a> {
                 self.write_freg_f32(
                     data.rd,
                     self.emit_fsgnj(
-                        self.read_freg_f32(data.rs1, true),
-                        self.read_freg_f32(data.rs2, true),
+                        self.read_freg_f32(data.rs1, false),
+                        self.read_freg_f32(data.rs2, false),
                     ),
-                    true,
+                    false,
                 );
                 return Ok(());
             }
@@ -5193,10 +7183,10 @@ impl<'a> InstructionTranslator<'a> {
                 self.write_freg_f32(
                     data.rd,
                     self.emit_fsgnjn(
-                        self.read_freg_f32(data.rs1, true),
-                        self.read_freg_f32(data.rs2, true),
+                        self.read_freg_f32(data.rs1, false),
+                        self.read_freg_f32(data.rs2, false),
                     ),
-                    true,
+                    false,
                 );
                 return Ok(());
             }
@@ -5204,10 +7194,10 @@ impl<'a> InstructionTranslator<'a> {
                 self.write_freg_f32(
                     data.rd,
                     self.emit_fsgnjx(
-                        self.read_freg_f32(data.rs1, true),
-                        self.read_freg_f32(data.rs2, true),
+                        self.read_freg_f32(data.rs1, false),
+                        self.read_freg_f32(data.rs2, false),
                     ),
-                    true,
+                    false,
                 );
                 return Ok(());
             }
@@ -5215,10 +7205,10 @@ impl<'a> InstructionTranslator<'a> {
                 self.write_freg_f64(
                     data.rd,
                     self.emit_fsgnj(
-                        self.read_freg_f64(data.rs1, true),
-                        self.read_freg_f64(data.rs2, true),
+                        self.read_freg_f64(data.rs1, false),
+                        self.read_freg_f64(data.rs2, false),
                     ),
-                    true,
+                    false,
                 );
                 return Ok(());
             }
@@ -5226,10 +7216,10 @@ impl<'a> InstructionTranslator<'a> {
                 self.write_freg_f64(
                     data.rd,
                     self.emit_fsgnjn(
-                        self.read_freg_f64(data.rs1, true),
-                        self.read_freg_f64(data.rs2, true),
+                        self.read_freg_f64(data.rs1, false),
+                        self.read_freg_f64(data.rs2, false),
                     ),
-                    true,
+                    false,
                 );
                 return Ok(());
             }
@@ -5237,10 +7227,10 @@ impl<'a> InstructionTranslator<'a> {
                 self.write_freg_f64(
                     data.rd,
                     self.emit_fsgnjx(
-                        self.read_freg_f64(data.rs1, true),
-                        self.read_freg_f64(data.rs2, true),
+                        self.read_freg_f64(data.rs1, false),
+                        self.read_freg_f64(data.rs2, false),
                     ),
-                    true,
+                    false,
                 );
                 return Ok(());
             }
@@ -5365,7 +7355,7 @@ impl<'a> InstructionTranslator<'a> {
                             flexfloat::FlexfloatOpCmp::Feq,
                             fpmode_dst,
                         ),
-                        LLVMInt32Type(),
+                        LLVMInt32TypeInContext(self.section.engine.context),
                         NONAME,
                     ),
                 );
@@ -5384,7 +7374,7 @@ impl<'a> InstructionTranslator<'a> {
                             flexfloat::FlexfloatOpCmp::Flt,
                             fpmode_dst,
                         ),
-                        LLVMInt32Type(),
+                        LLVMInt32TypeInContext(self.section.engine.context),
                         NONAME,
                     ),
                 );
@@ -5403,7 +7393,7 @@ impl<'a> InstructionTranslator<'a> {
                             flexfloat::FlexfloatOpCmp::Fle,
                             fpmode_dst,
                         ),
-                        LLVMInt32Type(),
+                        LLVMInt32TypeInContext(self.section.engine.context),
                         NONAME,
                     ),
                 );
@@ -5422,7 +7412,7 @@ impl<'a> InstructionTranslator<'a> {
                             flexfloat::FlexfloatOpCmp::Feq,
                             fpmode_dst,
                         ),
-                        LLVMInt32Type(),
+                        LLVMInt32TypeInContext(self.section.engine.context),
                         NONAME,
                     ),
                 );
@@ -5441,7 +7431,7 @@ impl<'a> InstructionTranslator<'a> {
                             flexfloat::FlexfloatOpCmp::Flt,
                             fpmode_dst,
                         ),
-                        LLVMInt32Type(),
+                        LLVMInt32TypeInContext(self.section.engine.context),
                         NONAME,
                     ),
                 );
@@ -5460,121 +7450,34 @@ impl<'a> InstructionTranslator<'a> {
                             flexfloat::FlexfloatOpCmp::Fle,
                             fpmode_dst,
                         ),
-                        LLVMInt32Type(),
-                        NONAME,
-                    ),
-                );
-                return Ok(());
-            }
-            riscv::OpcodeRdRs1Rs2::FeqS => {
-                self.was_freppable.set(false);
-                self.write_reg(
-                    data.rd,
-                    LLVMBuildZExt(
-                        self.builder,
-                        LLVMBuildFCmp(
-                            self.builder,
-                            LLVMRealOEQ,
-                            self.read_freg_f32(data.rs1, true),
-                            self.read_freg_f32(data.rs2, true),
-                            name,
-                        ),
-                        LLVMInt32Type(),
-                        NONAME,
-                    ),
-                );
-                return Ok(());
-            }
-            riscv::OpcodeRdRs1Rs2::FltS => {
-                self.was_freppable.set(false);
-                self.write_reg(
-                    data.rd,
-                    LLVMBuildZExt(
-                        self.builder,
-                        LLVMBuildFCmp(
-                            self.builder,
-                            LLVMRealOLT,
-                            self.read_freg_f32(data.rs1, true),
-                            self.read_freg_f32(data.rs2, true),
-                            name,
-                        ),
-                        LLVMInt32Type(),
-                        NONAME,
-                    ),
-                );
-                return Ok(());
-            }
-            riscv::OpcodeRdRs1Rs2::FleS => {
-                self.was_freppable.set(false);
-                self.write_reg(
-                    data.rd,
-                    LLVMBuildZExt(
-                        self.builder,
-                        LLVMBuildFCmp(
-                            self.builder,
-                            LLVMRealOLE,
-                            self.read_freg_f32(data.rs1, true),
-                            self.read_freg_f32(data.rs2, true),
-                            name,
-                        ),
-                        LLVMInt32Type(),
-                        NONAME,
-                    ),
-                );
-                return Ok(());
-            }
-            riscv::OpcodeRdRs1Rs2::FeqD => {
-                self.was_freppable.set(false);
-                self.write_reg(
-                    data.rd,
-                    LLVMBuildZExt(
-                        self.builder,
-                        LLVMBuildFCmp(
-                            self.builder,
-                            LLVMRealOEQ,
-                            self.read_freg_f64(data.rs1, true),
-                            self.read_freg_f64(data.rs2, true),
-                            name,
-                        ),
-                        LLVMInt32Type(),
-                        NONAME,
-                    ),
-                );
-                return Ok(());
-            }
-            riscv::OpcodeRdRs1Rs2::FltD => {
-                self.was_freppable.set(false);
-                self.write_reg(
-                    data.rd,
-                    LLVMBuildZExt(
-                        self.builder,
-                        LLVMBuildFCmp(
-                            self.builder,
-                            LLVMRealOLT,
-                            self.read_freg_f64(data.rs1, true),
-                            self.read_freg_f64(data.rs2, true),
-                            name,
-                        ),
-                        LLVMInt32Type(),
+                        LLVMInt32TypeInContext(self.section.engine.context),
                         NONAME,
                     ),
                 );
                 return Ok(());
             }
-            riscv::OpcodeRdRs1Rs2::FleD => {
+            riscv::OpcodeRdRs1Rs2::FeqS
+            | riscv::OpcodeRdRs1Rs2::FltS
+            | riscv::OpcodeRdRs1Rs2::FleS
+            | riscv::OpcodeRdRs1Rs2::FeqD
+            | riscv::OpcodeRdRs1Rs2::FltD
+            | riscv::OpcodeRdRs1Rs2::FleD => {
                 self.was_freppable.set(false);
+                let (pred, double) = match data.op {
+                    riscv::OpcodeRdRs1Rs2::FeqS => (LLVMRealOEQ, false),
+                    riscv::OpcodeRdRs1Rs2::FltS => (LLVMRealOLT, false),
+                    riscv::OpcodeRdRs1Rs2::FleS => (LLVMRealOLE, false),
+                    riscv::OpcodeRdRs1Rs2::FeqD => (LLVMRealOEQ, true),
+                    riscv::OpcodeRdRs1Rs2::FltD => (LLVMRealOLT, true),
+                    _ => (LLVMRealOLE, true),
+                };
+                let value = self.emit_fp_compare(pred, double, data.rs1, data.rs2, name);
                 self.write_reg(
                     data.rd,
                     LLVMBuildZExt(
                         self.builder,
-                        LLVMBuildFCmp(
-                            self.builder,
-                            LLVMRealOLE,
-                            self.read_freg_f64(data.rs1, true),
-                            self.read_freg_f64(data.rs2, true),
-                            name,
-                        ),
-                        LLVMInt32Type(),
+                        value,
+                        LLVMInt32TypeInContext(self.section.engine.context),
                         NONAME,
                     ),
                 );
@@ -5612,67 +7515,177 @@ impl<'a> InstructionTranslator<'a> {
             riscv::OpcodeRdRs1Rs2::Mulhu => {
                 let tmp = LLVMBuildMul(
                     self.builder,
-                    LLVMBuildZExt(self.builder, rs1, LLVMInt64Type(), NONAME),
-                    LLVMBuildZExt(self.builder, rs2, LLVMInt64Type(), NONAME),
+                    LLVMBuildZExt(
+                        self.builder,
+                        rs1,
+                        LLVMInt64TypeInContext(self.section.engine.context),
+                        NONAME,
+                    ),
+                    LLVMBuildZExt(
+                        self.builder,
+                        rs2,
+                        LLVMInt64TypeInContext(self.section.engine.context),
+                        NONAME,
+                    ),
                     name,
                 );
                 let tmp = LLVMBuildLShr(
                     self.builder,
                     tmp,
-                    LLVMConstInt(LLVMInt64Type(), 32 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt64TypeInContext(self.section.engine.context),
+                        32 as u64,
+                        0,
+                    ),
                     NONAME,
                 );
-                LLVMBuildTrunc(self.builder, tmp, LLVMInt32Type(), NONAME)
+                LLVMBuildTrunc(
+                    self.builder,
+                    tmp,
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    NONAME,
+                )
             }
             riscv::OpcodeRdRs1Rs2::Mulh => {
                 let tmp = LLVMBuildMul(
                     self.builder,
-                    LLVMBuildSExt(self.builder, rs1, LLVMInt64Type(), NONAME),
-                    LLVMBuildSExt(self.builder, rs2, LLVMInt64Type(), NONAME),
+                    LLVMBuildSExt(
+                        self.builder,
+                        rs1,
+                        LLVMInt64TypeInContext(self.section.engine.context),
+                        NONAME,
+                    ),
+                    LLVMBuildSExt(
+                        self.builder,
+                        rs2,
+                        LLVMInt64TypeInContext(self.section.engine.context),
+                        NONAME,
+                    ),
                     name,
                 );
                 let tmp = LLVMBuildLShr(
                     self.builder,
                     tmp,
-                    LLVMConstInt(LLVMInt64Type(), 32 as u64, 0),
+                    LLVMConstInt(
+                        LLVMInt64TypeInContext(self.section.engine.context),
+                        32 as u64,
+                        0,
+                    ),
                     NONAME,
                 );
-                LLVMBuildTrunc(self.builder, tmp, LLVMInt32Type(), NONAME)
+                LLVMBuildTrunc(
+                    self.builder,
+                    tmp,
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    NONAME,
+                )
             }
             riscv::OpcodeRdRs1Rs2::Mulhsu => {
                 let tmp = LLVMBuildMul(
                     self.builder,
-                    LLVMBuildSExt(self.builder, rs1, LLVMInt64Type(), NONAME),
-                    LLVMBuildZExt(self.builder, rs2, LLVMInt64Type(), NONAME),
+                    LLVMBuildSExt(
+                        self.builder,
+                        rs1,
+                        LLVMInt64TypeInContext(self.section.engine.context),
+                        NONAME,
+                    ),
+                    LLVMBuildZExt(
+                        self.builder,
+                        rs2,
+                        LLVMInt64TypeInContext(self.section.engine.context),
+                        NONAME,
+                    ),
                     name,
                 );
-                let tmp = LLVMBuildLShr(
+                let tmp = LLVMBuildLShr(
+                    self.builder,
+                    tmp,
+                    LLVMConstInt(
+                        LLVMInt64TypeInContext(self.section.engine.context),
+                        32 as u64,
+                        0,
+                    ),
+                    NONAME,
+                );
+                LLVMBuildTrunc(
                     self.builder,
                     tmp,
-                    LLVMConstInt(LLVMInt64Type(), 32 as u64, 0),
+                    LLVMInt32TypeInContext(self.section.engine.context),
                     NONAME,
-                );
-                LLVMBuildTrunc(self.builder, tmp, LLVMInt32Type(), NONAME)
+                )
             }
-            riscv::OpcodeRdRs1Rs2::Div => LLVMBuildSDiv(self.builder, rs1, rs2, name),
-            riscv::OpcodeRdRs1Rs2::Divu => LLVMBuildUDiv(self.builder, rs1, rs2, name),
-            riscv::OpcodeRdRs1Rs2::Rem => LLVMBuildSRem(self.builder, rs1, rs2, name),
-            riscv::OpcodeRdRs1Rs2::Remu => LLVMBuildURem(self.builder, rs1, rs2, name),
+            riscv::OpcodeRdRs1Rs2::Div => self.emit_div_rem(true, false, rs1, rs2, name),
+            riscv::OpcodeRdRs1Rs2::Divu => self.emit_div_rem(false, false, rs1, rs2, name),
+            riscv::OpcodeRdRs1Rs2::Rem => self.emit_div_rem(true, true, rs1, rs2, name),
+            riscv::OpcodeRdRs1Rs2::Remu => self.emit_div_rem(false, true, rs1, rs2, name),
             riscv::OpcodeRdRs1Rs2::Slt => LLVMBuildZExt(
                 self.builder,
                 LLVMBuildICmp(self.builder, LLVMIntSLT, rs1, rs2, NONAME),
-                LLVMInt32Type(),
+                LLVMInt32TypeInContext(self.section.engine.context),
                 name,
             ),
             riscv::OpcodeRdRs1Rs2::Sltu => LLVMBuildZExt(
                 self.builder,
                 LLVMBuildICmp(self.builder, LLVMIntULT, rs1, rs2, NONAME),
-                LLVMInt32Type(),
+                LLVMInt32TypeInContext(self.section.engine.context),
                 name,
             ),
             riscv::OpcodeRdRs1Rs2::Sll => LLVMBuildShl(self.builder, rs1, rs2, name),
             riscv::OpcodeRdRs1Rs2::Srl => LLVMBuildLShr(self.builder, rs1, rs2, name),
             riscv::OpcodeRdRs1Rs2::Sra => LLVMBuildAShr(self.builder, rs1, rs2, name),
+            riscv::OpcodeRdRs1Rs2::Andn => LLVMBuildAnd(
+                self.builder,
+                rs1,
+                LLVMBuildNot(self.builder, rs2, NONAME),
+                name,
+            ),
+            riscv::OpcodeRdRs1Rs2::Orn => LLVMBuildOr(
+                self.builder,
+                rs1,
+                LLVMBuildNot(self.builder, rs2, NONAME),
+                name,
+            ),
+            riscv::OpcodeRdRs1Rs2::Xnor => LLVMBuildNot(
+                self.builder,
+                LLVMBuildXor(self.builder, rs1, rs2, NONAME),
+                name,
+            ),
+            riscv::OpcodeRdRs1Rs2::Min
+            | riscv::OpcodeRdRs1Rs2::Max
+            | riscv::OpcodeRdRs1Rs2::Minu
+            | riscv::OpcodeRdRs1Rs2::Maxu => {
+                let pred = match data.op {
+                    riscv::OpcodeRdRs1Rs2::Min => LLVMIntSLT,
+                    riscv::OpcodeRdRs1Rs2::Max => LLVMIntSGT,
+                    riscv::OpcodeRdRs1Rs2::Minu => LLVMIntULT,
+                    _ => LLVMIntUGT,
+                };
+                let cond = LLVMBuildICmp(self.builder, pred, rs1, rs2, NONAME);
+                LLVMBuildSelect(self.builder, cond, rs1, rs2, name)
+            }
+            riscv::OpcodeRdRs1Rs2::Rol => self.emit_intrinsic("llvm.fshl", &[rs1, rs1, rs2]),
+            riscv::OpcodeRdRs1Rs2::Ror => self.emit_intrinsic("llvm.fshr", &[rs1, rs1, rs2]),
+            // Encodes `zext.h` if `rs2` is zero.
+            riscv::OpcodeRdRs1Rs2::Pack => LLVMBuildOr(
+                self.builder,
+                LLVMBuildAnd(
+                    self.builder,
+                    rs1,
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        0xffff,
+                        0,
+                    ),
+                    NONAME,
+                ),
+                LLVMBuildShl(
+                    self.builder,
+                    rs2,
+                    LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 16, 0),
+                    NONAME,
+                ),
+                name,
+            ),
             riscv::OpcodeRdRs1Rs2::Dmcpy => self.section.emit_call(
                 "banshee_dma_strt",
                 [self.dma_ptr(), self.section.state_ptr, rs1, rs2],
@@ -5683,6 +7696,56 @@ impl<'a> InstructionTranslator<'a> {
         Ok(())
     }
 
+    /// Emit a division or remainder with the results the ISA defines for
+    /// division by zero and signed overflow.
+    ///
+    /// LLVM leaves both cases undefined, and they trap on some hosts, so the
+    /// divisor is replaced by one if either occurs. This already yields the
+    /// results for signed overflow (`-2^31` and `0`); the results for
+    /// division by zero (all ones and the dividend) are selected explicitly.
+    unsafe fn emit_div_rem(
+        &self,
+        signed: bool,
+        rem: bool,
+        rs1: LLVMValueRef,
+        rs2: LLVMValueRef,
+        name: *const i8,
+    ) -> LLVMValueRef {
+        let zero = LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0, 0);
+        let one = LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 1, 0);
+        let ones = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            -1i32 as u32 as u64,
+            0,
+        );
+        let by_zero = LLVMBuildICmp(self.builder, LLVMIntEQ, rs2, zero, NONAME);
+        let invalid = if signed {
+            let min = LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                0x8000_0000,
+                0,
+            );
+            let overflow = LLVMBuildAnd(
+                self.builder,
+                LLVMBuildICmp(self.builder, LLVMIntEQ, rs1, min, NONAME),
+                LLVMBuildICmp(self.builder, LLVMIntEQ, rs2, ones, NONAME),
+                NONAME,
+            );
+            LLVMBuildOr(self.builder, by_zero, overflow, NONAME)
+        } else {
+            by_zero
+        };
+        let divisor = LLVMBuildSelect(self.builder, invalid, one, rs2, NONAME);
+        let value = match (signed, rem) {
+            (true, false) => LLVMBuildSDiv(self.builder, rs1, divisor, NONAME),
+            (false, false) => LLVMBuildUDiv(self.builder, rs1, divisor, NONAME),
+            (true, true) => LLVMBuildSRem(self.builder, rs1, divisor, NONAME),
+            (false, true) => LLVMBuildURem(self.builder, rs1, divisor, NONAME),
+        };
+        let by_zero_value = if rem { rs1 } else { ones };
+        LLVMBuildSelect(self.builder, by_zero, by_zero_value, value, name)
+    }
+
     unsafe fn emit_fsgnj(&self, rs1: LLVMValueRef, rs2: LLVMValueRef) -> LLVMValueRef {
         self.emit_fsgnj_common(rs1, rs2, |_, b| b)
     }
@@ -5701,15 +7764,76 @@ impl<'a> InstructionTranslator<'a> {
         rs2: LLVMValueRef,
         combine: impl FnOnce(LLVMValueRef, LLVMValueRef) -> LLVMValueRef,
     ) -> LLVMValueRef {
-        let fzero = LLVMConstNull(LLVMTypeOf(rs1));
-        let sign_rs1 = LLVMBuildFCmp(self.builder, LLVMRealOLT, rs1, fzero, NONAME);
-        let sign_rs2 = LLVMBuildFCmp(self.builder, LLVMRealOLT, rs2, fzero, NONAME);
+        // Operate on the raw bits, such that the sign of zeros and NaNs is
+        // taken into account and NaN payloads are preserved.
+        let ty = LLVMTypeOf(rs1);
+        let zero = LLVMConstNull(ty);
+        let sign_rs1 = LLVMBuildICmp(self.builder, LLVMIntSLT, rs1, zero, NONAME);
+        let sign_rs2 = LLVMBuildICmp(self.builder, LLVMIntSLT, rs2, zero, NONAME);
         let exp = combine(sign_rs1, sign_rs2);
         let need_flip = LLVMBuildICmp(self.builder, LLVMIntNE, sign_rs1, exp, NONAME);
-        let rs1_neg = LLVMBuildFNeg(self.builder, rs1, NONAME);
+        let sign_mask = LLVMConstInt(ty, 1 << (LLVMGetIntTypeWidth(ty) - 1), 0);
+        let rs1_neg = LLVMBuildXor(self.builder, rs1, sign_mask, NONAME);
         LLVMBuildSelect(self.builder, need_flip, rs1_neg, rs1, NONAME)
     }
 
+    /// Emit a call to the software implementation of a floating-point
+    /// operation, which honors the rounding mode and accrues exception flags.
+    unsafe fn emit_exact_fp(
+        &self,
+        op: FpOp,
+        rm: u32,
+        double: bool,
+        args: &[LLVMValueRef],
+    ) -> LLVMValueRef {
+        let mut operands =
+            [LLVMConstInt(LLVMInt64TypeInContext(self.section.engine.context), 0, 0); 3];
+        for (operand, &arg) in operands.iter_mut().zip(args) {
+            *operand = if LLVMTypeOf(arg) == LLVMInt64TypeInContext(self.section.engine.context) {
+                arg
+            } else {
+                LLVMBuildZExt(
+                    self.builder,
+                    arg,
+                    LLVMInt64TypeInContext(self.section.engine.context),
+                    NONAME,
+                )
+            };
+            if !matches!(op, FpOp::CvtFromW | FpOp::CvtFromWu) {
+                *operand = self.emit_flush_subnormal_reg(*operand, double);
+            }
+        }
+        let result = self.section.emit_call(
+            "banshee_fp_op",
+            [
+                self.section.state_ptr,
+                LLVMConstInt(
+                    LLVMInt8TypeInContext(self.section.engine.context),
+                    op as u64,
+                    0,
+                ),
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    rm as u64,
+                    0,
+                ),
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    double as u64,
+                    0,
+                ),
+                operands[0],
+                operands[1],
+                operands[2],
+            ],
+        );
+        match op {
+            FpOp::Eq | FpOp::Lt | FpOp::Le | FpOp::CvtToW | FpOp::CvtToWu => result,
+            FpOp::CvtToOther => self.emit_flush_subnormal_reg(result, !double),
+            _ => self.emit_flush_subnormal_reg(result, double),
+        }
+    }
+
     unsafe fn emit_binary_float_intrinsic(
         &self,
         name: &str,
@@ -5726,8 +7850,29 @@ impl<'a> InstructionTranslator<'a> {
         LLVMBuildCall(self.builder, decl, [rs1, rs2].as_mut_ptr(), 2, NONAME)
     }
 
+    /// Emit a call to an intrinsic which is overloaded on the type of its
+    /// first argument.
+    unsafe fn emit_intrinsic(&self, name: &str, args: &[LLVMValueRef]) -> LLVMValueRef {
+        let id = LLVMLookupIntrinsicID(name.as_ptr() as *const _, name.len());
+        let decl = LLVMGetIntrinsicDeclaration(
+            self.section.engine.modules[self.section.elf.cluster_id],
+            id,
+            [LLVMTypeOf(args[0])].as_mut_ptr(),
+            1,
+        );
+        let mut args = args.to_vec();
+        LLVMBuildCall(
+            self.builder,
+            decl,
+            args.as_mut_ptr(),
+            args.len() as u32,
+            NONAME,
+        )
+    }
+
     unsafe fn emit_rd_rs1_shamt(&self, data: riscv::FormatRdRs1Shamt) -> Result<()> {
         trace!(
+            target: log_target::TRANSLATE,
             "{} x{} = x{}, 0x{:x}",
             data.op,
             data.rd,
@@ -5735,13 +7880,42 @@ impl<'a> InstructionTranslator<'a> {
             data.shamt
         );
         let rs1 = self.read_reg(data.rs1);
-        let shamt = LLVMConstInt(LLVMInt32Type(), data.shamt as u64, 0);
+        let shamt = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            data.shamt as u64,
+            0,
+        );
         let name = format!("{}\0", data.op);
         let name = name.as_ptr() as *const _;
         let value = match data.op {
             riscv::OpcodeRdRs1Shamt::Slli => LLVMBuildShl(self.builder, rs1, shamt, name),
             riscv::OpcodeRdRs1Shamt::Srli => LLVMBuildLShr(self.builder, rs1, shamt, name),
             riscv::OpcodeRdRs1Shamt::Srai => LLVMBuildAShr(self.builder, rs1, shamt, name),
+            riscv::OpcodeRdRs1Shamt::Rori => self.emit_intrinsic("llvm.fshr", &[rs1, rs1, shamt]),
+            // Only `orc.b`, which sets each non-zero byte to all ones.
+            riscv::OpcodeRdRs1Shamt::Gorci if data.shamt == 7 => {
+                let bytes_ty =
+                    LLVMVectorType(LLVMInt8TypeInContext(self.section.engine.context), 4);
+                let bytes = LLVMBuildBitCast(self.builder, rs1, bytes_ty, NONAME);
+                let nonzero = LLVMBuildICmp(
+                    self.builder,
+                    LLVMIntNE,
+                    bytes,
+                    LLVMConstNull(bytes_ty),
+                    NONAME,
+                );
+                let bytes = LLVMBuildSExt(self.builder, nonzero, bytes_ty, NONAME);
+                LLVMBuildBitCast(
+                    self.builder,
+                    bytes,
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    name,
+                )
+            }
+            // Only `rev8`, which reverses the byte order.
+            riscv::OpcodeRdRs1Shamt::Grevi if data.shamt == 24 => {
+                self.emit_intrinsic("llvm.bswap", &[rs1])
+            }
             _ => bail!("Unsupported opcode {}", data.op),
         };
         self.write_reg(data.rd, value);
@@ -5749,7 +7923,7 @@ impl<'a> InstructionTranslator<'a> {
     }
 
     unsafe fn emit_rs1(&self, data: riscv::FormatRs1) -> Result<()> {
-        trace!("{} x{}", data.op, data.rs1);
+        trace!(target: log_target::TRANSLATE, "{} x{}", data.op, data.rs1);
         let name = format!("{}\0", data.op);
         let _name = name.as_ptr() as *const _;
         let rs1 = self.read_reg(data.rs1);
@@ -5767,7 +7941,7 @@ impl<'a> InstructionTranslator<'a> {
     }
 
     unsafe fn emit_rs1_rs2(&self, data: riscv::FormatRs1Rs2) -> Result<()> {
-        trace!("{} x{}, x{}", data.op, data.rs1, data.rs2);
+        trace!(target: log_target::TRANSLATE, "{} x{}, x{}", data.op, data.rs1, data.rs2);
         let name = format!("{}\0", data.op);
         let _name = name.as_ptr() as *const _;
         let rs1 = self.read_reg(data.rs1);
@@ -5776,41 +7950,7 @@ impl<'a> InstructionTranslator<'a> {
         // Perform the SSR write op
         match data.op {
             riscv::OpcodeRs1Rs2::Scfgw => {
-                // reorder rs2 to form address
-                // rs2[11:5]=reg_word rs2[4:0]=dm -> addr_off = {dm, reg_word[4:0], 000}
-                let reg = LLVMBuildLShr(
-                    self.builder,
-                    rs2,
-                    LLVMConstInt(LLVMInt32Type(), 2 as u64, 0),
-                    NONAME,
-                );
-                let reg_masked = LLVMBuildAnd(
-                    self.builder,
-                    reg,
-                    LLVMConstInt(LLVMInt32Type(), 0xf8 as u64, 0),
-                    NONAME,
-                );
-                let rs2_dm = LLVMBuildAnd(
-                    self.builder,
-                    rs2,
-                    LLVMConstInt(LLVMInt32Type(), 0x1f as u64, 0),
-                    NONAME,
-                );
-                let rs2_dm_shifted = LLVMBuildShl(
-                    self.builder,
-                    rs2_dm,
-                    LLVMConstInt(LLVMInt32Type(), 8 as u64, 0),
-                    NONAME,
-                );
-                let addr_off = LLVMBuildOr(self.builder, rs2_dm_shifted, reg_masked, NONAME);
-
-                let addr = LLVMBuildAdd(
-                    self.builder,
-                    LLVMConstInt(LLVMInt32Type(), SSR_BASE, 0),
-                    addr_off,
-                    NONAME,
-                );
-                self.write_mem(addr, rs1, 2);
+                self.emit_ssr_cfg_write(rs2, rs1);
                 return Ok(());
             }
             _ => (),
@@ -5832,14 +7972,32 @@ impl<'a> InstructionTranslator<'a> {
     }
 
     unsafe fn emit_unit(&self, data: riscv::FormatUnit) -> Result<()> {
-        trace!("{}", data.op,);
+        trace!(target: log_target::TRANSLATE, "{}", data.op,);
         match data.op {
-            riscv::OpcodeUnit::Wfi => {
+            riscv::OpcodeUnit::Ecall | riscv::OpcodeUnit::Ebreak if self.section.engine.traps => {
+                self.emit_trace();
+                let (cause, tval) = match data.op {
+                    riscv::OpcodeUnit::Ecall => (
+                        11,
+                        LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0, 0),
+                    ),
+                    _ => (3, self.section.addr_value(self.addr)),
+                };
+                self.section.emit_trap(self.addr, cause, tval, None);
+            }
+            riscv::OpcodeUnit::Wfi | riscv::OpcodeUnit::Ecall => {
                 self.emit_trace();
-                let terminate = self
-                    .section
-                    .emit_call("banshee_wfi", [self.section.state_ptr]);
-                let terminate = LLVMBuildIntCast(self.builder, terminate, LLVMInt1Type(), NONAME);
+                let name = match data.op {
+                    riscv::OpcodeUnit::Wfi => "banshee_wfi",
+                    _ => "banshee_ecall",
+                };
+                let terminate = self.section.emit_call(name, [self.section.state_ptr]);
+                let terminate = LLVMBuildIntCast(
+                    self.builder,
+                    terminate,
+                    LLVMInt1TypeInContext(self.section.engine.context),
+                    NONAME,
+                );
                 let bb_terminate =
                     LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
                 let bb_wake_up = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
@@ -5848,7 +8006,7 @@ impl<'a> InstructionTranslator<'a> {
                 LLVMBuildCondBr(self.builder, terminate, bb_terminate, bb_wake_up);
                 // Terminate
                 LLVMPositionBuilderAtEnd(self.builder, bb_terminate);
-                LLVMBuildRetVoid(self.builder);
+                self.section.emit_return();
                 // Continue
                 LLVMPositionBuilderAtEnd(self.builder, bb_wake_up);
             }
@@ -5860,25 +8018,37 @@ impl<'a> InstructionTranslator<'a> {
                 let mstatus_pie = LLVMBuildAnd(
                     self.builder,
                     mstatus,
-                    LLVMConstInt(LLVMInt32Type(), 1 << 7, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        1 << 7,
+                        0,
+                    ),
                     NONAME,
                 );
                 let mstatus_mie = LLVMBuildLShr(
                     self.builder,
                     mstatus_pie,
-                    LLVMConstInt(LLVMInt32Type(), 4, 0),
+                    LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 4, 0),
                     NONAME,
                 );
                 let mstatus_masked = LLVMBuildAnd(
                     self.builder,
                     mstatus,
-                    LLVMConstInt(LLVMInt32Type(), !((1 << 3) | (1 << 7)), 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        !((1 << 3) | (1 << 7)),
+                        0,
+                    ),
                     NONAME,
                 );
                 let mstatus_pie_set = LLVMBuildOr(
                     self.builder,
                     mstatus_masked,
-                    LLVMConstInt(LLVMInt32Type(), 1 << 7, 0),
+                    LLVMConstInt(
+                        LLVMInt32TypeInContext(self.section.engine.context),
+                        1 << 7,
+                        0,
+                    ),
                     NONAME,
                 );
                 let mstatus_wb = LLVMBuildOr(self.builder, mstatus_pie_set, mstatus_mie, NONAME);
@@ -5888,7 +8058,7 @@ impl<'a> InstructionTranslator<'a> {
                 LLVMBuildStore(self.builder, target, self.section.indirect_target_var);
                 LLVMBuildStore(
                     self.builder,
-                    LLVMConstInt(LLVMInt32Type(), self.addr as u64, 0),
+                    self.section.addr_value(self.addr),
                     self.section.indirect_addr_var,
                 );
                 LLVMBuildBr(self.builder, self.section.indirect_bb);
@@ -5923,7 +8093,11 @@ impl<'a> InstructionTranslator<'a> {
         let mut mip = LLVMBuildAnd(
             self.builder,
             mip_old,
-            LLVMConstInt(LLVMInt32Type(), !((1 << 3) | (1 << 19)), 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                !((1 << 3) | (1 << 19)),
+                0,
+            ),
             NONAME,
         );
         // mip |= (clint_ret << 3) // set MSIP bit
@@ -5933,7 +8107,7 @@ impl<'a> InstructionTranslator<'a> {
             LLVMBuildShl(
                 self.builder,
                 clint_ret,
-                LLVMConstInt(LLVMInt32Type(), 3, 0),
+                LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 3, 0),
                 NONAME,
             ),
             NONAME,
@@ -5945,7 +8119,7 @@ impl<'a> InstructionTranslator<'a> {
             LLVMBuildShl(
                 self.builder,
                 cl_clint_ret,
-                LLVMConstInt(LLVMInt32Type(), 19, 0),
+                LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 19, 0),
                 NONAME,
             ),
             NONAME,
@@ -6000,21 +8174,25 @@ impl<'a> InstructionTranslator<'a> {
         let mstatus_mie = LLVMBuildAnd(
             self.builder,
             mstatus,
-            LLVMConstInt(LLVMInt32Type(), 1 << 3, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                1 << 3,
+                0,
+            ),
             NONAME,
         );
         let is_mstatus_mie = LLVMBuildICmp(
             self.builder,
             LLVMIntNE,
             mstatus_mie,
-            LLVMConstInt(LLVMInt32Type(), 0, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0, 0),
             NONAME,
         );
         let is_any_interrupt_pending = LLVMBuildICmp(
             self.builder,
             LLVMIntNE,
             mirq,
-            LLVMConstInt(LLVMInt32Type(), 0, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0, 0),
             NONAME,
         );
         let is_irq = LLVMBuildAnd(
@@ -6038,13 +8216,17 @@ impl<'a> InstructionTranslator<'a> {
             LLVMBuildAnd(
                 self.builder,
                 mstatus,
-                LLVMConstInt(LLVMInt32Type(), !((1 << 3) | (1 << 7)), 0),
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    !((1 << 3) | (1 << 7)),
+                    0,
+                ),
                 NONAME,
             ),
             LLVMBuildShl(
                 self.builder,
                 mstatus_mie,
-                LLVMConstInt(LLVMInt32Type(), 4, 0),
+                LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 4, 0),
                 NONAME,
             ),
             NONAME,
@@ -6052,7 +8234,11 @@ impl<'a> InstructionTranslator<'a> {
         self.write_csr_silent(riscv::Csr::Mstatus as u32, mstatus_wb);
 
         // Set mcause register by priority encoding
-        let mcause_val_p = LLVMBuildAlloca(self.builder, LLVMInt32Type(), NONAME);
+        let mcause_val_p = LLVMBuildAlloca(
+            self.builder,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
         let bb_write_mcause = LLVMCreateBasicBlockInContext(
             self.section.engine.context,
             b"bb_write_mcause\0".as_ptr() as *const _,
@@ -6073,14 +8259,18 @@ impl<'a> InstructionTranslator<'a> {
             self.builder,
             LLVMIntNE,
             clint_ret,
-            LLVMConstInt(LLVMInt32Type(), 0, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0, 0),
             NONAME,
         );
         LLVMBuildCondBr(self.builder, is_msip, bb_is_msip, bb_is_not_msip);
         LLVMPositionBuilderAtEnd(self.builder, bb_is_msip);
         LLVMBuildStore(
             self.builder,
-            LLVMConstInt(LLVMInt32Type(), (1 << 31) | 3, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                (1 << 31) | 3,
+                0,
+            ),
             mcause_val_p,
         );
         LLVMBuildBr(self.builder, bb_write_mcause);
@@ -6100,14 +8290,18 @@ impl<'a> InstructionTranslator<'a> {
             self.builder,
             LLVMIntNE,
             cl_clint_ret,
-            LLVMConstInt(LLVMInt32Type(), 0, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0, 0),
             NONAME,
         );
         LLVMBuildCondBr(self.builder, is_mcip, bb_is_mcip, bb_is_not_mcip);
         LLVMPositionBuilderAtEnd(self.builder, bb_is_mcip);
         LLVMBuildStore(
             self.builder,
-            LLVMConstInt(LLVMInt32Type(), (1 << 31) | 19, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                (1 << 31) | 19,
+                0,
+            ),
             mcause_val_p,
         );
         LLVMBuildBr(self.builder, bb_write_mcause);
@@ -6115,7 +8309,11 @@ impl<'a> InstructionTranslator<'a> {
         // Failed to find mcause -> set to 0
         LLVMBuildStore(
             self.builder,
-            LLVMConstInt(LLVMInt32Type(), (1 << 31) | 0, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                (1 << 31) | 0,
+                0,
+            ),
             mcause_val_p,
         );
         LLVMBuildBr(self.builder, bb_write_mcause);
@@ -6130,16 +8328,13 @@ impl<'a> InstructionTranslator<'a> {
         // load mtvec address from CSR
         let target = self.read_csr_silent(riscv::Csr::Mtvec as u32);
         // write the next PC to MEPC CSR
-        self.write_csr_silent(
-            riscv::Csr::Mepc as u32,
-            LLVMConstInt(LLVMInt32Type(), self.addr, 0),
-        );
+        self.write_csr_silent(riscv::Csr::Mepc as u32, self.section.addr_value(self.addr));
 
         // Use the prepared indirect jump switch statement.
         LLVMBuildStore(self.builder, target, self.section.indirect_target_var);
         LLVMBuildStore(
             self.builder,
-            LLVMConstInt(LLVMInt32Type(), self.addr as u64, 0),
+            self.section.addr_value(self.addr),
             self.section.indirect_addr_var,
         );
         LLVMBuildBr(self.builder, self.section.indirect_bb);
@@ -6236,10 +8431,11 @@ impl<'a> InstructionTranslator<'a> {
                     NONAME,
                 )
             } else {
-                // Get instruction's latency or use default of one cycle
+                // Get instruction's latency or use the cycle model's default
+                let default = self.section.engine.config.cycle_model.cycles(&inst_name);
                 LLVMConstInt(
                     LLVMTypeOf(max_cycle),
-                    self.get_latency(inst_name, 1) as u64,
+                    self.get_latency(inst_name, default) as u64,
                     0,
                 )
             };
@@ -6269,15 +8465,30 @@ impl<'a> InstructionTranslator<'a> {
 
         // Compose a list of accesses.
         let accesses = self.trace_accesses.borrow();
-        let mut val_access = LLVMConstNull(LLVMArrayType(LLVMInt16Type(), accesses.len() as u32));
-        let mut val_data = LLVMConstNull(LLVMArrayType(LLVMInt64Type(), accesses.len() as u32));
+        let mut val_access = LLVMConstNull(LLVMArrayType(
+            LLVMInt16TypeInContext(self.section.engine.context),
+            accesses.len() as u32,
+        ));
+        let mut val_data = LLVMConstNull(LLVMArrayType(
+            LLVMInt64TypeInContext(self.section.engine.context),
+            accesses.len() as u32,
+        ));
         for (i, &(access, data)) in accesses.iter().enumerate().take(TRACE_BUFFER_LEN as usize) {
             let access: u16 = std::mem::transmute(access);
-            let data = LLVMBuildZExt(self.builder, data, LLVMInt64Type(), NONAME);
+            let data = LLVMBuildZExt(
+                self.builder,
+                data,
+                LLVMInt64TypeInContext(self.section.engine.context),
+                NONAME,
+            );
             val_access = LLVMBuildInsertValue(
                 self.builder,
                 val_access,
-                LLVMConstInt(LLVMInt16Type(), access as u64, 0),
+                LLVMConstInt(
+                    LLVMInt16TypeInContext(self.section.engine.context),
+                    access as u64,
+                    0,
+                ),
                 i as u32,
                 NONAME,
             );
@@ -6299,22 +8510,46 @@ impl<'a> InstructionTranslator<'a> {
         );
         LLVMBuildStore(self.builder, val_access, ptr_access);
         LLVMBuildStore(self.builder, val_data, ptr_data);
-        let ptr_access = LLVMBuildPtrToInt(self.builder, ptr_access, LLVMInt64Type(), NONAME);
-        let ptr_data = LLVMBuildPtrToInt(self.builder, ptr_data, LLVMInt64Type(), NONAME);
+        let ptr_access = LLVMBuildPtrToInt(
+            self.builder,
+            ptr_access,
+            LLVMInt64TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let ptr_data = LLVMBuildPtrToInt(
+            self.builder,
+            ptr_data,
+            LLVMInt64TypeInContext(self.section.engine.context),
+            NONAME,
+        );
 
         // Assemble the slice arguments in the format tha rust expects
         // `(ptr, len)`.
-        let len = LLVMConstInt(LLVMInt64Type(), accesses.len() as u64, 0);
-        let slice_access = LLVMConstNull(LLVMArrayType(LLVMInt64Type(), 2));
+        let len = LLVMConstInt(
+            LLVMInt64TypeInContext(self.section.engine.context),
+            accesses.len() as u64,
+            0,
+        );
+        let slice_access = LLVMConstNull(LLVMArrayType(
+            LLVMInt64TypeInContext(self.section.engine.context),
+            2,
+        ));
         let slice_access = LLVMBuildInsertValue(self.builder, slice_access, ptr_access, 0, NONAME);
         let slice_access = LLVMBuildInsertValue(self.builder, slice_access, len, 1, NONAME);
-        let slice_data = LLVMConstNull(LLVMArrayType(LLVMInt64Type(), 2));
+        let slice_data = LLVMConstNull(LLVMArrayType(
+            LLVMInt64TypeInContext(self.section.engine.context),
+            2,
+        ));
         let slice_data = LLVMBuildInsertValue(self.builder, slice_data, ptr_data, 0, NONAME);
         let slice_data = LLVMBuildInsertValue(self.builder, slice_data, len, 1, NONAME);
 
         // Call the trace function.
-        let addr = LLVMConstInt(LLVMInt32Type(), self.addr as u64, 0);
-        let inst = LLVMConstInt(LLVMInt32Type(), self.inst.raw() as u64, 0);
+        let addr = self.section.addr_value(self.addr);
+        let inst = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            self.raw as u64,
+            0,
+        );
         self.section.emit_call(
             "banshee_trace",
             [self.section.state_ptr, addr, inst, slice_access, slice_data],
@@ -6330,7 +8565,14 @@ impl<'a> InstructionTranslator<'a> {
 
     /// Emit the code to read-modify-write a CSR with an immediate rs1.
     unsafe fn emit_csr_imm(&self, data: riscv::FormatImm12RdRs1) -> Result<()> {
-        self.emit_csr(data, LLVMConstInt(LLVMInt32Type(), data.rs1 as u64, 0))
+        self.emit_csr(
+            data,
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                data.rs1 as u64,
+                0,
+            ),
+        )
     }
 
     /// Emit the code to read-modify-write a CSR with a register rs1.
@@ -6389,7 +8631,7 @@ impl<'a> InstructionTranslator<'a> {
         let aligned_addr = LLVMBuildAnd(
             self.builder,
             addr,
-            LLVMConstInt(LLVMInt32Type(), !3, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), !3, 0),
             NONAME,
         );
 
@@ -6403,7 +8645,7 @@ impl<'a> InstructionTranslator<'a> {
         let mut bb_no = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
         LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_yes);
         LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_no);
-        LLVMBuildCondBr(self.builder, is_tcdm, bb_yes, bb_no);
+        LLVMBuildCondBr(self.builder, self.fast_access(is_tcdm), bb_yes, bb_no);
 
         // Emit the TCDM fast case.
         LLVMPositionBuilderAtEnd(self.builder, bb_yes);
@@ -6419,7 +8661,7 @@ impl<'a> InstructionTranslator<'a> {
             bb_no = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
             LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_yes);
             LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_no);
-            LLVMBuildCondBr(self.builder, is_tcdm, bb_yes, bb_no);
+            LLVMBuildCondBr(self.builder, self.fast_access(is_tcdm), bb_yes, bb_no);
 
             // Emit the external TCDM fast case.
             LLVMPositionBuilderAtEnd(self.builder, bb_yes);
@@ -6459,11 +8701,15 @@ impl<'a> InstructionTranslator<'a> {
                 // LLVMBuildBitCast(
                 //     self.builder,
                 //     self.section.state_ptr,
-                //     LLVMPointerType(LLVMInt8Type(), 0),
+                //     LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
                 //     NONAME,
                 // ),
                 aligned_addr,
-                LLVMConstInt(LLVMInt8Type(), size as u64, 0),
+                LLVMConstInt(
+                    LLVMInt8TypeInContext(self.section.engine.context),
+                    size as u64,
+                    0,
+                ),
             ]
             .as_mut_ptr(),
             3,
@@ -6474,31 +8720,45 @@ impl<'a> InstructionTranslator<'a> {
 
         // Build the PHI node to bring the two together.
         LLVMPositionBuilderAtEnd(self.builder, bb_end);
-        let phi = LLVMBuildPhi(self.builder, LLVMInt32Type(), NONAME);
+        let phi = LLVMBuildPhi(
+            self.builder,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
         LLVMAddIncoming(phi, values.as_mut_ptr(), bbs.as_mut_ptr(), phi_size as u32);
 
         // Align the read.
         let shift = LLVMBuildAnd(
             self.builder,
             addr,
-            LLVMConstInt(LLVMInt32Type(), 3, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 3, 0),
             NONAME,
         );
         let shift = LLVMBuildMul(
             self.builder,
             shift,
-            LLVMConstInt(LLVMInt32Type(), 8, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 8, 0),
             NONAME,
         );
         let value = LLVMBuildLShr(self.builder, phi, shift, NONAME);
 
         // Align narrow reads, and perform truncation and extension.
-        let ty = LLVMIntType(8 << size);
+        let ty = LLVMIntTypeInContext(self.section.engine.context, 8 << size);
         let value = LLVMBuildTrunc(self.builder, value, ty, NONAME);
         let value = if sext {
-            LLVMBuildSExt(self.builder, value, LLVMInt32Type(), NONAME)
+            LLVMBuildSExt(
+                self.builder,
+                value,
+                LLVMInt32TypeInContext(self.section.engine.context),
+                NONAME,
+            )
         } else {
-            LLVMBuildZExt(self.builder, value, LLVMInt32Type(), NONAME)
+            LLVMBuildZExt(
+                self.builder,
+                value,
+                LLVMInt32TypeInContext(self.section.engine.context),
+                NONAME,
+            )
         };
 
         value
@@ -6516,11 +8776,12 @@ impl<'a> InstructionTranslator<'a> {
         let mut bb_no = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
         LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_yes);
         LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_no);
-        LLVMBuildCondBr(self.builder, is_tcdm, bb_yes, bb_no);
+        LLVMBuildCondBr(self.builder, self.fast_access(is_tcdm), bb_yes, bb_no);
 
         // Emit the TCDM fast case.
         LLVMPositionBuilderAtEnd(self.builder, bb_yes);
-        let ty = LLVMIntType(8 << size);
+        self.emit_count_access(MemRegion::Tcdm);
+        let ty = LLVMIntTypeInContext(self.section.engine.context, 8 << size);
         {
             let pty = LLVMPointerType(ty, 0);
             let value = LLVMBuildTrunc(self.builder, value, ty, NONAME);
@@ -6537,10 +8798,11 @@ impl<'a> InstructionTranslator<'a> {
             bb_no = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
             LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_yes);
             LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_no);
-            LLVMBuildCondBr(self.builder, is_tcdm, bb_yes, bb_no);
+            LLVMBuildCondBr(self.builder, self.fast_access(is_tcdm), bb_yes, bb_no);
 
             // Emit the external TCDM fast case.
             LLVMPositionBuilderAtEnd(self.builder, bb_yes);
+            self.emit_count_access(MemRegion::TcdmExternal);
             {
                 let pty = LLVMPointerType(ty, 0);
                 let value = LLVMBuildTrunc(self.builder, value, ty, NONAME);
@@ -6556,7 +8818,7 @@ impl<'a> InstructionTranslator<'a> {
         let aligned_addr = LLVMBuildAnd(
             self.builder,
             addr,
-            LLVMConstInt(LLVMInt32Type(), !3, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), !3, 0),
             NONAME,
         );
 
@@ -6564,20 +8826,25 @@ impl<'a> InstructionTranslator<'a> {
         let shift = LLVMBuildAnd(
             self.builder,
             addr,
-            LLVMConstInt(LLVMInt32Type(), 3, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 3, 0),
             NONAME,
         );
         let shift = LLVMBuildMul(
             self.builder,
             shift,
-            LLVMConstInt(LLVMInt32Type(), 8, 0),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 8, 0),
             NONAME,
         );
 
         // Align the data to the address and generate a bit mask.
         let mask = LLVMConstNull(ty);
         let mask = LLVMBuildNot(self.builder, mask, NONAME);
-        let mask = LLVMBuildZExt(self.builder, mask, LLVMInt32Type(), NONAME);
+        let mask = LLVMBuildZExt(
+            self.builder,
+            mask,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            NONAME,
+        );
         let mask = LLVMBuildShl(self.builder, mask, shift, NONAME);
         let value = LLVMBuildShl(self.builder, value, shift, NONAME);
 
@@ -6599,29 +8866,178 @@ impl<'a> InstructionTranslator<'a> {
         LLVMPositionBuilderAtEnd(self.builder, bb_nossr);
 
         // Emit the regular slow case.
-        self.section.emit_call(
+        let halt = self.section.emit_call(
             "banshee_store",
             [
                 self.section.state_ptr,
                 aligned_addr,
                 value,
                 mask,
-                LLVMConstInt(LLVMInt8Type(), size as u64, 0),
+                LLVMConstInt(
+                    LLVMInt8TypeInContext(self.section.engine.context),
+                    size as u64,
+                    0,
+                ),
             ],
         );
-        LLVMBuildBr(self.builder, bb_end);
+        let halt = LLVMBuildIntCast(
+            self.builder,
+            halt,
+            LLVMInt1TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let bb_halt = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_halt);
+        LLVMBuildCondBr(self.builder, halt, bb_halt, bb_end);
+
+        // Terminate if the store halts execution, as per the exit protocol.
+        LLVMPositionBuilderAtEnd(self.builder, bb_halt);
+        self.section.emit_return();
 
         // Reconverge.
         LLVMPositionBuilderAtEnd(self.builder, bb_end);
     }
 
+    /// Emit the code to return from the binary if execution should stop.
+    ///
+    /// Emitted at the end of basic blocks, such that all harts wind down
+    /// promptly once one of them faults or halts execution. Also enforces the
+    /// instruction limit, if any.
+    unsafe fn emit_stop_check(&self) {
+        if let Some(max) = self.section.engine.max_instret {
+            let instret = LLVMBuildLoad(self.builder, self.instret_ptr(), NONAME);
+            let exceeded = LLVMBuildICmp(
+                self.builder,
+                LLVMIntUGE,
+                instret,
+                LLVMConstInt(LLVMTypeOf(instret), max, 0),
+                NONAME,
+            );
+            let bb_exceeded = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+            let bb_cont = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+            LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_cont);
+            LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_exceeded);
+            LLVMBuildCondBr(self.builder, exceeded, bb_exceeded, bb_cont);
+            LLVMPositionBuilderAtEnd(self.builder, bb_exceeded);
+            self.section.emit_call(
+                "banshee_abort_limit",
+                [self.section.state_ptr, self.section.addr_value(self.addr)],
+            );
+            self.section.emit_return();
+            LLVMPositionBuilderAtEnd(self.builder, bb_cont);
+        }
+        let stop = self
+            .section
+            .emit_call("banshee_stop_requested", [self.section.state_ptr]);
+        let stop = LLVMBuildIntCast(
+            self.builder,
+            stop,
+            LLVMInt1TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let bb_stop = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+        let bb_cont = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_cont);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_stop);
+        LLVMBuildCondBr(self.builder, stop, bb_stop, bb_cont);
+        LLVMPositionBuilderAtEnd(self.builder, bb_stop);
+        self.section.emit_return();
+        LLVMPositionBuilderAtEnd(self.builder, bb_cont);
+    }
+
+    /// Emit the check whether the hart spins in a loop without side effects,
+    /// before taking the branch or jump which closes it.
+    unsafe fn emit_spin_check(&self) {
+        trace!(target: log_target::TRANSLATE, "Emit livelock check at 0x{:x}", self.addr);
+        self.section.emit_call(
+            "banshee_spin",
+            [self.section.state_ptr, self.section.addr_value(self.addr)],
+        );
+    }
+
+    /// Emit the call of the breakpoint callbacks at this instruction, and
+    /// return from the binary if they stop execution.
+    unsafe fn emit_breakpoint(&self) {
+        trace!(target: log_target::TRANSLATE, "Emit breakpoint at 0x{:x}", self.addr);
+        let stop = self.section.emit_call(
+            "banshee_breakpoint",
+            [self.section.state_ptr, self.section.addr_value(self.addr)],
+        );
+        let stop = LLVMBuildIntCast(
+            self.builder,
+            stop,
+            LLVMInt1TypeInContext(self.section.engine.context),
+            NONAME,
+        );
+        let bb_stop = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+        let bb_cont = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_cont);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_stop);
+        LLVMBuildCondBr(self.builder, stop, bb_stop, bb_cont);
+        LLVMPositionBuilderAtEnd(self.builder, bb_stop);
+        self.section.emit_return();
+        LLVMPositionBuilderAtEnd(self.builder, bb_cont);
+    }
+
+    /// Emit the code to count a memory access for the execution statistics.
+    ///
+    /// Only used for the accesses which do not go through `banshee_load` and
+    /// `banshee_store`. Does nothing if statistics are disabled.
+    unsafe fn emit_count_access(&self, region: MemRegion) {
+        if !self.section.engine.stats {
+            return;
+        }
+        let ptr = self.section.emit_call(
+            "banshee_mem_access_ptr",
+            [
+                self.section.state_ptr,
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    region as u64,
+                    0,
+                ),
+            ],
+        );
+        self.emit_increment(ptr);
+    }
+
+    /// Emit the code to increment a 64 bit counter in memory.
+    unsafe fn emit_increment(&self, ptr: LLVMValueRef) {
+        let value = LLVMBuildLoad(self.builder, ptr, NONAME);
+        let value = LLVMBuildAdd(
+            self.builder,
+            value,
+            LLVMConstInt(LLVMTypeOf(value), 1, 0),
+            NONAME,
+        );
+        LLVMBuildStore(self.builder, value, ptr);
+    }
+
+    /// Disable a fast memory access if the runtime has to check watchpoints
+    /// or log the access.
+    unsafe fn fast_access(&self, is_fast: LLVMValueRef) -> LLVMValueRef {
+        if self.section.elf.watch {
+            LLVMConstInt(LLVMInt1TypeInContext(self.section.engine.context), 0, 0)
+        } else {
+            is_fast
+        }
+    }
+
     /// Emit the code to check if an address is within the TCDM.
     ///
     /// Returns an `i1` indicating whether it is as first result, and a pointer
     /// to that location in the TCDM.
     unsafe fn emit_tcdm_check(&self, addr: LLVMValueRef) -> (LLVMValueRef, LLVMValueRef) {
-        let tcdm_start = LLVMConstInt(LLVMInt32Type(), self.section.elf.tcdm_start as u64, 0);
-        let tcdm_end = LLVMConstInt(LLVMInt32Type(), self.section.elf.tcdm_end as u64, 0);
+        let tcdm_start = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            self.section.elf.tcdm_start as u64,
+            0,
+        );
+        let tcdm_end = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            self.section.elf.tcdm_end as u64,
+            0,
+        );
         let in_range = LLVMBuildAnd(
             self.builder,
             LLVMBuildICmp(self.builder, LLVMIntUGE, addr, tcdm_start, NONAME),
@@ -6629,8 +9045,8 @@ impl<'a> InstructionTranslator<'a> {
             NONAME,
         );
         let index = LLVMBuildSub(self.builder, addr, tcdm_start, NONAME);
-        let pty32 = LLVMPointerType(LLVMInt32Type(), 0);
-        let pty8 = LLVMPointerType(LLVMInt8Type(), 0);
+        let pty32 = LLVMPointerType(LLVMInt32TypeInContext(self.section.engine.context), 0);
+        let pty8 = LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0);
         let ptr = LLVMBuildGEP(
             self.builder,
             LLVMBuildBitCast(self.builder, self.tcdm_ptr(), pty8, NONAME),
@@ -6648,8 +9064,16 @@ impl<'a> InstructionTranslator<'a> {
         addr: LLVMValueRef,
         tcdm_ext: (u32, u32, u32),
     ) -> (LLVMValueRef, LLVMValueRef) {
-        let tcdm_start = LLVMConstInt(LLVMInt32Type(), tcdm_ext.1 as u64, 0);
-        let tcdm_end = LLVMConstInt(LLVMInt32Type(), tcdm_ext.2 as u64, 0);
+        let tcdm_start = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            tcdm_ext.1 as u64,
+            0,
+        );
+        let tcdm_end = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            tcdm_ext.2 as u64,
+            0,
+        );
         let in_range = LLVMBuildAnd(
             self.builder,
             LLVMBuildICmp(self.builder, LLVMIntUGE, addr, tcdm_start, NONAME),
@@ -6657,8 +9081,8 @@ impl<'a> InstructionTranslator<'a> {
             NONAME,
         );
         let index = LLVMBuildSub(self.builder, addr, tcdm_start, NONAME);
-        let pty32 = LLVMPointerType(LLVMInt32Type(), 0);
-        let pty8 = LLVMPointerType(LLVMInt8Type(), 0);
+        let pty32 = LLVMPointerType(LLVMInt32TypeInContext(self.section.engine.context), 0);
+        let pty8 = LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0);
         let ptr = LLVMBuildGEP(
             self.builder,
             LLVMBuildBitCast(self.builder, self.tcdm_ext_ptr(tcdm_ext.0), pty8, NONAME),
@@ -6670,6 +9094,104 @@ impl<'a> InstructionTranslator<'a> {
         (in_range, ptr)
     }
 
+    /// Emit the code to compute the offset of an SSR configuration register
+    /// into the SSR configuration space, from a register address as used by
+    /// the `scfg*` instructions.
+    ///
+    /// The register address holds the register in bits 11:5 and the data
+    /// mover in bits 4:0, which maps to the offset `{dm, reg[4:0], 000}`.
+    unsafe fn emit_ssr_cfg_offset(&self, word: LLVMValueRef) -> LLVMValueRef {
+        let reg = LLVMBuildAnd(
+            self.builder,
+            LLVMBuildLShr(
+                self.builder,
+                word,
+                LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 2, 0),
+                NONAME,
+            ),
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0xf8, 0),
+            NONAME,
+        );
+        let dm = LLVMBuildAnd(
+            self.builder,
+            word,
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0x1f, 0),
+            NONAME,
+        );
+        let dm = LLVMBuildShl(
+            self.builder,
+            dm,
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 8, 0),
+            NONAME,
+        );
+        LLVMBuildOr(self.builder, dm, reg, NONAME)
+    }
+
+    /// Emit the code to write an SSR configuration register, from a register
+    /// address as used by the `scfg*` instructions.
+    ///
+    /// Writes to data mover `SSR_DM_ALL` are broadcast to all data movers.
+    unsafe fn emit_ssr_cfg_write(&self, word: LLVMValueRef, value: LLVMValueRef) {
+        let ssr_start = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            SSR_BASE,
+            0,
+        );
+        let addr_off = self.emit_ssr_cfg_offset(word);
+        let dm = LLVMBuildAnd(
+            self.builder,
+            word,
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0x1f, 0),
+            NONAME,
+        );
+        let is_all = LLVMBuildICmp(
+            self.builder,
+            LLVMIntEQ,
+            dm,
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                SSR_DM_ALL,
+                0,
+            ),
+            NONAME,
+        );
+        let bb_all = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+        let bb_one = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+        let bb_end = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_end);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_one);
+        LLVMInsertExistingBasicBlockAfterInsertBlock(self.builder, bb_all);
+        LLVMBuildCondBr(self.builder, is_all, bb_all, bb_one);
+
+        // Write the register of each data mover.
+        LLVMPositionBuilderAtEnd(self.builder, bb_all);
+        let reg_off = LLVMBuildAnd(
+            self.builder,
+            addr_off,
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0xff, 0),
+            NONAME,
+        );
+        for dm in 0..self.section.engine.config.ssr.num_dm as u64 {
+            let base = LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                SSR_BASE + (dm << 8),
+                0,
+            );
+            self.write_mem(LLVMBuildAdd(self.builder, base, reg_off, NONAME), value, 2);
+        }
+        LLVMBuildBr(self.builder, bb_end);
+
+        // Write the register of a single data mover.
+        LLVMPositionBuilderAtEnd(self.builder, bb_one);
+        self.write_mem(
+            LLVMBuildAdd(self.builder, ssr_start, addr_off, NONAME),
+            value,
+            2,
+        );
+        LLVMBuildBr(self.builder, bb_end);
+        LLVMPositionBuilderAtEnd(self.builder, bb_end);
+    }
+
     /// Emit the code to check if an address is within the SSR config range.
     ///
     /// Returns an `i1` indicating whether it is as first result, a pointer to
@@ -6679,13 +9201,21 @@ impl<'a> InstructionTranslator<'a> {
         &self,
         addr: LLVMValueRef,
     ) -> (LLVMValueRef, LLVMValueRef, LLVMValueRef) {
-        let ssr_start = LLVMConstInt(LLVMInt32Type(), SSR_BASE, 0);
+        let ssr_start = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            SSR_BASE,
+            0,
+        );
         let ssr_end = LLVMConstInt(
-            LLVMInt32Type(),
+            LLVMInt32TypeInContext(self.section.engine.context),
             SSR_BASE + 32 * 8 * (self.section.engine.config.ssr.num_dm as u32) as u64,
             0,
         );
-        let ssr_size = LLVMConstInt(LLVMInt32Type(), 32 * 8, 0);
+        let ssr_size = LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            32 * 8,
+            0,
+        );
         let in_range = LLVMBuildAnd(
             self.builder,
             LLVMBuildICmp(self.builder, LLVMIntUGE, addr, ssr_start, NONAME),
@@ -6702,7 +9232,7 @@ impl<'a> InstructionTranslator<'a> {
     /// Emit the code necessary to read a value from a register.
     unsafe fn read_reg(&self, rs: u32) -> LLVMValueRef {
         if rs == 0 {
-            LLVMConstInt(LLVMInt32Type(), 0, 0)
+            LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0, 0)
         } else {
             let ptr = self.reg_ptr(rs);
             let data = LLVMBuildLoad(self.builder, ptr, format!("x{}\0", rs).as_ptr() as *const _);
@@ -6749,18 +9279,23 @@ impl<'a> InstructionTranslator<'a> {
             LLVMBuildBitCast(
                 self.builder,
                 raw_ptr,
-                LLVMPointerType(LLVMDoubleType(), 0),
+                LLVMPointerType(LLVMDoubleTypeInContext(self.section.engine.context), 0),
                 NONAME,
             )
         } else {
             LLVMBuildBitCast(
                 self.builder,
                 raw_ptr,
-                LLVMPointerType(LLVMInt64Type(), 0),
+                LLVMPointerType(LLVMInt64TypeInContext(self.section.engine.context), 0),
                 NONAME,
             )
         };
-        LLVMBuildLoad(self.builder, ptr, format!("f{}\0", rs).as_ptr() as *const _)
+        let value = LLVMBuildLoad(self.builder, ptr, format!("f{}\0", rs).as_ptr() as *const _);
+        if llvm_float {
+            self.emit_flush_subnormal(value)
+        } else {
+            value
+        }
     }
 
     unsafe fn read_freg_vf64b(
@@ -6786,20 +9321,25 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_0 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         // read data1
         let ptr_1 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_1 = LLVMBuildGEP(
             self.builder,
             ptr_1,
-            [LLVMConstInt(LLVMInt8Type(), 1, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt8TypeInContext(self.section.engine.context),
+                1,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -6807,13 +9347,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_2 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_2 = LLVMBuildGEP(
             self.builder,
             ptr_2,
-            [LLVMConstInt(LLVMInt8Type(), 2, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt8TypeInContext(self.section.engine.context),
+                2,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -6821,13 +9366,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_3 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_3 = LLVMBuildGEP(
             self.builder,
             ptr_3,
-            [LLVMConstInt(LLVMInt8Type(), 3, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt8TypeInContext(self.section.engine.context),
+                3,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -6835,13 +9385,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_4 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_4 = LLVMBuildGEP(
             self.builder,
             ptr_4,
-            [LLVMConstInt(LLVMInt8Type(), 4, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt8TypeInContext(self.section.engine.context),
+                4,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -6849,13 +9404,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_5 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_5 = LLVMBuildGEP(
             self.builder,
             ptr_5,
-            [LLVMConstInt(LLVMInt8Type(), 5, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt8TypeInContext(self.section.engine.context),
+                5,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -6863,13 +9423,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_6 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_6 = LLVMBuildGEP(
             self.builder,
             ptr_6,
-            [LLVMConstInt(LLVMInt8Type(), 6, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt8TypeInContext(self.section.engine.context),
+                6,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -6877,13 +9442,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_7 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_7 = LLVMBuildGEP(
             self.builder,
             ptr_7,
-            [LLVMConstInt(LLVMInt8Type(), 7, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt8TypeInContext(self.section.engine.context),
+                7,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -6945,20 +9515,25 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_0 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt16Type(), 0),
+            LLVMPointerType(LLVMInt16TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         // read data1
         let ptr_1 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt16Type(), 0),
+            LLVMPointerType(LLVMInt16TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_1 = LLVMBuildGEP(
             self.builder,
             ptr_1,
-            [LLVMConstInt(LLVMInt16Type(), 1, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt16TypeInContext(self.section.engine.context),
+                1,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -6966,13 +9541,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_2 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt16Type(), 0),
+            LLVMPointerType(LLVMInt16TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_2 = LLVMBuildGEP(
             self.builder,
             ptr_2,
-            [LLVMConstInt(LLVMInt16Type(), 2, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt16TypeInContext(self.section.engine.context),
+                2,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -6980,13 +9560,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_3 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt16Type(), 0),
+            LLVMPointerType(LLVMInt16TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_3 = LLVMBuildGEP(
             self.builder,
             ptr_3,
-            [LLVMConstInt(LLVMInt16Type(), 3, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt16TypeInContext(self.section.engine.context),
+                3,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -7027,14 +9612,14 @@ impl<'a> InstructionTranslator<'a> {
             LLVMBuildBitCast(
                 self.builder,
                 raw_ptr,
-                LLVMPointerType(LLVMFloatType(), 0),
+                LLVMPointerType(LLVMFloatTypeInContext(self.section.engine.context), 0),
                 NONAME,
             )
         } else {
             LLVMBuildBitCast(
                 self.builder,
                 raw_ptr,
-                LLVMPointerType(LLVMInt32Type(), 0),
+                LLVMPointerType(LLVMInt32TypeInContext(self.section.engine.context), 0),
                 NONAME,
             )
         };
@@ -7044,33 +9629,41 @@ impl<'a> InstructionTranslator<'a> {
             LLVMBuildBitCast(
                 self.builder,
                 raw_ptr,
-                LLVMPointerType(LLVMFloatType(), 0),
+                LLVMPointerType(LLVMFloatTypeInContext(self.section.engine.context), 0),
                 NONAME,
             )
         } else {
             LLVMBuildBitCast(
                 self.builder,
                 raw_ptr,
-                LLVMPointerType(LLVMInt32Type(), 0),
+                LLVMPointerType(LLVMInt32TypeInContext(self.section.engine.context), 0),
                 NONAME,
             )
         };
         let ptr_hi = LLVMBuildGEP(
             self.builder,
             ptr_hi,
-            [LLVMConstInt(LLVMInt32Type(), 1, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                1,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
 
-        (
-            LLVMBuildLoad(
-                self.builder,
-                ptr_hi,
-                format!("f{}\0", rs).as_ptr() as *const _,
-            ),
-            LLVMBuildLoad(self.builder, ptr, format!("f{}\0", rs).as_ptr() as *const _),
-        )
+        let hi = LLVMBuildLoad(
+            self.builder,
+            ptr_hi,
+            format!("f{}\0", rs).as_ptr() as *const _,
+        );
+        let lo = LLVMBuildLoad(self.builder, ptr, format!("f{}\0", rs).as_ptr() as *const _);
+        if llvm_float {
+            (self.emit_flush_subnormal(hi), self.emit_flush_subnormal(lo))
+        } else {
+            (hi, lo)
+        }
     }
 
     /// Emit the code to read a f16 value from a float register.
@@ -7084,7 +9677,7 @@ impl<'a> InstructionTranslator<'a> {
         let ptr = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         LLVMBuildLoad(self.builder, ptr, format!("f{}\0", rs).as_ptr() as *const _)
@@ -7101,53 +9694,102 @@ impl<'a> InstructionTranslator<'a> {
         let ptr = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt16Type(), 0),
+            LLVMPointerType(LLVMInt16TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         LLVMBuildLoad(self.builder, ptr, format!("f{}\0", rs).as_ptr() as *const _)
     }
 
-    /// Emit the code to read a f32 value from a float register.
+    /// Emit the code to read a f32 value from a float register. Values which
+    /// are not properly NaN-boxed read as the canonical NaN.
     unsafe fn read_freg_f32(&self, rs: u32, llvm_float: bool) -> LLVMValueRef {
         self.emit_possible_ssr_read(rs);
-        let raw_ptr = self.freg_ptr(rs);
-        self.trace_access(
-            TraceAccess::ReadF32Reg(rs as u8),
-            LLVMBuildLoad(self.builder, raw_ptr, NONAME),
+        let raw = LLVMBuildLoad(self.builder, self.freg_ptr(rs), NONAME);
+        self.trace_access(TraceAccess::ReadF32Reg(rs as u8), raw);
+        let hi = LLVMBuildLShr(
+            self.builder,
+            raw,
+            LLVMConstInt(LLVMInt64TypeInContext(self.section.engine.context), 32, 0),
+            NONAME,
         );
-        let ptr = if llvm_float {
-            LLVMBuildBitCast(
+        let boxed = LLVMBuildICmp(
+            self.builder,
+            LLVMIntEQ,
+            hi,
+            LLVMConstInt(
+                LLVMInt64TypeInContext(self.section.engine.context),
+                0xffff_ffff,
+                0,
+            ),
+            NONAME,
+        );
+        let name = format!("f{}\0", rs);
+        let value = LLVMBuildSelect(
+            self.builder,
+            boxed,
+            LLVMBuildTrunc(
                 self.builder,
-                raw_ptr,
-                LLVMPointerType(LLVMFloatType(), 0),
+                raw,
+                LLVMInt32TypeInContext(self.section.engine.context),
                 NONAME,
-            )
-        } else {
-            LLVMBuildBitCast(
+            ),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                0x7fc0_0000,
+                0,
+            ),
+            if llvm_float {
+                NONAME
+            } else {
+                name.as_ptr() as *const _
+            },
+        );
+        if llvm_float {
+            self.emit_flush_subnormal(LLVMBuildBitCast(
                 self.builder,
-                raw_ptr,
-                LLVMPointerType(LLVMInt32Type(), 0),
-                NONAME,
-            )
-        };
-        LLVMBuildLoad(self.builder, ptr, format!("f{}\0", rs).as_ptr() as *const _)
+                value,
+                LLVMFloatTypeInContext(self.section.engine.context),
+                name.as_ptr() as *const _,
+            ))
+        } else {
+            value
+        }
+    }
+
+    /// Emit the code to read the lower 32 bits of a float register, without
+    /// checking the NaN-boxing, for transfers of single-precision values.
+    unsafe fn read_freg_f32_bits(&self, rs: u32) -> LLVMValueRef {
+        self.emit_possible_ssr_read(rs);
+        let raw = LLVMBuildLoad(self.builder, self.freg_ptr(rs), NONAME);
+        self.trace_access(TraceAccess::ReadF32Reg(rs as u8), raw);
+        LLVMBuildTrunc(
+            self.builder,
+            raw,
+            LLVMInt32TypeInContext(self.section.engine.context),
+            format!("f{}\0", rs).as_ptr() as *const _,
+        )
     }
 
     /// Emit the code to write a f64 value to a float register.
     unsafe fn write_freg_f64(&self, rd: u32, data: LLVMValueRef, llvm_float: bool) {
         let raw_ptr = self.freg_ptr(rd);
+        let data = if llvm_float {
+            self.emit_flush_subnormal(data)
+        } else {
+            data
+        };
         let ptr = if llvm_float {
             LLVMBuildBitCast(
                 self.builder,
                 raw_ptr,
-                LLVMPointerType(LLVMDoubleType(), 0),
+                LLVMPointerType(LLVMDoubleTypeInContext(self.section.engine.context), 0),
                 NONAME,
             )
         } else {
             LLVMBuildBitCast(
                 self.builder,
                 raw_ptr,
-                LLVMPointerType(LLVMInt64Type(), 0),
+                LLVMPointerType(LLVMInt64TypeInContext(self.section.engine.context), 0),
                 NONAME,
             )
         };
@@ -7167,19 +9809,28 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_hi = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt32Type(), 0),
+            LLVMPointerType(LLVMInt32TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_hi = LLVMBuildGEP(
             self.builder,
             ptr_hi,
-            [LLVMConstInt(LLVMInt32Type(), 1, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                1,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
         LLVMBuildStore(
             self.builder,
-            LLVMConstInt(LLVMInt32Type(), -1i32 as u64, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                -1i32 as u64,
+                0,
+            ),
             ptr_hi,
         );
 
@@ -7187,25 +9838,30 @@ impl<'a> InstructionTranslator<'a> {
         let ptr1 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMFloatType(), 0),
+            LLVMPointerType(LLVMFloatTypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr2 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMFloatType(), 0),
+            LLVMPointerType(LLVMFloatTypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr2 = LLVMBuildGEP(
             self.builder,
             ptr2,
-            [LLVMConstInt(LLVMInt32Type(), 1, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                1,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
 
-        LLVMBuildStore(self.builder, data1, ptr1);
-        LLVMBuildStore(self.builder, data2, ptr2);
+        LLVMBuildStore(self.builder, self.emit_flush_subnormal(data1), ptr1);
+        LLVMBuildStore(self.builder, self.emit_flush_subnormal(data2), ptr2);
         self.trace_access(
             TraceAccess::WriteFReg(rd as u8),
             LLVMBuildLoad(self.builder, raw_ptr, NONAME),
@@ -7228,7 +9884,7 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_0 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt16Type(), 0),
+            LLVMPointerType(LLVMInt16TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
 
@@ -7236,13 +9892,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_1 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt16Type(), 0),
+            LLVMPointerType(LLVMInt16TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_1 = LLVMBuildGEP(
             self.builder,
             ptr_1,
-            [LLVMConstInt(LLVMInt16Type(), 1, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt16TypeInContext(self.section.engine.context),
+                1,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -7251,13 +9912,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_2 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt16Type(), 0),
+            LLVMPointerType(LLVMInt16TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_2 = LLVMBuildGEP(
             self.builder,
             ptr_2,
-            [LLVMConstInt(LLVMInt16Type(), 2, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt16TypeInContext(self.section.engine.context),
+                2,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -7266,13 +9932,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_3 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt16Type(), 0),
+            LLVMPointerType(LLVMInt16TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_3 = LLVMBuildGEP(
             self.builder,
             ptr_3,
-            [LLVMConstInt(LLVMInt16Type(), 3, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt16TypeInContext(self.section.engine.context),
+                3,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -7307,7 +9978,7 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_0 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
 
@@ -7315,13 +9986,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_1 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_1 = LLVMBuildGEP(
             self.builder,
             ptr_1,
-            [LLVMConstInt(LLVMInt8Type(), 1, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt8TypeInContext(self.section.engine.context),
+                1,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -7330,13 +10006,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_2 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_2 = LLVMBuildGEP(
             self.builder,
             ptr_2,
-            [LLVMConstInt(LLVMInt8Type(), 2, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt8TypeInContext(self.section.engine.context),
+                2,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -7345,13 +10026,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_3 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_3 = LLVMBuildGEP(
             self.builder,
             ptr_3,
-            [LLVMConstInt(LLVMInt8Type(), 3, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt8TypeInContext(self.section.engine.context),
+                3,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -7360,13 +10046,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_4 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_4 = LLVMBuildGEP(
             self.builder,
             ptr_4,
-            [LLVMConstInt(LLVMInt8Type(), 4, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt8TypeInContext(self.section.engine.context),
+                4,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -7375,13 +10066,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_5 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_5 = LLVMBuildGEP(
             self.builder,
             ptr_5,
-            [LLVMConstInt(LLVMInt8Type(), 5, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt8TypeInContext(self.section.engine.context),
+                5,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -7390,13 +10086,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_6 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_6 = LLVMBuildGEP(
             self.builder,
             ptr_6,
-            [LLVMConstInt(LLVMInt8Type(), 6, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt8TypeInContext(self.section.engine.context),
+                6,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -7405,13 +10106,18 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_7 = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt8Type(), 0),
+            LLVMPointerType(LLVMInt8TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_7 = LLVMBuildGEP(
             self.builder,
             ptr_7,
-            [LLVMConstInt(LLVMInt8Type(), 7, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt8TypeInContext(self.section.engine.context),
+                7,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -7445,14 +10151,14 @@ impl<'a> InstructionTranslator<'a> {
             LLVMBuildBitCast(
                 self.builder,
                 raw_ptr,
-                LLVMPointerType(LLVMFloatType(), 0),
+                LLVMPointerType(LLVMFloatTypeInContext(self.section.engine.context), 0),
                 NONAME,
             )
         } else {
             LLVMBuildBitCast(
                 self.builder,
                 raw_ptr,
-                LLVMPointerType(LLVMInt32Type(), 0),
+                LLVMPointerType(LLVMInt32TypeInContext(self.section.engine.context), 0),
                 NONAME,
             )
         };
@@ -7460,7 +10166,12 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_hi = LLVMBuildGEP(
             self.builder,
             ptr_hi,
-            [LLVMConstInt(LLVMInt32Type(), 1, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                1,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
@@ -7470,18 +10181,26 @@ impl<'a> InstructionTranslator<'a> {
             LLVMBuildBitCast(
                 self.builder,
                 raw_ptr,
-                LLVMPointerType(LLVMFloatType(), 0),
+                LLVMPointerType(LLVMFloatTypeInContext(self.section.engine.context), 0),
                 NONAME,
             )
         } else {
             LLVMBuildBitCast(
                 self.builder,
                 raw_ptr,
-                LLVMPointerType(LLVMInt32Type(), 0),
+                LLVMPointerType(LLVMInt32TypeInContext(self.section.engine.context), 0),
                 NONAME,
             )
         };
 
+        let (data1, data0) = if llvm_float {
+            (
+                self.emit_flush_subnormal(data1),
+                self.emit_flush_subnormal(data0),
+            )
+        } else {
+            (data1, data0)
+        };
         LLVMBuildStore(self.builder, data0, ptr);
         LLVMBuildStore(self.builder, data1, ptr_hi);
         self.trace_access(
@@ -7499,35 +10218,49 @@ impl<'a> InstructionTranslator<'a> {
         let ptr_hi = LLVMBuildBitCast(
             self.builder,
             raw_ptr,
-            LLVMPointerType(LLVMInt32Type(), 0),
+            LLVMPointerType(LLVMInt32TypeInContext(self.section.engine.context), 0),
             NONAME,
         );
         let ptr_hi = LLVMBuildGEP(
             self.builder,
             ptr_hi,
-            [LLVMConstInt(LLVMInt32Type(), 1, 0)].as_mut_ptr(),
+            [LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                1,
+                0,
+            )]
+            .as_mut_ptr(),
             1 as u32,
             NONAME,
         );
         LLVMBuildStore(
             self.builder,
-            LLVMConstInt(LLVMInt32Type(), -1i32 as u64, 0),
+            LLVMConstInt(
+                LLVMInt32TypeInContext(self.section.engine.context),
+                -1i32 as u64,
+                0,
+            ),
             ptr_hi,
         );
 
         // Write the actual value.
+        let data = if llvm_float {
+            self.emit_flush_subnormal(data)
+        } else {
+            data
+        };
         let ptr = if llvm_float {
             LLVMBuildBitCast(
                 self.builder,
                 raw_ptr,
-                LLVMPointerType(LLVMFloatType(), 0),
+                LLVMPointerType(LLVMFloatTypeInContext(self.section.engine.context), 0),
                 NONAME,
             )
         } else {
             LLVMBuildBitCast(
                 self.builder,
                 raw_ptr,
-                LLVMPointerType(LLVMInt32Type(), 0),
+                LLVMPointerType(LLVMInt32TypeInContext(self.section.engine.context), 0),
                 NONAME,
             )
         };
@@ -7542,8 +10275,17 @@ impl<'a> InstructionTranslator<'a> {
     /// Emit the code to write a f16 value to a float register.
     unsafe fn write_freg_f16(&self, rd: u32, data: LLVMValueRef) {
         // Nan-box value
-        let nan_box = LLVMConstInt(LLVMInt64Type(), (-1i64 - 0xffff) as u64, 0);
-        let value = LLVMBuildZExt(self.builder, data, LLVMInt64Type(), NONAME);
+        let nan_box = LLVMConstInt(
+            LLVMInt64TypeInContext(self.section.engine.context),
+            (-1i64 - 0xffff) as u64,
+            0,
+        );
+        let value = LLVMBuildZExt(
+            self.builder,
+            data,
+            LLVMInt64TypeInContext(self.section.engine.context),
+            NONAME,
+        );
         let value = LLVMBuildOr(self.builder, nan_box, value, NONAME);
 
         // Store value
@@ -7559,8 +10301,17 @@ impl<'a> InstructionTranslator<'a> {
     /// Emit the code to write a f8 value to a float register.
     unsafe fn write_freg_f8(&self, rd: u32, data: LLVMValueRef) {
         // Nan-box value
-        let nan_box = LLVMConstInt(LLVMInt64Type(), (-1i64 - 0xff) as u64, 0);
-        let value = LLVMBuildZExt(self.builder, data, LLVMInt64Type(), NONAME);
+        let nan_box = LLVMConstInt(
+            LLVMInt64TypeInContext(self.section.engine.context),
+            (-1i64 - 0xff) as u64,
+            0,
+        );
+        let value = LLVMBuildZExt(
+            self.builder,
+            data,
+            LLVMInt64TypeInContext(self.section.engine.context),
+            NONAME,
+        );
         let value = LLVMBuildOr(self.builder, nan_box, value, NONAME);
 
         // Store value
@@ -7573,17 +10324,64 @@ impl<'a> InstructionTranslator<'a> {
         self.emit_possible_ssr_write(rd);
     }
 
+    /// Emit the code to check whether SSRs are enabled and an SSR streams in
+    /// the given direction.
+    unsafe fn emit_ssr_active(&self, ssr: u32, write: bool) -> LLVMValueRef {
+        let enabled_ptr = self.ssr_enabled_ptr();
+        let enabled = LLVMBuildLoad(self.builder, enabled_ptr, NONAME);
+        let active = self.section.emit_call(
+            "banshee_ssr_active",
+            [
+                self.ssr_ptr(ssr),
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    write as u64,
+                    0,
+                ),
+            ],
+        );
+        let enabled = LLVMBuildAnd(self.builder, enabled, active, NONAME);
+        LLVMBuildTrunc(
+            self.builder,
+            enabled,
+            LLVMInt1TypeInContext(self.section.engine.context),
+            NONAME,
+        )
+    }
+
+    /// Emit the code to report an access past the end of an SSR's stream.
+    unsafe fn emit_ssr_bounds_check(&self, ssr: u32, write: bool) {
+        self.section.emit_call(
+            "banshee_ssr_check",
+            [
+                self.ssr_ptr(ssr),
+                self.section.state_ptr,
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    ssr as u64,
+                    0,
+                ),
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    write as u64,
+                    0,
+                ),
+            ],
+        );
+    }
+
     /// Emit the code to load the next value of an SSR, if enabled.
     unsafe fn emit_possible_ssr_read(&self, rs: u32) {
         // Don't do anything for registers which are not SSR-enabled.
         if rs >= (self.section.engine.config.ssr.num_dm as u32) {
             return;
         }
+        if self.section.engine.check_ssr_bounds {
+            self.emit_ssr_bounds_check(rs, false);
+        }
 
-        // Check if SSRs are enabled.
-        let enabled_ptr = self.ssr_enabled_ptr();
-        let enabled = LLVMBuildLoad(self.builder, enabled_ptr, NONAME);
-        let enabled = LLVMBuildTrunc(self.builder, enabled, LLVMInt1Type(), NONAME);
+        // Check if SSRs are enabled and the lane is a read stream.
+        let enabled = self.emit_ssr_active(rs, false);
 
         let bb_ssron = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
         let bb_ssroff = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
@@ -7616,11 +10414,13 @@ impl<'a> InstructionTranslator<'a> {
         if rd >= (self.section.engine.config.ssr.num_dm as u32) {
             return;
         }
+        if self.section.engine.check_ssr_bounds {
+            self.emit_ssr_bounds_check(rd, true);
+        }
 
-        // Check if SSRs are enabled.
-        let enabled_ptr = self.ssr_enabled_ptr();
-        let enabled = LLVMBuildLoad(self.builder, enabled_ptr, NONAME);
-        let enabled = LLVMBuildTrunc(self.builder, enabled, LLVMInt1Type(), NONAME);
+        // Check if SSRs are enabled and the lane is a write stream which has
+        // not yet been exhausted.
+        let enabled = self.emit_ssr_active(rd, true);
 
         let bb_ssron = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
         let bb_ssroff = LLVMCreateBasicBlockInContext(self.section.engine.context, NONAME);
@@ -7657,9 +10457,13 @@ impl<'a> InstructionTranslator<'a> {
             ),
             [
                 self.section.state_ptr,
-                LLVMConstInt(LLVMInt16Type(), csr as u64, 0),
+                LLVMConstInt(
+                    LLVMInt16TypeInContext(self.section.engine.context),
+                    csr as u64,
+                    0,
+                ),
                 // notrace
-                LLVMConstInt(LLVMInt32Type(), 0, 0),
+                LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0, 0),
             ]
             .as_mut_ptr(),
             3,
@@ -7677,10 +10481,14 @@ impl<'a> InstructionTranslator<'a> {
             ),
             [
                 self.section.state_ptr,
-                LLVMConstInt(LLVMInt16Type(), csr as u64, 0),
+                LLVMConstInt(
+                    LLVMInt16TypeInContext(self.section.engine.context),
+                    csr as u64,
+                    0,
+                ),
                 data,
                 // notrace
-                LLVMConstInt(LLVMInt32Type(), 0, 0),
+                LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 0, 0),
             ]
             .as_mut_ptr(),
             4,
@@ -7698,9 +10506,13 @@ impl<'a> InstructionTranslator<'a> {
             ),
             [
                 self.section.state_ptr,
-                LLVMConstInt(LLVMInt16Type(), csr as u64, 0),
+                LLVMConstInt(
+                    LLVMInt16TypeInContext(self.section.engine.context),
+                    csr as u64,
+                    0,
+                ),
                 // notrace
-                LLVMConstInt(LLVMInt32Type(), 1, 0),
+                LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 1, 0),
             ]
             .as_mut_ptr(),
             3,
@@ -7718,10 +10530,14 @@ impl<'a> InstructionTranslator<'a> {
             ),
             [
                 self.section.state_ptr,
-                LLVMConstInt(LLVMInt16Type(), csr as u64, 0),
+                LLVMConstInt(
+                    LLVMInt16TypeInContext(self.section.engine.context),
+                    csr as u64,
+                    0,
+                ),
                 data,
                 // notrace
-                LLVMConstInt(LLVMInt32Type(), 1, 0),
+                LLVMConstInt(LLVMInt32TypeInContext(self.section.engine.context), 1, 0),
             ]
             .as_mut_ptr(),
             4,
@@ -7735,7 +10551,11 @@ impl<'a> InstructionTranslator<'a> {
             "banshee_reg_ptr",
             [
                 self.section.state_ptr,
-                LLVMConstInt(LLVMInt32Type(), r as u64, 0),
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    r as u64,
+                    0,
+                ),
             ],
             &format!("ptr_x{}", r),
         )
@@ -7747,7 +10567,11 @@ impl<'a> InstructionTranslator<'a> {
             "banshee_reg_cycle_ptr",
             [
                 self.section.state_ptr,
-                LLVMConstInt(LLVMInt32Type(), r as u64, 0),
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    r as u64,
+                    0,
+                ),
             ],
             &format!("ptr_x{}", r),
         )
@@ -7759,7 +10583,11 @@ impl<'a> InstructionTranslator<'a> {
             "banshee_freg_ptr",
             [
                 self.section.state_ptr,
-                LLVMConstInt(LLVMInt32Type(), r as u64, 0),
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    r as u64,
+                    0,
+                ),
             ],
             &format!("ptr_f{}", r),
         )
@@ -7771,7 +10599,11 @@ impl<'a> InstructionTranslator<'a> {
             "banshee_freg_cycle_ptr",
             [
                 self.section.state_ptr,
-                LLVMConstInt(LLVMInt32Type(), r as u64, 0),
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    r as u64,
+                    0,
+                ),
             ],
             &format!("ptr_f{}", r),
         )
@@ -7803,6 +10635,14 @@ impl<'a> InstructionTranslator<'a> {
             .emit_call_with_name("banshee_cycle_ptr", [self.section.state_ptr], "ptr_cycle")
     }
 
+    unsafe fn fflags_ptr(&self) -> LLVMValueRef {
+        self.section.emit_call_with_name(
+            "banshee_fflags_ptr",
+            [self.section.state_ptr],
+            "ptr_fflags",
+        )
+    }
+
     unsafe fn instret_ptr(&self) -> LLVMValueRef {
         self.section.emit_call_with_name(
             "banshee_instret_ptr",
@@ -7821,7 +10661,11 @@ impl<'a> InstructionTranslator<'a> {
             "banshee_tcdm_ext_ptr",
             [
                 self.section.state_ptr,
-                LLVMConstInt(LLVMInt32Type(), id as u64, 0),
+                LLVMConstInt(
+                    LLVMInt32TypeInContext(self.section.engine.context),
+                    id as u64,
+                    0,
+                ),
             ],
             &format!("ptr_tcdm_ext{}", id),
         )
@@ -7829,7 +10673,11 @@ impl<'a> InstructionTranslator<'a> {
 
     unsafe fn ssr_ptr(&self, ssr: u32) -> LLVMValueRef {
         assert!(ssr < (self.section.engine.config.ssr.num_dm as u32));
-        self.ssr_dyn_ptr(LLVMConstInt(LLVMInt32Type(), ssr as u64, 0))
+        self.ssr_dyn_ptr(LLVMConstInt(
+            LLVMInt32TypeInContext(self.section.engine.context),
+            ssr as u64,
+            0,
+        ))
     }
 
     unsafe fn ssr_dyn_ptr(&self, ssr: LLVMValueRef) -> LLVMValueRef {
@@ -7855,11 +10703,61 @@ impl<'a> InstructionTranslator<'a> {
 
     /// Extract latency from the config file or assign default value
     unsafe fn get_latency(&self, op: String, default_value: u64) -> u64 {
-        let latency = &self.section.engine.config.inst_latency.get(&op);
-        if let Some(&val) = latency {
+        let latency = self.section.engine.config.inst_latency(&op);
+        if let Some(val) = latency {
             val
         } else {
             default_value
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic_blocks() {
+        let program = [
+            0x00150513, // addi a0, a0, 1
+            0x00150513, // addi a0, a0, 1
+            0x00b50463, // beq a0, a1, 8
+            0x00150513, // addi a0, a0, 1
+            0x00150513, // addi a0, a0, 1
+            0x10500073, // wfi
+        ];
+        let insts = program
+            .iter()
+            .enumerate()
+            .map(|(i, &raw)| (i as u64 * 4, raw, riscv::parse_u32(raw)));
+        let target_addrs = [0x0, 0xc, 0x10].iter().cloned().collect();
+        assert_eq!(
+            split_basic_blocks(insts, &target_addrs),
+            vec![(0x0, 0xc), (0xc, 0x10), (0x10, 0x18)]
+        );
+
+        // A gap between sections ends a block.
+        let insts = vec![
+            (0x0, 0x00150513, riscv::parse_u32(0x00150513)),
+            (0x100, 0x00150513, riscv::parse_u32(0x00150513)),
+        ];
+        assert_eq!(
+            split_basic_blocks(insts.into_iter(), &BTreeSet::new()),
+            vec![(0x0, 0x4), (0x100, 0x104)]
+        );
+    }
+
+    #[test]
+    fn side_effects() {
+        let effects = |raw| has_side_effects(riscv::parse_u32(raw));
+        assert!(!effects(0x00150513)); // addi a0, a0, 1
+        assert!(!effects(0xfe029ee3)); // bnez t0, -4
+        assert!(!effects(0xb0002573)); // csrr a0, mcycle
+        assert!(effects(0x0002a503)); // lw a0, 0(t0)
+        assert!(effects(0x0002a507)); // flw fa0, 0(t0)
+        assert!(effects(0x00a2a223)); // sw a0, 4(t0)
+        assert!(effects(0x00a2a02f)); // amoadd.w zero, a0, (t0)
+        assert!(effects(0x30029073)); // csrw mstatus, t0
+        assert!(effects(0x10500073)); // wfi
+    }
+}
@@ -77,6 +77,110 @@ where
     }
 }
 
+/// Parse a duration such as `5s`, `500ms`, or `2m`. A plain number is taken
+/// as seconds.
+pub fn parse_duration(s: &str) -> anyhow::Result<std::time::Duration> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+    let value: f64 = value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid duration `{}`", s))?;
+    let scale = match unit.trim() {
+        "" | "s" => 1.0,
+        "ms" => 1e-3,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => anyhow::bail!("Invalid duration unit in `{}`", s),
+    };
+    Ok(std::time::Duration::from_secs_f64(value * scale))
+}
+
+/// Parse a binary assigned to clusters, such as `app.elf@0` for cluster 0 or
+/// `app.elf@1-3` for clusters 1 to 3 inclusive.
+pub fn parse_cluster_binary(s: &str) -> anyhow::Result<(&str, std::ops::Range<usize>)> {
+    let invalid = || anyhow::anyhow!("Invalid binary `{}`, expected PATH@CLUSTER[-CLUSTER]", s);
+    let (path, clusters) = s.rsplit_once('@').ok_or_else(invalid)?;
+    let (first, last) = clusters.split_once('-').unwrap_or((clusters, clusters));
+    let first: usize = first.parse().map_err(|_| invalid())?;
+    let last: usize = last.parse().map_err(|_| invalid())?;
+    if path.is_empty() || last < first {
+        return Err(invalid());
+    }
+    Ok((path, first..last + 1))
+}
+
+/// Determine the host CPU to pin the thread of the `index`th hart to, given a
+/// user-supplied list of CPUs or an empty list to assign them round-robin.
+pub fn pinned_cpu(cpus: &[usize], index: usize, num_host_cpus: usize) -> usize {
+    if cpus.is_empty() {
+        index % num_host_cpus.max(1)
+    } else {
+        cpus[index % cpus.len()]
+    }
+}
+
+/// Determine the number of online host CPUs.
+#[cfg(target_os = "linux")]
+pub fn num_host_cpus() -> usize {
+    unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN).max(1) as usize }
+}
+
+/// Determine the number of online host CPUs.
+#[cfg(not(target_os = "linux"))]
+pub fn num_host_cpus() -> usize {
+    1
+}
+
+/// Pin the calling thread to a host CPU. Returns whether this succeeded.
+#[cfg(target_os = "linux")]
+pub fn pin_thread(cpu: usize) -> bool {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    }
+}
+
+/// Pin the calling thread to a host CPU. Not supported on this platform.
+#[cfg(not(target_os = "linux"))]
+pub fn pin_thread(_cpu: usize) -> bool {
+    false
+}
+
+/// Make the floating-point unit of the calling thread keep subnormal inputs
+/// and results, rather than treating them as zero.
+#[cfg(target_arch = "x86_64")]
+pub fn keep_subnormals() {
+    // Clear the flush-to-zero and denormals-are-zero bits of the MXCSR.
+    let mut mxcsr = 0u32;
+    unsafe {
+        std::arch::asm!("stmxcsr [{}]", in(reg) &mut mxcsr);
+        mxcsr &= !(1 << 15 | 1 << 6);
+        std::arch::asm!("ldmxcsr [{}]", in(reg) &mxcsr);
+    }
+}
+
+/// Make the floating-point unit of the calling thread keep subnormal inputs
+/// and results. The default mode of other hosts already does.
+#[cfg(not(target_arch = "x86_64"))]
+pub fn keep_subnormals() {}
+
+/// The targets of the log messages, which can be enabled separately, e.g.
+/// `SNITCH_LOG=banshee::mem=trace` to only show the memory accesses.
+pub mod log_target {
+    /// Loading, translating, and optimizing binaries.
+    pub const TRANSLATE: &str = "banshee::translate";
+    /// Executing the harts, including CSR accesses and syscalls.
+    pub const EXEC: &str = "banshee::exec";
+    /// Memory and peripheral accesses.
+    pub const MEM: &str = "banshee::mem";
+    /// The periodic progress report of the execution.
+    pub const PROGRESS: &str = "banshee::progress";
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -210,4 +314,62 @@ mod tests {
         assert_eq!((-1.234e20f64).si_unit("B").to_string(), "-123.400 EB");
         assert_eq!((-1.234e21f64).si_unit("B").to_string(), "-1234.000 EB");
     }
+
+    #[test]
+    fn parse_duration() {
+        use std::time::Duration;
+        assert_eq!(super::parse_duration("5").unwrap(), Duration::from_secs(5));
+        assert_eq!(super::parse_duration("5s").unwrap(), Duration::from_secs(5));
+        assert_eq!(
+            super::parse_duration("250ms").unwrap(),
+            Duration::from_millis(250)
+        );
+        assert_eq!(
+            super::parse_duration("2m").unwrap(),
+            Duration::from_secs(120)
+        );
+        assert_eq!(
+            super::parse_duration("0.5s").unwrap(),
+            Duration::from_millis(500)
+        );
+        assert!(super::parse_duration("s").is_err());
+        assert!(super::parse_duration("5 parsecs").is_err());
+    }
+
+    #[test]
+    fn parse_cluster_binary() {
+        assert_eq!(
+            super::parse_cluster_binary("control.elf@0").unwrap(),
+            ("control.elf", 0..1)
+        );
+        assert_eq!(
+            super::parse_cluster_binary("a@b.elf@1-3").unwrap(),
+            ("a@b.elf", 1..4)
+        );
+        assert!(super::parse_cluster_binary("app.elf").is_err());
+        assert!(super::parse_cluster_binary("app.elf@3-1").is_err());
+        assert!(super::parse_cluster_binary("@0").is_err());
+    }
+
+    #[test]
+    fn pinned_cpu() {
+        assert_eq!(super::pinned_cpu(&[], 5, 4), 1);
+        assert_eq!(super::pinned_cpu(&[], 5, 0), 0);
+        assert_eq!(super::pinned_cpu(&[2, 4, 6], 4, 8), 4);
+    }
+
+    #[test]
+    fn pin_thread() {
+        // Pinning must not change the result of a computation.
+        let work = || (0..100_000u64).fold(0u64, |acc, x| acc.wrapping_mul(31).wrapping_add(x));
+        let expected = work();
+        let result = std::thread::spawn(move || {
+            let pinned = super::pin_thread(0);
+            assert_eq!(pinned, cfg!(target_os = "linux"));
+            work()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(result, expected);
+    }
 }
@@ -0,0 +1,56 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// Drives banshee through its C interface the way a testbench does: load a
+// binary, advance it in bounded steps, and exchange data through memory.
+// Expects the path to `tests/bin/dummy`.
+
+#include <stdio.h>
+#include <string.h>
+
+#include "banshee.h"
+
+#define CHECK(cond)                                                     \
+    do {                                                                \
+        if (!(cond)) {                                                  \
+            fprintf(stderr, "%s:%d: check failed: %s (%s)\n", __FILE__, \
+                    __LINE__, #cond, banshee_last_error(engine));       \
+            return 1;                                                   \
+        }                                                               \
+    } while (0)
+
+int main(int argc, char **argv) {
+    BansheeEngine *engine = NULL;
+    CHECK(argc == 2);
+    engine = banshee_create("{\"architecture\": {\"num_cores\": 1}}");
+    CHECK(engine != NULL);
+    CHECK(banshee_set_deterministic(engine, 1000) == BANSHEE_OK);
+    CHECK(banshee_load_elf(engine, "does/not/exist") == BANSHEE_ERR_TRANSLATE);
+    CHECK(strlen(banshee_last_error(engine)) > 0);
+    CHECK(banshee_load_elf(engine, argv[1]) == BANSHEE_OK);
+
+    // Step through the binary, and poke a word into memory between steps.
+    uint32_t word = 0xdeadbeef, exit_code;
+    int steps = 0, ret;
+    while ((ret = banshee_run(engine, 2)) == BANSHEE_OK) {
+        CHECK(banshee_exit_code(engine, &exit_code) == BANSHEE_ERR_RUNNING);
+        CHECK(banshee_write_mem(engine, 0x200, (uint8_t *)&word, 4) ==
+              BANSHEE_OK);
+        steps++;
+    }
+    CHECK(ret == BANSHEE_DONE);
+    CHECK(steps > 2);
+    CHECK(banshee_exit_code(engine, &exit_code) == BANSHEE_OK);
+    CHECK(exit_code == 0);
+
+    // The binary stores 0x100 to 0x100.
+    uint32_t value = 0;
+    CHECK(banshee_read_mem(engine, 0x100, (uint8_t *)&value, 4) == BANSHEE_OK);
+    CHECK(value == 0x100);
+    CHECK(banshee_read_mem(engine, 0x200, (uint8_t *)&value, 4) == BANSHEE_OK);
+    CHECK(value == word);
+    banshee_destroy(engine);
+    printf("C interface round trip passed in %d steps\n", steps);
+    return 0;
+}
@@ -0,0 +1,63 @@
+# Copyright 2021 ETH Zurich and University of Bologna.
+# Licensed under the Apache License, Version 2.0, see LICENSE for details.
+# SPDX-License-Identifier: Apache-2.0
+
+# Exercise the compressed instructions, including branches and calls to
+# targets which are only 2-byte aligned.
+
+.option rvc
+.globl _start
+.section .text.init;
+_start:
+    c.li    s0, 0
+
+    # Sum up 1..10 in a loop.
+    c.li    a0, 0
+    c.li    a1, 10
+1:  c.add   a0, a1
+    c.addi  a1, -1
+    c.bnez  a1, 1b
+    addi    t0, a0, -55
+    snez    t0, t0
+    c.add   s0, t0
+
+    # Call a function, which returns right behind the 2 byte `c.jal`.
+    c.li    a0, 3
+    c.jal   triple
+    addi    t0, a0, -9
+    snez    t0, t0
+    c.add   s0, t0
+
+    # Store and load through the stack pointer.
+    la      sp, buffer
+    c.li    a0, 21
+    c.swsp  a0, 4(sp)
+    c.lwsp  a1, 4(sp)
+    c.mv    a2, sp
+    c.lw    a3, 4(a2)
+    c.sub   a3, a1
+    c.j     2f
+    c.addi  s0, 1
+2:  snez    t0, a3
+    c.add   s0, t0
+    addi    t0, a1, -21
+    snez    t0, t0
+    c.add   s0, t0
+
+    c.slli  s0, 1
+    ori     s0, s0, 1
+    la      t0, scratch_reg
+    sw      s0, 0(t0)
+    wfi
+
+triple:
+    c.mv    t0, a0
+    c.add   a0, t0
+    c.add   a0, t0
+    c.jr    ra
+
+.section .l1,"aw",@progbits
+.align 3
+buffer:
+    .word 0
+    .word 0
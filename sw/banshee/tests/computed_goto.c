@@ -0,0 +1,70 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// Dispatch through computed gotos, a jump table, and function pointers, none
+// of whose targets can be predicted from the binary.
+
+#include <stdint.h>
+
+enum { OP_PUSH, OP_ADD, OP_MUL, OP_HALT };
+
+static const uint8_t program[] = {OP_PUSH, 3, OP_PUSH, 4, OP_ADD,
+                                  OP_PUSH, 5, OP_MUL,  OP_HALT};
+
+// Evaluate the program with a threaded interpreter.
+static __attribute__((noinline)) int32_t run(const uint8_t *pc) {
+    static void *const dispatch[] = {&&push, &&add, &&mul, &&halt};
+    int32_t stack[8];
+    int32_t *sp = stack;
+    goto *dispatch[*pc++];
+push:
+    *sp++ = *pc++;
+    goto *dispatch[*pc++];
+add:
+    sp--;
+    sp[-1] += sp[0];
+    goto *dispatch[*pc++];
+mul:
+    sp--;
+    sp[-1] *= sp[0];
+    goto *dispatch[*pc++];
+halt:
+    return sp[-1];
+}
+
+static __attribute__((noinline)) int32_t twice(int32_t x) { return 2 * x; }
+static __attribute__((noinline)) int32_t square(int32_t x) { return x * x; }
+static __attribute__((noinline)) int32_t negate(int32_t x) { return -x; }
+
+static int32_t (*volatile funcs[])(int32_t) = {twice, square, negate};
+
+// Use a dense switch, which is compiled to a jump table.
+static __attribute__((noinline)) int32_t pick(uint32_t i) {
+    switch (i) {
+        case 0:
+            return 11;
+        case 1:
+            return 22;
+        case 2:
+            return 33;
+        case 3:
+            return 44;
+        case 4:
+            return 55;
+        case 5:
+            return 66;
+        default:
+            return 0;
+    }
+}
+
+int main() {
+    int errors = 0;
+    errors += run(program) != 35;
+    errors += funcs[0](7) != 14;
+    errors += funcs[1](7) != 49;
+    errors += funcs[2](7) != -7;
+    for (uint32_t i = 0; i < 6; i++) errors += pick(i) != 11 * (i + 1);
+    return errors;
+}
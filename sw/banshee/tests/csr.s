@@ -0,0 +1,59 @@
+# Copyright 2021 ETH Zurich and University of Bologna.
+# Licensed under the Apache License, Version 2.0, see LICENSE for details.
+# SPDX-License-Identifier: Apache-2.0
+
+# Check the old values and the updates of the CSR instructions, using `mepc`
+# as a scratch CSR.
+
+.macro check reg, value
+    li      t6, \value
+    sub     t6, \reg, t6
+    snez    t6, t6
+    add     s0, s0, t6
+.endm
+
+.globl _start
+.section .text.init;
+_start:
+    li      s0, 0
+
+    li      t0, 0xf0
+    csrw    mepc, t0
+    li      t0, 0x0f
+    csrrs   t1, mepc, t0
+    check   t1, 0xf0
+    li      t0, 0x3c
+    csrrc   t1, mepc, t0
+    check   t1, 0xff
+    csrrsi  t1, mepc, 4
+    check   t1, 0xc3
+    csrrci  t1, mepc, 3
+    check   t1, 0xc7
+    csrrwi  t1, mepc, 5
+    check   t1, 0xc4
+    li      t0, 0x1234
+    csrrw   t1, mepc, t0
+    check   t1, 5
+    csrr    t1, mepc
+    check   t1, 0x1234
+
+    # The set and clear forms do not write with a zero operand.
+    csrrs   t1, mepc, zero
+    csrrc   t1, mepc, zero
+    csrrsi  t1, mepc, 0
+    csrrci  t1, mepc, 0
+    check   t1, 0x1234
+    csrr    t1, mepc
+    check   t1, 0x1234
+
+    # Reading a read-only CSR is not a write.
+    csrrs   t1, mhartid, zero
+    check   t1, 0
+    csrrsi  t1, mhartid, 0
+    check   t1, 0
+
+    slli    s0, s0, 1
+    ori     s0, s0, 1
+    la      t0, scratch_reg
+    sw      s0, 0(t0)
+    wfi
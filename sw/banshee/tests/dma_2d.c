@@ -0,0 +1,115 @@
+// Copyright 2020 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+#include <stdint.h>
+
+static uint8_t src[64];
+static uint8_t dst[64] __attribute__((section(".l1")));
+static uint8_t ref[64];
+
+// Start a 2D transfer, as done by the runtime's `snrt_dma_start_2d`.
+static uint32_t dma_start_2d(void *d, const void *s, uint32_t size,
+                             uint32_t dst_stride, uint32_t src_stride,
+                             uint32_t repeat) {
+    register uint32_t reg_dst_low asm("a0") = (uint32_t)d;     // 10
+    register uint32_t reg_dst_high asm("a1") = 0;              // 11
+    register uint32_t reg_src_low asm("a2") = (uint32_t)s;     // 12
+    register uint32_t reg_src_high asm("a3") = 0;              // 13
+    register uint32_t reg_size asm("a4") = size;               // 14
+    register uint32_t reg_dst_stride asm("a5") = dst_stride;   // 15
+    register uint32_t reg_src_stride asm("a6") = src_stride;   // 16
+    register uint32_t reg_repeat asm("a7") = repeat;           // 17
+
+    // dmsrc a2, a3
+    asm volatile(
+        ".word (0b0000000 << 25) | \
+               (     (13) << 20) | \
+               (     (12) << 15) | \
+               (    0b000 << 12) | \
+               (0b0101011 <<  0)   \n" ::"r"(reg_src_high),
+        "r"(reg_src_low));
+
+    // dmdst a0, a1
+    asm volatile(
+        ".word (0b0000001 << 25) | \
+               (     (11) << 20) | \
+               (     (10) << 15) | \
+               (    0b000 << 12) | \
+               (0b0101011 <<  0)   \n" ::"r"(reg_dst_high),
+        "r"(reg_dst_low));
+
+    // dmstr a6, a5
+    asm volatile(
+        ".word (0b0000110 << 25) | \
+               (     (15) << 20) | \
+               (     (16) << 15) | \
+               (    0b000 << 12) | \
+               (0b0101011 <<  0)   \n" ::"r"(reg_dst_stride),
+        "r"(reg_src_stride));
+
+    // dmrep a7
+    asm volatile(
+        ".word (0b0000111 << 25) | \
+               (     (17) << 15) | \
+               (    0b000 << 12) | \
+               (0b0101011 <<  0)   \n" ::"r"(reg_repeat));
+
+    // dmcpyi a0, a4, 0b10
+    register uint32_t reg_txid asm("a0");  // 10
+    asm volatile(
+        ".word (0b0000010 << 25) | \
+               (  0b00010 << 20) | \
+               (     (14) << 15) | \
+               (    0b000 << 12) | \
+               (     (10) <<  7) | \
+               (0b0101011 <<  0)   \n"
+        : "=r"(reg_txid)
+        : "r"(reg_size)
+        : "memory");
+    return reg_txid;
+}
+
+// Block until a transfer finishes, as done by the runtime's `snrt_dma_wait`.
+static void dma_wait(uint32_t tid) {
+    asm volatile(
+        "1: \n"
+        ".word (0b0000100 << 25) | \
+               (  0b00000 << 20) | \
+               (    0b000 << 12) | \
+               (      (5) <<  7) | \
+               (0b0101011 <<  0)   \n"
+        "sub t0, t0, %0 \n"
+        "blez t0, 1b \n" ::"r"(tid)
+        : "t0", "memory");
+}
+
+// Perform a 2D transfer with the DMA and on the reference buffer.
+static void copy_2d(uint8_t *d, const uint8_t *s, uint32_t size,
+                    uint32_t dst_stride, uint32_t src_stride,
+                    uint32_t repeat) {
+    uint8_t *rd = ref + (d - dst);
+    const uint8_t *rs = s >= dst && s < dst + 64 ? ref + (s - dst) : s;
+    for (uint32_t i = 0; i < repeat; i++)
+        for (uint32_t j = 0; j < size; j++)
+            rd[i * dst_stride + j] = rs[i * src_stride + j];
+    dma_wait(dma_start_2d(d, s, size, dst_stride, src_stride, repeat));
+}
+
+int main() {
+    int errs = 0;
+    for (int i = 0; i < 64; i++) src[i] = i + 1;
+
+    // Rows from DRAM into the TCDM, with aligned and unaligned strides.
+    copy_2d(dst, src, 8, 12, 8, 3);
+    copy_2d(dst + 37, src + 1, 5, 7, 6, 3);
+
+    // Zero repetitions do not copy anything.
+    copy_2d(dst, src + 32, 8, 8, 8, 0);
+
+    // Rows overlapping with each other and with the source.
+    copy_2d(dst + 1, dst, 4, 2, 2, 4);
+
+    for (int i = 0; i < 64; i++) errs += dst[i] != ref[i];
+    return errs;
+}
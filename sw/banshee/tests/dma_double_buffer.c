@@ -0,0 +1,136 @@
+// Copyright 2020 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+#include <stdint.h>
+
+#define TILE 16
+#define NUM_TILES 6
+
+static uint32_t src[NUM_TILES][TILE];
+static uint32_t buf[2][TILE] __attribute__((section(".l1")));
+static uint32_t scratch[32] __attribute__((section(".l1")));
+
+// Start a 1D transfer, as done by the runtime's `snrt_dma_start_1d`.
+static uint32_t dma_start_1d(void *d, const void *s, uint32_t size) {
+    register uint32_t reg_dst_low asm("a0") = (uint32_t)d;  // 10
+    register uint32_t reg_dst_high asm("a1") = 0;           // 11
+    register uint32_t reg_src_low asm("a2") = (uint32_t)s;  // 12
+    register uint32_t reg_src_high asm("a3") = 0;           // 13
+    register uint32_t reg_size asm("a4") = size;            // 14
+
+    // dmsrc a2, a3
+    asm volatile(
+        ".word (0b0000000 << 25) | \
+               (     (13) << 20) | \
+               (     (12) << 15) | \
+               (    0b000 << 12) | \
+               (0b0101011 <<  0)   \n" ::"r"(reg_src_high),
+        "r"(reg_src_low));
+
+    // dmdst a0, a1
+    asm volatile(
+        ".word (0b0000001 << 25) | \
+               (     (11) << 20) | \
+               (     (10) << 15) | \
+               (    0b000 << 12) | \
+               (0b0101011 <<  0)   \n" ::"r"(reg_dst_high),
+        "r"(reg_dst_low));
+
+    // dmcpyi a0, a4, 0b00
+    register uint32_t reg_txid asm("a0");  // 10
+    asm volatile(
+        ".word (0b0000010 << 25) | \
+               (  0b00000 << 20) | \
+               (     (14) << 15) | \
+               (    0b000 << 12) | \
+               (     (10) <<  7) | \
+               (0b0101011 <<  0)   \n"
+        : "=r"(reg_txid)
+        : "r"(reg_size)
+        : "memory");
+    return reg_txid;
+}
+
+// Read the DMA status, `dmstati t0, 0` yields the completed transfer ID.
+static uint32_t dma_completed_id() {
+    register uint32_t reg asm("t0");
+    asm volatile(
+        ".word (0b0000100 << 25) | \
+               (  0b00000 << 20) | \
+               (    0b000 << 12) | \
+               (      (5) <<  7) | \
+               (0b0101011 <<  0)   \n"
+        : "=r"(reg)::"memory");
+    return reg;
+}
+
+// Read the DMA status, `dmstati t0, 1` yields the next transfer ID.
+static uint32_t dma_next_id() {
+    register uint32_t reg asm("t0");
+    asm volatile(
+        ".word (0b0000100 << 25) | \
+               (  0b00001 << 20) | \
+               (    0b000 << 12) | \
+               (      (5) <<  7) | \
+               (0b0101011 <<  0)   \n"
+        : "=r"(reg)::"memory");
+    return reg;
+}
+
+// Read the DMA status, `dmstati t0, 2` yields the busy flag.
+static uint32_t dma_busy() {
+    register uint32_t reg asm("t0");
+    asm volatile(
+        ".word (0b0000100 << 25) | \
+               (  0b00010 << 20) | \
+               (    0b000 << 12) | \
+               (      (5) <<  7) | \
+               (0b0101011 <<  0)   \n"
+        : "=r"(reg)::"memory");
+    return reg;
+}
+
+// Block until a transfer finishes, as done by the runtime's `snrt_dma_wait`.
+static void dma_wait(uint32_t tid) {
+    while (dma_completed_id() <= tid)
+        ;
+}
+
+int main() {
+    int errs = 0;
+    for (int t = 0; t < NUM_TILES; t++)
+        for (int i = 0; i < TILE; i++) src[t][i] = t * TILE + i;
+
+    // Fetch the next tile while working on the current one, such that two
+    // transfers are in flight.
+    uint32_t tid = dma_start_1d(buf[0], src[0], sizeof(buf[0]));
+    uint32_t sum = 0;
+    for (int t = 0; t < NUM_TILES; t++) {
+        uint32_t next_tid = tid;
+        if (t + 1 < NUM_TILES) {
+            uint32_t expected = dma_next_id();
+            next_tid = dma_start_1d(buf[(t + 1) % 2], src[t + 1], sizeof(buf[0]));
+            errs += next_tid != expected;
+            errs += next_tid != tid + 1;
+        }
+        dma_wait(tid);
+        errs += dma_completed_id() > dma_next_id();
+        for (int i = 0; i < TILE; i++) sum += buf[t % 2][i];
+        tid = next_tid;
+    }
+    errs += sum != (NUM_TILES * TILE) * (NUM_TILES * TILE - 1) / 2;
+
+    // Enqueue more transfers than the DMA can hold; the core stalls until
+    // there is space, but no ID is lost.
+    uint32_t first = dma_next_id();
+    for (int i = 0; i < 32; i++) {
+        tid = dma_start_1d(&scratch[i], &src[0][i % TILE], 4);
+        errs += tid != first + i;
+    }
+    dma_wait(tid);
+    errs += dma_busy() != 0;
+    for (int i = 0; i < 32; i++) errs += scratch[i] != (uint32_t)(i % TILE);
+
+    return errs;
+}
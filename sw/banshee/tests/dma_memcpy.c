@@ -0,0 +1,99 @@
+// Copyright 2020 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+#include <stdint.h>
+
+static uint8_t src[32];
+static uint8_t dst[32] __attribute__((section(".l1")));
+
+// Start a 1D transfer, as done by the runtime's `snrt_dma_start_1d`.
+static uint32_t dma_start_1d(void *d, const void *s, uint32_t size) {
+    register uint32_t reg_dst_low asm("a0") = (uint32_t)d;  // 10
+    register uint32_t reg_dst_high asm("a1") = 0;           // 11
+    register uint32_t reg_src_low asm("a2") = (uint32_t)s;  // 12
+    register uint32_t reg_src_high asm("a3") = 0;           // 13
+    register uint32_t reg_size asm("a4") = size;            // 14
+
+    // dmsrc a2, a3
+    asm volatile(
+        ".word (0b0000000 << 25) | \
+               (     (13) << 20) | \
+               (     (12) << 15) | \
+               (    0b000 << 12) | \
+               (0b0101011 <<  0)   \n" ::"r"(reg_src_high),
+        "r"(reg_src_low));
+
+    // dmdst a0, a1
+    asm volatile(
+        ".word (0b0000001 << 25) | \
+               (     (11) << 20) | \
+               (     (10) << 15) | \
+               (    0b000 << 12) | \
+               (0b0101011 <<  0)   \n" ::"r"(reg_dst_high),
+        "r"(reg_dst_low));
+
+    // dmcpyi a0, a4, 0b00
+    register uint32_t reg_txid asm("a0");  // 10
+    asm volatile(
+        ".word (0b0000010 << 25) | \
+               (  0b00000 << 20) | \
+               (     (14) << 15) | \
+               (    0b000 << 12) | \
+               (     (10) <<  7) | \
+               (0b0101011 <<  0)   \n"
+        : "=r"(reg_txid)
+        : "r"(reg_size)
+        : "memory");
+    return reg_txid;
+}
+
+// Read the DMA status, `dmstati t0, 1` yields the next transfer ID.
+static uint32_t dma_next_id() {
+    register uint32_t reg_id asm("t0");
+    asm volatile(
+        ".word (0b0000100 << 25) | \
+               (  0b00001 << 20) | \
+               (    0b000 << 12) | \
+               (      (5) <<  7) | \
+               (0b0101011 <<  0)   \n"
+        : "=r"(reg_id));
+    return reg_id;
+}
+
+// Block until a transfer finishes, as done by the runtime's `snrt_dma_wait`.
+static void dma_wait(uint32_t tid) {
+    asm volatile(
+        "1: \n"
+        ".word (0b0000100 << 25) | \
+               (  0b00000 << 20) | \
+               (    0b000 << 12) | \
+               (      (5) <<  7) | \
+               (0b0101011 <<  0)   \n"
+        "sub t0, t0, %0 \n"
+        "blez t0, 1b \n" ::"r"(tid)
+        : "t0", "memory");
+}
+
+int main() {
+    int errs = 0;
+    for (int i = 0; i < 32; i++) src[i] = i + 1;
+
+    // An aligned and an unaligned transfer with an odd size.
+    uint32_t next = dma_next_id();
+    uint32_t tid = dma_start_1d(dst, src, 8);
+    errs += tid != next;
+    dma_wait(tid);
+    next = dma_next_id();
+    tid = dma_start_1d(dst + 9, src + 3, 13);
+    errs += tid != next;
+    dma_wait(tid);
+
+    for (int i = 0; i < 32; i++) {
+        uint8_t expected = 0;
+        if (i < 8) expected = src[i];
+        if (i >= 9 && i < 22) expected = src[i - 6];
+        errs += dst[i] != expected;
+    }
+    return errs;
+}
@@ -0,0 +1,14 @@
+# Copyright 2021 ETH Zurich and University of Bologna.
+# Licensed under the Apache License, Version 2.0, see LICENSE for details.
+# SPDX-License-Identifier: Apache-2.0
+
+# A few instructions with a known source line each, to test the parsing of
+# the DWARF line table.
+    .text
+    .globl _start
+_start:
+    li a0, 5
+    li a1, 3
+
+    add a0, a0, a1
+    wfi
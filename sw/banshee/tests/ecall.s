@@ -0,0 +1,27 @@
+# Copyright 2021 ETH Zurich and University of Bologna.
+# Licensed under the Apache License, Version 2.0, see LICENSE for details.
+# SPDX-License-Identifier: Apache-2.0
+
+# Print a message through the `write` syscall and exit through the `exit`
+# syscall, which places the result of `write` in `a0`.
+
+.globl _start
+.section .text.init;
+_start:
+    li      a0, 1
+    la      a1, message
+    li      a2, 6
+    li      a7, 64
+    ecall
+    addi    a0, a0, -6
+    li      a7, 93
+    ecall
+    # Not reached, report exit code 1.
+    li      a0, 3
+    la      t0, scratch_reg
+    sw      a0, 0(t0)
+    wfi
+
+.section .rodata
+message:
+    .ascii  "ecall\n"
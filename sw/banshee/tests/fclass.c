@@ -0,0 +1,78 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+#include <stdint.h>
+
+static uint32_t fclass_s(uint32_t bits) {
+    uint32_t res;
+    float f;
+    asm volatile(
+        "fmv.w.x %1, %2\n"
+        "fclass.s %0, %1\n"
+        : "=r"(res), "=f"(f)
+        : "r"(bits));
+    return res;
+}
+
+static uint32_t fclass_d(volatile double *value) {
+    uint32_t res;
+    double f;
+    asm volatile(
+        "fld %1, 0(%2)\n"
+        "fclass.d %0, %1\n"
+        : "=r"(res), "=f"(f)
+        : "r"(value));
+    return res;
+}
+
+int main() {
+    unsigned int errs = 0;
+
+    // fadd.s must NaN-box its result, such that fmv.x.w reads it back.
+    uint32_t a = 0x3fc00000;  // 1.5
+    uint32_t b = 0x40100000;  // 2.25
+    uint32_t sum;
+    float fa, fb;
+    asm volatile(
+        "fmv.w.x %1, %3\n"
+        "fmv.w.x %2, %4\n"
+        "fadd.s  %1, %1, %2\n"
+        "fmv.x.w %0, %1\n"
+        : "=r"(sum), "=&f"(fa), "=&f"(fb)
+        : "r"(a), "r"(b));
+    errs += sum != 0x40700000;  // 3.75
+
+    // fclass.s
+    errs += fclass_s(0xff800000) != (1 << 0);  // -inf
+    errs += fclass_s(0xbf800000) != (1 << 1);  // -1.0
+    errs += fclass_s(0x80000001) != (1 << 2);  // negative subnormal
+    errs += fclass_s(0x80000000) != (1 << 3);  // -0.0
+    errs += fclass_s(0x00000000) != (1 << 4);  // +0.0
+    errs += fclass_s(0x00000001) != (1 << 5);  // positive subnormal
+    errs += fclass_s(0x3f800000) != (1 << 6);  // 1.0
+    errs += fclass_s(0x7f800000) != (1 << 7);  // +inf
+    errs += fclass_s(0x7f800001) != (1 << 8);  // signaling NaN
+    errs += fclass_s(0x7fc00000) != (1 << 9);  // quiet NaN
+
+    // fclass.d
+    volatile double d = -1.0;
+    errs += fclass_d(&d) != (1 << 1);
+    d = 0.0;
+    errs += fclass_d(&d) != (1 << 4);
+    d = 1.0 / 0.0;
+    errs += fclass_d(&d) != (1 << 7);
+
+    // A double which is not NaN-boxed is a quiet NaN as a single.
+    uint32_t res;
+    float f;
+    d = 1.0;
+    asm volatile(
+        "fld %1, 0(%2)\n"
+        "fclass.s %0, %1\n"
+        : "=r"(res), "=f"(f)
+        : "r"(&d));
+    errs += res != (1 << 9);
+
+    return errs;
+}
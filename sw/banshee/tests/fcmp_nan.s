@@ -0,0 +1,91 @@
+# Copyright 2021 ETH Zurich and University of Bologna.
+# Licensed under the Apache License, Version 2.0, see LICENSE for details.
+# SPDX-License-Identifier: Apache-2.0
+
+# Compare NaNs and signed zeros, and check the results and the invalid
+# operation flag: `feq` only signals on signaling NaNs, `flt` and `fle` on
+# any NaN.
+
+.macro check reg, value
+    li      t6, \value
+    sub     t6, \reg, t6
+    snez    t6, t6
+    add     s0, s0, t6
+.endm
+
+# Compare two float registers and check the result and the flags.
+.macro cmp op, fa, fb, result, flags
+    fsflags zero
+    \op     t1, \fa, \fb
+    frflags t2
+    check   t1, \result
+    check   t2, \flags
+.endm
+
+.globl _start
+.section .text.init;
+_start:
+    li      s0, 0
+
+    li      t0, 0x7fc00000
+    fmv.w.x f1, t0          # quiet NaN
+    li      t0, 0x7f800001
+    fmv.w.x f2, t0          # signaling NaN
+    li      t0, 0x3f800000
+    fmv.w.x f3, t0          # 1.0
+    li      t0, 0x40000000
+    fmv.w.x f4, t0          # 2.0
+    fmv.w.x f5, zero        # +0.0
+    li      t0, 0x80000000
+    fmv.w.x f6, t0          # -0.0
+
+    cmp     feq.s, f3, f3, 1, 0
+    cmp     feq.s, f6, f5, 1, 0
+    cmp     flt.s, f3, f4, 1, 0
+    cmp     flt.s, f6, f5, 0, 0
+    cmp     fle.s, f6, f5, 1, 0
+    cmp     fle.s, f4, f3, 0, 0
+    cmp     feq.s, f1, f1, 0, 0
+    cmp     feq.s, f1, f3, 0, 0
+    cmp     feq.s, f3, f2, 0, 0x10
+    cmp     feq.s, f2, f2, 0, 0x10
+    cmp     flt.s, f1, f3, 0, 0x10
+    cmp     flt.s, f3, f1, 0, 0x10
+    cmp     fle.s, f3, f1, 0, 0x10
+    cmp     fle.s, f2, f3, 0, 0x10
+
+    la      t0, values
+    fld     f7, 0(t0)       # quiet NaN
+    fld     f8, 8(t0)       # signaling NaN
+    fld     f9, 16(t0)      # 1.0
+
+    cmp     feq.d, f9, f9, 1, 0
+    cmp     fle.d, f9, f9, 1, 0
+    cmp     feq.d, f7, f9, 0, 0
+    cmp     feq.d, f8, f9, 0, 0x10
+    cmp     flt.d, f9, f7, 0, 0x10
+    cmp     fle.d, f8, f8, 0, 0x10
+
+    # An improperly boxed single is the canonical quiet NaN, even if the
+    # double is a signaling NaN.
+    cmp     feq.s, f8, f8, 0, 0
+
+    # The flags accrue.
+    fsflags zero
+    flt.s   t1, f1, f3
+    feq.s   t1, f3, f3
+    frflags t2
+    check   t2, 0x10
+
+    slli    s0, s0, 1
+    ori     s0, s0, 1
+    la      t0, scratch_reg
+    sw      s0, 0(t0)
+    wfi
+
+.data
+.align 3
+values:
+    .dword 0x7ff8000000000000
+    .dword 0x7ff0000000000001
+    .dword 0x3ff0000000000000
@@ -0,0 +1,36 @@
+# Copyright 2021 ETH Zurich and University of Bologna.
+# Licensed under the Apache License, Version 2.0, see LICENSE for details.
+# SPDX-License-Identifier: Apache-2.0
+
+# Check that `fcsr` is composed of the `frm` and `fflags` fields.
+
+.globl _start
+.section .text.init;
+_start:
+    li      s0, 0
+
+    # Write the full register and read back the individual fields.
+    li      t0, 0x7f
+    csrw    fcsr, t0
+    csrr    t1, frm
+    addi    t1, t1, -3
+    snez    t1, t1
+    add     s0, s0, t1
+    csrr    t1, fflags
+    addi    t1, t1, -0x1f
+    snez    t1, t1
+    add     s0, s0, t1
+
+    # Write the individual fields and read back the full register.
+    csrwi   fflags, 0
+    csrwi   frm, 1
+    csrr    t1, fcsr
+    addi    t1, t1, -0x20
+    snez    t1, t1
+    add     s0, s0, t1
+
+    slli    s0, s0, 1
+    ori     s0, s0, 1
+    la      t0, scratch_reg
+    sw      s0, 0(t0)
+    wfi
@@ -0,0 +1,8 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// Run the subnormal checks with `--flush-to-zero`.
+
+#define FLUSH_TO_ZERO
+#include "subnormals.c"
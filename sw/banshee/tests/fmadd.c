@@ -0,0 +1,77 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// Exercise the fused multiply-add instructions, in particular the sign of
+// zero results, NaN results, and the single rounding of the fused operation.
+
+#include <stdint.h>
+
+#define FMA_S(inst)                                                        \
+    static uint32_t inst##_s(uint32_t a, uint32_t b, uint32_t c) {         \
+        float fa, fb, fc, fd;                                              \
+        uint32_t d;                                                        \
+        asm volatile("fmv.w.x %0, %1" : "=f"(fa) : "r"(a));                \
+        asm volatile("fmv.w.x %0, %1" : "=f"(fb) : "r"(b));                \
+        asm volatile("fmv.w.x %0, %1" : "=f"(fc) : "r"(c));                \
+        asm volatile(#inst ".s %0, %1, %2, %3"                             \
+                     : "=f"(fd)                                            \
+                     : "f"(fa), "f"(fb), "f"(fc));                         \
+        asm volatile("fmv.x.w %0, %1" : "=r"(d) : "f"(fd));                \
+        return d;                                                          \
+    }
+
+#define FMA_D(inst)                                                        \
+    static uint64_t inst##_d(double a, double b, double c) {               \
+        volatile double d;                                                 \
+        asm volatile(#inst ".d %0, %1, %2, %3"                             \
+                     : "=f"(d)                                             \
+                     : "f"(a), "f"(b), "f"(c));                            \
+        return *(volatile uint64_t *)&d;                                   \
+    }
+
+FMA_S(fmadd)
+FMA_S(fmsub)
+FMA_S(fnmadd)
+FMA_S(fnmsub)
+FMA_D(fmadd)
+FMA_D(fmsub)
+FMA_D(fnmadd)
+FMA_D(fnmsub)
+
+#define POS_ZERO 0x00000000
+#define NEG_ZERO 0x80000000
+#define ONE 0x3f800000
+#define CANONICAL_NAN 0x7fc00000
+
+int main() {
+    unsigned int errs = 0;
+
+    // The negated forms negate the product, so the sign of an exact zero
+    // result follows the usual rules for the sum.
+    errs += fmadd_s(NEG_ZERO, ONE, NEG_ZERO) != NEG_ZERO;
+    errs += fmadd_s(POS_ZERO, ONE, NEG_ZERO) != POS_ZERO;
+    errs += fmsub_s(NEG_ZERO, ONE, POS_ZERO) != NEG_ZERO;
+    errs += fmsub_s(POS_ZERO, ONE, POS_ZERO) != POS_ZERO;
+    errs += fnmadd_s(POS_ZERO, ONE, NEG_ZERO) != POS_ZERO;
+    errs += fnmadd_s(NEG_ZERO, ONE, NEG_ZERO) != NEG_ZERO;
+    errs += fnmsub_s(POS_ZERO, ONE, NEG_ZERO) != NEG_ZERO;
+    errs += fnmsub_s(POS_ZERO, ONE, POS_ZERO) != POS_ZERO;
+    errs += fnmadd_d(0.0, 1.0, -0.0) != 0x0000000000000000ull;
+    errs += fnmsub_d(0.0, 1.0, -0.0) != 0x8000000000000000ull;
+
+    // NaN inputs and invalid operations produce the canonical NaN.
+    errs += fmadd_s(0xffc12345, ONE, ONE) != CANONICAL_NAN;
+    errs += fnmadd_s(0x7f800001, ONE, ONE) != CANONICAL_NAN;
+    errs += fmsub_s(0x7f800000, POS_ZERO, ONE) != CANONICAL_NAN;
+    errs += fmadd_d(__builtin_inf(), 1.0, -__builtin_inf()) !=
+            0x7ff8000000000000ull;
+
+    // (1 + 2^-12)^2 - (1 + 2^-11) is 2^-24, which is lost if the product is
+    // rounded before the addition.
+    errs += fmsub_s(0x3f800800, 0x3f800800, 0x3f801000) != 0x33800000;
+    errs += fmsub_d(1.0 + 0x1p-27, 1.0 + 0x1p-27, 1.0 + 0x1p-26) !=
+            0x3c90000000000000ull;
+
+    return errs;
+}
@@ -0,0 +1,73 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// Exercise the half-precision scalar instructions with a dot product, which
+// is checked against a NumPy reference.
+
+#include <stdint.h>
+
+extern uint32_t input_size;
+extern uint16_t input_a[];
+extern uint16_t input_b[];
+extern uint16_t output_dotp;
+extern float output_dotp_f32;
+
+// The result as stored by `fsh`.
+volatile uint16_t result __attribute__((section(".l1")));
+
+int main() {
+    unsigned int errs = 0;
+
+    // Accumulate in half precision with one rounding per step, then widen
+    // the result to single precision, which is exact.
+    const uint16_t *a = input_a, *b = input_b;
+    uint32_t n = input_size, acc;
+    float f32;
+    asm volatile(
+        "fmv.h.x  ft0, zero\n"
+        "1:\n"
+        "flh      ft1, 0(%[a])\n"
+        "flh      ft2, 0(%[b])\n"
+        "fmadd.h  ft0, ft1, ft2, ft0\n"
+        "addi     %[a], %[a], 2\n"
+        "addi     %[b], %[b], 2\n"
+        "addi     %[n], %[n], -1\n"
+        "bnez     %[n], 1b\n"
+        "fsh      ft0, 0(%[out])\n"
+        "fmv.x.h  %[acc], ft0\n"
+        "fcvt.s.h %[f32], ft0\n"
+        : [a] "+r"(a), [b] "+r"(b), [n] "+r"(n), [acc] "=r"(acc),
+          [f32] "=f"(f32)
+        : [out] "r"(&result)
+        : "ft0", "ft1", "ft2", "memory");
+
+    // `fmv.x.h` sign-extends, the stored half word does not.
+    errs += result != output_dotp;
+    errs += acc != (uint32_t)(int16_t)output_dotp;
+    errs += f32 != output_dotp_f32;
+
+    // Narrow back and convert to and from integers.
+    uint32_t bits;
+    int32_t w;
+    asm volatile(
+        "fcvt.h.s ft1, %[f32]\n"
+        "fmv.x.h  %[bits], ft1\n"
+        "fcvt.w.h %[w], ft1, rtz\n"
+        : [bits] "=r"(bits), [w] "=r"(w)
+        : [f32] "f"(f32)
+        : "ft1");
+    errs += bits != acc;
+    errs += w != (int32_t)output_dotp_f32;
+
+    int32_t neg = -3;
+    asm volatile(
+        "fcvt.h.w ft1, %[neg]\n"
+        "fmv.x.h  %[bits], ft1\n"
+        : [bits] "=r"(bits)
+        : [neg] "r"(neg)
+        : "ft1");
+    errs += bits != 0xffffc200;
+
+    return errs;
+}
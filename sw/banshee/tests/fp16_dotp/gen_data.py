@@ -0,0 +1,47 @@
+#!/usr/bin/env python3
+# Copyright 2021 ETH Zurich and University of Bologna.
+# Licensed under the Apache License, Version 2.0, see LICENSE for details.
+# SPDX-License-Identifier: Apache-2.0
+
+import numpy as np
+
+N = 64
+
+# Magnitudes of at least 2^-4 keep every partial sum of the dot product
+# exactly representable in double precision, such that rounding the
+# double-precision result once gives the result of a fused multiply-add.
+A = (((np.arange(N) * 37) % 61 - 30) * 0.0673).astype(np.float16)
+B = (((np.arange(N) * 23) % 53 - 26) * 0.0791).astype(np.float16)
+
+# Accumulate with `fmadd.h`, rounding to half precision after each step.
+acc = np.float16(0)
+for a, b in zip(A, B):
+    acc = np.float16(np.float64(a) * np.float64(b) + np.float64(acc))
+result = acc
+
+
+def emit(name, array):
+    print(".global %s" % name)
+    print(".align 3")
+    print("%s:" % name)
+    bs = array.tobytes()
+    bs += bytes(-len(bs) % 4)
+    for i in range(0, len(bs), 4):
+        s = ""
+        for n in range(4):
+            s += "%02x" % bs[i + 3 - n]
+        print("    .word 0x%s" % s)
+
+
+print("# Copyright 2021 ETH Zurich and University of Bologna.")
+print(
+    "# Licensed under the Apache License, Version 2.0, see LICENSE for details."
+)
+print("# SPDX-License-Identifier: Apache-2.0")
+print()
+print(".section .l1,\"aw\",@progbits")
+emit("input_size", np.array(N, dtype=np.uint32))
+emit("input_a", A)
+emit("input_b", B)
+emit("output_dotp", np.array(result, dtype=np.float16))
+emit("output_dotp_f32", np.array(np.float32(result), dtype=np.float32))
@@ -0,0 +1,73 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// Exercise the D extension, modeled after the riscv-tests rv32ud suite.
+
+#include <stdint.h>
+
+// Double-precision values in DRAM and in the TCDM, to exercise the two-word
+// accesses of fld and fsd on both paths.
+volatile double dram[4] = {1.5, -2.25, 0.0, 0.0};
+volatile double tcdm[4] __attribute__((section(".l1"))) = {1.5, -2.25, 0.0, 0.0};
+
+static unsigned int check(volatile double *buf) {
+    unsigned int errs = 0;
+    double a = buf[0];
+    double b = buf[1];
+
+    // Arithmetic
+    buf[2] = a + b;
+    errs += buf[2] != -0.75;
+    buf[2] = a - b;
+    errs += buf[2] != 3.75;
+    buf[2] = a * b;
+    errs += buf[2] != -3.375;
+    buf[2] = b / a;
+    errs += buf[2] != -1.5;
+    buf[3] = __builtin_fma(a, b, a);  // fmadd.d
+    errs += buf[3] != -1.875;
+    buf[3] = __builtin_sqrt(a * 6.0);  // fsqrt.d
+    errs += buf[3] != 3.0;
+
+    // Sign injection, min/max
+    errs += __builtin_copysign(a, b) != -1.5;  // fsgnj.d
+    errs += -b != 2.25;                        // fsgnjn.d
+    errs += __builtin_fabs(b) != 2.25;         // fsgnjx.d
+    errs += __builtin_fmin(a, b) != -2.25;     // fmin.d
+    errs += __builtin_fmax(a, b) != 1.5;       // fmax.d
+
+    // Comparisons
+    errs += !(a > b);   // flt.d
+    errs += !(b <= a);  // fle.d
+    errs += a == b;     // feq.d
+
+    // Conversions to and from 32-bit integers
+    errs += (int32_t)b != -2;       // fcvt.w.d
+    errs += (uint32_t)a != 1;       // fcvt.wu.d
+    errs += (double)-7 != -7.0;     // fcvt.d.w
+    errs += (double)7u != 7.0;      // fcvt.d.wu
+
+    // Conversions between single and double precision, which must produce
+    // properly NaN-boxed single-precision values.
+    float s = (float)b;  // fcvt.s.d
+    uint32_t bits;
+    asm volatile("fmv.x.w %0, %1" : "=r"(bits) : "f"(s));
+    errs += bits != 0xc0100000;
+    errs += (double)s != -2.25;  // fcvt.d.s
+
+    // The raw bit pattern survives a store and load.
+    uint64_t raw;
+    buf[3] = -0.0;
+    raw = *(volatile uint64_t *)&buf[3];
+    errs += raw != 0x8000000000000000ull;
+
+    return errs;
+}
+
+int main() {
+    unsigned int errs = 0;
+    errs += check(dram);
+    errs += check(tcdm);
+    return errs;
+}
@@ -0,0 +1,59 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// Compute a dot product with two accumulators once with an `frep` over SSR
+// streams and once unrolled, and check that the results are identical.
+
+#include "runtime.h"
+
+#define N 16
+
+double a[N];
+double b[N];
+
+int main() {
+    for (int i = 0; i < N; i++) {
+        a[i] = 0.5 * i - 3.0;
+        b[i] = 1.25 - 0.125 * i;
+    }
+
+    // Unrolled reference, accumulating even and odd elements separately.
+    double r0 = 0, r1 = 0;
+    for (int i = 0; i < N; i += 2) {
+        asm volatile(
+            "fmadd.d %[r0], %[a0], %[b0], %[r0] \n"
+            "fmadd.d %[r1], %[a1], %[b1], %[r1] \n"
+            : [ r0 ] "+f"(r0), [ r1 ] "+f"(r1)
+            : [ a0 ] "f"(a[i]), [ b0 ] "f"(b[i]), [ a1 ] "f"(a[i + 1]),
+              [ b1 ] "f"(b[i + 1]));
+    }
+
+    // Start of SSR region.
+    register volatile double ft0 asm("ft0");
+    register volatile double ft1 asm("ft1");
+    asm volatile("" : "=f"(ft0), "=f"(ft1));
+    pulp_ssr_loop_1d(SSR_DM0, N, 8);
+    pulp_ssr_loop_1d(SSR_DM1, N, 8);
+    pulp_ssr_read(SSR_DM0, SSR_1D, a);
+    pulp_ssr_read(SSR_DM1, SSR_1D, b);
+    pulp_ssr_enable();
+
+    // Each iteration consumes the next two elements of both streams.
+    double c0 = 0, c1 = 0;
+    const register uint32_t rpt asm("t0") = N / 2 - 1;
+    asm volatile(
+        ".word (1 << 20)|(5 << 15)|(1 << 7)|(0b0001011 << 0) \n"  // frep t0, 2
+        "fmadd.d %[c0], ft0, ft1, %[c0] \n"
+        "fmadd.d %[c1], ft0, ft1, %[c1] \n"
+        : [ c0 ] "+f"(c0), [ c1 ] "+f"(c1)
+        : [ rpt ] "r"(rpt)
+        : "ft0", "ft1");
+
+    // End of SSR region.
+    fpu_fence();
+    pulp_ssr_disable();
+    asm volatile("" ::"f"(ft0), "f"(ft1));
+
+    return (c0 != r0) + (c1 != r1);
+}
@@ -0,0 +1,35 @@
+// Copyright 2020 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+int main() {
+    int errs = 0;
+
+    // Repeat each instruction on its own before moving on to the next.
+    volatile register double x asm("ft0") = 0.0;
+    volatile register double y asm("ft1") = 0.0;
+    volatile register int rpt_max asm("t0") = 3;
+    asm volatile(
+        ".word (1 << 20)|(5 << 15)|(0 << 12)|(0b0000 << 8)|(0 << 7)|(0b0001011 "
+        "<< 0) \n"
+        "fadd.d ft0, ft0, %2\n"
+        "fadd.d ft1, ft1, ft0\n"
+        : "+f"(x), "+f"(y)
+        : "f"(1.0), "r"(rpt_max));
+    errs += x != 4;
+    errs += y != 16;
+
+    // Stagger rd across two registers.
+    volatile register double a asm("ft2") = 0.0;
+    volatile register double b asm("ft3") = 0.0;
+    asm volatile(
+        ".word (0 << 20)|(5 << 15)|(1 << 12)|(0b0001 << 8)|(0 << 7)|(0b0001011 "
+        "<< 0) \n"
+        "fadd.d ft2, ft2, %2\n"
+        : "+f"(a), "+f"(b)
+        : "f"(1.0), "r"(rpt_max));
+    errs += a != 2;
+    errs += b != 3;
+
+    return errs;
+}
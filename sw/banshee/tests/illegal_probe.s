@@ -0,0 +1,38 @@
+# Copyright 2021 ETH Zurich and University of Bologna.
+# Licensed under the Apache License, Version 2.0, see LICENSE for details.
+# SPDX-License-Identifier: Apache-2.0
+
+# Probe for an unsupported instruction by catching the illegal instruction
+# trap. Requires `--trap-illegal-instructions`.
+
+.option norvc
+.globl _start
+.section .text.init;
+_start:
+    li      s0, 0
+    li      s1, 0
+    la      t0, handler
+    csrw    mtvec, t0
+
+    .word   0xffffffff
+    # The handler flags the instruction as unsupported and skips it.
+    addi    t0, s1, -1
+    snez    t0, t0
+    add     s0, s0, t0
+    csrr    t0, mcause
+    addi    t0, t0, -2
+    snez    t0, t0
+    add     s0, s0, t0
+
+    slli    s0, s0, 1
+    ori     s0, s0, 1
+    la      t0, scratch_reg
+    sw      s0, 0(t0)
+    wfi
+
+handler:
+    li      s1, 1
+    csrr    t0, mepc
+    addi    t0, t0, 4
+    csrw    mepc, t0
+    mret
@@ -0,0 +1,85 @@
+# Copyright 2021 ETH Zurich and University of Bologna.
+# Licensed under the Apache License, Version 2.0, see LICENSE for details.
+# SPDX-License-Identifier: Apache-2.0
+
+# Mix single- and double-precision sign injections and moves on the same
+# registers, and check the NaN-boxing of the results bit-exactly.
+
+.macro check reg, value
+    li      t6, \value
+    sub     t6, \reg, t6
+    snez    t6, t6
+    add     s0, s0, t6
+.endm
+
+# Check the upper and lower word of a float register.
+.macro checkf freg, hi, lo
+    fsd     \freg, 0(s1)
+    lw      t1, 0(s1)
+    check   t1, \lo
+    lw      t1, 4(s1)
+    check   t1, \hi
+.endm
+
+.globl _start
+.section .text.init;
+_start:
+    li      s0, 0
+    la      s1, out
+
+    # A double is not a properly NaN-boxed single, and reads as the
+    # canonical NaN. Moving it to an integer register transfers the bits.
+    la      t0, one
+    fld     f1, 0(t0)
+    fsgnj.s f2, f1, f1
+    checkf  f2, 0xffffffff, 0x7fc00000
+    fmv.x.w t1, f1
+    check   t1, 0
+
+    # Singles written from integer registers and by sign injections are
+    # NaN-boxed.
+    li      t0, 0xbf800000
+    fmv.w.x f3, t0
+    checkf  f3, 0xffffffff, 0xbf800000
+    fsgnjx.s f4, f3, f3
+    checkf  f4, 0xffffffff, 0x3f800000
+
+    # Double-precision sign injection operates on the whole register, which
+    # breaks the NaN-boxing of the single.
+    fsgnjn.d f5, f3, f3
+    checkf  f5, 0x7fffffff, 0xbf800000
+    fsgnj.s f6, f5, f3
+    checkf  f6, 0xffffffff, 0xffc00000
+    fsgnjn.s f6, f3, f5
+    checkf  f6, 0xffffffff, 0xbf800000
+    fmv.x.w t1, f5
+    check   t1, 0xbf800000
+
+    # The sign of zeros and NaNs is injected as well.
+    li      t0, 0x80000000
+    fmv.w.x f7, t0
+    li      t0, 0x3f800000
+    fmv.w.x f8, t0
+    fsgnj.s f9, f8, f7
+    checkf  f9, 0xffffffff, 0xbf800000
+    la      t0, nan
+    fld     f10, 0(t0)
+    fsgnj.d f11, f1, f10
+    checkf  f11, 0xbff00000, 0
+    fsgnjx.d f12, f10, f10
+    checkf  f12, 0x7ff80000, 1
+
+    slli    s0, s0, 1
+    ori     s0, s0, 1
+    la      t0, scratch_reg
+    sw      s0, 0(t0)
+    wfi
+
+.data
+.align 3
+one:
+    .dword 0x3ff0000000000000
+nan:
+    .dword 0xfff8000000000001
+out:
+    .dword 0
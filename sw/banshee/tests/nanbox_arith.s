@@ -0,0 +1,94 @@
+# Copyright 2021 ETH Zurich and University of Bologna.
+# Licensed under the Apache License, Version 2.0, see LICENSE for details.
+# SPDX-License-Identifier: Apache-2.0
+
+# Interleave single- and double-precision arithmetic, loads and moves on the
+# same registers, and check the results bit-exactly. The expected values were
+# obtained by running the same sequence on spike.
+
+.macro check reg, value
+    li      t6, \value
+    sub     t6, \reg, t6
+    snez    t6, t6
+    add     s0, s0, t6
+.endm
+
+# Check the upper and lower word of a float register.
+.macro checkf freg, hi, lo
+    fsd     \freg, 0(s1)
+    lw      t1, 0(s1)
+    check   t1, \lo
+    lw      t1, 4(s1)
+    check   t1, \hi
+.endm
+
+.globl _start
+.section .text.init;
+_start:
+    li      s0, 0
+    la      s1, out
+
+    # A double read as a single is the canonical NaN, which propagates
+    # through the arithmetic.
+    la      t0, one
+    fld     f1, 0(t0)
+    fadd.s  f2, f1, f1
+    checkf  f2, 0xffffffff, 0x7fc00000
+    la      t0, two
+    flw     f3, 0(t0)
+    fmadd.s f4, f3, f1, f3
+    checkf  f4, 0xffffffff, 0x7fc00000
+    fmax.s  f5, f3, f1
+    checkf  f5, 0xffffffff, 0x40000000
+    feq.s   t1, f1, f1
+    check   t1, 0
+    fclass.s t1, f1
+    check   t1, 0x200
+    fcvt.d.s f6, f1
+    checkf  f6, 0x7ff80000, 0
+
+    # Loaded singles are NaN-boxed, and read as a NaN by double-precision
+    # operations, whose NaN results are canonical.
+    checkf  f3, 0xffffffff, 0x40000000
+    fadd.s  f7, f3, f3
+    checkf  f7, 0xffffffff, 0x40800000
+    fadd.d  f8, f3, f3
+    checkf  f8, 0x7ff80000, 0
+    fcvt.s.d f9, f1
+    checkf  f9, 0xffffffff, 0x3f800000
+    fmul.d  f10, f9, f1
+    checkf  f10, 0x7ff80000, 0
+
+    # Stores and moves to integer registers transfer the raw lower word,
+    # whether or not the value is NaN-boxed.
+    fsw     f1, 0(s1)
+    lw      t1, 0(s1)
+    check   t1, 0
+    fsw     f3, 0(s1)
+    lw      t1, 0(s1)
+    check   t1, 0x40000000
+
+    # NaN payloads of singles are not propagated.
+    li      t0, 0x7f800001
+    fmv.w.x f11, t0
+    checkf  f11, 0xffffffff, 0x7f800001
+    fsub.s  f12, f11, f3
+    checkf  f12, 0xffffffff, 0x7fc00000
+    fdiv.s  f13, f3, f11
+    checkf  f13, 0xffffffff, 0x7fc00000
+
+    slli    s0, s0, 1
+    ori     s0, s0, 1
+    la      t0, scratch_reg
+    sw      s0, 0(t0)
+    wfi
+
+.data
+.align 3
+one:
+    .dword 0x3ff0000000000000
+two:
+    .word 0x40000000
+.align 3
+out:
+    .dword 0
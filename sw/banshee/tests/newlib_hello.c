@@ -0,0 +1,20 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// A "hello world" built against the stock newlib crt0 and libc, which only
+// works through the emulated syscalls: `printf` checks stdout with `fstat`,
+// allocates its buffer with `brk`, and flushes it with `write`, and `main`
+// returns through `exit`.
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+int main(int argc, char **argv) {
+    printf("Hello, %s!\n", argc > 1 ? argv[1] : "world");
+    char *buf = malloc(64);
+    if (!buf) return 1;
+    strcpy(buf, "heap");
+    return strcmp(buf, "heap") != 0;
+}
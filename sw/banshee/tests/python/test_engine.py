@@ -0,0 +1,86 @@
+# Copyright 2021 ETH Zurich and University of Bologna.
+# Licensed under the Apache License, Version 2.0, see LICENSE for details.
+# SPDX-License-Identifier: Apache-2.0
+
+# Drives banshee through its Python bindings: load a binary, execute it, and
+# check the memory, registers, and statistics it ends with. Run with
+# `make test-python`.
+
+import os
+import threading
+import time
+
+import pytest
+
+import banshee
+
+DUMMY = os.path.join(os.path.dirname(__file__), "..", "bin", "dummy")
+# Runs for tens of seconds, in a loop of 100 million iterations.
+LARGE_LFSR = os.path.join(os.path.dirname(__file__), "..", "bin", "large_lfsr")
+CONFIG = '{"architecture": {"num_cores": 1}}'
+
+
+def test_load_run_check():
+    engine = banshee.Engine(CONFIG)
+    engine.load_elf(DUMMY)
+    engine.write_memory(0x200, b"\xef\xbe\xad\xde")
+    assert engine.stats() is None
+    assert engine.run() == 0
+
+    # The binary stores 0x100 to 0x100, and loads two words of `.l1`.
+    assert engine.read_memory(0x100, 4) == (0x100).to_bytes(4, "little")
+    assert engine.read_memory(0x200, 4) == b"\xef\xbe\xad\xde"
+    assert engine.get_reg(0, 7) == 0x100
+    assert engine.get_reg(0, 29) == 0x10
+    assert engine.get_reg(0, 30) == 0x42
+
+    stats = engine.stats()
+    assert stats["exit_code"] == 0
+    assert not stats["error"]
+    assert stats["instret"] == stats["harts"][0]["instret"] > 0
+
+
+def test_run_in_thread():
+    # `run` releases the GIL while the binary executes, such that this thread
+    # keeps running Python code, and stops the execution like a watchdog.
+    engine = banshee.Engine(CONFIG)
+    engine.load_elf(LARGE_LFSR)
+    started = threading.Event()
+    result = []
+
+    def run():
+        started.set()
+        try:
+            result.append(engine.run())
+        except RuntimeError as e:
+            result.append(e)
+
+    thread = threading.Thread(target=run)
+    thread.start()
+    started.wait()
+    ticks = 0
+    deadline = time.monotonic() + 0.5
+    while time.monotonic() < deadline:
+        ticks += 1
+    assert thread.is_alive() and not result, ticks
+
+    # Keep stopping until `run` returns, in case the execution had not
+    # started yet.
+    deadline = time.monotonic() + 60
+    while thread.is_alive() and time.monotonic() < deadline:
+        engine.stop()
+        thread.join(timeout=0.1)
+    assert not thread.is_alive()
+    assert len(result) == 1
+
+
+def test_errors():
+    engine = banshee.Engine(CONFIG)
+    with pytest.raises(RuntimeError, match="No ELF image"):
+        engine.run()
+    with pytest.raises(RuntimeError, match="does/not/exist"):
+        engine.load_elf("does/not/exist")
+    with pytest.raises(RuntimeError):
+        engine.get_reg(0, 32)
+    with pytest.raises(RuntimeError):
+        banshee.Engine("{")
@@ -0,0 +1,48 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// Test environment to run the riscv-tests ISA tests on banshee. The tests
+// start directly at `_start` in machine mode and report their result through
+// the `exit` syscall, with the number of the failing test as exit code.
+
+#ifndef _ENV_BANSHEE_H
+#define _ENV_BANSHEE_H
+
+#define RVTEST_RV32U \
+    .macro init;     \
+    .endm
+
+#define RVTEST_RV32UF  \
+    .macro init;       \
+    csrwi fcsr, 0;     \
+    .endm
+
+#define RVTEST_RV64UF RVTEST_RV32UF
+
+#define TESTNUM gp
+
+#define RVTEST_CODE_BEGIN  \
+    .section .text.init;   \
+    .align 6;              \
+    .globl _start;         \
+    _start:                \
+    li TESTNUM, 0;         \
+    init;
+
+#define RVTEST_CODE_END
+
+#define RVTEST_PASS \
+    li a7, 93;      \
+    li a0, 0;       \
+    ecall
+
+#define RVTEST_FAIL       \
+    li a7, 93;            \
+    addi a0, TESTNUM, 0;  \
+    ecall
+
+#define RVTEST_DATA_BEGIN
+#define RVTEST_DATA_END
+
+#endif
@@ -0,0 +1,52 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// Requires LLVM toolchain with support for the Xssr extension
+
+// Configure all data movers at once through data mover 31, and mix the
+// `scfg*` instructions with accesses to the memory-mapped configuration.
+
+#include <stdint.h>
+
+#define SSR_CFG(dm, reg) ((volatile uint32_t *)(0x204800 + (dm)*256 + (reg)*8))
+
+int main() {
+    unsigned errs = 0;
+    uint32_t v0, v1, v2;
+
+    // Broadcast the repetition count with the immediate form.
+    asm volatile(
+        "li     t0, 5\n"
+        "scfgwi t0, 1<<5 | 31\n"
+        "scfgri %[v0], 1<<5 | 0\n"
+        "scfgri %[v1], 1<<5 | 1\n"
+        "scfgri %[v2], 1<<5 | 2\n"
+        : [ v0 ] "=r"(v0), [ v1 ] "=r"(v1), [ v2 ] "=r"(v2)::"t0");
+    errs += v0 != 5;
+    errs += v1 != 5;
+    errs += v2 != 5;
+
+    // Broadcast a stride with the register form.
+    asm volatile(
+        "li     t0, 6<<5 | 31\n"
+        "li     t1, 0x1234\n"
+        "scfgw  t1, t0\n"
+        "scfgri %[v0], 6<<5 | 0\n"
+        "scfgri %[v1], 6<<5 | 1\n"
+        "scfgri %[v2], 6<<5 | 2\n"
+        : [ v0 ] "=r"(v0), [ v1 ] "=r"(v1), [ v2 ] "=r"(v2)::"t0", "t1");
+    errs += v0 != 0x1234;
+    errs += v1 != 0x1234;
+    errs += v2 != 0x1234;
+
+    // The memory-mapped configuration and the instructions see the same
+    // registers.
+    *SSR_CFG(1, 2) = 0x77;
+    asm volatile("scfgri %[v0], 2<<5 | 1\n" : [ v0 ] "=r"(v0));
+    errs += v0 != 0x77;
+    asm volatile("li t0, 0x99\n scfgwi t0, 3<<5 | 2\n" ::: "t0");
+    errs += *SSR_CFG(2, 3) != 0x99;
+
+    return errs;
+}
@@ -0,0 +1,46 @@
+// Copyright 2020 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+#include "runtime.h"
+
+#define N 8
+
+static double x[N] = {1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0};
+static double y[N + 1];
+static double z[N + 1];
+
+int main() {
+    int errs = 0;
+    register volatile double ft0 asm("ft0");
+    register volatile double ft1 asm("ft1");
+    asm volatile("" : "=f"(ft0), "=f"(ft1));
+
+    // Copy x to y, reading through ft0 and writing through ft1. The write
+    // past the end of the stream must not land in memory.
+    y[N] = -1.0;
+    pulp_ssr_loop_1d(SSR_DM0, N, 8);
+    pulp_ssr_loop_1d(SSR_DM1, N, 8);
+    pulp_ssr_read(SSR_DM0, SSR_1D, x);
+    pulp_ssr_write(SSR_DM1, SSR_1D, y);
+    pulp_ssr_enable();
+    for (int i = 0; i < N; i++)
+        asm volatile("fmv.d ft1, ft0" ::: "ft0", "ft1");
+    asm volatile("fmv.d ft1, %0" ::"f"(0.0) : "ft0", "ft1");
+    pulp_ssr_disable();
+    for (int i = 0; i < N; i++) errs += y[i] != x[i];
+    errs += y[N] != -1.0;
+
+    // Copy y to z scaled by two, with the directions of the lanes swapped.
+    pulp_ssr_read(SSR_DM1, SSR_1D, y);
+    pulp_ssr_write(SSR_DM0, SSR_1D, z);
+    pulp_ssr_enable();
+    for (int i = 0; i < N; i++)
+        asm volatile("fadd.d ft0, ft1, ft1" ::: "ft0", "ft1");
+    pulp_ssr_disable();
+    for (int i = 0; i < N; i++) errs += z[i] != 2 * x[i];
+    errs += z[N] != 0.0;
+
+    asm volatile("" ::"f"(ft0), "f"(ft1));
+    return errs;
+}
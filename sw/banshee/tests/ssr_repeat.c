@@ -0,0 +1,44 @@
+// Copyright 2020 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+#include "runtime.h"
+
+static double x[4] = {1.0, 2.0, 3.0, 4.0};
+
+int main() {
+    int errs = 0;
+    register volatile double ft0 asm("ft0");
+    asm volatile("" : "=f"(ft0));
+
+    // Each element is served four times before the stream advances.
+    double y[16];
+    pulp_ssr_loop_1d(SSR_DM0, 4, 8);
+    ssr_config_reg[SSR_DM0].repeat.value = 3;
+    pulp_ssr_read(SSR_DM0, SSR_1D, x);
+    pulp_ssr_enable();
+    for (int i = 0; i < 16; i++)
+        asm volatile("fmv.d %0, ft0" : "=f"(y[i])::"ft0");
+    pulp_ssr_disable();
+    for (int i = 0; i < 16; i++) errs += y[i] != x[i / 4];
+
+    // Consume the repeated elements inside a hardware loop. The accumulation
+    // depends on the order of the values.
+    double acc = 0.0, two = 2.0, expected = 0.0;
+    for (int i = 0; i < 16; i++) expected = expected * two + x[i / 4];
+    register uint32_t rpt asm("t0") = 15;
+    pulp_ssr_read(SSR_DM0, SSR_1D, x);
+    pulp_ssr_enable();
+    asm volatile(
+        ".word (0 << 20)|(5 << 15)|(1 << 7)|(0b0001011 << 0) \n"  // frep t0, 1
+        "fmadd.d %[acc], %[acc], %[two], ft0 \n"
+        : [ acc ] "+f"(acc)
+        : [ two ] "f"(two), "r"(rpt)
+        : "ft0");
+    fpu_fence();
+    pulp_ssr_disable();
+    errs += acc != expected;
+
+    asm volatile("" ::"f"(ft0));
+    return errs;
+}
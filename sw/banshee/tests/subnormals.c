@@ -0,0 +1,77 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// Exercise subnormal inputs and results of scalar and packed-SIMD operations.
+// They are kept by default, and flushed to zero of the same sign with
+// `--flush-to-zero`, in which case this file is built with `FLUSH_TO_ZERO`.
+
+#include <stdint.h>
+
+#ifdef FLUSH_TO_ZERO
+#define EXPECT(subnormal, flushed) (flushed)
+#else
+#define EXPECT(subnormal, flushed) (subnormal)
+#endif
+
+#define OP_S(inst)                                                 \
+    static uint32_t inst##_s(uint32_t a, uint32_t b) {             \
+        float fa, fb, fd;                                          \
+        uint32_t d;                                                \
+        asm volatile("fmv.w.x %0, %1" : "=f"(fa) : "r"(a));        \
+        asm volatile("fmv.w.x %0, %1" : "=f"(fb) : "r"(b));        \
+        asm volatile(#inst ".s %0, %1, %2"                         \
+                     : "=f"(fd)                                    \
+                     : "f"(fa), "f"(fb));                          \
+        asm volatile("fmv.x.w %0, %1" : "=r"(d) : "f"(fd));        \
+        return d;                                                  \
+    }
+
+// Operate on the raw 64 bits of the registers, for doubles and for pairs of
+// singles.
+#define OP_64(name, inst)                                          \
+    static uint64_t name(uint64_t a, uint64_t b) {                 \
+        volatile uint64_t d;                                       \
+        asm volatile(                                              \
+            "fld ft3, 0(%1)\n"                                     \
+            "fld ft4, 0(%2)\n" inst " ft5, ft3, ft4\n"             \
+            "fsd ft5, 0(%0)\n"                                     \
+            :                                                      \
+            : "r"(&d), "r"(&a), "r"(&b)                            \
+            : "ft3", "ft4", "ft5", "memory");                      \
+        return d;                                                  \
+    }
+
+OP_S(fadd)
+OP_S(fmul)
+OP_64(fadd_d, "fadd.d")
+OP_64(fmul_d, "fmul.d")
+OP_64(vfadd_s, "vfadd.s")
+OP_64(vfmul_s, "vfmul.s")
+
+int main() {
+    unsigned int errs = 0;
+
+    // Subnormal inputs.
+    errs += fadd_s(0x00400000, 0x00400000) != EXPECT(0x00800000, 0);
+    errs += fmul_s(0x00400000, 0x40000000) != EXPECT(0x00800000, 0);
+    errs += fadd_d(0x0008000000000000, 0x0008000000000000) !=
+            EXPECT(0x0010000000000000, 0);
+    errs += vfadd_s(0x8040000000400000, 0x8040000000400000) !=
+            EXPECT(0x8080000000800000, 0x8000000000000000);
+
+    // Subnormal results.
+    errs += fmul_s(0x00800000, 0x3f000000) != EXPECT(0x00400000, 0);
+    errs += fmul_s(0x00800000, 0xbf000000) != EXPECT(0x80400000, 0x80000000);
+    errs += fmul_d(0x0010000000000000, 0x3fe0000000000000) !=
+            EXPECT(0x0008000000000000, 0);
+    errs += vfmul_s(0x0080000000800000, 0xbf0000003f000000) !=
+            EXPECT(0x8040000000400000, 0x8000000000000000);
+
+    // Normal values are not affected.
+    errs += fmul_s(0x00800000, 0x3f800000) != 0x00800000;
+    errs += fmul_d(0x0010000000000000, 0x3ff0000000000000) !=
+            0x0010000000000000;
+
+    return errs;
+}
@@ -0,0 +1,61 @@
+# Copyright 2021 ETH Zurich and University of Bologna.
+# Licensed under the Apache License, Version 2.0, see LICENSE for details.
+# SPDX-License-Identifier: Apache-2.0
+
+# Check that `ecall`, `ebreak`, and illegal instructions enter the trap handler
+# and return with `mret`. Requires `--traps`.
+
+.macro check reg, value
+    li      t6, \value
+    sub     t6, \reg, t6
+    snez    t6, t6
+    add     s0, s0, t6
+.endm
+
+.option norvc
+.globl _start
+.section .text.init;
+_start:
+    li      s0, 0
+    li      s1, 0
+    la      t0, handler
+    csrw    mtvec, t0
+    csrsi   mstatus, 8
+
+    ecall
+    check   s2, 11
+    check   s3, 0
+    check   s4, 0x80
+
+1:  ebreak
+    check   s2, 3
+    la      t0, 1b
+    sub     s3, s3, t0
+    check   s3, 0
+
+    .word   0xffffffff
+    check   s2, 2
+    check   s3, -1
+
+    # All three traps returned, with interrupts enabled again.
+    check   s1, 3
+    csrr    t0, mstatus
+    andi    t0, t0, 8
+    check   t0, 8
+
+    slli    s0, s0, 1
+    ori     s0, s0, 1
+    la      t0, scratch_reg
+    sw      s0, 0(t0)
+    wfi
+
+handler:
+    addi    s1, s1, 1
+    csrr    s2, mcause
+    csrr    s3, mtval
+    csrr    s4, mstatus
+    andi    s4, s4, 0x88
+    csrr    t0, mepc
+    addi    t0, t0, 4
+    csrw    mepc, t0
+    mret
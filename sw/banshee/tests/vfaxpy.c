@@ -0,0 +1,111 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// Exercise the packed-SIMD single-precision instructions with an AXPY
+// kernel, which is checked bit-exactly against a scalar `fmadd.s` loop.
+
+#include <stdint.h>
+
+#define N 16
+
+float x[N] __attribute__((aligned(8)));
+float y[N] __attribute__((aligned(8)));
+float y_vec[N] __attribute__((aligned(8)));
+float y_ref[N] __attribute__((aligned(8)));
+
+static uint32_t bits(float f) { return *(uint32_t *)&f; }
+
+int main() {
+    unsigned int errs = 0;
+    const float a = 1.7f;
+
+    for (int i = 0; i < N; i++) {
+        x[i] = 0.1f * i - 0.75f;
+        y[i] = 3.3f - 0.2f * i;
+        y_vec[i] = y[i];
+    }
+
+    // Scalar reference, with one rounding per element.
+    for (int i = 0; i < N; i++) {
+        float r;
+        asm volatile("fmadd.s %0, %1, %2, %3"
+                     : "=f"(r)
+                     : "f"(a), "f"(x[i]), "f"(y[i]));
+        y_ref[i] = r;
+    }
+
+    // Two elements per iteration, replicating the scalar `a` with the `.r`
+    // variant.
+    float *px = x, *py = y;
+    uint32_t n = N / 2;
+    asm volatile(
+        "1:\n"
+        "fld       ft1, 0(%[x])\n"
+        "fld       ft2, 0(%[y])\n"
+        "vfmac.r.s ft2, ft1, %[a]\n"
+        "fsd       ft2, 0(%[y])\n"
+        "addi      %[x], %[x], 8\n"
+        "addi      %[y], %[y], 8\n"
+        "addi      %[n], %[n], -1\n"
+        "bnez      %[n], 1b\n"
+        : [x] "+r"(px), [y] "+r"(py), [n] "+r"(n)
+        : [a] "f"(a)
+        : "ft1", "ft2", "memory");
+    for (int i = 0; i < N; i++) errs += bits(y[i]) != bits(y_ref[i]);
+
+    // The same with `a` packed into both lanes.
+    px = x;
+    py = y_vec;
+    n = N / 2;
+    asm volatile(
+        "vfcpka.s.s ft0, %[a], %[a]\n"
+        "1:\n"
+        "fld        ft1, 0(%[x])\n"
+        "fld        ft2, 0(%[y])\n"
+        "vfmac.s    ft2, ft1, ft0\n"
+        "fsd        ft2, 0(%[y])\n"
+        "addi       %[x], %[x], 8\n"
+        "addi       %[y], %[y], 8\n"
+        "addi       %[n], %[n], -1\n"
+        "bnez       %[n], 1b\n"
+        : [x] "+r"(px), [y] "+r"(py), [n] "+r"(n)
+        : [a] "f"(a)
+        : "ft0", "ft1", "ft2", "memory");
+    for (int i = 0; i < N; i++) errs += bits(y_vec[i]) != bits(y_ref[i]);
+
+    // Reductions into the lower lane of the destination.
+    float sum, nsum;
+    const float lo = 1.5f, hi = 2.25f, c = 10.0f;
+    asm volatile(
+        "vfcpka.s.s ft1, %[lo], %[hi]\n"
+        "fmv.s      %[sum], %[c]\n"
+        "vfsum.s    %[sum], ft1\n"
+        "fmv.s      %[nsum], %[c]\n"
+        "vfnsum.s   %[nsum], ft1\n"
+        : [sum] "=&f"(sum), [nsum] "=&f"(nsum)
+        : [lo] "f"(lo), [hi] "f"(hi), [c] "f"(c)
+        : "ft1");
+    errs += sum != 13.75f;
+    errs += nsum != 6.25f;
+
+    // Narrow both lanes into the lower and the upper halves, then widen the
+    // upper halves again.
+    uint64_t packed, halves, widened;
+    asm volatile(
+        "vfcpka.s.s ft1, %[lo], %[hi]\n"
+        "fsd        ft1, 0(%[packed])\n"
+        "vfcvt.h.s  ft2, ft1\n"
+        "vfcvtu.h.s ft2, ft1\n"
+        "fsd        ft2, 0(%[halves])\n"
+        "vfcvtu.s.h ft3, ft2\n"
+        "fsd        ft3, 0(%[widened])\n"
+        :
+        : [lo] "f"(lo), [hi] "f"(-hi), [packed] "r"(&packed),
+          [halves] "r"(&halves), [widened] "r"(&widened)
+        : "ft1", "ft2", "ft3", "memory");
+    errs += halves != 0xc0803e00c0803e00;
+    errs += widened != packed;
+
+    return errs;
+}
@@ -0,0 +1,23 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// All harts but hart 0 sleep in `wfi`; hart 0 wakes them all up at once
+// through the wake-up register and waits for them to check in.
+
+#include "runtime.h"
+
+volatile uint32_t awake = 0;
+
+int main(uint32_t core_id, uint32_t core_num) {
+    volatile uint32_t *wake_up = (void *)&wake_up_reg;
+    if (core_id == 0) {
+        *wake_up = -1;
+        while (awake != core_num - 1)
+            ;
+        return 0;
+    }
+    asm volatile("wfi");
+    __atomic_add_fetch(&awake, 1, __ATOMIC_RELAXED);
+    return 0;
+}
@@ -0,0 +1,23 @@
+// Copyright 2021 ETH Zurich and University of Bologna.
+// Licensed under the Apache License, Version 2.0, see LICENSE for details.
+// SPDX-License-Identifier: Apache-2.0
+
+// Hart 0 sleeps in `wfi` while the other harts make progress; the last one to
+// finish wakes it up.
+
+#include "runtime.h"
+
+volatile uint32_t sum = 0;
+volatile uint32_t done = 0;
+
+int main(uint32_t core_id, uint32_t core_num) {
+    volatile uint32_t *wake_up = (void *)&wake_up_reg;
+    if (core_id == 0) {
+        while (done != core_num - 1) asm volatile("wfi");
+        return sum != core_num * (core_num - 1) / 2;
+    }
+    __atomic_fetch_add(&sum, core_id, __ATOMIC_RELAXED);
+    if (__atomic_add_fetch(&done, 1, __ATOMIC_RELAXED) == core_num - 1)
+        *wake_up = 0;
+    return 0;
+}